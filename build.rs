@@ -0,0 +1,34 @@
+// Captures the compile-time configuration so outputs can report how the solver was built
+// (see `build_compiler_flags` in lib.rs).
+use std::env;
+
+fn main() {
+    let opt_level = env::var("OPT_LEVEL").unwrap_or_else(|_| "unknown".to_string());
+    let profile = env::var("PROFILE").unwrap_or_else(|_| "unknown".to_string());
+    let target = env::var("TARGET").unwrap_or_else(|_| "unknown".to_string());
+
+    // target-cpu only shows up in the rustflags; target features come from cfg
+    let rustflags: Vec<String> = env::var("CARGO_ENCODED_RUSTFLAGS")
+        .map(|flags| flags.split('\x1f').filter(|f| !f.is_empty()).map(str::to_string).collect())
+        .unwrap_or_default();
+    let target_cpu = rustflags
+        .iter()
+        .enumerate()
+        .find_map(|(i, flag)| {
+            let flag = match flag.as_str() {
+                "-C" => rustflags.get(i + 1)?.as_str(),
+                other => other.strip_prefix("-C").unwrap_or(other),
+            };
+            flag.strip_prefix("target-cpu=").map(str::to_string)
+        })
+        .unwrap_or_else(|| "generic".to_string());
+    let target_features = env::var("CARGO_CFG_TARGET_FEATURE").unwrap_or_default();
+
+    println!(
+        "cargo:rustc-env=MATMUL_BUILD_COMPILER_FLAGS=opt-level={} profile={} target={} target-cpu={} target-features={}",
+        opt_level, profile, target, target_cpu, target_features
+    );
+    println!("cargo:rerun-if-env-changed=CARGO_ENCODED_RUSTFLAGS");
+    println!("cargo:rerun-if-env-changed=RUSTFLAGS");
+    println!("cargo:rerun-if-changed=build.rs");
+}
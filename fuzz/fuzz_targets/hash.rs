@@ -0,0 +1,9 @@
+//! Result hashes in every dtype against preimages built byte by byte (`fuzz_checks::check_hash`),
+//! seeded from `fuzz/seeds/hash`; see `fuzz/smoke.sh`.
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+
+fuzz_target!(|data: &[u8]| {
+    matmul_solver::fuzz_checks::check_hash(data);
+});
@@ -0,0 +1,9 @@
+//! Every registered kernel against the integer and f64 references (`fuzz_checks::check_kernels`),
+//! seeded from `fuzz/seeds/kernels`; see `fuzz/smoke.sh`.
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+
+fuzz_target!(|data: &[u8]| {
+    matmul_solver::fuzz_checks::check_kernels(data);
+});
@@ -0,0 +1,10 @@
+//! Input JSON through the deserializer and compute path under the corpus bounds
+//! (`corpus::check_bytes`), seeded with the regression corpus by passing `tests/corpus`
+//! after the target name to `cargo +nightly fuzz run parse_input`.
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+
+fuzz_target!(|data: &[u8]| {
+    matmul_solver::corpus::check_bytes(data);
+});
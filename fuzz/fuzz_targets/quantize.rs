@@ -0,0 +1,9 @@
+//! Quantization bounds and round trips for every target (`fuzz_checks::check_quantize`),
+//! seeded from `fuzz/seeds/quantize`; see `fuzz/smoke.sh`.
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+
+fuzz_target!(|data: &[u8]| {
+    matmul_solver::fuzz_checks::check_quantize(data);
+});
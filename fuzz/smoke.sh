@@ -0,0 +1,24 @@
+#!/bin/bash
+# Time-boxed run of every fuzz target, for CI: each target starts from its seeds in
+# fuzz/seeds/<target> (parse_input from tests/corpus) and stops after FUZZ_SECONDS.
+# A violation fails the script and leaves the reproducer in fuzz/artifacts/<target>.
+#
+#   fuzz/smoke.sh                 # all targets, 60 s each
+#   FUZZ_SECONDS=10 fuzz/smoke.sh kernels hash
+
+set -e
+
+cd "$(dirname "$0")"
+SECONDS_PER_TARGET=${FUZZ_SECONDS:-60}
+TARGETS=${*:-parse_input kernels hash quantize}
+
+for target in $TARGETS; do
+    if [ "$target" = parse_input ]; then
+        seeds=../tests/corpus
+    else
+        seeds=seeds/$target
+    fi
+    mkdir -p "corpus/$target"
+    echo "=== $target (${SECONDS_PER_TARGET}s) ==="
+    cargo +nightly fuzz run "$target" "corpus/$target" "$seeds" -- -max_total_time="$SECONDS_PER_TARGET"
+done
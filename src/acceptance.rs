@@ -0,0 +1,562 @@
+//! Acceptance suite for bringing up a new machine (`matmul-solver acceptance`).
+//!
+//! `generate_suite` derives `count` workloads from an RNG seed: the shape classes in turn
+//! (square, skinny, tall, prime dims, the 16×k×16 seed shape), with a precision, a value
+//! distribution and the dimensions drawn from a SplitMix64 stream. Generation uses integer
+//! arithmetic only, and values are built from their bits, so every machine and build gets
+//! the same cases and the same matrices. Precisions this build lacks are skipped, not
+//! dropped, so case names and positions still line up between builds.
+//!
+//! `run_suite` computes each case through the normal kernel selection and again on the
+//! precision's reference kernel (the lowest-priority portable one, forced). Integer
+//! precisions must agree bit for bit; float precisions element by element within the
+//! forward error bound of both accumulations, 2·γ(k+1)·Σ|a||b|, or within the run's
+//! tolerance for the precision (`tolerance::TolerancePolicy`), which lets a deployment admit
+//! a kernel the rigorous bound would not. Each float case records the tolerance it used.
+//! The report carries hashes, kernels and timings; `AcceptanceReport::diff` compares it with
+//! one from a trusted machine, ignoring timings.
+
+use crate::bench::MachineFingerprint;
+use crate::kernels::{self, MatmulKernel};
+use crate::tolerance::{EffectiveTolerance, Tolerance, TolerancePolicy};
+use crate::{compute_workload_with_options, types, ComputeOptions, FlatMatrix, Precision};
+use serde::{Deserialize, Serialize};
+use std::sync::Arc;
+
+/// Shape classes, assigned to cases in this order
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ShapeClass {
+    /// s×s × s×s
+    Square,
+    /// At most 16 rows and columns around a long reduction
+    Skinny,
+    /// Many rows, short reduction, few columns
+    Tall,
+    /// Prime m, k and n, so no dimension divides into tiles
+    Prime,
+    /// 16×k × k×16, the shape of the packed kernels
+    Seed,
+}
+
+impl ShapeClass {
+    pub const ALL: [ShapeClass; 5] = [ShapeClass::Square, ShapeClass::Skinny, ShapeClass::Tall, ShapeClass::Prime, ShapeClass::Seed];
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ShapeClass::Square => "square",
+            ShapeClass::Skinny => "skinny",
+            ShapeClass::Tall => "tall",
+            ShapeClass::Prime => "prime",
+            ShapeClass::Seed => "seed",
+        }
+    }
+}
+
+/// How matrix values are drawn
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Distribution {
+    /// Uniform in [-1, 1), on a 2^-23 grid
+    Uniform,
+    /// Every element zero
+    Zeros,
+    /// ±2^e with e uniform in [-12, 12]
+    HugeRange,
+    /// Integers: A in [0, 255], B in [-128, 127]
+    ByteValued,
+}
+
+impl Distribution {
+    pub const ALL: [Distribution; 4] = [Distribution::Uniform, Distribution::Zeros, Distribution::HugeRange, Distribution::ByteValued];
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Distribution::Uniform => "uniform",
+            Distribution::Zeros => "zeros",
+            Distribution::HugeRange => "huge_range",
+            Distribution::ByteValued => "byte_valued",
+        }
+    }
+}
+
+/// One generated workload
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct AcceptanceCase {
+    /// `<index>-<shape>-<precision>-<distribution>`
+    pub name: String,
+    pub shape_class: ShapeClass,
+    pub precision: String,
+    pub distribution: Distribution,
+    pub m: usize,
+    pub k: usize,
+    pub n: usize,
+    /// Seeds the values of A and B
+    pub data_seed: u64,
+}
+
+impl AcceptanceCase {
+    /// A (m×k) and B (k×n), the same on every machine
+    pub fn matrices(&self) -> (FlatMatrix, FlatMatrix) {
+        let mut rng = SplitMix64(self.data_seed);
+        let mut matrix = |rows: usize, cols: usize, is_a: bool| {
+            let data = (0..rows * cols).map(|_| value(&mut rng, self.distribution, is_a)).collect();
+            FlatMatrix { data, rows, cols }
+        };
+        let a = matrix(self.m, self.k, true);
+        let b = matrix(self.k, self.n, false);
+        (a, b)
+    }
+}
+
+fn value(rng: &mut SplitMix64, distribution: Distribution, is_a: bool) -> f32 {
+    match distribution {
+        Distribution::Uniform => (rng.next() >> 40) as i32 as f32 / (1 << 23) as f32 - 1.0,
+        Distribution::Zeros => 0.0,
+        Distribution::HugeRange => {
+            let exponent = rng.range(0, 24) as i32 - 12;
+            let magnitude = f32::from_bits(((exponent + 127) as u32) << 23);
+            if rng.next() & 1 == 1 { -magnitude } else { magnitude }
+        }
+        Distribution::ByteValued => {
+            let byte = rng.range(0, 255) as i32;
+            let integer = if is_a { byte } else { byte - 128 };
+            integer as f32
+        }
+    }
+}
+
+/// SplitMix64: small, fast, and the same everywhere
+struct SplitMix64(u64);
+
+impl SplitMix64 {
+    fn next(&mut self) -> u64 {
+        self.0 = self.0.wrapping_add(0x9e37_79b9_7f4a_7c15);
+        let mut z = self.0;
+        z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+        z ^ (z >> 31)
+    }
+
+    /// Uniform in [lo, hi] (lo when hi < lo)
+    fn range(&mut self, lo: usize, hi: usize) -> usize {
+        if hi <= lo {
+            return lo;
+        }
+        lo + (self.next() % (hi - lo + 1) as u64) as usize
+    }
+
+    /// In [lo, hi], spread evenly over powers of two so small sizes are as common as large
+    fn log_range(&mut self, lo: usize, hi: usize) -> usize {
+        let lo = lo.max(1);
+        if hi <= lo {
+            return lo;
+        }
+        let bits = self.range(lo.ilog2() as usize, hi.ilog2() as usize);
+        self.range((1 << bits).max(lo), ((1 << (bits + 1)) - 1).min(hi))
+    }
+}
+
+/// Largest prime at most `n` (2 for n < 2)
+fn prime_at_most(n: usize) -> usize {
+    let is_prime = |p: usize| p >= 2 && (2..).take_while(|d| d * d <= p).all(|d| !p.is_multiple_of(d));
+    (2..=n.max(2)).rev().find(|&p| is_prime(p)).unwrap_or(2)
+}
+
+/// `count` cases from `rng_seed`, each operand and the result at most `max_elems` elements
+pub fn generate_suite(rng_seed: u64, count: usize, max_elems: usize) -> Vec<AcceptanceCase> {
+    let max = max_elems.max(16);
+    let side = max.isqrt();
+    let mut rng = SplitMix64(rng_seed);
+    (0..count)
+        .map(|index| {
+            let shape_class = ShapeClass::ALL[index % ShapeClass::ALL.len()];
+            let precision = Precision::ALL[rng.range(0, Precision::ALL.len() - 1)].as_str().to_string();
+            let distribution = Distribution::ALL[rng.range(0, Distribution::ALL.len() - 1)];
+            let (m, k, n) = match shape_class {
+                ShapeClass::Square => {
+                    let s = rng.log_range(1, side);
+                    (s, s, s)
+                }
+                ShapeClass::Skinny => {
+                    let (m, n) = (rng.log_range(1, 16), rng.log_range(1, 16));
+                    (m, rng.log_range(16, max / m.max(n)), n)
+                }
+                ShapeClass::Tall => {
+                    let (k, n) = (rng.log_range(1, 64), rng.log_range(1, 64));
+                    (rng.log_range(64, max / k.max(n)), k, n)
+                }
+                ShapeClass::Prime => {
+                    let mut dim = || prime_at_most(rng.log_range(2, side));
+                    (dim(), dim(), dim())
+                }
+                ShapeClass::Seed => (16, rng.log_range(16, max / 16), 16),
+            };
+            let data_seed = rng.next();
+            AcceptanceCase {
+                name: format!("{:03}-{}-{}-{}", index, shape_class.as_str(), precision, distribution.as_str()),
+                shape_class,
+                precision,
+                distribution,
+                m,
+                k,
+                n,
+                data_seed,
+            }
+        })
+        .collect()
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Verdict {
+    Passed,
+    Failed,
+    /// The precision is not compiled into this build
+    Skipped,
+}
+
+/// Outcome of one case
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct CaseResult {
+    #[serde(flatten)]
+    pub case: AcceptanceCase,
+    pub verdict: Verdict,
+    /// Why the case failed or was skipped
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub detail: Option<String>,
+    /// Kernel automatic selection picked, and its result hash and kernel time
+    pub kernel: Option<String>,
+    pub result_hash: Option<String>,
+    pub kernel_ms: Option<f64>,
+    pub reference_kernel: Option<String>,
+    pub reference_hash: Option<String>,
+    pub reference_ms: Option<f64>,
+    /// Largest |selected - reference| over the result, for float precisions
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_abs_error: Option<f64>,
+    /// The tolerance float results were held to, besides the error bound
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub tolerance: Option<EffectiveTolerance>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct AcceptanceSummary {
+    pub total: usize,
+    pub passed: usize,
+    pub failed: usize,
+    pub skipped: usize,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct AcceptanceReport {
+    pub rng_seed: u64,
+    pub count: usize,
+    pub max_elems: usize,
+    /// Tolerance profile and overrides of the run
+    #[serde(default)]
+    pub tolerance: TolerancePolicy,
+    pub fingerprint: MachineFingerprint,
+    pub summary: AcceptanceSummary,
+    pub cases: Vec<CaseResult>,
+}
+
+impl AcceptanceReport {
+    /// No case failed (skipped ones do not count)
+    pub fn all_passed(&self) -> bool {
+        self.summary.failed == 0
+    }
+
+    /// Human-readable table for the console
+    pub fn to_table(&self) -> String {
+        let mut out = format!(
+            "Acceptance suite (rng seed {}, {} cases, max {} elements, {} tolerance profile)\n",
+            self.rng_seed,
+            self.count,
+            self.max_elems,
+            self.tolerance.profile.as_str()
+        );
+        out.push_str(&format!("{:<36} {:>18} {:<16} {:>10}  {}\n", "case", "m×k×n", "kernel", "ms", "verdict"));
+        for result in &self.cases {
+            let case = &result.case;
+            let verdict = match result.verdict {
+                Verdict::Passed => "ok".to_string(),
+                Verdict::Failed => format!("FAILED: {}", result.detail.as_deref().unwrap_or("")),
+                Verdict::Skipped => "skipped".to_string(),
+            };
+            out.push_str(&format!(
+                "{:<36} {:>18} {:<16} {:>10.3}  {}\n",
+                case.name,
+                format!("{}×{}×{}", case.m, case.k, case.n),
+                result.kernel.as_deref().unwrap_or("-"),
+                result.kernel_ms.unwrap_or(0.0),
+                verdict
+            ));
+        }
+        out.push_str(&format!(
+            "{} passed, {} failed, {} skipped\n",
+            self.summary.passed, self.summary.failed, self.summary.skipped
+        ));
+        out
+    }
+
+    /// Differences from a report of the same suite on a trusted machine: generated cases,
+    /// verdicts, kernels and hashes. Timings and the machine fingerprint are not compared.
+    pub fn diff(&self, trusted: &AcceptanceReport) -> Vec<String> {
+        if (self.rng_seed, self.count, self.max_elems) != (trusted.rng_seed, trusted.count, trusted.max_elems) {
+            return vec![format!(
+                "different suites: rng seed {}, {} cases, max {} elements vs trusted {}, {}, {}",
+                self.rng_seed, self.count, self.max_elems, trusted.rng_seed, trusted.count, trusted.max_elems
+            )];
+        }
+        let mut differences = Vec::new();
+        for (ours, theirs) in self.cases.iter().zip(&trusted.cases) {
+            let name = &ours.case.name;
+            if ours.case != theirs.case {
+                differences.push(format!("{}: generated differently than trusted {}", name, theirs.case.name));
+                continue;
+            }
+            if ours.verdict != theirs.verdict {
+                differences.push(format!("{}: {:?} vs trusted {:?}", name, ours.verdict, theirs.verdict));
+            }
+            if ours.result_hash != theirs.result_hash {
+                differences.push(format!(
+                    "{}: result hash {} ({}) vs trusted {} ({})",
+                    name,
+                    ours.result_hash.as_deref().unwrap_or("-"),
+                    ours.kernel.as_deref().unwrap_or("-"),
+                    theirs.result_hash.as_deref().unwrap_or("-"),
+                    theirs.kernel.as_deref().unwrap_or("-")
+                ));
+            }
+            if ours.reference_hash != theirs.reference_hash {
+                differences.push(format!(
+                    "{}: reference hash {} vs trusted {}",
+                    name,
+                    ours.reference_hash.as_deref().unwrap_or("-"),
+                    theirs.reference_hash.as_deref().unwrap_or("-")
+                ));
+            }
+        }
+        differences
+    }
+}
+
+/// Generate the suite and run every case, in order, float cases under `tolerance`
+pub fn run_suite(rng_seed: u64, count: usize, max_elems: usize, tolerance: &TolerancePolicy) -> AcceptanceReport {
+    let cases: Vec<CaseResult> = generate_suite(rng_seed, count, max_elems).into_iter().map(|case| run_case(case, tolerance)).collect();
+    let count_of = |verdict: Verdict| cases.iter().filter(|c| c.verdict == verdict).count();
+    let summary = AcceptanceSummary {
+        total: cases.len(),
+        passed: count_of(Verdict::Passed),
+        failed: count_of(Verdict::Failed),
+        skipped: count_of(Verdict::Skipped),
+    };
+    AcceptanceReport { rng_seed, count, max_elems, tolerance: tolerance.clone(), fingerprint: MachineFingerprint::current(), summary, cases }
+}
+
+/// Lowest-priority kernel for `precision` that runs this shape without build or CPU features
+fn reference_kernel(precision: &str, a_shape: (usize, usize), b_shape: (usize, usize)) -> Option<Arc<dyn MatmulKernel>> {
+    kernels::available_kernels()
+        .into_iter()
+        .filter(|k| k.required_features().is_empty() && k.supports(precision, a_shape, b_shape))
+        .min_by_key(|k| k.priority())
+}
+
+/// Run one case on the selected and the reference kernel and compare
+pub fn run_case(case: AcceptanceCase, tolerance: &TolerancePolicy) -> CaseResult {
+    let mut result = CaseResult {
+        case,
+        verdict: Verdict::Failed,
+        detail: None,
+        kernel: None,
+        result_hash: None,
+        kernel_ms: None,
+        reference_kernel: None,
+        reference_hash: None,
+        reference_ms: None,
+        max_abs_error: None,
+        tolerance: None,
+    };
+    let case = &result.case;
+    if !Precision::parse(&case.precision).is_ok_and(|p| p.is_available()) {
+        result.verdict = Verdict::Skipped;
+        result.detail = Some(format!("{} is not in this build", case.precision));
+        return result;
+    }
+    let (a, b) = case.matrices();
+    let Some(reference) = reference_kernel(&case.precision, (a.rows, a.cols), (b.rows, b.cols)) else {
+        result.detail = Some(format!("no reference kernel for {}", case.precision));
+        return result;
+    };
+    let compute = |options: &ComputeOptions| {
+        let input = types::Input {
+            matrix_a: a.clone(),
+            matrix_b: b.clone(),
+            precision: case.precision.clone(),
+            ..Default::default()
+        };
+        compute_workload_with_options(input, options)
+    };
+    let selected = compute(&ComputeOptions::default());
+    let expected = compute(&ComputeOptions { kernel: Some(reference.name().to_string()), ..Default::default() });
+    let (selected, expected) = match (selected, expected) {
+        (Ok(selected), Ok(expected)) => (selected, expected),
+        (Err(e), _) | (_, Err(e)) => {
+            result.detail = Some(e.to_string());
+            return result;
+        }
+    };
+    let outcome = if matches!(case.precision.as_str(), "int8" | "int8_rowwise" | "int8_asym" | "int4" | "u8i8") {
+        // Integer accumulation is exact, so every kernel hashes the same
+        (selected.result_hash == expected.result_hash)
+            .then_some(())
+            .ok_or_else(|| "result hash differs from the reference kernel's".to_string())
+    } else {
+        let tolerance = tolerance.effective(&case.precision);
+        let (outcome, max_abs_error) = compare_float(&a, &b, &selected.result_matrix, &expected.result_matrix, tolerance.tolerance());
+        result.max_abs_error = Some(max_abs_error);
+        result.tolerance = Some(tolerance);
+        outcome
+    };
+    result.kernel = selected.metadata.kernel.clone();
+    result.result_hash = Some(selected.result_hash);
+    result.kernel_ms = selected.metrics.kernel_time_ms;
+    result.reference_kernel = Some(reference.name().to_string());
+    result.reference_hash = Some(expected.result_hash);
+    result.reference_ms = expected.metrics.kernel_time_ms;
+    match outcome {
+        Ok(()) => result.verdict = Verdict::Passed,
+        Err(detail) => result.detail = Some(detail),
+    }
+    result
+}
+
+/// Element-wise check of two float results of `a × b` within both kernels' error bounds or
+/// `tolerance`; also returns the largest difference
+fn compare_float(a: &FlatMatrix, b: &FlatMatrix, got: &FlatMatrix, reference: &FlatMatrix, tolerance: Tolerance) -> (Result<(), String>, f64) {
+    let (k, n) = (a.cols, b.cols);
+    let scale = reference.data.iter().filter(|x| x.is_finite()).fold(0f64, |m, &x| m.max(x.abs() as f64));
+    // Every float kernel accumulates in f32, fp16 included
+    let unit = 2f64.powi(-24);
+    let steps = (k + 1) as f64 * unit;
+    let gamma = if steps < 1.0 { steps / (1.0 - steps) } else { f64::INFINITY };
+    let mut max_abs_error = 0f64;
+    let mut first_failure = None;
+    for i in 0..a.rows {
+        for j in 0..n {
+            let (x, y) = (got.data[i * n + j], reference.data[i * n + j]);
+            if !x.is_finite() || !y.is_finite() {
+                if x.to_bits() != y.to_bits() && !(x.is_nan() && y.is_nan()) && first_failure.is_none() {
+                    first_failure = Some(format!("element ({}, {}): {} vs reference {}", i, j, x, y));
+                }
+                continue;
+            }
+            let error = (x as f64 - y as f64).abs();
+            max_abs_error = max_abs_error.max(error);
+            let magnitude: f64 = (0..k).map(|p| (a.data[i * k + p] as f64 * b.data[p * n + j] as f64).abs()).sum();
+            let bound = 2.0 * gamma * magnitude;
+            if error > bound && !tolerance.allows(error, scale) && first_failure.is_none() {
+                first_failure = Some(format!(
+                    "element ({}, {}): {} vs reference {}, bound {:e}, tolerance {:e}",
+                    i,
+                    j,
+                    x,
+                    y,
+                    bound,
+                    tolerance.abs + tolerance.rel * scale
+                ));
+            }
+        }
+    }
+    (first_failure.map_or(Ok(()), Err), max_abs_error)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_generation_is_deterministic_and_bounded() {
+        let suite = generate_suite(7, 40, 1 << 14);
+        assert_eq!(suite, generate_suite(7, 40, 1 << 14));
+        assert_ne!(suite, generate_suite(8, 40, 1 << 14));
+        // A longer suite starts with the same cases
+        assert_eq!(suite[..10], generate_suite(7, 10, 1 << 14)[..]);
+
+        for case in &suite {
+            assert!(case.m * case.k <= 1 << 14 && case.k * case.n <= 1 << 14 && case.m * case.n <= 1 << 14, "{:?}", case);
+            assert!(case.m > 0 && case.k > 0 && case.n > 0, "{:?}", case);
+            match case.shape_class {
+                ShapeClass::Square => assert!(case.m == case.k && case.k == case.n),
+                ShapeClass::Prime => assert!([case.m, case.k, case.n].iter().all(|&d| prime_at_most(d) == d)),
+                ShapeClass::Seed => assert_eq!((case.m, case.n), (16, 16)),
+                ShapeClass::Skinny => assert!(case.m <= 16 && case.n <= 16),
+                ShapeClass::Tall => assert!(case.k <= 64 && case.n <= 64),
+            }
+        }
+        let distinct = |f: fn(&AcceptanceCase) -> String| suite.iter().map(f).collect::<std::collections::BTreeSet<_>>().len();
+        assert_eq!(distinct(|c| c.shape_class.as_str().to_string()), ShapeClass::ALL.len());
+        assert_eq!(distinct(|c| c.precision.clone()), Precision::ALL.len());
+        assert_eq!(distinct(|c| c.distribution.as_str().to_string()), Distribution::ALL.len());
+
+        let zeros = suite.iter().find(|c| c.distribution == Distribution::Zeros).unwrap();
+        assert!(zeros.matrices().0.data.iter().all(|&x| x == 0.0));
+        let bytes = suite.iter().find(|c| c.distribution == Distribution::ByteValued).unwrap();
+        let (a, b) = bytes.matrices();
+        assert!(a.data.iter().all(|&x| x.fract() == 0.0 && (0.0..=255.0).contains(&x)));
+        assert!(b.data.iter().all(|&x| x.fract() == 0.0 && (-128.0..=127.0).contains(&x)));
+    }
+
+    #[test]
+    fn test_tiny_suite_end_to_end() {
+        let first = run_suite(16, 5, 4096, &TolerancePolicy::default());
+        assert_eq!(first.summary.total, 5);
+        assert!(first.all_passed(), "{}", first.to_table());
+        for case in first.cases.iter().filter(|c| c.verdict == Verdict::Passed) {
+            assert!(case.result_hash.is_some() && case.reference_hash.is_some(), "{:?}", case);
+        }
+        assert_eq!(first.summary.skipped, first.cases.iter().filter(|c| !crate::tests::precisions().contains(&c.case.precision.as_str())).count());
+
+        // Another run reproduces every case, verdict and hash; only timings differ
+        let second = run_suite(16, 5, 4096, &TolerancePolicy::default());
+        assert!(second.diff(&first).is_empty(), "{:?}", second.diff(&first));
+        let json = serde_json::to_string(&first).unwrap();
+        let reloaded: AcceptanceReport = serde_json::from_str(&json).unwrap();
+        assert!(reloaded.diff(&first).is_empty());
+
+        let mut tampered = first.clone();
+        let passed = tampered.cases.iter().position(|c| c.verdict == Verdict::Passed).unwrap();
+        tampered.cases[passed].result_hash = Some("00".to_string());
+        let differences = second.diff(&tampered);
+        assert_eq!(differences.len(), 1, "{:?}", differences);
+        assert!(differences[0].starts_with(&first.cases[passed].case.name));
+        let other = second.diff(&run_suite(17, 5, 4096, &TolerancePolicy::default()));
+        assert!(other.len() == 1 && other[0].starts_with("different suites"), "{:?}", other);
+    }
+
+    #[test]
+    fn test_float_cases_honor_the_tolerance_policy() {
+        let (a, b) = (FlatMatrix { data: vec![1.0; 8], rows: 2, cols: 4 }, FlatMatrix { data: vec![0.5; 8], rows: 4, cols: 2 });
+        let reference = FlatMatrix { data: vec![2.0; 4], rows: 2, cols: 2 };
+        // Off by 1e-3 relative: far outside the error bound and the default fp32 tolerance
+        let got = FlatMatrix { data: vec![2.0, 2.002, 2.0, 2.0], rows: 2, cols: 2 };
+        let check = |policy: &TolerancePolicy| compare_float(&a, &b, &got, &reference, policy.effective("fp32").tolerance()).0;
+        assert!(check(&TolerancePolicy::default()).unwrap_err().starts_with("element (0, 1)"));
+        assert!(check(&TolerancePolicy::parse(Some("strict"), &[]).unwrap()).is_err());
+        assert!(check(&TolerancePolicy::parse(Some("strict"), &["fp32=2e-3,0".to_string()]).unwrap()).is_ok());
+        assert!(check(&TolerancePolicy::parse(Some("loose"), &[]).unwrap()).is_ok());
+        // Identical results pass under any tolerance
+        assert!(compare_float(&a, &b, &reference, &reference, Tolerance { rel: 0.0, abs: 0.0 }).0.is_ok());
+
+        // Float cases record what they were held to, and the report the policy
+        let policy = TolerancePolicy::parse(Some("loose"), &["fp16=0.5,0".to_string()]).unwrap();
+        let report = run_suite(16, 5, 4096, &policy);
+        assert!(report.all_passed() && report.tolerance == policy);
+        for case in &report.cases {
+            let integer = matches!(case.case.precision.as_str(), "int8" | "int8_rowwise" | "int8_asym" | "int4" | "u8i8");
+            let float = !integer && case.verdict == Verdict::Passed;
+            assert_eq!(case.tolerance, float.then(|| policy.effective(&case.case.precision)), "{}", case.case.name);
+        }
+    }
+}
@@ -1,103 +1,1659 @@
 #[cfg(feature = "api")]
+#[allow(clippy::module_inception)]
 pub mod api {
     use axum::{
-        extract::State,
-        http::StatusCode,
-        response::Json,
-        routing::post,
+        body::{Body, Bytes, HttpBody},
+        extract::{DefaultBodyLimit, FromRequest},
+        extract::{ConnectInfo, Path, Query, Request, State},
+        http::{HeaderMap, HeaderValue, StatusCode},
+        middleware::{self, Next},
+        response::{IntoResponse, Json, Response},
+        routing::{get, post},
         Router,
     };
     use tower_http::cors::CorsLayer;
-    use crate::{compute_workload, types, add_timing_breakdown};
+    use crate::memory::{self, RequestMemory, RequestMemoryStats, RequestMemorySummary};
+    use crate::numbers::{InputRowFormat, LimitViolation, MatrixLimit, MatrixLimits};
+    use crate::preflight::{self, Preflight, WorkloadShape};
+    use crate::validation::Shapes;
+    use crate::capabilities::{Capabilities, MaxDims, CAPABILITIES_VERSION};
+    use crate::{compute_workload_ref, fused, numbers, types, add_timing_breakdown, normalize_name, Accumulator, CacheContext, ComputeOptions, FlatMatrix, Fp16Rounding, Fp32Accumulation, Fp32Summation, HashRounding, Redundancy, SolverError};
+    use crate::jobs::{JobError, JobStatus, JobStore, JobStoreConfig, JobSummary, ResultMemory, ResultPage, DEFAULT_PAGE_ROWS};
+    use crate::rate_limit::{RateLimitConfig, RateLimiter};
+    use crate::replay::{ReplayEntry, ReplayLog, ReplayLogConfig, ReplayRequest};
+    use crate::traffic::{CaptureConfig, TrafficCapture};
+    use crate::scheduler::{Band, QueueStats, Scheduler, SchedulerConfig};
+    use crate::warnings::SolverWarning;
+    use crate::webhook::{CallbackDelivery, JobCallback, WebhookConfig, Webhooks};
+    use crate::verify_batch::{self, BatchReport, Claim};
+    use crate::tenant::{QuotaRejection, TenantConfig, TenantQuota, TenantTracker, TenantUsage};
+    use crate::tolerance::{Tolerance, TolerancePolicy, ToleranceProfile};
+    use std::collections::{BTreeMap, HashMap};
+    use std::net::SocketAddr;
+    use std::pin::Pin;
+    use std::sync::atomic::{AtomicU64, Ordering};
     use std::sync::Arc;
-    use std::time::Instant;
+    use std::time::{Duration, Instant};
+
+    // Server configuration
+    #[derive(Debug, Clone, Default)]
+    pub struct ApiConfig {
+        // Per-client rate limiting; None disables it
+        pub rate_limit: Option<RateLimitConfig>,
+        // Bounded log of served requests for /debug/replay; None disables it
+        pub replay_log: Option<ReplayLogConfig>,
+        // JSONL capture of served /compute requests for `matmul-solver replay`; None disables it
+        pub capture: Option<CaptureConfig>,
+        // Bearer token required by the /debug endpoints; None keeps them closed
+        pub debug_token: Option<String>,
+        // Bearer tokens accepted by /compute; None leaves it open
+        pub api_tokens: Option<Vec<String>>,
+        // Tenant resolution and per-tenant quotas; None disables tenancy
+        pub tenants: Option<TenantConfig>,
+        // Per-computation memory ceiling in bytes (ComputeOptions::max_memory_bytes); 413 when exceeded
+        pub max_memory_bytes: Option<u64>,
+        // Capacity of the background job store (POST /jobs)
+        pub jobs: JobStoreConfig,
+        // Concurrent computations and the FLOPs thresholds of their priority bands
+        pub scheduler: SchedulerConfig,
+        // Allowlist, signing secret and retries of job completion callbacks
+        pub webhooks: WebhookConfig,
+        // Rows, columns and elements a request matrix may have, enforced while it is parsed
+        pub matrix_limits: MatrixLimits,
+        // Whether request matrix rows may be objects keyed by column index (array only by default)
+        pub input_row_format: InputRowFormat,
+        // Largest request body accepted by /compute and /jobs (413 beyond); DEFAULT_MAX_BODY_BYTES when unset
+        pub max_body_bytes: Option<usize>,
+        // Content-Length from which /compute and /jobs bodies are parsed as they arrive instead of
+        // buffered first; DEFAULT_STREAM_BODY_MIN_BYTES when unset
+        pub stream_body_min_bytes: Option<usize>,
+        // Per-request allocation peak above which a warning is logged (counting-alloc builds)
+        pub request_memory_warn_bytes: Option<u64>,
+        // Packed Bs each of the server's 16x16 caches (AppState::caches) keeps; the
+        // process-wide capacity (b_cache::DEFAULT_CAPACITY) when unset
+        pub packed_b_cache_capacity: Option<usize>,
+        // Bytes of packed Bs each of those caches keeps; b_cache::DEFAULT_MAX_BYTES when unset
+        pub packed_b_cache_max_bytes: Option<usize>,
+    }
+
+    impl ApiConfig {
+        /// Build config from environment variables:
+        /// RATE_LIMIT_RPM, RATE_LIMIT_COMPUTE_SECONDS (per minute), TRUST_FORWARDED_FOR,
+        /// REPLAY_LOG_CAPACITY, REPLAY_LOG_MAX_AGE_SECS, CAPTURE_FILE, CAPTURE_MAX_REQUESTS, DEBUG_TOKEN, API_TOKENS (comma-separated),
+        /// TENANT_TOKENS (tenant:token,...), TENANT_MAX_REQUESTS, TENANT_MAX_BYTES, TENANT_QUOTA_WINDOW_SECS, TRUST_TENANT_HEADER,
+        /// MAX_MEMORY (bytes or 512M/2G), JOB_STORE_CAPACITY, MAX_CONCURRENT_COMPUTE,
+        /// PRIORITY_HIGH_MAX_FLOPS, PRIORITY_NORMAL_MAX_FLOPS, PRIORITY_LOW_SHARE,
+        /// WEBHOOK_ALLOWED_HOSTS and WEBHOOK_ALLOWED_SCHEMES (comma-separated), WEBHOOK_SECRET,
+        /// WEBHOOK_MAX_ATTEMPTS, MAX_MATRIX_ROWS, MAX_MATRIX_COLS, MAX_MATRIX_ELEMENTS,
+        /// MAX_BODY_BYTES, REQUEST_MEMORY_WARN (bytes or 512M/2G), INPUT_ROW_FORMAT (array, map or map_strict),
+        /// PACKED_B_CACHE_CAPACITY, PACKED_B_CACHE_MAX_BYTES (bytes or 512M/2G)
+        pub fn from_env() -> Self {
+            let rpm = crate::host::env_var("RATE_LIMIT_RPM").ok().and_then(|v| v.parse::<u32>().ok());
+            let compute = crate::host::env_var("RATE_LIMIT_COMPUTE_SECONDS").ok().and_then(|v| v.parse::<f64>().ok());
+            let trust_forwarded_for = crate::host::env_var("TRUST_FORWARDED_FOR").is_ok_and(|v| v == "1" || v == "true");
+            let rate_limit = if rpm.is_some() || compute.is_some() {
+                Some(RateLimitConfig {
+                    requests_per_window: rpm,
+                    compute_seconds_per_window: compute,
+                    trust_forwarded_for,
+                    ..Default::default()
+                })
+            } else {
+                None
+            };
+            let replay_capacity = crate::host::env_var("REPLAY_LOG_CAPACITY").ok().and_then(|v| v.parse::<usize>().ok());
+            let replay_max_age = crate::host::env_var("REPLAY_LOG_MAX_AGE_SECS").ok().and_then(|v| v.parse::<u64>().ok());
+            let replay_log = if replay_capacity.is_some() || replay_max_age.is_some() {
+                let defaults = ReplayLogConfig::default();
+                Some(ReplayLogConfig {
+                    capacity: replay_capacity.unwrap_or(defaults.capacity),
+                    max_age: replay_max_age.map(Duration::from_secs).unwrap_or(defaults.max_age),
+                })
+            } else {
+                None
+            };
+            let capture = crate::host::env_var("CAPTURE_FILE").ok().filter(|p| !p.is_empty()).map(|path| CaptureConfig {
+                path: path.into(),
+                max_requests: crate::host::env_var("CAPTURE_MAX_REQUESTS")
+                    .ok()
+                    .and_then(|v| v.parse::<usize>().ok())
+                    .unwrap_or(CaptureConfig::DEFAULT_MAX_REQUESTS),
+            });
+            let debug_token = crate::host::env_var("DEBUG_TOKEN").ok().filter(|t| !t.is_empty());
+            let api_tokens = crate::host::env_var("API_TOKENS").ok().map(|v| {
+                v.split(',').map(|t| t.trim().to_string()).filter(|t| !t.is_empty()).collect::<Vec<_>>()
+            });
+            let tenant_tokens = crate::host::env_var("TENANT_TOKENS").ok();
+            let max_requests = crate::host::env_var("TENANT_MAX_REQUESTS").ok().and_then(|v| v.parse::<u64>().ok());
+            let max_bytes = crate::host::env_var("TENANT_MAX_BYTES").ok().and_then(|v| v.parse::<u64>().ok());
+            let quota_window = crate::host::env_var("TENANT_QUOTA_WINDOW_SECS").ok().and_then(|v| v.parse::<u64>().ok());
+            let trust_tenant_header = crate::host::env_var("TRUST_TENANT_HEADER").is_ok_and(|v| v == "1" || v == "true");
+            let tenants = if tenant_tokens.is_some() || max_requests.is_some() || max_bytes.is_some() {
+                let tokens = tenant_tokens
+                    .iter()
+                    .flat_map(|v| v.split(','))
+                    .filter_map(|pair| pair.split_once(':'))
+                    .map(|(tenant, token)| (token.trim().to_string(), tenant.trim().to_string()))
+                    .collect();
+                let default_quota = (max_requests.is_some() || max_bytes.is_some()).then(|| TenantQuota {
+                    max_requests,
+                    max_bytes,
+                    window: quota_window.map(Duration::from_secs).unwrap_or(Duration::from_secs(60)),
+                });
+                Some(TenantConfig { tokens, quotas: HashMap::new(), default_quota, trust_tenant_header })
+            } else {
+                None
+            };
+            let max_memory_bytes = crate::host::env_var("MAX_MEMORY").ok().and_then(|v| crate::memory::parse_size(&v).ok());
+            let jobs = match crate::host::env_var("JOB_STORE_CAPACITY").ok().and_then(|v| v.parse::<usize>().ok()) {
+                Some(capacity) => JobStoreConfig { capacity },
+                None => JobStoreConfig::default(),
+            };
+            let defaults = SchedulerConfig::default();
+            let scheduler = SchedulerConfig {
+                max_concurrent: crate::host::env_var("MAX_CONCURRENT_COMPUTE")
+                    .ok()
+                    .and_then(|v| v.parse::<usize>().ok())
+                    .unwrap_or(defaults.max_concurrent),
+                high_max_flops: crate::host::env_var("PRIORITY_HIGH_MAX_FLOPS")
+                    .ok()
+                    .and_then(|v| v.parse::<f64>().ok())
+                    .unwrap_or(defaults.high_max_flops),
+                normal_max_flops: crate::host::env_var("PRIORITY_NORMAL_MAX_FLOPS")
+                    .ok()
+                    .and_then(|v| v.parse::<f64>().ok())
+                    .unwrap_or(defaults.normal_max_flops),
+                low_share: crate::host::env_var("PRIORITY_LOW_SHARE")
+                    .ok()
+                    .and_then(|v| v.parse::<f64>().ok())
+                    .unwrap_or(defaults.low_share),
+            };
+            let list = |name: &str| {
+                crate::host::env_var(name).ok().map(|v| {
+                    v.split(',').map(|s| s.trim().to_string()).filter(|s| !s.is_empty()).collect::<Vec<_>>()
+                })
+            };
+            let webhook_defaults = WebhookConfig::default();
+            let webhooks = WebhookConfig {
+                allowed_hosts: list("WEBHOOK_ALLOWED_HOSTS").unwrap_or_default(),
+                allowed_schemes: list("WEBHOOK_ALLOWED_SCHEMES").unwrap_or(webhook_defaults.allowed_schemes),
+                secret: crate::host::env_var("WEBHOOK_SECRET").ok().filter(|s| !s.is_empty()),
+                max_attempts: crate::host::env_var("WEBHOOK_MAX_ATTEMPTS")
+                    .ok()
+                    .and_then(|v| v.parse::<u32>().ok())
+                    .unwrap_or(webhook_defaults.max_attempts),
+                ..webhook_defaults
+            };
+            let size = |name: &str| crate::host::env_var(name).ok().and_then(|v| v.parse::<usize>().ok());
+            let limit_defaults = MatrixLimits::default();
+            let matrix_limits = MatrixLimits {
+                max_rows: size("MAX_MATRIX_ROWS").unwrap_or(limit_defaults.max_rows),
+                max_cols: size("MAX_MATRIX_COLS").unwrap_or(limit_defaults.max_cols),
+                max_elements: size("MAX_MATRIX_ELEMENTS").unwrap_or(limit_defaults.max_elements),
+            };
+            let input_row_format = crate::host::env_var("INPUT_ROW_FORMAT")
+                .ok()
+                .and_then(|v| InputRowFormat::parse(v.trim()).ok())
+                .unwrap_or_default();
+            let max_body_bytes = size("MAX_BODY_BYTES");
+            let stream_body_min_bytes = size("STREAM_BODY_MIN_BYTES");
+            let request_memory_warn_bytes =
+                crate::host::env_var("REQUEST_MEMORY_WARN").ok().and_then(|v| crate::memory::parse_size(&v).ok());
+            Self {
+                rate_limit,
+                replay_log,
+                capture,
+                debug_token,
+                api_tokens,
+                tenants,
+                max_memory_bytes,
+                jobs,
+                scheduler,
+                webhooks,
+                matrix_limits,
+                input_row_format,
+                max_body_bytes,
+                stream_body_min_bytes,
+                request_memory_warn_bytes,
+                packed_b_cache_capacity: size("PACKED_B_CACHE_CAPACITY"),
+                packed_b_cache_max_bytes: crate::host::env_var("PACKED_B_CACHE_MAX_BYTES")
+                    .ok()
+                    .and_then(|v| crate::memory::parse_size(&v).ok())
+                    .map(|bytes| bytes as usize),
+            }
+        }
+    }
 
     // Shared state for the API
     pub struct AppState {
-        // Can be used for caching or other state if needed
+        pub config: ApiConfig,
+        pub rate_limiter: Option<RateLimiter>,
+        pub replay_log: Option<ReplayLog>,
+        pub capture: Option<TrafficCapture>,
+        pub tenants: Option<TenantTracker>,
+        pub jobs: JobStore,
+        pub scheduler: Scheduler,
+        pub webhooks: Webhooks,
+        pub parse_aborts: ParseAborts,
+        pub request_memory: RequestMemoryStats,
+        #[cfg(feature = "dashboard")]
+        pub dashboard: crate::dashboard::DashboardStats,
+        /// This server's packed-B caches, so its requests leave the process-wide ones alone
+        pub caches: Arc<CacheContext>,
+    }
+
+    // Request bodies refused mid-parse, per matrix limit
+    #[derive(Debug, Default)]
+    pub struct ParseAborts {
+        counts: [AtomicU64; 3],
+    }
+
+    impl ParseAborts {
+        fn record(&self, limit: MatrixLimit) {
+            self.counts[limit as usize].fetch_add(1, Ordering::Relaxed);
+        }
+
+        pub fn get(&self, limit: MatrixLimit) -> u64 {
+            self.counts[limit as usize].load(Ordering::Relaxed)
+        }
+    }
+
+    impl AppState {
+        pub fn new(config: ApiConfig) -> Self {
+            let rate_limiter = config.rate_limit.clone().map(RateLimiter::new);
+            let replay_log = config.replay_log.clone().map(ReplayLog::new);
+            // A capture file that cannot be created leaves capture off rather than the server down
+            let capture = config.capture.as_ref().and_then(|capture| match TrafficCapture::create(capture) {
+                Ok(file) => Some(file),
+                Err(e) => {
+                    eprintln!("warning: traffic capture disabled, cannot create {}: {}", capture.path.display(), e);
+                    None
+                }
+            });
+            let tenants = config.tenants.clone().map(TenantTracker::new);
+            let jobs = JobStore::new(config.jobs.clone());
+            let scheduler = Scheduler::new(config.scheduler.clone());
+            let webhooks = Webhooks::new(config.webhooks.clone());
+            let caches = Arc::new(CacheContext::with_limits(config.packed_b_cache_capacity, config.packed_b_cache_max_bytes));
+            Self {
+                config,
+                rate_limiter,
+                replay_log,
+                capture,
+                tenants,
+                jobs,
+                scheduler,
+                webhooks,
+                parse_aborts: ParseAborts::default(),
+                request_memory: RequestMemoryStats::default(),
+                #[cfg(feature = "dashboard")]
+                dashboard: Default::default(),
+                caches,
+            }
+        }
+
+        // Priority band of a request, from the shapes it will multiply
+        fn band_of(&self, req: &ComputeRequest) -> Band {
+            let (m, k, n) = match (&req.seed, &req.matrix_a, &req.matrix_b) {
+                (Some(_), _, _) => {
+                    let dims = req.seed_dims.unwrap_or_default();
+                    (dims.m, dims.k, dims.n)
+                }
+                (None, Some(a), Some(b)) => (a.rows, a.cols, b.cols),
+                // A sparse B costs a fraction of this; the band errs high
+                (None, Some(a), None) if req.matrix_b_sparse.is_some() => {
+                    (a.rows, a.cols, req.matrix_b_sparse.as_ref().map_or(0, |b| b.cols))
+                }
+                // Quantizing touches each element once
+                (None, Some(a), None) => (a.rows, a.cols, 1),
+                _ => (0, 0, 0),
+            };
+            self.config.scheduler.classify(crate::scheduler::matmul_flops(m, k, n))
+        }
+
+        // Tenant of a request, when tenancy is configured
+        fn tenant_of(&self, headers: &HeaderMap) -> Option<String> {
+            let tracker = self.tenants.as_ref()?;
+            let header = headers.get("x-tenant").and_then(|v| v.to_str().ok());
+            Some(tracker.config().resolve(bearer_token(headers), header))
+        }
+    }
+
+    fn bearer_token(headers: &HeaderMap) -> Option<&str> {
+        headers
+            .get("authorization")
+            .and_then(|v| v.to_str().ok())
+            .and_then(|v| v.strip_prefix("Bearer "))
+            .map(str::trim)
+    }
+
+    // Reject requests without one of the configured API tokens
+    async fn auth_middleware(State(state): State<Arc<AppState>>, req: Request, next: Next) -> Response {
+        if let Some(tokens) = state.config.api_tokens.as_ref() {
+            let authorized = bearer_token(req.headers()).is_some_and(|token| tokens.iter().any(|t| t == token));
+            if !authorized {
+                return (StatusCode::UNAUTHORIZED, "Missing or invalid API token").into_response();
+            }
+        }
+        next.run(req).await
+    }
+
+    // Identify the caller: bearer token if present, otherwise the client IP
+    fn client_key(headers: &HeaderMap, peer: Option<SocketAddr>, trust_forwarded_for: bool) -> String {
+        if let Some(token) = bearer_token(headers) {
+            return format!("token:{}", token);
+        }
+        if trust_forwarded_for {
+            if let Some(ip) = headers
+                .get("x-forwarded-for")
+                .and_then(|v| v.to_str().ok())
+                .and_then(|v| v.split(',').next())
+            {
+                return format!("ip:{}", ip.trim());
+            }
+        }
+        match peer {
+            Some(addr) => format!("ip:{}", addr.ip()),
+            None => "anonymous".to_string(),
+        }
+    }
+
+    // Charge everything a request allocates to it, then record its peak. A job's background
+    // task holds a clone and records instead when it outlives the response.
+    async fn request_memory_middleware(
+        State(state): State<Arc<AppState>>,
+        req: Request,
+        next: Next,
+    ) -> Response {
+        let what = format!("{} {}", req.method(), req.uri().path());
+        let memory = RequestMemory::new();
+        let response = memory::track(memory.clone(), next.run(req)).await;
+        record_request_memory(&state, memory, &what);
+        response
+    }
+
+    // Add a finished request's peak to /stats, warning above the soft threshold; only the last
+    // holder of the counter records
+    fn record_request_memory(state: &AppState, memory: Arc<RequestMemory>, what: &str) {
+        let Some(peak) = Arc::into_inner(memory).and_then(|memory| memory.peak_bytes()) else {
+            return;
+        };
+        let soft_limit = state.config.request_memory_warn_bytes.filter(|&limit| peak > limit);
+        if let Some(limit) = soft_limit {
+            eprintln!(
+                "warning: {} allocated up to {:.1} MB, above the soft limit of {:.1} MB",
+                what,
+                memory::bytes_to_mb(peak),
+                memory::bytes_to_mb(limit)
+            );
+        }
+        state.request_memory.record(peak, soft_limit.is_some());
+    }
+
+    // Count an output on the dashboard (`dashboard` builds)
+    fn record_served(state: &AppState, output: &types::Output) {
+        #[cfg(feature = "dashboard")]
+        state.dashboard.record(output);
+        #[cfg(not(feature = "dashboard"))]
+        let _ = (state, output);
+    }
+
+    // Peak so far of the request this thread is charged to
+    fn peak_request_memory_mb(memory: Option<&RequestMemory>) -> Option<f64> {
+        memory.and_then(RequestMemory::peak_bytes).map(memory::bytes_to_mb)
+    }
+
+    // Token-bucket admission + rate-limit headers on every limited response
+    async fn rate_limit_middleware(
+        State(state): State<Arc<AppState>>,
+        req: Request,
+        next: Next,
+    ) -> Response {
+        let Some(limiter) = state.rate_limiter.as_ref() else {
+            return next.run(req).await;
+        };
+        let peer = req.extensions().get::<ConnectInfo<SocketAddr>>().map(|c| c.0);
+        let mut key = client_key(req.headers(), peer, limiter.config().trust_forwarded_for);
+        if let Some(tenant) = state.tenant_of(req.headers()) {
+            key = format!("tenant:{}|{}", tenant, key);
+        }
+
+        let clock = limiter.clock();
+        let decision = limiter.check(&key, clock.now());
+        let mut response = if decision.allowed {
+            let start = clock.now();
+            let response = next.run(req).await;
+            limiter.record_compute(&key, clock.elapsed_since(start).as_secs_f64(), clock.now());
+            response
+        } else {
+            let mut response = (StatusCode::TOO_MANY_REQUESTS, "Rate limit exceeded").into_response();
+            response
+                .headers_mut()
+                .insert("retry-after", HeaderValue::from(decision.retry_after_secs));
+            response
+        };
+        let headers = response.headers_mut();
+        headers.insert("x-ratelimit-limit", HeaderValue::from(decision.limit));
+        headers.insert("x-ratelimit-remaining", HeaderValue::from(decision.remaining));
+        response
     }
 
     // Request body for /compute endpoint
-    #[derive(serde::Deserialize)]
+    #[derive(Debug, Clone, Default, serde::Serialize, serde::Deserialize)]
     pub struct ComputeRequest {
         // Option 1: Provide matrices directly
-        pub matrix_a: Option<Vec<Vec<f32>>>,
-        pub matrix_b: Option<Vec<Vec<f32>>>,
+        #[serde(default, deserialize_with = "crate::numbers::optional_matrix_a")]
+        pub matrix_a: Option<FlatMatrix>,
+        #[serde(default, deserialize_with = "crate::numbers::optional_matrix_b")]
+        pub matrix_b: Option<FlatMatrix>,
+        // Or B in CSR form (fp32 matmul only)
+        pub matrix_b_sparse: Option<crate::sparse::SparseMatrix>,
         
         // Option 2: Generate from seed (deterministic)
         pub seed: Option<String>,
+        // Seed matrix dimensions; 16×50240 × 50240×16 when omitted
+        pub seed_dims: Option<SeedDims>,
         
         pub precision: String,
         pub workload_type: Option<String>,
+        
+        // Optional: force a registered kernel by name
+        pub kernel: Option<String>,
+        
+        // Optional: compute only these result columns
+        pub result_columns: Option<Vec<usize>>,
+        
+        // Optional: compiler_flags/libraries override the auto-detected build info
+        pub metadata: Option<types::InputMetadata>,
+        
+        // Optional: "exact" (default) or "rounded", with either mantissa bits or a quantum
+        pub hash_mode: Option<String>,
+        pub hash_mantissa_bits: Option<u32>,
+        pub hash_quantum: Option<f64>,
+        
+        // Optional: include tile_trace/trace_root in the response
+        pub tile_trace: Option<bool>,
+        
+        // Optional: reject numbers that overflow f32 (or lose integer precision for
+        // int8/u8i8) instead of counting them in metadata.lossy_parse_count
+        pub strict_numbers: Option<bool>,
+        
+        // Optional: fp32 16x16 accumulation order, "strict" (default) or "fast"
+        pub fp32_accumulation: Option<String>,
+
+        // Optional: fp32 summation over k, "naive" (default), "kahan" or "pairwise"
+        pub fp32_summation: Option<String>,
+        
+        // Optional: int8/u8i8 accumulator, "i32" (default), "i64" (u8i8 only), "i16_saturating" or "i32_saturating"
+        pub accumulator: Option<String>,
+        
+        // Optional: fp16 operand conversion, "nearest_even" (default), "toward_zero" or
+        // "stochastic" (which needs fp16_rounding_seed)
+        pub fp16_rounding: Option<String>,
+        pub fp16_rounding_seed: Option<u64>,
+        
+        // Optional: hash preimage, "f32le" (default), "i32le" (u8i8 only) or "f64le" (fp64 only, its default)
+        pub hash_input: Option<String>,
+        
+        // Optional: "transposed" when matrix_a holds Aᵀ (k×m) instead of A
+        pub a_layout: Option<String>,
+        
+        // Optional: "transposed" when matrix_b holds Bᵀ (n×k) instead of B
+        pub b_layout: Option<String>,
+        
+        // Optional: with workload_type "batched_matmul", how many items matrix_a (or a seed's
+        // m rows) stacks; each is multiplied by matrix_b
+        pub batch: Option<usize>,
+        
+        // Optional: "dual" computes twice and fails (500) unless the hashes agree
+        pub redundancy: Option<String>,
+        
+        // Optional: recompute from the same matrices before answering (kernel, accumulators
+        // and rounding replayed from the output metadata); sets verified and
+        // metrics.verify_time_ms
+        pub verify: Option<bool>,
+        
+        // Optional: overlap seed generation with the u8i8 16x16 kernel (same hash)
+        pub seed_pipeline: Option<bool>,
+        
+        // Optional: threads the fp32 blocked kernel and the 16x16 seed kernels split their
+        // rows across (builds with the parallel feature; same hash for any count); the
+        // count used is metrics.threads_used
+        pub threads: Option<usize>,
+        
+        // Optional, POST /jobs only: URL notified with a signed JobCallback when the job ends
+        pub callback_url: Option<String>,
+        
+        // Optional: capability names ("precision:fp16", "seed_profile:blake3-xof-v1", ...)
+        // the request relies on; 426 without computing when this build lacks any of them
+        pub require_capabilities: Option<Vec<String>>,
+        
+        // Optional: match precision, workload_type and kernel exactly instead of trimmed
+        // and case-insensitively
+        pub exact_names: Option<bool>,
+        
+        // Optional: also compute a reduced-precision matmul at fp32 and return that
+        // reference's hash and largest difference in auxiliary_results, with its matrix
+        // when aux_result_matrices is set
+        pub error_metrics: Option<bool>,
+        pub aux_result_matrices: Option<bool>,
+        
+        // Optional: "strict", "default" (default) or "loose" tolerances for the comparisons of
+        // the request (error_metrics), with per-precision overrides {"fp16": {"rel": .., "abs": ..}}
+        pub tolerance_profile: Option<String>,
+        pub tolerances: Option<BTreeMap<String, Tolerance>>,
+    }
+
+    impl ComputeRequest {
+        // precision, workload_type and kernel in normalize_name form, unless exact_names
+        fn normalize_names(&mut self) {
+            if self.exact_names.unwrap_or(false) {
+                return;
+            }
+            self.precision = normalize_name(&self.precision);
+            for name in [&mut self.workload_type, &mut self.kernel].into_iter().flatten() {
+                *name = normalize_name(name);
+            }
+        }
+    }
+
+    pub use crate::SeedDims;
+
+    // Request body cap when ApiConfig::max_body_bytes is unset, axum's own default
+    pub const DEFAULT_MAX_BODY_BYTES: usize = 2 << 20;
+
+    // Streaming threshold when ApiConfig::stream_body_min_bytes is unset
+    pub const DEFAULT_STREAM_BODY_MIN_BYTES: usize = 1 << 20;
+
+    // Body chunks queued for the streaming parser before the upload waits for it, or for
+    // the client before a streamed response waits for it
+    const STREAM_CHUNKS_IN_FLIGHT: usize = 4;
+
+    // Responses whose result has at least this many elements are streamed (json_response)
+    const STREAM_RESPONSE_MIN_ELEMENTS: usize = 1 << 16;
+
+    // Size of the chunks a streamed response is sent in
+    const RESPONSE_CHUNK_BYTES: usize = 64 << 10;
+
+    // Largest seed matrix (in elements) the server will generate
+    const MAX_SEED_ELEMENTS: usize = 1 << 26;
+
+    // Whether the client asked for a MessagePack response
+    fn wants_msgpack(headers: &HeaderMap) -> bool {
+        headers
+            .get("accept")
+            .and_then(|v| v.to_str().ok())
+            .is_some_and(|accept| accept.split(',').any(|t| t.trim().starts_with(MSGPACK_CONTENT_TYPE)))
+    }
+
+    pub const MSGPACK_CONTENT_TYPE: &str = "application/msgpack";
+
+    // A compute request body that passed the tenant quota and number checks
+    struct AdmittedRequest {
+        req: ComputeRequest,
+        tenant: Option<String>,
+        // Lossy numbers in supplied matrices; None for seed requests
+        lossy_count: Option<usize>,
+    }
+
+    // A parsed body with its lossy numbers, and the matrix limit that stopped it if any
+    type ParsedBody = ((Result<ComputeRequest, serde_json::Error>, Vec<numbers::LossyValue>), Option<numbers::LimitViolation>);
+
+    // Read and admit a /compute or /jobs body. Bodies declaring at least
+    // stream_body_min_bytes are parsed as they arrive (admit_streamed_request); smaller ones
+    // are buffered first. Returns the request headers along with it.
+    async fn read_compute_request(state: &AppState, request: Request) -> Result<(HeaderMap, AdmittedRequest), Box<Response>> {
+        let headers = request.headers().clone();
+        let declared = headers
+            .get(axum::http::header::CONTENT_LENGTH)
+            .and_then(|v| v.to_str().ok())
+            .and_then(|v| v.parse::<usize>().ok());
+        let threshold = state.config.stream_body_min_bytes.unwrap_or(DEFAULT_STREAM_BODY_MIN_BYTES);
+        let admitted = match declared {
+            Some(declared) if declared >= threshold => {
+                admit_streamed_request(state, &headers, declared, request.into_body()).await?
+            }
+            _ => {
+                let body = Bytes::from_request(request, &()).await.map_err(|rejection| Box::new(rejection.into_response()))?;
+                admit_compute_request(state, &headers, &body)?
+            }
+        };
+        Ok((headers, admitted))
+    }
+
+    // Admit and parse a buffered /compute or /jobs body. The body is parsed here rather than by
+    // the Json extractor so lossy numbers can be collected during deserialization. A rejection
+    // comes back as the response to send.
+    fn admit_compute_request(state: &AppState, headers: &HeaderMap, body: &Bytes) -> Result<AdmittedRequest, Box<Response>> {
+        let tenant = admit_tenant(state, headers, body.len())?;
+        let parsed = numbers::with_row_format(state.config.input_row_format, || {
+            numbers::with_limits(state.config.matrix_limits, || numbers::collect_lossy(|| serde_json::from_slice::<ComputeRequest>(body)))
+        });
+        finish_admission(state, tenant, parsed)
+    }
+
+    // Parse a body of `declared` bytes while it is uploaded: each chunk is handed to a blocking
+    // parser that appends matrix rows as it reads, and is freed once consumed, so the request
+    // holds the parsed matrices plus a few chunks rather than the whole body as well. The size
+    // cap is enforced on the bytes actually received.
+    async fn admit_streamed_request(state: &AppState, headers: &HeaderMap, declared: usize, mut body: Body) -> Result<AdmittedRequest, Box<Response>> {
+        let limit = state.config.max_body_bytes.unwrap_or(DEFAULT_MAX_BODY_BYTES);
+        let too_large = || Box::new((StatusCode::PAYLOAD_TOO_LARGE, format!("Request body exceeds {} bytes", limit)).into_response());
+        if declared > limit {
+            return Err(too_large());
+        }
+        let tenant = admit_tenant(state, headers, declared)?;
+
+        let (chunks, receiver) = tokio::sync::mpsc::channel(STREAM_CHUNKS_IN_FLIGHT);
+        let (limits, row_format, memory) = (state.config.matrix_limits, state.config.input_row_format, RequestMemory::current());
+        let parser = tokio::task::spawn_blocking(move || {
+            let _scope = memory.as_ref().map(RequestMemory::enter);
+            let reader = std::io::BufReader::new(ChunkReader { chunks: receiver, current: Bytes::new() });
+            numbers::with_row_format(row_format, || {
+                numbers::with_limits(limits, || numbers::collect_lossy(|| serde_json::from_reader::<_, ComputeRequest>(reader)))
+            })
+        });
+
+        let mut received = 0;
+        let upload = loop {
+            let chunk = match std::future::poll_fn(|cx| Pin::new(&mut body).poll_frame(cx)).await {
+                None => break Ok(()),
+                Some(Err(e)) => {
+                    break Err(Box::new((StatusCode::BAD_REQUEST, format!("Failed to read the request body: {}", e)).into_response()))
+                }
+                Some(Ok(frame)) => match frame.into_data() {
+                    Ok(chunk) => chunk,
+                    Err(_trailers) => continue,
+                },
+            };
+            received += chunk.len();
+            if received > limit {
+                break Err(too_large());
+            }
+            // The parser hangs up early on malformed JSON or a matrix limit; its error says why
+            if chunks.send(chunk).await.is_err() {
+                break Ok(());
+            }
+        };
+        // End of input for the parser, also when the upload failed
+        drop(chunks);
+        let parsed = parser.await.map_err(|e| {
+            Box::new((StatusCode::INTERNAL_SERVER_ERROR, format!("Request body parser failed: {}", e)).into_response())
+        })?;
+        upload?;
+        finish_admission(state, tenant, parsed)
+    }
+
+    // Blocking reader over the chunks forwarded by admit_streamed_request. A chunk is dropped
+    // once it has been read; the channel closing is the end of the body.
+    struct ChunkReader {
+        chunks: tokio::sync::mpsc::Receiver<Bytes>,
+        current: Bytes,
+    }
+
+    impl std::io::Read for ChunkReader {
+        fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+            while self.current.is_empty() {
+                match self.chunks.blocking_recv() {
+                    Some(chunk) => self.current = chunk,
+                    None => return Ok(0),
+                }
+            }
+            let n = buf.len().min(self.current.len());
+            buf[..n].copy_from_slice(&self.current[..n]);
+            self.current = self.current.slice(n..);
+            Ok(n)
+        }
+    }
+
+    // Blocking writer behind a streamed response (json_response): bytes collect into chunks
+    // of RESPONSE_CHUNK_BYTES, each sent once full; the client going away fails the write
+    struct ChunkWriter {
+        chunk: Vec<u8>,
+        chunks: tokio::sync::mpsc::Sender<std::io::Result<Bytes>>,
+    }
+
+    impl ChunkWriter {
+        fn send(&mut self) -> std::io::Result<()> {
+            if self.chunk.is_empty() {
+                return Ok(());
+            }
+            let chunk = std::mem::replace(&mut self.chunk, Vec::with_capacity(RESPONSE_CHUNK_BYTES));
+            self.chunks
+                .blocking_send(Ok(chunk.into()))
+                .map_err(|_| std::io::Error::new(std::io::ErrorKind::BrokenPipe, "the client closed the response"))
+        }
+    }
+
+    impl std::io::Write for ChunkWriter {
+        fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+            self.chunk.extend_from_slice(buf);
+            if self.chunk.len() >= RESPONSE_CHUNK_BYTES {
+                self.send()?;
+            }
+            Ok(buf.len())
+        }
+
+        fn flush(&mut self) -> std::io::Result<()> {
+            self.send()
+        }
+    }
+
+    // Charge a body of `bytes` to the request's tenant quota; the tenant if there is one
+    fn admit_tenant(state: &AppState, headers: &HeaderMap, bytes: usize) -> Result<Option<String>, Box<Response>> {
+        let tenant = state.tenant_of(headers);
+        if let (Some(tracker), Some(tenant)) = (state.tenants.as_ref(), tenant.as_deref()) {
+            if let Err(rejection) = tracker.admit(tenant, bytes as u64, Instant::now()) {
+                return Err(Box::new(quota_response(tenant, rejection)));
+            }
+        }
+        Ok(tenant)
+    }
+
+    // The checks shared by both parse paths: matrix limits, JSON errors, strict numbers and
+    // required capabilities
+    fn finish_admission(state: &AppState, tenant: Option<String>, parsed: ParsedBody) -> Result<AdmittedRequest, Box<Response>> {
+        let ((parsed, lossy), violation) = parsed;
+        if let Some(violation) = violation {
+            state.parse_aborts.record(violation.limit);
+            return Err(Box::new(limit_error(&violation).into_response()));
+        }
+        let mut req = parsed.map_err(|e| Box::new(json_error(&e).into_response()))?;
+        req.normalize_names();
+        if req.strict_numbers.unwrap_or(false) {
+            numbers::check_strict(&lossy, &req.precision)
+                .map_err(|e| Box::new((StatusCode::BAD_REQUEST, e.to_string()).into_response()))?;
+        }
+        check_required_capabilities(&req).map_err(|e| Box::new(e.into_response()))?;
+        let lossy_count = req.seed.is_none().then_some(lossy.len());
+        Ok(AdmittedRequest { req, tenant, lossy_count })
+    }
+
+    // 413 naming the matrix limit a body exceeded
+    fn limit_error(violation: &LimitViolation) -> ApiError {
+        ApiError {
+            status: StatusCode::PAYLOAD_TOO_LARGE,
+            message: violation.to_string(),
+            details: Some(serde_json::json!(violation)),
+        }
+    }
+
+    // Same split as axum's Json rejection: malformed JSON vs wrong shape
+    fn json_error(e: &serde_json::Error) -> ApiError {
+        let status = if e.is_data() { StatusCode::UNPROCESSABLE_ENTITY } else { StatusCode::BAD_REQUEST };
+        (status, format!("Failed to parse the request body as JSON: {}", e)).into()
+    }
+
+    // 426 naming the capabilities in require_capabilities this build lacks
+    fn check_required_capabilities(req: &ComputeRequest) -> Result<(), ApiError> {
+        let Some(required) = req.require_capabilities.as_deref() else {
+            return Ok(());
+        };
+        let missing = Capabilities::current(None).missing(required);
+        if missing.is_empty() {
+            return Ok(());
+        }
+        Err(ApiError {
+            status: StatusCode::UPGRADE_REQUIRED,
+            message: format!("This solver lacks required capabilities: {}", missing.join(", ")),
+            details: Some(serde_json::json!({ "missing": missing, "capabilities_version": CAPABILITIES_VERSION })),
+        })
+    }
+
+    // Record lossy parsing in the output of a matrix request
+    fn note_lossy(output: &mut types::Output, lossy_count: Option<usize>) {
+        if let Some(count) = lossy_count {
+            output.metadata.lossy_parse_count = Some(count);
+            if count > 0 {
+                output.warnings.push(SolverWarning::lossy_parse(count));
+            }
+        }
     }
 
     // POST /compute - Accept matrix input (JSON or seed) and return result
     async fn compute_handler(
-        State(_state): State<Arc<AppState>>,
-        Json(req): Json<ComputeRequest>,
-    ) -> Result<Json<types::Output>, (StatusCode, String)> {
-        let parse_start = Instant::now();
-        
-        let input = if let Some(seed_hex) = req.seed {
-            // Generate from seed (deterministic)
-            let (matrix_a, matrix_b) = crate::generate_matrices_from_seed_hex(
-                &seed_hex,
-                16, 50240, 50240, 16,  // Seed dimensions
-            ).map_err(|e| (StatusCode::BAD_REQUEST, e))?;
-            
-            types::Input {
-                matrix_a,
-                matrix_b,
-                precision: req.precision,
-                workload_type: req.workload_type.or(Some("matmul".to_string())),
-                metadata: None,
+        State(state): State<Arc<AppState>>,
+        request: Request,
+    ) -> Result<Response, ApiError> {
+        let arrived = std::time::SystemTime::now();
+        let (headers, AdmittedRequest { mut req, tenant, lossy_count }) = match read_compute_request(&state, request).await {
+            Ok(admitted) => admitted,
+            Err(response) => return Ok(*response),
+        };
+        check_not_a_job(&req)?;
+        let replay = state.replay_log.as_ref().map(|_| replay_request(&req));
+        let captured = state.capture.as_ref().and_then(|_| crate::traffic::capture_request(&mut req, arrived));
+        let _permit = state.scheduler.acquire(state.band_of(&req)).await;
+        // The replay log and the capture record the hash before the response is written
+        let defer_hash = replay.is_none() && captured.is_none();
+        let (max_memory_bytes, caches) = (state.config.max_memory_bytes, state.caches.clone());
+        let mut output = compute_blocking(move || process_request_deferred(req, max_memory_bytes, defer_hash, Some(caches))).await?;
+        note_lossy(&mut output, lossy_count);
+        output.metrics.peak_request_memory_mb = peak_request_memory_mb(RequestMemory::current().as_deref());
+        record_served(&state, &output);
+        if let (Some(log), Some(replay)) = (state.replay_log.as_ref(), replay) {
+            log.record(ReplayEntry::new(replay, tenant, &output), Instant::now());
+        }
+        if let (Some(capture), Some(mut captured)) = (state.capture.as_ref(), captured) {
+            // Only a request replayed as it came can reproduce its hash
+            captured.result_hash = captured.synthetic.is_none().then(|| output.result_hash.clone());
+            if let Err(e) = capture.record(&captured) {
+                eprintln!("warning: traffic capture failed: {}", e);
             }
-        } else {
-            // Use provided matrices
-            let matrix_a = req.matrix_a.ok_or_else(|| (StatusCode::BAD_REQUEST, "matrix_a is required when not using seed".to_string()))?;
-            let matrix_b = req.matrix_b.ok_or_else(|| (StatusCode::BAD_REQUEST, "matrix_b is required when not using seed".to_string()))?;
-            
-            // Convert Vec<Vec<f32>> to FlatMatrix
-            let rows_a = matrix_a.len();
-            let cols_a = if rows_a > 0 { matrix_a[0].len() } else { 0 };
-            let mut a_data = Vec::with_capacity(rows_a * cols_a);
-            for row in matrix_a {
-                a_data.extend_from_slice(&row);
+        }
+        if wants_msgpack(&headers) {
+            let (bytes, _) = fused::serialize_hashed(&output, || rmp_serde::to_vec_named(&output))
+                .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+            return Ok(([("content-type", MSGPACK_CONTENT_TYPE)], bytes).into_response());
+        }
+        json_response(output)
+    }
+
+    fn check_not_a_job(req: &ComputeRequest) -> Result<(), ApiError> {
+        match req.callback_url {
+            Some(_) => Err((StatusCode::BAD_REQUEST, "callback_url applies to POST /jobs only".to_string()).into()),
+            None => Ok(()),
+        }
+    }
+
+    // Body of POST /validate describing a workload by its shapes rather than its matrices.
+    // `options` holds any other /compute fields (workload_type, kernel, hash_input, batch,
+    // layouts, ...), and not the matrices or a seed.
+    #[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
+    pub struct ValidateShapesRequest {
+        pub matrix_a_shape: (usize, usize),
+        // Not needed to quantize
+        pub matrix_b_shape: Option<(usize, usize)>,
+        pub precision: String,
+        #[serde(default)]
+        pub options: serde_json::Map<String, serde_json::Value>,
+    }
+
+    // POST /validate - Run the checks /compute makes on a /compute body, or on a
+    // ValidateShapesRequest, without computing anything: the same admission (quota, matrix
+    // limits, capabilities), the same options and the same pre-kernel checks
+    // (preflight::check). Answers the predicted result shape, op count, memory estimate and
+    // kernel, or exactly the error /compute would answer.
+    async fn validate_handler(
+        State(state): State<Arc<AppState>>,
+        request: Request,
+    ) -> Result<Json<Preflight>, Response> {
+        let headers = request.headers().clone();
+        let body = Bytes::from_request(request, &()).await.map_err(IntoResponse::into_response)?;
+        // Shapes-only bodies are told apart by matrix_a_shape; anything else is a /compute body
+        #[derive(serde::Deserialize)]
+        struct Probe {
+            matrix_a_shape: Option<serde::de::IgnoredAny>,
+        }
+        if serde_json::from_slice::<Probe>(&body).is_ok_and(|probe| probe.matrix_a_shape.is_some()) {
+            admit_tenant(&state, &headers, body.len()).map_err(|response| *response)?;
+            return validate_shapes(&state, &body).map(Json).map_err(IntoResponse::into_response);
+        }
+        let AdmittedRequest { req, .. } = admit_compute_request(&state, &headers, &body).map_err(|response| *response)?;
+        preflight_request(&req, state.config.max_memory_bytes).map(Json).map_err(IntoResponse::into_response)
+    }
+
+    fn validate_shapes(state: &AppState, body: &Bytes) -> Result<Preflight, ApiError> {
+        let shapes: ValidateShapesRequest = serde_json::from_slice(body).map_err(|e| json_error(&e))?;
+        if let Some(field) = ["matrix_a", "matrix_b", "matrix_b_sparse", "seed", "seed_dims"].into_iter().find(|&f| shapes.options.contains_key(f)) {
+            return Err((StatusCode::BAD_REQUEST, format!("{} does not belong in the options of a shapes-only request", field)).into());
+        }
+        let b_shape = shapes.matrix_b_shape.unwrap_or_default();
+        for (matrix, shape) in [("matrix_a", shapes.matrix_a_shape), ("matrix_b", b_shape)] {
+            state.config.matrix_limits.check_shape(matrix, shape).map_err(|violation| limit_error(&violation))?;
+        }
+        let mut fields = shapes.options;
+        fields.insert("precision".to_string(), serde_json::Value::String(shapes.precision));
+        let mut req: ComputeRequest = serde_json::from_value(serde_json::Value::Object(fields)).map_err(|e| json_error(&e))?;
+        req.normalize_names();
+        check_required_capabilities(&req)?;
+        check_not_a_job(&req)?;
+        let options = request_options(&req, state.config.max_memory_bytes, false)?;
+        if shapes.matrix_b_shape.is_none() && req.workload_type.as_deref() != Some("quantize") {
+            return Err((StatusCode::BAD_REQUEST, "matrix_b_shape is required except to quantize".to_string()).into());
+        }
+        let workload = request_workload(&req, Shapes::dense(shapes.matrix_a_shape, b_shape));
+        Ok(preflight::check(&workload, &options)?)
+    }
+
+    // preflight::check on an admitted /compute body, after the checks process_request_deferred
+    // makes before computing. A sparse B's arrays are checked too.
+    fn preflight_request(req: &ComputeRequest, max_memory_bytes: Option<u64>) -> Result<Preflight, ApiError> {
+        check_not_a_job(req)?;
+        let options = request_options(req, max_memory_bytes, false)?;
+        let shapes = match request_seed(req)? {
+            Some((_, dims)) => {
+                check_seed_budget(req, dims, max_memory_bytes)?;
+                Shapes::dense((dims.m, dims.k), (dims.k, dims.n))
             }
-            
-            let rows_b = matrix_b.len();
-            let cols_b = if rows_b > 0 { matrix_b[0].len() } else { 0 };
-            let mut b_data = Vec::with_capacity(rows_b * cols_b);
-            for row in matrix_b {
-                b_data.extend_from_slice(&row);
+            None => {
+                check_matrices(req)?;
+                let extents = |m: &FlatMatrix| (m.rows, m.cols, m.data.len());
+                Shapes {
+                    matrix_a: req.matrix_a.as_ref().map(extents).unwrap_or_default(),
+                    matrix_b: req.matrix_b.as_ref().map(extents).unwrap_or_default(),
+                    matrix_b_sparse: req.matrix_b_sparse.as_ref().map(|b| (b.rows, b.cols, b.nnz())),
+                    ..Default::default()
+                }
+            }
+        };
+        let preflight = preflight::check(&request_workload(req, shapes), &options)?;
+        if let Some(sparse) = &req.matrix_b_sparse {
+            sparse.check()?;
+        }
+        Ok(preflight)
+    }
+
+    // The workload of a request whose operands have `shapes`
+    fn request_workload(req: &ComputeRequest, shapes: Shapes) -> WorkloadShape {
+        WorkloadShape {
+            workload_type: req.workload_type.clone(),
+            precision: req.precision.clone(),
+            hash_input: req.hash_input.clone(),
+            shapes: Shapes { a_layout: req.a_layout.clone(), b_layout: req.b_layout.clone(), ..shapes },
+            batch: req.batch,
+            ..Default::default()
+        }
+    }
+
+    // An output as JSON, hashing a deferred result as it is serialized (fused.rs). Results
+    // of at least STREAM_RESPONSE_MIN_ELEMENTS are streamed: a blocking task serializes into
+    // chunks that are sent as they fill, so the response never exists whole in memory.
+    fn json_response(output: types::Output) -> Result<Response, ApiError> {
+        let content_type = [("content-type", "application/json")];
+        if output.result_matrix.data.len() < STREAM_RESPONSE_MIN_ELEMENTS {
+            let mut body = Vec::new();
+            fused::write_json(&mut body, &output, false).map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+            return Ok((content_type, body).into_response());
+        }
+        let (tx, rx) = tokio::sync::mpsc::channel(STREAM_CHUNKS_IN_FLIGHT);
+        tokio::task::spawn_blocking(move || {
+            let mut writer = ChunkWriter { chunk: Vec::with_capacity(RESPONSE_CHUNK_BYTES), chunks: tx };
+            if let Err(e) = fused::write_json(&mut writer, &output, false) {
+                // Ends the body with an error, so the client sees a broken response rather
+                // than a truncated document
+                let _ = writer.chunks.blocking_send(Err(std::io::Error::other(e.to_string())));
             }
+        });
+        let body = Body::from_stream(tokio_stream::wrappers::ReceiverStream::new(rx));
+        Ok((content_type, body).into_response())
+    }
+
+    #[derive(Debug, serde::Serialize, serde::Deserialize)]
+    pub struct JobAccepted {
+        pub id: String,
+        pub status: JobStatus,
+    }
+
+    // POST /jobs - Same body as /compute; runs in the background and answers 202 with the
+    // job id to poll at GET /jobs/{id}. With callback_url the outcome is also POSTed there.
+    async fn submit_job_handler(
+        State(state): State<Arc<AppState>>,
+        request: Request,
+    ) -> Response {
+        let (_, AdmittedRequest { req, tenant, lossy_count }) = match read_compute_request(&state, request).await {
+            Ok(admitted) => admitted,
+            Err(response) => return *response,
+        };
+        let callback = match req.callback_url.as_deref().map(|url| state.webhooks.check_url(url)).transpose() {
+            Ok(callback) => callback,
+            Err(e) => return (StatusCode::BAD_REQUEST, e).into_response(),
+        };
+        let id = state.jobs.submit(tenant);
+        if let Some(url) = &callback {
+            state.jobs.set_callback(&id, CallbackDelivery::pending(url));
+        }
+        let job_id = id.clone();
+        let memory = RequestMemory::current();
+        tokio::spawn(async move {
+            let _permit = state.scheduler.acquire(state.band_of(&req)).await;
+            let (max_memory_bytes, caches) = (state.config.max_memory_bytes, state.caches.clone());
+            let job_memory = memory.clone();
+            let compute = move || {
+                let _scope = job_memory.as_ref().map(RequestMemory::enter);
+                process_request_deferred(req, max_memory_bytes, false, Some(caches))
+            };
+            let outcome = match compute_blocking(compute).await {
+                Ok(mut output) => {
+                    note_lossy(&mut output, lossy_count);
+                    output.metrics.peak_request_memory_mb = peak_request_memory_mb(memory.as_deref());
+                    record_served(&state, &output);
+                    Ok(output)
+                }
+                Err(e) => Err(e.job_error()),
+            };
+            let payload = callback.as_ref().map(|_| JobCallback::new(&job_id, &outcome));
+            // In /stats by the time the job shows as finished
+            if let Some(memory) = memory {
+                record_request_memory(&state, memory, &format!("job {}", job_id));
+            }
+            state.jobs.complete(&job_id, outcome);
+            if let (Some(url), Some(payload)) = (callback, payload) {
+                state.webhooks.deliver(&url, &payload, |delivery| state.jobs.set_callback(&job_id, delivery.clone())).await;
+            }
+        });
+        (StatusCode::ACCEPTED, Json(JobAccepted { id, status: JobStatus::Running })).into_response()
+    }
+
+    impl From<JobError> for ApiError {
+        fn from(e: JobError) -> Self {
+            let status = match e {
+                JobError::NotFound(_) => StatusCode::NOT_FOUND,
+                JobError::NoResult(_) => StatusCode::CONFLICT,
+                JobError::InvalidRange(_) => StatusCode::BAD_REQUEST,
+            };
+            (status, e.to_string()).into()
+        }
+    }
+
+    #[derive(Debug, serde::Deserialize)]
+    struct JobQuery {
+        inline: Option<bool>,
+    }
+
+    // GET /jobs/{id}?inline=true - Status, hash, metrics and metadata; the full result
+    // only when inline is set and the result is small
+    async fn job_handler(
+        State(state): State<Arc<AppState>>,
+        headers: HeaderMap,
+        Path(id): Path<String>,
+        Query(query): Query<JobQuery>,
+    ) -> Result<Json<JobSummary>, ApiError> {
+        let tenant = state.tenant_of(&headers);
+        Ok(Json(state.jobs.summary(&id, tenant.as_deref(), query.inline.unwrap_or(false))?))
+    }
+
+    #[derive(Debug, serde::Deserialize)]
+    struct ResultQuery {
+        offset_rows: Option<usize>,
+        limit_rows: Option<usize>,
+    }
+
+    // GET /jobs/{id}/result?offset_rows=0&limit_rows=256 - A row range of a finished job's result
+    async fn job_result_handler(
+        State(state): State<Arc<AppState>>,
+        headers: HeaderMap,
+        Path(id): Path<String>,
+        Query(query): Query<ResultQuery>,
+    ) -> Result<Json<ResultPage>, ApiError> {
+        let tenant = state.tenant_of(&headers);
+        let (offset, limit) = (query.offset_rows.unwrap_or(0), query.limit_rows.unwrap_or(DEFAULT_PAGE_ROWS));
+        Ok(Json(state.jobs.page(&id, tenant.as_deref(), offset, limit)?))
+    }
+
+    fn quota_response(tenant: &str, rejection: QuotaRejection) -> Response {
+        match rejection {
+            QuotaRejection::Requests { retry_after_secs } => {
+                let mut response =
+                    (StatusCode::TOO_MANY_REQUESTS, format!("Request quota exceeded for tenant {}", tenant)).into_response();
+                response.headers_mut().insert("retry-after", HeaderValue::from(retry_after_secs));
+                response
+            }
+            QuotaRejection::Bytes { limit, used, requested } => (
+                StatusCode::PAYLOAD_TOO_LARGE,
+                format!(
+                    "Byte quota exceeded for tenant {}: {} of {} bytes used, request is {} bytes",
+                    tenant, used, limit, requested
+                ),
+            )
+                .into_response(),
+        }
+    }
+
+    // Replay-log view of a request: configuration plus matrix digests, never the matrices
+    fn replay_request(req: &ComputeRequest) -> ReplayRequest {
+        let digest = |m: &Option<FlatMatrix>| m.as_ref().map(|m| hex::encode(crate::pack_cache::matrix_digest(m)));
+        ReplayRequest {
+            seed: req.seed.clone(),
+            seed_dims: req.seed_dims,
+            matrix_a_digest: digest(&req.matrix_a),
+            matrix_b_digest: digest(&req.matrix_b),
+            precision: req.precision.clone(),
+            workload_type: req.workload_type.clone(),
+            kernel: req.kernel.clone(),
+            result_columns: req.result_columns.clone(),
+            hash_mode: req.hash_mode.clone(),
+            hash_mantissa_bits: req.hash_mantissa_bits,
+            hash_quantum: req.hash_quantum,
+            tile_trace: req.tile_trace,
+            fp32_accumulation: req.fp32_accumulation.clone(),
+            fp32_summation: req.fp32_summation.clone(),
+            hash_input: req.hash_input.clone(),
+            accumulator: req.accumulator.clone(),
+            fp16_rounding: req.fp16_rounding.clone(),
+            fp16_rounding_seed: req.fp16_rounding_seed,
+            a_layout: req.a_layout.clone(),
+            b_layout: req.b_layout.clone(),
+            batch: req.batch,
+            redundancy: req.redundancy.clone(),
+        }
+    }
+
+    // /debug, /stats, /metrics, /cache/clear and /dashboard need `Authorization: Bearer <DEBUG_TOKEN>`
+    fn require_debug_token(state: &AppState, headers: &HeaderMap) -> Result<(), (StatusCode, String)> {
+        match (state.config.debug_token.as_deref(), bearer_token(headers)) {
+            (Some(expected), Some(token)) if expected == token => Ok(()),
+            _ => Err((StatusCode::UNAUTHORIZED, "Unauthorized".to_string())),
+        }
+    }
+
+    fn replay_log_for<'a>(state: &'a AppState, headers: &HeaderMap) -> Result<&'a ReplayLog, (StatusCode, String)> {
+        require_debug_token(state, headers)?;
+        state
+            .replay_log
+            .as_ref()
+            .ok_or_else(|| (StatusCode::NOT_FOUND, "Replay log is disabled".to_string()))
+    }
+
+    #[derive(Debug, serde::Deserialize)]
+    struct ReplayQuery {
+        limit: Option<usize>,
+        tenant: Option<String>,
+    }
+
+    // GET /debug/replay?limit=N&tenant=T - Most recent entries, newest first (default 50)
+    async fn replay_list_handler(
+        State(state): State<Arc<AppState>>,
+        headers: HeaderMap,
+        Query(query): Query<ReplayQuery>,
+    ) -> Result<Json<Vec<ReplayEntry>>, (StatusCode, String)> {
+        let log = replay_log_for(&state, &headers)?;
+        Ok(Json(log.recent(query.limit.unwrap_or(50), query.tenant.as_deref(), Instant::now())))
+    }
+
+    #[derive(Debug, serde::Serialize)]
+    pub struct StatsResponse {
+        // Per-tenant usage; omitted when tenancy is not configured
+        #[serde(skip_serializing_if = "Option::is_none")]
+        pub tenants: Option<BTreeMap<String, TenantUsage>>,
+        // Running computations and per-band queue depths
+        pub queue: QueueStats,
+        // Result buffers held by finished jobs, shared buffers counted once
+        pub job_results: ResultMemory,
+        // Peak allocations of recent /compute, /jobs and /verify/batch requests (counting-alloc)
+        pub request_memory: RequestMemorySummary,
+        // Workloads per shape-specialized kernel and per fallback kernel (fp32 and u8i8)
+        pub dispatch: crate::shapes::DispatchStats,
+    }
+
+    // GET /stats - Per-tenant usage (requests/bytes admitted and rejected), the compute queue,
+    // memory held by job results, per-request allocation peaks and kernel dispatch counts
+    async fn stats_handler(
+        State(state): State<Arc<AppState>>,
+        headers: HeaderMap,
+    ) -> Result<Json<StatsResponse>, (StatusCode, String)> {
+        require_debug_token(&state, &headers)?;
+        Ok(Json(StatsResponse {
+            tenants: state.tenants.as_ref().map(TenantTracker::stats),
+            queue: state.scheduler.stats(),
+            job_results: state.jobs.result_memory(),
+            request_memory: state.request_memory.summary(),
+            dispatch: crate::shapes::dispatch_stats(),
+        }))
+    }
+
+    // GET /metrics - Counters in the Prometheus text format
+    async fn metrics_handler(State(state): State<Arc<AppState>>, headers: HeaderMap) -> Result<Response, (StatusCode, String)> {
+        require_debug_token(&state, &headers)?;
+        let mut body = String::from(
+            "# HELP solver_parse_aborts_total Request bodies refused mid-parse by a matrix limit\n\
+             # TYPE solver_parse_aborts_total counter\n",
+        );
+        for limit in MatrixLimit::ALL {
+            body.push_str(&format!("solver_parse_aborts_total{{limit=\"{}\"}} {}\n", limit.as_str(), state.parse_aborts.get(limit)));
+        }
+        Ok(([("content-type", "text/plain; version=0.0.4")], body).into_response())
+    }
+
+    // POST /cache/clear - Drop the in-process packed-B caches so their memory is released
+    async fn cache_clear_handler(
+        State(state): State<Arc<AppState>>,
+        headers: HeaderMap,
+    ) -> Result<Json<serde_json::Value>, (StatusCode, String)> {
+        require_debug_token(&state, &headers)?;
+        Ok(Json(serde_json::json!({ "cleared": state.caches.clear() })))
+    }
+
+    // GET /dashboard - Status page polling /dashboard/data, /stats and /metrics
+    #[cfg(feature = "dashboard")]
+    async fn dashboard_handler(State(state): State<Arc<AppState>>, headers: HeaderMap) -> Result<Response, (StatusCode, String)> {
+        require_debug_token(&state, &headers)?;
+        Ok(([("content-type", "text/html; charset=utf-8")], crate::dashboard::PAGE).into_response())
+    }
+
+    // GET /dashboard/data - Uptime, computations per precision, recent latencies, pack cache
+    // hit rates and build info
+    #[cfg(feature = "dashboard")]
+    async fn dashboard_data_handler(
+        State(state): State<Arc<AppState>>,
+        headers: HeaderMap,
+    ) -> Result<Json<crate::dashboard::DashboardData>, (StatusCode, String)> {
+        require_debug_token(&state, &headers)?;
+        Ok(Json(state.dashboard.data()))
+    }
+
+    #[derive(Debug, serde::Serialize, serde::Deserialize)]
+    pub struct RecomputeResponse {
+        pub request_digest: String,
+        pub original_hash: String,
+        pub recomputed_hash: String,
+        pub reproduced: bool,
+    }
+
+    // POST /debug/replay/{digest}/recompute - Re-run a logged request and compare hashes.
+    // Only seed requests can be re-run since matrices are not retained.
+    async fn replay_recompute_handler(
+        State(state): State<Arc<AppState>>,
+        headers: HeaderMap,
+        Path(digest): Path<String>,
+    ) -> Result<Json<RecomputeResponse>, ApiError> {
+        let log = replay_log_for(&state, &headers)?;
+        let entry = log
+            .find(&digest, Instant::now())
+            .ok_or_else(|| (StatusCode::NOT_FOUND, format!("No replay entry for {}", digest)))?;
+        if entry.request.seed.is_none() {
+            return Err((
+                StatusCode::CONFLICT,
+                "Request was made with explicit matrices, which are not retained".to_string(),
+            )
+                .into());
+        }
+        let request = entry.request;
+        let req = ComputeRequest {
+            seed: request.seed,
+            seed_dims: request.seed_dims,
+            precision: request.precision,
+            workload_type: request.workload_type,
+            kernel: request.kernel,
+            result_columns: request.result_columns,
+            hash_mode: request.hash_mode,
+            hash_mantissa_bits: request.hash_mantissa_bits,
+            hash_quantum: request.hash_quantum,
+            tile_trace: request.tile_trace,
+            fp32_accumulation: request.fp32_accumulation,
+            fp32_summation: request.fp32_summation,
+            hash_input: request.hash_input,
+            accumulator: request.accumulator,
+            fp16_rounding: request.fp16_rounding,
+            fp16_rounding_seed: request.fp16_rounding_seed,
+            a_layout: request.a_layout,
+            b_layout: request.b_layout,
+            batch: request.batch,
+            redundancy: request.redundancy,
+            ..Default::default()
+        };
+        let (max_memory_bytes, caches) = (state.config.max_memory_bytes, state.caches.clone());
+        let _permit = state.scheduler.acquire(state.band_of(&req)).await;
+        let output = compute_blocking(move || process_request_deferred(req, max_memory_bytes, false, Some(caches))).await?;
+        Ok(Json(RecomputeResponse {
+            reproduced: output.result_hash == entry.result_hash,
+            request_digest: entry.request_digest,
+            original_hash: entry.result_hash,
+            recomputed_hash: output.result_hash,
+        }))
+    }
+
+    // Most claims one POST /verify/batch may carry
+    pub const MAX_BATCH_CLAIMS: usize = 4096;
+
+    #[derive(Debug, serde::Serialize, serde::Deserialize)]
+    pub struct VerifyBatchRequest {
+        pub claims: Vec<Claim>,
+        // Worker threads; defaults to 1, capped at the machine's parallelism
+        #[serde(default)]
+        pub jobs: Option<usize>,
+    }
+
+    // POST /verify/batch - Recompute seed claims; answers 200 with the per-claim report
+    // whether or not every claim passed
+    async fn verify_batch_handler(
+        State(state): State<Arc<AppState>>,
+        Json(req): Json<VerifyBatchRequest>,
+    ) -> Result<Json<BatchReport>, ApiError> {
+        if req.claims.len() > MAX_BATCH_CLAIMS {
+            return Err((StatusCode::PAYLOAD_TOO_LARGE, format!("{} claims, at most {} per batch", req.claims.len(), MAX_BATCH_CLAIMS)).into());
+        }
+        let parallelism = std::thread::available_parallelism().map_or(1, |n| n.get());
+        let jobs = req.jobs.unwrap_or(1).clamp(1, parallelism);
+        let flops = req
+            .claims
+            .iter()
+            .map(|claim| {
+                let dims = claim.dims.unwrap_or_default();
+                crate::scheduler::matmul_flops(dims.m, dims.k, dims.n)
+            })
+            .sum();
+        let options = ComputeOptions { max_memory_bytes: state.config.max_memory_bytes, caches: Some(state.caches.clone()), ..Default::default() };
+        let _permit = state.scheduler.acquire(state.config.scheduler.classify(flops)).await;
+        let report = compute_blocking(move || Ok(verify_batch::verify_batch(&req.claims, jobs, &options))).await?;
+        Ok(Json(report))
+    }
+
+    // Run a computation (seed generation, kernel, hashing) on tokio's blocking pool, charged
+    // to the current request, so the async workers stay free for /health and the rest. The
+    // caller holds a scheduler permit, which caps how many run at once. A panic answers 500
+    // with a JSON body carrying the panic message.
+    async fn compute_blocking<T: Send + 'static>(f: impl FnOnce() -> Result<T, ApiError> + Send + 'static) -> Result<T, ApiError> {
+        let memory = RequestMemory::current();
+        tokio::task::spawn_blocking(move || {
+            let _scope = memory.as_ref().map(RequestMemory::enter);
+            f()
+        })
+        .await
+        .unwrap_or_else(|e| Err(blocking_failure(e)))
+    }
+
+    fn blocking_failure(e: tokio::task::JoinError) -> ApiError {
+        let message = match e.try_into_panic() {
+            Ok(payload) => match payload.downcast::<String>() {
+                Ok(message) => *message,
+                Err(payload) => payload.downcast::<&'static str>().map_or_else(|_| "unknown panic".to_string(), |message| message.to_string()),
+            },
+            Err(e) => return (StatusCode::INTERNAL_SERVER_ERROR, format!("computation did not finish: {}", e)).into(),
+        };
+        ApiError {
+            status: StatusCode::INTERNAL_SERVER_ERROR,
+            message: "computation panicked".to_string(),
+            details: Some(serde_json::json!({ "panic": message })),
+        }
+    }
+
+    // Full request pipeline (input build, compute, timing breakdown), shared by the
+    // HTTP handler and the pull-based worker
+    pub fn process_request(req: ComputeRequest) -> Result<types::Output, ApiError> {
+        process_request_with_limit(req, None)
+    }
+
+    // Error response: the message as plain text, or as JSON {"error", "details"} when the
+    // failure carries structured details (shape violations name the conflicting fields)
+    #[derive(Debug)]
+    pub struct ApiError {
+        pub status: StatusCode,
+        pub message: String,
+        pub details: Option<serde_json::Value>,
+    }
+
+    impl ApiError {
+        /// The error a job records: the message, and the details as JSON when there are any
+        /// (a panic's message, the conflicting fields)
+        fn job_error(self) -> String {
+            match self.details {
+                Some(details) => format!("{}: {}", self.message, details),
+                None => self.message,
+            }
+        }
+    }
+
+    impl From<(StatusCode, String)> for ApiError {
+        fn from((status, message): (StatusCode, String)) -> Self {
+            Self { status, message, details: None }
+        }
+    }
+
+    // Compute failures; memory ceilings map to 413, disagreeing redundant runs (a fault on
+    // this machine, not in the request) to 500
+    impl From<SolverError> for ApiError {
+        fn from(e: SolverError) -> Self {
+            let status = match e {
+                SolverError::MemoryLimitExceeded(_) => StatusCode::PAYLOAD_TOO_LARGE,
+                SolverError::InconsistentComputation { .. } => StatusCode::INTERNAL_SERVER_ERROR,
+                _ => StatusCode::BAD_REQUEST,
+            };
+            let details = match &e {
+                SolverError::ShapeMismatch(violations) => Some(serde_json::json!({ "violations": violations })),
+                _ => None,
+            };
+            Self { status, message: e.to_string(), details }
+        }
+    }
+
+    impl IntoResponse for ApiError {
+        fn into_response(self) -> Response {
+            match self.details {
+                Some(details) => (self.status, Json(serde_json::json!({ "error": self.message, "details": details }))).into_response(),
+                None => (self.status, self.message).into_response(),
+            }
+        }
+    }
+
+    /// A request's workload: explicit matrices, or a seed matmul left to
+    /// `compute_seed_workload` so it can pack the seed without f32 matrices
+    enum ParsedWorkload {
+        Input(Box<types::Input>),
+        SeedMatmul {
+            seed: Vec<u8>,
+            dims: SeedDims,
+            precision: String,
+            metadata: Option<types::InputMetadata>,
+            hash_input: Option<String>,
+        },
+    }
+
+    // `process_request` under ApiConfig::max_memory_bytes
+    pub fn process_request_with_limit(
+        req: ComputeRequest,
+        max_memory_bytes: Option<u64>,
+    ) -> Result<types::Output, ApiError> {
+        process_request_deferred(req, max_memory_bytes, false, None)
+    }
+
+    // `process_request_with_limit`, leaving the result hash to the response writer
+    // (ComputeOptions::defer_result_hash) when `defer_result_hash` is set and the request
+    // does not verify the result, and packing B through `caches` (the process-wide ones
+    // when None)
+    fn process_request_deferred(
+        req: ComputeRequest,
+        max_memory_bytes: Option<u64>,
+        defer_result_hash: bool,
+        caches: Option<Arc<CacheContext>>,
+    ) -> Result<types::Output, ApiError> {
+        let mut req = req;
+        req.normalize_names();
+        check_required_capabilities(&req)?;
+        let options = ComputeOptions { caches, ..request_options(&req, max_memory_bytes, defer_result_hash)? };
+        let verify = req.verify.unwrap_or(false);
+        let clock = options.clock();
+        let parse_start = clock.now();
+        
+        let parsed = if let Some((seed, dims)) = request_seed(&req)? {
+            // Generate from seed (deterministic), refusing before generating the matrices
+            check_seed_budget(&req, dims, max_memory_bytes)?;
+            if req.workload_type.as_deref().unwrap_or("matmul") == "matmul" && req.a_layout.is_none() && req.b_layout.is_none() {
+                ParsedWorkload::SeedMatmul {
+                    seed,
+                    dims,
+                    precision: req.precision,
+                    metadata: req.metadata,
+                    hash_input: req.hash_input,
+                }
+            } else {
+                let (matrix_a, matrix_b) = crate::generate_matrices_from_seed(&seed, dims.m, dims.k, dims.k, dims.n);
+
+                ParsedWorkload::Input(Box::new(types::Input {
+                    matrix_a,
+                    matrix_b,
+                    precision: req.precision,
+                    workload_type: req.workload_type.or(Some("matmul".to_string())),
+                    metadata: req.metadata,
+                    hash_input: req.hash_input,
+                    a_layout: req.a_layout,
+                    b_layout: req.b_layout,
+                    alpha: None,
+                    beta: None,
+                    matrix_c: None,
+                    batch: req.batch,
+                    matrix_b_sparse: None,
+                }))
+            }
+        } else {
+            // Use provided matrices
+            check_matrices(&req)?;
+            // Quantizing reads only matrix_a, and a sparse B replaces matrix_b
+            let (matrix_a, matrix_b) = (req.matrix_a.unwrap_or_default(), req.matrix_b.unwrap_or_default());
             
-            types::Input {
-                matrix_a: crate::FlatMatrix { data: a_data, rows: rows_a, cols: cols_a },
-                matrix_b: crate::FlatMatrix { data: b_data, rows: rows_b, cols: cols_b },
+            ParsedWorkload::Input(Box::new(types::Input {
+                matrix_a,
+                matrix_b,
                 precision: req.precision,
                 workload_type: req.workload_type.or(Some("matmul".to_string())),
-                metadata: None,
-            }
+                metadata: req.metadata,
+                hash_input: req.hash_input,
+                a_layout: req.a_layout,
+                b_layout: req.b_layout,
+                alpha: None,
+                beta: None,
+                matrix_c: None,
+                batch: req.batch,
+                matrix_b_sparse: req.matrix_b_sparse,
+            }))
         };
         
-        let parse_time_ms = parse_start.elapsed().as_secs_f64() * 1000.0;
+        let parse_time_ms = clock.elapsed_since(parse_start).as_secs_f64() * 1000.0;
         
-        let mut output = match compute_workload(input) {
-            Ok(output) => output,
-            Err(e) => return Err((StatusCode::BAD_REQUEST, e)),
+        let mut output = match parsed {
+            // Names were normalized with the request, and the matrices stay here for verify
+            ParsedWorkload::Input(input) => {
+                let mut output = compute_workload_ref(&input, &options)?;
+                if verify {
+                    verify_output(&mut output, &input.matrix_a, &*input.dense_b()?, clock)?;
+                }
+                output
+            }
+            ParsedWorkload::SeedMatmul { seed, dims, precision, metadata, hash_input } => {
+                let (m, k, n) = (dims.m, dims.k, dims.n);
+                let mut output = crate::compute_seed_workload(&seed, (m, k, n), &precision, metadata, hash_input, &options)?;
+                if verify {
+                    let (matrix_a, matrix_b) = crate::generate_matrices_from_seed(&seed, m, k, k, n);
+                    verify_output(&mut output, &matrix_a, &matrix_b, clock)?;
+                }
+                output
+            }
         };
         
         // Add parse time
         output = add_timing_breakdown(output, Some(parse_time_ms), None);
         
         // Time serialization
-        let serialize_start = Instant::now();
+        let serialize_start = clock.now();
         let _ = serde_json::to_string(&output);
-        let serialize_time_ms = serialize_start.elapsed().as_secs_f64() * 1000.0;
+        let serialize_time_ms = clock.elapsed_since(serialize_start).as_secs_f64() * 1000.0;
         output = add_timing_breakdown(output, Some(parse_time_ms), Some(serialize_time_ms));
         
-        Ok(Json(output))
+        Ok(output)
+    }
+
+    // The ComputeOptions of a request, or why they conflict
+    fn request_options(req: &ComputeRequest, max_memory_bytes: Option<u64>, defer_result_hash: bool) -> Result<ComputeOptions, ApiError> {
+        let hash_rounding = HashRounding::from_mode(
+            req.hash_mode.as_deref().unwrap_or("exact"),
+            req.hash_mantissa_bits,
+            req.hash_quantum,
+        )
+        .map_err(|e| (StatusCode::BAD_REQUEST, e.to_string()))?;
+        let fp32_accumulation = match req.fp32_accumulation.as_deref() {
+            Some(name) => Fp32Accumulation::parse(name).map_err(|e| (StatusCode::BAD_REQUEST, e.to_string()))?,
+            None => Fp32Accumulation::default(),
+        };
+        let fp32_summation = match req.fp32_summation.as_deref() {
+            Some(name) => Fp32Summation::parse(name).map_err(|e| (StatusCode::BAD_REQUEST, e.to_string()))?,
+            None => Fp32Summation::default(),
+        };
+        let accumulator = match req.accumulator.as_deref() {
+            Some(name) => Accumulator::parse(name).map_err(|e| (StatusCode::BAD_REQUEST, e.to_string()))?,
+            None => Accumulator::default(),
+        };
+        let fp16_rounding = Fp16Rounding::from_mode(req.fp16_rounding.as_deref().unwrap_or("nearest_even"), req.fp16_rounding_seed)
+            .map_err(|e| (StatusCode::BAD_REQUEST, e.to_string()))?;
+        let redundancy = match req.redundancy.as_deref() {
+            Some(name) => Redundancy::parse(name).map_err(|e| (StatusCode::BAD_REQUEST, e.to_string()))?,
+            None => Redundancy::default(),
+        };
+        let tolerance = TolerancePolicy {
+            profile: match req.tolerance_profile.as_deref() {
+                Some(name) => ToleranceProfile::parse(name).map_err(|e| (StatusCode::BAD_REQUEST, e.to_string()))?,
+                None => ToleranceProfile::default(),
+            },
+            overrides: req.tolerances.clone().unwrap_or_default(),
+        };
+        tolerance.validate().map_err(|e| (StatusCode::BAD_REQUEST, e.to_string()))?;
+        match req.threads {
+            Some(0) => return Err((StatusCode::BAD_REQUEST, "threads must be at least 1".to_string()).into()),
+            Some(threads) if threads > 1 && !cfg!(feature = "parallel") => {
+                return Err((StatusCode::BAD_REQUEST, "threads above 1 need a build with the parallel feature".to_string()).into())
+            }
+            _ => {}
+        }
+        // Capped like verify_batch's jobs, so a request cannot ask for more threads than cores
+        let parallelism = std::thread::available_parallelism().map_or(1, |n| n.get());
+        let verify = req.verify.unwrap_or(false);
+        if verify && (req.result_columns.is_some() || req.workload_type.as_deref() == Some("quantize")) {
+            return Err((StatusCode::BAD_REQUEST, "verify applies to full matmul results, not result_columns or quantize".to_string()).into());
+        }
+        let options = ComputeOptions {
+            kernel: req.kernel.clone(),
+            result_columns: req.result_columns.clone(),
+            hash_rounding,
+            tile_trace: req.tile_trace.unwrap_or(false),
+            fp32_accumulation,
+            fp32_summation,
+            accumulator,
+            fp16_rounding,
+            redundancy,
+            max_memory_bytes,
+            seed_pipeline: req.seed_pipeline.unwrap_or(false),
+            exact_names: req.exact_names.unwrap_or(false),
+            error_metrics: req.error_metrics.unwrap_or(false),
+            aux_result_matrices: req.aux_result_matrices.unwrap_or(false),
+            tolerance,
+            defer_result_hash: defer_result_hash && !verify,
+            threads: req.threads.map(|threads| threads.min(parallelism)),
+            ..Default::default()
+        };
+        Ok(options)
+    }
+
+    // A seed request's seed and seed_dims, refused before anything is generated; None for a
+    // matrix request
+    fn request_seed(req: &ComputeRequest) -> Result<Option<(Vec<u8>, SeedDims)>, ApiError> {
+        let Some(seed_hex) = &req.seed else {
+            return Ok(None);
+        };
+        let dims = req.seed_dims.unwrap_or_default();
+        let too_large = |rows: usize, cols: usize| rows.checked_mul(cols).is_none_or(|n| n > MAX_SEED_ELEMENTS);
+        if dims.m == 0 || dims.k == 0 || dims.n == 0 || too_large(dims.m, dims.k) || too_large(dims.k, dims.n) {
+            return Err((StatusCode::BAD_REQUEST, format!("Invalid seed_dims {:?}", dims)).into());
+        }
+        let seed = hex::decode(seed_hex).map_err(|e| (StatusCode::BAD_REQUEST, format!("Invalid hex seed: {}", e)))?;
+        Ok(Some((seed, dims)))
+    }
+
+    // A seed workload's matrices must fit the memory ceiling before they are generated
+    fn check_seed_budget(req: &ComputeRequest, dims: SeedDims, max_memory_bytes: Option<u64>) -> Result<(), ApiError> {
+        if let Some(limit) = max_memory_bytes {
+            crate::memory::check_budget(&req.precision, (dims.m, dims.k), (dims.k, dims.n), limit)
+                .map_err(|e| (StatusCode::PAYLOAD_TOO_LARGE, e.to_string()))?;
+        }
+        Ok(())
+    }
+
+    // A matrix request needs matrix_a, and matrix_b unless it quantizes or has a sparse B
+    fn check_matrices(req: &ComputeRequest) -> Result<(), ApiError> {
+        if req.matrix_a.is_none() {
+            return Err((StatusCode::BAD_REQUEST, "matrix_a is required when not using seed".to_string()).into());
+        }
+        if req.matrix_b.is_none() && req.workload_type.as_deref() != Some("quantize") && req.matrix_b_sparse.is_none() {
+            return Err((StatusCode::BAD_REQUEST, "matrix_b or matrix_b_sparse is required when not using seed".to_string()).into());
+        }
+        Ok(())
+    }
+
+    // Recompute `output` from its matrices with the kernel and settings its metadata records
+    fn verify_output(output: &mut types::Output, matrix_a: &FlatMatrix, matrix_b: &FlatMatrix, clock: &dyn crate::Clock) -> Result<(), ApiError> {
+        let (verified, elapsed) =
+            crate::clock::time(clock, || crate::verify_with_metadata(matrix_a, matrix_b, &output.metadata, &output.result_hash));
+        output.verified = Some(verified?);
+        output.metrics.verify_time_ms = Some(elapsed.as_secs_f64() * 1000.0);
+        Ok(())
     }
 
     // GET /health - Health check endpoint
@@ -105,22 +1661,1331 @@ pub mod api {
         "OK"
     }
 
-    pub async fn run_api_server(port: u16) -> Result<(), Box<dyn std::error::Error>> {
-        let state = Arc::new(AppState {});
+    // GET /capabilities - What this build computes and the request size limits it applies
+    async fn capabilities_handler(State(state): State<Arc<AppState>>) -> Json<Capabilities> {
+        let limits = state.config.matrix_limits;
+        Json(Capabilities::current(Some(MaxDims {
+            max_rows: limits.max_rows,
+            max_cols: limits.max_cols,
+            max_elements: limits.max_elements,
+            max_seed_elements: MAX_SEED_ELEMENTS,
+        })))
+    }
 
-        let app = Router::new()
+    // Build the router; /health, /capabilities and /debug are exempt from rate limiting and API tokens, and
+    // polling a job needs a token but is not rate limited
+    pub fn router(state: Arc<AppState>) -> Router {
+        let body_limit = DefaultBodyLimit::max(state.config.max_body_bytes.unwrap_or(DEFAULT_MAX_BODY_BYTES));
+        let limited = Router::new()
             .route("/compute", post(compute_handler))
-            .route("/health", axum::routing::get(health_handler))
+            .route("/validate", post(validate_handler))
+            .route("/jobs", post(submit_job_handler))
+            .route("/verify/batch", post(verify_batch_handler))
+            .layer(body_limit)
+            .route_layer(middleware::from_fn_with_state(state.clone(), request_memory_middleware))
+            .route_layer(middleware::from_fn_with_state(state.clone(), rate_limit_middleware))
+            .route_layer(middleware::from_fn_with_state(state.clone(), auth_middleware));
+        let polling = Router::new()
+            .route("/jobs/:id", get(job_handler))
+            .route("/jobs/:id/result", get(job_result_handler))
+            .route_layer(middleware::from_fn_with_state(state.clone(), auth_middleware));
+
+        let debug = Router::new()
+            .route("/stats", get(stats_handler))
+            .route("/metrics", get(metrics_handler))
+            .route("/cache/clear", post(cache_clear_handler))
+            .route("/debug/replay", get(replay_list_handler))
+            .route("/debug/replay/:digest/recompute", post(replay_recompute_handler));
+        #[cfg(feature = "dashboard")]
+        let debug = debug
+            .route("/dashboard", get(dashboard_handler))
+            .route("/dashboard/data", get(dashboard_data_handler));
+
+        Router::new()
+            .merge(limited)
+            .merge(polling)
+            .merge(debug)
+            .route("/health", get(health_handler))
+            .route("/capabilities", get(capabilities_handler))
             .layer(CorsLayer::permissive())
-            .with_state(state);
+            .with_state(state)
+    }
+
+    pub async fn run_api_server(port: u16) -> Result<(), Box<dyn std::error::Error>> {
+        run_api_server_with_config(port, ApiConfig::from_env()).await
+    }
+
+    pub async fn run_api_server_with_config(port: u16, config: ApiConfig) -> Result<(), Box<dyn std::error::Error>> {
+        let state = Arc::new(AppState::new(config));
+
+        // Periodically drop idle rate-limit buckets
+        if state.rate_limiter.is_some() {
+            let state = state.clone();
+            tokio::spawn(async move {
+                let mut interval = tokio::time::interval(Duration::from_secs(60));
+                loop {
+                    interval.tick().await;
+                    if let Some(limiter) = state.rate_limiter.as_ref() {
+                        limiter.cleanup(limiter.clock().now());
+                    }
+                }
+            });
+        }
+
+        let state_has_replay = state.replay_log.is_some();
+        let app = router(state);
 
         let listener = tokio::net::TcpListener::bind(format!("0.0.0.0:{}", port)).await?;
         println!("API server listening on port {}", port);
         println!("Endpoints:");
         println!("  POST /compute - Submit matrix computation");
+        println!("  POST /jobs    - Submit a background computation");
+        println!("  POST /verify/batch - Recompute a list of seed claims");
+        println!("  GET  /jobs/:id[?inline=true]                     - Job status, hash and metadata");
+        println!("  GET  /jobs/:id/result?offset_rows=0&limit_rows=256 - Page of a job's result rows");
         println!("  GET  /health  - Health check");
-        axum::serve(listener, app).await?;
+        println!("  GET  /capabilities - Precisions, hash modes, kernels and limits this build supports");
+        if state_has_replay {
+            println!("  GET  /debug/replay                    - Recent requests (Bearer DEBUG_TOKEN)");
+            println!("  POST /debug/replay/:digest/recompute  - Re-run a logged seed request");
+        }
+        println!("  GET  /stats   - Per-tenant usage and queue depths (Bearer DEBUG_TOKEN)");
+        println!("  GET  /metrics - Prometheus counters (Bearer DEBUG_TOKEN)");
+        println!("  POST /cache/clear - Release the packed-B caches (Bearer DEBUG_TOKEN)");
+        if cfg!(feature = "dashboard") {
+            println!("  GET  /dashboard - Status page (Bearer DEBUG_TOKEN)");
+        }
+        axum::serve(listener, app.into_make_service_with_connect_info::<SocketAddr>()).await?;
         Ok(())
     }
-}
 
+    #[cfg(test)]
+    mod tests {
+        use super::*;
+        use crate::compute_workload_with_options;
+        use axum::body::Body;
+        use tower::ServiceExt;
+
+        fn compute_request(token: &str) -> Request {
+            Request::builder()
+                .method("POST")
+                .uri("/compute")
+                .header("content-type", "application/json")
+                .header("authorization", format!("Bearer {}", token))
+                .body(Body::from(r#"{"matrix_a": [[1.0, 2.0]], "matrix_b": [[3.0], [4.0]], "precision": "fp32"}"#))
+                .unwrap()
+        }
+
+        #[tokio::test]
+        async fn test_rate_limit_returns_429_and_recovers() {
+            let limit = RateLimitConfig { requests_per_window: Some(2), window: Duration::from_millis(400), ..Default::default() };
+            let config = ApiConfig { rate_limit: Some(limit.clone()), ..Default::default() };
+            // The limiter refills on its own clock, so the test moves time instead of sleeping
+            let clock = crate::MockClock::new();
+            let state = AppState::new(config);
+            let state = AppState { rate_limiter: Some(RateLimiter::with_clock(limit, Arc::new(clock.clone()))), ..state };
+            let app = router(Arc::new(state));
+
+            let first = app.clone().oneshot(compute_request("alice")).await.unwrap();
+            assert_eq!(first.status(), StatusCode::OK);
+            assert_eq!(first.headers()["x-ratelimit-limit"], "2");
+            assert_eq!(first.headers()["x-ratelimit-remaining"], "1");
+
+            let second = app.clone().oneshot(compute_request("alice")).await.unwrap();
+            assert_eq!(second.status(), StatusCode::OK);
+            assert_eq!(second.headers()["x-ratelimit-remaining"], "0");
+
+            let limited = app.clone().oneshot(compute_request("alice")).await.unwrap();
+            assert_eq!(limited.status(), StatusCode::TOO_MANY_REQUESTS);
+            assert_eq!(limited.headers()["retry-after"], "1");
+
+            // A different token has its own bucket
+            let other = app.clone().oneshot(compute_request("bob")).await.unwrap();
+            assert_eq!(other.status(), StatusCode::OK);
+
+            // /health is never limited
+            let health = Request::builder().uri("/health").body(Body::empty()).unwrap();
+            assert_eq!(app.clone().oneshot(health).await.unwrap().status(), StatusCode::OK);
+
+            clock.advance(Duration::from_millis(100));
+            let still_limited = app.clone().oneshot(compute_request("alice")).await.unwrap();
+            assert_eq!(still_limited.status(), StatusCode::TOO_MANY_REQUESTS);
+            clock.advance(Duration::from_millis(150));
+            let recovered = app.clone().oneshot(compute_request("alice")).await.unwrap();
+            assert_eq!(recovered.status(), StatusCode::OK);
+        }
+
+        #[tokio::test]
+        async fn test_memory_limit_returns_413() {
+            let config = ApiConfig { max_memory_bytes: Some(64 << 10), ..Default::default() };
+            let app = router(Arc::new(AppState::new(config)));
+
+            // The tiny matrix request fits
+            assert_eq!(app.clone().oneshot(compute_request("alice")).await.unwrap().status(), StatusCode::OK);
+
+            let seed = Request::builder()
+                .method("POST")
+                .uri("/compute")
+                .header("content-type", "application/json")
+                .body(Body::from(r#"{"seed": "0102", "seed_dims": {"m": 16, "k": 4096, "n": 16}, "precision": "fp32"}"#))
+                .unwrap();
+            let response = app.clone().oneshot(seed).await.unwrap();
+            assert_eq!(response.status(), StatusCode::PAYLOAD_TOO_LARGE);
+            let body = axum::body::to_bytes(response.into_body(), usize::MAX).await.unwrap();
+            assert!(String::from_utf8_lossy(&body).contains("needs an estimated"));
+        }
+
+        #[tokio::test]
+        async fn test_matrix_limits_abort_the_parse() {
+            let config = ApiConfig {
+                matrix_limits: MatrixLimits { max_rows: 4096, max_cols: 4096, max_elements: 1 << 16 },
+                max_body_bytes: Some(64 << 20),
+                debug_token: Some("debug-secret".to_string()),
+                ..Default::default()
+            };
+            let app = router(Arc::new(AppState::new(config)));
+            let post = |a: &str, b: &str| {
+                let body = format!(r#"{{"matrix_a": {}, "matrix_b": {}, "precision": "fp32"}}"#, a, b);
+                Request::builder().method("POST").uri("/compute").header("content-type", "application/json").body(Body::from(body)).unwrap()
+            };
+            let matrix = |rows: usize, cols: usize| format!("[{}]", vec![format!("[{}]", vec!["1"; cols].join(",")); rows].join(","));
+
+            // A million one-element rows stop at row 4096, with only those rows stored
+            let many_rows = post(&matrix(1_000_000, 1), "[[1]]");
+            let guard = crate::memory::MemoryGuard::start(u64::MAX, 0);
+            let response = app.clone().oneshot(many_rows).await.unwrap();
+            if let Some(peak) = guard.peak_allocated() {
+                assert!(peak < 1 << 20, "parsing allocated {} bytes", peak);
+            }
+            assert_eq!(response.status(), StatusCode::PAYLOAD_TOO_LARGE);
+            let body = axum::body::to_bytes(response.into_body(), usize::MAX).await.unwrap();
+            let json: serde_json::Value = serde_json::from_slice(&body).unwrap();
+            assert_eq!(json["details"], serde_json::json!({"matrix": "matrix_a", "limit": "max_rows", "max": 4096, "row": 4096}));
+            assert_eq!(json["error"], "matrix_a exceeds max_rows = 4096 at row 4096");
+
+            for (a, b, limit) in [
+                ("[[1]]".to_string(), matrix(1, 200_000), "max_cols"),
+                (matrix(32, 4096), "[[1]]".to_string(), "max_elements"),
+            ] {
+                let response = app.clone().oneshot(post(&a, &b)).await.unwrap();
+                assert_eq!(response.status(), StatusCode::PAYLOAD_TOO_LARGE);
+                let body = axum::body::to_bytes(response.into_body(), usize::MAX).await.unwrap();
+                assert!(String::from_utf8_lossy(&body).contains(limit), "{}", limit);
+            }
+
+            // Large inputs right at the caps still compute
+            let response = app.clone().oneshot(post(&matrix(16, 4096), &matrix(4096, 16))).await.unwrap();
+            assert_eq!(response.status(), StatusCode::OK);
+
+            let metrics = Request::builder().uri("/metrics").header("authorization", "Bearer debug-secret").body(Body::empty()).unwrap();
+            let response = app.clone().oneshot(metrics).await.unwrap();
+            assert_eq!(response.status(), StatusCode::OK);
+            let text = String::from_utf8(axum::body::to_bytes(response.into_body(), usize::MAX).await.unwrap().to_vec()).unwrap();
+            for limit in ["max_rows", "max_cols", "max_elements"] {
+                assert!(text.contains(&format!("solver_parse_aborts_total{{limit=\"{}\"}} 1\n", limit)), "{}", text);
+            }
+            let anonymous = Request::builder().uri("/metrics").body(Body::empty()).unwrap();
+            assert_eq!(app.oneshot(anonymous).await.unwrap().status(), StatusCode::UNAUTHORIZED);
+        }
+
+        #[test]
+        fn test_client_key_forwarded_for() {
+            let mut headers = HeaderMap::new();
+            headers.insert("x-forwarded-for", HeaderValue::from_static("203.0.113.7, 10.0.0.1"));
+            let peer: SocketAddr = "10.0.0.1:5000".parse().unwrap();
+            assert_eq!(client_key(&headers, Some(peer), true), "ip:203.0.113.7");
+            assert_eq!(client_key(&headers, Some(peer), false), "ip:10.0.0.1");
+            headers.insert("authorization", HeaderValue::from_static("Bearer secret"));
+            assert_eq!(client_key(&headers, Some(peer), true), "token:secret");
+        }
+
+        #[test]
+        fn test_request_metadata_overrides_build_info() {
+            let mut req: ComputeRequest = serde_json::from_str(
+                r#"{"matrix_a": [[1.0, 2.0]], "matrix_b": [[3.0], [4.0]], "precision": "fp32"}"#,
+            )
+            .unwrap();
+            let auto = process_request(req.clone()).unwrap().metadata;
+            assert_eq!(auto.compiler_flags.as_deref(), Some(crate::build_compiler_flags()));
+            assert_eq!(auto.libraries, Some(crate::build_libraries()));
+
+            req.metadata = Some(types::InputMetadata {
+                compiler_flags: None,
+                libraries: Some(vec!["custom-blas".to_string()]),
+                cache_enabled: None,
+            });
+            let out = process_request(req).unwrap().metadata;
+            assert_eq!(out.compiler_flags.as_deref(), Some(crate::build_compiler_flags()));
+            assert_eq!(out.libraries, Some(vec!["custom-blas".to_string()]));
+        }
+
+        #[tokio::test]
+        async fn test_replay_log_records_and_recomputes() {
+            let config = ApiConfig {
+                replay_log: Some(ReplayLogConfig { capacity: 4, max_age: Duration::from_secs(60) }),
+                debug_token: Some("debug-secret".to_string()),
+                ..Default::default()
+            };
+            let app = router(Arc::new(AppState::new(config)));
+            let call = |method: &str, uri: &str, token: Option<&str>, body: &'static str| {
+                let app = app.clone();
+                let mut builder = Request::builder().method(method).uri(uri);
+                if let Some(token) = token {
+                    builder = builder.header("authorization", format!("Bearer {}", token));
+                }
+                let request = builder.body(Body::from(body)).unwrap();
+                async move {
+                    let response = app.oneshot(request).await.unwrap();
+                    let status = response.status();
+                    let bytes = axum::body::to_bytes(response.into_body(), usize::MAX).await.unwrap();
+                    (status, serde_json::from_slice::<serde_json::Value>(&bytes).unwrap_or_default())
+                }
+            };
+
+            let (status, output) = call("POST", "/compute", None, r#"{"seed": "7265706c6179", "precision": "u8i8"}"#).await;
+            assert_eq!(status, StatusCode::OK);
+            let (status, _) = call("POST", "/compute", None, r#"{"matrix_a": [[1.0, 2.0]], "matrix_b": [[3.0], [4.0]], "precision": "fp32"}"#).await;
+            assert_eq!(status, StatusCode::OK);
+
+            // Closed without the debug token
+            assert_eq!(call("GET", "/debug/replay", None, "").await.0, StatusCode::UNAUTHORIZED);
+            assert_eq!(call("GET", "/debug/replay", Some("wrong"), "").await.0, StatusCode::UNAUTHORIZED);
+
+            let (status, entries) = call("GET", "/debug/replay?limit=10", Some("debug-secret"), "").await;
+            assert_eq!(status, StatusCode::OK);
+            let entries = entries.as_array().unwrap();
+            assert_eq!(entries.len(), 2);
+            // Newest first; matrices are summarized by digest only
+            assert!(entries[0]["request"]["matrix_a_digest"].is_string());
+            assert!(entries[0].get("matrix_a").is_none());
+            assert_eq!(entries[1]["result_hash"], output["result_hash"]);
+
+            let seed_digest = entries[1]["request_digest"].as_str().unwrap().to_string();
+            let (status, recompute) = call("POST", &format!("/debug/replay/{}/recompute", seed_digest), Some("debug-secret"), "").await;
+            assert_eq!(status, StatusCode::OK);
+            assert_eq!(recompute["reproduced"], true);
+            assert_eq!(recompute["recomputed_hash"], output["result_hash"]);
+
+            let matrix_digest = entries[0]["request_digest"].as_str().unwrap().to_string();
+            assert_eq!(call("POST", &format!("/debug/replay/{}/recompute", matrix_digest), Some("debug-secret"), "").await.0, StatusCode::CONFLICT);
+            assert_eq!(call("POST", "/debug/replay/unknown/recompute", Some("debug-secret"), "").await.0, StatusCode::NOT_FOUND);
+        }
+
+        #[tokio::test]
+        async fn test_tenants_have_independent_quotas_and_logs() {
+            let quota = |max_requests, max_bytes| TenantQuota {
+                max_requests: Some(max_requests),
+                max_bytes: Some(max_bytes),
+                window: Duration::from_secs(60),
+            };
+            let config = ApiConfig {
+                tenants: Some(TenantConfig {
+                    tokens: HashMap::from([
+                        ("tok-a".to_string(), "team-a".to_string()),
+                        ("tok-b".to_string(), "team-b".to_string()),
+                    ]),
+                    quotas: HashMap::from([
+                        ("team-a".to_string(), quota(1, 10_000)),
+                        ("team-b".to_string(), quota(10, 100)),
+                    ]),
+                    default_quota: None,
+                    trust_tenant_header: false,
+                }),
+                replay_log: Some(ReplayLogConfig::default()),
+                debug_token: Some("debug".to_string()),
+                ..Default::default()
+            };
+            let app = router(Arc::new(AppState::new(config)));
+            let call = |token: &str, uri: &str, body: &'static str| {
+                let app = app.clone();
+                let method = if uri == "/compute" { "POST" } else { "GET" };
+                let request = Request::builder()
+                    .method(method)
+                    .uri(uri)
+                    .header("authorization", format!("Bearer {}", token))
+                    // Ignored: a mapped token decides the tenant
+                    .header("x-tenant", "team-b")
+                    .body(Body::from(body))
+                    .unwrap();
+                async move {
+                    let response = app.oneshot(request).await.unwrap();
+                    let status = response.status();
+                    let bytes = axum::body::to_bytes(response.into_body(), usize::MAX).await.unwrap();
+                    (status, serde_json::from_slice::<serde_json::Value>(&bytes).unwrap_or_default())
+                }
+            };
+            let small = r#"{"matrix_a": [[1.0, 2.0]], "matrix_b": [[3.0], [4.0]], "precision": "fp32"}"#;
+
+            // team-a: one request per window
+            assert_eq!(call("tok-a", "/compute", small).await.0, StatusCode::OK);
+            assert_eq!(call("tok-a", "/compute", small).await.0, StatusCode::TOO_MANY_REQUESTS);
+            // team-b is unaffected, until its 100-byte budget runs out
+            assert_eq!(call("tok-b", "/compute", small).await.0, StatusCode::OK);
+            assert_eq!(call("tok-b", "/compute", small).await.0, StatusCode::PAYLOAD_TOO_LARGE);
+
+            let (_, entries) = call("debug", "/debug/replay?tenant=team-a", "").await;
+            let entries = entries.as_array().unwrap();
+            assert_eq!(entries.len(), 1);
+            assert_eq!(entries[0]["tenant"], "team-a");
+            assert_eq!(call("debug", "/debug/replay", "").await.1.as_array().unwrap().len(), 2);
+
+            assert_eq!(call("tok-a", "/stats", "").await.0, StatusCode::UNAUTHORIZED);
+            let (status, stats) = call("debug", "/stats", "").await;
+            assert_eq!(status, StatusCode::OK);
+            assert_eq!(stats["tenants"]["team-a"]["requests"], 1);
+            assert_eq!(stats["tenants"]["team-a"]["rejected_requests"], 1);
+            assert_eq!(stats["tenants"]["team-b"]["requests"], 1);
+            assert_eq!(stats["tenants"]["team-b"]["rejected_bytes"], 1);
+            // Both served requests were tiny
+            assert_eq!(stats["queue"]["bands"]["high"]["served"], 2);
+            assert_eq!(stats["queue"]["bands"]["low"]["queued"], 0);
+            assert_eq!(stats["job_results"], serde_json::json!({"results": 0, "buffers": 0, "bytes": 0}));
+        }
+
+        #[tokio::test]
+        async fn test_x_tenant_does_not_reset_quota() {
+            let tenants = |trust_tenant_header| TenantConfig {
+                tokens: HashMap::from([("tok-a".to_string(), "team-a".to_string())]),
+                default_quota: Some(TenantQuota { max_requests: Some(1), max_bytes: None, window: Duration::from_secs(60) }),
+                trust_tenant_header,
+                ..Default::default()
+            };
+            let small = r#"{"matrix_a": [[1.0, 2.0]], "matrix_b": [[3.0], [4.0]], "precision": "fp32"}"#;
+            let call = |app: Router, token: &'static str, tenant: String| async move {
+                let request = Request::builder()
+                    .method("POST")
+                    .uri("/compute")
+                    .header("authorization", format!("Bearer {}", token))
+                    .header("x-tenant", tenant)
+                    .body(Body::from(small))
+                    .unwrap();
+                app.oneshot(request).await.unwrap().status()
+            };
+
+            // Unmapped callers all share the default tenant's window, whatever they send
+            let app = router(Arc::new(AppState::new(ApiConfig { tenants: Some(tenants(false)), ..Default::default() })));
+            assert_eq!(call(app.clone(), "unknown", "rotate-0".to_string()).await, StatusCode::OK);
+            for i in 1..4 {
+                assert_eq!(call(app.clone(), "unknown", format!("rotate-{}", i)).await, StatusCode::TOO_MANY_REQUESTS);
+            }
+            // ... and cannot spend a mapped tenant's quota by naming it
+            assert_eq!(call(app.clone(), "unknown", "team-a".to_string()).await, StatusCode::TOO_MANY_REQUESTS);
+            assert_eq!(call(app.clone(), "tok-a", "other".to_string()).await, StatusCode::OK);
+
+            // Behind a trusted proxy the header names the tenant
+            let app = router(Arc::new(AppState::new(ApiConfig { tenants: Some(tenants(true)), ..Default::default() })));
+            assert_eq!(call(app.clone(), "unknown", "proxied-a".to_string()).await, StatusCode::OK);
+            assert_eq!(call(app.clone(), "unknown", "proxied-b".to_string()).await, StatusCode::OK);
+        }
+
+        #[tokio::test]
+        async fn test_cache_clear_and_cache_disabled_requests() {
+            let config = ApiConfig { debug_token: Some("debug-secret".to_string()), ..Default::default() };
+            let app = router(Arc::new(AppState::new(config)));
+            let call = |uri: &str, token: Option<&str>, body: String| {
+                let app = app.clone();
+                let mut builder = Request::builder().method("POST").uri(uri).header("content-type", "application/json");
+                if let Some(token) = token {
+                    builder = builder.header("authorization", format!("Bearer {}", token));
+                }
+                let request = builder.body(Body::from(body)).unwrap();
+                async move {
+                    let response = app.oneshot(request).await.unwrap();
+                    let status = response.status();
+                    let bytes = axum::body::to_bytes(response.into_body(), usize::MAX).await.unwrap();
+                    (status, serde_json::from_slice::<serde_json::Value>(&bytes).unwrap_or_default())
+                }
+            };
+            let seed = |metadata: &str| {
+                format!(r#"{{"seed": "636c656172", "seed_dims": {{"m": 16, "k": 256, "n": 16}}, "precision": "int8"{}}}"#, metadata)
+            };
+
+            let (status, cached) = call("/compute", None, seed("")).await;
+            assert_eq!(status, StatusCode::OK);
+            assert_eq!(cached["metadata"]["b_cache_used"], true);
+            let metadata = r#", "metadata": {"compiler_flags": null, "libraries": null, "cache_enabled": false}"#;
+            let (status, uncached) = call("/compute", None, seed(metadata)).await;
+            assert_eq!(status, StatusCode::OK);
+            assert_eq!(uncached["metadata"]["b_cache_used"], false);
+            assert_eq!(uncached["result_hash"], cached["result_hash"]);
+            assert!(uncached["metrics"].get("pack_cache_hit").is_none() && uncached["metrics"].get("cache_stats").is_none());
+
+            assert_eq!(call("/cache/clear", None, String::new()).await.0, StatusCode::UNAUTHORIZED);
+            assert_eq!(call("/cache/clear", Some("wrong"), String::new()).await.0, StatusCode::UNAUTHORIZED);
+            let (status, cleared) = call("/cache/clear", Some("debug-secret"), String::new()).await;
+            assert_eq!(status, StatusCode::OK);
+            // The server's own caches held only the cached request's B
+            assert_eq!(cleared["cleared"], 1);
+            assert_eq!(call("/cache/clear", Some("debug-secret"), String::new()).await.1["cleared"], 0);
+        }
+
+        #[cfg(feature = "dashboard")]
+        #[tokio::test]
+        async fn test_dashboard_page_and_data() {
+            let config = ApiConfig { debug_token: Some("debug-secret".to_string()), ..Default::default() };
+            let app = router(Arc::new(AppState::new(config)));
+            let call = |method: &str, uri: &str, token: Option<&str>, body: &'static str| {
+                let app = app.clone();
+                let mut builder = Request::builder().method(method).uri(uri);
+                if let Some(token) = token {
+                    builder = builder.header("authorization", format!("Bearer {}", token));
+                }
+                let request = builder.body(Body::from(body)).unwrap();
+                async move { app.oneshot(request).await.unwrap() }
+            };
+            let json = |response: Response| async move {
+                let bytes = axum::body::to_bytes(response.into_body(), usize::MAX).await.unwrap();
+                serde_json::from_slice::<serde_json::Value>(&bytes).unwrap()
+            };
+
+            let seed = r#"{"seed": "64617368", "seed_dims": {"m": 16, "k": 256, "n": 16}, "precision": "int8"}"#;
+            let small = r#"{"matrix_a": [[1.0, 2.0]], "matrix_b": [[3.0], [4.0]], "precision": "fp32"}"#;
+            for body in [seed, small, small] {
+                assert_eq!(call("POST", "/compute", None, body).await.status(), StatusCode::OK);
+            }
+
+            for uri in ["/dashboard", "/dashboard/data"] {
+                assert_eq!(call("GET", uri, None, "").await.status(), StatusCode::UNAUTHORIZED);
+                assert_eq!(call("GET", uri, Some("wrong"), "").await.status(), StatusCode::UNAUTHORIZED);
+            }
+            let page = call("GET", "/dashboard", Some("debug-secret"), "").await;
+            assert_eq!(page.status(), StatusCode::OK);
+            assert_eq!(page.headers()["content-type"], "text/html; charset=utf-8");
+            let html = axum::body::to_bytes(page.into_body(), usize::MAX).await.unwrap();
+            assert!(String::from_utf8_lossy(&html).contains("/dashboard/data"));
+
+            let response = call("GET", "/dashboard/data", Some("debug-secret"), "").await;
+            assert_eq!(response.status(), StatusCode::OK);
+            let data = json(response).await;
+            assert!(data["uptime_secs"].as_f64().unwrap() > 0.0);
+            assert_eq!(data["computations"], 3);
+            assert_eq!(data["by_precision"], serde_json::json!({"fp32": 2, "int8": 1}));
+            let recent = data["recent"].as_array().unwrap();
+            assert_eq!(recent.iter().map(|c| c["precision"].as_str().unwrap()).collect::<Vec<_>>(), ["int8", "fp32", "fp32"]);
+            assert!(recent.iter().all(|c| c["latency_ms"].is_f64() && c["kernel"].is_string()));
+            assert!(data["latency_p50_ms"].as_f64().unwrap() <= data["latency_p90_ms"].as_f64().unwrap());
+            // The seed request ran the packing int8 kernel; the fp32 ones do not pack
+            assert_eq!(data["pack_cache"]["int8"]["hits"].as_u64().unwrap() + data["pack_cache"]["int8"]["misses"].as_u64().unwrap(), 1);
+            assert!(data["pack_cache"].get("fp32").is_none());
+            assert_eq!(data["build"]["solver_version"], env!("CARGO_PKG_VERSION"));
+            assert!(data["build"]["libraries"].is_array() && data["build"]["compiler_flags"].is_string());
+        }
+
+        #[tokio::test]
+        async fn test_strict_numbers_through_api() {
+            let app = router(Arc::new(AppState::new(ApiConfig::default())));
+            let send = |body: &'static str| {
+                let app = app.clone();
+                async move {
+                    let response = app
+                        .oneshot(Request::builder().method("POST").uri("/compute").body(Body::from(body)).unwrap())
+                        .await
+                        .unwrap();
+                    let status = response.status();
+                    let bytes = axum::body::to_bytes(response.into_body(), usize::MAX).await.unwrap();
+                    (status, String::from_utf8(bytes.to_vec()).unwrap())
+                }
+            };
+
+            let (status, body) =
+                send(r#"{"matrix_a": [[1e40, 2.0]], "matrix_b": [[3.0], [4.0]], "precision": "fp32"}"#).await;
+            assert_eq!(status, StatusCode::OK);
+            let output: serde_json::Value = serde_json::from_str(&body).unwrap();
+            assert_eq!(output["metadata"]["lossy_parse_count"], 1);
+            assert_eq!(output["warnings"][0]["code"], "lossy_parse");
+            assert_eq!(output["warnings"][0]["context"]["count"], 1);
+
+            let (status, body) = send(
+                r#"{"matrix_a": [[12345678901234, 2]], "matrix_b": [[3], [4]], "precision": "u8i8", "strict_numbers": true}"#,
+            )
+            .await;
+            assert_eq!(status, StatusCode::BAD_REQUEST);
+            assert!(body.contains("matrix_a[0][0] = 12345678901234"), "{}", body);
+
+            let (status, _) = send(r#"{"matrix_a": [[1.0], [1.0, 2.0]], "matrix_b": [[3.0]], "precision": "fp32"}"#).await;
+            assert_eq!(status, StatusCode::UNPROCESSABLE_ENTITY);
+        }
+
+        #[tokio::test]
+        async fn test_messy_names_through_api() {
+            let app = router(Arc::new(AppState::new(ApiConfig::default())));
+            let send = |body: serde_json::Value| {
+                let app = app.clone();
+                async move {
+                    let request = Request::builder().method("POST").uri("/compute").body(Body::from(body.to_string())).unwrap();
+                    let response = app.oneshot(request).await.unwrap();
+                    let status = response.status();
+                    let bytes = axum::body::to_bytes(response.into_body(), usize::MAX).await.unwrap();
+                    (status, String::from_utf8(bytes.to_vec()).unwrap())
+                }
+            };
+            let body = |precision: &str, extra: serde_json::Value| {
+                let mut body = serde_json::json!({ "matrix_a": [[1, 2], [3, 4]], "matrix_b": [[5, 6], [7, 8]], "precision": precision });
+                body.as_object_mut().unwrap().extend(extra.as_object().unwrap().clone());
+                body
+            };
+
+            let (status, clean) = send(body("u8i8", serde_json::json!({}))).await;
+            assert_eq!(status, StatusCode::OK);
+            let clean: serde_json::Value = serde_json::from_str(&clean).unwrap();
+            let messy = serde_json::json!({ "workload_type": "MATMUL ", "kernel": " U8I8_Generic", "strict_numbers": true });
+            let (status, output) = send(body(" U8i8\n", messy)).await;
+            assert_eq!(status, StatusCode::OK, "{}", output);
+            let output: serde_json::Value = serde_json::from_str(&output).unwrap();
+            assert_eq!(output["metadata"]["precision"], "u8i8");
+            assert_eq!(output["metadata"]["kernel"], "u8i8_generic");
+            assert_eq!(output["result_hash"], clean["result_hash"]);
+
+            let (status, output) = send(body("U8I8", serde_json::json!({ "exact_names": true }))).await;
+            assert_eq!(status, StatusCode::BAD_REQUEST);
+            assert!(output.contains("(accepted: fp32"), "{}", output);
+            let (status, output) = send(body("fp8", serde_json::json!({}))).await;
+            assert_eq!(status, StatusCode::BAD_REQUEST);
+            assert!(output.contains("\"fp8\" (accepted: fp32"), "{}", output);
+        }
+
+        #[tokio::test]
+        async fn test_health_answers_during_computes() {
+            // One runtime thread: a computation running on it would hold /health until it ended
+            let app = router(Arc::new(AppState::new(ApiConfig::default())));
+            let computes: Vec<_> = (0..4)
+                .map(|i| {
+                    let app = app.clone();
+                    tokio::spawn(async move {
+                        let body = serde_json::json!({ "seed": format!("{:02x}", i), "precision": "u8i8" });
+                        let request = Request::builder().method("POST").uri("/compute").body(Body::from(body.to_string())).unwrap();
+                        app.oneshot(request).await.unwrap().status()
+                    })
+                })
+                .collect();
+            tokio::time::sleep(std::time::Duration::from_millis(20)).await;
+            // Answers that came back before the last compute finished
+            let mut answered_while_computing = 0;
+            while computes.iter().any(|c| !c.is_finished()) {
+                let health = Request::builder().uri("/health").body(Body::empty()).unwrap();
+                assert_eq!(app.clone().oneshot(health).await.unwrap().status(), StatusCode::OK);
+                if computes.iter().any(|c| !c.is_finished()) {
+                    answered_while_computing += 1;
+                }
+                tokio::time::sleep(std::time::Duration::from_millis(5)).await;
+            }
+            assert!(answered_while_computing > 0);
+            for compute in computes {
+                assert_eq!(compute.await.unwrap(), StatusCode::OK);
+            }
+        }
+
+        #[tokio::test]
+        async fn test_panicking_computation_is_a_json_500() {
+            let err = compute_blocking::<()>(|| panic!("kernel exploded")).await.unwrap_err();
+            assert_eq!(err.status, StatusCode::INTERNAL_SERVER_ERROR);
+            let response = err.into_response();
+            let bytes = axum::body::to_bytes(response.into_body(), usize::MAX).await.unwrap();
+            let json: serde_json::Value = serde_json::from_slice(&bytes).unwrap();
+            assert_eq!(json["error"], "computation panicked");
+            assert_eq!(json["details"]["panic"], "kernel exploded");
+            let err = compute_blocking::<()>(|| panic!("{} of {}", 3, 4)).await.unwrap_err();
+            assert_eq!(err.details.unwrap()["panic"], "3 of 4");
+            // A job records the same details
+            let err = compute_blocking::<()>(|| panic!("kernel exploded")).await.unwrap_err();
+            assert_eq!(err.job_error(), r#"computation panicked: {"panic":"kernel exploded"}"#);
+        }
+
+        #[tokio::test]
+        async fn test_threads_field() {
+            let app = router(Arc::new(AppState::new(ApiConfig::default())));
+            let send = |threads: usize| {
+                let app = app.clone();
+                async move {
+                    let body = serde_json::json!({ "seed": "0badc0de", "seed_dims": { "m": 16, "k": 640, "n": 16 }, "precision": "u8i8", "threads": threads });
+                    let request = Request::builder().method("POST").uri("/compute").body(Body::from(body.to_string())).unwrap();
+                    let response = app.oneshot(request).await.unwrap();
+                    let status = response.status();
+                    let bytes = axum::body::to_bytes(response.into_body(), usize::MAX).await.unwrap();
+                    (status, serde_json::from_slice::<serde_json::Value>(&bytes).unwrap_or_default())
+                }
+            };
+            let (status, serial) = send(1).await;
+            assert_eq!(status, StatusCode::OK);
+            assert_eq!(serial["metrics"]["threads_used"], 1);
+            let parallelism = std::thread::available_parallelism().unwrap().get();
+            let (status, split) = send(2).await;
+            if cfg!(feature = "parallel") {
+                assert_eq!(status, StatusCode::OK);
+                assert_eq!(split["result_hash"], serial["result_hash"]);
+                assert_eq!(split["metrics"]["threads_used"], 2.min(parallelism));
+                // Counts beyond the cores are capped, not granted
+                let (status, many) = send(100_000).await;
+                assert_eq!(status, StatusCode::OK);
+                assert_eq!(many["result_hash"], serial["result_hash"]);
+                assert!(many["metrics"]["threads_used"].as_u64().unwrap() as usize <= parallelism);
+            } else {
+                assert_eq!(status, StatusCode::BAD_REQUEST);
+            }
+            assert_eq!(send(0).await.0, StatusCode::BAD_REQUEST);
+        }
+
+        #[tokio::test]
+        async fn test_shape_mismatch_details() {
+            let app = router(Arc::new(AppState::new(ApiConfig::default())));
+            let body = r#"{"matrix_a": [[1.0, 2.0], [3.0, 4.0]], "matrix_b": [[5.0, 6.0]], "precision": "fp32"}"#;
+            let response = app
+                .oneshot(Request::builder().method("POST").uri("/compute").body(Body::from(body)).unwrap())
+                .await
+                .unwrap();
+            assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+            let bytes = axum::body::to_bytes(response.into_body(), usize::MAX).await.unwrap();
+            let json: serde_json::Value = serde_json::from_slice(&bytes).unwrap();
+            assert_eq!(json["error"], "Matrix dimensions incompatible: matrix_a.cols (=2) must equal matrix_b.rows (=1)");
+            let violation = &json["details"]["violations"][0];
+            assert_eq!(violation["field"], "matrix_a.cols");
+            assert_eq!(violation["other"], "matrix_b.rows");
+            assert_eq!(violation["relation"], "equal");
+            assert_eq!((violation["value"].as_u64(), violation["other_value"].as_u64()), (Some(2), Some(1)));
+        }
+
+        #[tokio::test]
+        async fn test_sparse_b_request() {
+            let send = |body: serde_json::Value| async move {
+                let app = router(Arc::new(AppState::new(ApiConfig::default())));
+                let request = Request::builder().method("POST").uri("/compute").body(Body::from(body.to_string())).unwrap();
+                let response = app.oneshot(request).await.unwrap();
+                let status = response.status();
+                let bytes = axum::body::to_bytes(response.into_body(), usize::MAX).await.unwrap();
+                (status, String::from_utf8(bytes.to_vec()).unwrap())
+            };
+            let a = serde_json::json!([[1.0, 2.0, 3.0], [0.5, 0.0, -1.0]]);
+            let dense = serde_json::json!({ "matrix_a": a, "matrix_b": [[0, 2], [0, 0], [5, 0]], "precision": "fp32", "kernel": "fp32_blocked" });
+            let sparse_b = serde_json::json!({ "rows": 3, "cols": 2, "values": [2, 5], "col_indices": [1, 0], "row_ptr": [0, 1, 1, 2] });
+            let sparse = serde_json::json!({ "matrix_a": a, "matrix_b_sparse": sparse_b, "precision": "fp32", "verify": true });
+
+            let (status, dense) = send(dense).await;
+            assert_eq!(status, StatusCode::OK, "{}", dense);
+            let (status, output) = send(sparse.clone()).await;
+            assert_eq!(status, StatusCode::OK, "{}", output);
+            let (dense, output): (serde_json::Value, serde_json::Value) = (serde_json::from_str(&dense).unwrap(), serde_json::from_str(&output).unwrap());
+            assert_eq!((&output["result_matrix"], &output["result_hash"]), (&dense["result_matrix"], &dense["result_hash"]));
+            assert_eq!((output["metadata"]["kernel"].as_str(), output["verified"].as_bool()), (Some("fp32_sparse"), Some(true)));
+
+            // Sparse B is an alternative to matrix_b, not an addition
+            let mut both = sparse;
+            both["matrix_b"] = serde_json::json!([[0, 2], [0, 0], [5, 0]]);
+            let (status, output) = send(both).await;
+            assert_eq!(status, StatusCode::BAD_REQUEST);
+            assert_eq!(output, "matrix_b and matrix_b_sparse are alternatives; set only one");
+        }
+
+        #[tokio::test]
+        async fn test_tolerance_profile_request() {
+            let send = |extra: serde_json::Value| async move {
+                let mut body = serde_json::json!({ "matrix_a": [[1.1, 2.3]], "matrix_b": [[3.7], [0.9]], "precision": "bf16", "error_metrics": true });
+                body.as_object_mut().unwrap().extend(extra.as_object().unwrap().clone());
+                let app = router(Arc::new(AppState::new(ApiConfig::default())));
+                let request = Request::builder().method("POST").uri("/compute").body(Body::from(body.to_string())).unwrap();
+                let response = app.oneshot(request).await.unwrap();
+                let status = response.status();
+                let bytes = axum::body::to_bytes(response.into_body(), usize::MAX).await.unwrap();
+                (status, String::from_utf8(bytes.to_vec()).unwrap())
+            };
+            let aux = |output: &str| serde_json::from_str::<serde_json::Value>(output).unwrap()["auxiliary_results"][0].clone();
+
+            let (status, output) = send(serde_json::json!({})).await;
+            assert_eq!(status, StatusCode::OK, "{}", output);
+            let default = aux(&output);
+            assert_eq!((default["within_tolerance"].as_bool(), default["tolerance"]["profile"].as_str()), (Some(true), Some("default")));
+
+            let overridden = serde_json::json!({ "tolerance_profile": "strict", "tolerances": { "bf16": { "rel": 1e-5, "abs": 0.0 } } });
+            let (status, output) = send(overridden).await;
+            assert_eq!(status, StatusCode::OK, "{}", output);
+            let strict = aux(&output);
+            assert_eq!((strict["within_tolerance"].as_bool(), strict["tolerance"]["profile"].as_str()), (Some(false), Some("strict")));
+            assert_eq!((strict["tolerance"]["rel"].as_f64(), strict["tolerance"]["overridden"].as_bool()), (Some(1e-5), Some(true)));
+
+            let (status, output) = send(serde_json::json!({ "tolerance_profile": "lax" })).await;
+            assert_eq!(status, StatusCode::BAD_REQUEST);
+            assert!(output.contains("Unknown tolerance profile: lax"), "{}", output);
+            let (status, output) = send(serde_json::json!({ "tolerances": { "bf16": { "rel": -1.0, "abs": 0.0 } } })).await;
+            assert_eq!(status, StatusCode::BAD_REQUEST);
+            assert!(output.contains("Invalid bf16 tolerance"), "{}", output);
+        }
+
+        #[tokio::test]
+        async fn test_validate_request() {
+            let config = ApiConfig { matrix_limits: MatrixLimits { max_rows: 100, max_cols: 100, max_elements: 1 << 12 }, ..Default::default() };
+            let state = Arc::new(AppState::new(config));
+            let send = |uri: &'static str, body: serde_json::Value| {
+                let app = router(state.clone());
+                async move {
+                    let request = Request::builder().method("POST").uri(uri).body(Body::from(body.to_string())).unwrap();
+                    let response = app.oneshot(request).await.unwrap();
+                    let status = response.status();
+                    let bytes = axum::body::to_bytes(response.into_body(), usize::MAX).await.unwrap();
+                    (status, serde_json::from_slice::<serde_json::Value>(&bytes).unwrap_or_else(|_| String::from_utf8_lossy(&bytes).into()))
+                }
+            };
+            let matrix = |rows: usize, cols: usize| vec![vec![1.0; cols]; rows];
+
+            // Shapes alone: the predicted result shape, op count and memory estimate
+            let shapes = serde_json::json!({ "matrix_a_shape": [16, 64], "matrix_b_shape": [64, 16], "precision": "int8", "options": { "hash_mode": "exact" } });
+            let (status, preflight) = send("/validate", shapes).await;
+            assert_eq!(status, StatusCode::OK, "{}", preflight);
+            let estimate = crate::memory::estimate_workload_bytes("int8", (16, 64), (64, 16));
+            assert_eq!((&preflight["result_shape"], &preflight["ops"], &preflight["memory_bytes"]), (&serde_json::json!([16, 16]), &serde_json::json!(16 * 64 * 16), &serde_json::json!(estimate.total())));
+            // ... the same as for the full request, whose kernel /compute then runs
+            let full = serde_json::json!({ "matrix_a": matrix(16, 64), "matrix_b": matrix(64, 16), "precision": "int8" });
+            assert_eq!(send("/validate", full.clone()).await, (StatusCode::OK, preflight.clone()));
+            let (_, output) = send("/compute", full).await;
+            assert_eq!(output["metadata"]["kernel"], preflight["kernel"]);
+
+            // Incompatible shapes get /compute's error and violation details
+            let (status, error) = send("/validate", serde_json::json!({ "matrix_a_shape": [2, 3], "matrix_b_shape": [4, 2], "precision": "fp32" })).await;
+            assert_eq!(status, StatusCode::BAD_REQUEST);
+            assert_eq!(error["details"]["violations"][0]["field"], "matrix_a.cols", "{}", error);
+            let full = serde_json::json!({ "matrix_a": matrix(2, 3), "matrix_b": matrix(4, 2), "precision": "fp32" });
+            assert_eq!(send("/compute", full).await, (status, error));
+
+            // So do shapes over the matrix limits, where the parse of such a body would stop
+            let (status, error) = send("/validate", serde_json::json!({ "matrix_a_shape": [101, 1], "matrix_b_shape": [1, 4], "precision": "fp32" })).await;
+            assert_eq!(status, StatusCode::PAYLOAD_TOO_LARGE);
+            assert_eq!((&error["details"]["matrix"], &error["details"]["limit"], &error["details"]["row"]), (&serde_json::json!("matrix_a"), &serde_json::json!("max_rows"), &serde_json::json!(100)));
+            let full = serde_json::json!({ "matrix_a": matrix(101, 1), "matrix_b": matrix(1, 4), "precision": "fp32" });
+            assert_eq!(send("/compute", full).await, (status, error));
+
+            // Option conflicts and misplaced fields
+            let (status, error) = send("/validate", serde_json::json!({ "matrix_a_shape": [4, 4], "matrix_b_shape": [4, 4], "precision": "fp32", "options": { "accumulator": "i64" } })).await;
+            assert_eq!((status, error.as_str()), (StatusCode::BAD_REQUEST, Some("accumulator i64 applies to u8i8, not fp32")));
+            let (status, error) = send("/validate", serde_json::json!({ "matrix_a_shape": [4, 4], "precision": "fp32", "options": { "seed": "00" } })).await;
+            assert_eq!((status, error.as_str()), (StatusCode::BAD_REQUEST, Some("seed does not belong in the options of a shapes-only request")));
+            let (status, _) = send("/validate", serde_json::json!({ "matrix_a_shape": [4, 4], "precision": "int8", "options": { "workload_type": "quantize" } })).await;
+            assert_eq!(status, StatusCode::OK);
+        }
+
+        #[tokio::test]
+        async fn test_capabilities_negotiation() {
+            let config = ApiConfig {
+                matrix_limits: MatrixLimits { max_rows: 4096, max_cols: 4096, max_elements: 1 << 16 },
+                api_tokens: Some(vec!["good".to_string()]),
+                ..Default::default()
+            };
+            let app = router(Arc::new(AppState::new(config)));
+            let call = |method: &'static str, uri: &'static str, body: String| {
+                let app = app.clone();
+                async move {
+                    let request = Request::builder().method(method).uri(uri).header("authorization", "Bearer good");
+                    let response = app.oneshot(request.body(Body::from(body)).unwrap()).await.unwrap();
+                    let status = response.status();
+                    let bytes = axum::body::to_bytes(response.into_body(), usize::MAX).await.unwrap();
+                    (status, serde_json::from_slice::<serde_json::Value>(&bytes).unwrap_or_default())
+                }
+            };
+
+            // Needs no token
+            let response = app.clone().oneshot(Request::builder().uri("/capabilities").body(Body::empty()).unwrap()).await.unwrap();
+            assert_eq!(response.status(), StatusCode::OK);
+            let bytes = axum::body::to_bytes(response.into_body(), usize::MAX).await.unwrap();
+            let caps: Capabilities = serde_json::from_slice(&bytes).unwrap();
+            assert_eq!(caps.capabilities_version, CAPABILITIES_VERSION);
+            assert_eq!(caps.precisions, crate::tests::precisions());
+            assert!(caps.seed_profiles.contains(&"blake3-xof-v1".to_string()));
+            assert_eq!(caps.features.contains(&"fp16".to_string()), cfg!(feature = "fp16"));
+            let max_dims = caps.max_dims.unwrap();
+            assert_eq!((max_dims.max_rows, max_dims.max_elements, max_dims.max_seed_elements), (4096, 1 << 16, MAX_SEED_ELEMENTS));
+
+            let request = |require: &str| {
+                format!(
+                    r#"{{"seed": "0102", "seed_dims": {{"m": 4, "k": 8, "n": 4}}, "precision": "u8i8", "require_capabilities": [{}]}}"#,
+                    require
+                )
+            };
+            let (status, json) = call("POST", "/compute", request(r#""precision:u8i8", "seed_profile:blake3-xof-v1""#)).await;
+            assert_eq!(status, StatusCode::OK);
+            assert!(json["result_hash"].is_string());
+
+            // Refused before computing or queuing, on /compute and /jobs alike
+            for uri in ["/compute", "/jobs"] {
+                let (status, json) = call("POST", uri, request(r#""seed_profile:blake3-xof-v2", "hash_input:i32le""#)).await;
+                assert_eq!(status, StatusCode::UPGRADE_REQUIRED, "{}", uri);
+                assert_eq!(json["details"], serde_json::json!({"missing": ["seed_profile:blake3-xof-v2"], "capabilities_version": 1}));
+                assert!(json["error"].as_str().unwrap().contains("seed_profile:blake3-xof-v2"));
+            }
+            let (status, json) = call("POST", "/compute", request(r#""precision:fp16""#)).await;
+            if cfg!(feature = "fp16") {
+                assert_eq!(status, StatusCode::OK);
+            } else {
+                assert_eq!(status, StatusCode::UPGRADE_REQUIRED);
+                assert_eq!(json["details"]["missing"], serde_json::json!(["precision:fp16"]));
+            }
+        }
+
+        #[cfg(feature = "counting-alloc")]
+        #[tokio::test]
+        async fn test_request_memory_peaks() {
+            let config = ApiConfig {
+                debug_token: Some("debug-secret".to_string()),
+                request_memory_warn_bytes: Some(1 << 20),
+                max_body_bytes: Some(64 << 20),
+                ..Default::default()
+            };
+            let state = Arc::new(AppState::new(config));
+            let app = router(state.clone());
+            let call = |method: &'static str, uri: String, body: String| {
+                let app = app.clone();
+                async move {
+                    let request = Request::builder().method(method).uri(uri).header("authorization", "Bearer debug-secret");
+                    let response = app.oneshot(request.body(Body::from(body)).unwrap()).await.unwrap();
+                    let status = response.status();
+                    let bytes = axum::body::to_bytes(response.into_body(), usize::MAX).await.unwrap();
+                    (status, serde_json::from_slice::<serde_json::Value>(&bytes).unwrap_or_default())
+                }
+            };
+            let peak_mb = |output: &serde_json::Value| output["metrics"]["peak_request_memory_mb"].as_f64().unwrap();
+
+            // u8i8 seeds are generated and packed as bytes: 16·4096 + 4096·16 of them
+            let seed = r#"{"seed": "0102", "seed_dims": {"m": 16, "k": 4096, "n": 16}, "precision": "u8i8"}"#;
+            let (status, output) = call("POST", "/compute".to_string(), seed.to_string()).await;
+            assert_eq!(status, StatusCode::OK);
+            let seed_peak = peak_mb(&output);
+            assert!(seed_peak > 0.0 && seed_peak < 0.5, "seed request peaked at {} MB", seed_peak);
+
+            // fp32 matrices parsed from JSON, packed and multiplied
+            let (m, k, n) = (256, 1024, 256);
+            let (a, b) = crate::generate_matrices_from_seed(b"request-memory", m, k, k, n);
+            let body = serde_json::json!({ "matrix_a": a, "matrix_b": b, "precision": "fp32" }).to_string();
+            let body_mb = memory::bytes_to_mb(body.len() as u64);
+            let (status, output) = call("POST", "/compute".to_string(), body).await;
+            assert_eq!(status, StatusCode::OK);
+            let matrix_peak = peak_mb(&output);
+            let estimate = memory::estimate_workload_bytes("fp32", (m, k), (k, n));
+            // The body was allocated before the request and is freed once parsed, which credits
+            // the request before the result is allocated; the parsed inputs are the floor
+            let held_mb = memory::bytes_to_mb(estimate.inputs);
+            let total_mb = memory::bytes_to_mb(estimate.total());
+            assert!(matrix_peak > seed_peak);
+            assert!(matrix_peak >= held_mb, "matrix request peaked at {} MB, inputs are {} MB", matrix_peak, held_mb);
+            assert!(matrix_peak <= total_mb + body_mb, "matrix request peaked at {} MB, estimate {} MB + body {} MB", matrix_peak, total_mb, body_mb);
+
+            // A job carries its counter into the blocking pool and is recorded once, when it ends
+            let (status, accepted) = call("POST", "/jobs".to_string(), seed.to_string()).await;
+            assert_eq!(status, StatusCode::ACCEPTED);
+            let summary = loop {
+                let (_, summary) = call("GET", format!("/jobs/{}", accepted["id"].as_str().unwrap()), String::new()).await;
+                if summary["status"] != "running" {
+                    break summary;
+                }
+                tokio::time::sleep(Duration::from_millis(10)).await;
+            };
+            let job_peak = peak_mb(&summary);
+            assert!(job_peak > 0.0 && job_peak < 0.5, "job peaked at {} MB", job_peak);
+
+            let (_, stats) = call("GET", "/stats".to_string(), String::new()).await;
+            let request_memory = &stats["request_memory"];
+            assert_eq!(request_memory["requests"], 3);
+            // Only the matrix request crossed the 1 MB soft limit
+            assert_eq!(request_memory["over_soft_limit"], 1);
+            assert!(request_memory["max_mb"].as_f64().unwrap() >= matrix_peak);
+            assert!(request_memory["p50_mb"].as_f64().unwrap() < 0.5);
+        }
+
+        // Yields a body in 64 KiB chunks, each allocated when polled so it is charged to the
+        // request that reads it, like a socket read
+        struct ChunkedBody {
+            bytes: Arc<Vec<u8>>,
+            offset: usize,
+        }
+
+        impl HttpBody for ChunkedBody {
+            type Data = Bytes;
+            type Error = std::convert::Infallible;
+
+            fn poll_frame(
+                mut self: Pin<&mut Self>,
+                _: &mut std::task::Context<'_>,
+            ) -> std::task::Poll<Option<Result<http_body::Frame<Bytes>, Self::Error>>> {
+                let end = (self.offset + (64 << 10)).min(self.bytes.len());
+                if self.offset == end {
+                    return std::task::Poll::Ready(None);
+                }
+                let chunk = Bytes::copy_from_slice(&self.bytes[self.offset..end]);
+                self.offset = end;
+                std::task::Poll::Ready(Some(Ok(http_body::Frame::data(chunk))))
+            }
+        }
+
+        // POST `body` to /compute in chunks, declaring `declared` bytes
+        fn chunked_compute(body: &Arc<Vec<u8>>, declared: usize) -> Request {
+            Request::builder()
+                .method("POST")
+                .uri("/compute")
+                .header("content-type", "application/json")
+                .header("content-length", declared)
+                .body(Body::new(ChunkedBody { bytes: body.clone(), offset: 0 }))
+                .unwrap()
+        }
+
+        // fp32 matrices whose JSON (thirds of the seed bytes) is about 2.5x their f32 size
+        fn streaming_body(m: usize, k: usize, n: usize) -> (types::Input, Arc<Vec<u8>>) {
+            let (mut a, mut b) = crate::generate_matrices_from_seed(b"streaming", m, k, k, n);
+            a.data.iter_mut().chain(&mut b.data).for_each(|x| *x /= 3.0);
+            let body = serde_json::json!({ "matrix_a": a, "matrix_b": b, "precision": "fp32" }).to_string();
+            let input = types::Input::matmul(a, b, "fp32");
+            (input, Arc::new(body.into_bytes()))
+        }
+
+        #[tokio::test]
+        async fn test_streamed_bodies() {
+            let config = ApiConfig {
+                stream_body_min_bytes: Some(0),
+                max_body_bytes: Some(1 << 20),
+                matrix_limits: MatrixLimits { max_rows: 100, ..Default::default() },
+                ..Default::default()
+            };
+            let app = router(Arc::new(AppState::new(config)));
+            let call = |request: Request| {
+                let app = app.clone();
+                async move {
+                    let response = app.oneshot(request).await.unwrap();
+                    let status = response.status();
+                    let bytes = axum::body::to_bytes(response.into_body(), usize::MAX).await.unwrap();
+                    (status, String::from_utf8_lossy(&bytes).into_owned())
+                }
+            };
+
+            let (input, body) = streaming_body(32, 64, 16);
+            let expected = compute_workload_with_options(input, &ComputeOptions::default()).unwrap();
+            let (status, text) = call(chunked_compute(&body, body.len())).await;
+            assert_eq!(status, StatusCode::OK, "{}", text);
+            let output: types::Output = serde_json::from_str(&text).unwrap();
+            assert_eq!(output.result_hash, expected.result_hash);
+            assert_eq!(output.metadata.lossy_parse_count, Some(0));
+
+            // The cap applies to the declared length up front and to the bytes that arrive
+            let (status, _) = call(chunked_compute(&body, 2 << 20)).await;
+            assert_eq!(status, StatusCode::PAYLOAD_TOO_LARGE);
+            let (_, large) = streaming_body(16, 100, 4096);
+            let (status, text) = call(chunked_compute(&large, 1024)).await;
+            assert_eq!((status, text.as_str()), (StatusCode::PAYLOAD_TOO_LARGE, "Request body exceeds 1048576 bytes"));
+
+            // Matrix limits and malformed JSON are reported as on the buffered path
+            let (_, tall) = streaming_body(128, 8, 16);
+            let (status, text) = call(chunked_compute(&tall, tall.len())).await;
+            assert_eq!(status, StatusCode::PAYLOAD_TOO_LARGE);
+            assert!(text.contains("max_rows"), "{}", text);
+            let truncated = Arc::new(body[..body.len() / 2].to_vec());
+            let (status, text) = call(chunked_compute(&truncated, truncated.len())).await;
+            assert_eq!(status, StatusCode::BAD_REQUEST);
+            assert!(text.starts_with("Failed to parse the request body as JSON"), "{}", text);
+        }
+
+        #[cfg(feature = "counting-alloc")]
+        #[tokio::test]
+        async fn test_streamed_body_peak_memory() {
+            let (input, body) = streaming_body(64, 4096, 64);
+            let expected = compute_workload_with_options(input, &ComputeOptions::default()).unwrap();
+            let body_mb = memory::bytes_to_mb(body.len() as u64);
+
+            let mut peaks = Vec::new();
+            for stream_body_min_bytes in [usize::MAX, 0] {
+                let config = ApiConfig { stream_body_min_bytes: Some(stream_body_min_bytes), max_body_bytes: Some(64 << 20), ..Default::default() };
+                let app = router(Arc::new(AppState::new(config)));
+                let response = app.oneshot(chunked_compute(&body, body.len())).await.unwrap();
+                assert_eq!(response.status(), StatusCode::OK);
+                let bytes = axum::body::to_bytes(response.into_body(), usize::MAX).await.unwrap();
+                let output: types::Output = serde_json::from_slice(&bytes).unwrap();
+                assert_eq!(output.result_hash, expected.result_hash);
+                peaks.push(output.metrics.peak_request_memory_mb.unwrap());
+            }
+
+            // The buffered request holds the whole body next to the parsed matrices
+            let (buffered, streamed) = (peaks[0], peaks[1]);
+            assert!(buffered >= body_mb, "buffered peak {} MB, body {} MB", buffered, body_mb);
+            assert!(streamed + body_mb / 2.0 < buffered, "streamed peak {} MB, buffered {} MB, body {} MB", streamed, buffered, body_mb);
+        }
+
+        #[tokio::test]
+        async fn test_streamed_responses() {
+            let app = router(Arc::new(AppState::new(ApiConfig::default())));
+            for precision in ["fp32", "u8i8"] {
+                // 512×1024 results (several MB of JSON) are streamed, 16×16 ones are not
+                for (m, n, streamed) in [(512, 1024, true), (16, 16, false)] {
+                    let body = format!(r#"{{"seed": "5eed", "seed_dims": {{"m": {}, "k": 16, "n": {}}}, "precision": "{}"}}"#, m, n, precision);
+                    let expected = process_request(serde_json::from_str(&body).unwrap()).unwrap();
+                    let request = Request::builder().method("POST").uri("/compute").header("content-type", "application/json").body(Body::from(body.clone())).unwrap();
+                    let response = app.clone().oneshot(request).await.unwrap();
+                    assert_eq!(response.status(), StatusCode::OK);
+                    assert_eq!(response.headers().get("content-length").is_none(), streamed, "{} {}x{}", precision, m, n);
+                    let bytes = axum::body::to_bytes(response.into_body(), usize::MAX).await.unwrap();
+
+                    // The hash written while serializing is the separately computed one, over
+                    // the same result, and the body is that output's canonical JSON
+                    let output: types::Output = serde_json::from_slice(&bytes).unwrap();
+                    assert_eq!(output.result_hash, expected.result_hash, "{} {}x{}", precision, m, n);
+                    assert_eq!(output.result_matrix.data, expected.result_matrix.data);
+                    assert!(serde_json::to_vec(&output).unwrap() == bytes, "{} {}x{}", precision, m, n);
+
+                    let request = Request::builder().method("POST").uri("/compute").header("accept", MSGPACK_CONTENT_TYPE).body(Body::from(body)).unwrap();
+                    let bytes = axum::body::to_bytes(app.clone().oneshot(request).await.unwrap().into_body(), usize::MAX).await.unwrap();
+                    let output: types::Output = rmp_serde::from_slice(&bytes).unwrap();
+                    assert_eq!(output.result_hash, expected.result_hash, "{} {}x{} msgpack", precision, m, n);
+                }
+            }
+        }
+
+        #[tokio::test]
+        async fn test_job_result_pages() {
+            let app = router(Arc::new(AppState::new(ApiConfig::default())));
+            let call = |method: &'static str, uri: String, body: &'static str| {
+                let app = app.clone();
+                async move {
+                    let response = app
+                        .oneshot(Request::builder().method(method).uri(uri).body(Body::from(body)).unwrap())
+                        .await
+                        .unwrap();
+                    let status = response.status();
+                    let bytes = axum::body::to_bytes(response.into_body(), usize::MAX).await.unwrap();
+                    (status, serde_json::from_slice::<serde_json::Value>(&bytes).unwrap_or_default())
+                }
+            };
+
+            let body = r#"{"seed": "0a0b", "seed_dims": {"m": 300, "k": 64, "n": 16}, "precision": "fp32"}"#;
+            let (status, accepted) = call("POST", "/jobs".to_string(), body).await;
+            assert_eq!(status, StatusCode::ACCEPTED);
+            let id = accepted["id"].as_str().unwrap().to_string();
+            let summary = loop {
+                let (status, summary) = call("GET", format!("/jobs/{}", id), "").await;
+                assert_eq!(status, StatusCode::OK);
+                if summary["status"] != "running" {
+                    break summary;
+                }
+                tokio::time::sleep(Duration::from_millis(10)).await;
+            };
+            assert_eq!(summary["status"], "ok");
+            assert_eq!(summary["result_shape"], serde_json::json!([300, 16]));
+            assert!(summary.get("result_matrix").is_none());
+
+            // Two pages, reassembled, hash to the job's result hash
+            let (_, first) = call("GET", format!("/jobs/{}/result?offset_rows=0&limit_rows=256", id), "").await;
+            assert_eq!(first["next_offset_rows"], 256);
+            let (_, second) = call("GET", format!("/jobs/{}/result?offset_rows=256&limit_rows=256", id), "").await;
+            assert!(second["next_offset_rows"].is_null());
+            let mut rows: Vec<Vec<f32>> = serde_json::from_value(first["result_matrix"].clone()).unwrap();
+            rows.extend(serde_json::from_value::<Vec<Vec<f32>>>(second["result_matrix"].clone()).unwrap());
+            assert_eq!(rows.len(), 300);
+            let data: Vec<f32> = rows.into_iter().flatten().collect();
+            let hash = crate::compute_hash(&FlatMatrix { data, rows: 300, cols: 16 });
+            assert_eq!(summary["result_hash"], hash.as_str());
+            assert_eq!(second["result_hash"], hash.as_str());
+
+            let (status, _) = call("GET", format!("/jobs/{}/result?offset_rows=300", id), "").await;
+            assert_eq!(status, StatusCode::BAD_REQUEST);
+            let (status, _) = call("GET", format!("/jobs/{}?inline=true", id), "").await;
+            assert_eq!(status, StatusCode::OK);
+            assert_eq!(call("GET", "/jobs/nope".to_string(), "").await.0, StatusCode::NOT_FOUND);
+        }
+
+        #[derive(Default)]
+        struct Receiver {
+            // (timestamp, signature, body) of every delivery attempt
+            received: std::sync::Mutex<Vec<(String, String, Bytes)>>,
+            // Answer 500 to the next delivery
+            fail_next: std::sync::Mutex<bool>,
+        }
+
+        async fn receive(State(receiver): State<Arc<Receiver>>, headers: HeaderMap, body: Bytes) -> StatusCode {
+            let header = |name: &str| headers.get(name).and_then(|v| v.to_str().ok()).unwrap_or_default().to_string();
+            let attempt = (header(crate::webhook::TIMESTAMP_HEADER), header(crate::webhook::SIGNATURE_HEADER), body);
+            receiver.received.lock().unwrap().push(attempt);
+            if std::mem::take(&mut *receiver.fail_next.lock().unwrap()) {
+                return StatusCode::INTERNAL_SERVER_ERROR;
+            }
+            StatusCode::OK
+        }
+
+        #[tokio::test]
+        async fn test_job_callbacks_are_signed_and_retried() {
+            let receiver = Arc::new(Receiver { fail_next: std::sync::Mutex::new(true), ..Default::default() });
+            let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+            let callback_url = format!("http://{}/done", listener.local_addr().unwrap());
+            let mock = Router::new().route("/done", post(receive)).with_state(receiver.clone());
+            tokio::spawn(async move { axum::serve(listener, mock).await.unwrap() });
+
+            let config = ApiConfig {
+                webhooks: WebhookConfig {
+                    allowed_hosts: vec!["127.0.0.1".to_string()],
+                    allowed_schemes: vec!["http".to_string()],
+                    secret: Some("s3cret".to_string()),
+                    initial_backoff: Duration::from_millis(10),
+                    ..Default::default()
+                },
+                ..Default::default()
+            };
+            let app = router(Arc::new(AppState::new(config)));
+            let call = |method: &'static str, uri: String, body: String| {
+                let app = app.clone();
+                async move {
+                    let response = app
+                        .oneshot(Request::builder().method(method).uri(uri).body(Body::from(body)).unwrap())
+                        .await
+                        .unwrap();
+                    let status = response.status();
+                    let bytes = axum::body::to_bytes(response.into_body(), usize::MAX).await.unwrap();
+                    (status, serde_json::from_slice::<serde_json::Value>(&bytes).unwrap_or_default())
+                }
+            };
+            // Submit a job and wait until its callback is settled
+            let run_job = |precision: &'static str| {
+                let body = serde_json::json!({
+                    "seed": "0a0b",
+                    "seed_dims": {"m": 16, "k": 64, "n": 16},
+                    "precision": precision,
+                    "callback_url": callback_url,
+                });
+                let call = &call;
+                async move {
+                    let (status, accepted) = call("POST", "/jobs".to_string(), body.to_string()).await;
+                    assert_eq!(status, StatusCode::ACCEPTED);
+                    let id = accepted["id"].as_str().unwrap().to_string();
+                    loop {
+                        let (_, summary) = call("GET", format!("/jobs/{}", id), String::new()).await;
+                        if summary["callback"]["state"] != "pending" {
+                            break summary;
+                        }
+                        tokio::time::sleep(Duration::from_millis(10)).await;
+                    }
+                }
+            };
+            let verify = |attempt: &(String, String, Bytes)| {
+                let (timestamp, signature, body) = attempt;
+                let expected = crate::webhook::signature(b"s3cret", timestamp.parse().unwrap(), body);
+                assert_eq!(*signature, expected);
+                serde_json::from_slice::<serde_json::Value>(body).unwrap()
+            };
+
+            // The first attempt gets a 500 and is retried
+            let ok = run_job("fp32").await;
+            assert_eq!(ok["callback"]["state"], "delivered");
+            assert_eq!((ok["callback"]["attempts"].as_u64(), ok["callback"]["last_status"].as_u64()), (Some(2), Some(200)));
+            {
+                let received = receiver.received.lock().unwrap();
+                assert_eq!(received.len(), 2);
+                let payload = verify(&received[1]);
+                assert_eq!(payload, verify(&received[0]));
+                assert_eq!((&payload["id"], &payload["status"]), (&ok["id"], &serde_json::json!("ok")));
+                assert_eq!(payload["result_hash"], ok["result_hash"]);
+                assert!(payload["metrics"]["latency_ms"].is_number());
+                assert!(payload.get("result_matrix").is_none());
+            }
+
+            let failed = run_job("fp8").await;
+            assert_eq!(failed["status"], "error");
+            assert_eq!((failed["callback"]["state"].as_str(), failed["callback"]["attempts"].as_u64()), (Some("delivered"), Some(1)));
+            let payload = verify(receiver.received.lock().unwrap().last().unwrap());
+            assert_eq!((&payload["status"], &payload["error"]), (&serde_json::json!("error"), &failed["error"]));
+            assert!(payload.get("result_hash").is_none());
+
+            // Off-allowlist URLs are refused up front, and /compute takes no callback
+            for url in ["http://169.254.169.254/latest", "https://127.0.0.1/done", "file:///etc/passwd"] {
+                let body = serde_json::json!({"seed": "0a0b", "precision": "fp32", "callback_url": url});
+                assert_eq!(call("POST", "/jobs".to_string(), body.to_string()).await.0, StatusCode::BAD_REQUEST, "{}", url);
+            }
+            let body = serde_json::json!({"seed": "0a0b", "precision": "fp32", "callback_url": callback_url});
+            assert_eq!(call("POST", "/compute".to_string(), body.to_string()).await.0, StatusCode::BAD_REQUEST);
+        }
+
+        #[tokio::test]
+        async fn test_verify_batch_endpoint() {
+            let app = router(Arc::new(AppState::new(ApiConfig::default())));
+            let dims = SeedDims { m: 16, k: 256, n: 16 };
+            let hash = crate::compute_seed_workload(&[0x0a, 0x0b], (16, 256, 16), "u8i8", None, None, &ComputeOptions::default())
+                .unwrap()
+                .result_hash;
+            let post = |body: serde_json::Value| {
+                Request::builder()
+                    .method("POST")
+                    .uri("/verify/batch")
+                    .header("content-type", "application/json")
+                    .body(Body::from(body.to_string()))
+                    .unwrap()
+            };
+            let claims = serde_json::json!([
+                {"seed": "0a0b", "dims": dims, "precision": "u8i8", "expected_hash": hash},
+                {"seed": "0a0b", "dims": dims, "precision": "u8i8", "expected_hash": "00"},
+                {"seed": "zz", "dims": dims, "precision": "u8i8", "expected_hash": hash},
+            ]);
+            let response = app.clone().oneshot(post(serde_json::json!({"claims": claims, "jobs": 64}))).await.unwrap();
+            assert_eq!(response.status(), StatusCode::OK);
+            let body = axum::body::to_bytes(response.into_body(), usize::MAX).await.unwrap();
+            let report: BatchReport = serde_json::from_slice(&body).unwrap();
+            let verdicts: Vec<bool> = report.results.iter().map(|r| r.passed).collect();
+            assert_eq!(verdicts, [true, false, false]);
+            assert_eq!((report.summary.failed, report.summary.errors), (2, 1));
+            assert!(report.summary.jobs <= std::thread::available_parallelism().unwrap().get());
+
+            let too_many: Vec<_> = (0..=MAX_BATCH_CLAIMS).map(|_| claims[0].clone()).collect();
+            let response = app.clone().oneshot(post(serde_json::json!({"claims": too_many}))).await.unwrap();
+            assert_eq!(response.status(), StatusCode::PAYLOAD_TOO_LARGE);
+        }
+
+        #[test]
+        fn test_verify_reuses_the_request_matrices() {
+            use crate::faults::{with_plan, BitFlip, FaultPlan};
+            let (a, b) = crate::generate_matrices_from_seed(b"api-verify", 16, 48, 48, 16);
+            let request = |verify| ComputeRequest {
+                matrix_a: Some(a.clone()),
+                matrix_b: Some(b.clone()),
+                precision: "FP32".to_string(),
+                verify,
+                ..Default::default()
+            };
+            let plain = process_request(request(None)).unwrap();
+            assert_eq!((plain.verified, plain.metrics.verify_time_ms), (None, None));
+            let checked = process_request(request(Some(true))).unwrap();
+            assert_eq!((checked.verified, &checked.result_hash), (Some(true), &plain.result_hash));
+            assert!(checked.metrics.verify_time_ms.is_some());
+            assert_eq!(serde_json::to_value(&checked).unwrap()["verified"], true);
+
+            // Only the first kernel run is corrupted, so the recomputation disagrees with it
+            let plan = FaultPlan { flip_result_bit: Some(BitFlip { element: 3, bit: 12 }), kernel_runs: Some(vec![0]), ..Default::default() };
+            let corrupted = with_plan(plan, || process_request(request(Some(true)))).unwrap();
+            assert_ne!(corrupted.result_hash, plain.result_hash);
+            assert_eq!(corrupted.verified, Some(false));
+
+            // Seed requests regenerate the matrices; the i32le hash is replayed from metadata
+            let seed = ComputeRequest {
+                seed: Some("0badcafe".to_string()),
+                seed_dims: Some(SeedDims { m: 16, k: 64, n: 16 }),
+                precision: "u8i8".to_string(),
+                hash_input: Some("i32le".to_string()),
+                verify: Some(true),
+                ..Default::default()
+            };
+            assert_eq!(process_request(seed).unwrap().verified, Some(true));
+
+            let partial = ComputeRequest { result_columns: Some(vec![0]), ..request(Some(true)) };
+            assert_eq!(process_request(partial).unwrap_err().status, StatusCode::BAD_REQUEST);
+        }
+
+        #[test]
+        fn test_batched_matmul_requests() {
+            // A seed's m rows are the whole stack: four 8×64 items here
+            let seed = ComputeRequest {
+                seed: Some("0badcafe".to_string()),
+                seed_dims: Some(SeedDims { m: 32, k: 64, n: 16 }),
+                precision: "int8".to_string(),
+                workload_type: Some("batched_matmul".to_string()),
+                batch: Some(4),
+                verify: Some(true),
+                ..Default::default()
+            };
+            let output = process_request(seed.clone()).unwrap();
+            assert_eq!((output.verified, output.metadata.batch), (Some(true), Some(4)));
+            assert_eq!(output.batch_hashes.as_ref().map(Vec::len), Some(4));
+            assert_eq!(output.metrics.batch_kernel_time_ms.as_ref().map(Vec::len), Some(4));
+
+            let (a, b) = crate::generate_matrices_from_seed_hex("0badcafe", 32, 64, 64, 16).unwrap();
+            let matrices = ComputeRequest { seed: None, seed_dims: None, matrix_a: Some(a), matrix_b: Some(b), ..seed.clone() };
+            assert_eq!(process_request(matrices).unwrap().result_hash, output.result_hash);
+            let uneven = ComputeRequest { batch: Some(5), ..seed };
+            assert_eq!(process_request(uneven).unwrap_err().status, StatusCode::BAD_REQUEST);
+        }
+    }
+}
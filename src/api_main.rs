@@ -1,9 +1,57 @@
 #[cfg(feature = "api")]
-use matmul_solver::api;
+use matmul_solver::{api, simd, worker};
+
+#[cfg(feature = "api")]
+use clap::Parser;
+
+#[cfg(feature = "counting-alloc")]
+#[global_allocator]
+static GLOBAL: matmul_solver::memory::CountingAllocator = matmul_solver::memory::CountingAllocator;
+
+#[cfg(feature = "api")]
+#[derive(Parser, Debug)]
+#[command(author, version, about, long_about = None)]
+struct Args {
+    /// Run as a pull-based worker against this job server instead of serving HTTP
+    #[arg(long)]
+    worker: Option<String>,
+
+    /// Jobs executed concurrently in worker mode
+    #[arg(long, default_value_t = 1)]
+    concurrency: usize,
+
+    /// Milliseconds to wait between polls when the job server is idle
+    #[arg(long, default_value_t = 500)]
+    poll_interval_ms: u64,
+
+    /// Bearer token sent to the job server (falls back to WORKER_TOKEN)
+    #[arg(long)]
+    worker_token: Option<String>,
+
+    /// Exit after this many jobs (runs forever when omitted)
+    #[arg(long)]
+    max_jobs: Option<usize>,
+}
 
 #[cfg(feature = "api")]
 #[tokio::main]
 async fn main() -> Result<(), Box<dyn std::error::Error>> {
+    let args = Args::parse();
+    if let Ok(name) = std::env::var("SIMD_VARIANT") {
+        simd::force(simd::SimdVariant::parse(name.trim())?)?;
+    }
+
+    if let Some(url) = args.worker {
+        let mut config = worker::WorkerConfig::new(url);
+        config.concurrency = args.concurrency;
+        config.poll_interval = std::time::Duration::from_millis(args.poll_interval_ms);
+        config.auth_token = args.worker_token.or_else(|| std::env::var("WORKER_TOKEN").ok());
+        config.max_jobs = args.max_jobs;
+        let completed = worker::run_worker(config).await?;
+        println!("Worker finished after {} jobs", completed);
+        return Ok(());
+    }
+
     let port = std::env::var("PORT")
         .unwrap_or_else(|_| "8000".to_string())
         .parse::<u16>()
@@ -18,4 +66,3 @@ fn main() {
     eprintln!("API feature is not enabled. Build with --features api");
     std::process::exit(1);
 }
-
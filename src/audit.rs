@@ -0,0 +1,139 @@
+//! Output-file round-trip audit.
+//!
+//! `result_hash` is computed over the in-memory f32 result, while the output file holds
+//! decimal text. `write_output_audited` writes the file atomically and, before it becomes
+//! visible, re-reads it, rebuilds the result matrix from the text and recomputes the hash
+//! with the dtype and rounding recorded in its metadata (fp64 outputs from their
+//! `result_f64` values). Only a file that reproduces its own `result_hash` is published,
+//! with `audit_passed: true`.
+
+use crate::{compute_hash_f64, compute_hash_rounded, host, types, ResultDtype, SolverError};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+
+/// Recompute the hash of a serialized Output and compare it with its `result_hash`
+pub fn audit_output_json(json: &str) -> Result<(), SolverError> {
+    let output: types::Output = serde_json::from_str(json)
+        .map_err(|e| SolverError::Internal(format!("audit: output does not parse back: {}", e)))?;
+    let dtype = ResultDtype::parse(&output.metadata.result_dtype).ok_or_else(|| {
+        SolverError::Internal(format!("audit: unknown result_dtype {}", output.metadata.result_dtype))
+    })?;
+    let recomputed = match (dtype, &output.result_f64) {
+        (ResultDtype::F64, Some(values)) => compute_hash_f64(values),
+        (ResultDtype::F64, None) => {
+            return Err(SolverError::Internal("audit: result_dtype is f64 but the output has no result_f64".to_string()));
+        }
+        _ => compute_hash_rounded(&output.result_matrix, dtype, output.metadata.hash_rounding),
+    };
+    if recomputed != output.result_hash {
+        return Err(SolverError::Internal(format!(
+            "audit: hash of the serialized result is {}, but result_hash is {}",
+            recomputed, output.result_hash
+        )));
+    }
+    Ok(())
+}
+
+fn serialize(output: &types::Output, pretty: bool) -> Result<String, SolverError> {
+    let json = if pretty { serde_json::to_string_pretty(output) } else { serde_json::to_string(output) };
+    json.map_err(|e| SolverError::Internal(format!("failed to serialize output: {}", e)))
+}
+
+fn io_error(path: &Path, e: std::io::Error) -> SolverError {
+    SolverError::Internal(format!("{}: {}", path.display(), e))
+}
+
+fn write_file(path: &Path, contents: &str) -> Result<(), SolverError> {
+    let mut file = host::create(path).map_err(|e| io_error(path, e))?;
+    file.write_all(contents.as_bytes()).map_err(|e| io_error(path, e))?;
+    file.sync_all().map_err(|e| io_error(path, e))
+}
+
+fn temp_path(path: &Path) -> PathBuf {
+    let mut name = path.file_name().map(|n| n.to_os_string()).unwrap_or_default();
+    name.push(format!(".{}.tmp", std::process::id()));
+    path.with_file_name(name)
+}
+
+/// Write `output` to `path` atomically after auditing the serialized text.
+///
+/// The text is serialized once, already carrying `audit_passed: true`, and the bytes read
+/// back from the temporary file are the ones audited and then renamed into place. On
+/// success `output.audit_passed` is `Some(true)`. On failure nothing is written to `path`
+/// and the error says which hash the file would have had.
+pub fn write_output_audited(path: &Path, output: &mut types::Output, pretty: bool) -> Result<(), SolverError> {
+    let tmp = temp_path(path);
+    let result = (|| {
+        output.audit_passed = Some(true);
+        write_file(&tmp, &serialize(output, pretty)?)?;
+        audit_output_json(&host::read_to_string(&tmp).map_err(|e| io_error(&tmp, e))?)?;
+        host::rename(&tmp, path).map_err(|e| io_error(path, e))
+    })();
+    if result.is_err() {
+        output.audit_passed = None;
+        let _ = host::remove_file(&tmp);
+    }
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::faults::{with_plan, FaultPlan};
+    use crate::{compute_workload_with_options, generate_matrices_from_seed, ComputeOptions, HashRounding};
+    use std::fs;
+
+    fn output_for(precision: &str, options: &ComputeOptions) -> types::Output {
+        let (matrix_a, matrix_b) = generate_matrices_from_seed(b"audit", 16, 72, 72, 16);
+        let input = types::Input::matmul(matrix_a, matrix_b, precision);
+        compute_workload_with_options(input, options).unwrap()
+    }
+
+    #[test]
+    fn test_every_precision_round_trips_in_both_styles() {
+        let dir = tempfile::tempdir().unwrap();
+        let rounded = ComputeOptions {
+            hash_rounding: Some(HashRounding::MantissaBits { bits: 12 }),
+            ..Default::default()
+        };
+        let cases = [
+            ("fp32", ComputeOptions::default()),
+            ("fp32", rounded),
+            ("fp16", ComputeOptions::default()),
+            ("int8", ComputeOptions::default()),
+            ("u8i8", ComputeOptions::default()),
+        ];
+        let cases: Vec<_> = cases.into_iter().filter(|(p, _)| crate::tests::precisions().contains(p)).collect();
+        for (i, (precision, options)) in cases.iter().enumerate() {
+            for pretty in [true, false] {
+                let path = dir.path().join(format!("{}-{}-{}.json", i, precision, pretty));
+                let mut output = output_for(precision, options);
+                write_output_audited(&path, &mut output, pretty).unwrap();
+                assert_eq!(output.audit_passed, Some(true));
+
+                // The published bytes are exactly the audited serialization
+                let text = fs::read_to_string(&path).unwrap();
+                assert_eq!(text, serialize(&output, pretty).unwrap());
+                assert_eq!(text.contains('\n'), pretty);
+                let value: serde_json::Value = serde_json::from_str(&text).unwrap();
+                assert_eq!(value["audit_passed"], true, "{} pretty={}", precision, pretty);
+                audit_output_json(&text).unwrap();
+            }
+        }
+        // No temp files left behind
+        assert_eq!(fs::read_dir(dir.path()).unwrap().count(), cases.len() * 2);
+    }
+
+    #[test]
+    fn test_audit_failure_leaves_no_file() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("out.json");
+        // A hash corrupted on the way into the hasher no longer matches the result it came with
+        let plan = FaultPlan { corrupt_hash_byte: Some(13), ..Default::default() };
+        let mut output = with_plan(plan, || output_for("fp32", &ComputeOptions::default()));
+        let err = write_output_audited(&path, &mut output, true).unwrap_err();
+        assert!(err.to_string().contains("audit: hash of the serialized result"), "{}", err);
+        assert!(!path.exists());
+        assert_eq!(fs::read_dir(dir.path()).unwrap().count(), 0);
+    }
+}
@@ -0,0 +1,466 @@
+//! In-process LRU caches of B packed for the 16x16 kernels.
+//!
+//! The fp16, bf16 and int8 16x16 kernels transpose (and round or quantize) B into a 16×k
+//! buffer before their dot products, and fp32_blocked copies a B wider than one tile into
+//! column panels. A `PackedBCache` keeps the last few of those buffers,
+//! so an API alternating between a handful of seeds packs each B once. An entry matches a
+//! B of the same shape and the same digest of its elements (`CacheKey`: blake3 with the
+//! `blake3-hash` feature, else sha256, over the elements as one byte slice). B's address
+//! alone identifies nothing: a freed B's allocation is often reused by the next, with
+//! other elements. Within one request it does, as long as B stays borrowed, so
+//! `compute_workload_ref` runs under `with_request_b` and its B is digested at most once
+//! however many kernels or runs look it up.
+//!
+//! A `CacheContext` holds one cache per kernel family. Runs use `ComputeOptions::caches`,
+//! else the process-wide `CacheContext::global()` the CLI relies on; the API server keeps
+//! its own in `AppState`. Each cache's lock is held only to hash-match and reorder entries:
+//! B is digested before and packed after, so requests for different Bs pack side by side
+//! (two missing the same B may both pack it, and the first inserted is kept).
+//!
+//! Entries are handed out as `Arc`s, so evicting or clearing one while another request
+//! still reads it is safe. A context's capacity is its own (`CacheContext::with_limits`)
+//! or the process-wide one (`set_capacity`, the API's `PACKED_B_CACHE_CAPACITY`); at 0
+//! nothing is kept. Each cache also holds at most `max_bytes` of packed buffers
+//! (`set_max_bytes`, the API's `PACKED_B_CACHE_MAX_BYTES`), evicting the least recently
+//! used until it fits, so a few huge Bs cannot pin gigabytes outside `max_memory_bytes`;
+//! a buffer larger than the whole budget is used once and not kept. Counters run from the context's creation and are reported as
+//! `Metrics::cache_stats` by the kernels that pack through a cache. A request with
+//! `InputMetadata::cache_enabled: false` packs B for itself and leaves the caches alone.
+
+use crate::types::{CacheStats, FlatMatrix};
+#[cfg(feature = "fp16")]
+use crate::Fp16Rounding;
+use crate::{AlignedBufferF32, AlignedBufferI8, PackedBtI8};
+#[cfg(not(feature = "blake3-hash"))]
+use sha2::{Digest, Sha256};
+use std::cell::RefCell;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
+
+/// Entries each cache keeps unless `set_capacity` says otherwise
+pub const DEFAULT_CAPACITY: usize = 4;
+
+static CAPACITY: AtomicUsize = AtomicUsize::new(DEFAULT_CAPACITY);
+
+/// Set how many packed Bs each cache keeps; caches over it drop their least recently used
+/// entries on their next insertion
+pub fn set_capacity(capacity: usize) {
+    CAPACITY.store(capacity, Ordering::Relaxed);
+}
+
+pub fn capacity() -> usize {
+    CAPACITY.load(Ordering::Relaxed)
+}
+
+/// Bytes of packed Bs each cache keeps unless `set_max_bytes` says otherwise
+pub const DEFAULT_MAX_BYTES: usize = 256 << 20;
+
+static MAX_BYTES: AtomicUsize = AtomicUsize::new(DEFAULT_MAX_BYTES);
+
+/// Set how many bytes of packed Bs each cache keeps; caches over it drop their least
+/// recently used entries on their next insertion
+pub fn set_max_bytes(max_bytes: usize) {
+    MAX_BYTES.store(max_bytes, Ordering::Relaxed);
+}
+
+pub fn max_bytes() -> usize {
+    MAX_BYTES.load(Ordering::Relaxed)
+}
+
+/// Bytes a packed B holds, which count against its cache's `max_bytes`
+pub(crate) trait Footprint {
+    fn bytes(&self) -> usize;
+}
+
+impl Footprint for AlignedBufferF32 {
+    fn bytes(&self) -> usize {
+        self.len * std::mem::size_of::<f32>()
+    }
+}
+
+impl Footprint for AlignedBufferI8 {
+    fn bytes(&self) -> usize {
+        self.len
+    }
+}
+
+impl Footprint for PackedBtI8 {
+    fn bytes(&self) -> usize {
+        self.buf.bytes()
+    }
+}
+
+/// What identifies a B: its shape and a digest of all its elements
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) struct CacheKey {
+    pub rows: usize,
+    pub cols: usize,
+    digest: [u8; 32],
+}
+
+impl CacheKey {
+    /// `b`'s key: the one its request already computed when `b` is the request's B
+    /// (`with_request_b`), else a fresh digest
+    pub(crate) fn of(b: &FlatMatrix) -> Self {
+        let id = RequestB::id(b);
+        REQUEST_B.with(|request| match request.borrow_mut().as_mut() {
+            Some(request) if request.id == id => *request.key.get_or_insert_with(|| Self::digest(b)),
+            _ => Self::digest(b),
+        })
+    }
+
+    pub(crate) fn digest(b: &FlatMatrix) -> Self {
+        // SAFETY: f32 has no padding bytes; the key never leaves the process, so the
+        // native byte order is as good as any
+        let bytes = unsafe { std::slice::from_raw_parts(b.data.as_ptr().cast::<u8>(), std::mem::size_of_val(b.data.as_slice())) };
+        #[cfg(feature = "blake3-hash")]
+        let digest = blake3::hash(bytes).into();
+        #[cfg(not(feature = "blake3-hash"))]
+        let digest = Sha256::digest(bytes).into();
+        Self { rows: b.rows, cols: b.cols, digest }
+    }
+}
+
+/// The B of the request running on a thread, by address, length and shape, with its key
+/// once a lookup computed it
+struct RequestB {
+    id: (usize, usize, usize, usize),
+    key: Option<CacheKey>,
+}
+
+impl RequestB {
+    fn id(b: &FlatMatrix) -> (usize, usize, usize, usize) {
+        (b.data.as_ptr() as usize, b.data.len(), b.rows, b.cols)
+    }
+}
+
+thread_local! {
+    static REQUEST_B: RefCell<Option<RequestB>> = const { RefCell::new(None) };
+}
+
+/// Run `f` with `b` as this thread's request B: the first cache lookup of `b` inside it
+/// digests it and every later one (other kernels, repeated runs) reuses that key. `b` is
+/// borrowed throughout, so its elements cannot change and its allocation cannot pass to
+/// another matrix meanwhile. The previous request B is restored afterwards, even when `f`
+/// panics.
+pub(crate) fn with_request_b<T>(b: &FlatMatrix, f: impl FnOnce() -> T) -> T {
+    struct Reset(Option<RequestB>);
+    impl Drop for Reset {
+        fn drop(&mut self) {
+            REQUEST_B.with(|request| *request.borrow_mut() = self.0.take());
+        }
+    }
+    let _reset = Reset(REQUEST_B.with(|request| request.borrow_mut().replace(RequestB { id: RequestB::id(b), key: None })));
+    f()
+}
+
+/// The packed-B caches one server, or the whole process, shares
+pub struct CacheContext {
+    #[cfg(feature = "fp16")]
+    pub(crate) fp16: PackedBCache<Fp16Rounding, AlignedBufferF32>,
+    pub(crate) bf16: PackedBCache<(), AlignedBufferF32>,
+    pub(crate) int8: PackedBCache<(), PackedBtI8>,
+    pub(crate) fp32: PackedBCache<(), AlignedBufferF32>,
+}
+
+static GLOBAL: CacheContext = CacheContext::new();
+
+impl CacheContext {
+    /// Empty caches of the process-wide capacity and byte budget
+    pub const fn new() -> Self {
+        Self::with_limits(None, None)
+    }
+
+    /// Empty caches keeping `capacity` packed Bs each, whatever `set_capacity` says
+    pub const fn with_capacity(capacity: usize) -> Self {
+        Self::with_limits(Some(capacity), None)
+    }
+
+    /// Empty caches keeping at most `capacity` packed Bs and `max_bytes` bytes of them
+    /// each; a None limit follows the process-wide one
+    pub const fn with_limits(capacity: Option<usize>, max_bytes: Option<usize>) -> Self {
+        Self {
+            #[cfg(feature = "fp16")]
+            fp16: PackedBCache::new("fp16", capacity, max_bytes),
+            bf16: PackedBCache::new("bf16", capacity, max_bytes),
+            int8: PackedBCache::new("int8", capacity, max_bytes),
+            fp32: PackedBCache::new("fp32", capacity, max_bytes),
+        }
+    }
+
+    /// The context of runs that name none
+    pub fn global() -> &'static Self {
+        &GLOBAL
+    }
+
+    /// Drop every entry, returning how many packed Bs there were
+    pub fn clear(&self) -> usize {
+        #[cfg(feature = "fp16")]
+        let fp16 = self.fp16.clear();
+        #[cfg(not(feature = "fp16"))]
+        let fp16 = 0;
+        fp16 + self.bf16.clear() + self.int8.clear() + self.fp32.clear()
+    }
+
+    /// Counters of the cache a built-in kernel packs a row-major B through
+    pub(crate) fn stats_for(&self, kernel: &str) -> Option<CacheStats> {
+        match kernel {
+            #[cfg(feature = "fp16")]
+            "fp16_16x16" => Some(self.fp16.stats()),
+            "bf16_16x16" => Some(self.bf16.stats()),
+            "int8_16x16" | "int8_rowwise_16x16" | "int8_asym_16x16" => Some(self.int8.stats()),
+            "fp32_blocked" => Some(self.fp32.stats()),
+            _ => None,
+        }
+    }
+}
+
+impl Default for CacheContext {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl std::fmt::Debug for CacheContext {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("CacheContext").finish_non_exhaustive()
+    }
+}
+
+struct Entry<T, V> {
+    key: CacheKey,
+    /// The packing variant (fp16's rounding mode); `()` where there is one
+    tag: T,
+    value: Arc<V>,
+}
+
+/// Packed Bs, most recently used first
+pub(crate) struct PackedBCache<T, V> {
+    name: &'static str,
+    /// Overrides the process-wide capacity
+    capacity: Option<usize>,
+    /// Overrides the process-wide byte budget
+    max_bytes: Option<usize>,
+    state: Mutex<CacheState<T, V>>,
+}
+
+struct CacheState<T, V> {
+    entries: Vec<Entry<T, V>>,
+    /// `Footprint::bytes` of every entry
+    bytes: usize,
+    hits: u64,
+    misses: u64,
+    evictions: u64,
+}
+
+impl<T: Copy + PartialEq, V: Footprint> PackedBCache<T, V> {
+    pub(crate) const fn new(name: &'static str, capacity: Option<usize>, max_bytes: Option<usize>) -> Self {
+        let state = CacheState { entries: Vec::new(), bytes: 0, hits: 0, misses: 0, evictions: 0 };
+        Self { name, capacity, max_bytes, state: Mutex::new(state) }
+    }
+
+    fn capacity(&self) -> usize {
+        self.capacity.unwrap_or_else(capacity)
+    }
+
+    fn max_bytes(&self) -> usize {
+        self.max_bytes.unwrap_or_else(max_bytes)
+    }
+
+    /// The entry packed from `b` under `tag`, or `pack()`'s, kept as the most recently used;
+    /// also whether it was a hit. `pack` runs without the lock.
+    pub(crate) fn get_or_pack(&self, b: &FlatMatrix, tag: T, pack: impl FnOnce() -> V) -> (Arc<V>, bool) {
+        self.get_or_pack_keyed(CacheKey::of(b), tag, pack)
+    }
+
+    /// `get_or_pack` for a B whose key the caller already has, e.g. to digest B outside a
+    /// timed region
+    pub(crate) fn get_or_pack_keyed(&self, key: CacheKey, tag: T, pack: impl FnOnce() -> V) -> (Arc<V>, bool) {
+        {
+            let mut state = self.state.lock().unwrap();
+            let found = state.entries.iter().position(|entry| {
+                entry.tag == tag && (entry.key == key || crate::stale_hit(&entry.key, &key))
+            });
+            if let Some(index) = found {
+                state.hits += 1;
+                let entry = state.entries.remove(index);
+                let value = entry.value.clone();
+                state.entries.insert(0, entry);
+                return (value, true);
+            }
+            state.misses += 1;
+        }
+        let value = Arc::new(pack());
+        let (capacity, max_bytes, bytes) = (self.capacity(), self.max_bytes(), value.bytes());
+        let mut state = self.state.lock().unwrap();
+        if capacity > 0 && bytes <= max_bytes && !state.entries.iter().any(|entry| entry.tag == tag && entry.key == key) {
+            state.entries.insert(0, Entry { key, tag, value: value.clone() });
+            state.bytes += bytes;
+        }
+        while state.entries.len() > capacity || state.bytes > max_bytes {
+            let Some(evicted) = state.entries.pop() else { break };
+            state.bytes -= evicted.value.bytes();
+            state.evictions += 1;
+        }
+        (value, false)
+    }
+
+    /// Drop every entry, returning how many there were; the counters keep running
+    pub(crate) fn clear(&self) -> usize {
+        let mut state = self.state.lock().unwrap();
+        let cleared = state.entries.len();
+        state.entries.clear();
+        state.bytes = 0;
+        cleared
+    }
+
+    pub(crate) fn stats(&self) -> CacheStats {
+        let state = self.state.lock().unwrap();
+        CacheStats {
+            cache: self.name.to_string(),
+            hits: state.hits,
+            misses: state.misses,
+            evictions: state.evictions,
+            entries: state.entries.len(),
+            capacity: self.capacity(),
+            bytes: state.bytes,
+            max_bytes: self.max_bytes(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    impl Footprint for f32 {
+        fn bytes(&self) -> usize {
+            *self as usize
+        }
+    }
+
+    impl Footprint for usize {
+        fn bytes(&self) -> usize {
+            0
+        }
+    }
+
+    impl Footprint for u8 {
+        fn bytes(&self) -> usize {
+            0
+        }
+    }
+
+    fn matrix(fill: f32) -> FlatMatrix {
+        FlatMatrix { data: (0..640).map(|i| fill + i as f32).collect(), rows: 40, cols: 16 }
+    }
+
+    #[test]
+    fn test_lru_eviction_order() {
+        let cache: PackedBCache<(), f32> = PackedBCache::new("test", Some(2), Some(usize::MAX));
+        let (b1, b2, b3) = (matrix(1.0), matrix(2.0), matrix(3.0));
+        let get = |b: &FlatMatrix| {
+            let (value, hit) = cache.get_or_pack(b, (), || b.data[0]);
+            (*value, hit, cache.state.lock().unwrap().entries.iter().map(|e| *e.value).collect::<Vec<_>>())
+        };
+        assert_eq!(get(&b1), (1.0, false, vec![1.0]));
+        assert_eq!(get(&b2), (2.0, false, vec![2.0, 1.0]));
+        // Using b1 makes b2 the least recently used, so b3 evicts it
+        assert_eq!(get(&b1), (1.0, true, vec![1.0, 2.0]));
+        assert_eq!(get(&b3), (3.0, false, vec![3.0, 1.0]));
+        assert_eq!(get(&b2), (2.0, false, vec![2.0, 3.0]));
+        // Interleaving the two cached Bs keeps hitting
+        for b in [&b3, &b2, &b3, &b2] {
+            assert!(get(b).1);
+        }
+        let stats = cache.stats();
+        assert_eq!((stats.hits, stats.misses, stats.evictions, stats.entries, stats.capacity), (5, 4, 2, 2, 2));
+
+        let off: PackedBCache<(), f32> = PackedBCache::new("test", Some(0), Some(usize::MAX));
+        assert!(!off.get_or_pack(&b1, (), || 1.0).1 && !off.get_or_pack(&b1, (), || 1.0).1);
+        assert_eq!(off.stats().entries, 0);
+    }
+
+    #[test]
+    fn test_byte_budget_evicts_least_recently_used() {
+        // Each value's footprint is the value itself
+        let cache: PackedBCache<(), f32> = PackedBCache::new("test", Some(8), Some(100));
+        let (b1, b2, b3, b4) = (matrix(1.0), matrix(2.0), matrix(3.0), matrix(4.0));
+        let bytes = || cache.state.lock().unwrap().bytes;
+        cache.get_or_pack(&b1, (), || 40.0);
+        cache.get_or_pack(&b2, (), || 50.0);
+        assert_eq!(bytes(), 90);
+        // 30 more only fit without b1, the least recently used
+        cache.get_or_pack(&b3, (), || 30.0);
+        assert_eq!((bytes(), cache.stats().entries, cache.stats().evictions), (80, 2, 1));
+        assert!(cache.get_or_pack(&b2, (), || 0.0).1 && !cache.get_or_pack(&b1, (), || 0.0).1);
+        // Larger than the whole budget: used, not kept, nothing else evicted
+        let (value, hit) = cache.get_or_pack(&b4, (), || 150.0);
+        assert_eq!((*value, hit), (150.0, false));
+        let stats = cache.stats();
+        assert_eq!((stats.entries, stats.bytes, stats.max_bytes), (3, 80, 100));
+        assert_eq!(cache.clear(), 3);
+        assert_eq!(bytes(), 0);
+    }
+
+    #[test]
+    fn test_matches_content_not_address() {
+        let cache: PackedBCache<u8, usize> = PackedBCache::new("test", Some(4), Some(usize::MAX));
+        let b = matrix(0.0);
+        let mut packs = 0;
+        let mut pack = || {
+            packs += 1;
+            packs
+        };
+        assert!(!cache.get_or_pack(&b, 0, &mut pack).1);
+        // An equal B elsewhere hits; another tag, or one changed element, does not
+        assert_eq!(*cache.get_or_pack(&b.clone(), 0, &mut pack).0, 1);
+        assert!(!cache.get_or_pack(&b, 1, &mut pack).1);
+        let mut changed = b.clone();
+        changed.data[37] += 1.0;
+        assert_ne!(CacheKey::of(&changed), CacheKey::of(&b));
+        assert_eq!(*cache.get_or_pack(&changed, 0, &mut pack).0, 3);
+        assert_eq!(cache.clear(), 3);
+        assert!(!cache.get_or_pack(&b, 0, &mut pack).1);
+    }
+
+    #[test]
+    fn test_request_b_is_digested_once() {
+        let cache: PackedBCache<u8, u8> = PackedBCache::new("test", Some(4), Some(usize::MAX));
+        let (b, copy) = (matrix(0.0), matrix(0.0));
+        let key = || REQUEST_B.with(|request| request.borrow().as_ref().map(|request| request.key));
+        with_request_b(&b, || {
+            assert_eq!(key(), Some(None));
+            // Another matrix is digested for itself, and leaves the request's key alone
+            assert_eq!(CacheKey::of(&copy), CacheKey::digest(&b));
+            assert_eq!(key(), Some(None));
+            cache.get_or_pack(&b, 0, || 0);
+            assert_eq!(key(), Some(Some(CacheKey::digest(&b))));
+            assert!(!cache.get_or_pack(&b, 1, || 1).1 && cache.get_or_pack(&copy, 1, || 2).1);
+        });
+        assert_eq!(key(), None);
+    }
+
+    #[test]
+    fn test_packing_one_b_does_not_hold_up_another() {
+        let cache: PackedBCache<(), f32> = PackedBCache::new("test", Some(4), Some(usize::MAX));
+        let (b1, b2) = (matrix(1.0), matrix(2.0));
+        let (packed_b2, b2_done) = std::sync::mpsc::channel();
+        std::thread::scope(|scope| {
+            // b1's packing waits until b2 was looked up, packed and inserted meanwhile
+            let slow = scope.spawn(|| {
+                cache.get_or_pack(&b1, (), move || {
+                    b2_done.recv_timeout(std::time::Duration::from_secs(10)).expect("b2 was held up by b1's packing");
+                    1.0
+                })
+            });
+            while cache.stats().misses == 0 {
+                std::thread::yield_now();
+            }
+            assert_eq!(*cache.get_or_pack(&b2, (), || 2.0).0, 2.0);
+            packed_b2.send(()).unwrap();
+            assert_eq!(*slow.join().unwrap().0, 1.0);
+        });
+        assert!(cache.get_or_pack(&b1, (), || 0.0).1 && cache.get_or_pack(&b2, (), || 0.0).1);
+    }
+}
@@ -0,0 +1,450 @@
+//! Directory batch mode (`matmul-solver batch`): compute every `*.json` input in a directory.
+//!
+//! Inputs run one after another in file-name order, each parsed, computed and written to
+//! `<output_dir>/<stem>.json` on its own. An input that cannot be read, parsed or computed
+//! becomes a record with an error code rather than stopping the batch, so the summary
+//! always has one record per input.
+//!
+//! Besides the records, the summary carries percentiles of kernel and end-to-end time
+//! over the inputs that succeeded, computed by `histogram` as /stats computes its own. It
+//! is written as `summary.json`, or as `summary.csv` for spreadsheets: one row per record,
+//! then one `aggregate` row per percentile with the same columns.
+
+use crate::clock::Clock;
+use crate::histogram::{self, Bucket, Percentiles};
+use crate::host;
+use crate::{add_timing_breakdown, compute_workload_with_options, numbers, ComputeOptions, SolverError};
+use serde::{Deserialize, Serialize};
+use std::io;
+use std::path::{Path, PathBuf};
+
+/// `BatchRecord::error_code` of an input that could not be read or whose output could not
+/// be written
+pub const IO_ERROR: &str = "io";
+/// `BatchRecord::error_code` of an input that is not a valid input document
+pub const PARSE_ERROR: &str = "parse";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SummaryFormat {
+    Json,
+    Csv,
+}
+
+impl SummaryFormat {
+    pub fn parse(format: &str) -> Result<Self, SolverError> {
+        match format {
+            "json" => Ok(SummaryFormat::Json),
+            "csv" => Ok(SummaryFormat::Csv),
+            other => Err(SolverError::InvalidInput(format!("Unknown summary format '{}' (expected json or csv)", other))),
+        }
+    }
+
+    pub fn file_name(self) -> &'static str {
+        match self {
+            SummaryFormat::Json => "summary.json",
+            SummaryFormat::Csv => "summary.csv",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum RecordStatus {
+    Ok,
+    Error,
+}
+
+impl RecordStatus {
+    fn as_str(self) -> &'static str {
+        match self {
+            RecordStatus::Ok => "ok",
+            RecordStatus::Error => "error",
+        }
+    }
+}
+
+/// One input of the batch
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct BatchRecord {
+    /// File name within the input directory
+    pub file: String,
+    pub status: RecordStatus,
+    /// `SolverError::code` of a failed computation, or `IO_ERROR` / `PARSE_ERROR`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub error_code: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+    /// Known once the input parsed
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub precision: Option<String>,
+    /// As computed; for an input that failed to compute, as supplied
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub matrix_a_shape: Option<(usize, usize)>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub matrix_b_shape: Option<(usize, usize)>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub kernel: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub kernel_ms: Option<f64>,
+    /// Reading the input through writing its output
+    pub total_ms: f64,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub result_hash: Option<String>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct BatchSummary {
+    pub input_dir: String,
+    pub total: usize,
+    pub ok: usize,
+    pub failed: usize,
+    /// Over the inputs that succeeded; absent when none did
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub kernel_ms: Option<Percentiles>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub total_ms: Option<Percentiles>,
+    /// Power-of-two buckets of `total_ms` over the inputs that succeeded
+    pub total_ms_histogram: Vec<Bucket>,
+    pub wall_ms: f64,
+    pub records: Vec<BatchRecord>,
+}
+
+/// Columns of the CSV summary, in order
+pub const CSV_COLUMNS: [&str; 11] = [
+    "file",
+    "status",
+    "error_code",
+    "precision",
+    "matrix_a_shape",
+    "matrix_b_shape",
+    "kernel",
+    "kernel_ms",
+    "total_ms",
+    "result_hash",
+    "error",
+];
+
+impl BatchSummary {
+    /// Human-readable summary for the console: failing inputs, then totals and percentiles
+    pub fn to_table(&self) -> String {
+        let mut out = String::new();
+        for record in self.records.iter().filter(|r| r.status == RecordStatus::Error) {
+            out.push_str(&format!(
+                "FAIL {} [{}]: {}\n",
+                record.file,
+                record.error_code.as_deref().unwrap_or("-"),
+                record.error.as_deref().unwrap_or("")
+            ));
+        }
+        out.push_str(&format!(
+            "Processed {} inputs from {}: {} ok, {} failed in {:.1} ms\n",
+            self.total, self.input_dir, self.ok, self.failed, self.wall_ms
+        ));
+        for (name, percentiles) in [("kernel", &self.kernel_ms), ("total", &self.total_ms)] {
+            if let Some(p) = percentiles {
+                out.push_str(&format!(
+                    "  {:<6} ms  p50 {:>10.3}  p90 {:>10.3}  p99 {:>10.3}  max {:>10.3}\n",
+                    name, p.p50, p.p90, p.p99, p.max
+                ));
+            }
+        }
+        out
+    }
+
+    /// The CSV summary: a `CSV_COLUMNS` header, one row per record, then `aggregate` rows
+    /// named p50, p90, p99 and max carrying the kernel and total percentiles
+    pub fn to_csv(&self) -> String {
+        let mut out = CSV_COLUMNS.join(",");
+        out.push('\n');
+        let shape = |s: Option<(usize, usize)>| s.map(|(r, c)| format!("{}x{}", r, c)).unwrap_or_default();
+        let number = |v: Option<f64>| v.map(|v| v.to_string()).unwrap_or_default();
+        for r in &self.records {
+            let row = [
+                r.file.clone(),
+                r.status.as_str().to_string(),
+                r.error_code.clone().unwrap_or_default(),
+                r.precision.clone().unwrap_or_default(),
+                shape(r.matrix_a_shape),
+                shape(r.matrix_b_shape),
+                r.kernel.clone().unwrap_or_default(),
+                number(r.kernel_ms),
+                r.total_ms.to_string(),
+                r.result_hash.clone().unwrap_or_default(),
+                r.error.clone().unwrap_or_default(),
+            ];
+            push_csv_row(&mut out, &row);
+        }
+        for (i, name) in ["p50", "p90", "p99", "max"].into_iter().enumerate() {
+            let pick = |p: &Percentiles| [p.p50, p.p90, p.p99, p.max][i];
+            let mut row: [String; 11] = Default::default();
+            row[0] = name.to_string();
+            row[1] = "aggregate".to_string();
+            row[7] = number(self.kernel_ms.as_ref().map(pick));
+            row[8] = number(self.total_ms.as_ref().map(pick));
+            push_csv_row(&mut out, &row);
+        }
+        out
+    }
+
+    /// Write the summary into `dir` as `format.file_name()`; returns the path written
+    pub fn write(&self, dir: &Path, format: SummaryFormat) -> io::Result<PathBuf> {
+        let path = dir.join(format.file_name());
+        let contents = match format {
+            SummaryFormat::Json => serde_json::to_string_pretty(self).map_err(io::Error::other)?,
+            SummaryFormat::Csv => self.to_csv(),
+        };
+        host::write(&path, contents)?;
+        Ok(path)
+    }
+}
+
+fn push_csv_row(out: &mut String, fields: &[String]) {
+    let quoted: Vec<String> = fields
+        .iter()
+        .map(|f| {
+            if f.contains([',', '"', '\n', '\r']) {
+                format!("\"{}\"", f.replace('"', "\"\""))
+            } else {
+                f.clone()
+            }
+        })
+        .collect();
+    out.push_str(&quoted.join(","));
+    out.push('\n');
+}
+
+/// Compute every `*.json` file directly in `input_dir`, writing outputs into `output_dir`
+/// (created if missing). Fails only when a directory cannot be read or created.
+pub fn run_directory(
+    input_dir: &Path,
+    output_dir: &Path,
+    options: &ComputeOptions,
+    strict_numbers: bool,
+    clock: &dyn Clock,
+) -> io::Result<BatchSummary> {
+    // Listed before anything is written, so outputs in the same directory are not picked up
+    let mut inputs = host::files_in(input_dir)?;
+    inputs.retain(|p| p.extension().is_some_and(|e| e == "json"));
+    inputs.sort();
+    host::create_dir_all(output_dir)?;
+
+    let batch_start = clock.now();
+    let mut records = Vec::with_capacity(inputs.len());
+    for path in &inputs {
+        let start = clock.now();
+        let mut record = run_one(path, output_dir, options, strict_numbers, clock);
+        record.total_ms = clock.elapsed_since(start).as_secs_f64() * 1000.0;
+        records.push(record);
+    }
+
+    let ok: Vec<&BatchRecord> = records.iter().filter(|r| r.status == RecordStatus::Ok).collect();
+    let kernel_ms: Vec<f64> = ok.iter().filter_map(|r| r.kernel_ms).collect();
+    let total_ms: Vec<f64> = ok.iter().map(|r| r.total_ms).collect();
+    Ok(BatchSummary {
+        input_dir: input_dir.display().to_string(),
+        total: records.len(),
+        ok: ok.len(),
+        failed: records.len() - ok.len(),
+        kernel_ms: Percentiles::of(&kernel_ms),
+        total_ms: Percentiles::of(&total_ms),
+        total_ms_histogram: histogram::log2_histogram(&total_ms),
+        wall_ms: clock.elapsed_since(batch_start).as_secs_f64() * 1000.0,
+        records,
+    })
+}
+
+/// One input's record, `total_ms` left for the caller
+fn run_one(path: &Path, output_dir: &Path, options: &ComputeOptions, strict_numbers: bool, clock: &dyn Clock) -> BatchRecord {
+    let file = path.file_name().map(|f| f.to_string_lossy().into_owned()).unwrap_or_default();
+    let mut record = BatchRecord {
+        file,
+        status: RecordStatus::Error,
+        error_code: None,
+        error: None,
+        precision: None,
+        matrix_a_shape: None,
+        matrix_b_shape: None,
+        kernel: None,
+        kernel_ms: None,
+        total_ms: 0.0,
+        result_hash: None,
+    };
+    let fail = |mut record: BatchRecord, code: &str, error: String| {
+        record.error_code = Some(code.to_string());
+        record.error = Some(error);
+        record
+    };
+
+    let parse_start = clock.now();
+    let json = match host::read_to_string(path) {
+        Ok(json) => json,
+        Err(e) => return fail(record, IO_ERROR, e.to_string()),
+    };
+    let (input, lossy) = match numbers::parse_input_json(&json, strict_numbers) {
+        Ok(parsed) => parsed,
+        Err(e) => return fail(record, PARSE_ERROR, e.to_string()),
+    };
+    let parse_ms = clock.elapsed_since(parse_start).as_secs_f64() * 1000.0;
+    record.precision = Some(input.precision.clone());
+    record.matrix_a_shape = Some((input.matrix_a.rows, input.matrix_a.cols));
+    record.matrix_b_shape = Some(match &input.matrix_b_sparse {
+        Some(b) => (b.rows, b.cols),
+        None => (input.matrix_b.rows, input.matrix_b.cols),
+    });
+
+    let mut output = match compute_workload_with_options(input, options) {
+        Ok(output) => output,
+        Err(e) => return fail(record, e.code(), e.to_string()),
+    };
+    output.metadata.lossy_parse_count = Some(lossy);
+    if lossy > 0 {
+        output.warnings.push(crate::warnings::SolverWarning::lossy_parse(lossy));
+    }
+    let output = add_timing_breakdown(output, Some(parse_ms), None);
+    record.matrix_a_shape = Some(output.metadata.matrix_a_shape);
+    record.matrix_b_shape = Some(output.metadata.matrix_b_shape);
+    record.kernel = output.metadata.kernel.clone();
+    record.kernel_ms = output.metrics.kernel_time_ms;
+    record.precision = Some(output.metadata.precision.clone());
+    record.result_hash = Some(output.result_hash.clone());
+
+    let stem = path.file_stem().map(|s| s.to_string_lossy().into_owned()).unwrap_or_default();
+    let written = serde_json::to_string_pretty(&output)
+        .map_err(io::Error::other)
+        .and_then(|json| host::write(output_dir.join(format!("{}.json", stem)), json));
+    match written {
+        Ok(()) => {
+            record.status = RecordStatus::Ok;
+            record
+        }
+        Err(e) => fail(record, IO_ERROR, e.to_string()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{generate_matrices_from_seed, SystemClock};
+    use std::fs;
+
+    fn write_input(dir: &Path, name: &str, precision: &str, a: (usize, usize), b: (usize, usize)) {
+        let (matrix_a, matrix_b) = generate_matrices_from_seed(name.as_bytes(), a.0, a.1, b.0, b.1);
+        let rows = |m: &crate::FlatMatrix| -> Vec<Vec<f32>> { m.data.chunks(m.cols).map(<[f32]>::to_vec).collect() };
+        let json = serde_json::json!({ "matrix_a": rows(&matrix_a), "matrix_b": rows(&matrix_b), "precision": precision });
+        fs::write(dir.join(name), json.to_string()).unwrap();
+    }
+
+    /// Splits CSV as `push_csv_row` writes it: quoted fields may hold commas and quotes
+    fn parse_csv(csv: &str) -> Vec<Vec<String>> {
+        csv.lines()
+            .map(|line| {
+                let mut fields = vec![String::new()];
+                let (mut quoted, mut chars) = (false, line.chars().peekable());
+                while let Some(c) = chars.next() {
+                    match c {
+                        '"' if quoted && chars.peek() == Some(&'"') => {
+                            chars.next();
+                            fields.last_mut().unwrap().push('"');
+                        }
+                        '"' => quoted = !quoted,
+                        ',' if !quoted => fields.push(String::new()),
+                        c => fields.last_mut().unwrap().push(c),
+                    }
+                }
+                fields
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_batch_summaries_agree() {
+        let inputs = tempfile::tempdir().unwrap();
+        let outputs = tempfile::tempdir().unwrap();
+        write_input(inputs.path(), "a_fp32.json", "fp32", (16, 64), (64, 16));
+        write_input(inputs.path(), "b_u8i8.json", "u8i8", (8, 32), (32, 4));
+        write_input(inputs.path(), "c_int8.json", "int8", (4, 4), (4, 4));
+        // k disagrees between A and B
+        write_input(inputs.path(), "d_mismatch.json", "fp32", (4, 8), (5, 4));
+        fs::write(inputs.path().join("notes.txt"), "not an input").unwrap();
+
+        let summary = run_directory(inputs.path(), outputs.path(), &ComputeOptions::default(), false, &SystemClock).unwrap();
+        assert_eq!((summary.total, summary.ok, summary.failed), (4, 3, 1));
+        let files: Vec<&str> = summary.records.iter().map(|r| r.file.as_str()).collect();
+        assert_eq!(files, ["a_fp32.json", "b_u8i8.json", "c_int8.json", "d_mismatch.json"]);
+
+        let failed = &summary.records[3];
+        assert_eq!(failed.status, RecordStatus::Error);
+        assert_eq!(failed.error_code.as_deref(), Some("shape_mismatch"));
+        assert_eq!((failed.matrix_a_shape, failed.matrix_b_shape), (Some((4, 8)), Some((5, 4))));
+        assert!(failed.result_hash.is_none() && failed.kernel_ms.is_none());
+        assert!(!outputs.path().join("d_mismatch.json").exists());
+
+        for record in &summary.records[..3] {
+            let written: crate::types::Output =
+                serde_json::from_str(&fs::read_to_string(outputs.path().join(&record.file)).unwrap()).unwrap();
+            assert_eq!(record.result_hash.as_ref(), Some(&written.result_hash));
+            assert_eq!(record.kernel, written.metadata.kernel);
+            assert!(record.kernel_ms.unwrap() <= record.total_ms);
+        }
+        assert_eq!(summary.records[1].matrix_a_shape, Some((8, 32)));
+        assert_eq!(summary.total_ms.unwrap().count, 3);
+        assert_eq!(summary.total_ms_histogram.iter().map(|b| b.count).sum::<usize>(), 3);
+
+        // JSON: reloads with the same counts and percentiles (up to float formatting)
+        let json_path = summary.write(outputs.path(), SummaryFormat::Json).unwrap();
+        let reloaded: BatchSummary = serde_json::from_str(&fs::read_to_string(json_path).unwrap()).unwrap();
+        assert_eq!((reloaded.total, reloaded.ok, reloaded.failed), (4, 3, 1));
+        assert_eq!(reloaded.records.len(), 4);
+        let close = |a: f64, b: f64| (a - b).abs() <= 1e-9 * a.abs().max(1.0);
+        let (kernel, total) = (reloaded.kernel_ms.unwrap(), reloaded.total_ms.unwrap());
+
+        // CSV: a row per record and per aggregate, agreeing with the JSON
+        let csv_path = summary.write(outputs.path(), SummaryFormat::Csv).unwrap();
+        let rows = parse_csv(&fs::read_to_string(csv_path).unwrap());
+        assert_eq!(rows[0], CSV_COLUMNS);
+        assert!(rows.iter().all(|r| r.len() == CSV_COLUMNS.len()));
+        let status = |s: &str| rows[1..].iter().filter(|r| r[1] == s).count();
+        assert_eq!((status("ok"), status("error"), status("aggregate")), (reloaded.ok, reloaded.failed, 4));
+        let error_row = rows.iter().find(|r| r[1] == "error").unwrap();
+        assert_eq!((error_row[0].as_str(), error_row[2].as_str()), ("d_mismatch.json", "shape_mismatch"));
+        assert_eq!(error_row[10], failed.error.clone().unwrap());
+
+        let column = |i: usize| -> Vec<f64> { rows[1..].iter().filter(|r| r[1] == "ok").map(|r| r[i].parse().unwrap()).collect() };
+        let aggregate = |name: &str, i: usize| -> f64 {
+            rows.iter().find(|r| r[1] == "aggregate" && r[0] == name).unwrap()[i].parse().unwrap()
+        };
+        for (i, from_json) in [(7, kernel), (8, total)] {
+            let from_rows = Percentiles::of(&column(i)).unwrap();
+            for (name, json_value, row_value) in [
+                ("p50", from_json.p50, from_rows.p50),
+                ("p90", from_json.p90, from_rows.p90),
+                ("p99", from_json.p99, from_rows.p99),
+                ("max", from_json.max, from_rows.max),
+            ] {
+                assert_eq!(aggregate(name, i), row_value, "{} column {}", name, i);
+                assert!(close(aggregate(name, i), json_value), "{} column {}", name, i);
+            }
+        }
+    }
+
+    #[test]
+    fn test_unreadable_inputs_become_records() {
+        let inputs = tempfile::tempdir().unwrap();
+        fs::write(inputs.path().join("broken.json"), "{\"matrix_a\": [[1, 2]").unwrap();
+        let summary = run_directory(inputs.path(), inputs.path(), &ComputeOptions::default(), false, &SystemClock).unwrap();
+        assert_eq!((summary.total, summary.failed), (1, 1));
+        assert_eq!(summary.records[0].error_code.as_deref(), Some(PARSE_ERROR));
+        assert!(summary.records[0].precision.is_none());
+        assert!(summary.kernel_ms.is_none() && summary.total_ms_histogram.is_empty());
+        assert!(summary.to_csv().lines().skip(1).any(|row| row == "p50,aggregate,,,,,,,,,"));
+
+        assert!(SummaryFormat::parse("xlsx").is_err());
+        assert_eq!(SummaryFormat::parse("csv").unwrap().file_name(), "summary.csv");
+
+        // Its IO goes through host, like the library's other opt-in IO
+        let listed = std::panic::catch_unwind(|| {
+            host::forbid_io(|| run_directory(inputs.path(), inputs.path(), &ComputeOptions::default(), false, &SystemClock))
+        });
+        assert!(listed.unwrap_err().downcast::<String>().unwrap().starts_with("read_dir"));
+    }
+}
@@ -0,0 +1,245 @@
+//! Batched matmul (`workload_type: "batched_matmul"`).
+//!
+//! Multi-head style workloads multiply one B by many A's. `Input::batch` says how many m×k
+//! items matrix_a stacks row-wise (batch·m × k); each item runs through the regular matmul
+//! pipeline against the same borrowed B, so every item gets the same kernel and the
+//! packed-B caches, keyed by B's buffer, pack B for the first item and hit for the rest. A
+//! B supplied transposed is transposed back once, up front, for the same reason.
+//!
+//! `result_matrix` stacks the item results in order (batch·m × n) and `batch_hashes` holds
+//! each item's `result_hash`. The aggregate `result_hash` is the hash of the whole stack,
+//! i.e. over every item's hash preimage in order, so audits, deltas and
+//! `verify_with_metadata` (which recomputes item by item) treat it like any other result.
+//! `metrics.kernel_time_ms` is the total kernel time and `metrics.batch_kernel_time_ms`
+//! the time of each item.
+
+use crate::kernels::KernelResult;
+use crate::preflight::WorkloadShape;
+use crate::{types, warnings, BLayout, ComputeOptions, FlatMatrix, MatmulOperands, Redundancy, ResultDtype, SolverError};
+use std::sync::Arc;
+
+/// `compute_workload` for `workload_type: "batched_matmul"`
+pub(crate) fn compute_batched(input: &types::Input, options: &ComputeOptions) -> Result<types::Output, SolverError> {
+    let a = &input.matrix_a;
+    let (batch, result_dtype, b_layout) = check(&WorkloadShape::of(input), options)?;
+    let row_major_b;
+    let b = match b_layout {
+        BLayout::RowMajor => &input.matrix_b,
+        BLayout::Transposed => {
+            row_major_b = crate::transpose(&input.matrix_b);
+            &row_major_b
+        }
+    };
+
+    // Each item's hash goes into batch_hashes
+    let options = &ComputeOptions { defer_result_hash: false, ..options.clone() };
+    let clock = options.clock();
+    let start = clock.now();
+    let mut warnings = warnings::WarningCollector::default();
+    // Every item multiplies the same B, digested once for the caches (a transposed B was
+    // copied, so it is not the request's)
+    let mut outputs = crate::b_cache::with_request_b(b, || {
+        let mut outputs = Vec::with_capacity(batch);
+        for i in 0..batch {
+            let a_item = item(a, i, batch);
+            // Every item raises the same warnings; the first item's are kept
+            let mut repeated = warnings::WarningCollector::default();
+            let collector = if i == 0 { &mut warnings } else { &mut repeated };
+            let operands = MatmulOperands::Matrices(&a_item, b);
+            outputs.push(crate::compute_matmul_internal(operands, &input.precision, result_dtype, &input.metadata, None, options, collector)?);
+            if let Some(deadline) = options.deadline {
+                let spent = clock.elapsed_since(start);
+                if spent > deadline {
+                    return Err(SolverError::DeadlineExceeded(format!(
+                        "{} of {} batch items took {:.3} ms, deadline was {:.3} ms",
+                        i + 1,
+                        batch,
+                        spent.as_secs_f64() * 1000.0,
+                        deadline.as_secs_f64() * 1000.0
+                    )));
+                }
+            }
+        }
+        Ok(outputs)
+    })?;
+
+    let cols = outputs[0].result_matrix.cols;
+    let data = outputs.iter().flat_map(|o| o.result_matrix.data.iter().copied()).collect();
+    let result = FlatMatrix { data, rows: a.rows, cols };
+    let result_f64 = match result_dtype {
+        ResultDtype::F64 => {
+            let items: Option<Vec<&[f64]>> = outputs.iter().map(|o| o.result_f64.as_deref().map(Vec::as_slice)).collect();
+            let values = items.ok_or_else(|| SolverError::Internal("a batch item has no f64 result".to_string()))?.concat();
+            Some(Arc::new(values))
+        }
+        _ => None,
+    };
+    let result_hash = match &result_f64 {
+        Some(values) => crate::compute_hash_f64(values),
+        None => crate::compute_hash_rounded(&result, result_dtype, options.hash_rounding),
+    };
+    let batch_hashes = outputs.iter().map(|o| o.result_hash.clone()).collect();
+    let item_ms: Vec<f64> = outputs.iter().map(|o| o.metrics.kernel_time_ms.unwrap_or(o.metrics.latency_ms)).collect();
+    let total_ms: f64 = item_ms.iter().sum();
+
+    // The first item's output carries everything the items share (kernel, dtype, options)
+    let mut output = outputs.swap_remove(0);
+    output.result_matrix = Arc::new(result);
+    output.result_f64 = result_f64;
+    output.result_hash = result_hash;
+    output.batch_hashes = Some(batch_hashes);
+    output.warnings = warnings.into_vec();
+    let metrics = &mut output.metrics;
+    metrics.latency_ms = total_ms;
+    metrics.kernel_time_ms = Some(total_ms);
+    metrics.batch_kernel_time_ms = Some(item_ms);
+    metrics.ops_per_second = (a.rows * a.cols * b.cols) as f64 / (total_ms / 1000.0);
+    metrics.throughput_ops_per_sec = metrics.ops_per_second;
+    metrics.memory_usage_mb = Some(crate::estimate_memory_usage(a.rows, a.cols, b.rows, b.cols));
+    let metadata = &mut output.metadata;
+    metadata.matrix_a_shape = (a.rows, a.cols);
+    metadata.result_shape = (a.rows, output.result_matrix.cols);
+    metadata.b_layout = (b_layout == BLayout::Transposed).then(|| b_layout.as_str().to_string());
+    metadata.batch = Some(batch);
+    Ok(output)
+}
+
+/// The item count, result dtype and B layout of a batched workload, or why it cannot run
+/// batched. The items then run the matmul checks themselves.
+pub(crate) fn check(workload: &WorkloadShape, options: &ComputeOptions) -> Result<(usize, ResultDtype, BLayout), SolverError> {
+    let batch = items(workload.batch, workload.shapes.matrix_a.0)?;
+    let result_dtype = ResultDtype::for_hash_input(&workload.precision, workload.hash_input.as_deref())?;
+    let shapes = &workload.shapes;
+    let unsupported = [
+        ("a_layout", shapes.a_layout.as_deref().is_some_and(|l| l != "row_major")),
+        ("alpha, beta and matrix_c", workload.alpha.is_some() || workload.beta.is_some() || workload.matrix_c.is_some()),
+        ("redundancy", options.redundancy != Redundancy::Single),
+        ("tile_trace", options.tile_trace),
+        ("error_metrics", options.error_metrics),
+        ("matrix_b_sparse", shapes.matrix_b_sparse.is_some()),
+        // The items' i32 accumulators do not survive into their outputs to be stacked
+        ("hash_input i32le", result_dtype == ResultDtype::I32),
+    ];
+    if let Some((name, _)) = unsupported.iter().find(|(_, set)| *set) {
+        return Err(SolverError::InvalidInput(format!("{} is not supported for the batched_matmul workload", name)));
+    }
+    Ok((batch, result_dtype, BLayout::parse(shapes.b_layout.as_deref())?))
+}
+
+/// The number of items `batch` splits `rows` stacked rows into
+pub(crate) fn items(batch: Option<usize>, rows: usize) -> Result<usize, SolverError> {
+    match batch {
+        Some(batch) if batch > 0 && rows.is_multiple_of(batch) => Ok(batch),
+        Some(batch) => Err(SolverError::InvalidInput(format!(
+            "batch {} does not split matrix_a's {} rows into equal items",
+            batch, rows
+        ))),
+        None => Err(SolverError::InvalidInput(
+            "batched_matmul needs batch, the number of items stacked in matrix_a".to_string(),
+        )),
+    }
+}
+
+/// Item `i` of the `batch` items stacked in `a`
+pub(crate) fn item(a: &FlatMatrix, i: usize, batch: usize) -> FlatMatrix {
+    let (rows, cols) = (a.rows / batch, a.cols);
+    FlatMatrix { data: a.data[i * rows * cols..(i + 1) * rows * cols].to_vec(), rows, cols }
+}
+
+/// The kernel results of a batch's items as one result of the stacked shape
+pub(crate) fn stack(mut runs: Vec<KernelResult>) -> KernelResult {
+    if runs.len() == 1 {
+        return runs.pop().unwrap();
+    }
+    let result = FlatMatrix {
+        data: runs.iter().flat_map(|r| r.result.data.iter().copied()).collect(),
+        rows: runs.iter().map(|r| r.result.rows).sum(),
+        cols: runs[0].result.cols,
+    };
+    let accumulators: Option<Vec<&[i32]>> = runs.iter().map(|r| r.accumulators.as_deref()).collect();
+    let result_f64: Option<Vec<&[f64]>> = runs.iter().map(|r| r.result_f64.as_deref()).collect();
+    KernelResult {
+        result,
+        kernel_time: runs.iter().map(|r| r.kernel_time).sum(),
+        pack_cache_hit: runs[0].pack_cache_hit,
+        accumulators: accumulators.map(|parts| parts.concat()),
+        result_f64: result_f64.map(|parts| parts.concat()),
+        simd_variant: runs[0].simd_variant,
+        threads: runs.iter().filter_map(|r| r.threads).max(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{compute_workload, generate_matrices_from_seed, verify_with_metadata};
+
+    fn input(precision: &str, (batch, m, k, n): (usize, usize, usize, usize)) -> types::Input {
+        let (matrix_a, matrix_b) = generate_matrices_from_seed(b"batched", batch * m, k, k, n);
+        types::Input {
+            matrix_a,
+            matrix_b,
+            precision: precision.to_string(),
+            workload_type: Some("batched_matmul".to_string()),
+            batch: Some(batch),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_items_match_single_matmuls() {
+        for precision in crate::tests::precisions() {
+            // 16x16 items run the packed kernels, 5x7 the generic ones
+            for shape in [(3, 16, 40, 16), (2, 5, 33, 7)] {
+                let input = input(precision, shape);
+                let output = compute_workload(input.clone()).unwrap();
+                let batch_hashes = output.batch_hashes.as_ref().unwrap();
+                assert_eq!(batch_hashes.len(), shape.0);
+                for (i, hash) in batch_hashes.iter().enumerate() {
+                    let single = types::Input {
+                        matrix_a: item(&input.matrix_a, i, shape.0),
+                        workload_type: None,
+                        batch: None,
+                        ..input.clone()
+                    };
+                    let single = compute_workload(single).unwrap();
+                    assert_eq!(&single.result_hash, hash, "{} item {}", precision, i);
+                    assert_eq!(single.metadata.kernel, output.metadata.kernel, "{}", precision);
+                }
+
+                // The aggregate is the hash of the stacked results, and verifies item by item
+                let rows = shape.0 * shape.1;
+                assert_eq!((output.result_matrix.rows, output.metadata.result_shape), (rows, (rows, shape.3)));
+                assert_eq!(output.metadata.batch, Some(shape.0));
+                if output.result_f64.is_none() {
+                    let dtype = ResultDtype::parse(&output.metadata.result_dtype).unwrap();
+                    assert_eq!(crate::compute_hash_as(&output.result_matrix, dtype), output.result_hash, "{}", precision);
+                }
+                assert!(verify_with_metadata(&input.matrix_a, &input.matrix_b, &output.metadata, &output.result_hash).unwrap());
+                let times = output.metrics.batch_kernel_time_ms.as_ref().unwrap();
+                assert_eq!(times.len(), shape.0);
+                assert_eq!(output.metrics.kernel_time_ms, Some(times.iter().sum()));
+            }
+        }
+    }
+
+    #[test]
+    fn test_transposed_b() {
+        // B is transposed back once and gives the same items
+        let plain = input("fp32", (3, 4, 9, 5));
+        let transposed = types::Input { matrix_b: crate::transpose(&plain.matrix_b), b_layout: Some("transposed".to_string()), ..plain.clone() };
+        let (plain, transposed) = (compute_workload(plain).unwrap(), compute_workload(transposed).unwrap());
+        assert_eq!((plain.batch_hashes, plain.result_hash), (transposed.batch_hashes, transposed.result_hash));
+        assert_eq!(transposed.metadata.b_layout.as_deref(), Some("transposed"));
+    }
+
+    #[test]
+    fn test_batch_errors() {
+        let err = |input: types::Input| compute_workload(input).unwrap_err().to_string();
+        assert_eq!(err(types::Input { batch: Some(4), ..input("fp32", (3, 2, 8, 4)) }), "batch 4 does not split matrix_a's 6 rows into equal items");
+        assert_eq!(err(types::Input { batch: Some(0), ..input("fp32", (3, 2, 8, 4)) }), "batch 0 does not split matrix_a's 6 rows into equal items");
+        assert!(err(types::Input { batch: None, ..input("fp32", (3, 2, 8, 4)) }).contains("batched_matmul needs batch"));
+        let i32le = types::Input { hash_input: Some("i32le".to_string()), ..input("u8i8", (2, 2, 8, 4)) };
+        assert_eq!(err(i32le), "hash_input i32le is not supported for the batched_matmul workload");
+    }
+}
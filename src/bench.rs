@@ -0,0 +1,689 @@
+//! Kernel performance regression harness (`matmul-solver bench-regress`).
+//!
+//! `run_suite` times a fixed set of workloads and keeps the median kernel time of each.
+//! A run is stored as a `BenchRun`: the timings plus a `MachineFingerprint`, because
+//! timings are only comparable on the machine and build that produced them. `compare`
+//! checks a new run against a stored baseline and flags every workload whose median got
+//! slower by more than the threshold; `RegressionReport::to_table` formats the result.
+//! Only `run_suite` measures anything, so the comparison side is testable with
+//! synthetic numbers.
+//!
+//! Every timed iteration is tagged cold or warm: the first run on freshly generated
+//! operands, any run after a flush and any packed-B cache miss after a hit are cold. The
+//! median compared against the baseline is the warm one, and both sides are reported
+//! separately. `ColdIterations` (`--cold-iterations`) flushes the in-process packed-B
+//! caches before every iteration, and optionally overwrites a large buffer to evict the
+//! last-level cache, for worst-case numbers; `BenchRun::flushed` lists what was flushed.
+//!
+//! `k_crossover` (`matmul-solver bench-crossover`) times the packed 16x16 kernel against
+//! the plain one over a range of k, packing included, to place
+//! `ComputeOptions::packed_min_k` for a machine.
+//!
+//! `simd_paths` (`matmul-solver bench-simd`) times the u8i8 16x16 inner loop on every SIMD
+//! path this CPU supports (see simd.rs), e.g. USMMLA against SDOT on Graviton3, in one
+//! process and on the same operands.
+
+use crate::profile::median;
+use crate::simd::{self, SimdPaths, SimdVariant};
+use crate::{generate_matrices_from_seed, kernels, Clock, ComputeOptions, SolverError};
+use serde::{Deserialize, Serialize};
+
+/// Seed the suite's matrices are generated from
+const BENCH_SEED: &[u8] = b"bench-regress";
+
+/// One workload of the suite: `m×k · k×n` at `precision`
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct BenchWorkload {
+    pub name: String,
+    pub precision: String,
+    pub m: usize,
+    pub k: usize,
+    pub n: usize,
+}
+
+impl BenchWorkload {
+    pub fn new(name: &str, precision: &str, (m, k, n): (usize, usize, usize)) -> Self {
+        Self { name: name.to_string(), precision: precision.to_string(), m, k, n }
+    }
+}
+
+/// The seed shape for every precision, a 512³ fp32 cube and a skinny 2048×64×2048 fp32
+pub fn standard_suite() -> Vec<BenchWorkload> {
+    let mut suite: Vec<BenchWorkload> = ["fp32", "fp16", "int8", "u8i8"]
+        .into_iter()
+        .map(|precision| BenchWorkload::new(&format!("seed_{}", precision), precision, (16, 50240, 16)))
+        .collect();
+    suite.push(BenchWorkload::new("fp32_512_cube", "fp32", (512, 512, 512)));
+    suite.push(BenchWorkload::new("fp32_skinny", "fp32", (2048, 64, 2048)));
+    suite
+}
+
+/// What a timing depends on besides the code: hardware, OS and how the solver was built
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct MachineFingerprint {
+    pub arch: String,
+    pub os: String,
+    /// CPU model string, where the OS reports one
+    pub cpu_model: Option<String>,
+    pub logical_cpus: usize,
+    /// `build_compiler_flags()` of the binary that ran the suite
+    pub compiler_flags: String,
+}
+
+impl MachineFingerprint {
+    pub fn current() -> Self {
+        Self {
+            arch: std::env::consts::ARCH.to_string(),
+            os: std::env::consts::OS.to_string(),
+            cpu_model: cpu_model(),
+            logical_cpus: std::thread::available_parallelism().map_or(1, |n| n.get()),
+            compiler_flags: crate::build_compiler_flags().to_string(),
+        }
+    }
+}
+
+#[cfg(target_os = "linux")]
+fn cpu_model() -> Option<String> {
+    let cpuinfo = crate::host::read_to_string(std::path::Path::new("/proc/cpuinfo")).ok()?;
+    cpuinfo
+        .lines()
+        .find(|line| line.starts_with("model name") || line.starts_with("Model"))
+        .and_then(|line| line.split_once(':'))
+        .map(|(_, model)| model.trim().to_string())
+}
+
+#[cfg(not(target_os = "linux"))]
+fn cpu_model() -> Option<String> {
+    None
+}
+
+/// One timed iteration of a workload
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct BenchIteration {
+    pub kernel_ms: f64,
+    /// First run on freshly generated operands, a run right after a flush, or a packed-B
+    /// cache miss after the cache had hit
+    pub cold: bool,
+    /// `KernelResult::pack_cache_hit` of the run
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub pack_cache_hit: Option<bool>,
+}
+
+/// Kernel times of the cold or the warm iterations of a workload
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct IterationStats {
+    pub count: usize,
+    pub median_ms: f64,
+    pub mean_ms: f64,
+    pub min_ms: f64,
+    pub max_ms: f64,
+}
+
+impl IterationStats {
+    fn of(mut samples: Vec<f64>) -> Option<Self> {
+        if samples.is_empty() {
+            return None;
+        }
+        let count = samples.len();
+        let mean_ms = samples.iter().sum::<f64>() / count as f64;
+        let median_ms = median(&mut samples);
+        Some(Self { count, median_ms, mean_ms, min_ms: samples[0], max_ms: samples[count - 1] })
+    }
+}
+
+/// Median kernel time of one workload
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct BenchResult {
+    pub name: String,
+    pub precision: String,
+    /// (m, k, n)
+    pub shape: (usize, usize, usize),
+    /// Kernel chosen by automatic selection
+    pub kernel: String,
+    pub iterations: usize,
+    /// Median of the warm iterations, or of the cold ones when every iteration was cold
+    pub median_kernel_ms: f64,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub cold: Option<IterationStats>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub warm: Option<IterationStats>,
+    /// Every timed iteration, in order
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub samples: Vec<BenchIteration>,
+}
+
+/// A timed run of the suite; the baseline file is one of these
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct BenchRun {
+    pub fingerprint: MachineFingerprint,
+    pub results: Vec<BenchResult>,
+    /// What was flushed before every iteration (`ColdIterations`); empty for a normal run
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub flushed: Vec<String>,
+}
+
+impl BenchRun {
+    /// Human-readable table of the cold and warm medians
+    pub fn to_table(&self) -> String {
+        let mut out = String::from("Benchmark run
+");
+        if !self.flushed.is_empty() {
+            out.push_str(&format!("Flushed before every iteration: {}
+", self.flushed.join("; ")));
+        }
+        out.push_str(&format!("{:<16} {:<14} {:>6} {:>12} {:>6} {:>12}
+", "workload", "kernel", "cold", "cold ms", "warm", "warm ms"));
+        let columns = |stats: &Option<IterationStats>| match stats {
+            Some(s) => (s.count, format!("{:.4}", s.median_ms)),
+            None => (0, "-".to_string()),
+        };
+        for r in &self.results {
+            let ((cold, cold_ms), (warm, warm_ms)) = (columns(&r.cold), columns(&r.warm));
+            out.push_str(&format!("{:<16} {:<14} {:>6} {:>12} {:>6} {:>12}
+", r.name, r.kernel, cold, cold_ms, warm, warm_ms));
+        }
+        out
+    }
+}
+
+/// `--cold-iterations`: flush before every timed iteration, for worst-case numbers
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct ColdIterations {
+    /// Also overwrite a buffer of this many bytes to evict the last-level cache
+    pub evict_bytes: Option<usize>,
+}
+
+/// Touch one byte per cache line of `buf`, pushing everything else out of the caches
+pub(crate) fn evict(buf: &mut [u8]) {
+    for line in buf.chunks_mut(64) {
+        line[0] = line[0].wrapping_add(1);
+    }
+    std::hint::black_box(buf);
+}
+
+/// Time every workload `iterations` times, measuring the kernel only, on `options.clock()`.
+/// With `cold`, the in-process packed-B caches (and optionally the last-level cache) are
+/// flushed before every iteration; the on-disk pack cache cannot be, so `pack_cache_dir`
+/// is refused.
+pub fn run_suite(
+    suite: &[BenchWorkload],
+    iterations: usize,
+    cold: Option<ColdIterations>,
+    options: &ComputeOptions,
+) -> Result<BenchRun, SolverError> {
+    if iterations == 0 {
+        return Err(SolverError::InvalidInput("iterations must be at least 1".to_string()));
+    }
+    if cold.is_some() && options.pack_cache_dir.is_some() {
+        return Err(SolverError::InvalidInput(
+            "cold iterations flush the in-process caches only; run them without pack_cache_dir".to_string(),
+        ));
+    }
+    let mut evict_buf = cold.and_then(|c| c.evict_bytes).map(|bytes| vec![0u8; bytes]);
+    let mut flushed = Vec::new();
+    let mut results = Vec::with_capacity(suite.len());
+    for workload in suite {
+        let (a, b) = generate_matrices_from_seed(BENCH_SEED, workload.m, workload.k, workload.k, workload.n);
+        let (_, kernel) = kernels::resolve_choice(&workload.precision, (a.rows, a.cols), (b.rows, b.cols), options)?;
+        let mut samples = Vec::with_capacity(iterations);
+        let mut seen_hit = false;
+        for i in 0..iterations {
+            if cold.is_some() {
+                flushed = crate::flush_packed_b_caches().into_iter().map(str::to_string).collect();
+                if let Some(buf) = &mut evict_buf {
+                    evict(buf);
+                    flushed.push(format!("last-level cache ({} byte buffer overwritten)", buf.len()));
+                }
+            }
+            let run = kernel.run(&a, &b, options)?;
+            let miss_after_hit = seen_hit && run.pack_cache_hit == Some(false);
+            seen_hit |= run.pack_cache_hit == Some(true);
+            samples.push(BenchIteration {
+                kernel_ms: run.kernel_time.as_secs_f64() * 1000.0,
+                cold: i == 0 || cold.is_some() || miss_after_hit,
+                pack_cache_hit: run.pack_cache_hit,
+            });
+        }
+        let times = |cold: bool| samples.iter().filter(|s| s.cold == cold).map(|s| s.kernel_ms).collect();
+        let (cold_stats, warm_stats) = (IterationStats::of(times(true)), IterationStats::of(times(false)));
+        results.push(BenchResult {
+            name: workload.name.clone(),
+            precision: workload.precision.clone(),
+            shape: (workload.m, workload.k, workload.n),
+            kernel: kernel.name().to_string(),
+            iterations,
+            median_kernel_ms: warm_stats.as_ref().or(cold_stats.as_ref()).map_or(0.0, |s| s.median_ms),
+            cold: cold_stats,
+            warm: warm_stats,
+            samples,
+        });
+    }
+    Ok(BenchRun { fingerprint: MachineFingerprint::current(), results, flushed })
+}
+
+/// Median wall time of both kernels for 16×k × k×16 at one k
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct CrossoverPoint {
+    pub k: usize,
+    pub packed_kernel: String,
+    /// Whole `run`, packing included
+    pub packed_ms: f64,
+    pub plain_kernel: String,
+    pub plain_ms: f64,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct CrossoverReport {
+    pub precision: String,
+    pub iterations: usize,
+    pub points: Vec<CrossoverPoint>,
+}
+
+impl CrossoverReport {
+    /// Smallest measured k from which the packed kernel stays faster
+    pub fn crossover_k(&self) -> Option<usize> {
+        let slower = self.points.iter().rposition(|p| p.packed_ms >= p.plain_ms);
+        let first_faster = slower.map_or(0, |i| i + 1);
+        self.points.get(first_faster).map(|p| p.k)
+    }
+
+    /// Human-readable table for the console
+    pub fn to_table(&self) -> String {
+        let mut out = format!("Packed vs plain {} kernel, 16×k × k×16 (median of {})\n", self.precision, self.iterations);
+        out.push_str(&format!("{:>8} {:>12} {:>12} {:>8}\n", "k", "packed ms", "plain ms", "speedup"));
+        for p in &self.points {
+            let speedup = if p.packed_ms > 0.0 { p.plain_ms / p.packed_ms } else { f64::INFINITY };
+            out.push_str(&format!("{:>8} {:>12.4} {:>12.4} {:>7.2}x\n", p.k, p.packed_ms, p.plain_ms, speedup));
+        }
+        match self.crossover_k() {
+            Some(k) => out.push_str(&format!("Packed kernel is faster from k = {}\n", k)),
+            None => out.push_str("Packed kernel is never faster in this range\n"),
+        }
+        out
+    }
+}
+
+/// Time the kernel automatic selection takes for 16×k × k×16 with and without the packed
+/// fast path, for every k in `ks` (sorted ascending), `iterations` times each after one
+/// warm-up run. Unlike `run_suite` this times the whole `run`, since the packing is the
+/// cost being weighed.
+pub fn k_crossover(precision: &str, ks: &[usize], iterations: usize, options: &ComputeOptions) -> Result<CrossoverReport, SolverError> {
+    if iterations == 0 {
+        return Err(SolverError::InvalidInput("iterations must be at least 1".to_string()));
+    }
+    let mut ks = ks.to_vec();
+    ks.sort_unstable();
+    let packed = ComputeOptions { packed_min_k: Some(0), ..options.clone() };
+    let plain = ComputeOptions { packed_min_k: Some(usize::MAX), ..options.clone() };
+    let mut points = Vec::with_capacity(ks.len());
+    for k in ks {
+        let (a, b) = generate_matrices_from_seed(BENCH_SEED, 16, k, k, 16);
+        let time = |options: &ComputeOptions| {
+            let (_, kernel) = kernels::resolve_choice(precision, (a.rows, a.cols), (b.rows, b.cols), options)?;
+            kernel.run(&a, &b, options)?;
+            let mut samples = Vec::with_capacity(iterations);
+            for _ in 0..iterations {
+                let (out, elapsed) = crate::clock::time(options.clock(), || kernel.run(&a, &b, options));
+                out?;
+                samples.push(elapsed.as_secs_f64() * 1000.0);
+            }
+            Ok::<_, SolverError>((kernel.name().to_string(), median(&mut samples)))
+        };
+        let (packed_kernel, packed_ms) = time(&packed)?;
+        let (plain_kernel, plain_ms) = time(&plain)?;
+        points.push(CrossoverPoint { k, packed_kernel, packed_ms, plain_kernel, plain_ms });
+    }
+    Ok(CrossoverReport { precision: precision.to_string(), iterations, points })
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SimdTiming {
+    /// `SimdVariant::as_str`
+    pub variant: String,
+    /// Median time of one 16×k × k×16 accumulation, packing excluded
+    pub ms: f64,
+    /// Scalar time over this one
+    pub speedup_vs_scalar: f64,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SimdReport {
+    pub k: usize,
+    pub iterations: usize,
+    /// The variant dispatch uses in this process
+    pub active: String,
+    /// Supported variants, in order of preference
+    pub timings: Vec<SimdTiming>,
+}
+
+impl SimdReport {
+    /// Human-readable table for the console
+    pub fn to_table(&self) -> String {
+        let mut out = format!("u8i8 16x16 inner loop per SIMD path, 16×{} × {}×16 (median of {})\n", self.k, self.k, self.iterations);
+        out.push_str(&format!("{:<12} {:>12} {:>10}\n", "variant", "ms", "vs scalar"));
+        for t in &self.timings {
+            let marker = if t.variant == self.active { " (active)" } else { "" };
+            out.push_str(&format!("{:<12} {:>12.4} {:>9.2}x{}\n", t.variant, t.ms, t.speedup_vs_scalar, marker));
+        }
+        out
+    }
+}
+
+/// Time `SimdPaths::u8i8_16x16` of every supported variant on the seed-shaped 16×k × k×16
+/// u8i8 operands, `iterations` times each after one warm-up run. Every path must give the
+/// scalar path's accumulators; one that does not fails the run. k is capped at
+/// `U8I8_SAFE_K`, past which the i32 accumulators could overflow.
+pub fn simd_paths(k: usize, iterations: usize, clock: &dyn Clock) -> Result<SimdReport, SolverError> {
+    if iterations == 0 {
+        return Err(SolverError::InvalidInput("iterations must be at least 1".to_string()));
+    }
+    if k == 0 || k > crate::U8I8_SAFE_K {
+        return Err(SolverError::InvalidInput(format!("k must be between 1 and {}", crate::U8I8_SAFE_K)));
+    }
+    let (a, b) = generate_matrices_from_seed(BENCH_SEED, 16, k, k, 16);
+    let (a_u8, b_i8) = (crate::pack_a_u8(&a), crate::pack_b_u8i8(&b));
+    let run = |paths: &SimdPaths| {
+        let mut acc = vec![0i32; 16 * 16];
+        // SAFETY: only supported variants are run, on 16×k and k×16 packed operands
+        unsafe { (paths.u8i8_16x16)(a_u8.as_ptr(), b_i8.as_ptr(), k, 16, acc.as_mut_ptr()) };
+        acc
+    };
+    let expected = run(&SimdPaths::for_variant(SimdVariant::Scalar));
+    let mut timings = Vec::new();
+    for variant in SimdVariant::ALL.into_iter().filter(SimdVariant::is_supported) {
+        let paths = SimdPaths::for_variant(variant);
+        if run(&paths) != expected {
+            return Err(SolverError::Internal(format!("SIMD variant '{}' disagrees with the scalar path at k = {}", variant.as_str(), k)));
+        }
+        let mut samples = Vec::with_capacity(iterations);
+        for _ in 0..iterations {
+            let (acc, elapsed) = crate::clock::time(clock, || run(&paths));
+            std::hint::black_box(acc);
+            samples.push(elapsed.as_secs_f64() * 1000.0);
+        }
+        timings.push(SimdTiming { variant: variant.as_str().to_string(), ms: median(&mut samples), speedup_vs_scalar: 0.0 });
+    }
+    let scalar_ms = timings.last().map_or(0.0, |t| t.ms);
+    for t in &mut timings {
+        t.speedup_vs_scalar = if t.ms > 0.0 { scalar_ms / t.ms } else { f64::INFINITY };
+    }
+    Ok(SimdReport { k, iterations, active: simd::active().as_str().to_string(), timings })
+}
+
+/// One workload present in both runs
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct Comparison {
+    pub name: String,
+    pub baseline_ms: f64,
+    pub current_ms: f64,
+    /// Relative change of the median, positive when slower
+    pub change_pct: f64,
+    pub regressed: bool,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct RegressionReport {
+    pub threshold_pct: f64,
+    pub comparisons: Vec<Comparison>,
+    /// In the baseline but not in this run
+    pub missing: Vec<String>,
+    /// In this run but not in the baseline
+    pub added: Vec<String>,
+}
+
+impl RegressionReport {
+    pub fn regressions(&self) -> impl Iterator<Item = &Comparison> {
+        self.comparisons.iter().filter(|c| c.regressed)
+    }
+
+    pub fn has_regressions(&self) -> bool {
+        self.regressions().next().is_some()
+    }
+
+    /// Human-readable table for the console
+    pub fn to_table(&self) -> String {
+        let mut out = format!("Benchmark regression check (threshold +{:.1}%)\n", self.threshold_pct);
+        out.push_str(&format!("{:<16} {:>12} {:>12} {:>9}\n", "workload", "baseline ms", "current ms", "change"));
+        for c in &self.comparisons {
+            out.push_str(&format!(
+                "{:<16} {:>12.4} {:>12.4} {:>+8.1}%{}\n",
+                c.name,
+                c.baseline_ms,
+                c.current_ms,
+                c.change_pct,
+                if c.regressed { "  REGRESSED" } else { "" }
+            ));
+        }
+        for name in &self.missing {
+            out.push_str(&format!("{:<16} not run (in baseline only)\n", name));
+        }
+        for name in &self.added {
+            out.push_str(&format!("{:<16} new (no baseline)\n", name));
+        }
+        let regressed = self.regressions().count();
+        if regressed == 0 {
+            out.push_str("No regressions\n");
+        } else {
+            out.push_str(&format!("{} of {} workloads regressed\n", regressed, self.comparisons.len()));
+        }
+        out
+    }
+}
+
+/// Compare `current` against `baseline`; a workload regressed when its median kernel time
+/// grew by more than `threshold_pct` percent. Runs from different machines or builds are
+/// refused rather than compared.
+pub fn compare(baseline: &BenchRun, current: &BenchRun, threshold_pct: f64) -> Result<RegressionReport, SolverError> {
+    if !threshold_pct.is_finite() || threshold_pct < 0.0 {
+        return Err(SolverError::InvalidInput(format!("Invalid regression threshold: {}%", threshold_pct)));
+    }
+    if baseline.fingerprint != current.fingerprint {
+        return Err(SolverError::InvalidInput(format!(
+            "Baseline was recorded on a different machine or build ({:?}), this run is {:?}; record a new baseline here",
+            baseline.fingerprint, current.fingerprint
+        )));
+    }
+    if baseline.flushed != current.flushed {
+        return Err(SolverError::InvalidInput(format!(
+            "Baseline flushed {:?} before every iteration, this run flushed {:?}; compare runs made the same way",
+            baseline.flushed, current.flushed
+        )));
+    }
+    let comparisons = current
+        .results
+        .iter()
+        .filter_map(|cur| {
+            let base = baseline.results.iter().find(|b| b.name == cur.name)?;
+            let change_pct = if base.median_kernel_ms > 0.0 {
+                (cur.median_kernel_ms - base.median_kernel_ms) / base.median_kernel_ms * 100.0
+            } else {
+                0.0
+            };
+            Some(Comparison {
+                name: cur.name.clone(),
+                baseline_ms: base.median_kernel_ms,
+                current_ms: cur.median_kernel_ms,
+                change_pct,
+                regressed: change_pct > threshold_pct,
+            })
+        })
+        .collect();
+    let names = |run: &BenchRun| run.results.iter().map(|r| r.name.clone()).collect::<Vec<_>>();
+    let (baseline_names, current_names) = (names(baseline), names(current));
+    Ok(RegressionReport {
+        threshold_pct,
+        comparisons,
+        missing: baseline_names.iter().filter(|n| !current_names.contains(n)).cloned().collect(),
+        added: current_names.iter().filter(|n| !baseline_names.contains(n)).cloned().collect(),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn fingerprint() -> MachineFingerprint {
+        MachineFingerprint {
+            arch: "aarch64".to_string(),
+            os: "linux".to_string(),
+            cpu_model: Some("Neoverse-N1".to_string()),
+            logical_cpus: 4,
+            compiler_flags: "opt-level=3".to_string(),
+        }
+    }
+
+    fn run(timings: &[(&str, f64)]) -> BenchRun {
+        let results = timings
+            .iter()
+            .map(|&(name, ms)| BenchResult {
+                name: name.to_string(),
+                precision: "fp32".to_string(),
+                shape: (16, 50240, 16),
+                kernel: "fp32_16x16".to_string(),
+                iterations: 10,
+                median_kernel_ms: ms,
+                cold: None,
+                warm: None,
+                samples: Vec::new(),
+            })
+            .collect();
+        BenchRun { fingerprint: fingerprint(), results, flushed: Vec::new() }
+    }
+
+    #[test]
+    fn test_compare_flags_regressions_over_threshold() {
+        let baseline = run(&[("seed_fp32", 10.0), ("seed_u8i8", 4.0), ("fp32_skinny", 20.0), ("retired", 1.0)]);
+        let current = run(&[("seed_fp32", 10.9), ("seed_u8i8", 4.8), ("fp32_skinny", 12.0), ("brand_new", 3.0)]);
+        let report = compare(&baseline, &current, 10.0).unwrap();
+
+        let regressed: Vec<&str> = report.regressions().map(|c| c.name.as_str()).collect();
+        assert_eq!(regressed, ["seed_u8i8"]);
+        assert!((report.comparisons[1].change_pct - 20.0).abs() < 1e-9);
+        // Getting faster is never a regression
+        assert!(report.comparisons[2].change_pct < 0.0 && !report.comparisons[2].regressed);
+        assert_eq!(report.missing, ["retired"]);
+        assert_eq!(report.added, ["brand_new"]);
+
+        let table = report.to_table();
+        assert!(table.contains("seed_u8i8") && table.contains("+20.0%  REGRESSED"), "{}", table);
+        assert!(table.contains("1 of 3 workloads regressed"), "{}", table);
+
+        // A looser threshold lets the same numbers pass
+        let report = compare(&baseline, &current, 25.0).unwrap();
+        assert!(!report.has_regressions());
+        assert!(report.to_table().contains("No regressions"));
+    }
+
+    #[test]
+    fn test_compare_requires_same_fingerprint() {
+        let baseline = run(&[("seed_fp32", 10.0)]);
+        let mut current = run(&[("seed_fp32", 10.0)]);
+        current.fingerprint.logical_cpus = 64;
+        let err = compare(&baseline, &current, 10.0).unwrap_err();
+        assert!(err.to_string().contains("different machine"), "{}", err);
+        assert!(compare(&baseline, &baseline, -1.0).is_err());
+
+        // Baselines survive a round trip through their JSON file
+        let json = serde_json::to_string_pretty(&baseline).unwrap();
+        assert_eq!(serde_json::from_str::<BenchRun>(&json).unwrap(), baseline);
+    }
+
+    #[test]
+    fn test_run_suite_structure() {
+        let suite = [BenchWorkload::new("tiny_u8i8", "u8i8", (16, 64, 16)), BenchWorkload::new("tiny_fp32", "fp32", (8, 8, 8))];
+        let run = run_suite(&suite, 3, None, &ComputeOptions::default()).unwrap();
+        assert_eq!(run.fingerprint, MachineFingerprint::current());
+        assert_eq!(run.results[0].kernel, "u8i8_16x16");
+        assert_eq!(run.results[1].shape, (8, 8, 8));
+        assert!(run.results.iter().all(|r| r.iterations == 3 && r.median_kernel_ms >= 0.0));
+        assert!(run.flushed.is_empty());
+
+        let names: Vec<String> = standard_suite().into_iter().map(|w| w.name).collect();
+        assert_eq!(names, ["seed_fp32", "seed_fp16", "seed_int8", "seed_u8i8", "fp32_512_cube", "fp32_skinny"]);
+    }
+
+    #[test]
+    fn test_cold_and_warm_iterations() {
+        let suite = [BenchWorkload::new("tiny_int8", "int8", (16, 64, 16)), BenchWorkload::new("tiny_fp32", "fp32", (8, 8, 8))];
+        let split = |result: &BenchResult| {
+            let count = |stats: &Option<IterationStats>| stats.as_ref().map_or(0, |s| s.count);
+            (count(&result.cold), count(&result.warm))
+        };
+
+        // The first run on new operands is cold, and so is an int8 packed-B cache miss after a
+        // hit (other tests running int8 in parallel can evict the entry)
+        let run = run_suite(&suite, 4, None, &ComputeOptions::default()).unwrap();
+        for result in &run.results {
+            let (cold, warm) = split(result);
+            assert_eq!((cold + warm, result.samples.len()), (4, 4), "{}", result.name);
+            let mut seen_hit = false;
+            for (i, sample) in result.samples.iter().enumerate() {
+                assert_eq!(sample.cold, i == 0 || (seen_hit && sample.pack_cache_hit == Some(false)), "{} {}", result.name, i);
+                seen_hit |= sample.pack_cache_hit == Some(true);
+            }
+        }
+        assert_eq!(split(&run.results[1]), (1, 3));
+        assert_eq!(run.results[1].median_kernel_ms, run.results[1].warm.as_ref().unwrap().median_ms);
+        assert_eq!(run.results[0].samples[0].pack_cache_hit, Some(false));
+
+        let cold = ColdIterations { evict_bytes: Some(1 << 20) };
+        let run = run_suite(&suite, 4, Some(cold), &ComputeOptions::default()).unwrap();
+        for result in &run.results {
+            assert_eq!(split(result), (4, 0), "{}", result.name);
+            assert_eq!(result.median_kernel_ms, result.cold.as_ref().unwrap().median_ms);
+        }
+        assert!(run.results[0].samples.iter().all(|s| s.pack_cache_hit == Some(false)));
+        assert!(run.flushed.iter().any(|f| f == "int8 packed-B cache"), "{:?}", run.flushed);
+        assert!(run.flushed.iter().any(|f| f.starts_with("last-level cache (1048576 byte")), "{:?}", run.flushed);
+        assert!(run.to_table().contains("Flushed before every iteration: "), "{}", run.to_table());
+
+        // A cold run is not compared against a warm baseline
+        let warm = run_suite(&suite[1..], 1, None, &ComputeOptions::default()).unwrap();
+        let flushed = run_suite(&suite[1..], 1, Some(ColdIterations::default()), &ComputeOptions::default()).unwrap();
+        assert!(compare(&warm, &flushed, 10.0).unwrap_err().to_string().contains("compare runs made the same way"));
+        let on_disk = ComputeOptions { pack_cache_dir: Some(std::env::temp_dir()), ..Default::default() };
+        assert!(run_suite(&suite, 1, Some(ColdIterations::default()), &on_disk).is_err());
+    }
+
+    #[test]
+    fn test_simd_paths_report() {
+        let report = simd_paths(1000, 2, &crate::SystemClock).unwrap();
+        let variants: Vec<&str> = report.timings.iter().map(|t| t.variant.as_str()).collect();
+        assert_eq!(variants.last(), Some(&"scalar"));
+        assert!(variants.contains(&simd::active().as_str()) && variants.contains(&report.active.as_str()));
+        assert_eq!(report.timings.last().unwrap().speedup_vs_scalar, 1.0);
+        let table = report.to_table();
+        assert!(table.starts_with("u8i8 16x16 inner loop per SIMD path, 16×1000 × 1000×16 (median of 2)\n"), "{}", table);
+        assert!(table.contains(" (active)"), "{}", table);
+        assert!(simd_paths(crate::U8I8_SAFE_K + 1, 1, &crate::SystemClock).is_err());
+        assert!(simd_paths(16, 0, &crate::SystemClock).is_err());
+    }
+
+    #[test]
+    fn test_crossover_report() {
+        let report = k_crossover("u8i8", &[256, 16], 2, &ComputeOptions::default()).unwrap();
+        let ks: Vec<usize> = report.points.iter().map(|p| p.k).collect();
+        assert_eq!(ks, [16, 256]);
+        assert!(report.points.iter().all(|p| (p.packed_kernel.as_str(), p.plain_kernel.as_str()) == ("u8i8_16x16", "u8i8_generic")));
+
+        let point = |k, packed_ms, plain_ms| CrossoverPoint {
+            k,
+            packed_kernel: "fp32_16x16".to_string(),
+            packed_ms,
+            plain_kernel: "fp32_blocked".to_string(),
+            plain_ms,
+        };
+        let mut report = CrossoverReport {
+            precision: "fp32".to_string(),
+            iterations: 5,
+            points: vec![point(64, 0.5, 0.1), point(512, 0.9, 1.0), point(1024, 2.5, 2.0), point(4096, 4.0, 9.0)],
+        };
+        // A noisy win below the last loss does not count
+        assert_eq!(report.crossover_k(), Some(4096));
+        assert!(report.to_table().contains("Packed kernel is faster from k = 4096"), "{}", report.to_table());
+        report.points.pop();
+        assert_eq!(report.crossover_k(), None);
+        assert!(k_crossover("fp32", &[64], 0, &ComputeOptions::default()).is_err());
+    }
+
+}
@@ -0,0 +1,466 @@
+//! Reproducible-case bundles (`matmul-solver --bundle case.tar.gz`, `bundle verify`).
+//!
+//! Sharing a case used to mean collecting the input, output, seed and build info by hand.
+//! `write_bundle` puts them in one gzipped tar:
+//!
+//! - `manifest.json`, first: the bundle version, the solver version and the size and
+//!   SHA-256 of every other member
+//! - `input.json`, the parsed input re-serialized (so numbers a file rounded are recorded
+//!   as the values that were computed), or `seed.json` with the seed, dims and precision
+//! - `output.json`, the full output
+//! - `report.json`, the `ExecutionReport`: machine, timings, verification and notices
+//! - `warnings.json`, the output's warnings
+//!
+//! Members are streamed: each is serialized once into a digest to learn its size and
+//! SHA-256 for the manifest and the tar header, then again straight into the archive, so
+//! no member is ever held in memory whole. Entries have mode 0644 and mtime 0, so the same
+//! run gives the same archive.
+//!
+//! `verify_bundle` checks every member against the manifest and recomputes the bundled
+//! input on this machine with `verify_with_metadata`, replaying the kernel, accumulation
+//! and rounding recorded in the bundled output's metadata.
+
+use crate::bench::MachineFingerprint;
+use crate::{generate_matrices_from_seed_hex, host, numbers, types, verify_with_metadata, SeedDims, SolverError};
+use flate2::read::GzDecoder;
+use flate2::write::GzEncoder;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::collections::HashMap;
+use std::io::{self, Read, Write};
+use std::path::Path;
+
+/// Version of the bundle layout, recorded in its manifest
+pub const BUNDLE_VERSION: u32 = 1;
+
+pub const MANIFEST: &str = "manifest.json";
+pub const INPUT: &str = "input.json";
+pub const SEED: &str = "seed.json";
+pub const OUTPUT: &str = "output.json";
+pub const REPORT: &str = "report.json";
+pub const WARNINGS: &str = "warnings.json";
+
+/// `manifest.json`
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Manifest {
+    pub bundle_version: u32,
+    /// Version of the solver that wrote the bundle
+    pub solver_version: String,
+    /// Every other member, in archive order
+    pub members: Vec<Member>,
+}
+
+/// One member as the manifest records it
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Member {
+    pub name: String,
+    pub bytes: u64,
+    /// Hex SHA-256 of the member
+    pub sha256: String,
+}
+
+/// `seed.json`: the matrices are `generate_matrices_from_seed` of the seed with these dims
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SeedInput {
+    /// Hex seed
+    pub seed: String,
+    pub dims: SeedDims,
+    pub precision: String,
+}
+
+/// What a bundle recomputes from
+#[derive(Debug, Clone, Copy)]
+pub enum BundleInput<'a> {
+    Input(&'a types::Input),
+    Seed(&'a SeedInput),
+}
+
+/// `report.json`: how and where the bundled output was produced
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ExecutionReport {
+    pub solver_version: String,
+    pub machine: MachineFingerprint,
+    pub parse_time_ms: Option<f64>,
+    pub serialize_time_ms: Option<f64>,
+    pub latency_ms: f64,
+    pub kernel_time_ms: Option<f64>,
+    /// "verified", "mismatch" or the verification error, when the run verified
+    pub verification: Option<String>,
+    /// What the run printed on stderr, one line each
+    pub notices: Vec<String>,
+}
+
+impl ExecutionReport {
+    /// A report of `output` on this machine; the caller fills in verification and notices
+    pub fn current(output: &types::Output) -> Self {
+        Self {
+            solver_version: env!("CARGO_PKG_VERSION").to_string(),
+            machine: MachineFingerprint::current(),
+            parse_time_ms: output.metrics.parse_time_ms,
+            serialize_time_ms: output.metrics.serialize_time_ms,
+            latency_ms: output.metrics.latency_ms,
+            kernel_time_ms: output.metrics.kernel_time_ms,
+            verification: None,
+            notices: Vec::new(),
+        }
+    }
+}
+
+/// Size and SHA-256 of everything written, keeping none of it
+#[derive(Default)]
+struct MemberDigest {
+    bytes: u64,
+    hasher: Sha256,
+}
+
+impl MemberDigest {
+    fn finish(self, name: &str) -> Member {
+        Member { name: name.to_string(), bytes: self.bytes, sha256: hex::encode(self.hasher.finalize()) }
+    }
+}
+
+impl Write for MemberDigest {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.hasher.update(buf);
+        self.bytes += buf.len() as u64;
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+/// Forwards to `inner`, digesting what passes through
+struct Tee<'a, W> {
+    inner: &'a mut W,
+    digest: MemberDigest,
+}
+
+impl<W: Write> Write for Tee<'_, W> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let n = self.inner.write(buf)?;
+        self.digest.write_all(&buf[..n])?;
+        Ok(n)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.inner.flush()
+    }
+}
+
+/// A member's value and whether it is pretty-printed (matrices are not, to keep inputs small)
+type Source<'a> = (&'static str, &'a dyn erased::Json, bool);
+
+mod erased {
+    /// `serde_json` serialization of members of different types through one list
+    pub trait Json {
+        fn write_json(&self, w: &mut dyn std::io::Write, pretty: bool) -> serde_json::Result<()>;
+    }
+
+    impl<T: serde::Serialize> Json for T {
+        fn write_json(&self, w: &mut dyn std::io::Write, pretty: bool) -> serde_json::Result<()> {
+            if pretty {
+                serde_json::to_writer_pretty(w, self)
+            } else {
+                serde_json::to_writer(w, self)
+            }
+        }
+    }
+}
+
+fn io_error(path: &Path, e: impl std::fmt::Display) -> SolverError {
+    SolverError::Internal(format!("{}: {}", path.display(), e))
+}
+
+/// Write a bundle of `input`, `output` and `report` to `path`, returning its manifest
+pub fn write_bundle(path: &Path, input: BundleInput, output: &types::Output, report: &ExecutionReport) -> Result<Manifest, SolverError> {
+    let mut sources: Vec<Source> = match input {
+        BundleInput::Input(input) => vec![(INPUT, input, false)],
+        BundleInput::Seed(seed) => vec![(SEED, seed, true)],
+    };
+    sources.extend([(OUTPUT, output as &dyn erased::Json, true), (REPORT, report, true), (WARNINGS, &output.warnings, true)]);
+
+    let mut members = Vec::with_capacity(sources.len());
+    for &(name, value, pretty) in &sources {
+        let mut digest = MemberDigest::default();
+        value.write_json(&mut digest, pretty).map_err(|e| io_error(path, e))?;
+        members.push(digest.finish(name));
+    }
+    let manifest = Manifest { bundle_version: BUNDLE_VERSION, solver_version: env!("CARGO_PKG_VERSION").to_string(), members };
+
+    let file = host::create(path).map_err(|e| io_error(path, e))?;
+    let gz = GzEncoder::new(io::BufWriter::new(file), flate2::Compression::default());
+    let mut archive = tar::Builder::new(gz);
+    append(&mut archive, path, (MANIFEST, &manifest, true), None)?;
+    for (&source, member) in sources.iter().zip(&manifest.members) {
+        append(&mut archive, path, source, Some(member))?;
+    }
+    let gz = archive.into_inner().map_err(|e| io_error(path, e))?;
+    let mut file = gz.finish().map_err(|e| io_error(path, e))?;
+    file.flush().map_err(|e| io_error(path, e))?;
+    Ok(manifest)
+}
+
+/// Append one member, serializing it straight into the archive. `measured` is its size and
+/// digest from the first pass; the manifest itself is small and measured here.
+fn append<W: Write>(archive: &mut tar::Builder<W>, path: &Path, (name, value, pretty): Source, measured: Option<&Member>) -> Result<(), SolverError> {
+    let measured = match measured {
+        Some(member) => member.clone(),
+        None => {
+            let mut digest = MemberDigest::default();
+            value.write_json(&mut digest, pretty).map_err(|e| io_error(path, e))?;
+            digest.finish(name)
+        }
+    };
+    let mut header = tar::Header::new_gnu();
+    header.set_path(name).map_err(|e| io_error(path, e))?;
+    header.set_size(measured.bytes);
+    header.set_mode(0o644);
+    header.set_mtime(0);
+    header.set_cksum();
+
+    let out = archive.get_mut();
+    out.write_all(header.as_bytes()).map_err(|e| io_error(path, e))?;
+    let mut tee = Tee { inner: &mut *out, digest: MemberDigest::default() };
+    value.write_json(&mut tee, pretty).map_err(|e| io_error(path, e))?;
+    // The header already promised the first pass's size
+    let written = tee.digest.finish(name);
+    if written != measured {
+        return Err(SolverError::Internal(format!("{}: {} serialized differently on the second pass", path.display(), name)));
+    }
+    let padding = (512 - measured.bytes % 512) % 512;
+    out.write_all(&[0; 512][..padding as usize]).map_err(|e| io_error(path, e))
+}
+
+/// One member checked against the manifest
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct MemberCheck {
+    pub name: String,
+    /// SHA-256 the manifest records; None when the member is not in the manifest
+    pub expected: Option<String>,
+    /// SHA-256 of the member; None when the archive lacks it
+    pub actual: Option<String>,
+}
+
+impl MemberCheck {
+    pub fn passed(&self) -> bool {
+        self.expected.is_some() && self.expected == self.actual
+    }
+}
+
+/// Whether recomputing the bundled input reproduces the bundled `result_hash`
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Recomputed {
+    Match,
+    Mismatch,
+    /// The bundle could not be recomputed (missing or unreadable members, or a workload
+    /// `verify_with_metadata` cannot replay)
+    Failed(String),
+}
+
+/// Outcome of `verify_bundle`
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct BundleVerification {
+    pub manifest: Manifest,
+    /// The manifest's members in order, then members it does not list
+    pub members: Vec<MemberCheck>,
+    /// The bundled output's `result_hash`, when it parses
+    pub result_hash: Option<String>,
+    pub recomputed: Recomputed,
+}
+
+impl BundleVerification {
+    pub fn passed(&self) -> bool {
+        self.members.iter().all(MemberCheck::passed) && self.recomputed == Recomputed::Match
+    }
+
+    pub fn to_table(&self) -> String {
+        let mut out = String::new();
+        for member in &self.members {
+            let status = match (&member.expected, &member.actual) {
+                (Some(expected), Some(actual)) if expected == actual => "ok".to_string(),
+                (Some(expected), Some(actual)) => format!("digest mismatch: manifest {}, member {}", expected, actual),
+                (Some(_), None) => "missing from the archive".to_string(),
+                (None, _) => "not in the manifest".to_string(),
+            };
+            out.push_str(&format!("  {:<14} {}\n", member.name, status));
+        }
+        let hash = self.result_hash.as_deref().unwrap_or("(none)");
+        match &self.recomputed {
+            Recomputed::Match => out.push_str(&format!("✅ Recomputed result hash {} on this machine\n", hash)),
+            Recomputed::Mismatch => out.push_str(&format!("❌ Recomputing the input does not give result hash {}\n", hash)),
+            Recomputed::Failed(e) => out.push_str(&format!("⚠️  Could not recompute the bundle: {}\n", e)),
+        }
+        out
+    }
+}
+
+/// Check the members of the bundle at `path` against its manifest and recompute its input.
+///
+/// Unreadable archives and manifests are errors; tampered members are reported in the
+/// returned verification.
+pub fn verify_bundle(path: &Path) -> Result<BundleVerification, SolverError> {
+    let file = host::open(path).map_err(|e| io_error(path, e))?;
+    let mut archive = tar::Archive::new(GzDecoder::new(io::BufReader::new(file)));
+    let mut contents: HashMap<String, Vec<u8>> = HashMap::new();
+    let mut order = Vec::new();
+    for entry in archive.entries().map_err(|e| io_error(path, e))? {
+        let mut entry = entry.map_err(|e| io_error(path, e))?;
+        let name = entry.path().map_err(|e| io_error(path, e))?.to_string_lossy().into_owned();
+        let mut bytes = Vec::new();
+        entry.read_to_end(&mut bytes).map_err(|e| io_error(path, e))?;
+        order.push(name.clone());
+        contents.insert(name, bytes);
+    }
+    let manifest: Manifest = match contents.get(MANIFEST) {
+        Some(bytes) => serde_json::from_slice(bytes).map_err(|e| io_error(path, format!("{}: {}", MANIFEST, e)))?,
+        None => return Err(io_error(path, format!("no {}; not a solver bundle", MANIFEST))),
+    };
+    if manifest.bundle_version != BUNDLE_VERSION {
+        return Err(io_error(path, format!("bundle version {}, this build reads {}", manifest.bundle_version, BUNDLE_VERSION)));
+    }
+
+    let digest = |name: &str| contents.get(name).map(|bytes| hex::encode(Sha256::digest(bytes)));
+    let mut members: Vec<MemberCheck> = manifest
+        .members
+        .iter()
+        .map(|m| MemberCheck { name: m.name.clone(), expected: Some(m.sha256.clone()), actual: digest(&m.name) })
+        .collect();
+    let unlisted = order.iter().filter(|name| *name != MANIFEST && !manifest.members.iter().any(|m| &m.name == *name));
+    members.extend(unlisted.map(|name| MemberCheck { name: name.clone(), expected: None, actual: digest(name) }));
+
+    let output: Result<types::Output, String> = match contents.get(OUTPUT) {
+        Some(bytes) => serde_json::from_slice(bytes).map_err(|e| format!("{}: {}", OUTPUT, e)),
+        None => Err(format!("no {}", OUTPUT)),
+    };
+    let result_hash = output.as_ref().ok().map(|o| o.result_hash.clone());
+    let recomputed = match output.and_then(|output| recompute(&contents, &output)) {
+        Ok(true) => Recomputed::Match,
+        Ok(false) => Recomputed::Mismatch,
+        Err(e) => Recomputed::Failed(e),
+    };
+    Ok(BundleVerification { manifest, members, result_hash, recomputed })
+}
+
+/// Recompute the bundled input's matrices and check them against `output`
+fn recompute(contents: &HashMap<String, Vec<u8>>, output: &types::Output) -> Result<bool, String> {
+    let (matrix_a, matrix_b) = match (contents.get(INPUT), contents.get(SEED)) {
+        (Some(bytes), _) => {
+            let json = std::str::from_utf8(bytes).map_err(|e| format!("{}: {}", INPUT, e))?;
+            let (input, _) = numbers::parse_input_json(json, false).map_err(|e| format!("{}: {}", INPUT, e))?;
+            let matrix_b = match &input.matrix_b_sparse {
+                Some(_) => input.dense_b().map_err(|e| format!("{}: {}", INPUT, e))?.into_owned(),
+                None => input.matrix_b,
+            };
+            (input.matrix_a, matrix_b)
+        }
+        (None, Some(bytes)) => {
+            let seed: SeedInput = serde_json::from_slice(bytes).map_err(|e| format!("{}: {}", SEED, e))?;
+            let SeedDims { m, k, n } = seed.dims;
+            generate_matrices_from_seed_hex(&seed.seed, m, k, k, n).map_err(|e| format!("{}: {}", SEED, e))?
+        }
+        (None, None) => return Err(format!("no {} or {}", INPUT, SEED)),
+    };
+    verify_with_metadata(&matrix_a, &matrix_b, &output.metadata, &output.result_hash).map_err(|e| e.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{compute_workload, FlatMatrix};
+
+    fn input() -> types::Input {
+        types::Input::matmul(
+            FlatMatrix { data: vec![1.0, 2.0, 3.0, 4.0, 5.0, 16777216.0], rows: 2, cols: 3 },
+            FlatMatrix { data: vec![1.0, 2.0, 3.0, 4.0, 5.0, 6.0], rows: 3, cols: 2 },
+            "fp32",
+        )
+    }
+
+    /// Rewrite the bundle at `path` with `edit` applied to each (name, contents)
+    fn tamper(path: &Path, edit: impl Fn(&str, &mut Vec<u8>)) {
+        let bytes = std::fs::read(path).unwrap();
+        let mut archive = tar::Archive::new(GzDecoder::new(&bytes[..]));
+        let mut builder = tar::Builder::new(GzEncoder::new(Vec::new(), flate2::Compression::default()));
+        for entry in archive.entries().unwrap() {
+            let mut entry = entry.unwrap();
+            let name = entry.path().unwrap().to_string_lossy().into_owned();
+            let mut contents = Vec::new();
+            entry.read_to_end(&mut contents).unwrap();
+            edit(&name, &mut contents);
+            let mut header = tar::Header::new_gnu();
+            header.set_size(contents.len() as u64);
+            header.set_mode(0o644);
+            builder.append_data(&mut header, &name, &contents[..]).unwrap();
+        }
+        std::fs::write(path, builder.into_inner().unwrap().finish().unwrap()).unwrap();
+    }
+
+    fn set_json(contents: &mut Vec<u8>, edit: impl FnOnce(&mut serde_json::Value)) {
+        let mut value: serde_json::Value = serde_json::from_slice(contents).unwrap();
+        edit(&mut value);
+        *contents = serde_json::to_vec_pretty(&value).unwrap();
+    }
+
+    #[test]
+    fn test_bundle_round_trip() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("case.tar.gz");
+        let input = input();
+        let output = compute_workload(input.clone()).unwrap();
+        let report = ExecutionReport::current(&output);
+        let manifest = write_bundle(&path, BundleInput::Input(&input), &output, &report).unwrap();
+        let names: Vec<&str> = manifest.members.iter().map(|m| m.name.as_str()).collect();
+        assert_eq!(names, [INPUT, OUTPUT, REPORT, WARNINGS]);
+
+        let verified = verify_bundle(&path).unwrap();
+        assert!(verified.passed(), "{}", verified.to_table());
+        assert_eq!((verified.manifest, verified.result_hash), (manifest, Some(output.result_hash.clone())));
+        // The same run gives the same archive
+        let again = dir.path().join("again.tar.gz");
+        write_bundle(&again, BundleInput::Input(&input), &output, &report).unwrap();
+        assert_eq!(std::fs::read(&path).unwrap(), std::fs::read(&again).unwrap());
+
+        let seed = SeedInput { seed: "00ff".to_string(), dims: SeedDims { m: 4, k: 24, n: 3 }, precision: "u8i8".to_string() };
+        let (matrix_a, matrix_b) = generate_matrices_from_seed_hex(&seed.seed, 4, 24, 24, 3).unwrap();
+        let output = compute_workload(types::Input { matrix_a, matrix_b, precision: "u8i8".to_string(), ..input }).unwrap();
+        write_bundle(&path, BundleInput::Seed(&seed), &output, &report).unwrap();
+        let verified = verify_bundle(&path).unwrap();
+        assert!(verified.passed(), "{}", verified.to_table());
+        assert_eq!(verified.members[0].name, SEED);
+    }
+
+    #[test]
+    fn test_tampering_is_detected() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("case.tar.gz");
+        let input = input();
+        let output = compute_workload(input.clone()).unwrap();
+        let fresh = || write_bundle(&path, BundleInput::Input(&input), &output, &ExecutionReport::current(&output)).unwrap();
+
+        // An edited member no longer matches its manifest digest, though it still recomputes
+        fresh();
+        tamper(&path, |name, contents| if name == REPORT { set_json(contents, |v| v["latency_ms"] = 0.5.into()) });
+        let verified = verify_bundle(&path).unwrap();
+        assert!(!verified.passed());
+        assert_eq!(verified.recomputed, Recomputed::Match);
+        let failed: Vec<&str> = verified.members.iter().filter(|m| !m.passed()).map(|m| m.name.as_str()).collect();
+        assert_eq!(failed, [REPORT]);
+        assert!(verified.to_table().contains("digest mismatch: manifest "), "{}", verified.to_table());
+
+        // A changed input with a manifest to match passes every digest but not the recompute
+        fresh();
+        tamper(&path, |name, contents| if name == INPUT { set_json(contents, |v| v["matrix_a"][0][0] = 2.0.into()) });
+        let edited = verify_bundle(&path).unwrap().members[0].actual.clone().unwrap();
+        tamper(&path, |name, contents| if name == MANIFEST { set_json(contents, |v| v["members"][0]["sha256"] = edited.clone().into()) });
+        let verified = verify_bundle(&path).unwrap();
+        assert!(verified.members.iter().all(MemberCheck::passed), "{}", verified.to_table());
+        assert_eq!(verified.recomputed, Recomputed::Mismatch);
+        assert!(verified.to_table().contains("❌ Recomputing the input does not give result hash"));
+
+        std::fs::write(&path, b"not a bundle").unwrap();
+        assert!(verify_bundle(&path).is_err());
+    }
+}
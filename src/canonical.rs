@@ -0,0 +1,87 @@
+//! Canonical signing preimage of an Output.
+//!
+//! serde_json output is not a stable byte string to sign: field order follows the struct,
+//! optional fields come and go, and metrics are floats whose formatting can change. Signers
+//! and transcript chains instead use `canonical_bytes`, a compact JSON object over the
+//! fields that identify a result, always in this order and without whitespace:
+//!
+//! ```text
+//! {"schema_version":1,"result_hash":"…","result_dtype":"f32","precision":"fp32",
+//!  "matrix_a_shape":[16,50240],"matrix_b_shape":[50240,16],"result_shape":[16,16]}
+//! ```
+//!
+//! (shown wrapped). Values are strings and unsigned integers only; strings use
+//! serde_json's escaping. Metrics, build metadata and warnings are not part of it. Outputs
+//! do not carry digests of their inputs yet; adding those, or any other change to the
+//! preimage, bumps `SCHEMA_VERSION`.
+
+use crate::types;
+
+/// Version of the preimage layout, its first field
+pub const SCHEMA_VERSION: u32 = 1;
+
+/// The canonical signing preimage of `output`
+pub fn canonical_bytes(output: &types::Output) -> Vec<u8> {
+    let metadata = &output.metadata;
+    let string = |value: &str| serde_json::to_string(value).expect("strings serialize");
+    let shape = |(rows, cols): (usize, usize)| format!("[{},{}]", rows, cols);
+    let fields = [
+        ("schema_version", SCHEMA_VERSION.to_string()),
+        ("result_hash", string(&output.result_hash)),
+        ("result_dtype", string(&metadata.result_dtype)),
+        ("precision", string(&metadata.precision)),
+        ("matrix_a_shape", shape(metadata.matrix_a_shape)),
+        ("matrix_b_shape", shape(metadata.matrix_b_shape)),
+        ("result_shape", shape(metadata.result_shape)),
+    ];
+    let body: Vec<String> = fields.iter().map(|(name, value)| format!("\"{}\":{}", name, value)).collect();
+    format!("{{{}}}", body.join(",")).into_bytes()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::types::Input;
+    use crate::{compute_workload, FlatMatrix};
+    use sha2::Digest;
+
+    fn fixture() -> types::Output {
+        let input = Input::matmul(
+            FlatMatrix { data: vec![1.0, 2.0, 3.0, 4.0, 5.0, 6.0], rows: 2, cols: 3 },
+            FlatMatrix { data: vec![1.0, 0.0, 0.0, 1.0, 1.0, 1.0], rows: 3, cols: 2 },
+            "fp32",
+        );
+        compute_workload(input).unwrap()
+    }
+
+    #[test]
+    fn test_preimage_bytes_are_pinned() {
+        // Changing these bytes breaks every signature made so far: bump SCHEMA_VERSION instead
+        let expected = concat!(
+            r#"{"schema_version":1,"#,
+            r#""result_hash":"5becf853ffe31b15df1b9a788970d8575b060cce2f9c8bcd42f58c375409bc29","#,
+            r#""result_dtype":"f32","precision":"fp32","#,
+            r#""matrix_a_shape":[2,3],"matrix_b_shape":[3,2],"result_shape":[2,2]}"#,
+        );
+        let bytes = canonical_bytes(&fixture());
+        assert_eq!(String::from_utf8(bytes.clone()).unwrap(), expected);
+        assert_eq!(hex::encode(sha2::Sha256::digest(&bytes)), "0b66cb5c4b315d3d82f4e80436ba26c721edd4b4fc6ab60382c2408c6788e168");
+    }
+
+    #[test]
+    fn test_preimage_ignores_metrics_and_build_metadata() {
+        let output = fixture();
+        let mut other = output.clone();
+        other.metrics.latency_ms *= 3.0;
+        other.metrics.parse_time_ms = Some(0.1 + 0.2);
+        other.metadata.compiler_flags = Some("-O0".to_string());
+        other.metadata.kernel = None;
+        other.warnings.clear();
+        assert_eq!(canonical_bytes(&other), canonical_bytes(&output));
+
+        other.result_hash.replace_range(0..1, "\"");
+        let bytes = String::from_utf8(canonical_bytes(&other)).unwrap();
+        assert!(bytes.contains(r#""result_hash":"\""#), "{}", bytes);
+        assert!(!bytes.contains(char::is_whitespace));
+    }
+}
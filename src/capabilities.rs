@@ -0,0 +1,182 @@
+//! What this build can compute (`GET /capabilities`), and requests that depend on it.
+//!
+//! Pools dispatch to mixed solver versions, and a solver that silently ignores a mode it
+//! does not know returns a wrong but plausible hash. `Capabilities::current` describes the
+//! running build from the same sources dispatch uses: precisions and kernels from the
+//! kernel registry (only kernels whose CPU and build features are present), hash modes and
+//! inputs by asking `HashRounding::from_mode` and `ResultDtype::for_hash_input`, workload
+//! types from `WORKLOAD_TYPES`, plus compiled-in cargo features.
+//!
+//! Each capability also has a name, `<kind>:<value>` (e.g. `precision:fp16`,
+//! `hash_input:i32le`, `seed_profile:blake3-xof-v1`, `schema_version:1`), and a request can
+//! list the names it relies on. `Capabilities::missing` returns the ones this build lacks,
+//! which the API answers with 426 before computing anything. Unknown kinds are missing too.
+
+use crate::kernels;
+use crate::{HashRounding, Precision, ResultDtype, WORKLOAD_TYPES};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeSet;
+
+/// Version of the document layout; bumped when a field changes meaning or goes away
+pub const CAPABILITIES_VERSION: u32 = 1;
+
+/// Seed generation as `generate_matrices_from_seed` does it: one blake3 XOF stream, A as
+/// u8 bytes followed by B as offset i8 bytes, both row-major (see `SeedLayout`)
+pub const SEED_PROFILE_V1: &str = "blake3-xof-v1";
+
+/// Cargo features that change what a build can do
+const FEATURES: [(&str, bool); 9] = [
+    ("openblas", cfg!(feature = "openblas")),
+    ("fp16", cfg!(feature = "fp16")),
+    ("blake3-hash", cfg!(feature = "blake3-hash")),
+    ("seed-gen", cfg!(feature = "seed-gen")),
+    ("api", cfg!(feature = "api")),
+    ("dashboard", cfg!(feature = "dashboard")),
+    ("counting-alloc", cfg!(feature = "counting-alloc")),
+    ("parallel", cfg!(feature = "parallel")),
+    ("unstable-internals", cfg!(feature = "unstable-internals")),
+];
+
+/// Size limits a server applies to requests
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct MaxDims {
+    /// Caps on each supplied matrix, applied while parsing (`numbers::MatrixLimits`)
+    pub max_rows: usize,
+    pub max_cols: usize,
+    pub max_elements: usize,
+    /// Largest seed-generated matrix, in elements
+    pub max_seed_elements: usize,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Capabilities {
+    pub capabilities_version: u32,
+    /// Crate version of the solver
+    pub solver_version: String,
+    /// Versions of the canonical signing preimage this build produces (`canonical`)
+    pub schema_versions: Vec<u32>,
+    pub precisions: Vec<String>,
+    pub workload_types: Vec<String>,
+    /// "sha256" for result hashes; "blake3" when tile traces are available
+    pub hash_algorithms: Vec<String>,
+    /// `hash_mode` values
+    pub hash_modes: Vec<String>,
+    /// `hash_input` values
+    pub hash_inputs: Vec<String>,
+    /// Seed generation schemes; empty without the `seed-gen` feature
+    pub seed_profiles: Vec<String>,
+    /// Kernels this machine can run, in registration order
+    pub kernels: Vec<String>,
+    /// Compiled-in cargo features
+    pub features: Vec<String>,
+    /// Request size limits, when served by the API
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_dims: Option<MaxDims>,
+}
+
+impl Capabilities {
+    /// The document for this build and machine
+    pub fn current(max_dims: Option<MaxDims>) -> Self {
+        let kernels = kernels::available_kernels();
+        let precisions: Vec<&str> = Precision::ALL
+            .iter()
+            .filter(|p| p.is_available() && kernels.iter().any(|k| k.precisions().contains(&p.as_str())))
+            .map(Precision::as_str)
+            .collect();
+        let hash_modes = ["exact", "rounded"].into_iter().filter(|mode| HashRounding::from_mode(mode, None, None).is_ok());
+        let hash_inputs = ["f32le", "i32le", "f64le"]
+            .into_iter()
+            .filter(|input| precisions.iter().any(|p| ResultDtype::for_hash_input(p, Some(input)).is_ok()));
+        Self {
+            capabilities_version: CAPABILITIES_VERSION,
+            solver_version: env!("CARGO_PKG_VERSION").to_string(),
+            schema_versions: vec![crate::canonical::SCHEMA_VERSION],
+            precisions: strings(precisions.iter().copied()),
+            workload_types: strings(WORKLOAD_TYPES),
+            hash_algorithms: strings(["sha256"].into_iter().chain(cfg!(feature = "blake3-hash").then_some("blake3"))),
+            hash_modes: strings(hash_modes),
+            hash_inputs: strings(hash_inputs),
+            seed_profiles: strings(cfg!(feature = "seed-gen").then_some(SEED_PROFILE_V1)),
+            kernels: kernels.iter().map(|k| k.name().to_string()).collect(),
+            features: strings(FEATURES.into_iter().filter(|f| f.1).map(|f| f.0)),
+            max_dims,
+        }
+    }
+
+    /// Every capability name, `<kind>:<value>`
+    pub fn names(&self) -> BTreeSet<String> {
+        let kinds: [(&str, Vec<String>); 9] = [
+            ("schema_version", self.schema_versions.iter().map(u32::to_string).collect()),
+            ("precision", self.precisions.clone()),
+            ("workload_type", self.workload_types.clone()),
+            ("hash_algorithm", self.hash_algorithms.clone()),
+            ("hash_mode", self.hash_modes.clone()),
+            ("hash_input", self.hash_inputs.clone()),
+            ("seed_profile", self.seed_profiles.clone()),
+            ("kernel", self.kernels.clone()),
+            ("feature", self.features.clone()),
+        ];
+        kinds
+            .into_iter()
+            .flat_map(|(kind, values)| values.into_iter().map(move |value| format!("{}:{}", kind, value)))
+            .collect()
+    }
+
+    /// The names in `required` this build lacks, in the order given
+    pub fn missing(&self, required: &[String]) -> Vec<String> {
+        let names = self.names();
+        required.iter().filter(|name| !names.contains(name.trim())).cloned().collect()
+    }
+}
+
+fn strings<'a>(items: impl IntoIterator<Item = &'a str>) -> Vec<String> {
+    items.into_iter().map(str::to_string).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_document_reflects_the_build() {
+        let caps = Capabilities::current(None);
+        assert_eq!(caps.capabilities_version, CAPABILITIES_VERSION);
+        assert_eq!(caps.schema_versions, [crate::canonical::SCHEMA_VERSION]);
+        assert_eq!(caps.precisions, crate::tests::precisions());
+        assert_eq!(caps.workload_types, ["matmul", "quantize", "batched_matmul"]);
+        assert_eq!(caps.hash_modes, ["exact", "rounded"]);
+        assert_eq!(caps.hash_inputs, ["f32le", "i32le", "f64le"]);
+        assert_eq!(caps.features.contains(&"fp16".to_string()), cfg!(feature = "fp16"));
+        assert_eq!(caps.features.contains(&"openblas".to_string()), cfg!(feature = "openblas"));
+        assert_eq!(caps.hash_algorithms.contains(&"blake3".to_string()), cfg!(feature = "blake3-hash"));
+        assert_eq!(caps.kernels.contains(&"fp16_16x16".to_string()), cfg!(feature = "fp16"));
+        assert!(caps.kernels.contains(&"u8i8_16x16".to_string()));
+        // Advertised with the feature only, although the tests always compile seed generation
+        assert_eq!(caps.seed_profiles.contains(&SEED_PROFILE_V1.to_string()), cfg!(feature = "seed-gen"));
+
+        // Every advertised precision and kernel actually dispatches
+        for precision in &caps.precisions {
+            let shape = (16, 64);
+            assert!(kernels::resolve_kernel(precision, shape, (64, 16), None).is_ok(), "{}", precision);
+        }
+        for kernel in &caps.kernels {
+            assert!(kernels::kernel_by_name(kernel).is_some(), "{}", kernel);
+        }
+    }
+
+    #[test]
+    fn test_missing_capabilities() {
+        let caps = Capabilities::current(None);
+        let names = caps.names();
+        assert!(names.contains("precision:u8i8"));
+        assert!(names.contains("hash_input:i32le"));
+        assert!(names.contains("schema_version:1"));
+        assert_eq!(names.contains("precision:fp16"), cfg!(feature = "fp16"));
+
+        let required: Vec<String> = ["precision:u8i8", "seed_profile:blake3-xof-v2", "hash_mode:exact", "teleport:yes"]
+            .map(str::to_string)
+            .to_vec();
+        assert_eq!(caps.missing(&required), ["seed_profile:blake3-xof-v2", "teleport:yes"]);
+        assert!(caps.missing(&[]).is_empty());
+    }
+}
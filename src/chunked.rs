@@ -0,0 +1,307 @@
+//! Chunked reduction for the 16x16 kernels (`ComputeOptions::k_chunk`).
+//!
+//! The 16x16 kernels convert and pack all of A and B before their inner loop, so staging
+//! memory grows with k and a call cannot be stopped until the whole reduction is done.
+//! When k exceeds the chunk length the reduction is split into slices instead: each slice
+//! is packed into buffers that are reused for every slice and accumulated into the same
+//! 16x16 accumulators. Between slices the loop checks `ComputeOptions::cancel` and the
+//! deadline (against the time spent in the kernel so far).
+//!
+//! u8i8, int8 and int8_rowwise accumulate exactly in i32 with scales taken from the full
+//! operands (u8i8 slices are capped at `U8I8_SAFE_K` and added up in i64, which is also how
+//! its k beyond that bound and `Accumulator::I64` runs are computed), fp32 slices start on multiples of 4 so its four-wide groups line up, and bf16
+//! carries each running sum from one slice into the next; all of them give bit-identical
+//! results to the single-pass kernels. fp16 adds one dot product per slice, so its low bits may
+//! differ. Chunked runs never touch the pack caches.
+
+use crate::clock::Timestamp;
+#[cfg(feature = "fp16")]
+use crate::Operand;
+use crate::{
+    Accumulator, AlignedBufferF32, AlignedBufferI8, AlignedBufferU8, ComputeOptions, FlatMatrix, Int8Scaling, KernelResult, SolverError,
+    U8I8_SAFE_K,
+};
+use std::ops::Range;
+use std::sync::atomic::Ordering;
+
+/// Reduction slice length used when `ComputeOptions::k_chunk` is unset
+pub const DEFAULT_K_CHUNK: usize = 1 << 20;
+
+/// Slice length to use for a reduction of length `k`, or `None` when one pass suffices
+pub(crate) fn chunk_len(k: usize, options: &ComputeOptions) -> Option<usize> {
+    // Multiples of 4 keep the fp32 accumulation groups aligned
+    let chunk = (options.k_chunk.unwrap_or(DEFAULT_K_CHUNK).max(4) / 4) * 4;
+    (k > chunk).then_some(chunk)
+}
+
+/// Slice length for the u8i8 16x16 kernel: `chunk_len` capped at `U8I8_SAFE_K` so each
+/// slice's i32 sums are exact, and always set under `Accumulator::I64`
+pub(crate) fn u8i8_chunk_len(k: usize, options: &ComputeOptions) -> Option<usize> {
+    let chunk = chunk_len(k, options).unwrap_or(k.max(4)).min(U8I8_SAFE_K / 4 * 4);
+    (k > chunk || options.accumulator == Accumulator::I64).then_some(chunk)
+}
+
+/// Run `f` on consecutive slices of `0..k`, checking cancellation and the deadline in between
+fn for_each_chunk(
+    k: usize,
+    chunk: usize,
+    options: &ComputeOptions,
+    start: Timestamp,
+    mut f: impl FnMut(Range<usize>),
+) -> Result<(), SolverError> {
+    let total = k.div_ceil(chunk);
+    for (index, begin) in (0..k).step_by(chunk).enumerate() {
+        if index > 0 {
+            check_interrupt(options, start, index, total)?;
+        }
+        f(begin..(begin + chunk).min(k));
+    }
+    Ok(())
+}
+
+pub(crate) fn check_interrupt(options: &ComputeOptions, start: Timestamp, done: usize, total: usize) -> Result<(), SolverError> {
+    if options.cancel.as_ref().is_some_and(|flag| flag.load(Ordering::Relaxed)) {
+        return Err(SolverError::Cancelled(format!("stopped after k slice {} of {}", done, total)));
+    }
+    if let Some(deadline) = options.deadline {
+        let spent = options.clock().elapsed_since(start);
+        if spent > deadline {
+            return Err(SolverError::DeadlineExceeded(format!(
+                "kernel took {:.3} ms after k slice {} of {}, deadline was {:.3} ms",
+                spent.as_secs_f64() * 1000.0,
+                done,
+                total,
+                deadline.as_secs_f64() * 1000.0
+            )));
+        }
+    }
+    Ok(())
+}
+
+fn all_columns(columns: Option<&[usize]>) -> Vec<usize> {
+    columns.map_or_else(|| (0..16).collect(), |c| c.to_vec())
+}
+
+/// fp32 16x16 kernel over k slices (bit-identical to `matmul_fp32_16x16`)
+pub(crate) fn fp32_16x16(a: &FlatMatrix, b: &FlatMatrix, options: &ComputeOptions, chunk: usize) -> Result<KernelResult, SolverError> {
+    let clock = options.clock();
+    let start = clock.now();
+    let mut acc = vec![0.0f32; 16 * 16];
+    for_each_chunk(a.cols, chunk, options, start, |range| {
+        crate::fp32_16x16_accumulate(a, b, options.fp32_accumulation, range, &mut acc)
+    })?;
+    Ok(KernelResult::new(FlatMatrix { data: acc, rows: 16, cols: 16 }, clock.elapsed_since(start)))
+}
+
+/// fp16 16x16 kernel over k slices; each slice's dot product is rounded separately
+#[cfg(feature = "fp16")]
+pub(crate) fn fp16_16x16(a: &FlatMatrix, b: &FlatMatrix, options: &ComputeOptions, chunk: usize) -> Result<KernelResult, SolverError> {
+    let k = a.cols;
+    let columns = all_columns(options.result_columns.as_deref());
+    let n_out = columns.len();
+    let clock = options.clock();
+    let start = clock.now();
+
+    let rounding = options.fp16_rounding;
+    let mut a_h = AlignedBufferF32::new(16 * chunk, 64);
+    let mut b_t = AlignedBufferF32::new(16 * chunk, 64);
+    let mut acc = vec![0.0f32; 16 * n_out];
+    for_each_chunk(k, chunk, options, start, |range| {
+        let len = range.len();
+        unsafe {
+            let (a_ptr, b_ptr) = (a.data.as_ptr(), b.data.as_ptr());
+            let (a_h_ptr, b_t_ptr) = (a_h.as_mut_ptr(), b_t.as_mut_ptr());
+            for (q, p) in range.enumerate() {
+                for i in 0..16 {
+                    *a_h_ptr.add(i * len + q) = rounding.round(*a_ptr.add(i * k + p), Operand::A, i * k + p);
+                }
+                for j in 0..16 {
+                    *b_t_ptr.add(j * len + q) = rounding.round(*b_ptr.add(p * 16 + j), Operand::B, p * 16 + j);
+                }
+            }
+            for i in 0..16 {
+                let a_row = a_h.as_ptr().add(i * len);
+                for (jo, &j) in columns.iter().enumerate() {
+                    acc[i * n_out + jo] += crate::dot_f32(a_row, b_t.as_ptr().add(j * len), len);
+                }
+            }
+        }
+    })?;
+    Ok(KernelResult::new(FlatMatrix { data: acc, rows: 16, cols: n_out }, clock.elapsed_since(start)))
+}
+
+/// bf16 16x16 kernel over k slices (bit-identical to `matmul_bf16_16x16`)
+pub(crate) fn bf16_16x16(a: &FlatMatrix, b: &FlatMatrix, options: &ComputeOptions, chunk: usize) -> Result<KernelResult, SolverError> {
+    let k = a.cols;
+    let columns = all_columns(options.result_columns.as_deref());
+    let n_out = columns.len();
+    let clock = options.clock();
+    let start = clock.now();
+
+    let mut a_q = AlignedBufferF32::new(16 * chunk, 64);
+    let mut b_t = AlignedBufferF32::new(16 * chunk, 64);
+    let mut acc = vec![0.0f32; 16 * n_out];
+    for_each_chunk(k, chunk, options, start, |range| {
+        let len = range.len();
+        unsafe {
+            let (a_ptr, b_ptr) = (a.data.as_ptr(), b.data.as_ptr());
+            let (a_q_ptr, b_t_ptr) = (a_q.as_mut_ptr(), b_t.as_mut_ptr());
+            for (q, p) in range.enumerate() {
+                for i in 0..16 {
+                    *a_q_ptr.add(i * len + q) = crate::round_bf16(*a_ptr.add(i * k + p));
+                }
+                for j in 0..16 {
+                    *b_t_ptr.add(j * len + q) = crate::round_bf16(*b_ptr.add(p * 16 + j));
+                }
+            }
+            for i in 0..16 {
+                let row = &mut acc[i * n_out..(i + 1) * n_out];
+                crate::bf16_accumulate_row(a_q.as_ptr().add(i * len), b_t.as_ptr(), len, len, &columns, row);
+            }
+        }
+    })?;
+    Ok(KernelResult::new(FlatMatrix { data: acc, rows: 16, cols: n_out }, clock.elapsed_since(start)))
+}
+
+/// int8 16x16 kernel over k slices (bit-identical to `matmul_int8_16x16`)
+pub(crate) fn int8_16x16(
+    a: &FlatMatrix,
+    b: &FlatMatrix,
+    scaling: Int8Scaling,
+    options: &ComputeOptions,
+    chunk: usize,
+) -> Result<KernelResult, SolverError> {
+    let columns = all_columns(options.result_columns.as_deref());
+    let n_out = columns.len();
+    let clock = options.clock();
+    let start = clock.now();
+
+    // Scales come from the whole operands so every slice quantizes exactly as one pass would
+    let (scales_a, scale_b) = (scaling.a_scales(a), crate::int8_scale(b.data.iter().copied()));
+    let mut a_q = AlignedBufferI8::new(16 * chunk, 64);
+    let mut b_t = AlignedBufferI8::new(16 * chunk, 64);
+    let mut acc = vec![0i32; 16 * n_out];
+    for_each_chunk(a.cols, chunk, options, start, |range| {
+        let len = range.len();
+        crate::quantize_a_i8_cols(a, range.clone(), &scales_a, &mut a_q);
+        crate::pack_b_int8_transposed_rows(b, range, scale_b, &mut b_t);
+        for i in 0..16 {
+            let a_row = unsafe { a_q.as_ptr().add(i * len) };
+            for (jo, &j) in columns.iter().enumerate() {
+                let b_row = unsafe { b_t.as_ptr().add(j * len) };
+                acc[i * n_out + jo] += crate::dot_i8(a_row, b_row, len);
+            }
+        }
+    })?;
+    let data = acc.iter().enumerate().map(|(idx, &x)| x as f32 * (1.0 / (scales_a[idx / n_out] * scale_b))).collect();
+    Ok(KernelResult::new(FlatMatrix { data, rows: 16, cols: n_out }, clock.elapsed_since(start)))
+}
+
+/// u8i8 16x16 kernel over k slices (bit-identical to `matmul_u8i8_16x16` whenever the
+/// sums fit in i32); `chunk` must not exceed `U8I8_SAFE_K`
+pub(crate) fn u8i8_16x16(a: &FlatMatrix, b: &FlatMatrix, options: &ComputeOptions, chunk: usize) -> Result<KernelResult, SolverError> {
+    assert!(chunk <= U8I8_SAFE_K);
+    let clock = options.clock();
+    let start = clock.now();
+    let mut a_u8 = AlignedBufferU8::new(16 * chunk, 64);
+    let mut b_i8 = AlignedBufferI8::new(16 * chunk, 64);
+    let mut acc = vec![0i32; 16 * 16];
+    let mut sums = vec![0i64; 16 * 16];
+    for_each_chunk(a.cols, chunk, options, start, |range| {
+        let len = range.len();
+        crate::pack_a_u8_cols(a, range.clone(), &mut a_u8);
+        crate::pack_b_u8i8_rows(b, range, &mut b_i8);
+        acc.fill(0);
+        crate::u8i8_16x16_accumulate(&a_u8, &b_i8, len, &mut acc);
+        for (sum, &x) in sums.iter_mut().zip(&acc) {
+            *sum += x as i64;
+        }
+    })?;
+    crate::u8i8_wide_result(sums, 16, a.cols, options.accumulator, clock.elapsed_since(start))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::clock::Clock;
+    use crate::{compute_workload_with_options, generate_matrices_from_seed, types};
+    use std::sync::atomic::{AtomicBool, AtomicU64};
+    use std::sync::Arc;
+    use std::time::Duration;
+
+    const K: usize = 200_000;
+
+    fn input(precision: &str) -> types::Input {
+        let (matrix_a, matrix_b) = generate_matrices_from_seed(b"k-chunk", 16, K, K, 16);
+        types::Input::matmul(matrix_a, matrix_b, precision)
+    }
+
+    /// Clock that moves forward one millisecond every time it is read
+    #[derive(Debug, Default)]
+    struct TickingClock(AtomicU64);
+
+    impl Clock for TickingClock {
+        fn now(&self) -> Timestamp {
+            Timestamp::from_offset(Duration::from_millis(self.0.fetch_add(1, Ordering::SeqCst)))
+        }
+    }
+
+    #[test]
+    fn test_chunked_matches_single_pass() {
+        for precision in ["fp32", "bf16", "int8", "int8_rowwise", "u8i8"] {
+            let single = compute_workload_with_options(input(precision), &ComputeOptions::default()).unwrap();
+            // 30_000 slices k = 200_000 into six full slices and a short tail
+            let options = ComputeOptions { k_chunk: Some(30_000), ..Default::default() };
+            let chunked = compute_workload_with_options(input(precision), &options).unwrap();
+            assert_eq!(chunked.result_hash, single.result_hash, "{}", precision);
+        }
+
+        // fp16 rounds per slice, so only closeness is guaranteed
+        #[cfg(feature = "fp16")]
+        {
+            let single = compute_workload_with_options(input("fp16"), &ComputeOptions::default()).unwrap();
+            let options = ComputeOptions { k_chunk: Some(30_000), ..Default::default() };
+            let chunked = compute_workload_with_options(input("fp16"), &options).unwrap();
+            let scale = single.result_matrix.data.iter().fold(1.0f32, |m, x| m.max(x.abs()));
+            for (x, y) in chunked.result_matrix.data.iter().zip(&single.result_matrix.data) {
+                assert!((x - y).abs() <= 1e-4 * scale, "{} vs {}", x, y);
+            }
+        }
+    }
+
+    #[test]
+    fn test_chunked_columns_and_short_k() {
+        let columns = vec![15, 0, 7];
+        let options = |k_chunk| ComputeOptions { k_chunk, result_columns: Some(columns.clone()), ..Default::default() };
+        let single = compute_workload_with_options(input("int8"), &options(None)).unwrap();
+        let chunked = compute_workload_with_options(input("int8"), &options(Some(65_536))).unwrap();
+        assert_eq!(chunked.result_hash, single.result_hash);
+
+        assert_eq!(chunk_len(K, &ComputeOptions::default()), None);
+        assert_eq!(chunk_len(K, &ComputeOptions { k_chunk: Some(30_001), ..Default::default() }), Some(30_000));
+        assert_eq!(chunk_len(8, &ComputeOptions { k_chunk: Some(1), ..Default::default() }), Some(4));
+    }
+
+    #[test]
+    fn test_cancel_between_chunks() {
+        let cancel = Arc::new(AtomicBool::new(true));
+        let options = ComputeOptions { k_chunk: Some(50_000), cancel: Some(cancel.clone()), ..Default::default() };
+        let err = compute_workload_with_options(input("u8i8"), &options).unwrap_err();
+        assert_eq!(err, SolverError::Cancelled("stopped after k slice 1 of 4".to_string()));
+
+        cancel.store(false, Ordering::Relaxed);
+        assert!(compute_workload_with_options(input("u8i8"), &options).is_ok());
+
+        // Every clock read takes 1 ms, so the kernel is over a zero deadline at the first
+        // slice boundary instead of only once it returns
+        let options = ComputeOptions {
+            k_chunk: Some(50_000),
+            clock: Some(Arc::new(TickingClock::default())),
+            deadline: Some(Duration::ZERO),
+            ..Default::default()
+        };
+        let err = compute_workload_with_options(input("fp32"), &options).unwrap_err();
+        assert!(matches!(err, SolverError::DeadlineExceeded(_)), "{}", err);
+        assert!(err.to_string().contains("after k slice 1 of 4"), "{}", err);
+    }
+}
@@ -0,0 +1,1331 @@
+//! The `matmul-solver` command line, as library functions `main.rs` wires to argv.
+//!
+//! A single-workload run is `load_input` (file or seed, timed), compute, `write_output`
+//! (full, delta or audited, with the serialization timed) and optional verification;
+//! `run` does all of it for parsed `Args` and returns a `RunSummary` holding what the
+//! binary prints. Nothing here but `run_command` (the subcommands) prints or exits.
+
+use crate::{
+    acceptance, add_timing_breakdown, audit, batch, bench, compute_workload_with_options, delta, envelope,
+    fused, host, memory, numbers, profile, simd, soak, tolerance::TolerancePolicy, types, variance, verify_batch, verify_correctness_with_rounding, verify_with_metadata, warnings,
+    Accumulator, Clock, ComputeOptions, Fp16Rounding, Fp32Accumulation, Fp32Summation, HashRounding, Redundancy, ResultDtype, SolverError,
+    SystemClock,
+};
+#[cfg(feature = "bundle")]
+use crate::{bundle, SeedDims};
+#[cfg(feature = "cross-check")]
+use crate::cross_check;
+use clap::{Parser, Subcommand};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+
+#[derive(Parser, Debug)]
+#[command(author, version, about, long_about = None)]
+pub struct Args {
+    /// Input JSON file path
+    #[arg(short, long)]
+    pub input: Option<String>,
+
+    /// Output JSON file path
+    #[arg(short, long, default_value = "outputs/output.json")]
+    pub output: String,
+
+    /// Generate matrices from seed (hex string) instead of JSON file
+    /// For seed dimensions: generates 16×50240 × 50240×16 matrices
+    #[arg(long)]
+    pub seed: Option<String>,
+
+    /// Precision to use (fp32, fp32_strict, fp64, fp16, bf16, int8, int8_rowwise, int8_asym, int4, u8i8)
+    /// Required when using --seed
+    #[arg(long)]
+    pub precision: Option<String>,
+
+    /// Verify correctness by recomputing and checking hash
+    #[arg(long)]
+    pub verify: bool,
+
+    /// Directory for persisting packed B buffers between runs
+    /// Subsequent runs with the same B skip re-packing
+    #[arg(long)]
+    pub pack_cache_dir: Option<String>,
+
+    /// Force a registered kernel by name (e.g. fp32_blocked, u8i8_16x16)
+    #[arg(long)]
+    pub kernel: Option<String>,
+
+    /// Threads for large fp32 products on the blocked kernel and for the 16x16 seed kernels'
+    /// rows (builds with the `parallel` feature; the blocked kernel uses rayon's default,
+    /// every core or RAYON_NUM_THREADS, when omitted, the seed kernels run serially)
+    #[arg(long)]
+    pub threads: Option<usize>,
+
+    /// Run the dot products and u8i8 16x16 inner loop on this SIMD path instead of the
+    /// detected one (avx512vnni, avx2, i8mm, dotprod, neon or scalar), e.g. to A/B them
+    #[arg(long)]
+    pub simd: Option<String>,
+
+    /// Compute only these result columns (comma-separated indices, e.g. 3,7,12)
+    #[arg(long, value_delimiter = ',')]
+    pub result_columns: Option<Vec<usize>>,
+
+    /// Hash mode: exact (default) or rounded
+    #[arg(long, default_value = "exact")]
+    pub hash_mode: String,
+
+    /// With --hash-mode rounded: mantissa bits kept per element (default 16)
+    #[arg(long)]
+    pub hash_mantissa_bits: Option<u32>,
+
+    /// With --hash-mode rounded: round each element to a multiple of this instead
+    #[arg(long)]
+    pub hash_quantum: Option<f64>,
+
+    /// Record per-tile digests and a trace root in the output (for fraud proofs)
+    #[arg(long)]
+    pub tile_trace: bool,
+
+    /// Reject JSON numbers that overflow f32, or lose integer precision for int8/u8i8
+    #[arg(long)]
+    pub strict_numbers: bool,
+
+    /// How matrix rows are written: array (default), map (objects keyed by column index,
+    /// missing trailing columns read as 0.0) or map_strict (objects giving every column)
+    #[arg(long, default_value = "array")]
+    pub input_row_format: String,
+
+    /// Match precision, workload_type and --kernel names exactly; by default they are
+    /// trimmed and matched case-insensitively ("FP32", " fp16")
+    #[arg(long)]
+    pub exact_names: bool,
+
+    /// fp32 16x16 accumulation order: strict (default, reproducible) or fast (FMA when available)
+    #[arg(long, default_value = "strict")]
+    fp32_accumulation: String,
+
+    /// fp32 summation over k: naive (default), kahan (compensated) or pairwise (tree of
+    /// 32-step blocks); both of the latter drift far less than naive over long k
+    #[arg(long, default_value = "naive")]
+    pub fp32_summation: String,
+
+    /// int8/u8i8 accumulator: i32 (default), i64 (u8i8 only: exact sums for any k), or
+    /// i16_saturating / i32_saturating to emulate fixed-width hardware that clamps the running sum
+    #[arg(long, default_value = "i32")]
+    pub accumulator: String,
+
+    /// fp16 operand conversion: nearest_even (default), toward_zero, or stochastic
+    /// (needs --fp16-rounding-seed)
+    #[arg(long, default_value = "nearest_even")]
+    pub fp16_rounding: String,
+
+    /// With --fp16-rounding stochastic: seed of the rounding decisions
+    #[arg(long)]
+    pub fp16_rounding_seed: Option<u64>,
+
+    /// single (default), or dual: compute twice (on a second bit-identical kernel where
+    /// one exists) and fail unless both hashes agree, retrying once
+    #[arg(long, default_value = "single")]
+    pub redundancy: String,
+
+    /// Also compute a reduced-precision matmul at fp32 and record that reference's hash and
+    /// largest difference from the result in `auxiliary_results`
+    #[arg(long)]
+    pub error_metrics: bool,
+
+    /// With --error-metrics, include the reference matrix in the output too
+    #[arg(long)]
+    pub aux_matrices: bool,
+
+    /// strict, default or loose: the tolerances of every comparison in the run (error
+    /// metrics, cross-check, acceptance), per precision
+    #[arg(long)]
+    pub tolerance_profile: Option<String>,
+
+    /// Override the profile's tolerance of one precision, as PRECISION=REL,ABS (repeatable)
+    #[arg(long = "tolerance")]
+    pub tolerances: Vec<String>,
+
+    /// Re-read the written output, recompute its hash from the file and fail on mismatch
+    #[arg(long)]
+    pub audit_output: bool,
+
+    /// Write only the result elements that differ from this earlier output (see `reconstruct`)
+    #[arg(long)]
+    pub baseline: Option<String>,
+
+    /// Hash preimage: f32le (default), i32le (u8i8 only: the exact i32 accumulators) or
+    /// f64le (fp64 only, and its default: the unrounded f64 result)
+    #[arg(long)]
+    pub hash_input: Option<String>,
+
+    /// Refuse or abort workloads that need more memory than this (bytes, or e.g. 512M, 2G)
+    #[arg(long)]
+    pub max_memory: Option<String>,
+
+    /// Reduction slice length for the 16x16 kernels (default 1048576); longer k is packed
+    /// and accumulated one slice at a time
+    #[arg(long)]
+    pub k_chunk: Option<usize>,
+
+    /// Smallest k that takes the packed fp32/int8/u8i8 16x16 kernels (default 16);
+    /// shorter reductions run on the plain kernels (see bench-crossover)
+    #[arg(long)]
+    pub packed_min_k: Option<usize>,
+
+    /// Also write the run as a reproducible-case archive (.tar.gz) of the input or seed,
+    /// output, execution report and warnings (see `bundle verify`)
+    #[cfg(feature = "bundle")]
+    #[arg(long)]
+    pub bundle: Option<String>,
+
+    #[command(subcommand)]
+    pub command: Option<Command>,
+}
+
+#[derive(Subcommand, Debug)]
+pub enum Command {
+    /// Time generation, packing, kernel and hash of the seed workload in isolation
+    ProfileSeed {
+        /// Seed (hex string)
+        #[arg(long)]
+        seed: String,
+
+        /// Precision to profile (u8i8 or int8)
+        #[arg(long, default_value = "u8i8")]
+        precision: String,
+
+        #[arg(long, default_value_t = 20)]
+        iterations: usize,
+
+        /// Reduction dimension k (matrices are 16×k × k×16)
+        #[arg(long, default_value_t = 50240)]
+        k: usize,
+
+        /// Write the JSON report here
+        #[arg(long, default_value = "outputs/profile_seed.json")]
+        report: String,
+    },
+    /// Time the benchmark suite and fail if a workload got slower than the stored baseline
+    BenchRegress {
+        /// Baseline JSON; written when missing or with --update-baseline
+        #[arg(long, default_value = "bench/baseline.json")]
+        baseline: String,
+
+        /// Time the suite on this machine (slow; CI opts in)
+        #[arg(long)]
+        run: bool,
+
+        /// Compare this previously saved run instead of timing (see --save)
+        #[arg(long, conflicts_with = "run")]
+        current: Option<String>,
+
+        /// Also write this run's timings here
+        #[arg(long)]
+        save: Option<String>,
+
+        #[arg(long, default_value_t = 10)]
+        iterations: usize,
+
+        /// Fail when a median kernel time grew by more than this many percent
+        #[arg(long, default_value_t = 10.0)]
+        threshold_pct: f64,
+
+        /// Replace the baseline with this run instead of comparing
+        #[arg(long)]
+        update_baseline: bool,
+
+        /// Only run these workloads of the suite (comma-separated names)
+        #[arg(long, value_delimiter = ',')]
+        workloads: Option<Vec<String>>,
+
+        /// Flush the in-process packed-B caches before every iteration (worst-case timings)
+        #[arg(long, requires = "run")]
+        cold_iterations: bool,
+
+        /// With --cold-iterations, also overwrite a buffer this large to evict the
+        /// last-level cache (bytes, or e.g. 64M)
+        #[arg(long, requires = "cold_iterations")]
+        evict_llc: Option<String>,
+    },
+    /// Time the packed 16x16 kernel against the plain one over k, to place --packed-min-k
+    BenchCrossover {
+        #[arg(long, default_value = "fp32")]
+        precision: String,
+
+        /// Reduction lengths to time (comma-separated)
+        #[arg(long, value_delimiter = ',', default_value = "16,64,256,512,1024,2048,4096,16384,50240")]
+        ks: Vec<usize>,
+
+        #[arg(long, default_value_t = 20)]
+        iterations: usize,
+    },
+    /// Time the u8i8 16x16 inner loop on every SIMD path this CPU supports
+    BenchSimd {
+        /// Reduction length (the seed shape's by default)
+        #[arg(long, default_value_t = 50240)]
+        k: usize,
+
+        #[arg(long, default_value_t = 20)]
+        iterations: usize,
+
+        #[arg(long, default_value = "outputs/bench_simd.json")]
+        report: String,
+    },
+    /// Compare the seed workload with an external reference command (see `cross_check`)
+    #[cfg(feature = "cross-check")]
+    CrossCheck {
+        /// Shell command of the reference; gets the request on stdin, answers on stdout
+        #[arg(long)]
+        reference_cmd: String,
+
+        /// Seed (hex string)
+        #[arg(long)]
+        seed: String,
+
+        #[arg(long, default_value = "fp32")]
+        precision: String,
+
+        /// Reduction dimension k (matrices are 16×k × k×16)
+        #[arg(long, default_value_t = 50240)]
+        k: usize,
+
+        /// Kill the reference after this many seconds
+        #[arg(long, default_value_t = 300)]
+        timeout_secs: u64,
+
+        /// Write the JSON report here
+        #[arg(long, default_value = "outputs/cross_check.json")]
+        report: String,
+    },
+    /// Recompute a file of seed claims and report which hashes match (see `verify_batch`)
+    VerifyBatch {
+        /// JSON array of claims: {"seed", "dims"?, "precision", "expected_hash", "hash_input"?}
+        #[arg(long)]
+        claims: String,
+
+        /// Worker threads
+        #[arg(long, default_value_t = 1)]
+        jobs: usize,
+
+        /// Write the per-claim JSON report here
+        #[arg(long, default_value = "outputs/verify_batch.json")]
+        report: String,
+    },
+    /// Run a generated suite of workloads against the reference kernels (see `acceptance`)
+    Acceptance {
+        /// Seeds the workload generator; the same seed gives the same suite everywhere
+        #[arg(long, default_value_t = 7)]
+        rng_seed: u64,
+
+        /// Number of workloads
+        #[arg(long, default_value_t = 50)]
+        count: usize,
+
+        /// Largest operand or result, in elements (e.g. 4e6)
+        #[arg(long, default_value_t = 4e6)]
+        max_elems: f64,
+
+        /// Write the JSON report here
+        #[arg(long, default_value = "outputs/acceptance.json")]
+        out: String,
+
+        /// Report of the same suite from a trusted machine; fail on any hash or verdict difference
+        #[arg(long)]
+        trusted: Option<String>,
+    },
+    /// Compute every *.json input in a directory and summarize per-input results and latency
+    /// percentiles (see `batch`); uses the top-level compute flags
+    Batch {
+        #[arg(long)]
+        input_dir: String,
+
+        /// Outputs (<input stem>.json) and the summary go here
+        #[arg(long, default_value = "outputs/batch")]
+        output_dir: String,
+
+        /// json (summary.json) or csv (summary.csv)
+        #[arg(long, default_value = "json")]
+        summary_format: String,
+    },
+    /// Rebuild a full output from a baseline output and a delta written with --baseline
+    Reconstruct {
+        /// The output the delta was made against
+        #[arg(long)]
+        baseline: String,
+
+        #[arg(long)]
+        delta: String,
+
+        /// Write the reconstructed output here
+        #[arg(short, long, default_value = "outputs/output.json")]
+        output: String,
+    },
+    /// Compute and verify sequential nonces for a long time, failing on memory growth or a
+    /// drifting control hash (see `soak`); uses the top-level compute flags
+    Soak {
+        /// Seed prefix (hex string); iteration i uses the seed prefix || i as u64 LE
+        #[arg(long)]
+        seed_prefix: String,
+
+        /// How long to run, e.g. 90, 30s, 10m, 1h
+        #[arg(long, default_value = "1h")]
+        duration: String,
+
+        #[arg(long, default_value = "u8i8")]
+        precision: String,
+
+        /// Reduction dimension k (matrices are 16×k × k×16)
+        #[arg(long, default_value_t = 50240)]
+        k: usize,
+
+        /// Iterations between memory and throughput samples
+        #[arg(long, default_value_t = 1000)]
+        sample_every: u64,
+
+        /// Iterations between re-runs of the control seed
+        #[arg(long, default_value_t = 10000)]
+        control_every: u64,
+
+        /// Fail when RSS or allocated memory grows faster than this many bytes per iteration
+        #[arg(long, default_value_t = 64.0)]
+        max_trend: f64,
+
+        /// The JSON report, rewritten at most every 10 seconds while running
+        #[arg(long, default_value = "outputs/soak.json")]
+        report: String,
+    },
+    /// Compute the seed workload many times and report how noisy the kernel time is on this
+    /// host (see `variance`); uses the top-level compute flags
+    Variance {
+        /// Seed (hex string)
+        #[arg(long)]
+        seed: String,
+
+        #[arg(long, default_value = "u8i8")]
+        precision: String,
+
+        #[arg(long, default_value_t = 50)]
+        runs: usize,
+
+        /// Reduction dimension k (matrices are 16×k × k×16)
+        #[arg(long, default_value_t = 50240)]
+        k: usize,
+
+        /// Pin to one core and flush the packed-B and last-level caches before every run
+        #[arg(long)]
+        isolate: bool,
+
+        /// Write the JSON report here
+        #[arg(long, default_value = "outputs/variance.json")]
+        report: String,
+    },
+    /// Print the envelope and payload header of a binary solver artifact, such as a
+    /// packed-B cache entry (see `envelope`)
+    Inspect {
+        file: String,
+    },
+    /// Re-send traffic captured by an API server (CAPTURE_FILE) on its original schedule,
+    /// scaled by --rate, checking the result hashes it recorded (see `traffic`)
+    #[cfg(feature = "client")]
+    Replay {
+        /// The capture, one request per line
+        #[arg(long)]
+        file: String,
+
+        /// Speed relative to the capture: 1x, 2x, 0.5x, ... or max (all at once)
+        #[arg(long, default_value = "1x")]
+        rate: String,
+
+        /// Server to send to, e.g. http://host:8000
+        #[arg(long)]
+        target: String,
+
+        /// Bearer token for /compute
+        #[arg(long)]
+        token: Option<String>,
+
+        /// Write the JSON report here
+        #[arg(long, default_value = "outputs/replay.json")]
+        report: String,
+    },
+    /// Work with archives written by --bundle
+    #[cfg(feature = "bundle")]
+    Bundle {
+        #[command(subcommand)]
+        command: BundleCommand,
+    },
+}
+
+#[cfg(feature = "bundle")]
+#[derive(Subcommand, Debug)]
+pub enum BundleCommand {
+    /// Check every member against the manifest and recompute the bundled input on this
+    /// machine, failing unless it reproduces the bundled result hash
+    Verify {
+        file: String,
+    },
+}
+
+impl Args {
+    /// The compute flags as `ComputeOptions`
+    pub fn compute_options(&self) -> Result<ComputeOptions, SolverError> {
+        match self.threads {
+            Some(0) => return Err(SolverError::InvalidInput("--threads must be at least 1".to_string())),
+            Some(threads) if threads > 1 && !cfg!(feature = "parallel") => {
+                return Err(SolverError::InvalidInput("--threads needs a build with the parallel feature".to_string()))
+            }
+            _ => {}
+        }
+        Ok(ComputeOptions {
+            pack_cache_dir: self.pack_cache_dir.as_ref().map(Into::into),
+            kernel: self.kernel.clone(),
+            result_columns: self.result_columns.clone(),
+            hash_rounding: HashRounding::from_mode(&self.hash_mode, self.hash_mantissa_bits, self.hash_quantum)?,
+            tile_trace: self.tile_trace,
+            fp32_accumulation: Fp32Accumulation::parse(&self.fp32_accumulation)?,
+            fp32_summation: Fp32Summation::parse(&self.fp32_summation)?,
+            max_memory_bytes: self.max_memory.as_deref().map(memory::parse_size).transpose()?,
+            k_chunk: self.k_chunk,
+            packed_min_k: self.packed_min_k,
+            accumulator: Accumulator::parse(&self.accumulator)?,
+            fp16_rounding: Fp16Rounding::from_mode(&self.fp16_rounding, self.fp16_rounding_seed)?,
+            redundancy: Redundancy::parse(&self.redundancy)?,
+            exact_names: self.exact_names,
+            error_metrics: self.error_metrics,
+            aux_result_matrices: self.aux_matrices,
+            tolerance: self.tolerance_policy()?,
+            threads: self.threads,
+            ..Default::default()
+        })
+    }
+
+    /// Apply `--simd` to this process (see simd.rs); without it the paths are detected
+    pub fn force_simd(&self) -> Result<(), SolverError> {
+        match &self.simd {
+            Some(name) => simd::force(simd::SimdVariant::parse(name)?),
+            None => Ok(()),
+        }
+    }
+
+    /// `--tolerance-profile` with the `--tolerance` overrides
+    pub fn tolerance_policy(&self) -> Result<TolerancePolicy, SolverError> {
+        TolerancePolicy::parse(self.tolerance_profile.as_deref(), &self.tolerances)
+    }
+
+    /// `--seed` (which needs `--precision`), else `--input`, else inputs/input.json
+    pub fn input_source(&self) -> Result<InputSource, SolverError> {
+        match &self.seed {
+            Some(seed_hex) => {
+                let precision = self.precision.clone().ok_or_else(|| {
+                    SolverError::InvalidInput("--precision is required when using --seed".to_string())
+                })?;
+                Ok(InputSource::Seed { seed_hex: seed_hex.clone(), precision })
+            }
+            None => Ok(InputSource::File(self.input.as_deref().unwrap_or("inputs/input.json").into())),
+        }
+    }
+}
+
+/// Where a workload comes from
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum InputSource {
+    /// An input JSON document
+    File(PathBuf),
+    /// 16×50240 × 50240×16 matmul matrices generated from a hex seed
+    Seed { seed_hex: String, precision: String },
+}
+
+/// How a `File` source is parsed; seeds ignore it
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum InputFormat {
+    /// JSON, counting lossy numbers
+    #[default]
+    Json,
+    /// JSON, rejecting lossy numbers (`--strict-numbers`, see `numbers::check_strict`)
+    StrictJson,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ParseTiming {
+    /// Reading and parsing the file, or generating the seed matrices
+    pub parse_time_ms: f64,
+    /// Lossy numbers in a file source; None for seeds
+    pub lossy_parse_count: Option<usize>,
+}
+
+/// Load the workload of `source`, timing it on `clock`
+pub fn load_input(source: &InputSource, format: InputFormat, clock: &dyn Clock) -> Result<(types::Input, ParseTiming), SolverError> {
+    let start = clock.now();
+    let (input, lossy_parse_count) = match source {
+        InputSource::Seed { seed_hex, precision } => {
+            // Seed dimensions: 16×50240 × 50240×16
+            let (matrix_a, matrix_b) =
+                crate::generate_matrices_from_seed_hex(seed_hex, 16, 50240, 50240, 16).map_err(SolverError::InvalidInput)?;
+            let input = types::Input {
+                matrix_a,
+                matrix_b,
+                precision: precision.clone(),
+                workload_type: Some("matmul".to_string()),
+                ..Default::default()
+            };
+            (input, None)
+        }
+        InputSource::File(path) => {
+            let json = read_json(path, "an Input JSON document")?;
+            let (input, lossy) = numbers::parse_input_json(&json, format == InputFormat::StrictJson)?;
+            (input, Some(lossy))
+        }
+    };
+    let parse_time_ms = clock.elapsed_since(start).as_secs_f64() * 1000.0;
+    Ok((input, ParseTiming { parse_time_ms, lossy_parse_count }))
+}
+
+/// Where `write_output` puts the output
+pub enum OutputSink<'a> {
+    File(&'a Path),
+    /// Any writer; `OutputStyle::Audited` needs a file
+    Writer(&'a mut dyn Write),
+}
+
+/// What `write_output` writes
+#[derive(Debug, Clone, Copy)]
+pub enum OutputStyle<'a> {
+    /// The whole output, pretty-printed
+    Full,
+    /// Only the result elements that differ from this earlier output (`--baseline`)
+    Delta(&'a types::Output),
+    /// The whole output, published only once re-reading it reproduces `result_hash`
+    /// (`--audit-output`, `audit::write_output_audited`)
+    Audited,
+}
+
+/// What `write_output` wrote
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Written {
+    Full,
+    /// `changed` of `total` result elements differ from the baseline
+    Delta { changed: usize, total: usize },
+    Audited,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct WriteTiming {
+    /// Serializing the full output once, as recorded in its `metrics.serialize_time_ms`
+    pub serialize_time_ms: f64,
+    pub written: Written,
+    /// The output's result hash; a full output computed with
+    /// `ComputeOptions::defer_result_hash` has it computed while it is written
+    pub result_hash: String,
+}
+
+/// Write `output` in `style`, with the time one serialization takes recorded in the
+/// written `metrics.serialize_time_ms` (parse time is kept as `output` has it)
+pub fn write_output(sink: OutputSink, output: &types::Output, style: OutputStyle, clock: &dyn Clock) -> Result<WriteTiming, SolverError> {
+    let serialize_error = |e: serde_json::Error| SolverError::Internal(format!("failed to serialize output: {}", e));
+    let start = clock.now();
+    let _ = serde_json::to_string_pretty(output).map_err(serialize_error)?;
+    let serialize_time_ms = clock.elapsed_since(start).as_secs_f64() * 1000.0;
+    let mut output = add_timing_breakdown(output.clone(), output.metrics.parse_time_ms, Some(serialize_time_ms));
+
+    let write_error = |e: std::io::Error| SolverError::Internal(format!("failed to write output: {}", e));
+    let (json, written) = match style {
+        // Streamed to the sink, hashing the result as it goes (fused.rs)
+        OutputStyle::Full => {
+            let result_hash = match sink {
+                OutputSink::File(path) => fused::write_json(std::io::BufWriter::new(host::create(path).map_err(write_error)?), &output, true)?,
+                OutputSink::Writer(writer) => fused::write_json(writer, &output, true)?,
+            };
+            return Ok(WriteTiming { serialize_time_ms, written: Written::Full, result_hash });
+        }
+        OutputStyle::Delta(baseline) => {
+            let delta = delta::diff_outputs(baseline, &output)?;
+            let written = Written::Delta { changed: delta.changes.len(), total: output.result_matrix.data.len() };
+            (serde_json::to_string_pretty(&delta).map_err(serialize_error)?, written)
+        }
+        OutputStyle::Audited => {
+            let OutputSink::File(path) = sink else {
+                return Err(SolverError::InvalidInput("--audit-output writes to a file".to_string()));
+            };
+            audit::write_output_audited(path, &mut output, true)?;
+            return Ok(WriteTiming { serialize_time_ms, written: Written::Audited, result_hash: output.result_hash });
+        }
+    };
+    match sink {
+        OutputSink::File(path) => host::write(path, json),
+        OutputSink::Writer(writer) => writer.write_all(json.as_bytes()),
+    }
+    .map_err(write_error)?;
+    Ok(WriteTiming { serialize_time_ms, written, result_hash: output.result_hash })
+}
+
+/// An Output file, as written by `write_output` with `OutputStyle::Full`
+pub fn read_output(path: &str) -> Result<types::Output, SolverError> {
+    let json = read_json(Path::new(path), "an Output JSON document")?;
+    serde_json::from_str(&json).map_err(|e| SolverError::InvalidInput(format!("{}: {}", path, e)))
+}
+
+/// Read a JSON file, naming binary solver artifacts instead of failing to parse them
+fn read_json(path: &Path, expected: &str) -> Result<String, SolverError> {
+    let with_path = |e: &dyn std::fmt::Display| SolverError::InvalidInput(format!("{}: {}", path.display(), e));
+    let bytes = host::read(path).map_err(|e| with_path(&e))?;
+    envelope::reject_binary(&bytes, expected).map_err(|e| with_path(&e))?;
+    String::from_utf8(bytes).map_err(|e| with_path(&e))
+}
+
+/// Outcome of `--verify`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Verification {
+    Verified,
+    Mismatch,
+    /// Verification itself failed; the run still succeeds
+    Error(String),
+}
+
+/// A finished single-workload run
+#[derive(Debug, Clone)]
+pub struct RunSummary {
+    /// The output with its complete timing breakdown
+    pub output: types::Output,
+    pub output_path: String,
+    /// `--baseline`, when a delta was written
+    pub baseline: Option<String>,
+    pub parse: ParseTiming,
+    pub write: WriteTiming,
+    pub verification: Option<Verification>,
+    /// Warnings for stderr, in the order they arose
+    pub notices: Vec<String>,
+    /// `--bundle` and the manifest written to it
+    #[cfg(feature = "bundle")]
+    pub bundle: Option<(String, bundle::Manifest)>,
+}
+
+impl RunSummary {
+    /// What the binary prints on stdout
+    pub fn stdout(&self) -> String {
+        let mut out = String::new();
+        let output = &self.output;
+        match self.write.written {
+            Written::Delta { changed, total } => out.push_str(&format!(
+                "Wrote delta against {}: {} of {} elements changed\n",
+                self.baseline.as_deref().unwrap_or_default(),
+                changed,
+                total
+            )),
+            Written::Audited => out.push_str(&format!("✅ Output audit passed: {} reproduces result_hash\n", self.output_path)),
+            Written::Full => {}
+        }
+        out.push_str("Matrix multiplication completed successfully!\n");
+        out.push_str(&format!("Latency: {:.4} ms\n", output.metrics.latency_ms));
+        out.push_str(&format!("Throughput: {:.2} ops/sec\n", output.metrics.throughput_ops_per_sec));
+        out.push_str(&format!("Result hash: {}\n", output.result_hash));
+        if let Some(hashes) = &output.batch_hashes {
+            let times = output.metrics.batch_kernel_time_ms.as_deref().unwrap_or_default();
+            for (i, hash) in hashes.iter().enumerate() {
+                let time = times.get(i).map(|ms| format!(" ({:.4} ms kernel)", ms)).unwrap_or_default();
+                out.push_str(&format!("  Item {}: {}{}\n", i, hash, time));
+            }
+        }
+        for aux in &output.auxiliary_results {
+            let verdict = if aux.within_tolerance { "within" } else { "OUTSIDE" };
+            out.push_str(&format!(
+                "  {} reference: {} (max abs error {:e}, {} {})\n",
+                aux.precision,
+                aux.result_hash,
+                aux.error_vs_primary,
+                verdict,
+                aux.tolerance.describe()
+            ));
+        }
+        if let Some(kernel_time) = output.metrics.kernel_time_ms {
+            out.push_str("\nTiming Breakdown:\n");
+            if let Some(parse_time) = output.metrics.parse_time_ms {
+                out.push_str(&format!("  Parse time:     {:.4} ms\n", parse_time));
+            }
+            out.push_str(&format!("  Kernel time:    {:.4} ms (matmul computation)\n", kernel_time));
+            if let Some(serialize_time) = output.metrics.serialize_time_ms {
+                out.push_str(&format!("  Serialize time: {:.4} ms\n", serialize_time));
+            }
+        }
+        match self.verification {
+            Some(Verification::Verified) => out.push_str("✅ Correctness verified: Hash matches recomputed result\n"),
+            // The binary exits before the closing note
+            Some(Verification::Mismatch) => return out,
+            _ => {}
+        }
+        #[cfg(feature = "bundle")]
+        if let Some((path, manifest)) = &self.bundle {
+            out.push_str(&format!("Bundle written to {} ({} members)\n", path, manifest.members.len() + 1));
+        }
+        out.push_str("\nNote: Latency may vary between runs due to system load, CPU scheduling, and cache effects (`matmul-solver variance` measures how much).\n");
+        out.push_str("      For consistent benchmarking, run multiple iterations and average the results.\n");
+        out
+    }
+
+    /// What the binary prints on stderr
+    pub fn stderr(&self) -> String {
+        let mut out: String = self.notices.iter().map(|notice| format!("{}\n", notice)).collect();
+        match &self.verification {
+            Some(Verification::Mismatch) => out.push_str("❌ Correctness check failed: Hash mismatch!\n"),
+            Some(Verification::Error(e)) => out.push_str(&format!("⚠️  Verification error: {}\n", e)),
+            _ => {}
+        }
+        out
+    }
+
+    /// 1 when verification found a mismatch
+    pub fn exit_code(&self) -> i32 {
+        (self.verification == Some(Verification::Mismatch)) as i32
+    }
+}
+
+/// Compute the workload `args` describe (no subcommand), write its output and verify it
+/// if asked
+pub fn run(args: &Args) -> Result<RunSummary, SolverError> {
+    args.force_simd()?;
+    if args.audit_output && args.baseline.is_some() {
+        return Err(SolverError::InvalidInput(
+            "--audit-output applies to full outputs and cannot be combined with --baseline".to_string(),
+        ));
+    }
+    let clock = SystemClock;
+    let format = if args.strict_numbers { InputFormat::StrictJson } else { InputFormat::Json };
+    let source = args.input_source()?;
+    let row_format = numbers::InputRowFormat::parse(&args.input_row_format)?;
+    let (mut input, parse) = numbers::with_row_format(row_format, || load_input(&source, format, &clock))?;
+    // The flag wins over a hash_input in the input file
+    if args.hash_input.is_some() {
+        input.hash_input = args.hash_input.clone();
+    }
+    // Kept for the bundle, which records a file's input as computed (a seed is recorded as is)
+    #[cfg(feature = "bundle")]
+    let bundle_input = (args.bundle.is_some() && matches!(source, InputSource::File(_))).then(|| input.clone());
+    // Kept for verification, which recomputes from the inputs
+    let verify_inputs = args.verify.then(|| input.clone());
+
+    // A full output is hashed while it is written (fused.rs); deltas and audits need the
+    // hash up front
+    let options = ComputeOptions { defer_result_hash: args.baseline.is_none() && !args.audit_output, ..args.compute_options()? };
+    let mut notices = Vec::new();
+    if args.verify && options.fp32_accumulation == Fp32Accumulation::Fast {
+        notices.push("⚠️  Verification recomputes with strict fp32 accumulation; fast results may not match".to_string());
+    }
+    let mut output = compute_workload_with_options(input, &options)?;
+    output.metadata.lossy_parse_count = parse.lossy_parse_count;
+    if let Some(count) = parse.lossy_parse_count.filter(|&n| n > 0) {
+        output.warnings.push(warnings::SolverWarning::lossy_parse(count));
+    }
+    notices.extend(output.warnings.iter().map(|w| format!("⚠️  [{}] {}", w.code, w.message)));
+    let output = add_timing_breakdown(output, Some(parse.parse_time_ms), None);
+
+    let baseline = args.baseline.as_deref().map(read_output).transpose()?;
+    let style = match (&baseline, args.audit_output) {
+        (Some(baseline), _) => OutputStyle::Delta(baseline),
+        (None, true) => OutputStyle::Audited,
+        (None, false) => OutputStyle::Full,
+    };
+    let write = write_output(OutputSink::File(Path::new(&args.output)), &output, style, &clock)?;
+    let mut output = add_timing_breakdown(output, Some(parse.parse_time_ms), Some(write.serialize_time_ms));
+    output.result_hash = write.result_hash.clone();
+
+    let verification = verify_inputs.map(|input| {
+        let replay_metadata = output.metadata.result_dtype == ResultDtype::I32.as_str()
+            || output.metadata.f64_accumulation.is_some()
+            || output.metadata.accumulator.is_some()
+            || output.metadata.batch.is_some()
+            || input.matrix_b_sparse.is_some()
+            || options.fp16_rounding != Fp16Rounding::default()
+            || output.metadata.fp32_summation.is_some();
+        let verified = input.dense_b().and_then(|matrix_b| match replay_metadata {
+            true => verify_with_metadata(&input.matrix_a, &matrix_b, &output.metadata, &output.result_hash),
+            false => verify_correctness_with_rounding(&input.matrix_a, &matrix_b, &input.precision, options.hash_rounding, &output.result_hash),
+        });
+        match verified {
+            Ok(true) => Verification::Verified,
+            Ok(false) => Verification::Mismatch,
+            Err(e) => Verification::Error(e.to_string()),
+        }
+    });
+    #[cfg(feature = "bundle")]
+    let bundle = match &args.bundle {
+        Some(path) => {
+            let mut report = bundle::ExecutionReport::current(&output);
+            report.verification = verification.as_ref().map(|v| match v {
+                Verification::Verified => "verified".to_string(),
+                Verification::Mismatch => "mismatch".to_string(),
+                Verification::Error(e) => e.clone(),
+            });
+            report.notices = notices.clone();
+            let seed = match &source {
+                InputSource::Seed { seed_hex, precision } => {
+                    Some(bundle::SeedInput { seed: seed_hex.clone(), dims: SeedDims::default(), precision: precision.clone() })
+                }
+                InputSource::File(_) => None,
+            };
+            let bundled = match (&seed, &bundle_input) {
+                (Some(seed), _) => bundle::BundleInput::Seed(seed),
+                (None, input) => bundle::BundleInput::Input(input.as_ref().expect("file inputs are kept for --bundle")),
+            };
+            let manifest = bundle::write_bundle(Path::new(path), bundled, &output, &report)?;
+            Some((path.clone(), manifest))
+        }
+        None => None,
+    };
+    Ok(RunSummary {
+        output,
+        output_path: args.output.clone(),
+        baseline: args.baseline.clone(),
+        parse,
+        write,
+        verification,
+        notices,
+        #[cfg(feature = "bundle")]
+        bundle,
+    })
+}
+
+/// Run a subcommand, printing its report; some fail with a non-zero exit
+pub fn run_command(command: Command, args: &Args) -> Result<(), Box<dyn std::error::Error>> {
+    args.force_simd()?;
+    match command {
+        Command::ProfileSeed { seed, precision, iterations, k, report } => {
+            let seed_bytes = hex::decode(&seed).map_err(|e| format!("Invalid hex seed: {}", e))?;
+            let profile = profile::profile_seed(&seed_bytes, &precision, k, iterations, &SystemClock)?;
+            print!("{}", profile.to_table());
+            host::write(&report, serde_json::to_string_pretty(&profile)?)?;
+            println!("Report written to {}", report);
+            Ok(())
+        }
+        Command::BenchCrossover { precision, ks, iterations } => {
+            let report = bench::k_crossover(&precision, &ks, iterations, &ComputeOptions::default())?;
+            print!("{}", report.to_table());
+            Ok(())
+        }
+        Command::BenchSimd { k, iterations, report } => {
+            let result = bench::simd_paths(k, iterations, &SystemClock)?;
+            print!("{}", result.to_table());
+            host::write(&report, serde_json::to_string_pretty(&result)?)?;
+            println!("Report written to {}", report);
+            Ok(())
+        }
+        #[cfg(feature = "cross-check")]
+        Command::CrossCheck { reference_cmd, seed, precision, k, timeout_secs, report } => {
+            let seed_bytes = hex::decode(&seed).map_err(|e| format!("Invalid hex seed: {}", e))?;
+            let config = cross_check::CrossCheckConfig {
+                reference_cmd,
+                timeout: std::time::Duration::from_secs(timeout_secs),
+            };
+            let options = ComputeOptions { tolerance: args.tolerance_policy()?, ..Default::default() };
+            let result = match cross_check::cross_check(&seed_bytes, (16, k, 16), &precision, &options, &config) {
+                Ok(result) => result,
+                Err(cross_check::CrossCheckError::Reference(failure)) => {
+                    // Not a verdict on this machine: exit 2 so scripts can tell it from a mismatch
+                    eprintln!("❌ Reference failed: {}", failure);
+                    std::process::exit(2);
+                }
+                Err(e) => return Err(e.into()),
+            };
+            print!("{}", result.to_table());
+            host::write(&report, serde_json::to_string_pretty(&result)?)?;
+            println!("Report written to {}", report);
+            if !result.hash_match {
+                return Err(format!("result hash differs from the reference ({} vs {})", result.local_hash, result.reference_hash).into());
+            }
+            Ok(())
+        }
+        Command::VerifyBatch { claims, jobs, report } => {
+            let claims: Vec<verify_batch::Claim> =
+                serde_json::from_str(&host::read_to_string(&claims)?).map_err(|e| format!("{}: {}", claims, e))?;
+            let result = verify_batch::verify_batch(&claims, jobs, &ComputeOptions::default());
+            print!("{}", result.to_table());
+            host::write(&report, serde_json::to_string_pretty(&result)?)?;
+            println!("Report written to {}", report);
+            if !result.all_passed() {
+                return Err(format!("{} of {} claims failed", result.summary.failed, result.summary.total).into());
+            }
+            Ok(())
+        }
+        Command::Acceptance { rng_seed, count, max_elems, out, trusted } => {
+            if max_elems.is_nan() || max_elems < 1.0 {
+                return Err(format!("--max-elems must be at least 1, got {}", max_elems).into());
+            }
+            let report = acceptance::run_suite(rng_seed, count, max_elems as usize, &args.tolerance_policy()?);
+            print!("{}", report.to_table());
+            host::write(&out, serde_json::to_string_pretty(&report)?)?;
+            println!("Report written to {}", out);
+            if !report.all_passed() {
+                return Err(format!("{} of {} cases failed", report.summary.failed, report.summary.total).into());
+            }
+            if let Some(path) = trusted {
+                let trusted: acceptance::AcceptanceReport =
+                    serde_json::from_str(&host::read_to_string(&path)?).map_err(|e| format!("{}: {}", path, e))?;
+                let differences = report.diff(&trusted);
+                for difference in &differences {
+                    println!("❌ {}", difference);
+                }
+                if !differences.is_empty() {
+                    return Err(format!("{} differences from {}", differences.len(), path).into());
+                }
+                println!("✅ Matches {}", path);
+            }
+            Ok(())
+        }
+        Command::Batch { input_dir, output_dir, summary_format } => {
+            let format = batch::SummaryFormat::parse(&summary_format)?;
+            let output_dir = std::path::Path::new(&output_dir);
+            let (options, row_format) = (args.compute_options()?, numbers::InputRowFormat::parse(&args.input_row_format)?);
+            let summary = numbers::with_row_format(row_format, || {
+                batch::run_directory(std::path::Path::new(&input_dir), output_dir, &options, args.strict_numbers, &SystemClock)
+            })
+            .map_err(|e| format!("{}: {}", input_dir, e))?;
+            print!("{}", summary.to_table());
+            let path = summary.write(output_dir, format)?;
+            println!("Summary written to {}", path.display());
+            if summary.failed > 0 {
+                return Err(format!("{} of {} inputs failed", summary.failed, summary.total).into());
+            }
+            Ok(())
+        }
+        Command::Reconstruct { baseline, delta, output } => {
+            let base = read_output(&baseline)?;
+            let delta: delta::OutputDelta = serde_json::from_str(&host::read_to_string(&delta)?)?;
+            let rebuilt = delta::apply_delta(&base, &delta)?;
+            host::write(&output, serde_json::to_string_pretty(&rebuilt)?)?;
+            println!("✅ Reconstructed {} ({} changed elements), result hash {}", output, delta.changes.len(), rebuilt.result_hash);
+            Ok(())
+        }
+        Command::Soak { seed_prefix, duration, precision, k, sample_every, control_every, max_trend, report } => {
+            let config = soak::SoakConfig {
+                seed_prefix: hex::decode(&seed_prefix).map_err(|e| format!("Invalid hex seed prefix: {}", e))?,
+                precision,
+                dims: (16, k, 16),
+                duration: soak::parse_duration(&duration)?,
+                max_iterations: None,
+                sample_every,
+                control_every,
+                max_trend_bytes_per_iter: max_trend,
+            };
+            if let Some(dir) = Path::new(&report).parent().filter(|d| !d.as_os_str().is_empty()) {
+                host::create_dir_all(dir)?;
+            }
+            let clock = SystemClock;
+            let mut written = None;
+            let soaked = soak::run_soak(&config, &args.compute_options()?, &clock, |partial| {
+                // A failed write is retried at the next sample; only the final one is an error
+                let due = written.is_none_or(|at| clock.elapsed_since(at).as_secs() >= 10);
+                if due && serde_json::to_string_pretty(partial).is_ok_and(|json| host::write(&report, json).is_ok()) {
+                    written = Some(clock.now());
+                }
+            })?;
+            print!("{}", soaked.to_table());
+            host::write(&report, serde_json::to_string_pretty(&soaked)?)?;
+            println!("Report written to {}", report);
+            if !soaked.passed() {
+                return Err(format!("soak failed after {} iterations", soaked.iterations).into());
+            }
+            Ok(())
+        }
+        #[cfg(feature = "bundle")]
+        Command::Bundle { command: BundleCommand::Verify { file } } => {
+            let verified = bundle::verify_bundle(Path::new(&file))?;
+            print!("{}", verified.to_table());
+            if !verified.passed() {
+                return Err(format!("{} does not verify", file).into());
+            }
+            Ok(())
+        }
+        Command::Variance { seed, precision, runs, k, isolate, report } => {
+            let config = variance::VarianceConfig {
+                seed: hex::decode(&seed).map_err(|e| format!("Invalid hex seed: {}", e))?,
+                precision,
+                dims: (16, k, 16),
+                runs,
+                isolate,
+            };
+            let result = variance::run_variance(&config, &args.compute_options()?)?;
+            print!("{}", result.to_table());
+            if let Some(dir) = Path::new(&report).parent().filter(|d| !d.as_os_str().is_empty()) {
+                host::create_dir_all(dir)?;
+            }
+            host::write(&report, serde_json::to_string_pretty(&result)?)?;
+            println!("Report written to {}", report);
+            Ok(())
+        }
+        Command::Inspect { file } => {
+            let bytes = host::read(&file).map_err(|e| format!("{}: {}", file, e))?;
+            let inspection = envelope::inspect(&bytes).map_err(|e| format!("{}: {}", file, e))?;
+            print!("{}", inspection.to_table());
+            Ok(())
+        }
+        #[cfg(feature = "client")]
+        Command::Replay { file, rate, target, token, report } => {
+            let captured = crate::traffic::read_capture(Path::new(&file))?;
+            let rate = crate::traffic::ReplayRate::parse(&rate)?;
+            // Retries would hide the server's answers and skew the latencies
+            let client = crate::client::SolverClient::new(&target, token)
+                .with_config(crate::client::ClientConfig { max_retries: 0, ..Default::default() });
+            let replayed = tokio::runtime::Runtime::new()?.block_on(crate::traffic::replay(&captured, std::sync::Arc::new(client), &target, rate))?;
+            print!("{}", replayed.to_table());
+            if let Some(dir) = Path::new(&report).parent().filter(|d| !d.as_os_str().is_empty()) {
+                host::create_dir_all(dir)?;
+            }
+            host::write(&report, serde_json::to_string_pretty(&replayed)?)?;
+            println!("Report written to {}", report);
+            if !replayed.passed() {
+                return Err(format!("{} hash mismatches, {} failed requests", replayed.mismatches.len(), replayed.failures.len()).into());
+            }
+            Ok(())
+        }
+        Command::BenchRegress {
+            baseline,
+            run,
+            current,
+            save,
+            iterations,
+            threshold_pct,
+            update_baseline,
+            workloads,
+            cold_iterations,
+            evict_llc,
+        } => {
+            let current = match (run, current) {
+                (true, _) => {
+                    let mut suite = bench::standard_suite();
+                    if let Some(names) = &workloads {
+                        suite.retain(|w| names.contains(&w.name));
+                    }
+                    let evict_bytes = evict_llc.as_deref().map(memory::parse_size).transpose()?.map(|b| b as usize);
+                    let cold = cold_iterations.then_some(bench::ColdIterations { evict_bytes });
+                    let run = bench::run_suite(&suite, iterations, cold, &ComputeOptions::default())?;
+                    print!("{}", run.to_table());
+                    run
+                }
+                (false, Some(path)) => serde_json::from_str(&host::read_to_string(&path)?)?,
+                (false, None) => return Err("Nothing to compare: pass --run to time the suite or --current FILE".into()),
+            };
+            if let Some(path) = &save {
+                host::write(path, serde_json::to_string_pretty(&current)?)?;
+            }
+            if update_baseline || !std::path::Path::new(&baseline).exists() {
+                if let Some(dir) = std::path::Path::new(&baseline).parent().filter(|d| !d.as_os_str().is_empty()) {
+                    host::create_dir_all(dir)?;
+                }
+                host::write(&baseline, serde_json::to_string_pretty(&current)?)?;
+                println!("Baseline written to {} ({} workloads)", baseline, current.results.len());
+                return Ok(());
+            }
+            let stored: bench::BenchRun = serde_json::from_str(&host::read_to_string(&baseline)?)?;
+            let report = bench::compare(&stored, &current, threshold_pct)?;
+            print!("{}", report.to_table());
+            if report.has_regressions() {
+                return Err(format!("{} workloads regressed against {}", report.regressions().count(), baseline).into());
+            }
+            Ok(())
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+    use crate::MockClock;
+
+    // 16777217 is one past 2^24, so parsing it is lossy
+    const INPUT: &str = r#"{"matrix_a": [[1, 2, 3], [4, 5, 16777217]], "matrix_b": [[1, 2], [3, 4], [5, 6]], "precision": "fp32"}"#;
+
+    fn write_file(dir: &Path, name: &str, contents: &str) -> PathBuf {
+        let path = dir.join(name);
+        fs::write(&path, contents).unwrap();
+        path
+    }
+
+    fn args(argv: &[&str]) -> Args {
+        Args::try_parse_from(["matmul-solver"].iter().chain(argv)).unwrap()
+    }
+
+    #[test]
+    fn test_load_input() {
+        let dir = tempfile::tempdir().unwrap();
+        let source = InputSource::File(write_file(dir.path(), "in.json", INPUT));
+        let clock = MockClock::new();
+        let (input, timing) = load_input(&source, InputFormat::Json, &clock).unwrap();
+        assert_eq!((input.matrix_a.rows, input.matrix_a.cols, input.precision.as_str()), (2, 3, "fp32"));
+        assert_eq!(timing, ParseTiming { parse_time_ms: 0.0, lossy_parse_count: Some(1) });
+
+        assert!(load_input(&source, InputFormat::StrictJson, &clock).is_ok());
+        let overflowing = InputSource::File(write_file(dir.path(), "overflow.json", &INPUT.replace("16777217", "1e40")));
+        let err = load_input(&overflowing, InputFormat::StrictJson, &clock).unwrap_err();
+        assert!(err.to_string().contains("matrix_a[1][2] = 1e40"), "{}", err);
+        let missing = InputSource::File(dir.path().join("missing.json"));
+        let err = load_input(&missing, InputFormat::Json, &clock).unwrap_err();
+        assert!(err.to_string().contains("missing.json"), "{}", err);
+
+        let seed = InputSource::Seed { seed_hex: "zz".to_string(), precision: "u8i8".to_string() };
+        assert!(load_input(&seed, InputFormat::Json, &clock).unwrap_err().to_string().contains("Invalid hex seed"));
+        assert!(args(&["--seed", "abcd"]).input_source().is_err());
+        assert_eq!(
+            args(&["--seed", "abcd", "--precision", "u8i8"]).input_source().unwrap(),
+            InputSource::Seed { seed_hex: "abcd".to_string(), precision: "u8i8".to_string() }
+        );
+        assert_eq!(args(&[]).input_source().unwrap(), InputSource::File("inputs/input.json".into()));
+
+        // Files are read through host
+        let read = std::panic::catch_unwind(|| host::forbid_io(|| load_input(&source, InputFormat::Json, &clock)));
+        assert!(read.unwrap_err().downcast::<String>().unwrap().contains("while host IO is forbidden"));
+    }
+
+    #[test]
+    fn test_write_output() {
+        let dir = tempfile::tempdir().unwrap();
+        let clock = MockClock::new();
+        let (input, _) = load_input(&InputSource::File(write_file(dir.path(), "in.json", INPUT)), InputFormat::Json, &clock).unwrap();
+        let output = add_timing_breakdown(compute_workload_with_options(input, &ComputeOptions::default()).unwrap(), Some(1.5), None);
+
+        let mut sink = Vec::new();
+        let timing = write_output(OutputSink::Writer(&mut sink), &output, OutputStyle::Full, &clock).unwrap();
+        assert_eq!(timing, WriteTiming { serialize_time_ms: 0.0, written: Written::Full, result_hash: output.result_hash.clone() });
+        let written: types::Output = serde_json::from_slice(&sink).unwrap();
+        assert_eq!(written.result_hash, output.result_hash);
+        assert_eq!((written.metrics.parse_time_ms, written.metrics.serialize_time_ms), (Some(1.5), Some(0.0)));
+
+        // A deferred hash is computed while writing, into the same bytes
+        let deferred = crate::types::Output { result_hash: String::new(), ..output.clone() };
+        let mut fused = Vec::new();
+        let timing = write_output(OutputSink::Writer(&mut fused), &deferred, OutputStyle::Full, &clock).unwrap();
+        assert_eq!((timing.result_hash, fused), (output.result_hash.clone(), sink));
+
+        let mut baseline = output.clone();
+        let mut changed = (*baseline.result_matrix).clone();
+        changed.data[0] += 1.0;
+        baseline.result_matrix = changed.into();
+        baseline.result_hash = crate::compute_hash(&baseline.result_matrix);
+        let mut sink = Vec::new();
+        let timing = write_output(OutputSink::Writer(&mut sink), &output, OutputStyle::Delta(&baseline), &clock).unwrap();
+        assert_eq!(timing.written, Written::Delta { changed: 1, total: 4 });
+        let delta: delta::OutputDelta = serde_json::from_slice(&sink).unwrap();
+        assert_eq!(delta::apply_delta(&baseline, &delta).unwrap().result_hash, output.result_hash);
+
+        let mut sink = Vec::new();
+        assert!(write_output(OutputSink::Writer(&mut sink), &output, OutputStyle::Audited, &clock).is_err());
+        let path = dir.path().join("audited.json");
+        let timing = write_output(OutputSink::File(&path), &output, OutputStyle::Audited, &clock).unwrap();
+        assert_eq!(timing.written, Written::Audited);
+        assert_eq!(read_output(path.to_str().unwrap()).unwrap().audit_passed, Some(true));
+    }
+
+    #[test]
+    fn test_json_readers_name_binary_artifacts() {
+        let dir = tempfile::tempdir().unwrap();
+        let packed = dir.path().join("u8i8-00.pack");
+        fs::write(&packed, envelope::encode(envelope::Format::PackedB, 2, &[0; 16])).unwrap();
+
+        let err = read_output(packed.to_str().unwrap()).unwrap_err().to_string();
+        assert_eq!(err, format!("{}: this file is a packed-B cache v2, expected an Output JSON document", packed.display()));
+        let err = load_input(&InputSource::File(packed.clone()), InputFormat::Json, &MockClock::new()).unwrap_err();
+        assert!(err.to_string().ends_with("this file is a packed-B cache v2, expected an Input JSON document"), "{}", err);
+    }
+
+    #[test]
+    fn test_run() {
+        let dir = tempfile::tempdir().unwrap();
+        let input = write_file(dir.path(), "in.json", INPUT);
+        let out = dir.path().join("out.json");
+        let (input, out) = (input.to_str().unwrap(), out.to_str().unwrap());
+
+        let summary = run(&args(&["--input", input, "-o", out, "--verify"])).unwrap();
+        assert_eq!(summary.verification, Some(Verification::Verified));
+        assert_eq!(summary.exit_code(), 0);
+        let written = read_output(out).unwrap();
+        assert_eq!(written.result_hash, summary.output.result_hash);
+        // serde_json does not round-trip every f64 exactly
+        assert!((written.metrics.serialize_time_ms.unwrap() - summary.write.serialize_time_ms).abs() < 1e-9);
+        assert_eq!(written.metadata.lossy_parse_count, Some(1));
+        assert_eq!(summary.notices.len(), 1);
+        assert!(summary.stderr().starts_with("⚠️  [lossy_parse] "));
+
+        let stdout = summary.stdout();
+        let lines: Vec<&str> = stdout.lines().collect();
+        assert_eq!(lines[0], "Matrix multiplication completed successfully!");
+        assert_eq!(lines[3], format!("Result hash: {}", summary.output.result_hash));
+        assert!(lines.contains(&"✅ Correctness verified: Hash matches recomputed result"));
+        assert!(stdout.ends_with("average the results.\n"));
+
+        // A mismatch exits 1 before the closing note
+        let mismatch = RunSummary { verification: Some(Verification::Mismatch), ..summary.clone() };
+        assert_eq!(mismatch.exit_code(), 1);
+        assert!(!mismatch.stdout().contains("Note:"));
+        assert!(mismatch.stderr().ends_with("❌ Correctness check failed: Hash mismatch!\n"));
+
+        let delta_out = dir.path().join("delta.json");
+        let summary = run(&args(&["--input", input, "-o", delta_out.to_str().unwrap(), "--baseline", out])).unwrap();
+        assert_eq!(summary.write.written, Written::Delta { changed: 0, total: 4 });
+        assert!(summary.stdout().starts_with(&format!("Wrote delta against {}: 0 of 4 elements changed\n", out)));
+
+        let err = run(&args(&["--input", input, "-o", out, "--baseline", out, "--audit-output"])).unwrap_err();
+        assert!(err.to_string().contains("cannot be combined"), "{}", err);
+        assert!(run(&args(&["--input", input, "-o", out, "--hash-mode", "approximate"])).is_err());
+    }
+
+    #[test]
+    fn test_run_batched_matmul() {
+        let dir = tempfile::tempdir().unwrap();
+        let json = r#"{"workload_type": "batched_matmul", "batch": 3, "precision": "fp32",
+            "matrix_a": [[1, 2], [3, 4], [5, 6]], "matrix_b": [[1, 0, 2], [0, 1, 3]]}"#;
+        let input = write_file(dir.path(), "batched.json", json);
+        let out = dir.path().join("out.json");
+        let (input, out) = (input.to_str().unwrap(), out.to_str().unwrap());
+
+        let summary = run(&args(&["--input", input, "-o", out, "--verify", "--audit-output"])).unwrap();
+        assert_eq!(summary.verification, Some(Verification::Verified));
+        assert_eq!(summary.output.result_matrix.data, [1.0, 2.0, 8.0, 3.0, 4.0, 18.0, 5.0, 6.0, 28.0]);
+        let hashes = summary.output.batch_hashes.clone().unwrap();
+        assert_eq!(hashes[1], crate::compute_hash(&crate::FlatMatrix { data: vec![3.0, 4.0, 18.0], rows: 1, cols: 3 }));
+        assert!(summary.stdout().contains(&format!("  Item 2: {} (", hashes[2])), "{}", summary.stdout());
+        assert_eq!(read_output(out).unwrap().batch_hashes, Some(hashes));
+    }
+
+    #[test]
+    fn test_run_error_metrics() {
+        let dir = tempfile::tempdir().unwrap();
+        let input = write_file(dir.path(), "bf16.json", r#"{"matrix_a": [[1.1, 2.3]], "matrix_b": [[3.7], [0.9]], "precision": "bf16"}"#);
+        let out = dir.path().join("out.json");
+        let (input, out) = (input.to_str().unwrap(), out.to_str().unwrap());
+
+        let summary = run(&args(&["--input", input, "-o", out, "--error-metrics", "--aux-matrices"])).unwrap();
+        let aux = &summary.output.auxiliary_results[0];
+        assert_eq!(aux.result_matrix.as_ref().unwrap().data, [1.1f32 * 3.7 + 2.3 * 0.9]);
+        assert!(aux.error_vs_primary > 0.0 && aux.error_vs_primary < 0.1, "{}", aux.error_vs_primary);
+        let line = format!("  fp32 reference: {} (max abs error {:e}, within default tolerance", aux.result_hash, aux.error_vs_primary);
+        assert!(summary.stdout().contains(&line), "{}", summary.stdout());
+        assert!(aux.within_tolerance);
+        let written = read_output(out).unwrap();
+        assert_eq!(written.auxiliary_results[0].result_hash, aux.result_hash);
+        assert!(written.auxiliary_results[0].result_matrix.is_some());
+
+        // --tolerance-profile and --tolerance govern the verdict and are recorded
+        let summary = run(&args(&["--input", input, "-o", out, "--error-metrics", "--tolerance-profile", "strict", "--tolerance", "bf16=1e-5,0"])).unwrap();
+        let aux = &summary.output.auxiliary_results[0];
+        assert!(!aux.within_tolerance);
+        assert_eq!((aux.tolerance.profile.as_str(), aux.tolerance.rel, aux.tolerance.overridden), ("strict", 1e-5, true));
+        assert!(summary.stdout().contains("OUTSIDE overridden tolerance (rel 1e-5, abs 0e0)"), "{}", summary.stdout());
+        assert!(run(&args(&["--input", input, "-o", out, "--tolerance-profile", "lax"])).is_err());
+    }
+
+    #[cfg(feature = "bundle")]
+    #[test]
+    fn test_run_bundle() {
+        let dir = tempfile::tempdir().unwrap();
+        let input = write_file(dir.path(), "in.json", INPUT);
+        let (out, case) = (dir.path().join("out.json"), dir.path().join("case.tar.gz"));
+        let argv = ["--input", input.to_str().unwrap(), "-o", out.to_str().unwrap(), "--verify", "--bundle", case.to_str().unwrap()];
+        let summary = run(&args(&argv)).unwrap();
+        let (path, manifest) = summary.bundle.clone().unwrap();
+        assert_eq!(manifest.members.len(), 4);
+        assert!(summary.stdout().contains(&format!("Bundle written to {} (5 members)\n", path)), "{}", summary.stdout());
+
+        let verified = bundle::verify_bundle(&case).unwrap();
+        assert!(verified.passed(), "{}", verified.to_table());
+        assert_eq!(verified.result_hash, Some(summary.output.result_hash));
+        let verify = Command::Bundle { command: BundleCommand::Verify { file: case.to_str().unwrap().to_string() } };
+        assert!(run_command(verify, &args(&[])).is_ok());
+    }
+}
@@ -0,0 +1,267 @@
+//! Typed async client for the solver API (feature `client`).
+//!
+//! Requests and responses use the server's own `ComputeRequest` and `types::Output`, so
+//! callers cannot drift from the wire format. Responses with status 429 or 503 are
+//! retried with exponential backoff (honoring `Retry-After`, capped at `max_backoff`);
+//! other failures surface as `ClientError::Api` with the server's error message.
+
+use crate::api::api::{ComputeRequest, SeedDims, MSGPACK_CONTENT_TYPE};
+use crate::types;
+use std::fmt;
+use std::time::Duration;
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum ClientError {
+    /// The request never got a response (connection refused, timeout, ...)
+    Transport(String),
+    /// The server answered with a non-success status
+    Api { status: u16, message: String },
+    /// The response body was not a valid Output
+    Decode(String),
+}
+
+impl fmt::Display for ClientError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ClientError::Transport(msg) => write!(f, "Transport error: {}", msg),
+            ClientError::Api { status, message } => write!(f, "API error {}: {}", status, message),
+            ClientError::Decode(msg) => write!(f, "Invalid response: {}", msg),
+        }
+    }
+}
+
+impl std::error::Error for ClientError {}
+
+#[derive(Debug, Clone)]
+pub struct ClientConfig {
+    /// Retries after the first attempt for 429/503 responses
+    pub max_retries: u32,
+    /// First backoff delay; doubles per retry
+    pub initial_backoff: Duration,
+    /// Upper bound on any single delay, including a server-sent Retry-After
+    pub max_backoff: Duration,
+    /// Ask for MessagePack responses (falls back to JSON if the server ignores it)
+    pub prefer_msgpack: bool,
+}
+
+impl Default for ClientConfig {
+    fn default() -> Self {
+        Self {
+            max_retries: 3,
+            initial_backoff: Duration::from_millis(200),
+            max_backoff: Duration::from_secs(10),
+            prefer_msgpack: false,
+        }
+    }
+}
+
+pub struct SolverClient {
+    base_url: String,
+    auth: Option<String>,
+    config: ClientConfig,
+    http: reqwest::Client,
+}
+
+/// Pull the message out of an error body: `{"error": "..."}` or plain text
+fn error_message(body: &[u8]) -> String {
+    serde_json::from_slice::<serde_json::Value>(body)
+        .ok()
+        .and_then(|v| v.get("error").and_then(|e| e.as_str()).map(str::to_string))
+        .unwrap_or_else(|| String::from_utf8_lossy(body).trim().to_string())
+}
+
+impl SolverClient {
+    /// `auth` is sent as `Authorization: Bearer <auth>`
+    pub fn new(base_url: impl Into<String>, auth: Option<String>) -> Self {
+        Self {
+            base_url: base_url.into().trim_end_matches('/').to_string(),
+            auth,
+            config: ClientConfig::default(),
+            http: reqwest::Client::new(),
+        }
+    }
+
+    pub fn with_config(mut self, config: ClientConfig) -> Self {
+        self.config = config;
+        self
+    }
+
+    fn authorize(&self, builder: reqwest::RequestBuilder) -> reqwest::RequestBuilder {
+        match &self.auth {
+            Some(token) => builder.bearer_auth(token),
+            None => builder,
+        }
+    }
+
+    /// Send with retries on 429/503; returns the first non-retryable response
+    async fn send(&self, build: impl Fn() -> reqwest::RequestBuilder) -> Result<reqwest::Response, ClientError> {
+        let mut delay = self.config.initial_backoff;
+        let mut attempt = 0;
+        loop {
+            let response = self
+                .authorize(build())
+                .send()
+                .await
+                .map_err(|e| ClientError::Transport(e.to_string()))?;
+            let status = response.status();
+            let retryable = status == reqwest::StatusCode::TOO_MANY_REQUESTS
+                || status == reqwest::StatusCode::SERVICE_UNAVAILABLE;
+            if !retryable || attempt >= self.config.max_retries {
+                return Ok(response);
+            }
+            let retry_after = response
+                .headers()
+                .get("retry-after")
+                .and_then(|v| v.to_str().ok())
+                .and_then(|v| v.parse::<u64>().ok())
+                .map(Duration::from_secs);
+            tokio::time::sleep(retry_after.unwrap_or(delay).min(self.config.max_backoff)).await;
+            delay *= 2;
+            attempt += 1;
+        }
+    }
+
+    async fn check(response: reqwest::Response) -> Result<reqwest::Response, ClientError> {
+        let status = response.status();
+        if status.is_success() {
+            return Ok(response);
+        }
+        let body = response.bytes().await.map_err(|e| ClientError::Transport(e.to_string()))?;
+        Err(ClientError::Api { status: status.as_u16(), message: error_message(&body) })
+    }
+
+    /// GET /health
+    pub async fn health(&self) -> Result<(), ClientError> {
+        let url = format!("{}/health", self.base_url);
+        let response = self.send(|| self.http.get(&url)).await?;
+        Self::check(response).await.map(|_| ())
+    }
+
+    /// POST /compute
+    pub async fn compute(&self, request: ComputeRequest) -> Result<types::Output, ClientError> {
+        let url = format!("{}/compute", self.base_url);
+        let body = serde_json::to_vec(&request).map_err(|e| ClientError::Decode(e.to_string()))?;
+        let accept = if self.config.prefer_msgpack {
+            "application/msgpack, application/json;q=0.9"
+        } else {
+            "application/json"
+        };
+        let response = self
+            .send(|| {
+                self.http
+                    .post(&url)
+                    .header("content-type", "application/json")
+                    .header("accept", accept)
+                    .body(body.clone())
+            })
+            .await?;
+        let response = Self::check(response).await?;
+        let is_msgpack = response
+            .headers()
+            .get("content-type")
+            .and_then(|v| v.to_str().ok())
+            .is_some_and(|v| v.starts_with(MSGPACK_CONTENT_TYPE));
+        let bytes = response.bytes().await.map_err(|e| ClientError::Transport(e.to_string()))?;
+        if is_msgpack {
+            rmp_serde::from_slice(&bytes).map_err(|e| ClientError::Decode(e.to_string()))
+        } else {
+            serde_json::from_slice(&bytes).map_err(|e| ClientError::Decode(e.to_string()))
+        }
+    }
+
+    /// Compute matrices generated from a hex seed
+    pub async fn compute_seed(&self, seed: &str, precision: &str, dims: SeedDims) -> Result<types::Output, ClientError> {
+        self.compute(ComputeRequest {
+            seed: Some(seed.to_string()),
+            seed_dims: Some(dims),
+            precision: precision.to_string(),
+            ..Default::default()
+        })
+        .await
+    }
+
+    /// Have the server recompute `request` and compare against `expected_hash`
+    pub async fn verify(&self, request: ComputeRequest, expected_hash: &str) -> Result<bool, ClientError> {
+        Ok(self.compute(request).await?.result_hash == expected_hash)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::api::api::{router, ApiConfig, AppState};
+    use crate::rate_limit::RateLimitConfig;
+    use std::net::SocketAddr;
+    use std::sync::Arc;
+
+    async fn spawn_server(config: ApiConfig) -> String {
+        let app = router(Arc::new(AppState::new(config)));
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        tokio::spawn(async move {
+            axum::serve(listener, app.into_make_service_with_connect_info::<SocketAddr>()).await.unwrap()
+        });
+        format!("http://{}", addr)
+    }
+
+    #[tokio::test]
+    async fn test_client_end_to_end() {
+        let url = spawn_server(ApiConfig {
+            api_tokens: Some(vec!["good".to_string()]),
+            rate_limit: Some(RateLimitConfig {
+                requests_per_window: Some(1),
+                window: Duration::from_millis(300),
+                ..Default::default()
+            }),
+            ..Default::default()
+        })
+        .await;
+        let config = ClientConfig { max_backoff: Duration::from_millis(400), ..Default::default() };
+
+        let anonymous = SolverClient::new(&url, None);
+        anonymous.health().await.unwrap();
+        let denied = SolverClient::new(&url, Some("bad".to_string()))
+            .compute_seed("0102", "fp32", SeedDims { m: 16, k: 96, n: 16 })
+            .await
+            .unwrap_err();
+        assert!(matches!(&denied, ClientError::Api { status: 401, message } if message.contains("API token")), "{}", denied);
+
+        // Each call after the first hits the 1-per-window limit and succeeds on retry
+        let client = SolverClient::new(&url, Some("good".to_string())).with_config(config.clone());
+        let dims = SeedDims { m: 16, k: 96, n: 24 };
+        let output = client.compute_seed("0102", "fp32", dims).await.unwrap();
+        let (matrix_a, matrix_b) = crate::generate_matrices_from_seed_hex("0102", 16, 96, 96, 24).unwrap();
+        let input = types::Input::matmul(matrix_a, matrix_b, "fp32");
+        assert_eq!(output.result_hash, crate::compute_workload(input).unwrap().result_hash);
+        assert_eq!(output.metadata.matrix_b_shape, (96, 24));
+
+        let request = ComputeRequest {
+            seed: Some("0102".to_string()),
+            seed_dims: Some(dims),
+            precision: "fp32".to_string(),
+            ..Default::default()
+        };
+        assert!(client.verify(request.clone(), &output.result_hash).await.unwrap());
+        assert!(!client.verify(request.clone(), "00").await.unwrap());
+
+        let msgpack = SolverClient::new(&url, Some("good".to_string()))
+            .with_config(ClientConfig { prefer_msgpack: true, ..config.clone() });
+        let packed = msgpack.compute(request).await.unwrap();
+        assert_eq!(packed.result_hash, output.result_hash);
+        assert_eq!(packed.result_matrix.data, output.result_matrix.data);
+
+        // Without retries the limit surfaces as a 429
+        let no_retry = SolverClient::new(&url, Some("good".to_string()))
+            .with_config(ClientConfig { max_retries: 0, ..config });
+        no_retry.health().await.unwrap();
+        let _ = no_retry.compute_seed("0102", "fp32", dims).await;
+        let limited = no_retry.compute_seed("0102", "fp32", dims).await.unwrap_err();
+        assert!(matches!(limited, ClientError::Api { status: 429, .. }), "{}", limited);
+    }
+
+    #[test]
+    fn test_error_message_parsing() {
+        assert_eq!(error_message(br#"{"error": "bad shape"}"#), "bad shape");
+        assert_eq!(error_message(b"Rate limit exceeded\n"), "Rate limit exceeded");
+    }
+}
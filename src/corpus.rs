@@ -0,0 +1,199 @@
+//! Adversarial input corpus.
+//!
+//! `tests/corpus` holds malformed and hostile Input documents (ragged rows, NaN literals,
+//! deep nesting, wrong types, huge rows, ...). Each file name starts with the outcome it
+//! must produce, `<expected>-<description>.json`, e.g. `type-ragged-rows.json`; see
+//! `Expected` for the codes. The corpus test feeds every file through the CLI's serde path
+//! (`numbers::parse_input_json` and `compute_workload_with_options`) and through
+//! `POST /compute`, and requires the expected rejection from both within
+//! `CASE_TIME_LIMIT`, and, with `counting-alloc`, within `CASE_MEMORY_BUDGET`.
+//!
+//! The parse fuzz target (`fuzz/fuzz_targets/parse_input.rs`) calls `check_bytes`, so a
+//! fuzzer finding is a file the same checks fail on: copy it into `tests/corpus` under the
+//! code `classify` now returns for it, and it stays covered.
+
+use crate::{compute_workload_with_options, numbers, ComputeOptions, SolverError};
+use std::path::Path;
+use std::time::{Duration, Instant};
+
+/// Longest any single input may take to be rejected (or computed)
+pub const CASE_TIME_LIMIT: Duration = Duration::from_secs(1);
+/// Most bytes any single input may allocate, checked with `counting-alloc`
+pub const CASE_MEMORY_BUDGET: u64 = 64 << 20;
+
+/// Outcome an input must produce, the prefix of its corpus file name
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Expected {
+    /// Computes (lossy values are counted, not rejected)
+    Ok,
+    /// Not JSON: truncated, NaN/Infinity or hex literals, trailing commas, invalid UTF-8
+    Syntax,
+    /// JSON of the wrong shape: ragged rows, wrong element or field types, missing fields
+    Type,
+    /// `SolverError::ShapeMismatch`
+    Shape,
+    /// `SolverError::UnsupportedPrecision`
+    Precision,
+    /// `SolverError::UnsupportedWorkload`
+    Workload,
+    /// Any other `SolverError::InvalidInput`
+    Invalid,
+}
+
+impl Expected {
+    pub const ALL: [Expected; 7] = [
+        Expected::Ok,
+        Expected::Syntax,
+        Expected::Type,
+        Expected::Shape,
+        Expected::Precision,
+        Expected::Workload,
+        Expected::Invalid,
+    ];
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Expected::Ok => "ok",
+            Expected::Syntax => "syntax",
+            Expected::Type => "type",
+            Expected::Shape => "shape",
+            Expected::Precision => "precision",
+            Expected::Workload => "workload",
+            Expected::Invalid => "invalid",
+        }
+    }
+
+    /// The code a corpus file name starts with
+    pub fn from_file_name(name: &str) -> Option<Self> {
+        let code = name.split_once('-')?.0;
+        Self::ALL.into_iter().find(|expected| expected.as_str() == code)
+    }
+
+    /// Status `POST /compute` answers with
+    pub fn http_status(&self) -> u16 {
+        match self {
+            Expected::Ok => 200,
+            // axum's split: malformed JSON is a 400, well-formed JSON of the wrong shape a 422
+            Expected::Type => 422,
+            _ => 400,
+        }
+    }
+}
+
+/// Outcome of the serde path for `bytes`: parse as an Input, then compute it
+pub fn classify(bytes: &[u8]) -> Expected {
+    let json = match std::str::from_utf8(bytes) {
+        Ok(json) => json,
+        Err(_) => return Expected::Syntax,
+    };
+    let input = match numbers::parse_input_json(json, false) {
+        Ok((input, _)) => input,
+        Err(_) => {
+            // parse_input_json flattens the serde error; classify it again
+            return match serde_json::from_str::<crate::types::Input>(json) {
+                Err(e) if e.is_data() => Expected::Type,
+                _ => Expected::Syntax,
+            };
+        }
+    };
+    match compute_workload_with_options(input, &ComputeOptions::default()) {
+        Ok(_) => Expected::Ok,
+        Err(SolverError::ShapeMismatch(_)) => Expected::Shape,
+        Err(SolverError::UnsupportedPrecision(_)) => Expected::Precision,
+        Err(SolverError::UnsupportedWorkload(_)) => Expected::Workload,
+        Err(_) => Expected::Invalid,
+    }
+}
+
+/// `classify` under the corpus bounds; panics when `bytes` takes longer than
+/// `CASE_TIME_LIMIT` or, with `counting-alloc`, allocates more than `CASE_MEMORY_BUDGET`
+pub fn check_bytes(bytes: &[u8]) -> Expected {
+    let (expected, elapsed, peak) = measure(|| classify(bytes));
+    assert_within_bounds(elapsed, peak);
+    expected
+}
+
+/// Run `f`, returning its time and, with `counting-alloc`, peak allocation on this thread
+pub fn measure<T>(f: impl FnOnce() -> T) -> (T, Duration, Option<u64>) {
+    let guard = crate::memory::MemoryGuard::start(u64::MAX, 0);
+    let start = Instant::now();
+    let out = f();
+    (out, start.elapsed(), guard.peak_allocated())
+}
+
+pub fn assert_within_bounds(elapsed: Duration, peak: Option<u64>) {
+    assert!(elapsed <= CASE_TIME_LIMIT, "took {:?}, limit {:?}", elapsed, CASE_TIME_LIMIT);
+    if let Some(peak) = peak {
+        assert!(peak <= CASE_MEMORY_BUDGET, "allocated {} bytes, budget {}", peak, CASE_MEMORY_BUDGET);
+    }
+}
+
+/// Every case in `dir` as (file name, expected outcome, contents), sorted by name.
+/// Panics on a file without a known code, so no case is silently skipped.
+pub fn load(dir: &Path) -> Vec<(String, Expected, Vec<u8>)> {
+    let mut cases: Vec<_> = std::fs::read_dir(dir)
+        .unwrap_or_else(|e| panic!("reading {}: {}", dir.display(), e))
+        .map(|entry| {
+            let path = entry.expect("corpus entry").path();
+            let name = path.file_name().unwrap().to_string_lossy().into_owned();
+            let expected = Expected::from_file_name(&name)
+                .unwrap_or_else(|| panic!("corpus file {} does not start with an expected outcome", name));
+            (name, expected, std::fs::read(&path).expect("corpus file"))
+        })
+        .collect();
+    cases.sort_by(|a, b| a.0.cmp(&b.0));
+    cases
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn corpus() -> Vec<(String, Expected, Vec<u8>)> {
+        load(&Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/corpus"))
+    }
+
+    #[test]
+    fn test_corpus_through_serde_path() {
+        let cases = corpus();
+        assert!(cases.len() >= 20, "only {} corpus cases", cases.len());
+        for expected in Expected::ALL {
+            assert!(cases.iter().any(|case| case.1 == expected), "no {} case", expected.as_str());
+        }
+        for (name, expected, bytes) in &cases {
+            let (got, elapsed, peak) = measure(|| classify(bytes));
+            assert_eq!(got, *expected, "{}", name);
+            assert_within_bounds(elapsed, peak);
+        }
+    }
+
+    #[cfg(feature = "api")]
+    #[tokio::test]
+    async fn test_corpus_through_api() {
+        use crate::api::api::{router, ApiConfig, AppState};
+        use axum::body::Body;
+        use axum::http::Request;
+        use tower::ServiceExt;
+
+        let app = router(std::sync::Arc::new(AppState::new(ApiConfig::default())));
+        for (name, expected, bytes) in corpus() {
+            let request = Request::builder()
+                .method("POST")
+                .uri("/compute")
+                .header("content-type", "application/json")
+                .body(Body::from(bytes))
+                .unwrap();
+            let guard = crate::memory::MemoryGuard::start(u64::MAX, 0);
+            let start = Instant::now();
+            let response = app.clone().oneshot(request).await.unwrap();
+            let status = response.status().as_u16();
+            let body = axum::body::to_bytes(response.into_body(), usize::MAX).await.unwrap();
+            assert_within_bounds(start.elapsed(), guard.peak_allocated());
+            assert_eq!(status, expected.http_status(), "{}: {}", name, String::from_utf8_lossy(&body));
+            if expected == Expected::Shape {
+                let json: serde_json::Value = serde_json::from_slice(&body).unwrap();
+                assert!(json["details"]["violations"].as_array().is_some_and(|v| !v.is_empty()), "{}", name);
+            }
+        }
+    }
+}
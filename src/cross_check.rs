@@ -0,0 +1,405 @@
+//! Comparison against an external reference implementation (`matmul-solver cross-check`).
+//!
+//! New hardware is certified by comparing with a known-good program: an older solver, a
+//! NumPy script. `cross_check` computes the seed workload locally, then runs the reference
+//! command through `sh -c` with this contract:
+//!
+//! - stdin: one `ReferenceRequest`, the seed, dimensions and precision plus both matrices
+//!   as row arrays, so the reference need not reimplement seed generation
+//! - stdout: one `ReferenceResponse` with `result_hash` (hex SHA-256 of the row-major
+//!   result encoded as `result_dtype`, i.e. f32 little-endian for float results) and/or
+//!   `result_matrix`. A hash-only answer is compared as is; a matrix is hashed here when
+//!   no hash is given and also yields the element-wise maximum absolute error, and
+//!   whether the local result is within the run's tolerance for the precision
+//!   (`ComputeOptions::tolerance`) of it. Only the hash decides the exit status.
+//!
+//! A command that cannot start, exits non-zero, times out or prints no valid response is a
+//! `ReferenceFailure`, reported apart from a mismatch.
+//!
+//! Starting the reference is the library's only process spawn, so the module is behind the
+//! `cross-check` feature and goes through `host`.
+
+use crate::host;
+use crate::tolerance::{self, EffectiveTolerance};
+use crate::{compute_hash_as, compute_workload_with_options, generate_matrices_from_seed, types, ComputeOptions, FlatMatrix, ResultDtype, SolverError};
+use serde::{Deserialize, Serialize};
+use std::io::{Read, Write};
+use std::time::{Duration, Instant};
+
+/// Written to the reference command's stdin
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ReferenceRequest {
+    /// Seed the matrices were generated from (hex)
+    pub seed: String,
+    pub m: usize,
+    pub k: usize,
+    pub n: usize,
+    pub precision: String,
+    /// Encoding `result_hash` must use: "f32" (little-endian), or "i32" for exact integers
+    pub result_dtype: String,
+    pub matrix_a: FlatMatrix,
+    pub matrix_b: FlatMatrix,
+}
+
+/// Read from the reference command's stdout
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct ReferenceResponse {
+    #[serde(default)]
+    pub result_hash: Option<String>,
+    #[serde(default)]
+    pub result_matrix: Option<FlatMatrix>,
+}
+
+#[derive(Debug, Clone)]
+pub struct CrossCheckConfig {
+    /// Shell command of the reference implementation
+    pub reference_cmd: String,
+    /// Kill the reference after this long
+    pub timeout: Duration,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CrossCheckReport {
+    pub seed: String,
+    pub precision: String,
+    pub dims: (usize, usize, usize),
+    pub kernel: Option<String>,
+    pub local_hash: String,
+    pub reference_hash: String,
+    pub hash_match: bool,
+    /// Largest |local - reference| element difference, when the reference sent its matrix
+    pub max_abs_error: Option<f64>,
+    /// Whether the local result is within `tolerance` of the reference matrix, when sent
+    #[serde(default)]
+    pub within_tolerance: Option<bool>,
+    #[serde(default)]
+    pub tolerance: Option<EffectiveTolerance>,
+    /// Wall time of the local generation and computation
+    pub local_ms: f64,
+    /// Wall time of the reference command, process start-up included
+    pub reference_ms: f64,
+}
+
+impl CrossCheckReport {
+    /// Human-readable summary for the console
+    pub fn to_table(&self) -> String {
+        let (m, k, n) = self.dims;
+        let mut out = format!("Cross-check: {} {}x{} × {}x{}, seed {}\n", self.precision, m, k, k, n, self.seed);
+        out.push_str(&format!("{:<16} {}\n", "local hash", self.local_hash));
+        out.push_str(&format!("{:<16} {}\n", "reference hash", self.reference_hash));
+        out.push_str(&format!("{:<16} {}\n", "hash match", if self.hash_match { "yes" } else { "NO" }));
+        if let Some(error) = self.max_abs_error {
+            out.push_str(&format!("{:<16} {:e}\n", "max abs error", error));
+        }
+        if let (Some(within), Some(tolerance)) = (self.within_tolerance, &self.tolerance) {
+            out.push_str(&format!("{:<16} {} ({})\n", "within tolerance", if within { "yes" } else { "NO" }, tolerance.describe()));
+        }
+        out.push_str(&format!("{:<16} {:.3} ms ({})\n", "local", self.local_ms, self.kernel.as_deref().unwrap_or("-")));
+        out.push_str(&format!("{:<16} {:.3} ms\n", "reference", self.reference_ms));
+        out
+    }
+}
+
+/// Why the reference gave no answer to compare
+#[derive(Debug, Clone, PartialEq)]
+pub enum ReferenceFailure {
+    /// The command could not be started
+    Spawn(String),
+    /// It exited unsuccessfully (None when killed by a signal)
+    Exit { code: Option<i32>, stderr: String },
+    Timeout(Duration),
+    /// Its stdout was not a usable `ReferenceResponse`
+    BadOutput(String),
+}
+
+impl std::fmt::Display for ReferenceFailure {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ReferenceFailure::Spawn(msg) => write!(f, "could not start the reference command: {}", msg),
+            ReferenceFailure::Exit { code, stderr } => {
+                match code {
+                    Some(code) => write!(f, "reference command exited with status {}", code)?,
+                    None => write!(f, "reference command was killed by a signal")?,
+                }
+                if !stderr.trim().is_empty() {
+                    write!(f, ": {}", stderr.trim())?;
+                }
+                Ok(())
+            }
+            ReferenceFailure::Timeout(limit) => write!(f, "reference command did not finish within {:?}", limit),
+            ReferenceFailure::BadOutput(msg) => write!(f, "reference output unusable: {}", msg),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum CrossCheckError {
+    /// The local computation failed
+    Local(SolverError),
+    Reference(ReferenceFailure),
+}
+
+impl std::fmt::Display for CrossCheckError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            CrossCheckError::Local(e) => write!(f, "local computation failed: {}", e),
+            CrossCheckError::Reference(e) => write!(f, "{}", e),
+        }
+    }
+}
+
+impl std::error::Error for CrossCheckError {}
+
+/// Compute the m×k × k×n matrices of `seed` locally and with the reference, and compare
+pub fn cross_check(
+    seed: &[u8],
+    (m, k, n): (usize, usize, usize),
+    precision: &str,
+    options: &ComputeOptions,
+    config: &CrossCheckConfig,
+) -> Result<CrossCheckReport, CrossCheckError> {
+    let clock = options.clock();
+    let start = clock.now();
+    let (matrix_a, matrix_b) = generate_matrices_from_seed(seed, m, k, k, n);
+    let input = types::Input {
+        matrix_a: matrix_a.clone(),
+        matrix_b: matrix_b.clone(),
+        precision: precision.to_string(),
+        ..Default::default()
+    };
+    let output = compute_workload_with_options(input, options).map_err(CrossCheckError::Local)?;
+    let local_ms = clock.elapsed_since(start).as_secs_f64() * 1000.0;
+
+    let request = ReferenceRequest {
+        seed: hex::encode(seed),
+        m,
+        k,
+        n,
+        precision: precision.to_string(),
+        result_dtype: output.metadata.result_dtype.clone(),
+        matrix_a,
+        matrix_b,
+    };
+    let stdin = serde_json::to_vec(&request).expect("reference request serializes");
+    let start = clock.now();
+    let stdout = run_reference(&config.reference_cmd, stdin, config.timeout).map_err(CrossCheckError::Reference)?;
+    let reference_ms = clock.elapsed_since(start).as_secs_f64() * 1000.0;
+
+    let bad_output = |msg: String| CrossCheckError::Reference(ReferenceFailure::BadOutput(msg));
+    let response: ReferenceResponse = serde_json::from_slice(&stdout).map_err(|e| bad_output(e.to_string()))?;
+    let dtype = ResultDtype::parse(&output.metadata.result_dtype)
+        .ok_or_else(|| CrossCheckError::Local(SolverError::Internal(format!("unknown dtype {}", output.metadata.result_dtype))))?;
+    let tolerance = options.tolerance.effective(precision);
+    let comparison = match &response.result_matrix {
+        Some(reference) => Some(compare(&output.result_matrix, reference, &tolerance).map_err(bad_output)?),
+        None => None,
+    };
+    let reference_hash = match (response.result_hash, &response.result_matrix) {
+        (Some(hash), _) => hash.to_ascii_lowercase(),
+        (None, Some(matrix)) => compute_hash_as(matrix, dtype),
+        (None, None) => return Err(bad_output("neither result_hash nor result_matrix".to_string())),
+    };
+    Ok(CrossCheckReport {
+        seed: request.seed,
+        precision: precision.to_string(),
+        dims: (m, k, n),
+        kernel: output.metadata.kernel.clone(),
+        hash_match: reference_hash == output.result_hash,
+        local_hash: output.result_hash,
+        reference_hash,
+        max_abs_error: comparison.map(|c| c.max_abs_error),
+        within_tolerance: comparison.map(|c| c.within_tolerance),
+        tolerance: comparison.map(|_| tolerance),
+        local_ms,
+        reference_ms,
+    })
+}
+
+fn compare(local: &FlatMatrix, reference: &FlatMatrix, tolerance: &EffectiveTolerance) -> Result<tolerance::Comparison, String> {
+    if (reference.rows, reference.cols) != (local.rows, local.cols) || reference.data.len() != local.data.len() {
+        return Err(format!(
+            "result_matrix is {}x{}, expected {}x{}",
+            reference.rows, reference.cols, local.rows, local.cols
+        ));
+    }
+    Ok(tolerance::compare(local, reference, tolerance.tolerance()))
+}
+
+/// Run `cmd` with `stdin`, returning its stdout once it exits successfully
+fn run_reference(cmd: &str, stdin: Vec<u8>, timeout: Duration) -> Result<Vec<u8>, ReferenceFailure> {
+    let mut child = host::spawn_shell(cmd).map_err(|e| ReferenceFailure::Spawn(e.to_string()))?;
+
+    // Feed and drain the pipes on their own threads so a chatty reference cannot deadlock us.
+    // A reference that exits without reading its input closes the pipe; that is not an error.
+    let mut child_stdin = child.stdin.take().expect("piped stdin");
+    let writer = std::thread::spawn(move || {
+        let _ = child_stdin.write_all(&stdin);
+    });
+    let drain = |mut pipe: Box<dyn Read + Send>| {
+        std::thread::spawn(move || {
+            let mut bytes = Vec::new();
+            let _ = pipe.read_to_end(&mut bytes);
+            bytes
+        })
+    };
+    let stdout = drain(Box::new(child.stdout.take().expect("piped stdout")));
+    let stderr = drain(Box::new(child.stderr.take().expect("piped stderr")));
+
+    let deadline = Instant::now() + timeout;
+    let status = loop {
+        match child.try_wait() {
+            Ok(Some(status)) => break status,
+            Ok(None) if Instant::now() >= deadline => {
+                let _ = child.kill();
+                let _ = child.wait();
+                return Err(ReferenceFailure::Timeout(timeout));
+            }
+            Ok(None) => std::thread::sleep(Duration::from_millis(5)),
+            Err(e) => return Err(ReferenceFailure::Spawn(e.to_string())),
+        }
+    };
+    let _ = writer.join();
+    let stdout = stdout.join().unwrap_or_default();
+    let stderr = stderr.join().unwrap_or_default();
+    if !status.success() {
+        return Err(ReferenceFailure::Exit { code: status.code(), stderr: String::from_utf8_lossy(&stderr).into_owned() });
+    }
+    Ok(stdout)
+}
+
+#[cfg(all(test, unix))]
+mod tests {
+    use super::*;
+    use crate::faults::{with_plan, BitFlip, FaultPlan};
+
+    const DIMS: (usize, usize, usize) = (4, 32, 3);
+
+    fn local_output() -> types::Output {
+        let (matrix_a, matrix_b) = generate_matrices_from_seed(b"ref", DIMS.0, DIMS.1, DIMS.1, DIMS.2);
+        let input = types::Input {
+            matrix_a,
+            matrix_b,
+            precision: "fp32".to_string(),
+            ..Default::default()
+        };
+        compute_workload_with_options(input, &ComputeOptions::default()).unwrap()
+    }
+
+    /// Cross-check against a reference that saves its stdin and prints `response`
+    fn run(dir: &std::path::Path, response: &str) -> Result<CrossCheckReport, CrossCheckError> {
+        let answer = dir.join("answer.json");
+        std::fs::write(&answer, response).unwrap();
+        let cmd = format!("cat > '{}'; cat '{}'", dir.join("stdin.json").display(), answer.display());
+        check(&cmd)
+    }
+
+    fn check(cmd: &str) -> Result<CrossCheckReport, CrossCheckError> {
+        check_with(cmd, &ComputeOptions::default())
+    }
+
+    fn check_with(cmd: &str, options: &ComputeOptions) -> Result<CrossCheckReport, CrossCheckError> {
+        let config = CrossCheckConfig { reference_cmd: cmd.to_string(), timeout: Duration::from_secs(10) };
+        cross_check(b"ref", DIMS, "fp32", options, &config)
+    }
+
+    #[test]
+    fn test_match_and_mismatch() {
+        let dir = tempfile::tempdir().unwrap();
+        let local = local_output();
+
+        // A hash-only answer that matches, in upper case
+        let report = run(dir.path(), &format!(r#"{{"result_hash": "{}"}}"#, local.result_hash.to_uppercase())).unwrap();
+        assert!(report.hash_match);
+        assert_eq!((report.max_abs_error, report.local_hash.as_str()), (None, local.result_hash.as_str()));
+        assert!(report.to_table().contains("hash match       yes"));
+
+        // The reference saw the documented request
+        let request: ReferenceRequest = serde_json::from_slice(&std::fs::read(dir.path().join("stdin.json")).unwrap()).unwrap();
+        assert_eq!((request.seed.as_str(), request.m, request.k, request.n), ("726566", 4, 32, 3));
+        assert_eq!((request.precision.as_str(), request.result_dtype.as_str()), ("fp32", "f32"));
+        let (a, b) = generate_matrices_from_seed(b"ref", 4, 32, 32, 3);
+        assert_eq!((request.matrix_a.data, request.matrix_b.data), (a.data, b.data));
+
+        // A matrix-only answer is hashed here; one nudged element is a mismatch with its error
+        let mut matrix = FlatMatrix::clone(&local.result_matrix);
+        let response = serde_json::json!({ "result_matrix": matrix }).to_string();
+        let report = run(dir.path(), &response).unwrap();
+        assert!(report.hash_match);
+        assert_eq!(report.max_abs_error, Some(0.0));
+        matrix.data[5] += 0.5;
+        let report = run(dir.path(), &serde_json::json!({ "result_matrix": matrix }).to_string()).unwrap();
+        assert!(!report.hash_match);
+        assert_eq!(report.max_abs_error, Some(0.5));
+        assert!(report.to_table().contains("hash match       NO"));
+    }
+
+    #[test]
+    fn test_tolerance_verdict_follows_the_policy() {
+        let dir = tempfile::tempdir().unwrap();
+        let local = local_output();
+        let scale = local.result_matrix.data.iter().fold(0f64, |m, &x| m.max(x.abs() as f64));
+        let mut matrix = FlatMatrix::clone(&local.result_matrix);
+        matrix.data[5] += (scale * 1e-4) as f32;
+        let answer = dir.path().join("answer.json");
+        std::fs::write(&answer, serde_json::json!({ "result_matrix": matrix }).to_string()).unwrap();
+        let cmd = format!("cat '{}'", answer.display());
+
+        // 1e-4 of the scale is outside default fp32 tolerance and inside loose
+        let report = check(&cmd).unwrap();
+        assert_eq!(report.within_tolerance, Some(false));
+        assert_eq!(report.tolerance.as_ref().map(|t| (t.profile, t.precision.as_str())), Some((tolerance::ToleranceProfile::Default, "fp32")));
+        assert!(report.to_table().contains("within tolerance NO (default tolerance"), "{}", report.to_table());
+        let loose = ComputeOptions { tolerance: tolerance::TolerancePolicy::parse(Some("loose"), &[]).unwrap(), ..Default::default() };
+        let report = check_with(&cmd, &loose).unwrap();
+        assert_eq!((report.hash_match, report.within_tolerance), (false, Some(true)));
+        let overridden = ComputeOptions { tolerance: tolerance::TolerancePolicy::parse(Some("loose"), &["fp32=0,0".to_string()]).unwrap(), ..Default::default() };
+        let report = check_with(&cmd, &overridden).unwrap();
+        assert_eq!(report.within_tolerance, Some(false));
+        assert!(report.tolerance.unwrap().overridden);
+
+        // A hash-only answer has nothing to judge
+        let report = run(dir.path(), &format!(r#"{{"result_hash": "{}"}}"#, local.result_hash)).unwrap();
+        assert_eq!((report.within_tolerance, report.tolerance), (None, None));
+    }
+
+    #[test]
+    fn test_local_faults_are_caught() {
+        let dir = tempfile::tempdir().unwrap();
+        let local = local_output();
+        let response = serde_json::json!({ "result_matrix": local.result_matrix }).to_string();
+
+        // A flipped sign bit in the local result is a mismatch against a correct reference
+        let flip = FaultPlan { flip_result_bit: Some(BitFlip { element: 7, bit: 31 }), ..Default::default() };
+        let report = with_plan(flip, || run(dir.path(), &response)).unwrap();
+        assert!(!report.hash_match);
+        assert_eq!(report.max_abs_error, Some(2.0 * local.result_matrix.data[7].abs() as f64));
+
+        // A failing kernel is a local error, not a reference failure
+        let fail = FaultPlan { kernel_error: true, ..Default::default() };
+        let err = with_plan(fail, || run(dir.path(), &response)).unwrap_err();
+        assert!(matches!(err, CrossCheckError::Local(SolverError::Internal(_))), "{:?}", err);
+    }
+
+    #[test]
+    fn test_reference_failures_are_distinct() {
+        let dir = tempfile::tempdir().unwrap();
+        match check("echo 'no numpy here' >&2; exit 3") {
+            Err(CrossCheckError::Reference(ReferenceFailure::Exit { code: Some(3), stderr })) => {
+                assert_eq!(stderr.trim(), "no numpy here")
+            }
+            other => panic!("expected an exit failure, got {:?}", other),
+        }
+        let bad = run(dir.path(), "42 is the hash").unwrap_err();
+        assert!(matches!(bad, CrossCheckError::Reference(ReferenceFailure::BadOutput(_))), "{:?}", bad);
+        let empty = run(dir.path(), "{}").unwrap_err();
+        assert!(empty.to_string().contains("neither result_hash nor result_matrix"), "{}", empty);
+        let wrong_shape = run(dir.path(), r#"{"result_matrix": [[1.0]]}"#).unwrap_err();
+        assert!(wrong_shape.to_string().contains("result_matrix is 1x1, expected 4x3"), "{}", wrong_shape);
+
+        let config = CrossCheckConfig { reference_cmd: "sleep 5".to_string(), timeout: Duration::from_millis(50) };
+        let timeout = cross_check(b"ref", DIMS, "fp32", &ComputeOptions::default(), &config).unwrap_err();
+        assert_eq!(timeout, CrossCheckError::Reference(ReferenceFailure::Timeout(Duration::from_millis(50))));
+        let local = cross_check(b"ref", DIMS, "fp8", &ComputeOptions::default(), &config).unwrap_err();
+        assert!(matches!(local, CrossCheckError::Local(SolverError::UnsupportedPrecision(_))));
+    }
+}
@@ -0,0 +1,167 @@
+//! Status page of a running API server.
+//!
+//! GET /dashboard serves `dashboard.html`, a single page compiled into the binary with no
+//! external assets. Its script polls GET /dashboard/data, /stats and /metrics every few
+//! seconds. /dashboard/data holds what the other two do not already report: uptime,
+//! computations served per precision, their recent latencies, packed-B cache hit rates and
+//! the build. All four routes take the DEBUG_TOKEN. Headless deployments build without the
+//! `dashboard` feature, and then neither route nor the counters exist.
+
+use crate::{histogram, types};
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, VecDeque};
+use std::sync::Mutex;
+use std::time::Instant;
+
+/// The page, served as is
+pub const PAGE: &str = include_str!("dashboard.html");
+
+/// Computations kept for the latency chart and percentiles
+pub const RECENT_COMPUTATIONS: usize = 64;
+
+/// Counters behind /dashboard/data, fed with every output the API returns
+#[derive(Debug)]
+pub struct DashboardStats {
+    started: Instant,
+    inner: Mutex<DashboardInner>,
+}
+
+#[derive(Debug, Default)]
+struct DashboardInner {
+    by_precision: BTreeMap<String, u64>,
+    recent: VecDeque<RecentComputation>,
+    pack_cache: BTreeMap<String, CacheHitRate>,
+}
+
+/// One served computation
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct RecentComputation {
+    pub precision: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub kernel: Option<String>,
+    pub latency_ms: f64,
+}
+
+/// Computations whose kernel reused a packed B, against those that packed it
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct CacheHitRate {
+    pub hits: u64,
+    pub misses: u64,
+    /// hits / (hits + misses)
+    pub hit_rate: f64,
+}
+
+/// The binary serving the page
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct BuildInfo {
+    pub solver_version: String,
+    pub compiler_flags: String,
+    pub libraries: Vec<String>,
+}
+
+/// GET /dashboard/data
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct DashboardData {
+    pub uptime_secs: f64,
+    /// Outputs returned by /compute and finished jobs
+    pub computations: u64,
+    pub by_precision: BTreeMap<String, u64>,
+    /// The last `RECENT_COMPUTATIONS`, oldest first
+    pub recent: Vec<RecentComputation>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub latency_p50_ms: Option<f64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub latency_p90_ms: Option<f64>,
+    /// Per precision, for kernels that pack B
+    pub pack_cache: BTreeMap<String, CacheHitRate>,
+    pub build: BuildInfo,
+}
+
+impl Default for DashboardStats {
+    fn default() -> Self {
+        Self { started: Instant::now(), inner: Mutex::default() }
+    }
+}
+
+impl DashboardStats {
+    pub fn record(&self, output: &types::Output) {
+        let precision = &output.metadata.precision;
+        let mut inner = self.inner.lock().unwrap();
+        *inner.by_precision.entry(precision.clone()).or_default() += 1;
+        if inner.recent.len() == RECENT_COMPUTATIONS {
+            inner.recent.pop_front();
+        }
+        inner.recent.push_back(RecentComputation {
+            precision: precision.clone(),
+            kernel: output.metadata.kernel.clone(),
+            latency_ms: output.metrics.latency_ms,
+        });
+        if let Some(hit) = output.metrics.pack_cache_hit {
+            let cache = inner.pack_cache.entry(precision.clone()).or_default();
+            if hit {
+                cache.hits += 1;
+            } else {
+                cache.misses += 1;
+            }
+            cache.hit_rate = cache.hits as f64 / (cache.hits + cache.misses) as f64;
+        }
+    }
+
+    pub fn data(&self) -> DashboardData {
+        let inner = self.inner.lock().unwrap();
+        let mut latencies: Vec<f64> = inner.recent.iter().map(|c| c.latency_ms).collect();
+        latencies.sort_unstable_by(f64::total_cmp);
+        DashboardData {
+            uptime_secs: self.started.elapsed().as_secs_f64(),
+            computations: inner.by_precision.values().sum(),
+            by_precision: inner.by_precision.clone(),
+            recent: inner.recent.iter().cloned().collect(),
+            latency_p50_ms: histogram::percentile(&latencies, 0.5),
+            latency_p90_ms: histogram::percentile(&latencies, 0.9),
+            pack_cache: inner.pack_cache.clone(),
+            build: BuildInfo {
+                solver_version: env!("CARGO_PKG_VERSION").to_string(),
+                compiler_flags: crate::build_compiler_flags().to_string(),
+                libraries: crate::build_libraries(),
+            },
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::compute_workload;
+
+    #[test]
+    fn test_counts_window_and_hit_rates() {
+        let input = |precision: &str| types::Input::matmul(
+            crate::FlatMatrix { data: vec![1.0; 16 * 32], rows: 16, cols: 32 },
+            crate::FlatMatrix { data: vec![2.0; 32 * 16], rows: 32, cols: 16 },
+            precision,
+        );
+        let stats = DashboardStats::default();
+        let empty = stats.data();
+        assert_eq!((empty.computations, empty.latency_p50_ms), (0, None));
+
+        let fp32 = compute_workload(input("fp32")).unwrap();
+        let mut int8 = compute_workload(input("int8")).unwrap();
+        for hit in [false, true, true, true] {
+            int8.metrics.pack_cache_hit = Some(hit);
+            stats.record(&int8);
+        }
+        for _ in 0..RECENT_COMPUTATIONS {
+            stats.record(&fp32);
+        }
+
+        let data = stats.data();
+        assert_eq!(data.computations, RECENT_COMPUTATIONS as u64 + 4);
+        assert_eq!(data.by_precision["int8"], 4);
+        assert_eq!(data.recent.len(), RECENT_COMPUTATIONS);
+        assert!(data.recent.iter().all(|c| c.precision == "fp32"));
+        assert_eq!(data.latency_p50_ms, Some(fp32.metrics.latency_ms));
+        assert_eq!(data.pack_cache["int8"], CacheHitRate { hits: 3, misses: 1, hit_rate: 0.75 });
+        assert_eq!(data.pack_cache.contains_key("fp32"), fp32.metrics.pack_cache_hit.is_some());
+        assert_eq!(data.build.solver_version, env!("CARGO_PKG_VERSION"));
+    }
+}
@@ -0,0 +1,172 @@
+//! Result delta encoding against a baseline output.
+//!
+//! Nonce-search debugging produces many outputs whose results differ in a few elements.
+//! `diff_outputs` records only the changed elements (row-major index + new value) along
+//! with the baseline's hash and the new output's metrics and metadata; `apply_delta`
+//! rebuilds the full output from the baseline and checks the rebuilt result against the
+//! recorded `result_hash`.
+
+use crate::{compute_hash_rounded, types, FlatMatrix, ResultDtype, SolverError};
+use serde::{Deserialize, Serialize};
+
+/// One result element that differs from the baseline
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct DeltaElement {
+    /// Row-major index into the result
+    pub index: usize,
+    pub value: f32,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct OutputDelta {
+    /// `result_hash` of the output this delta applies to
+    pub baseline_hash: String,
+    /// `result_hash` of the encoded output
+    pub result_hash: String,
+    pub result_shape: (usize, usize),
+    pub changes: Vec<DeltaElement>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub tile_trace: Option<Vec<crate::TileDigest>>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub trace_root: Option<String>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub warnings: Vec<crate::warnings::SolverWarning>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub auxiliary_results: Vec<crate::error_metrics::AuxResult>,
+    pub metrics: types::Metrics,
+    pub metadata: types::OutputMetadata,
+}
+
+/// Encode `output` as the elements that differ from `baseline`.
+///
+/// Elements are compared bit for bit, so sign-of-zero and NaN payload changes are kept.
+pub fn diff_outputs(baseline: &types::Output, output: &types::Output) -> Result<OutputDelta, SolverError> {
+    if output.result_f64.is_some() {
+        return Err(SolverError::InvalidInput(
+            "f64 results are hashed over values a delta cannot carry; write the full output".to_string(),
+        ));
+    }
+    let (base, new) = (&baseline.result_matrix, &output.result_matrix);
+    if (base.rows, base.cols) != (new.rows, new.cols) {
+        return Err(SolverError::InvalidInput(format!(
+            "Baseline result is {}x{}, output result is {}x{}",
+            base.rows, base.cols, new.rows, new.cols
+        )));
+    }
+    let changes = base
+        .data
+        .iter()
+        .zip(&new.data)
+        .enumerate()
+        .filter(|(_, (b, n))| b.to_bits() != n.to_bits())
+        .map(|(index, (_, &value))| DeltaElement { index, value })
+        .collect();
+    Ok(OutputDelta {
+        baseline_hash: baseline.result_hash.clone(),
+        result_hash: output.result_hash.clone(),
+        result_shape: (new.rows, new.cols),
+        changes,
+        tile_trace: output.tile_trace.clone(),
+        trace_root: output.trace_root.clone(),
+        warnings: output.warnings.clone(),
+        auxiliary_results: output.auxiliary_results.clone(),
+        metrics: output.metrics.clone(),
+        metadata: output.metadata.clone(),
+    })
+}
+
+/// Rebuild the full output encoded by `delta` on top of `baseline`
+pub fn apply_delta(baseline: &types::Output, delta: &OutputDelta) -> Result<types::Output, SolverError> {
+    if baseline.result_hash != delta.baseline_hash {
+        return Err(SolverError::InvalidInput(format!(
+            "Baseline hash mismatch: delta was made against {}, baseline is {}",
+            delta.baseline_hash, baseline.result_hash
+        )));
+    }
+    let base = &baseline.result_matrix;
+    if (base.rows, base.cols) != delta.result_shape {
+        return Err(SolverError::InvalidInput(format!(
+            "Baseline result is {}x{}, delta expects {}x{}",
+            base.rows, base.cols, delta.result_shape.0, delta.result_shape.1
+        )));
+    }
+    let mut data = base.data.clone();
+    for change in &delta.changes {
+        let slot = data.get_mut(change.index).ok_or_else(|| {
+            SolverError::InvalidInput(format!("Delta index {} is outside the {}-element result", change.index, base.data.len()))
+        })?;
+        *slot = change.value;
+    }
+    let result_matrix = FlatMatrix { data, rows: base.rows, cols: base.cols };
+
+    let dtype = ResultDtype::parse(&delta.metadata.result_dtype)
+        .ok_or_else(|| SolverError::InvalidInput(format!("Unsupported result dtype: {}", delta.metadata.result_dtype)))?;
+    let result_hash = compute_hash_rounded(&result_matrix, dtype, delta.metadata.hash_rounding);
+    if result_hash != delta.result_hash {
+        return Err(SolverError::InvalidInput(format!(
+            "Reconstructed result hashes to {}, delta records {}",
+            result_hash, delta.result_hash
+        )));
+    }
+    Ok(types::Output {
+        result_matrix: std::sync::Arc::new(result_matrix),
+        result_f64: None,
+        result_hash,
+        tile_trace: delta.tile_trace.clone(),
+        trace_root: delta.trace_root.clone(),
+        audit_passed: None,
+        verified: None,
+        warnings: delta.warnings.clone(),
+        batch_hashes: None,
+        auxiliary_results: delta.auxiliary_results.clone(),
+        metrics: delta.metrics.clone(),
+        metadata: delta.metadata.clone(),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{compute_workload, generate_matrices_from_seed};
+
+    fn output(seed: &[u8], tweak: Option<usize>) -> types::Output {
+        let (mut matrix_a, matrix_b) = generate_matrices_from_seed(seed, 16, 48, 48, 16);
+        if let Some(i) = tweak {
+            matrix_a.data[i] += 1.0;
+        }
+        compute_workload(types::Input::matmul(matrix_a, matrix_b, "fp32"))
+            .unwrap()
+    }
+
+    #[test]
+    fn test_delta_round_trip() {
+        let baseline = output(b"delta", None);
+        // Bumping A[2][5] changes exactly row 2 of the result
+        let next = output(b"delta", Some(2 * 48 + 5));
+        let delta = diff_outputs(&baseline, &next).unwrap();
+        assert!(!delta.changes.is_empty() && delta.changes.len() <= 16);
+        assert!(delta.changes.iter().all(|c| c.index / 16 == 2));
+
+        let json = serde_json::to_string(&delta).unwrap();
+        let rebuilt = apply_delta(&baseline, &serde_json::from_str(&json).unwrap()).unwrap();
+        assert_eq!(rebuilt.result_hash, next.result_hash);
+        assert_eq!(rebuilt.result_matrix.data, next.result_matrix.data);
+
+        // Identical outputs encode as no changes
+        assert!(diff_outputs(&baseline, &baseline).unwrap().changes.is_empty());
+    }
+
+    #[test]
+    fn test_delta_rejects_wrong_baseline() {
+        let baseline = output(b"delta", None);
+        let delta = diff_outputs(&baseline, &output(b"delta", Some(0))).unwrap();
+        let err = apply_delta(&output(b"other", None), &delta).unwrap_err();
+        assert!(err.to_string().contains("Baseline hash mismatch"), "{}", err);
+
+        // A tampered change fails the final hash check
+        let mut tampered = delta.clone();
+        tampered.changes[0].value += 1.0;
+        let err = apply_delta(&baseline, &tampered).unwrap_err();
+        assert!(err.to_string().contains("Reconstructed result hashes to"), "{}", err);
+    }
+}
@@ -0,0 +1,222 @@
+//! Self-describing header for the solver's binary artifacts.
+//!
+//! JSON files identify themselves; binary ones do not, and a file fed to the wrong reader
+//! should fail with a message naming both formats rather than a decoding error halfway
+//! through the payload. Every binary file the solver writes (today: the packed-B cache,
+//! see `pack_cache`) starts with this envelope, and its reader calls `open` before
+//! touching the payload. The JSON readers call `reject_binary` so that an enveloped file
+//! passed as an input or output is named instead of reported as invalid JSON.
+//!
+//! Layout (all integers little-endian):
+//!
+//! ```text
+//! magic     8 bytes  "MMSOLVE\0"
+//! format    u16      Format id
+//! version   u16      schema version of the payload
+//! len       u64      payload length in bytes
+//! payload   len bytes
+//! ```
+
+use crate::SolverError;
+
+pub const MAGIC: &[u8; 8] = b"MMSOLVE\0";
+pub const HEADER_LEN: usize = 8 + 2 + 2 + 8;
+
+/// Magic of packed-B cache files written before the envelope (format version 1)
+const LEGACY_PACK_MAGIC: &[u8; 8] = b"MMPACKB\0";
+
+/// What an enveloped payload holds
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Format {
+    /// A persisted packed B buffer (`pack_cache`)
+    PackedB,
+}
+
+impl Format {
+    pub fn id(&self) -> u16 {
+        match self {
+            Format::PackedB => 1,
+        }
+    }
+
+    pub fn from_id(id: u16) -> Option<Format> {
+        match id {
+            1 => Some(Format::PackedB),
+            _ => None,
+        }
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Format::PackedB => "packed-B cache",
+        }
+    }
+}
+
+/// A parsed envelope header
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Envelope {
+    pub format: Format,
+    pub version: u16,
+    pub payload_len: u64,
+}
+
+impl std::fmt::Display for Envelope {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{} v{}", self.format.name(), self.version)
+    }
+}
+
+/// `payload` behind an envelope for `format` at schema `version`
+pub fn encode(format: Format, version: u16, payload: &[u8]) -> Vec<u8> {
+    let mut out = Vec::with_capacity(HEADER_LEN + payload.len());
+    out.extend_from_slice(MAGIC);
+    out.extend_from_slice(&format.id().to_le_bytes());
+    out.extend_from_slice(&version.to_le_bytes());
+    out.extend_from_slice(&(payload.len() as u64).to_le_bytes());
+    out.extend_from_slice(payload);
+    out
+}
+
+/// What `bytes` look like, for error messages: "a packed-B cache v2", "a JSON document", ...
+pub fn identify(bytes: &[u8]) -> String {
+    if bytes.len() >= HEADER_LEN && &bytes[0..8] == MAGIC {
+        let id = u16::from_le_bytes([bytes[8], bytes[9]]);
+        let version = u16::from_le_bytes([bytes[10], bytes[11]]);
+        return match Format::from_id(id) {
+            Some(format) => format!("a {} v{}", format.name(), version),
+            None => format!("an unknown artifact format {} v{} (written by a newer solver?)", id, version),
+        };
+    }
+    if bytes.starts_with(MAGIC) {
+        return "a truncated solver artifact".to_string();
+    }
+    if bytes.starts_with(LEGACY_PACK_MAGIC) {
+        return "a packed-B cache v1".to_string();
+    }
+    match bytes.iter().find(|b| !b.is_ascii_whitespace()) {
+        Some(b'{') | Some(b'[') => "a JSON document".to_string(),
+        None => "empty".to_string(),
+        Some(_) => "not a solver artifact".to_string(),
+    }
+}
+
+/// Parse the envelope at the start of `bytes`, checking the payload length
+pub fn read_header(bytes: &[u8]) -> Result<Envelope, SolverError> {
+    let invalid = |detail: String| SolverError::InvalidInput(format!("this file is {}", detail));
+    if bytes.len() < HEADER_LEN || &bytes[0..8] != MAGIC {
+        return Err(invalid(format!("{}, not an enveloped solver artifact", identify(bytes))));
+    }
+    let id = u16::from_le_bytes([bytes[8], bytes[9]]);
+    let format = Format::from_id(id).ok_or_else(|| invalid(identify(bytes)))?;
+    let envelope = Envelope {
+        format,
+        version: u16::from_le_bytes([bytes[10], bytes[11]]),
+        payload_len: u64::from_le_bytes(bytes[12..20].try_into().unwrap()),
+    };
+    let actual = (bytes.len() - HEADER_LEN) as u64;
+    if actual != envelope.payload_len {
+        return Err(invalid(format!(
+            "a {} whose header declares {} payload bytes, but {} follow",
+            envelope, envelope.payload_len, actual
+        )));
+    }
+    Ok(envelope)
+}
+
+/// The payload of `bytes`, if they hold `format` at schema `version`
+pub fn open(bytes: &[u8], format: Format, version: u16) -> Result<&[u8], SolverError> {
+    let expected = Envelope { format, version, payload_len: 0 };
+    let mismatch = || SolverError::InvalidInput(format!("this file is {}, expected a {}", identify(bytes), expected));
+    if bytes.len() < HEADER_LEN || &bytes[0..8] != MAGIC {
+        return Err(mismatch());
+    }
+    let envelope = read_header(bytes)?;
+    if envelope.format != format || envelope.version != version {
+        return Err(mismatch());
+    }
+    Ok(&bytes[HEADER_LEN..])
+}
+
+/// Fail with a descriptive error if a reader of `expected` (e.g. "an Output JSON document")
+/// was handed a binary solver artifact
+pub fn reject_binary(bytes: &[u8], expected: &str) -> Result<(), SolverError> {
+    if bytes.starts_with(MAGIC) || bytes.starts_with(LEGACY_PACK_MAGIC) {
+        return Err(SolverError::InvalidInput(format!("this file is {}, expected {}", identify(bytes), expected)));
+    }
+    Ok(())
+}
+
+/// The envelope of a file and what its payload header says (`matmul-solver inspect`)
+#[derive(Debug, Clone, PartialEq)]
+pub struct Inspection {
+    pub envelope: Envelope,
+    pub file_len: usize,
+    /// Format-specific header fields, in file order
+    pub fields: Vec<(&'static str, String)>,
+}
+
+impl Inspection {
+    pub fn to_table(&self) -> String {
+        let e = &self.envelope;
+        let mut out = format!(
+            "format    {} (id {})\nversion   {}\npayload   {} bytes ({} bytes with the envelope)\n",
+            e.format.name(),
+            e.format.id(),
+            e.version,
+            e.payload_len,
+            self.file_len
+        );
+        for (name, value) in &self.fields {
+            out.push_str(&format!("{:<9} {}\n", name, value));
+        }
+        out
+    }
+}
+
+/// Read the envelope of `bytes` and the header of its payload, without decoding the payload
+pub fn inspect(bytes: &[u8]) -> Result<Inspection, SolverError> {
+    let envelope = read_header(bytes)?;
+    let payload = &bytes[HEADER_LEN..];
+    let fields = match envelope.format {
+        #[cfg(feature = "blake3-hash")]
+        Format::PackedB => crate::pack_cache::describe(payload, envelope.version),
+        #[cfg(not(feature = "blake3-hash"))]
+        Format::PackedB => {
+            let _ = payload;
+            vec![("note", "built without blake3-hash; payload not described".to_string())]
+        }
+    };
+    Ok(Inspection { envelope, file_len: bytes.len(), fields })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_envelope_round_trip_and_mismatches() {
+        let bytes = encode(Format::PackedB, 2, b"payload");
+        assert_eq!(read_header(&bytes).unwrap(), Envelope { format: Format::PackedB, version: 2, payload_len: 7 });
+        assert_eq!(open(&bytes, Format::PackedB, 2).unwrap(), b"payload");
+
+        let err = open(&bytes, Format::PackedB, 3).unwrap_err().to_string();
+        assert_eq!(err, "this file is a packed-B cache v2, expected a packed-B cache v3");
+        let err = open(br#"{"result_hash": "ab"}"#, Format::PackedB, 2).unwrap_err().to_string();
+        assert_eq!(err, "this file is a JSON document, expected a packed-B cache v2");
+        let err = open(b"MMPACKB\0\x01\0\0\0", Format::PackedB, 2).unwrap_err().to_string();
+        assert_eq!(err, "this file is a packed-B cache v1, expected a packed-B cache v2");
+
+        let mut unknown = bytes.clone();
+        unknown[8] = 9;
+        let err = open(&unknown, Format::PackedB, 2).unwrap_err().to_string();
+        assert!(err.starts_with("this file is an unknown artifact format 9 v2"), "{}", err);
+
+        let err = read_header(&bytes[..bytes.len() - 1]).unwrap_err().to_string();
+        assert!(err.contains("declares 7 payload bytes, but 6 follow"), "{}", err);
+
+        let err = reject_binary(&bytes, "an Output JSON document").unwrap_err().to_string();
+        assert_eq!(err, "this file is a packed-B cache v2, expected an Output JSON document");
+        assert!(reject_binary(b"{}", "an Output JSON document").is_ok());
+    }
+}
@@ -0,0 +1,98 @@
+use crate::validation::Violation;
+use std::fmt;
+
+/// Errors produced by the compute and verification pipeline
+#[derive(Debug, Clone, PartialEq)]
+pub enum SolverError {
+    /// The workload itself is malformed (shapes, missing fields, bad values)
+    InvalidInput(String),
+    /// Input fields violate their workload's shape constraints (see `validation`)
+    ShapeMismatch(Vec<Violation>),
+    /// Precision string not handled by any path
+    UnsupportedPrecision(String),
+    /// workload_type not handled by compute_workload
+    UnsupportedWorkload(String),
+    /// A requested kernel is not registered or cannot run this workload
+    KernelUnavailable(String),
+    /// `ComputeOptions::deadline` elapsed before the result was ready
+    DeadlineExceeded(String),
+    /// `ComputeOptions::cancel` was set while a chunked kernel was running
+    Cancelled(String),
+    /// The workload would exceed, or did exceed, `ComputeOptions::max_memory_bytes`
+    MemoryLimitExceeded(String),
+    /// `redundancy: "dual"` runs disagreed twice in a row (the hashes of the last pair)
+    InconsistentComputation { first_hash: String, second_hash: String },
+    /// A kernel failed in a way the caller cannot fix
+    Internal(String),
+}
+
+impl fmt::Display for SolverError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SolverError::InvalidInput(msg) => write!(f, "{}", msg),
+            SolverError::ShapeMismatch(violations) => {
+                let messages: Vec<String> = violations.iter().map(ToString::to_string).collect();
+                write!(f, "Matrix dimensions incompatible: {}", messages.join("; "))
+            }
+            SolverError::UnsupportedPrecision(precision) => write!(f, "Unsupported precision: {}", precision),
+            SolverError::UnsupportedWorkload(workload) => write!(f, "Unsupported workload type: {}", workload),
+            SolverError::KernelUnavailable(msg) => write!(f, "Kernel unavailable: {}", msg),
+            SolverError::DeadlineExceeded(msg) => write!(f, "Deadline exceeded: {}", msg),
+            SolverError::Cancelled(msg) => write!(f, "Cancelled: {}", msg),
+            SolverError::MemoryLimitExceeded(msg) => write!(f, "Memory limit exceeded: {}", msg),
+            SolverError::InconsistentComputation { first_hash, second_hash } => write!(
+                f,
+                "Inconsistent computation: redundant runs hashed to {} and {}",
+                first_hash, second_hash
+            ),
+            SolverError::Internal(msg) => write!(f, "Internal error: {}", msg),
+        }
+    }
+}
+
+impl SolverError {
+    /// Stable snake_case name of the variant, for reports that outlive the message text
+    pub fn code(&self) -> &'static str {
+        match self {
+            SolverError::InvalidInput(_) => "invalid_input",
+            SolverError::ShapeMismatch(_) => "shape_mismatch",
+            SolverError::UnsupportedPrecision(_) => "unsupported_precision",
+            SolverError::UnsupportedWorkload(_) => "unsupported_workload",
+            SolverError::KernelUnavailable(_) => "kernel_unavailable",
+            SolverError::DeadlineExceeded(_) => "deadline_exceeded",
+            SolverError::Cancelled(_) => "cancelled",
+            SolverError::MemoryLimitExceeded(_) => "memory_limit_exceeded",
+            SolverError::InconsistentComputation { .. } => "inconsistent_computation",
+            SolverError::Internal(_) => "internal",
+        }
+    }
+}
+
+impl std::error::Error for SolverError {}
+
+/// Why a `FlatMatrix` could not be built from, or does not match, its parts
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum MatrixError {
+    /// `data.len()` is not `rows * cols`
+    LengthMismatch { len: usize, rows: usize, cols: usize },
+    /// `rows * cols` does not fit in a usize
+    TooLarge { rows: usize, cols: usize },
+    /// Row `row` of a nested matrix has `len` elements where the rows before it have `cols`
+    RaggedRow { row: usize, len: usize, cols: usize },
+}
+
+impl fmt::Display for MatrixError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            MatrixError::LengthMismatch { len, rows, cols } => {
+                write!(f, "data has {} elements but a {}x{} matrix needs {}", len, rows, cols, rows * cols)
+            }
+            MatrixError::TooLarge { rows, cols } => write!(f, "a {}x{} matrix has more elements than fit in memory", rows, cols),
+            MatrixError::RaggedRow { row, len, cols } => {
+                write!(f, "Inconsistent row lengths: row {} has {} elements, expected {}", row, len, cols)
+            }
+        }
+    }
+}
+
+impl std::error::Error for MatrixError {}
@@ -0,0 +1,130 @@
+//! fp32 reference results for reduced-precision matmuls (`ComputeOptions::error_metrics`).
+//!
+//! How far an fp16, bf16 or quantized result is from full precision takes a second, fp32
+//! computation on the same operands. Rather than discarding it once the error is known,
+//! the reference is kept in `Output::auxiliary_results`: its precision, its `result_hash`
+//! (exact fp32 hash, whatever hash rounding the primary used) and the largest absolute
+//! difference from the primary result. With `ComputeOptions::aux_result_matrices` the
+//! reference matrix is included too; hashes always are. fp32 and fp64 runs have nothing
+//! to compare against and get no entry, and neither do fp32_strict ones.
+//!
+//! Each entry also says whether the primary result is within the run's tolerance
+//! (`ComputeOptions::tolerance`) for its precision, and which tolerance that was.
+
+use crate::tolerance::{self, EffectiveTolerance};
+use crate::{types, ComputeOptions, FlatMatrix, SolverError};
+use serde::{Deserialize, Serialize};
+use std::sync::Arc;
+
+/// The precision reduced-precision results are compared against
+pub const REFERENCE_PRECISION: &str = "fp32";
+
+/// A further full computation of an output's workload, kept next to the primary result
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AuxResult {
+    pub precision: String,
+    pub result_hash: String,
+    /// Largest |primary − aux| over the result elements
+    pub error_vs_primary: f64,
+    /// Whether the primary result is within `tolerance` of this one
+    pub within_tolerance: bool,
+    pub tolerance: EffectiveTolerance,
+    /// The aux result, when asked for (`aux_result_matrices`)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub result_matrix: Option<Arc<FlatMatrix>>,
+}
+
+/// Whether `precision` gets an fp32 reference
+fn has_reference(precision: &str) -> bool {
+    !matches!(precision, "fp32" | "fp32_strict" | "fp64")
+}
+
+/// Recompute `input` at fp32 and record it in `output.auxiliary_results`
+pub(crate) fn attach_reference(input: &types::Input, output: &mut types::Output, options: &ComputeOptions) -> Result<(), SolverError> {
+    if !has_reference(&input.precision) {
+        return Ok(());
+    }
+    let reference_input = types::Input { precision: REFERENCE_PRECISION.to_string(), hash_input: None, ..input.clone() };
+    // Only what shapes the result or bounds the run carries over; the reference is the
+    // default fp32 computation
+    let reference_options = ComputeOptions {
+        result_columns: options.result_columns.clone(),
+        clock: options.clock.clone(),
+        deadline: options.deadline,
+        max_memory_bytes: options.max_memory_bytes,
+        cancel: options.cancel.clone(),
+        exact_names: options.exact_names,
+        ..Default::default()
+    };
+    let reference = crate::compute_workload_ref(&reference_input, &reference_options)?;
+    let tolerance = options.tolerance.effective(&input.precision);
+    let comparison = tolerance::compare(&output.result_matrix, &reference.result_matrix, tolerance.tolerance());
+    output.auxiliary_results.push(AuxResult {
+        precision: REFERENCE_PRECISION.to_string(),
+        result_hash: reference.result_hash,
+        error_vs_primary: comparison.max_abs_error,
+        within_tolerance: comparison.within_tolerance,
+        tolerance,
+        result_matrix: options.aux_result_matrices.then_some(reference.result_matrix),
+    });
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tolerance::{ToleranceProfile, TolerancePolicy};
+    use crate::{compute_hash, compute_workload_with_options, generate_matrices_from_seed};
+
+    fn input(precision: &str) -> types::Input {
+        let (mut matrix_a, mut matrix_b) = generate_matrices_from_seed(b"error-metrics", 16, 64, 64, 16);
+        // Fractional operands, so reduced precisions round them
+        matrix_a.data.iter_mut().chain(&mut matrix_b.data).for_each(|x| *x /= 7.0);
+        types::Input::matmul(matrix_a, matrix_b, precision)
+    }
+
+    #[test]
+    fn test_reduced_precisions_get_an_fp32_reference() {
+        let options = ComputeOptions { error_metrics: true, ..Default::default() };
+        let fp32 = compute_workload_with_options(input("fp32"), &ComputeOptions::default()).unwrap();
+        let reduced = ["bf16", "int8"].into_iter().chain(cfg!(feature = "fp16").then_some("fp16"));
+        for precision in reduced {
+            let output = compute_workload_with_options(input(precision), &options).unwrap();
+            let [aux] = output.auxiliary_results.as_slice() else { panic!("{}: {:?}", precision, output.auxiliary_results) };
+            assert_eq!((aux.precision.as_str(), &aux.result_hash), ("fp32", &fp32.result_hash), "{}", precision);
+            assert!(aux.result_matrix.is_none());
+
+            // Rounding the operands costs something, but little next to the results' size
+            let largest = fp32.result_matrix.data.iter().fold(0f64, |m, &x| m.max(x.abs() as f64));
+            assert!(aux.error_vs_primary > 0.0 && aux.error_vs_primary < largest / 20.0, "{}: {} of {}", precision, aux.error_vs_primary, largest);
+            // ... and within the default profile's tolerance for the precision
+            assert!(aux.within_tolerance, "{}: {} of {} under {:?}", precision, aux.error_vs_primary, largest, aux.tolerance);
+            assert_eq!((aux.tolerance.profile, aux.tolerance.precision.as_str()), (ToleranceProfile::Default, precision));
+        }
+
+        // The matrix on request; nothing for fp32 or without the option
+        let with_matrix = ComputeOptions { aux_result_matrices: true, ..options.clone() };
+        let output = compute_workload_with_options(input("bf16"), &with_matrix).unwrap();
+        let matrix = output.auxiliary_results[0].result_matrix.as_ref().unwrap();
+        assert_eq!(compute_hash(matrix), fp32.result_hash);
+        assert!(compute_workload_with_options(input("fp32"), &options).unwrap().auxiliary_results.is_empty());
+        assert!(compute_workload_with_options(input("bf16"), &ComputeOptions::default()).unwrap().auxiliary_results.is_empty());
+    }
+
+    #[test]
+    fn test_verdict_follows_the_tolerance_policy() {
+        let verdict = |policy: TolerancePolicy| {
+            let options = ComputeOptions { error_metrics: true, tolerance: policy, ..Default::default() };
+            let output = compute_workload_with_options(input("bf16"), &options).unwrap();
+            let aux = output.auxiliary_results.into_iter().next().unwrap();
+            (aux.within_tolerance, aux.tolerance)
+        };
+        // bf16 rounding is far outside fp32-class tolerances, and within a loose override
+        let (within, tolerance) = verdict(TolerancePolicy::parse(Some("strict"), &["bf16=1e-7,0".to_string()]).unwrap());
+        assert!(!within);
+        assert_eq!((tolerance.rel, tolerance.abs, tolerance.overridden), (1e-7, 0.0, true));
+        let (within, tolerance) = verdict(TolerancePolicy::parse(Some("strict"), &["bf16=0.5,0".to_string()]).unwrap());
+        assert!(within);
+        assert_eq!(tolerance.profile, ToleranceProfile::Strict);
+    }
+}
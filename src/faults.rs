@@ -0,0 +1,155 @@
+//! Deterministic fault injection for tests (`fault-injection` feature, always on in unit tests).
+//!
+//! Redundancy, verification, audits and cross-checks exist to catch a solver that gives a
+//! wrong answer, so their tests need one on demand. A `FaultPlan` installed on the current
+//! thread with `with_plan` can flip a bit of a result element after the kernel, make the
+//! kernel fail with `SolverError::Internal`, corrupt one byte fed to the result hasher, or
+//! make the in-process packed-B caches reuse an entry packed from a different B of the same
+//! shape. Without the feature this module and every hook calling it are compiled out.
+
+use crate::kernels::KernelResult;
+use crate::SolverError;
+use sha2::{Digest, Sha256};
+use std::cell::{Cell, RefCell};
+
+/// Faults to inject on the current thread
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct FaultPlan {
+    /// Flip a bit of a result element after the kernel
+    pub flip_result_bit: Option<BitFlip>,
+    /// Fail the kernel with `SolverError::Internal`
+    pub kernel_error: bool,
+    /// Kernel runs (0-based, counted on this thread from `with_plan`) that the two kernel
+    /// faults above apply to; None applies them to every run
+    pub kernel_runs: Option<Vec<usize>>,
+    /// Invert this byte (0-based) of the bytes fed to every result hash
+    pub corrupt_hash_byte: Option<usize>,
+    /// The packed-B caches report a hit for any entry of the same shape, whatever B it came from
+    pub stale_cache_hit: bool,
+    /// Allocate and free this many bytes once the kernel returns, as a kernel that outgrows
+    /// its memory estimate would
+    pub kernel_ballast_bytes: Option<usize>,
+}
+
+/// One bit of one row-major result element
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct BitFlip {
+    pub element: usize,
+    pub bit: u32,
+}
+
+thread_local! {
+    static PLAN: RefCell<FaultPlan> = RefCell::new(FaultPlan::default());
+    static KERNEL_RUNS: Cell<usize> = const { Cell::new(0) };
+}
+
+/// Run `f` with `plan` installed on the current thread, restoring the previous plan (and
+/// its kernel run count) afterwards, even when `f` panics
+pub fn with_plan<T>(plan: FaultPlan, f: impl FnOnce() -> T) -> T {
+    struct Reset(FaultPlan, usize);
+    impl Drop for Reset {
+        fn drop(&mut self) {
+            PLAN.with(|p| *p.borrow_mut() = std::mem::take(&mut self.0));
+            KERNEL_RUNS.with(|runs| runs.set(self.1));
+        }
+    }
+    let _reset = Reset(PLAN.with(|p| p.replace(plan)), KERNEL_RUNS.with(|runs| runs.replace(0)));
+    f()
+}
+
+/// Apply the kernel faults to a kernel's result. The flipped bit is flipped in the exact
+/// accumulators and f64 results too, so every hash input sees it.
+pub(crate) fn after_kernel(result: &mut KernelResult) -> Result<(), SolverError> {
+    let run = KERNEL_RUNS.with(|runs| runs.replace(runs.get() + 1));
+    let plan = PLAN.with(|p| p.borrow().clone());
+    if plan.kernel_runs.as_ref().is_some_and(|runs| !runs.contains(&run)) {
+        return Ok(());
+    }
+    if plan.kernel_error {
+        return Err(SolverError::Internal(format!("injected kernel fault in run {}", run)));
+    }
+    if let Some(bytes) = plan.kernel_ballast_bytes {
+        drop(std::hint::black_box(vec![0u8; bytes]));
+    }
+    if let Some(BitFlip { element, bit }) = plan.flip_result_bit {
+        let x = &mut result.result.data[element];
+        *x = f32::from_bits(x.to_bits() ^ (1 << bit));
+        if let Some(acc) = result.accumulators.as_mut() {
+            acc[element] ^= 1 << bit;
+        }
+        if let Some(values) = result.result_f64.as_mut() {
+            values[element] = f64::from_bits(values[element].to_bits() ^ (1 << bit));
+        }
+    }
+    Ok(())
+}
+
+/// Whether the packed-B caches should reuse a same-shaped entry of another B
+pub(crate) fn stale_cache_hit() -> bool {
+    PLAN.with(|p| p.borrow().stale_cache_hit)
+}
+
+/// SHA-256 of a result, corrupting the byte the plan names
+pub(crate) struct Hasher {
+    inner: Sha256,
+    fed: usize,
+    corrupt: Option<usize>,
+}
+
+impl Hasher {
+    pub(crate) fn new() -> Self {
+        Self { inner: Sha256::new(), fed: 0, corrupt: PLAN.with(|p| p.borrow().corrupt_hash_byte) }
+    }
+
+    pub(crate) fn update(&mut self, bytes: impl AsRef<[u8]>) {
+        let bytes = bytes.as_ref();
+        match self.corrupt {
+            Some(n) if (self.fed..self.fed + bytes.len()).contains(&n) => {
+                let mut copy = bytes.to_vec();
+                copy[n - self.fed] = !copy[n - self.fed];
+                self.inner.update(&copy);
+            }
+            _ => self.inner.update(bytes),
+        }
+        self.fed += bytes.len();
+    }
+
+    pub(crate) fn finalize(self) -> sha2::digest::Output<Sha256> {
+        self.inner.finalize()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{compute_hash, compute_hash_i32, FlatMatrix};
+
+    #[test]
+    fn test_plans_are_scoped_and_counted() {
+        let matrix = FlatMatrix { data: vec![1.0, 2.0], rows: 1, cols: 2 };
+        let clean = compute_hash(&matrix);
+        let corrupt = |n| FaultPlan { corrupt_hash_byte: Some(n), ..Default::default() };
+        // Bytes 0..8 are the two f32 elements, so byte 8 is never fed
+        assert_ne!(with_plan(corrupt(5), || compute_hash(&matrix)), clean);
+        assert_eq!(with_plan(corrupt(8), || compute_hash(&matrix)), clean);
+        assert_ne!(with_plan(corrupt(0), || compute_hash_i32(&[7])), compute_hash_i32(&[7]));
+
+        // Only the listed runs fail, and nested plans restore the outer one and its count
+        let plan = FaultPlan { kernel_error: true, kernel_runs: Some(vec![1]), ..Default::default() };
+        let mut result = KernelResult::new(matrix.clone(), std::time::Duration::ZERO);
+        with_plan(plan, || {
+            assert!(after_kernel(&mut result).is_ok());
+            with_plan(FaultPlan::default(), || assert!(after_kernel(&mut result).is_ok()));
+            let err = after_kernel(&mut result).unwrap_err();
+            assert_eq!(err.to_string(), "Internal error: injected kernel fault in run 1");
+        });
+        assert!(after_kernel(&mut result).is_ok());
+
+        let flip = FaultPlan { flip_result_bit: Some(BitFlip { element: 1, bit: 31 }), ..Default::default() };
+        result.accumulators = Some(vec![0, 3]);
+        with_plan(flip, || after_kernel(&mut result)).unwrap();
+        assert_eq!((result.result.data[1], result.accumulators.unwrap()[1]), (-2.0, 3 ^ (1 << 31)));
+        let caught = std::panic::catch_unwind(|| with_plan(FaultPlan { stale_cache_hit: true, ..Default::default() }, || panic!()));
+        assert!(caught.is_err() && !stale_cache_hit());
+    }
+}
@@ -0,0 +1,202 @@
+//! Hashing a result while it is serialized (`ComputeOptions::defer_result_hash`).
+//!
+//! Writing a large output takes two passes over the result after the kernel: one to hash
+//! it and one to serialize it. With the hash deferred there is one. `FlatMatrix`'s
+//! serializer feeds each row of the output's result to the hasher just before it writes
+//! the row, while it is still in cache, and `result_hash`, which follows `result_matrix` in
+//! an Output, is written from the finished digest. The hash covers the canonical bytes
+//! `compute_hash_rounded` covers (each element after hash rounding, in the result dtype's
+//! encoding), not the serialized text, so it is the hash a separate pass produces and the
+//! written document is byte-for-byte the one a separately hashed output serializes to.
+//!
+//! `write_json` does this for JSON (the CLI output file, API responses); `serialize_hashed`
+//! wraps any other serializer, e.g. msgpack. The hasher lives in a thread-local for the
+//! length of the call, like the parse limits in numbers.rs: serde gives a serializer no
+//! other way to carry it, and serialization does not leave the calling thread.
+
+use crate::{types, FlatMatrix, HashRounding, ResultDtype, ResultHasher, SolverError};
+use serde::Serializer;
+#[cfg(not(any(feature = "fault-injection", test)))]
+use sha2::Digest;
+use std::cell::RefCell;
+use std::io::Write;
+use std::sync::Arc;
+
+thread_local! {
+    static PENDING: RefCell<Option<Pending>> = const { RefCell::new(None) };
+}
+
+/// The deferred hash of one output's result, while that output is being serialized
+struct Pending {
+    /// The result being hashed; other matrices serialized meanwhile are not fed
+    result: Arc<FlatMatrix>,
+    dtype: ResultDtype,
+    rounding: Option<HashRounding>,
+    hasher: Option<ResultHasher>,
+    /// Elements fed so far
+    fed: usize,
+    hash: Option<String>,
+}
+
+impl Pending {
+    fn finish(&mut self) -> String {
+        if let Some(hash) = &self.hash {
+            return hash.clone();
+        }
+        let hasher = self.hasher.take().expect("a pending hash without its hash has a hasher");
+        let hash = if self.fed == self.result.data.len() {
+            hex::encode(hasher.finalize())
+        } else {
+            // The serializer skipped rows (or wrote the hash first): hash in a separate pass
+            crate::compute_hash_rounded(&self.result, self.dtype, self.rounding)
+        };
+        self.hash = Some(hash.clone());
+        hash
+    }
+}
+
+/// Clears the pending hash when serialization ends, however it ends
+struct Installed;
+
+impl Drop for Installed {
+    fn drop(&mut self) {
+        PENDING.with(|p| p.borrow_mut().take());
+    }
+}
+
+/// Called by `FlatMatrix`'s serializer for each row it is about to write
+pub(crate) fn feed_row(matrix: &FlatMatrix, row: &[f32]) {
+    PENDING.with(|p| {
+        if let Some(pending) = p.borrow_mut().as_mut().filter(|p| std::ptr::eq(Arc::as_ptr(&p.result), matrix)) {
+            if let Some(hasher) = pending.hasher.as_mut() {
+                crate::hash_elements(hasher, row, pending.dtype, pending.rounding);
+                pending.fed += row.len();
+            }
+        }
+    });
+}
+
+/// `serialize_with` of `Output::result_hash`: a deferred (empty) hash is written as the
+/// digest of the rows fed so far
+pub(crate) fn serialize_result_hash<S: Serializer>(hash: &str, serializer: S) -> Result<S::Ok, S::Error> {
+    match PENDING.with(|p| p.borrow_mut().as_mut().map(Pending::finish)) {
+        Some(fused) if hash.is_empty() => serializer.serialize_str(&fused),
+        _ => serializer.serialize_str(hash),
+    }
+}
+
+/// Run `serialize`, which serializes `output`, computing a deferred `result_hash` as it
+/// goes. Returns what `serialize` returned and the output's result hash; an output that
+/// already has its hash is serialized as is.
+pub fn serialize_hashed<T, E: std::fmt::Display>(
+    output: &types::Output,
+    serialize: impl FnOnce() -> Result<T, E>,
+) -> Result<(T, String), SolverError> {
+    let serialize_error = |e: E| SolverError::Internal(format!("failed to serialize output: {}", e));
+    if !output.result_hash.is_empty() {
+        return Ok((serialize().map_err(serialize_error)?, output.result_hash.clone()));
+    }
+    let dtype = ResultDtype::parse(&output.metadata.result_dtype)
+        .ok_or_else(|| SolverError::Internal(format!("unknown result dtype {:?}", output.metadata.result_dtype)))?;
+    if matches!(dtype, ResultDtype::I32 | ResultDtype::F64) {
+        return Err(SolverError::Internal(format!(
+            "a {} result hash does not cover result_matrix and cannot be deferred",
+            dtype.as_str()
+        )));
+    }
+    let pending = Pending {
+        result: Arc::clone(&output.result_matrix),
+        dtype,
+        rounding: output.metadata.hash_rounding,
+        hasher: Some(ResultHasher::new()),
+        fed: 0,
+        hash: None,
+    };
+    PENDING.with(|p| *p.borrow_mut() = Some(pending));
+    let _installed = Installed;
+    let serialized = serialize().map_err(serialize_error)?;
+    let hash = PENDING.with(|p| p.borrow_mut().as_mut().map(Pending::finish)).expect("the pending hash is installed");
+    Ok((serialized, hash))
+}
+
+/// Write `output` as JSON (pretty-printed or compact) to `writer`, returning its result
+/// hash; a deferred hash is computed in the same pass over the result
+pub fn write_json<W: Write>(mut writer: W, output: &types::Output, pretty: bool) -> Result<String, SolverError> {
+    let ((), hash) = serialize_hashed(output, || match pretty {
+        true => serde_json::to_writer_pretty(&mut writer, output),
+        false => serde_json::to_writer(&mut writer, output),
+    })?;
+    writer.flush().map_err(|e| SolverError::Internal(format!("failed to write output: {}", e)))?;
+    Ok(hash)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{compute_workload_with_options, generate_matrices_from_seed, ComputeOptions};
+
+    fn outputs(precision: &str, (m, k, n): (usize, usize, usize), rounding: Option<HashRounding>) -> (types::Output, types::Output) {
+        let (matrix_a, matrix_b) = generate_matrices_from_seed(b"fused", m, k, k, n);
+        let input = types::Input::matmul(matrix_a, matrix_b, precision);
+        let options = ComputeOptions { hash_rounding: rounding, ..Default::default() };
+        let separate = compute_workload_with_options(input.clone(), &options).unwrap();
+        let mut deferred = compute_workload_with_options(input, &ComputeOptions { defer_result_hash: true, ..options }).unwrap();
+        assert_eq!(deferred.result_matrix.data, separate.result_matrix.data);
+        // Only the timings differ between the two runs
+        deferred.metrics = separate.metrics.clone();
+        (separate, deferred)
+    }
+
+    #[test]
+    fn test_fused_matches_separate_passes() {
+        // 512×1024 results: 2 MB of f32, several MB of JSON
+        for precision in ["fp32", "u8i8"] {
+            let (separate, deferred) = outputs(precision, (512, 16, 1024), None);
+            assert!(deferred.result_hash.is_empty(), "{}", precision);
+            for pretty in [true, false] {
+                let expected = match pretty {
+                    true => serde_json::to_vec_pretty(&separate).unwrap(),
+                    false => serde_json::to_vec(&separate).unwrap(),
+                };
+                let mut written = Vec::new();
+                let hash = write_json(&mut written, &deferred, pretty).unwrap();
+                assert_eq!(hash, separate.result_hash, "{}", precision);
+                assert!(written == expected, "{} pretty={}: fused bytes differ", precision, pretty);
+            }
+
+            // msgpack through serialize_hashed
+            #[cfg(feature = "api")]
+            {
+                let (bytes, hash) = serialize_hashed(&deferred, || rmp_serde::to_vec_named(&deferred)).unwrap();
+                assert_eq!((hash, bytes), (separate.result_hash.clone(), rmp_serde::to_vec_named(&separate).unwrap()));
+            }
+
+            // An output that has its hash already is written as is
+            let mut written = Vec::new();
+            assert_eq!(write_json(&mut written, &separate, false).unwrap(), separate.result_hash);
+        }
+    }
+
+    #[test]
+    fn test_fused_hash_rounding_and_scope() {
+        let rounding = Some(HashRounding::MantissaBits { bits: 10 });
+        let (separate, deferred) = outputs("fp32", (16, 64, 48), rounding);
+        let mut written = Vec::new();
+        assert_eq!(write_json(&mut written, &deferred, true).unwrap(), separate.result_hash);
+
+        // Serializing the same output outside write_json writes the empty hash, and other
+        // matrices serialized meanwhile are not fed
+        assert!(serde_json::to_string(&deferred).unwrap().contains("\"result_hash\":\"\""));
+        let (_, hash) = serialize_hashed(&deferred, || {
+            serde_json::to_string(&*separate.result_matrix)?;
+            serde_json::to_string(&deferred)
+        })
+        .unwrap();
+        assert_eq!(hash, separate.result_hash);
+
+        // i32le hashes are never deferred, so an empty one is an error
+        let mut i32le = deferred.clone();
+        i32le.metadata.result_dtype = ResultDtype::I32.as_str().to_string();
+        assert!(write_json(Vec::new(), &i32le, false).is_err());
+    }
+}
@@ -0,0 +1,319 @@
+//! Invariant checks on the numerical core, the bodies of the `kernels`, `hash` and
+//! `quantize` fuzz targets (`fuzz/fuzz_targets`, feature `fuzzing`).
+//!
+//! Each check reads shapes and matrix contents from arbitrary bytes (zeros once the bytes
+//! run out) and panics on a violation, so the fuzzer minimizes a reproducer:
+//!
+//! - `check_kernels`: every registered kernel for int8 and u8i8 equals a scalar i64
+//!   reference bit for bit, and every fp32 and fp16 kernel stays within the forward error
+//!   bound `γ(k+1)·Σ|a||b|` of an f64 reference
+//! - `check_hash`: `compute_hash_as` over arbitrary f32 bit patterns, in every result
+//!   dtype, `compute_hash_i32` and `compute_hash_f64` equal SHA-256 over preimages built
+//!   here byte by byte
+//! - `check_quantize`: quantized values are integral and within their type's bounds,
+//!   int8 values dequantize to within one step of the input unless clamped, and fp16
+//!   rounding is idempotent and within half an ulp
+//!
+//! Shapes stay small (k ≤ `MAX_K`), so integer sums are exact in f32 and fp16 sums cannot
+//! overflow. `fuzz/seeds/<target>` holds each target's seed inputs; the tests below run
+//! them and a few fixed inputs through the same checks.
+
+use crate::quantize::{quantize_matrix, QuantizeTarget};
+use crate::{
+    compute_hash, compute_hash_as, compute_hash_f64, compute_hash_i32, compute_workload_with_options, kernels, types,
+    ComputeOptions, FlatMatrix, ResultDtype, SolverError,
+};
+use sha2::{Digest, Sha256};
+
+/// Largest row count of A and column count of B
+pub const MAX_DIM: usize = 20;
+/// Largest reduction length
+pub const MAX_K: usize = 64;
+
+/// Fuzzer bytes, read front to back; zeros once they run out
+struct Bytes<'a>(&'a [u8]);
+
+impl Bytes<'_> {
+    fn byte(&mut self) -> u8 {
+        match self.0.split_first() {
+            Some((&byte, rest)) => {
+                self.0 = rest;
+                byte
+            }
+            None => 0,
+        }
+    }
+
+    /// 1..=max
+    fn dim(&mut self, max: usize) -> usize {
+        1 + self.byte() as usize % max
+    }
+
+    fn f32(&mut self) -> f32 {
+        f32::from_le_bytes([self.byte(), self.byte(), self.byte(), self.byte()])
+    }
+
+    fn matrix(&mut self, rows: usize, cols: usize, element: impl Fn(u8) -> f32) -> FlatMatrix {
+        FlatMatrix { data: (0..rows * cols).map(|_| element(self.byte())).collect(), rows, cols }
+    }
+}
+
+/// Every kernel against the references; see the module docs
+pub fn check_kernels(data: &[u8]) {
+    let mut bytes = Bytes(data);
+    // Odd first bytes pick the 16×k × k×16 shape the packed kernels need
+    let wide = bytes.byte() % 2 == 1;
+    let k = bytes.dim(MAX_K);
+    let (m, n) = if wide { (16, 16) } else { (bytes.dim(MAX_DIM), bytes.dim(MAX_DIM)) };
+
+    let a = bytes.matrix(m, k, |byte| byte as f32);
+    let b = bytes.matrix(k, n, |byte| byte as i8 as f32);
+    check_integer("u8i8", &a, &b);
+
+    // A ±127 in each operand makes the int8 scales exactly 1, so quantization is the identity
+    let int8 = |byte: u8| (byte as i8).max(-127) as f32;
+    let mut a = bytes.matrix(m, k, int8);
+    let mut b = bytes.matrix(k, n, int8);
+    a.data[0] = if a.data[0] < 0.0 { -127.0 } else { 127.0 };
+    b.data[0] = if b.data[0] < 0.0 { -127.0 } else { 127.0 };
+    check_integer("int8", &a, &b);
+
+    // Multiples of 1/16 in [-8, 8): exact in f16, so both float precisions see the same values
+    let float = |byte: u8| byte as i8 as f32 / 16.0;
+    let a = bytes.matrix(m, k, float);
+    let b = bytes.matrix(k, n, float);
+    for precision in ["fp32", "fp16"] {
+        check_float(precision, &a, &b);
+    }
+}
+
+/// Run `a × b` on every registered kernel for `precision` that this machine can run
+fn each_kernel(precision: &str, a: &FlatMatrix, b: &FlatMatrix, mut check: impl FnMut(&str, types::Output)) {
+    let shapes = ((a.rows, a.cols), (b.rows, b.cols));
+    for name in kernels::kernel_names() {
+        if !kernels::kernel_by_name(&name).is_some_and(|kernel| kernel.supports(precision, shapes.0, shapes.1)) {
+            continue;
+        }
+        let input = types::Input {
+            matrix_a: a.clone(),
+            matrix_b: b.clone(),
+            precision: precision.to_string(),
+            ..Default::default()
+        };
+        let options = ComputeOptions { kernel: Some(name.clone()), ..Default::default() };
+        match compute_workload_with_options(input, &options) {
+            Ok(output) => check(&name, output),
+            // Needs a CPU or build feature this machine lacks
+            Err(SolverError::KernelUnavailable(_)) => {}
+            Err(e) => panic!("{} failed on {} {}x{}x{}: {}", name, precision, a.rows, a.cols, b.cols, e),
+        }
+    }
+}
+
+/// Integer kernels against `integer_reference`, bit for bit
+fn check_integer(precision: &str, a: &FlatMatrix, b: &FlatMatrix) {
+    let reference = integer_reference(a, b);
+    each_kernel(precision, a, b, |name, output| {
+        for (i, (&got, &want)) in output.result_matrix.data.iter().zip(&reference).enumerate() {
+            assert_eq!(got.to_bits(), (want as f32).to_bits(), "{} element {}: {} vs reference {}", name, i, got, want);
+        }
+    });
+}
+
+/// A × B over integer-valued matrices, accumulated in i64
+fn integer_reference(a: &FlatMatrix, b: &FlatMatrix) -> Vec<i64> {
+    let mut c = vec![0i64; a.rows * b.cols];
+    for i in 0..a.rows {
+        for j in 0..b.cols {
+            c[i * b.cols + j] = (0..a.cols).map(|p| a.data[i * a.cols + p] as i64 * b.data[p * b.cols + j] as i64).sum();
+        }
+    }
+    c
+}
+
+/// Float kernels against an f64 reference, within the forward error bound of k
+/// multiply-adds at f32's unit roundoff (the fp16 kernels accumulate in f32 too)
+fn check_float(precision: &str, a: &FlatMatrix, b: &FlatMatrix) {
+    let (k, n) = (a.cols, b.cols);
+    // The operands are multiples of 1/16, so no product or partial sum is subnormal, and
+    // they are exact in f16
+    let unit = 2f64.powi(-24);
+    let steps = (k + 1) as f64 * unit;
+    let gamma = steps / (1.0 - steps);
+    each_kernel(precision, a, b, |name, output| {
+        for i in 0..a.rows {
+            for j in 0..n {
+                let products = (0..k).map(|p| a.data[i * k + p] as f64 * b.data[p * n + j] as f64);
+                let (exact, magnitude) = products.fold((0.0, 0.0), |(sum, abs), x| (sum + x, abs + x.abs()));
+                let got = output.result_matrix.data[i * n + j] as f64;
+                let bound = gamma * magnitude;
+                assert!(
+                    (got - exact).abs() <= bound,
+                    "{} element ({}, {}): {} vs reference {}, bound {:e}",
+                    name, i, j, got, exact, bound
+                );
+            }
+        }
+    });
+}
+
+/// Result hashes against preimages built here; see the module docs
+pub fn check_hash(data: &[u8]) {
+    let mut bytes = Bytes(data);
+    let (rows, cols) = (bytes.dim(MAX_DIM), bytes.dim(MAX_DIM));
+    // Any bit pattern: NaNs, infinities and subnormals included
+    let matrix = FlatMatrix { data: (0..rows * cols).map(|_| bytes.f32()).collect(), rows, cols };
+
+    assert_eq!(compute_hash(&matrix), reference_hash(&matrix, ResultDtype::F32));
+    for dtype in ["f32", "f64", "i32", "i8", "u8", "f16"].into_iter().filter_map(ResultDtype::parse) {
+        assert_eq!(compute_hash_as(&matrix, dtype), reference_hash(&matrix, dtype), "{}", dtype.as_str());
+    }
+
+    let values: Vec<i32> = matrix.data.iter().map(|x| x.to_bits() as i32).collect();
+    let preimage: Vec<u8> = values.iter().flat_map(|v| v.to_le_bytes()).collect();
+    assert_eq!(compute_hash_i32(&values), hex_digest(&preimage));
+
+    let values: Vec<f64> = matrix.data.iter().map(|&x| x as f64 * 3.0).collect();
+    let preimage: Vec<u8> = values.iter().flat_map(|v| v.to_bits().to_le_bytes()).collect();
+    assert_eq!(compute_hash_f64(&values), hex_digest(&preimage));
+}
+
+/// SHA-256 of `matrix` encoded as `dtype`, per the `OutputMetadata::hash_input_description`
+/// contract
+fn reference_hash(matrix: &FlatMatrix, dtype: ResultDtype) -> String {
+    let mut preimage = Vec::new();
+    for &x in &matrix.data {
+        match dtype {
+            ResultDtype::F32 => preimage.extend(x.to_bits().to_le_bytes()),
+            ResultDtype::F64 => preimage.extend((x as f64).to_bits().to_le_bytes()),
+            // Rust's float-to-int casts saturate, and NaN becomes 0
+            ResultDtype::I32 => preimage.extend((x as i32).to_le_bytes()),
+            ResultDtype::I8 => preimage.push((x as i8) as u8),
+            ResultDtype::U8 => preimage.push(x as u8),
+            #[cfg(feature = "fp16")]
+            ResultDtype::F16 => preimage.extend(half::f16::from_f32(x).to_bits().to_le_bytes()),
+        }
+    }
+    hex_digest(&preimage)
+}
+
+fn hex_digest(preimage: &[u8]) -> String {
+    Sha256::digest(preimage).iter().map(|byte| format!("{:02x}", byte)).collect()
+}
+
+/// Quantization bounds for every target; see the module docs
+pub fn check_quantize(data: &[u8]) {
+    let mut bytes = Bytes(data);
+    let (rows, cols) = (bytes.dim(MAX_DIM), bytes.dim(MAX_DIM));
+    let matrix = FlatMatrix { data: (0..rows * cols).map(|_| bytes.f32()).collect(), rows, cols };
+
+    for target in QuantizeTarget::ALL {
+        let quantized = quantize_matrix(&matrix, target);
+        let values = &quantized.values;
+        assert_eq!((values.rows, values.cols, values.data.len()), (rows, cols, matrix.data.len()), "{}", target.as_str());
+        let scale_count = match target {
+            QuantizeTarget::Int8 => 1,
+            QuantizeTarget::Int8PerChannel => cols,
+            QuantizeTarget::Int8Rowwise => rows,
+            _ => 0,
+        };
+        assert_eq!(quantized.scales.len(), scale_count, "{}", target.as_str());
+        assert!(quantized.scales.iter().all(|&scale| scale >= 0.0), "{} scales {:?}", target.as_str(), quantized.scales);
+        // The scale each element was quantized with
+        let scale_of = |idx: usize| match target {
+            QuantizeTarget::Int8 => quantized.scales[0],
+            QuantizeTarget::Int8PerChannel => quantized.scales[idx % cols],
+            QuantizeTarget::Int8Rowwise => quantized.scales[idx / cols],
+            _ => 1.0,
+        };
+
+        for (idx, (&x, &q)) in matrix.data.iter().zip(&values.data).enumerate() {
+            let context = || format!("{} element {}: {} -> {}", target.as_str(), idx, x, q);
+            match target {
+                QuantizeTarget::Int8 | QuantizeTarget::Int8PerChannel | QuantizeTarget::Int8Rowwise => {
+                    assert!(q.fract() == 0.0 && (-128.0..=127.0).contains(&q), "{}", context());
+                    let scaled = x * scale_of(idx);
+                    if scaled.is_finite() {
+                        // Truncation toward zero: never larger, and less than one step off once clamped
+                        assert!(q.abs() <= scaled.abs(), "{}", context());
+                        assert!((scaled.clamp(-128.0, 127.0) - q).abs() < 1.0, "{}", context());
+                    }
+                }
+                QuantizeTarget::U8 => {
+                    assert!(q.fract() == 0.0 && (0.0..=255.0).contains(&q), "{}", context());
+                    if x.is_finite() {
+                        assert_eq!(q, x.clamp(0.0, 255.0).trunc(), "{}", context());
+                    } else if x.is_nan() {
+                        assert_eq!(q, 0.0, "{}", context());
+                    }
+                }
+                #[cfg(feature = "fp16")]
+                QuantizeTarget::Fp16 => {
+                    assert_eq!(half::f16::from_f32(q).to_f32().to_bits(), q.to_bits(), "{}", context());
+                    if x.abs() <= 65504.0 {
+                        // Half an ulp (2^-11 relative), or half the subnormal spacing 2^-24
+                        let bound = (x.abs() as f64 * 2f64.powi(-11)).max(2f64.powi(-25));
+                        assert!((q as f64 - x as f64).abs() <= bound, "{}", context());
+                    }
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::path::Path;
+
+    /// The target's seed corpus plus a few fixed inputs. None of them may land on the
+    /// shapes the kernels tests register mock kernels for (3×7 × 7×5, 5×11 × 11×3).
+    fn inputs(target: &str) -> Vec<Vec<u8>> {
+        let dir = Path::new(env!("CARGO_MANIFEST_DIR")).join("fuzz/seeds").join(target);
+        let mut inputs: Vec<Vec<u8>> = std::fs::read_dir(&dir)
+            .unwrap_or_else(|e| panic!("reading {}: {}", dir.display(), e))
+            .map(|entry| std::fs::read(entry.unwrap().path()).unwrap())
+            .collect();
+        assert!(inputs.len() >= 3, "{} has {} seeds", dir.display(), inputs.len());
+        inputs.extend([Vec::new(), vec![0xff; 64], (0..=255).collect()]);
+        inputs
+    }
+
+    /// Whether `check_kernels` runs `input` on the 16×k × k×16 shape
+    fn is_wide(input: &[u8]) -> bool {
+        input.first().is_some_and(|byte| byte % 2 == 1)
+    }
+
+    #[test]
+    fn test_kernel_invariants_on_fixed_inputs() {
+        for input in inputs("kernels").into_iter().filter(|input| !is_wide(input)) {
+            check_kernels(&input);
+        }
+    }
+
+    #[test]
+    fn test_packed_kernel_invariants_on_fixed_inputs() {
+        for input in inputs("kernels").into_iter().filter(|input| is_wide(input)) {
+            check_kernels(&input);
+        }
+    }
+
+    #[test]
+    fn test_hash_invariants_on_fixed_inputs() {
+        for input in inputs("hash") {
+            check_hash(&input);
+        }
+    }
+
+    #[test]
+    fn test_quantize_invariants_on_fixed_inputs() {
+        for input in inputs("quantize") {
+            check_quantize(&input);
+        }
+        // NaN, infinities, f32::MAX and a subnormal in one 1×5 matrix
+        let specials = [f32::NAN, f32::INFINITY, f32::NEG_INFINITY, f32::MAX, 1e-40];
+        let mut data = vec![0, 4];
+        data.extend(specials.iter().flat_map(|x| x.to_le_bytes()));
+        check_quantize(&data);
+    }
+}
@@ -0,0 +1,96 @@
+//! Distributions of latencies and sizes: nearest-rank percentiles and power-of-two buckets.
+//!
+//! /stats (per-request memory peaks) and batch summaries (kernel and end-to-end time) both
+//! report through this module, so a p99 means the same thing wherever it appears: the
+//! smallest sample that at least 99% of samples do not exceed.
+
+use serde::{Deserialize, Serialize};
+
+/// Nearest-rank percentile (`p` in 0..=1) of samples sorted ascending; None when empty
+pub fn percentile(sorted: &[f64], p: f64) -> Option<f64> {
+    let rank = ((p * sorted.len() as f64).ceil() as usize).max(1);
+    sorted.get(rank - 1).copied()
+}
+
+/// Percentiles of one set of samples
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct Percentiles {
+    pub count: usize,
+    pub min: f64,
+    pub p50: f64,
+    pub p90: f64,
+    pub p99: f64,
+    pub max: f64,
+}
+
+impl Percentiles {
+    /// None when there are no samples; NaN samples are ignored
+    pub fn of(samples: &[f64]) -> Option<Self> {
+        let mut sorted: Vec<f64> = samples.iter().copied().filter(|s| !s.is_nan()).collect();
+        sorted.sort_by(f64::total_cmp);
+        Some(Self {
+            count: sorted.len(),
+            min: *sorted.first()?,
+            p50: percentile(&sorted, 0.5)?,
+            p90: percentile(&sorted, 0.9)?,
+            p99: percentile(&sorted, 0.99)?,
+            max: *sorted.last()?,
+        })
+    }
+}
+
+/// Samples in `(le / 2, le]`; the first bucket of a histogram also holds everything below
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct Bucket {
+    pub le: f64,
+    pub count: usize,
+}
+
+/// Smallest bucket bound, 2^-10 (about a microsecond when samples are milliseconds)
+const MIN_EXPONENT: i32 = -10;
+
+/// Power-of-two buckets from the one holding the smallest sample to the one holding the
+/// largest, empty buckets in between included; empty when there are no finite samples
+pub fn log2_histogram(samples: &[f64]) -> Vec<Bucket> {
+    let exponent = |s: f64| (s.log2().ceil() as i32).max(MIN_EXPONENT);
+    let exponents: Vec<i32> = samples.iter().filter(|s| s.is_finite()).map(|&s| exponent(s)).collect();
+    let (Some(&lo), Some(&hi)) = (exponents.iter().min(), exponents.iter().max()) else {
+        return Vec::new();
+    };
+    let mut buckets: Vec<Bucket> = (lo..=hi).map(|e| Bucket { le: 2f64.powi(e), count: 0 }).collect();
+    for e in exponents {
+        buckets[(e - lo) as usize].count += 1;
+    }
+    buckets
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_nearest_rank_percentiles() {
+        let samples: Vec<f64> = (1..=100).rev().map(f64::from).collect();
+        let p = Percentiles::of(&samples).unwrap();
+        assert_eq!((p.count, p.min, p.p50, p.p90, p.p99, p.max), (100, 1.0, 50.0, 90.0, 99.0, 100.0));
+
+        let one = Percentiles::of(&[3.5]).unwrap();
+        assert_eq!((one.p50, one.p99), (3.5, 3.5));
+        assert_eq!(percentile(&[1.0, 2.0], 0.0), Some(1.0));
+        assert!(Percentiles::of(&[]).is_none());
+        assert!(Percentiles::of(&[f64::NAN]).is_none());
+    }
+
+    #[test]
+    fn test_log2_histogram() {
+        let buckets = log2_histogram(&[0.0, 0.75, 1.0, 3.0, 3.5, 9.0]);
+        let bounds: Vec<f64> = buckets.iter().map(|b| b.le).collect();
+        let mut expected = vec![2f64.powi(MIN_EXPONENT)];
+        expected.extend((MIN_EXPONENT + 1..=4).map(|e| 2f64.powi(e)));
+        assert_eq!(bounds, expected);
+        let count = |le: f64| buckets.iter().find(|b| b.le == le).unwrap().count;
+        assert_eq!((count(2f64.powi(MIN_EXPONENT)), count(1.0), count(2.0), count(4.0), count(16.0)), (1, 2, 0, 2, 1));
+        assert_eq!(buckets.iter().map(|b| b.count).sum::<usize>(), 6);
+        assert!(log2_histogram(&[]).is_empty());
+    }
+}
@@ -0,0 +1,236 @@
+//! The library's only access to the host filesystem and environment.
+//!
+//! The core pipeline (compute_workload*, verification, seed generation, hashing) never
+//! touches either, so it runs unchanged inside seccomp or wasm sandboxes. The opt-in
+//! pieces that do are: the disk pack cache (only with `ComputeOptions::pack_cache_dir`),
+//! `audit::write_output_audited`, `bench::MachineFingerprint::current`,
+//! `api::ApiConfig::from_env`, the soak run's RSS samples, `variance`'s core pinning,
+//! `bundle`, `traffic`'s capture files, `batch::run_directory`'s inputs, outputs and
+//! summaries, the CLI's (`cli::run`, `cli::run_command`) inputs and reports, and
+//! `cross_check`'s reference command, the only process the library starts (`cross-check`
+//! feature). All of them go through the helpers below, which tests can shut off per thread
+//! with `forbid_io` to prove a code path is pure compute.
+
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+#[cfg(test)]
+thread_local! {
+    static FORBIDDEN: std::cell::Cell<bool> = const { std::cell::Cell::new(false) };
+}
+
+#[inline]
+fn guard(op: &str, target: &dyn std::fmt::Debug) {
+    #[cfg(test)]
+    if FORBIDDEN.with(std::cell::Cell::get) {
+        panic!("{} {:?} while host IO is forbidden", op, target);
+    }
+    #[cfg(not(test))]
+    let _ = (op, target);
+}
+
+#[cfg(any(feature = "blake3-hash", feature = "seed-gen", test))]
+pub(crate) fn read(path: impl AsRef<Path>) -> io::Result<Vec<u8>> {
+    guard("read", &path.as_ref());
+    fs::read(path)
+}
+
+pub(crate) fn read_to_string(path: impl AsRef<Path>) -> io::Result<String> {
+    guard("read", &path.as_ref());
+    fs::read_to_string(path)
+}
+
+#[cfg(feature = "bundle")]
+pub(crate) fn open(path: &Path) -> io::Result<fs::File> {
+    guard("open", &path);
+    fs::File::open(path)
+}
+
+/// Write `contents` to `path`, creating or truncating it
+pub(crate) fn write(path: impl AsRef<Path>, contents: impl AsRef<[u8]>) -> io::Result<()> {
+    guard("write", &path.as_ref());
+    fs::write(path, contents)
+}
+
+/// The regular files directly in `dir`, in no particular order
+pub(crate) fn files_in(dir: &Path) -> io::Result<Vec<PathBuf>> {
+    guard("read_dir", &dir);
+    let mut files = Vec::new();
+    for entry in fs::read_dir(dir)? {
+        let path = entry?.path();
+        if path.is_file() {
+            files.push(path);
+        }
+    }
+    Ok(files)
+}
+
+/// Create (or truncate) `path` for writing
+pub(crate) fn create(path: impl AsRef<Path>) -> io::Result<fs::File> {
+    guard("create", &path.as_ref());
+    fs::File::create(path)
+}
+
+pub(crate) fn rename(from: &Path, to: &Path) -> io::Result<()> {
+    guard("rename", &from);
+    fs::rename(from, to)
+}
+
+pub(crate) fn remove_file(path: &Path) -> io::Result<()> {
+    guard("remove", &path);
+    fs::remove_file(path)
+}
+
+pub(crate) fn create_dir_all(path: impl AsRef<Path>) -> io::Result<()> {
+    guard("create_dir_all", &path.as_ref());
+    fs::create_dir_all(path)
+}
+
+/// Resident set size of this process (VmRSS); None where /proc is unavailable
+#[cfg(any(feature = "seed-gen", test))]
+pub(crate) fn resident_set_bytes() -> Option<u64> {
+    guard("read", &"/proc/self/status");
+    let status = fs::read_to_string("/proc/self/status").ok()?;
+    let line = status.lines().find_map(|line| line.strip_prefix("VmRSS:"))?;
+    let kb: u64 = line.trim().strip_suffix("kB")?.trim().parse().ok()?;
+    Some(kb * 1024)
+}
+
+/// The calling thread pinned to one CPU (`variance --isolate`); dropping it restores the
+/// affinity the thread had before
+#[cfg(any(feature = "seed-gen", test))]
+pub(crate) struct PinnedThread {
+    pub cpu: usize,
+    #[cfg(target_os = "linux")]
+    previous: affinity::CpuSet,
+}
+
+#[cfg(all(any(feature = "seed-gen", test), target_os = "linux"))]
+mod affinity {
+    /// glibc's and musl's cpu_set_t: 1024 bits
+    pub type CpuSet = [u64; 16];
+
+    extern "C" {
+        pub fn sched_getcpu() -> i32;
+        pub fn sched_getaffinity(pid: i32, size: usize, mask: *mut CpuSet) -> i32;
+        pub fn sched_setaffinity(pid: i32, size: usize, mask: *const CpuSet) -> i32;
+    }
+}
+
+/// Pin the calling thread to the CPU it is running on; None where the platform has no
+/// affinity call (anything but Linux) or the kernel refuses it
+#[cfg(all(any(feature = "seed-gen", test), target_os = "linux"))]
+pub(crate) fn pin_current_thread() -> Option<PinnedThread> {
+    guard("pin", &"current thread");
+    let size = std::mem::size_of::<affinity::CpuSet>();
+    let mut previous = [0u64; 16];
+    // SAFETY: pid 0 is the calling thread and every mask is a whole cpu_set_t
+    unsafe {
+        let cpu = usize::try_from(affinity::sched_getcpu()).ok().filter(|&cpu| cpu < 1024)?;
+        if affinity::sched_getaffinity(0, size, &mut previous) != 0 {
+            return None;
+        }
+        let mut pinned = [0u64; 16];
+        pinned[cpu / 64] = 1 << (cpu % 64);
+        (affinity::sched_setaffinity(0, size, &pinned) == 0).then_some(PinnedThread { cpu, previous })
+    }
+}
+
+#[cfg(all(any(feature = "seed-gen", test), not(target_os = "linux")))]
+pub(crate) fn pin_current_thread() -> Option<PinnedThread> {
+    guard("pin", &"current thread");
+    None
+}
+
+#[cfg(all(any(feature = "seed-gen", test), target_os = "linux"))]
+impl Drop for PinnedThread {
+    fn drop(&mut self) {
+        // SAFETY: as in pin_current_thread
+        unsafe {
+            affinity::sched_setaffinity(0, std::mem::size_of::<affinity::CpuSet>(), &self.previous);
+        }
+    }
+}
+
+/// `sh -c cmd` with piped stdin, stdout and stderr
+#[cfg(feature = "cross-check")]
+pub(crate) fn spawn_shell(cmd: &str) -> io::Result<std::process::Child> {
+    use std::process::{Command, Stdio};
+    guard("spawn", &cmd);
+    Command::new("sh").arg("-c").arg(cmd).stdin(Stdio::piped()).stdout(Stdio::piped()).stderr(Stdio::piped()).spawn()
+}
+
+#[cfg(feature = "api")]
+pub(crate) fn env_var(name: &str) -> Result<String, std::env::VarError> {
+    guard("env", &name);
+    std::env::var(name)
+}
+
+/// Run `f` with every helper in this module panicking on the current thread
+#[cfg(test)]
+pub(crate) fn forbid_io<T>(f: impl FnOnce() -> T) -> T {
+    struct Reset(bool);
+    impl Drop for Reset {
+        fn drop(&mut self) {
+            FORBIDDEN.with(|flag| flag.set(self.0));
+        }
+    }
+    let _reset = Reset(FORBIDDEN.with(|flag| flag.replace(true)));
+    f()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::types::Input;
+    use crate::{
+        compute_hash, compute_workload_with_options, generate_matrices_from_seed, generate_matrices_from_seed_hex,
+        verify_correctness, verify_u8i8_integer, ComputeOptions,
+    };
+
+    fn input(precision: &str, workload_type: Option<&str>) -> Input {
+        let (matrix_a, matrix_b) = generate_matrices_from_seed(b"no-fs", 16, 256, 256, 16);
+        Input {
+            matrix_a,
+            matrix_b,
+            precision: precision.to_string(),
+            workload_type: workload_type.map(str::to_string),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_core_pipeline_touches_no_host_io() {
+        forbid_io(|| {
+            let (a, b) = generate_matrices_from_seed_hex("00ff", 16, 256, 256, 16).unwrap();
+            compute_hash(&a);
+            for precision in crate::tests::precisions() {
+                let output = compute_workload_with_options(input(precision, None), &ComputeOptions::default()).unwrap();
+                assert!(verify_correctness(&a, &b, precision, &compute_hash(&output.result_matrix)).is_ok());
+                // Traced, chunked and column-subset runs too (fp64 hashes f64 values, which are not traced)
+                let tile_trace = cfg!(feature = "blake3-hash") && precision != "fp64";
+                let options = ComputeOptions { tile_trace, k_chunk: Some(64), ..Default::default() };
+                compute_workload_with_options(input(precision, None), &options).unwrap();
+            }
+            let options = ComputeOptions { result_columns: Some(vec![3, 1]), ..Default::default() };
+            compute_workload_with_options(input("int8", None), &options).unwrap();
+            compute_workload_with_options(input("int8_per_channel", Some("quantize")), &ComputeOptions::default()).unwrap();
+            verify_u8i8_integer(b"no-fs", (16, 64, 16), "00").unwrap();
+        });
+    }
+
+    #[cfg(feature = "blake3-hash")]
+    #[test]
+    fn test_forbid_io_catches_the_pack_cache() {
+        let dir = tempfile::tempdir().unwrap();
+        let options = ComputeOptions { pack_cache_dir: Some(dir.path().to_path_buf()), ..Default::default() };
+        let run = || forbid_io(|| compute_workload_with_options(input("u8i8", None), &options));
+        let caught = std::panic::catch_unwind(std::panic::AssertUnwindSafe(run));
+        let message = caught.unwrap_err().downcast::<String>().unwrap();
+        assert!(message.contains("while host IO is forbidden"), "{}", message);
+
+        // The guard is lifted again afterwards, even after a panic
+        assert!(compute_workload_with_options(input("u8i8", None), &options).unwrap().metrics.pack_cache_hit == Some(false));
+    }
+}
@@ -0,0 +1,33 @@
+//! Kernel entry points and packing helpers with no stability guarantee.
+//!
+//! Only compiled with the `unstable-internals` feature. These track the implementation
+//! and may change in any release; prefer `compute_workload_with_options` with
+//! `ComputeOptions::kernel` to pin a kernel.
+
+use crate::{FlatMatrix, SolverError, SystemClock};
+use std::time::Duration;
+
+pub use crate::PreparedOperands;
+
+/// Convert and pack A (16×k) and B (k×16) for "u8i8" or "int8"
+pub fn prepare_operands(a: &FlatMatrix, b: &FlatMatrix, precision: &str) -> Result<PreparedOperands, SolverError> {
+    crate::pack_operands(a, b, precision)
+}
+
+/// Cache-blocked fp32 kernel; returns (result, kernel_time)
+pub fn matmul_fp32_blocked(a: &FlatMatrix, b: &FlatMatrix) -> (FlatMatrix, Duration) {
+    crate::matmul_fp32_blocked(a, b, &SystemClock)
+}
+
+/// Reference u8×i8 kernel for any shape
+pub fn matmul_u8i8_generic(a: &FlatMatrix, b: &FlatMatrix) -> FlatMatrix {
+    crate::matmul_u8i8_generic(a, b)
+}
+
+/// u8×i8 kernel for 16×k × k×16; returns (result, kernel_time). Panics on any other
+/// shape, or when a matrix's `data` is not `rows * cols` long.
+pub fn matmul_u8i8_16x16(a: &FlatMatrix, b: &FlatMatrix) -> (FlatMatrix, Duration) {
+    crate::check_16x16_operands(a, b).unwrap_or_else(|err| panic!("{}", err));
+    let b_i8 = crate::pack_b_u8i8(b);
+    crate::matmul_u8i8_16x16_packed(a, &b_i8, &SystemClock)
+}
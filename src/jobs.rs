@@ -0,0 +1,378 @@
+//! In-memory store of asynchronous compute jobs (`POST /jobs`).
+//!
+//! A submitted job runs in the background and its Output is kept here with the result
+//! matrix in its flat row-major form, so `GET /jobs/{id}` can answer with the hash,
+//! metrics and metadata alone and `GET /jobs/{id}/result` can hand out row ranges by
+//! slicing, without serializing the rest. The store is bounded: once it holds `capacity`
+//! jobs, the oldest finished job is dropped for each new one.
+//!
+//! Outputs hold their result behind an `Arc`, so a stored Output, an inline summary of it
+//! and any other clone share one buffer; `result_memory` counts each buffer once.
+
+use crate::types::{Metrics, Output, OutputMetadata};
+use crate::warnings::SolverWarning;
+use crate::webhook::CallbackDelivery;
+use crate::FlatMatrix;
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet, VecDeque};
+use std::sync::{Arc, Mutex};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// Rows returned by a result page when `limit_rows` is not given
+pub const DEFAULT_PAGE_ROWS: usize = 256;
+/// Largest `limit_rows` a result page accepts
+pub const MAX_PAGE_ROWS: usize = 4096;
+/// Largest result (in elements) `GET /jobs/{id}?inline=true` returns in full
+pub const MAX_INLINE_ELEMENTS: usize = 1 << 16;
+
+#[derive(Debug, Clone)]
+pub struct JobStoreConfig {
+    /// Maximum number of jobs kept
+    pub capacity: usize,
+}
+
+impl Default for JobStoreConfig {
+    fn default() -> Self {
+        Self { capacity: 1000 }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum JobStatus {
+    Running,
+    Ok,
+    Error,
+}
+
+/// Why a job lookup failed
+#[derive(Debug, Clone, PartialEq)]
+pub enum JobError {
+    /// No job with this id (or it belongs to another tenant, or was evicted)
+    NotFound(String),
+    /// The job has no result yet, or failed
+    NoResult(String),
+    /// The requested rows or inline size are out of range
+    InvalidRange(String),
+}
+
+impl std::fmt::Display for JobError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            JobError::NotFound(msg) | JobError::NoResult(msg) | JobError::InvalidRange(msg) => write!(f, "{}", msg),
+        }
+    }
+}
+
+#[derive(Debug)]
+struct Job {
+    status: JobStatus,
+    tenant: Option<String>,
+    output: Option<Output>,
+    error: Option<String>,
+    callback: Option<CallbackDelivery>,
+}
+
+/// `GET /jobs/{id}`: everything but the result matrix, unless it was asked for inline
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct JobSummary {
+    pub id: String,
+    pub status: JobStatus,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub result_hash: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub result_shape: Option<(usize, usize)>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub metrics: Option<Metrics>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub metadata: Option<OutputMetadata>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub warnings: Vec<SolverWarning>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+    /// Completion callback delivery, for jobs submitted with `callback_url`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub callback: Option<CallbackDelivery>,
+    /// Full result, only with `inline=true`; shares the stored buffer
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub result_matrix: Option<Arc<FlatMatrix>>,
+}
+
+/// `GET /jobs/{id}/result`: rows `offset_rows..offset_rows + result_matrix.rows` of the result
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ResultPage {
+    pub id: String,
+    /// Shape and hash of the whole result
+    pub result_shape: (usize, usize),
+    pub result_hash: String,
+    pub result_dtype: String,
+    pub offset_rows: usize,
+    pub result_matrix: FlatMatrix,
+    /// Offset of the next page; None after the last row
+    pub next_offset_rows: Option<usize>,
+}
+
+/// Result buffers held by finished jobs, reported by `GET /stats`
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ResultMemory {
+    /// Jobs holding a result
+    pub results: usize,
+    /// Distinct result buffers among them
+    pub buffers: usize,
+    /// Bytes of those buffers, each counted once however many jobs share it
+    pub bytes: u64,
+}
+
+pub struct JobStore {
+    config: JobStoreConfig,
+    inner: Mutex<Inner>,
+}
+
+#[derive(Default)]
+struct Inner {
+    jobs: HashMap<String, Job>,
+    /// Submission order, oldest first
+    order: VecDeque<String>,
+    submitted: u64,
+}
+
+impl JobStore {
+    pub fn new(config: JobStoreConfig) -> Self {
+        Self { config, inner: Mutex::new(Inner::default()) }
+    }
+
+    /// Register a running job and return its id
+    pub fn submit(&self, tenant: Option<String>) -> String {
+        let mut inner = self.inner.lock().unwrap();
+        inner.submitted += 1;
+        // Ids are not sequential so one client cannot enumerate another's jobs
+        let nanos = SystemTime::now().duration_since(UNIX_EPOCH).map(|d| d.as_nanos()).unwrap_or(0);
+        let mut hasher = blake3::Hasher::new();
+        hasher.update(&inner.submitted.to_le_bytes());
+        hasher.update(&nanos.to_le_bytes());
+        hasher.update(&(self as *const Self as usize).to_le_bytes());
+        let id = hasher.finalize().to_hex()[..24].to_string();
+
+        while inner.jobs.len() >= self.config.capacity.max(1) {
+            let Some(index) = inner.order.iter().position(|id| inner.jobs[id].status != JobStatus::Running) else {
+                break;
+            };
+            let evicted = inner.order.remove(index).expect("index is in range");
+            inner.jobs.remove(&evicted);
+        }
+        inner.jobs.insert(id.clone(), Job { status: JobStatus::Running, tenant, output: None, error: None, callback: None });
+        inner.order.push_back(id.clone());
+        id
+    }
+
+    /// Record the outcome of job `id`
+    pub fn complete(&self, id: &str, outcome: Result<Output, String>) {
+        let mut inner = self.inner.lock().unwrap();
+        if let Some(job) = inner.jobs.get_mut(id) {
+            match outcome {
+                Ok(output) => {
+                    job.status = JobStatus::Ok;
+                    job.output = Some(output);
+                }
+                Err(message) => {
+                    job.status = JobStatus::Error;
+                    job.error = Some(message);
+                }
+            }
+        }
+    }
+
+    /// Record the progress of job `id`'s completion callback
+    pub fn set_callback(&self, id: &str, delivery: CallbackDelivery) {
+        if let Some(job) = self.inner.lock().unwrap().jobs.get_mut(id) {
+            job.callback = Some(delivery);
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.inner.lock().unwrap().jobs.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Memory held by stored results, shared buffers counted once
+    pub fn result_memory(&self) -> ResultMemory {
+        let inner = self.inner.lock().unwrap();
+        let mut seen = HashSet::new();
+        let mut memory = ResultMemory::default();
+        for matrix in inner.jobs.values().filter_map(|job| job.output.as_ref()).map(|o| &o.result_matrix) {
+            memory.results += 1;
+            if seen.insert(Arc::as_ptr(matrix)) {
+                memory.buffers += 1;
+                memory.bytes += std::mem::size_of_val(matrix.data.as_slice()) as u64;
+            }
+        }
+        memory
+    }
+
+    /// Status, hash, metrics and metadata of job `id`; the whole result too with `inline`
+    pub fn summary(&self, id: &str, tenant: Option<&str>, inline: bool) -> Result<JobSummary, JobError> {
+        let inner = self.inner.lock().unwrap();
+        let job = find(&inner, id, tenant)?;
+        let output = job.output.as_ref();
+        let result_matrix = match output {
+            Some(output) if inline => {
+                let elements = output.result_matrix.data.len();
+                if elements > MAX_INLINE_ELEMENTS {
+                    return Err(JobError::InvalidRange(format!(
+                        "result has {} elements, more than the {} returned inline; page it with /jobs/{}/result",
+                        elements, MAX_INLINE_ELEMENTS, id
+                    )));
+                }
+                Some(Arc::clone(&output.result_matrix))
+            }
+            _ => None,
+        };
+        Ok(JobSummary {
+            id: id.to_string(),
+            status: job.status,
+            result_hash: output.map(|o| o.result_hash.clone()),
+            result_shape: output.map(|o| (o.result_matrix.rows, o.result_matrix.cols)),
+            metrics: output.map(|o| o.metrics.clone()),
+            metadata: output.map(|o| o.metadata.clone()),
+            warnings: output.map(|o| o.warnings.clone()).unwrap_or_default(),
+            error: job.error.clone(),
+            callback: job.callback.clone(),
+            result_matrix,
+        })
+    }
+
+    /// Up to `limit_rows` result rows of job `id` starting at `offset_rows`
+    pub fn page(&self, id: &str, tenant: Option<&str>, offset_rows: usize, limit_rows: usize) -> Result<ResultPage, JobError> {
+        if limit_rows == 0 || limit_rows > MAX_PAGE_ROWS {
+            return Err(JobError::InvalidRange(format!(
+                "limit_rows (={}) must be between 1 and {}",
+                limit_rows, MAX_PAGE_ROWS
+            )));
+        }
+        let inner = self.inner.lock().unwrap();
+        let job = find(&inner, id, tenant)?;
+        let output = job.output.as_ref().ok_or_else(|| match job.status {
+            JobStatus::Running => JobError::NoResult(format!("job {} is still running", id)),
+            _ => JobError::NoResult(format!("job {} failed: {}", id, job.error.as_deref().unwrap_or("unknown error"))),
+        })?;
+        let result = &output.result_matrix;
+        if offset_rows >= result.rows {
+            return Err(JobError::InvalidRange(format!(
+                "offset_rows (={}) must be less than the result's rows (={})",
+                offset_rows, result.rows
+            )));
+        }
+        let end = (offset_rows + limit_rows).min(result.rows);
+        Ok(ResultPage {
+            id: id.to_string(),
+            result_shape: (result.rows, result.cols),
+            result_hash: output.result_hash.clone(),
+            result_dtype: output.metadata.result_dtype.clone(),
+            offset_rows,
+            result_matrix: FlatMatrix {
+                data: result.data[offset_rows * result.cols..end * result.cols].to_vec(),
+                rows: end - offset_rows,
+                cols: result.cols,
+            },
+            next_offset_rows: (end < result.rows).then_some(end),
+        })
+    }
+}
+
+/// Job `id`, if it exists and is visible to `tenant`
+fn find<'a>(inner: &'a Inner, id: &str, tenant: Option<&str>) -> Result<&'a Job, JobError> {
+    inner
+        .jobs
+        .get(id)
+        .filter(|job| job.tenant.is_none() || job.tenant.as_deref() == tenant)
+        .ok_or_else(|| JobError::NotFound(format!("No job {}", id)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::types::Input;
+    use crate::{compute_workload, generate_matrices_from_seed};
+
+    fn output(rows: usize) -> Output {
+        output_with_seed(b"jobs", rows)
+    }
+
+    fn output_with_seed(seed: &[u8], rows: usize) -> Output {
+        let (matrix_a, matrix_b) = generate_matrices_from_seed(seed, rows, 8, 8, 16);
+        let input = Input::matmul(matrix_a, matrix_b, "fp32");
+        compute_workload(input).unwrap()
+    }
+
+    #[test]
+    fn test_pages_and_bounds() {
+        let store = JobStore::new(JobStoreConfig::default());
+        let id = store.submit(None);
+        assert!(matches!(store.page(&id, None, 0, 10), Err(JobError::NoResult(_))));
+        assert_eq!(store.summary(&id, None, false).unwrap().status, JobStatus::Running);
+
+        store.complete(&id, Ok(output(40)));
+        let first = store.page(&id, None, 0, 32).unwrap();
+        assert_eq!((first.result_matrix.rows, first.next_offset_rows), (32, Some(32)));
+        let last = store.page(&id, None, 32, 32).unwrap();
+        assert_eq!((last.result_matrix.rows, last.next_offset_rows), (8, None));
+
+        let err = store.page(&id, None, 40, 1).unwrap_err();
+        assert_eq!(err.to_string(), "offset_rows (=40) must be less than the result's rows (=40)");
+        assert!(matches!(store.page(&id, None, 0, 0), Err(JobError::InvalidRange(_))));
+        assert!(matches!(store.page(&id, None, 0, MAX_PAGE_ROWS + 1), Err(JobError::InvalidRange(_))));
+
+        let summary = store.summary(&id, None, false).unwrap();
+        assert_eq!(summary.result_shape, Some((40, 16)));
+        assert!(summary.result_matrix.is_none());
+        assert_eq!(store.summary(&id, None, true).unwrap().result_matrix.unwrap().data.len(), 640);
+    }
+
+    #[test]
+    fn test_shared_results_are_stored_once() {
+        let store = JobStore::new(JobStoreConfig::default());
+        let output = output(64);
+        let (first, second) = (store.submit(None), store.submit(None));
+        store.complete(&first, Ok(output.clone()));
+        store.complete(&second, Ok(output.clone()));
+        // This Output and both stored copies point at one buffer
+        assert_eq!(Arc::strong_count(&output.result_matrix), 3);
+        let inline = store.summary(&second, None, true).unwrap().result_matrix.unwrap();
+        assert!(Arc::ptr_eq(&inline, &output.result_matrix));
+        assert_eq!(Arc::strong_count(&output.result_matrix), 4);
+
+        let bytes = 64 * 16 * 4;
+        assert_eq!(store.result_memory(), ResultMemory { results: 2, buffers: 1, bytes });
+        let third = store.submit(None);
+        store.complete(&third, Ok(output_with_seed(b"other", 64)));
+        assert_eq!(store.result_memory(), ResultMemory { results: 3, buffers: 2, bytes: 2 * bytes });
+
+        // Mutating a copy copies on write and leaves the stored result alone
+        let mut copy = output.clone();
+        Arc::make_mut(&mut copy.result_matrix).data[0] += 1.0;
+        assert!(!Arc::ptr_eq(&copy.result_matrix, &output.result_matrix));
+        assert_eq!(store.summary(&first, None, true).unwrap().result_matrix.unwrap().data, output.result_matrix.data);
+    }
+
+    #[test]
+    fn test_failures_tenants_and_eviction() {
+        let store = JobStore::new(JobStoreConfig { capacity: 2 });
+        let failed = store.submit(Some("team-a".to_string()));
+        store.complete(&failed, Err("boom".to_string()));
+        let err = store.page(&failed, Some("team-a"), 0, 1).unwrap_err();
+        assert_eq!(err.to_string(), format!("job {} failed: boom", failed));
+        // Another tenant cannot see it
+        assert!(matches!(store.summary(&failed, Some("team-b"), false), Err(JobError::NotFound(_))));
+
+        // Full store: the finished job goes, the running one stays
+        let running = store.submit(None);
+        let newest = store.submit(None);
+        assert_eq!(store.len(), 2);
+        assert!(store.summary(&failed, Some("team-a"), false).is_err());
+        assert!(store.summary(&running, None, false).is_ok());
+        assert_ne!(running, newest);
+    }
+}
@@ -0,0 +1,1183 @@
+//! Kernel registry.
+//!
+//! Every matmul kernel, built-in or third-party, implements `MatmulKernel` and lives in a
+//! process-wide registry. `select_kernel` is the single dispatch decision: it picks the
+//! highest-priority kernel that supports the precision and shapes and whose required
+//! features are available (or the kernel named by `ComputeOptions::kernel`), so compute
+//! and verification always agree and embedders can plug in experimental kernels without
+//! forking the dispatch code.
+//!
+//! Kernels that pack their operands first (the fp32, bf16, int8, int8_rowwise, int8_asym,
+//! int4 and u8i8 16x16 ones) are skipped by automatic selection while k is below
+//! `ComputeOptions::packed_min_k`: for a short reduction, allocating and filling the packed
+//! buffers costs more than the plain kernel's whole multiply. `bench::k_crossover` measures where the two meet.
+//!
+//! When A is 1×k or B is k×1, the fp32, int8 and int8_rowwise matrix-vector kernels
+//! (`*_gemv`) outrank the general ones, openblas included: with one side a vector, tiling
+//! only adds overhead. Their results are bit-identical to `fp32_blocked` and the
+//! `*_generic` kernels.
+//!
+//! `fp32_sparse` multiplies by a B supplied in CSR form (`Input::matrix_b_sparse`, see
+//! sparse.rs). It ranks below every other fp32 kernel, so automatic selection never picks
+//! it for a dense B.
+//!
+//! `fp32_strict` is the one kernel of the fp32_strict precision, in every build: its
+//! summation order is documented (see `matmul_fp32_strict`) so its results, and their
+//! hash, are the same on every target and with or without openblas.
+//!
+//! The fp32 and u8i8 dot-product kernels specialized for other fixed output shapes are
+//! generated in `shapes` and registered after the built-ins.
+
+use crate::{BOperand, ComputeOptions, FlatMatrix, Fp32Summation, Int8Scaling, Precision, SolverError};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeSet;
+use std::sync::{Arc, OnceLock, RwLock};
+use std::time::Duration;
+
+/// `ComputeOptions::packed_min_k` when unset
+pub const DEFAULT_PACKED_MIN_K: usize = 16;
+
+/// What a kernel hands back to the pipeline
+#[derive(Debug, Clone)]
+pub struct KernelResult {
+    pub result: FlatMatrix,
+    /// Time attributed to the kernel (see each kernel for what is included)
+    pub kernel_time: Duration,
+    /// Whether a packed-B cache was consulted and hit; None if the kernel has no cache
+    pub pack_cache_hit: Option<bool>,
+    /// Exact integer accumulators (row-major) for kernels that have them; needed by
+    /// `hash_input: "i32le"`, since `result` rounds them to f32
+    pub accumulators: Option<Vec<i32>>,
+    /// Unrounded f64 results (row-major) of the fp64 kernel; needed by the f64le hash,
+    /// since `result` rounds them to f32
+    pub result_f64: Option<Vec<f64>>,
+    /// Path the integer dot products took ("avx512vnni", "avx2", "i8mm", "dotprod", "neon", "scalar") for kernels
+    /// built on them; recorded as `OutputMetadata::simd_variant`
+    pub simd_variant: Option<&'static str>,
+    /// Threads the kernel split its work across, when more than one; recorded as
+    /// `OutputMetadata::threads`
+    pub threads: Option<usize>,
+}
+
+impl KernelResult {
+    pub fn new(result: FlatMatrix, kernel_time: Duration) -> Self {
+        Self { result, kernel_time, pack_cache_hit: None, accumulators: None, result_f64: None, simd_variant: None, threads: None }
+    }
+
+    /// Record that this run went through the dispatched integer dot products
+    fn int_simd(self) -> Self {
+        Self { simd_variant: Some(crate::int_dot_variant()), ..self }
+    }
+}
+
+/// A matmul implementation that can be selected by the dispatcher
+pub trait MatmulKernel: Send + Sync {
+    /// Unique name, recorded in OutputMetadata and accepted by the kernel override
+    fn name(&self) -> &str;
+
+    /// Precision strings this kernel implements
+    fn precisions(&self) -> &[&str];
+
+    /// Whether the kernel can run A (rows×cols) × B (rows×cols)
+    fn supports_shape(&self, a_shape: (usize, usize), b_shape: (usize, usize)) -> bool;
+
+    /// Higher wins when several kernels apply; ties go to the earliest registered
+    fn priority(&self) -> i32 {
+        0
+    }
+
+    fn run(&self, a: &FlatMatrix, b: &FlatMatrix, options: &ComputeOptions) -> Result<KernelResult, SolverError>;
+
+    /// `run` with B supplied as Bᵀ (n×k row-major, `b_layout: "transposed"`). The default
+    /// transposes it back; kernels that read Bᵀ anyway override it to skip that transpose
+    /// (and the packed-B cache holding it). Results must match `run` bit for bit.
+    fn run_b_transposed(&self, a: &FlatMatrix, b_t: &FlatMatrix, options: &ComputeOptions) -> Result<KernelResult, SolverError> {
+        self.run(a, &crate::transpose(b_t), options)
+    }
+
+    /// Whether `run` honors `ComputeOptions::result_columns` itself. Kernels that
+    /// return false always produce the full result and the pipeline slices it.
+    fn supports_column_subset(&self) -> bool {
+        false
+    }
+
+    /// Whether `run` honors a Kahan or pairwise `ComputeOptions::fp32_summation`, by running
+    /// `matmul_fp32_summed`. fp32 kernels that do not are skipped by automatic selection
+    /// when one is asked for, and cannot be forced.
+    fn supports_fp32_summation(&self) -> bool {
+        false
+    }
+
+    /// Build or CPU features the kernel needs (see `DetectedFeatures`), e.g. "openblas"
+    fn required_features(&self) -> &[&str] {
+        &[]
+    }
+
+    /// Whether `run` packs its operands before multiplying and a plain kernel of the same
+    /// precision gives the same result; automatic selection skips such kernels when k is
+    /// below `ComputeOptions::packed_min_k`
+    fn packs_operands(&self) -> bool {
+        false
+    }
+
+    fn supports(&self, precision: &str, a_shape: (usize, usize), b_shape: (usize, usize)) -> bool {
+        self.precisions().contains(&precision) && self.supports_shape(a_shape, b_shape)
+    }
+}
+
+/// Features kernels may require: "openblas" when built with it, plus CPU features
+/// detected at runtime ("sse4.1", "avx2", "fma", "avx512f", "avx512vnni" on x86_64;
+/// "neon", "dotprod", "i8mm" on aarch64). The SIMD paths (simd.rs) are chosen from the same set.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct DetectedFeatures(BTreeSet<String>);
+
+impl DetectedFeatures {
+    pub fn from_names<I: IntoIterator<Item = S>, S: Into<String>>(names: I) -> Self {
+        Self(names.into_iter().map(Into::into).collect())
+    }
+
+    /// Features of this build on this machine (detected once)
+    pub fn current() -> &'static DetectedFeatures {
+        static CURRENT: OnceLock<DetectedFeatures> = OnceLock::new();
+        CURRENT.get_or_init(|| {
+            let mut names: Vec<&str> = Vec::new();
+            if cfg!(feature = "openblas") {
+                names.push("openblas");
+            }
+            #[cfg(target_arch = "x86_64")]
+            {
+                if std::arch::is_x86_feature_detected!("sse4.1") {
+                    names.push("sse4.1");
+                }
+                if std::arch::is_x86_feature_detected!("avx2") {
+                    names.push("avx2");
+                }
+                if std::arch::is_x86_feature_detected!("fma") {
+                    names.push("fma");
+                }
+                if std::arch::is_x86_feature_detected!("avx512f") {
+                    names.push("avx512f");
+                }
+                if std::arch::is_x86_feature_detected!("avx512vnni") {
+                    names.push("avx512vnni");
+                }
+            }
+            #[cfg(target_arch = "aarch64")]
+            {
+                if std::arch::is_aarch64_feature_detected!("neon") {
+                    names.push("neon");
+                }
+                if std::arch::is_aarch64_feature_detected!("dotprod") {
+                    names.push("dotprod");
+                }
+                if std::arch::is_aarch64_feature_detected!("i8mm") {
+                    names.push("i8mm");
+                }
+            }
+            DetectedFeatures::from_names(names)
+        })
+    }
+
+    pub fn has(&self, name: &str) -> bool {
+        self.0.contains(name)
+    }
+
+    pub fn names(&self) -> impl Iterator<Item = &str> {
+        self.0.iter().map(String::as_str)
+    }
+}
+
+/// Why `select_kernel` picked a kernel
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ChoiceReason {
+    /// Named by `ComputeOptions::kernel`
+    Forced,
+    /// Highest priority among the applicable kernels
+    HighestPriority,
+    /// Highest priority once packing kernels were skipped because k is below
+    /// `ComputeOptions::packed_min_k`
+    SmallK,
+}
+
+/// Outcome of kernel selection, recorded verbatim in `OutputMetadata::kernel_choice`
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct KernelChoice {
+    pub kernel: String,
+    pub precision: String,
+    pub a_shape: (usize, usize),
+    pub b_shape: (usize, usize),
+    pub reason: ChoiceReason,
+    pub priority: i32,
+    pub required_features: Vec<String>,
+}
+
+type RunFn = fn(&FlatMatrix, &FlatMatrix, &ComputeOptions) -> Result<KernelResult, SolverError>;
+
+/// Runs on Bᵀ directly; None when these options need the row-major path
+type RunBtFn = fn(&FlatMatrix, &FlatMatrix, &ComputeOptions) -> Option<Result<KernelResult, SolverError>>;
+
+struct BuiltinKernel {
+    name: &'static str,
+    precisions: &'static [&'static str],
+    priority: i32,
+    requires: &'static [&'static str],
+    shape: fn((usize, usize), (usize, usize)) -> bool,
+    column_subset: bool,
+    /// Runs `ComputeOptions::fp32_summation` (fp32 kernels only)
+    summation: bool,
+    packs: bool,
+    run: RunFn,
+    run_b_t: Option<RunBtFn>,
+}
+
+impl MatmulKernel for BuiltinKernel {
+    fn name(&self) -> &str {
+        self.name
+    }
+
+    fn precisions(&self) -> &[&str] {
+        self.precisions
+    }
+
+    fn supports_shape(&self, a_shape: (usize, usize), b_shape: (usize, usize)) -> bool {
+        (self.shape)(a_shape, b_shape)
+    }
+
+    fn priority(&self) -> i32 {
+        self.priority
+    }
+
+    fn supports_column_subset(&self) -> bool {
+        self.column_subset
+    }
+
+    fn supports_fp32_summation(&self) -> bool {
+        self.summation
+    }
+
+    fn required_features(&self) -> &[&str] {
+        self.requires
+    }
+
+    fn packs_operands(&self) -> bool {
+        self.packs
+    }
+
+    fn run(&self, a: &FlatMatrix, b: &FlatMatrix, options: &ComputeOptions) -> Result<KernelResult, SolverError> {
+        (self.run)(a, b, options)
+    }
+
+    fn run_b_transposed(&self, a: &FlatMatrix, b_t: &FlatMatrix, options: &ComputeOptions) -> Result<KernelResult, SolverError> {
+        match self.run_b_t.and_then(|run_b_t| run_b_t(a, b_t, options)) {
+            Some(result) => result,
+            None => (self.run)(a, &crate::transpose(b_t), options),
+        }
+    }
+}
+
+fn any_shape(_a: (usize, usize), _b: (usize, usize)) -> bool {
+    true
+}
+
+fn seed_shape(a: (usize, usize), b: (usize, usize)) -> bool {
+    a.0 == 16 && b.1 == 16 && a.1 == b.0
+}
+
+/// A 1×k or B k×1
+fn vector_shape(a: (usize, usize), b: (usize, usize)) -> bool {
+    (a.0 == 1 || b.1 == 1) && a.1 == b.0
+}
+
+/// Fallback paths time the whole call, including quantization/conversion
+fn timed(options: &ComputeOptions, f: impl FnOnce() -> FlatMatrix) -> Result<KernelResult, SolverError> {
+    let (res, elapsed) = crate::clock::time(options.clock(), f);
+    Ok(KernelResult::new(res, elapsed))
+}
+
+fn builtin_kernels() -> Vec<Arc<dyn MatmulKernel>> {
+    let kernels: Vec<BuiltinKernel> = vec![
+        BuiltinKernel {
+            name: "fp32_16x16",
+            precisions: &["fp32"],
+            priority: 20,
+            requires: &[],
+            shape: seed_shape,
+            column_subset: false,
+            summation: true,
+            packs: true,
+            run: |a, b, options| {
+                if options.fp32_summation != Fp32Summation::Naive {
+                    let (res, t) = crate::matmul_fp32_summed(a, b, options.fp32_summation, options.clock());
+                    return Ok(KernelResult::new(res, t));
+                }
+                if let Some(chunk) = crate::chunked::chunk_len(a.cols, options) {
+                    return crate::chunked::fp32_16x16(a, b, options, chunk);
+                }
+                let (res, t) = crate::matmul_fp32_16x16(a, b, options.fp32_accumulation, options.clock());
+                Ok(KernelResult::new(res, t))
+            },
+            run_b_t: None,
+        },
+        BuiltinKernel {
+            name: "fp32_gemv",
+            precisions: &["fp32"],
+            priority: 15,
+            requires: &[],
+            shape: vector_shape,
+            column_subset: false,
+            summation: true,
+            packs: false,
+            run: |a, b, options| {
+                let (res, t) = match options.fp32_summation {
+                    Fp32Summation::Naive => crate::matmul_fp32_gemv(a, b, options.clock()),
+                    summation => crate::matmul_fp32_summed(a, b, summation, options.clock()),
+                };
+                Ok(KernelResult::new(res, t))
+            },
+            run_b_t: None,
+        },
+        #[cfg(feature = "openblas")]
+        BuiltinKernel {
+            name: "fp32_openblas",
+            precisions: &["fp32"],
+            priority: 10,
+            requires: &["openblas"],
+            shape: any_shape,
+            column_subset: false,
+            summation: false,
+            packs: false,
+            run: |a, b, options| {
+                let (res, t) = crate::matmul_fp32_openblas(a, b, options.clock());
+                Ok(KernelResult::new(res, t))
+            },
+            run_b_t: None,
+        },
+        BuiltinKernel {
+            name: "fp32_blocked",
+            precisions: &["fp32"],
+            priority: 0,
+            requires: &[],
+            shape: any_shape,
+            column_subset: false,
+            summation: true,
+            packs: false,
+            run: |a, b, options| {
+                let (res, t, threads, hit) = match options.fp32_summation {
+                    Fp32Summation::Naive => crate::matmul_fp32_blocked_cached(a, b, options),
+                    summation => {
+                        let (res, t) = crate::matmul_fp32_summed(a, b, summation, options.clock());
+                        (res, t, 1, None)
+                    }
+                };
+                Ok(KernelResult { pack_cache_hit: hit, threads: (threads > 1).then_some(threads), ..KernelResult::new(res, t) })
+            },
+            run_b_t: None,
+        },
+        // Runs every matrix_b_sparse input; below fp32_blocked, so never chosen for a dense B
+        // unless forced (which converts it to CSR first, inside the timing)
+        BuiltinKernel {
+            name: crate::sparse::KERNEL,
+            precisions: &["fp32"],
+            priority: -10,
+            requires: &[],
+            shape: any_shape,
+            column_subset: false,
+            summation: false,
+            packs: false,
+            run: |a, b, options| {
+                timed(options, || crate::sparse::matmul_fp32_sparse(a, &crate::sparse::SparseMatrix::from_dense(b), options.clock()).0)
+            },
+            run_b_t: None,
+        },
+        // The only fp32_strict kernel, whatever the build: automatic selection has nothing
+        // else to pick, so compute and verification both run its fixed summation order
+        BuiltinKernel {
+            name: "fp32_strict",
+            precisions: &["fp32_strict"],
+            priority: 0,
+            requires: &[],
+            shape: any_shape,
+            column_subset: false,
+            summation: false,
+            packs: false,
+            run: |a, b, options| {
+                let (res, t) = crate::matmul_fp32_strict(a, b, options.clock());
+                Ok(KernelResult::new(res, t))
+            },
+            run_b_t: None,
+        },
+        BuiltinKernel {
+            name: "fp64_blocked",
+            precisions: &["fp64"],
+            priority: 0,
+            requires: &[],
+            shape: any_shape,
+            column_subset: false,
+            summation: false,
+            packs: false,
+            run: |a, b, options| {
+                let (values, t) = crate::matmul_fp64(a, b, options.clock());
+                let data = values.iter().map(|&x| x as f32).collect();
+                let result = FlatMatrix { data, rows: a.rows, cols: b.cols };
+                Ok(KernelResult { result_f64: Some(values), ..KernelResult::new(result, t) })
+            },
+            run_b_t: None,
+        },
+        #[cfg(feature = "fp16")]
+        BuiltinKernel {
+            name: "fp16_16x16",
+            precisions: &["fp16"],
+            priority: 20,
+            requires: &[],
+            shape: seed_shape,
+            column_subset: true,
+            summation: false,
+            // Stages its operands too, but on aarch64 its NEON dot product sums k in four lanes
+            // where fp16_generic sums in order, so the two hashes differ there
+            packs: false,
+            run: |a, b, options| {
+                if let Some(chunk) = crate::chunked::chunk_len(a.cols, options) {
+                    return crate::chunked::fp16_16x16(a, b, options, chunk);
+                }
+                fp16_16x16(a, BOperand::row_major(b, options), options)
+            },
+            run_b_t: Some(|a, b_t, options| {
+                crate::chunked::chunk_len(a.cols, options).is_none().then(|| fp16_16x16(a, BOperand::Transposed(b_t), options))
+            }),
+        },
+        #[cfg(all(feature = "openblas", feature = "fp16"))]
+        BuiltinKernel {
+            name: "fp16_openblas",
+            precisions: &["fp16"],
+            priority: 10,
+            requires: &["openblas"],
+            shape: any_shape,
+            column_subset: false,
+            summation: false,
+            packs: false,
+            run: |a, b, options| timed(options, || crate::matmul_fp16_openblas(a, b, options.fp16_rounding)),
+            run_b_t: None,
+        },
+        #[cfg(feature = "fp16")]
+        BuiltinKernel {
+            name: "fp16_generic",
+            precisions: &["fp16"],
+            priority: 0,
+            requires: &[],
+            shape: any_shape,
+            column_subset: false,
+            summation: false,
+            packs: false,
+            run: |a, b, options| timed(options, || crate::matmul_fp16(a, b, options.fp16_rounding)),
+            run_b_t: None,
+        },
+        BuiltinKernel {
+            name: "bf16_16x16",
+            precisions: &["bf16"],
+            priority: 20,
+            requires: &[],
+            shape: seed_shape,
+            column_subset: true,
+            summation: false,
+            packs: true,
+            run: |a, b, options| {
+                if let Some(chunk) = crate::chunked::chunk_len(a.cols, options) {
+                    return crate::chunked::bf16_16x16(a, b, options, chunk);
+                }
+                bf16_16x16(a, BOperand::row_major(b, options), options)
+            },
+            run_b_t: Some(|a, b_t, options| {
+                crate::chunked::chunk_len(a.cols, options).is_none().then(|| bf16_16x16(a, BOperand::Transposed(b_t), options))
+            }),
+        },
+        BuiltinKernel {
+            name: "bf16_generic",
+            precisions: &["bf16"],
+            priority: 0,
+            requires: &[],
+            shape: any_shape,
+            column_subset: false,
+            summation: false,
+            packs: false,
+            run: |a, b, options| timed(options, || crate::matmul_bf16(a, b)),
+            run_b_t: None,
+        },
+        BuiltinKernel {
+            name: "int8_16x16",
+            precisions: &["int8"],
+            priority: 20,
+            requires: &[],
+            shape: seed_shape,
+            column_subset: true,
+            summation: false,
+            packs: true,
+            run: |a, b, options| {
+                if options.accumulator.is_saturating() {
+                    return crate::saturating::kernel_result(a, b, "int8", options, true);
+                }
+                if let Some(chunk) = crate::chunked::chunk_len(a.cols, options) {
+                    return crate::chunked::int8_16x16(a, b, Int8Scaling::PerTensor, options, chunk).map(KernelResult::int_simd);
+                }
+                int8_16x16(a, BOperand::row_major(b, options), Int8Scaling::PerTensor, options)
+            },
+            run_b_t: Some(|a, b_t, options| {
+                let direct = !options.accumulator.is_saturating() && crate::chunked::chunk_len(a.cols, options).is_none();
+                direct.then(|| int8_16x16(a, BOperand::Transposed(b_t), Int8Scaling::PerTensor, options))
+            }),
+        },
+        BuiltinKernel {
+            name: "int8_gemv",
+            precisions: &["int8"],
+            priority: 15,
+            requires: &[],
+            shape: vector_shape,
+            column_subset: false,
+            summation: false,
+            packs: false,
+            run: |a, b, options| {
+                if options.accumulator.is_saturating() {
+                    return crate::saturating::kernel_result(a, b, "int8", options, false);
+                }
+                timed(options, || crate::matmul_int8_gemv(a, b, Int8Scaling::PerTensor)).map(KernelResult::int_simd)
+            },
+            run_b_t: None,
+        },
+        #[cfg(feature = "openblas")]
+        BuiltinKernel {
+            name: "int8_openblas",
+            precisions: &["int8"],
+            priority: 10,
+            requires: &["openblas"],
+            shape: any_shape,
+            column_subset: false,
+            summation: false,
+            packs: false,
+            run: |a, b, options| {
+                if options.accumulator.is_saturating() {
+                    return crate::saturating::kernel_result(a, b, "int8", options, false);
+                }
+                timed(options, || crate::matmul_int8_openblas(a, b))
+            },
+            run_b_t: None,
+        },
+        BuiltinKernel {
+            name: "int8_generic",
+            precisions: &["int8"],
+            priority: 0,
+            requires: &[],
+            shape: any_shape,
+            column_subset: false,
+            summation: false,
+            packs: false,
+            run: |a, b, options| {
+                if options.accumulator.is_saturating() {
+                    return crate::saturating::kernel_result(a, b, "int8", options, false);
+                }
+                timed(options, || crate::matmul_int8(a, b, Int8Scaling::PerTensor))
+            },
+            run_b_t: None,
+        },
+        BuiltinKernel {
+            name: "int8_rowwise_16x16",
+            precisions: &["int8_rowwise"],
+            priority: 20,
+            requires: &[],
+            shape: seed_shape,
+            column_subset: true,
+            summation: false,
+            packs: true,
+            run: |a, b, options| {
+                if let Some(chunk) = crate::chunked::chunk_len(a.cols, options) {
+                    return crate::chunked::int8_16x16(a, b, Int8Scaling::PerRow, options, chunk).map(KernelResult::int_simd);
+                }
+                int8_16x16(a, BOperand::row_major(b, options), Int8Scaling::PerRow, options)
+            },
+            run_b_t: Some(|a, b_t, options| {
+                let direct = crate::chunked::chunk_len(a.cols, options).is_none();
+                direct.then(|| int8_16x16(a, BOperand::Transposed(b_t), Int8Scaling::PerRow, options))
+            }),
+        },
+        BuiltinKernel {
+            name: "int8_rowwise_gemv",
+            precisions: &["int8_rowwise"],
+            priority: 15,
+            requires: &[],
+            shape: vector_shape,
+            column_subset: false,
+            summation: false,
+            packs: false,
+            run: |a, b, options| timed(options, || crate::matmul_int8_gemv(a, b, Int8Scaling::PerRow)).map(KernelResult::int_simd),
+            run_b_t: None,
+        },
+        BuiltinKernel {
+            name: "int8_rowwise_generic",
+            precisions: &["int8_rowwise"],
+            priority: 0,
+            requires: &[],
+            shape: any_shape,
+            column_subset: false,
+            summation: false,
+            packs: false,
+            run: |a, b, options| timed(options, || crate::matmul_int8(a, b, Int8Scaling::PerRow)),
+            run_b_t: None,
+        },
+        BuiltinKernel {
+            name: "int8_asym_16x16",
+            precisions: &["int8_asym"],
+            priority: 20,
+            requires: &[],
+            shape: seed_shape,
+            column_subset: true,
+            summation: false,
+            packs: true,
+            run: |a, b, options| int8_asym_16x16(a, BOperand::row_major(b, options), options),
+            run_b_t: Some(|a, b_t, options| Some(int8_asym_16x16(a, BOperand::Transposed(b_t), options))),
+        },
+        BuiltinKernel {
+            name: "int8_asym_generic",
+            precisions: &["int8_asym"],
+            priority: 0,
+            requires: &[],
+            shape: any_shape,
+            column_subset: false,
+            summation: false,
+            packs: false,
+            run: |a, b, options| timed(options, || crate::matmul_int8_asym(a, b)),
+            run_b_t: None,
+        },
+        BuiltinKernel {
+            name: "int4_16x16",
+            precisions: &["int4"],
+            priority: 20,
+            requires: &[],
+            shape: seed_shape,
+            column_subset: true,
+            summation: false,
+            packs: true,
+            run: |a, b, options| {
+                let (res, t) = crate::matmul_int4_16x16(a, b, options.result_columns.as_deref(), options.clock());
+                Ok(KernelResult::new(res, t))
+            },
+            run_b_t: None,
+        },
+        BuiltinKernel {
+            name: "int4_generic",
+            precisions: &["int4"],
+            priority: 0,
+            requires: &[],
+            shape: any_shape,
+            column_subset: false,
+            summation: false,
+            packs: false,
+            run: |a, b, options| timed(options, || crate::matmul_int4(a, b)),
+            run_b_t: None,
+        },
+        BuiltinKernel {
+            name: "u8i8_16x16",
+            precisions: &["u8i8"],
+            priority: 20,
+            requires: &[],
+            shape: seed_shape,
+            column_subset: false,
+            summation: false,
+            packs: true,
+            run: |a, b, options| {
+                if options.accumulator.is_saturating() {
+                    return crate::saturating::kernel_result(a, b, "u8i8", options, true);
+                }
+                if let Some(chunk) = crate::chunked::u8i8_chunk_len(a.cols, options) {
+                    return crate::chunked::u8i8_16x16(a, b, options, chunk).map(KernelResult::int_simd);
+                }
+                let (b_i8, hit) = match options.pack_cache_dir.as_deref() {
+                    #[cfg(feature = "blake3-hash")]
+                    Some(dir) => {
+                        let (buf, _, hit) = crate::pack_cache::load_or_pack_i8(
+                            dir,
+                            crate::pack_cache::PackKind::U8I8RowMajor,
+                            b,
+                            || (crate::pack_b_u8i8(b), 1.0),
+                        );
+                        (buf, hit)
+                    }
+                    _ => (crate::pack_b_u8i8(b), false),
+                };
+                let (acc, t, threads) = crate::matmul_u8i8_16x16_packed_i32(a, &b_i8, options.threads, options.clock());
+                let result = KernelResult::new(crate::i32_to_f32_matrix(&acc, 16, 16), t);
+                Ok(KernelResult { pack_cache_hit: Some(hit), accumulators: Some(acc), threads: (threads > 1).then_some(threads), ..result }.int_simd())
+            },
+            run_b_t: Some(|a, b_t, options| {
+                if options.accumulator.is_saturating() || crate::chunked::u8i8_chunk_len(a.cols, options).is_some() {
+                    return None;
+                }
+                let (acc, t) = crate::matmul_u8i8_16x16_bt_i32(a, b_t, options.clock());
+                let result = KernelResult::new(crate::i32_to_f32_matrix(&acc, 16, 16), t);
+                Some(Ok(KernelResult { accumulators: Some(acc), ..result }.int_simd()))
+            }),
+        },
+        BuiltinKernel {
+            name: "u8i8_generic",
+            precisions: &["u8i8"],
+            priority: 0,
+            requires: &[],
+            shape: any_shape,
+            column_subset: false,
+            summation: false,
+            packs: false,
+            run: u8i8_generic,
+            run_b_t: None,
+        },
+    ];
+    kernels
+        .into_iter()
+        .map(|k| Arc::new(k) as Arc<dyn MatmulKernel>)
+        .chain(crate::shapes::kernels())
+        .collect()
+}
+
+/// The u8i8_generic kernel (also what the u8i8 shape specializations run for other
+/// accumulators and long reductions)
+pub(crate) fn u8i8_generic(a: &FlatMatrix, b: &FlatMatrix, options: &ComputeOptions) -> Result<KernelResult, SolverError> {
+    if options.accumulator.is_saturating() {
+        return crate::saturating::kernel_result(a, b, "u8i8", options, false);
+    }
+    if options.accumulator == crate::Accumulator::I64 || a.cols > crate::U8I8_SAFE_K {
+        let (sums, t) = crate::clock::time(options.clock(), || crate::matmul_u8i8_generic_i64(a, b));
+        return crate::u8i8_wide_result(sums, b.cols, a.cols, options.accumulator, t);
+    }
+    let (acc, t) = crate::clock::time(options.clock(), || crate::matmul_u8i8_generic_i32(a, b));
+    let mut result = KernelResult::new(crate::i32_to_f32_matrix(&acc, a.rows, b.cols), t);
+    result.accumulators = Some(acc);
+    Ok(result)
+}
+
+#[cfg(feature = "fp16")]
+fn fp16_16x16(a: &FlatMatrix, b: BOperand, options: &ComputeOptions) -> Result<KernelResult, SolverError> {
+    let (res, t, threads) = crate::matmul_fp16_16x16(a, b, options.result_columns.as_deref(), options.fp16_rounding, options.threads, options.clock());
+    Ok(KernelResult { threads: (threads > 1).then_some(threads), ..KernelResult::new(res, t) })
+}
+
+fn bf16_16x16(a: &FlatMatrix, b: BOperand, options: &ComputeOptions) -> Result<KernelResult, SolverError> {
+    let (res, t) = crate::matmul_bf16_16x16(a, b, options.result_columns.as_deref(), options.clock());
+    Ok(KernelResult::new(res, t))
+}
+
+fn int8_16x16(a: &FlatMatrix, b: BOperand, scaling: Int8Scaling, options: &ComputeOptions) -> Result<KernelResult, SolverError> {
+    let (res, t, hit, threads) = crate::matmul_int8_16x16(
+        a,
+        b,
+        scaling,
+        options.pack_cache_dir.as_deref(),
+        options.result_columns.as_deref(),
+        options.threads,
+        options.clock(),
+    );
+    Ok(KernelResult { pack_cache_hit: hit, threads: (threads > 1).then_some(threads), ..KernelResult::new(res, t) }.int_simd())
+}
+
+fn int8_asym_16x16(a: &FlatMatrix, b: BOperand, options: &ComputeOptions) -> Result<KernelResult, SolverError> {
+    let (res, t, hit, threads) = crate::matmul_int8_asym_16x16(
+        a,
+        b,
+        options.pack_cache_dir.as_deref(),
+        options.result_columns.as_deref(),
+        options.threads,
+        options.clock(),
+    );
+    Ok(KernelResult { pack_cache_hit: hit, threads: (threads > 1).then_some(threads), ..KernelResult::new(res, t) }.int_simd())
+}
+
+static KERNEL_REGISTRY: OnceLock<RwLock<Vec<Arc<dyn MatmulKernel>>>> = OnceLock::new();
+
+fn registry() -> &'static RwLock<Vec<Arc<dyn MatmulKernel>>> {
+    KERNEL_REGISTRY.get_or_init(|| RwLock::new(builtin_kernels()))
+}
+
+/// Add a kernel to the process-wide registry. Names must be unique.
+pub fn register_kernel(kernel: Box<dyn MatmulKernel>) -> Result<(), SolverError> {
+    let mut kernels = registry().write().unwrap();
+    if kernels.iter().any(|k| k.name() == kernel.name()) {
+        return Err(SolverError::InvalidInput(format!(
+            "A kernel named '{}' is already registered",
+            kernel.name()
+        )));
+    }
+    kernels.push(Arc::from(kernel));
+    Ok(())
+}
+
+/// Names of all registered kernels, in registration order
+pub fn kernel_names() -> Vec<String> {
+    registry().read().unwrap().iter().map(|k| k.name().to_string()).collect()
+}
+
+fn missing_features(kernel: &dyn MatmulKernel, features: &DetectedFeatures) -> Vec<String> {
+    kernel.required_features().iter().filter(|f| !features.has(f)).map(|f| f.to_string()).collect()
+}
+
+/// The dispatch decision for a workload, shared by compute and verification.
+///
+/// With `options.kernel` set, that kernel is chosen if it is registered, supports the
+/// workload and its required features are in `features`; otherwise the
+/// highest-priority applicable kernel wins (ties go to the earliest registered), leaving
+/// out kernels that pack their operands when k is below `options.packed_min_k`.
+pub fn select_kernel(
+    a_shape: (usize, usize),
+    b_shape: (usize, usize),
+    precision: &str,
+    options: &ComputeOptions,
+    features: &DetectedFeatures,
+) -> Result<KernelChoice, SolverError> {
+    let kernels = registry().read().unwrap();
+    let choice = |kernel: &Arc<dyn MatmulKernel>, reason| KernelChoice {
+        kernel: kernel.name().to_string(),
+        precision: precision.to_string(),
+        a_shape,
+        b_shape,
+        reason,
+        priority: kernel.priority(),
+        required_features: kernel.required_features().iter().map(|f| f.to_string()).collect(),
+    };
+
+    // Only kernels that implement a Kahan or pairwise summation run one
+    let summed = precision == "fp32" && options.fp32_summation != Fp32Summation::Naive;
+    if let Some(name) = options.kernel.as_deref() {
+        let name = &*options.match_name(name);
+        let kernel = kernels.iter().find(|k| k.name() == name).ok_or_else(|| {
+            let names: Vec<&str> = kernels.iter().map(|k| k.name()).collect();
+            SolverError::KernelUnavailable(format!("no kernel named {:?} (registered: {})", name, names.join(", ")))
+        })?;
+        if !kernel.supports(precision, a_shape, b_shape) {
+            return Err(SolverError::KernelUnavailable(format!(
+                "kernel '{}' does not support {} for A {}x{} × B {}x{}",
+                name, precision, a_shape.0, a_shape.1, b_shape.0, b_shape.1
+            )));
+        }
+        if summed && !kernel.supports_fp32_summation() {
+            return Err(SolverError::KernelUnavailable(format!(
+                "kernel '{}' does not implement fp32 summation {}",
+                name,
+                options.fp32_summation.as_str()
+            )));
+        }
+        let missing = missing_features(kernel.as_ref(), features);
+        if !missing.is_empty() {
+            return Err(SolverError::KernelUnavailable(format!(
+                "kernel '{}' requires {}",
+                name,
+                missing.join(", ")
+            )));
+        }
+        return Ok(choice(kernel, ChoiceReason::Forced));
+    }
+
+    let applicable: Vec<&Arc<dyn MatmulKernel>> = kernels
+        .iter()
+        .filter(|k| k.supports(precision, a_shape, b_shape) && missing_features(k.as_ref(), features).is_empty())
+        .filter(|k| !summed || k.supports_fp32_summation())
+        .collect();
+    let highest = |skip_packing: bool| {
+        let mut best: Option<&Arc<dyn MatmulKernel>> = None;
+        for &kernel in applicable.iter().filter(|k| !(skip_packing && k.packs_operands())) {
+            if best.is_none_or(|b| kernel.priority() > b.priority()) {
+                best = Some(kernel);
+            }
+        }
+        best
+    };
+    // A packing kernel that is the only one applicable still runs, whatever k is
+    let best = highest(false);
+    let small_k = a_shape.1 < options.packed_min_k.unwrap_or(DEFAULT_PACKED_MIN_K);
+    let unpacked = if small_k && best.is_some_and(|k| k.packs_operands()) { highest(true) } else { None };
+    match unpacked.or(best) {
+        Some(kernel) if unpacked.is_some() => Ok(choice(kernel, ChoiceReason::SmallK)),
+        Some(kernel) => Ok(choice(kernel, ChoiceReason::HighestPriority)),
+        None if kernels.iter().any(|k| k.precisions().contains(&precision)) => Err(SolverError::KernelUnavailable(format!(
+            "no {} kernel supports A {}x{} × B {}x{}",
+            precision, a_shape.0, a_shape.1, b_shape.0, b_shape.1
+        ))),
+        None => Err(Precision::unsupported(precision)),
+    }
+}
+
+/// Registered kernels whose required features this machine has, in registration order
+pub fn available_kernels() -> Vec<Arc<dyn MatmulKernel>> {
+    let features = DetectedFeatures::current();
+    registry().read().unwrap().iter().filter(|k| missing_features(k.as_ref(), features).is_empty()).cloned().collect()
+}
+
+/// Registered kernel by name
+pub fn kernel_by_name(name: &str) -> Option<Arc<dyn MatmulKernel>> {
+    registry().read().unwrap().iter().find(|k| k.name() == name).cloned()
+}
+
+/// `select_kernel` with this machine's features, plus the chosen kernel itself
+pub fn resolve_choice(
+    precision: &str,
+    a_shape: (usize, usize),
+    b_shape: (usize, usize),
+    options: &ComputeOptions,
+) -> Result<(KernelChoice, Arc<dyn MatmulKernel>), SolverError> {
+    let choice = select_kernel(a_shape, b_shape, precision, options, DetectedFeatures::current())?;
+    let kernel = kernel_by_name(&choice.kernel)
+        .ok_or_else(|| SolverError::Internal(format!("kernel '{}' vanished from the registry", choice.kernel)))?;
+    Ok((choice, kernel))
+}
+
+/// Pick the kernel for a workload (see `select_kernel`)
+pub fn resolve_kernel(
+    precision: &str,
+    a_shape: (usize, usize),
+    b_shape: (usize, usize),
+    forced: Option<&str>,
+) -> Result<Arc<dyn MatmulKernel>, SolverError> {
+    let options = ComputeOptions { kernel: forced.map(str::to_string), ..Default::default() };
+    resolve_choice(precision, a_shape, b_shape, &options).map(|(_, kernel)| kernel)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{compute_workload_with_options, types};
+
+    const SENTINEL: f32 = -4242.0;
+
+    /// Claims fp32 for one unusual shape only and returns a constant result
+    struct MockKernel;
+
+    impl MatmulKernel for MockKernel {
+        fn name(&self) -> &str {
+            "mock_sentinel"
+        }
+
+        fn precisions(&self) -> &[&str] {
+            &["fp32"]
+        }
+
+        fn supports_shape(&self, a_shape: (usize, usize), b_shape: (usize, usize)) -> bool {
+            a_shape == (3, 7) && b_shape == (7, 5)
+        }
+
+        fn run(&self, a: &FlatMatrix, b: &FlatMatrix, _options: &ComputeOptions) -> Result<KernelResult, SolverError> {
+            let result = FlatMatrix { data: vec![SENTINEL; a.rows * b.cols], rows: a.rows, cols: b.cols };
+            Ok(KernelResult::new(result, Duration::ZERO))
+        }
+    }
+
+    fn ensure_mock_registered() {
+        static ONCE: OnceLock<()> = OnceLock::new();
+        ONCE.get_or_init(|| register_kernel(Box::new(MockKernel)).unwrap());
+    }
+
+    fn input(rows_a: usize, k: usize, cols_b: usize) -> types::Input {
+        types::Input::matmul(
+            FlatMatrix { data: vec![1.0; rows_a * k], rows: rows_a, cols: k },
+            FlatMatrix { data: vec![1.0; k * cols_b], rows: k, cols: cols_b },
+            "fp32",
+        )
+    }
+
+    #[test]
+    fn test_forced_mock_kernel_is_selected() {
+        ensure_mock_registered();
+        let options = ComputeOptions { kernel: Some("mock_sentinel".to_string()), ..Default::default() };
+        let output = compute_workload_with_options(input(3, 7, 5), &options).unwrap();
+        assert!(output.result_matrix.data.iter().all(|&x| x == SENTINEL));
+        assert_eq!(output.metadata.kernel.as_deref(), Some("mock_sentinel"));
+    }
+
+    #[test]
+    fn test_mock_kernel_skipped_when_shape_does_not_match() {
+        ensure_mock_registered();
+        // Default priority never outranks the built-ins on automatic selection
+        let output = compute_workload_with_options(input(3, 7, 5), &ComputeOptions::default()).unwrap();
+        assert_ne!(output.metadata.kernel.as_deref(), Some("mock_sentinel"));
+        assert!(output.result_matrix.data.iter().all(|&x| x == 7.0));
+
+        // Forcing it on a shape its predicate rejects is an error, not a silent fallback
+        let options = ComputeOptions { kernel: Some("mock_sentinel".to_string()), ..Default::default() };
+        let err = compute_workload_with_options(input(4, 7, 5), &options).unwrap_err();
+        assert!(matches!(err, SolverError::KernelUnavailable(_)));
+    }
+
+    /// The dispatch table. A change to kernel selection must show up as a diff here.
+    #[test]
+    fn test_dispatch_table() {
+        const SEED: ((usize, usize), (usize, usize)) = ((16, 64), (64, 16));
+        const SMALL_K: ((usize, usize), (usize, usize)) = ((16, 8), (8, 16));
+        const SEED_WIDE_B: ((usize, usize), (usize, usize)) = ((16, 64), (64, 17));
+        const SHORT_A: ((usize, usize), (usize, usize)) = ((8, 64), (64, 16));
+        const SCALAR: ((usize, usize), (usize, usize)) = ((1, 1), (1, 1));
+        const TALL: ((usize, usize), (usize, usize)) = ((256, 32), (32, 48));
+        const WIDE: ((usize, usize), (usize, usize)) = ((32, 64), (64, 32));
+        const ROW_VECTOR: ((usize, usize), (usize, usize)) = ((1, 300), (300, 24));
+        const COLUMN_VECTOR: ((usize, usize), (usize, usize)) = ((24, 300), (300, 1));
+        // (precision, shape, without openblas, with openblas)
+        let table = [
+            ("fp32", SEED, "fp32_16x16", "fp32_16x16"),
+            ("fp32", SMALL_K, "fp32_blocked", "fp32_openblas"),
+            ("fp32", SEED_WIDE_B, "fp32_blocked", "fp32_openblas"),
+            ("fp32", SHORT_A, "fp32_blocked", "fp32_openblas"),
+            ("fp32", SCALAR, "fp32_gemv", "fp32_gemv"),
+            ("fp32", TALL, "fp32_blocked", "fp32_openblas"),
+            ("fp32", WIDE, "fp32_dot_32x32", "fp32_dot_32x32"),
+            ("fp32", ROW_VECTOR, "fp32_gemv", "fp32_gemv"),
+            ("fp32", COLUMN_VECTOR, "fp32_gemv", "fp32_gemv"),
+            ("fp32_strict", SEED, "fp32_strict", "fp32_strict"),
+            ("fp32_strict", SMALL_K, "fp32_strict", "fp32_strict"),
+            ("fp32_strict", SCALAR, "fp32_strict", "fp32_strict"),
+            ("fp32_strict", TALL, "fp32_strict", "fp32_strict"),
+            ("fp32_strict", WIDE, "fp32_strict", "fp32_strict"),
+            ("fp32_strict", ROW_VECTOR, "fp32_strict", "fp32_strict"),
+            ("fp64", SEED, "fp64_blocked", "fp64_blocked"),
+            ("fp64", SMALL_K, "fp64_blocked", "fp64_blocked"),
+            ("fp64", SEED_WIDE_B, "fp64_blocked", "fp64_blocked"),
+            ("fp64", SHORT_A, "fp64_blocked", "fp64_blocked"),
+            ("fp64", SCALAR, "fp64_blocked", "fp64_blocked"),
+            ("fp64", TALL, "fp64_blocked", "fp64_blocked"),
+            ("fp64", WIDE, "fp64_blocked", "fp64_blocked"),
+            ("fp16", SEED, "fp16_16x16", "fp16_16x16"),
+            ("fp16", SMALL_K, "fp16_16x16", "fp16_16x16"),
+            ("fp16", SEED_WIDE_B, "fp16_generic", "fp16_openblas"),
+            ("fp16", SHORT_A, "fp16_generic", "fp16_openblas"),
+            ("fp16", SCALAR, "fp16_generic", "fp16_openblas"),
+            ("fp16", TALL, "fp16_generic", "fp16_openblas"),
+            ("fp16", WIDE, "fp16_generic", "fp16_openblas"),
+            ("bf16", SEED, "bf16_16x16", "bf16_16x16"),
+            ("bf16", SMALL_K, "bf16_generic", "bf16_generic"),
+            ("bf16", SEED_WIDE_B, "bf16_generic", "bf16_generic"),
+            ("bf16", SHORT_A, "bf16_generic", "bf16_generic"),
+            ("bf16", SCALAR, "bf16_generic", "bf16_generic"),
+            ("bf16", TALL, "bf16_generic", "bf16_generic"),
+            ("bf16", WIDE, "bf16_generic", "bf16_generic"),
+            ("int8", SEED, "int8_16x16", "int8_16x16"),
+            ("int8", SMALL_K, "int8_generic", "int8_openblas"),
+            ("int8", SEED_WIDE_B, "int8_generic", "int8_openblas"),
+            ("int8", SHORT_A, "int8_generic", "int8_openblas"),
+            ("int8", SCALAR, "int8_gemv", "int8_gemv"),
+            ("int8", TALL, "int8_generic", "int8_openblas"),
+            ("int8", WIDE, "int8_generic", "int8_openblas"),
+            ("int8", ROW_VECTOR, "int8_gemv", "int8_gemv"),
+            ("int8", COLUMN_VECTOR, "int8_gemv", "int8_gemv"),
+            ("int8_rowwise", SEED, "int8_rowwise_16x16", "int8_rowwise_16x16"),
+            ("int8_rowwise", SMALL_K, "int8_rowwise_generic", "int8_rowwise_generic"),
+            ("int8_rowwise", SEED_WIDE_B, "int8_rowwise_generic", "int8_rowwise_generic"),
+            ("int8_rowwise", SHORT_A, "int8_rowwise_generic", "int8_rowwise_generic"),
+            ("int8_rowwise", SCALAR, "int8_rowwise_gemv", "int8_rowwise_gemv"),
+            ("int8_rowwise", TALL, "int8_rowwise_generic", "int8_rowwise_generic"),
+            ("int8_rowwise", WIDE, "int8_rowwise_generic", "int8_rowwise_generic"),
+            ("int8_rowwise", ROW_VECTOR, "int8_rowwise_gemv", "int8_rowwise_gemv"),
+            ("int8_rowwise", COLUMN_VECTOR, "int8_rowwise_gemv", "int8_rowwise_gemv"),
+            ("int8_asym", SEED, "int8_asym_16x16", "int8_asym_16x16"),
+            ("int8_asym", SMALL_K, "int8_asym_generic", "int8_asym_generic"),
+            ("int8_asym", SEED_WIDE_B, "int8_asym_generic", "int8_asym_generic"),
+            ("int8_asym", SHORT_A, "int8_asym_generic", "int8_asym_generic"),
+            ("int8_asym", SCALAR, "int8_asym_generic", "int8_asym_generic"),
+            ("int8_asym", TALL, "int8_asym_generic", "int8_asym_generic"),
+            ("int8_asym", WIDE, "int8_asym_generic", "int8_asym_generic"),
+            ("int4", SEED, "int4_16x16", "int4_16x16"),
+            ("int4", SMALL_K, "int4_generic", "int4_generic"),
+            ("int4", SEED_WIDE_B, "int4_generic", "int4_generic"),
+            ("int4", SHORT_A, "int4_generic", "int4_generic"),
+            ("int4", SCALAR, "int4_generic", "int4_generic"),
+            ("int4", TALL, "int4_generic", "int4_generic"),
+            ("int4", WIDE, "int4_generic", "int4_generic"),
+            ("u8i8", SEED, "u8i8_16x16", "u8i8_16x16"),
+            ("u8i8", SMALL_K, "u8i8_generic", "u8i8_generic"),
+            ("u8i8", SEED_WIDE_B, "u8i8_generic", "u8i8_generic"),
+            ("u8i8", SHORT_A, "u8i8_generic", "u8i8_generic"),
+            ("u8i8", SCALAR, "u8i8_generic", "u8i8_generic"),
+            ("u8i8", TALL, "u8i8_generic", "u8i8_generic"),
+            ("u8i8", WIDE, "u8i8_dot_32x32", "u8i8_dot_32x32"),
+        ];
+        let feature_sets = [
+            DetectedFeatures::default(),
+            DetectedFeatures::from_names(["avx2", "fma"]),
+            DetectedFeatures::from_names(["neon"]),
+            DetectedFeatures::from_names(["openblas"]),
+            DetectedFeatures::from_names(["openblas", "avx2", "fma", "avx512f"]),
+        ];
+        let options = ComputeOptions::default();
+        for features in &feature_sets {
+            // openblas kernels are only registered in builds with the feature
+            let blas = features.has("openblas") && cfg!(feature = "openblas");
+            for (precision, (a_shape, b_shape), plain, with_blas) in table.into_iter().filter(|row| crate::tests::precisions().contains(&row.0)) {
+                let choice = select_kernel(a_shape, b_shape, precision, &options, features).unwrap();
+                let expected = if blas { with_blas } else { plain };
+                assert_eq!(choice.kernel, expected, "{} {:?}x{:?} with {:?}", precision, a_shape, b_shape, features);
+                let small_k = (a_shape, b_shape) == SMALL_K && !matches!(precision, "fp16" | "fp32_strict" | "fp64");
+                let reason = if small_k { ChoiceReason::SmallK } else { ChoiceReason::HighestPriority };
+                assert_eq!(choice.reason, reason, "{} {:?}x{:?}", precision, a_shape, b_shape);
+                assert!(choice.required_features.iter().all(|f| features.has(f)));
+            }
+        }
+
+        // Forcing a kernel whose features are missing is an error, not a fallback
+        let forced = ComputeOptions { kernel: Some("fp32_blocked".to_string()), ..Default::default() };
+        let choice = select_kernel(SEED.0, SEED.1, "fp32", &forced, &DetectedFeatures::default()).unwrap();
+        assert_eq!((choice.kernel.as_str(), choice.reason), ("fp32_blocked", ChoiceReason::Forced));
+        if cfg!(feature = "openblas") {
+            let forced = ComputeOptions { kernel: Some("fp32_openblas".to_string()), ..Default::default() };
+            let err = select_kernel(TALL.0, TALL.1, "fp32", &forced, &DetectedFeatures::default()).unwrap_err();
+            assert!(err.to_string().contains("requires openblas"), "{}", err);
+        }
+    }
+
+    #[test]
+    fn test_compute_and_verify_share_the_selector() {
+        let (a, b) = crate::generate_matrices_from_seed(b"dispatch", 16, 40, 40, 16);
+        for precision in crate::tests::precisions() {
+            let input = types::Input::matmul(a.clone(), b.clone(), precision);
+            let output = compute_workload_with_options(input, &ComputeOptions::default()).unwrap();
+            let choice = output.metadata.kernel_choice.clone().unwrap();
+            assert_eq!(Some(&choice.kernel), output.metadata.kernel.as_ref());
+            assert_eq!(choice, select_kernel((16, 40), (40, 16), precision, &ComputeOptions::default(), DetectedFeatures::current()).unwrap());
+            assert!(crate::verify_correctness(&a, &b, precision, &output.result_hash).unwrap(), "{}", precision);
+            assert!(crate::verify_with_metadata(&a, &b, &output.metadata, &output.result_hash).unwrap(), "{}", precision);
+        }
+
+        // A forced, non-default kernel is replayed by verify_with_metadata
+        let options = ComputeOptions { kernel: Some("fp32_blocked".to_string()), ..Default::default() };
+        let input = types::Input::matmul(a.clone(), b.clone(), "fp32");
+        let output = compute_workload_with_options(input, &options).unwrap();
+        assert_eq!(output.metadata.kernel_choice.as_ref().unwrap().reason, ChoiceReason::Forced);
+        assert!(crate::verify_with_metadata(&a, &b, &output.metadata, &output.result_hash).unwrap());
+    }
+
+    #[test]
+    fn test_builtin_selection() {
+        assert_eq!(resolve_kernel("u8i8", (16, 64), (64, 16), None).unwrap().name(), "u8i8_16x16");
+        assert_eq!(resolve_kernel("u8i8", (16, 8), (8, 16), None).unwrap().name(), "u8i8_generic");
+        // Mismatched inner dimensions never reach the 16x16 kernels
+        assert_eq!(resolve_kernel("u8i8", (16, 2048), (1024, 16), None).unwrap().name(), "u8i8_generic");
+        assert_eq!(resolve_kernel("u8i8", (8, 64), (64, 16), None).unwrap().name(), "u8i8_generic");
+        assert!(matches!(
+            resolve_kernel("fp128", (2, 2), (2, 2), None),
+            Err(SolverError::UnsupportedPrecision(_))
+        ));
+        // Duplicate names are rejected
+        ensure_mock_registered();
+        assert!(register_kernel(Box::new(MockKernel)).is_err());
+    }
+
+    #[test]
+    fn test_packed_min_k_keeps_hashes() {
+        for k in [1, 4, 15, 16, 64, 1024, 3000] {
+            let (a, b) = crate::generate_matrices_from_seed(b"packed-min-k", 16, k, k, 16);
+            // fp64 and fp32_strict have no packed kernel
+            for precision in crate::tests::precisions().into_iter().filter(|&p| !matches!(p, "fp64" | "fp32_strict")) {
+                let input = types::Input::matmul(a.clone(), b.clone(), precision);
+                let auto = compute_workload_with_options(input.clone(), &ComputeOptions::default()).unwrap();
+                let packed = ComputeOptions { packed_min_k: Some(0), ..Default::default() };
+                let fast_path = compute_workload_with_options(input, &packed).unwrap();
+                assert_eq!(fast_path.metadata.kernel.as_deref(), Some(format!("{}_16x16", precision).as_str()));
+                let expected = if k < DEFAULT_PACKED_MIN_K && precision != "fp16" { "plain" } else { "packed" };
+                let took = if auto.metadata.kernel == fast_path.metadata.kernel { "packed" } else { "plain" };
+                assert_eq!(took, expected, "{} k={}", precision, k);
+                assert_eq!(auto.result_hash, fast_path.result_hash, "{} k={}", precision, k);
+                assert!(crate::verify_correctness(&a, &b, precision, &auto.result_hash).unwrap());
+            }
+        }
+    }
+
+}
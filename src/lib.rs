@@ -1,13 +1,99 @@
+#[cfg(not(any(feature = "fault-injection", test)))]
 use sha2::{Digest, Sha256};
-use std::time::Instant;
 use serde::{Deserialize, Deserializer, Serialize, Serializer};
-use blake3;
 
+#[cfg(any(feature = "seed-gen", test))]
+pub mod acceptance;
 #[cfg(feature = "api")]
 pub mod api;
-use std::sync::{Mutex, OnceLock};
+pub mod audit;
+mod b_cache;
+pub mod batch;
+mod batched;
+#[cfg(any(feature = "seed-gen", test))]
+pub mod bench;
+#[cfg(feature = "bundle")]
+pub mod bundle;
+pub mod canonical;
+pub mod capabilities;
+pub mod chunked;
+#[cfg(any(feature = "seed-gen", test))]
+pub mod cli;
+#[cfg(feature = "client")]
+pub mod client;
+pub mod clock;
+#[cfg(any(feature = "fuzzing", test))]
+pub mod corpus;
+#[cfg(feature = "cross-check")]
+pub mod cross_check;
+#[cfg(feature = "dashboard")]
+pub mod dashboard;
+pub mod delta;
+pub mod envelope;
+pub mod error;
+pub mod error_metrics;
+#[cfg(any(feature = "fault-injection", test))]
+pub mod faults;
+#[cfg(any(feature = "fuzzing", test))]
+pub mod fuzz_checks;
+pub mod fused;
+pub mod histogram;
+mod host;
+#[cfg(feature = "api")]
+pub mod jobs;
+#[cfg(feature = "unstable-internals")]
+pub mod internals;
+pub mod kernels;
+pub mod memory;
+pub mod numbers;
+#[cfg(feature = "parallel")]
+mod parallel;
+#[cfg(feature = "blake3-hash")]
+pub mod pack_cache;
+pub mod preflight;
+pub mod prelude;
+#[cfg(any(feature = "seed-gen", test))]
+pub mod profile;
+pub mod quantize;
+#[cfg(feature = "api")]
+pub mod rate_limit;
+#[cfg(feature = "api")]
+pub mod replay;
+mod redundancy;
+mod saturating;
+#[cfg(feature = "api")]
+pub mod scheduler;
+#[cfg(any(feature = "seed-gen", test))]
+mod seed_pipeline;
+pub mod shapes;
+pub mod simd;
+#[cfg(any(feature = "seed-gen", test))]
+pub mod soak;
+pub mod sparse;
+#[cfg(feature = "api")]
+pub mod tenant;
+pub mod tolerance;
+#[cfg(feature = "api")]
+pub mod traffic;
+pub mod validation;
+#[cfg(any(feature = "seed-gen", test))]
+pub mod variance;
+#[cfg(any(feature = "seed-gen", test))]
+pub mod verify_batch;
+pub mod warnings;
+#[cfg(feature = "api")]
+pub mod webhook;
+#[cfg(feature = "api")]
+pub mod worker;
+pub use b_cache::CacheContext;
+pub use clock::{Clock, MockClock, SystemClock};
+pub use error::{MatrixError, SolverError};
+pub use kernels::{register_kernel, KernelResult, MatmulKernel};
+use std::sync::Arc;
 #[cfg(target_arch = "aarch64")]
 use std::arch::aarch64::*;
+#[cfg(target_arch = "x86_64")]
+use std::arch::x86_64::*;
 #[cfg(feature = "openblas")]
 extern crate openblas_src;
 #[cfg(feature = "openblas")]
@@ -37,6 +123,11 @@ impl AlignedBufferF32 {
     fn as_mut_ptr(&mut self) -> *mut f32 {
         self.ptr
     }
+
+    /// Every element; only once all were written
+    fn as_slice(&self) -> &[f32] {
+        unsafe { std::slice::from_raw_parts(self.ptr, self.len) }
+    }
 }
 
 impl Drop for AlignedBufferF32 {
@@ -74,6 +165,20 @@ impl AlignedBufferI8 {
     fn as_mut_ptr(&mut self) -> *mut i8 {
         self.ptr
     }
+
+    fn as_slice(&self) -> &[i8] {
+        unsafe { std::slice::from_raw_parts(self.ptr, self.len) }
+    }
+
+    fn as_mut_slice(&mut self) -> &mut [i8] {
+        unsafe { std::slice::from_raw_parts_mut(self.ptr, self.len) }
+    }
+
+    /// The buffer's bytes, for filling it straight from a byte stream
+    #[cfg(any(feature = "seed-gen", test))]
+    fn as_mut_bytes(&mut self) -> &mut [u8] {
+        unsafe { std::slice::from_raw_parts_mut(self.ptr as *mut u8, self.len) }
+    }
 }
 
 impl Drop for AlignedBufferI8 {
@@ -87,6 +192,40 @@ impl Drop for AlignedBufferI8 {
 unsafe impl Send for AlignedBufferI8 {}
 unsafe impl Sync for AlignedBufferI8 {}
 
+/// Signed 4-bit values packed two per byte: element 2i in the low nibble of byte i,
+/// element 2i + 1 in the high nibble
+struct PackedI4 {
+    buf: AlignedBufferI8,
+    len: usize,
+}
+
+impl PackedI4 {
+    /// Pack `values`, each already in -8..=7
+    fn pack(values: &[i8]) -> Self {
+        let mut buf = AlignedBufferI8::new(values.len().div_ceil(2), 64);
+        let buf_ptr = buf.as_mut_ptr();
+        for (i, pair) in values.chunks(2).enumerate() {
+            let low = pair[0] as u8 & 0x0F;
+            let high = pair.get(1).map_or(0, |&v| v as u8 & 0x0F);
+            unsafe { *buf_ptr.add(i) = (low | high << 4) as i8 };
+        }
+        Self { buf, len: values.len() }
+    }
+
+    /// The values sign-extended to one i8 each, laid out for the int8 kernels
+    fn unpack(&self) -> AlignedBufferI8 {
+        let mut out = AlignedBufferI8::new(self.len, 64);
+        let packed = self.buf.as_slice();
+        let out_ptr = out.as_mut_ptr();
+        for i in 0..self.len {
+            let byte = packed[i / 2] as u8;
+            let nibble = if i % 2 == 0 { byte << 4 } else { byte & 0xF0 };
+            unsafe { *out_ptr.add(i) = (nibble as i8) >> 4 };
+        }
+        out
+    }
+}
+
 struct AlignedBufferU8 {
     ptr: *mut u8,
     len: usize,
@@ -97,7 +236,7 @@ impl AlignedBufferU8 {
     fn new(len: usize, align: usize) -> Self {
         let layout = std::alloc::Layout::from_size_align(len * std::mem::size_of::<u8>(), align)
             .expect("aligned layout");
-        let ptr = unsafe { std::alloc::alloc(layout) as *mut u8 };
+        let ptr = unsafe { std::alloc::alloc(layout) };
         if ptr.is_null() {
             std::alloc::handle_alloc_error(layout);
         }
@@ -111,12 +250,17 @@ impl AlignedBufferU8 {
     fn as_mut_ptr(&mut self) -> *mut u8 {
         self.ptr
     }
+
+    #[cfg(any(feature = "seed-gen", test))]
+    fn as_mut_slice(&mut self) -> &mut [u8] {
+        unsafe { std::slice::from_raw_parts_mut(self.ptr, self.len) }
+    }
 }
 
 impl Drop for AlignedBufferU8 {
     fn drop(&mut self) {
         unsafe {
-            std::alloc::dealloc(self.ptr as *mut u8, self.layout);
+            std::alloc::dealloc(self.ptr, self.layout);
         }
     }
 }
@@ -124,224 +268,728 @@ impl Drop for AlignedBufferU8 {
 unsafe impl Send for AlignedBufferU8 {}
 unsafe impl Sync for AlignedBufferU8 {}
 
-#[derive(Clone, Copy, PartialEq, Eq)]
-struct CacheKey {
-    ptr: usize,
-    rows: usize,
-    cols: usize,
-    len: usize,
-}
-
-struct AlignedF32Cache {
-    key: CacheKey,
-    buf: AlignedBufferF32,
+/// Whether a `faults::FaultPlan` makes a cache entry for `cached` serve `key`: any entry
+/// of the same shape does, so the stale buffer is always large enough
+#[inline(always)]
+fn stale_hit(cached: &b_cache::CacheKey, key: &b_cache::CacheKey) -> bool {
+    #[cfg(any(feature = "fault-injection", test))]
+    return faults::stale_cache_hit() && (cached.rows, cached.cols) == (key.rows, key.cols);
+    #[cfg(not(any(feature = "fault-injection", test)))]
+    {
+        let _ = (cached, key);
+        false
+    }
 }
 
-struct AlignedI8Cache {
-    key: CacheKey,
+/// int8's packed Bᵀ as its cache keeps it
+struct PackedBtI8 {
     buf: AlignedBufferI8,
     scale: f32,
+    /// Sum of each quantized column of B, for int8_asym's zero-point correction
+    col_sums: [i32; 16],
 }
 
-static B_T_FP16_CACHE: OnceLock<Mutex<Option<AlignedF32Cache>>> = OnceLock::new();
-static B_T_I8_CACHE: OnceLock<Mutex<Option<AlignedI8Cache>>> = OnceLock::new();
+/// Empty the process-wide packed-B caches (fp16, bf16, int8, fp32) so the next run packs B
+/// again, releasing their memory once no running request still reads an entry; returns the
+/// packed Bs dropped. An API server clears its own `CacheContext` on `POST /cache/clear`.
+pub fn clear_kernel_caches() -> usize {
+    CacheContext::global().clear()
+}
 
-#[inline(always)]
-fn get_bt_fp16_cache(b: &FlatMatrix) -> (*const f32, usize) {
-    use half::f16;
+/// `clear_kernel_caches`, returning the caches emptied (`bench::run_suite` cold iterations
+/// report them)
+#[cfg(any(feature = "seed-gen", test))]
+pub(crate) fn flush_packed_b_caches() -> Vec<&'static str> {
+    clear_kernel_caches();
+    let mut flushed = Vec::new();
+    #[cfg(feature = "fp16")]
+    flushed.push("fp16 packed-B cache");
+    flushed.extend(["bf16 packed-B cache", "int8 packed-B cache", "fp32 packed-B cache"]);
+    flushed
+}
 
-    let k = b.rows;
-    let key = CacheKey {
-        ptr: b.data.as_ptr() as usize,
-        rows: b.rows,
-        cols: b.cols,
-        len: b.data.len(),
-    };
+/// Set how many packed Bs each in-process 16x16 cache keeps (default
+/// `b_cache::DEFAULT_CAPACITY`, 0 keeps none)
+pub fn set_packed_b_cache_capacity(capacity: usize) {
+    b_cache::set_capacity(capacity);
+}
 
-    let cache = B_T_FP16_CACHE.get_or_init(|| Mutex::new(None));
-    let mut guard = cache.lock().unwrap();
-    let reuse = guard.as_ref().is_some_and(|entry| entry.key == key);
-    if !reuse {
-        let mut buf = AlignedBufferF32::new(16 * k, 64);
-        let b_ptr = b.data.as_ptr();
-        unsafe {
-            for p in 0..k {
-                let b_base = p * 16;
-                for j in 0..16 {
-                    let val = *b_ptr.add(b_base + j);
-                    *buf.as_mut_ptr().add(j * k + p) = f16::from_f32(val).to_f32();
-                }
-            }
+/// Set how many bytes of packed Bs each in-process cache keeps (default
+/// `b_cache::DEFAULT_MAX_BYTES`)
+pub fn set_packed_b_cache_max_bytes(max_bytes: usize) {
+    b_cache::set_max_bytes(max_bytes);
+}
+
+/// Whether `kernel` packs a row-major k×n B through an in-process cache under `options`
+/// (before `bypass_b_cache`); chunked and saturating 16x16 runs stage B themselves, and
+/// fp32_blocked reads a B of one panel, or one it sums compensated, as is
+fn packs_b_through_cache(kernel: &str, (k, n): (usize, usize), options: &ComputeOptions) -> bool {
+    match kernel {
+        "fp16_16x16" | "bf16_16x16" | "int8_16x16" | "int8_rowwise_16x16" | "int8_asym_16x16" => {
+            chunked::chunk_len(k, options).is_none() && !options.accumulator.is_saturating()
         }
-        *guard = Some(AlignedF32Cache { key, buf });
+        "fp32_blocked" => fp32_packs_panels(k, n) && options.fp32_summation == Fp32Summation::Naive,
+        _ => false,
     }
-    let entry = guard.as_ref().unwrap();
-    (entry.buf.as_ptr(), k)
 }
 
-#[inline(always)]
-fn get_bt_i8_cache(b: &FlatMatrix) -> (*const i8, f32, usize) {
-    let k = b.rows;
-    let max_b = b.data.iter().map(|&x| x.abs()).fold(0.0f32, f32::max);
-    let scale_b = if max_b == 0.0 { 1.0 } else { 127.0 / max_b };
-
-    let key = CacheKey {
-        ptr: b.data.as_ptr() as usize,
-        rows: b.rows,
-        cols: b.cols,
-        len: b.data.len(),
-    };
+/// B as the 16x16 kernels that read Bᵀ receive it
+#[derive(Clone, Copy)]
+pub(crate) enum BOperand<'a> {
+    /// B (k×16); the kernel transposes it through its packed-B cache in this context
+    RowMajor(&'a FlatMatrix, &'a CacheContext),
+    /// Bᵀ (16×k) from `b_layout: "transposed"`, already in the kernel's layout: it is only
+    /// converted, without a transpose or the cache
+    Transposed(&'a FlatMatrix),
+    /// B (k×16) packed into a buffer of this run's own, for `ComputeOptions::bypass_b_cache`
+    Uncached(&'a FlatMatrix),
+}
 
-    let cache = B_T_I8_CACHE.get_or_init(|| Mutex::new(None));
-    let mut guard = cache.lock().unwrap();
-    let reuse = guard
-        .as_ref()
-        .is_some_and(|entry| entry.key == key && (entry.scale - scale_b).abs() < f32::EPSILON);
-    if !reuse {
-        let mut buf = AlignedBufferI8::new(16 * k, 64);
-        let b_ptr = b.data.as_ptr();
-        unsafe {
-            for p in 0..k {
-                let b_base = p * 16;
-                for j in 0..16 {
-                    let val = *b_ptr.add(b_base + j);
-                    *buf.as_mut_ptr().add(j * k + p) = (val * scale_b).clamp(-128.0, 127.0) as i8;
-                }
-            }
+impl<'a> BOperand<'a> {
+    /// Row-major B as `options` want it packed: through their caches unless bypassed
+    pub(crate) fn row_major(b: &'a FlatMatrix, options: &'a ComputeOptions) -> Self {
+        if options.bypass_b_cache {
+            BOperand::Uncached(b)
+        } else {
+            BOperand::RowMajor(b, options.caches())
         }
-        *guard = Some(AlignedI8Cache { key, buf, scale: scale_b });
     }
-    let entry = guard.as_ref().unwrap();
-    (entry.buf.as_ptr(), entry.scale, k)
 }
 
-#[inline(always)]
-fn dot_f32(a: *const f32, b: *const f32, len: usize) -> f32 {
-    #[cfg(target_arch = "aarch64")]
-    unsafe {
-        let mut acc = vdupq_n_f32(0.0);
-        let mut p = 0usize;
-        while p + 4 <= len {
-            let av = vld1q_f32(a.add(p));
-            let bv = vld1q_f32(b.add(p));
-            acc = vmlaq_f32(acc, av, bv);
-            p += 4;
+/// Bᵀ (16×k) rounded to f16 as `get_bt_fp16_cache` packs B; stochastic rounding is keyed
+/// by each element's index in B, so both give the same buffer
+#[cfg(feature = "fp16")]
+fn pack_bt_fp16(b_t: &FlatMatrix, rounding: Fp16Rounding) -> AlignedBufferF32 {
+    let k = b_t.cols;
+    let mut buf = AlignedBufferF32::new(16 * k, 64);
+    let buf_ptr = buf.as_mut_ptr();
+    for (idx, &val) in b_t.data.iter().enumerate() {
+        let (j, p) = (idx / k, idx % k);
+        unsafe { *buf_ptr.add(idx) = rounding.round(val, Operand::B, p * 16 + j) };
+    }
+    buf
+}
+
+/// Bᵀ (16×k) truncated to bf16 as `get_bt_bf16_cache` packs B
+fn pack_bt_bf16(b_t: &FlatMatrix) -> AlignedBufferF32 {
+    let mut buf = AlignedBufferF32::new(b_t.data.len(), 64);
+    let buf_ptr = buf.as_mut_ptr();
+    for (idx, &val) in b_t.data.iter().enumerate() {
+        unsafe { *buf_ptr.add(idx) = round_bf16(val) };
+    }
+    buf
+}
+
+/// Bᵀ (16×k) quantized to i8 as `pack_b_int8_transposed` packs B
+fn pack_bt_int8(b_t: &FlatMatrix) -> (AlignedBufferI8, f32) {
+    let scale_b = int8_scale(b_t.data.iter().copied());
+    let mut buf = AlignedBufferI8::new(b_t.data.len(), 64);
+    for (q, &val) in buf.as_mut_slice().iter_mut().zip(&b_t.data) {
+        *q = quantize_i8(val, scale_b);
+    }
+    (buf, scale_b)
+}
+
+/// int8's packed Bᵀ with its scale and column sums, and whether the cache was hit (None
+/// for a transposed or uncached B, which bypass it)
+fn int8_bt(b: BOperand, pack_cache_dir: Option<&std::path::Path>) -> (Arc<PackedBtI8>, Option<bool>) {
+    match b {
+        BOperand::RowMajor(b, caches) => {
+            let (packed, hit) = get_bt_i8_cache(b, caches, pack_cache_dir);
+            (packed, Some(hit))
         }
-        let acc_low = vget_low_f32(acc);
-        let acc_high = vget_high_f32(acc);
-        let sum2 = vadd_f32(acc_low, acc_high);
-        let sum1 = vpadd_f32(sum2, sum2);
-        let mut total = vget_lane_f32(sum1, 0);
-        while p < len {
-            total += *a.add(p) * *b.add(p);
-            p += 1;
+        BOperand::Transposed(b_t) => {
+            let (buf, scale) = pack_bt_int8(b_t);
+            let col_sums = column_sums_i8(buf.as_slice(), b_t.cols);
+            (Arc::new(PackedBtI8 { buf, scale, col_sums }), None)
+        }
+        BOperand::Uncached(b) => {
+            let (buf, scale) = pack_b_int8_transposed(b);
+            let col_sums = column_sums_i8(buf.as_slice(), b.rows);
+            (Arc::new(PackedBtI8 { buf, scale, col_sums }), None)
         }
-        total
     }
-    #[cfg(not(target_arch = "aarch64"))]
+}
+
+/// Bᵀ (16×k) of row-major B, rounded to f16
+#[cfg(feature = "fp16")]
+fn pack_b_fp16(b: &FlatMatrix, rounding: Fp16Rounding) -> AlignedBufferF32 {
+    let k = b.rows;
+    let mut buf = AlignedBufferF32::new(16 * k, 64);
+    let b_ptr = b.data.as_ptr();
     unsafe {
-        let mut total = 0.0f32;
-        let mut p = 0usize;
-        while p < len {
-            total += *a.add(p) * *b.add(p);
-            p += 1;
+        for p in 0..k {
+            let b_base = p * 16;
+            for j in 0..16 {
+                let val = *b_ptr.add(b_base + j);
+                *buf.as_mut_ptr().add(j * k + p) = rounding.round(val, Operand::B, b_base + j);
+            }
         }
-        total
     }
+    buf
 }
 
+#[cfg(feature = "fp16")]
 #[inline(always)]
-fn dot_i8(a: *const i8, b: *const i8, len: usize) -> i32 {
-    #[cfg(target_arch = "aarch64")]
+fn get_bt_fp16_cache(b: &FlatMatrix, caches: &CacheContext, rounding: Fp16Rounding) -> Arc<AlignedBufferF32> {
+    // Keyed by the rounding mode too, so switching modes repacks B
+    caches.fp16.get_or_pack(b, rounding, || pack_b_fp16(b, rounding)).0
+}
+
+/// Bᵀ (16×k) of row-major B, truncated to bf16
+fn pack_b_bf16(b: &FlatMatrix) -> AlignedBufferF32 {
+    let k = b.rows;
+    let mut buf = AlignedBufferF32::new(16 * k, 64);
+    let b_ptr = b.data.as_ptr();
     unsafe {
-        let mut acc = vdupq_n_s32(0);
-        let mut p = 0usize;
-        while p + 16 <= len {
-            let av = vld1q_s8(a.add(p));
-            let bv = vld1q_s8(b.add(p));
-            let prod_low = vmull_s8(vget_low_s8(av), vget_low_s8(bv));
-            let prod_high = vmull_s8(vget_high_s8(av), vget_high_s8(bv));
-            let sum_low = vpaddlq_s16(prod_low);
-            let sum_high = vpaddlq_s16(prod_high);
-            acc = vaddq_s32(acc, vaddq_s32(sum_low, sum_high));
-            p += 16;
-        }
-        let acc_low = vget_low_s32(acc);
-        let acc_high = vget_high_s32(acc);
-        let sum2 = vadd_s32(acc_low, acc_high);
-        let sum1 = vpadd_s32(sum2, sum2);
-        let mut total = vget_lane_s32(sum1, 0);
-        while p < len {
-            total += (*a.add(p) as i32) * (*b.add(p) as i32);
-            p += 1;
+        for p in 0..k {
+            let b_base = p * 16;
+            for j in 0..16 {
+                let val = *b_ptr.add(b_base + j);
+                *buf.as_mut_ptr().add(j * k + p) = round_bf16(val);
+            }
         }
-        total
     }
-    #[cfg(not(target_arch = "aarch64"))]
-    unsafe {
-        let mut total = 0i32;
-        let mut p = 0usize;
-        while p < len {
-            total += (*a.add(p) as i32) * (*b.add(p) as i32);
-            p += 1;
-        }
-        total
+    buf
+}
+
+#[inline(always)]
+fn get_bt_bf16_cache(b: &FlatMatrix, caches: &CacheContext) -> Arc<AlignedBufferF32> {
+    caches.bf16.get_or_pack(b, (), || pack_b_bf16(b)).0
+}
+
+/// int8's packed Bᵀ for row-major B, and whether the in-process or on-disk cache had it
+#[inline(always)]
+fn get_bt_i8_cache(b: &FlatMatrix, caches: &CacheContext, pack_cache_dir: Option<&std::path::Path>) -> (Arc<PackedBtI8>, bool) {
+    let k = b.rows;
+    let disk_hit = std::cell::Cell::new(false);
+    let pack = || {
+        let pack = || pack_b_int8_transposed(b);
+        let (buf, scale) = match pack_cache_dir {
+            #[cfg(feature = "blake3-hash")]
+            Some(dir) => {
+                let (buf, scale, hit) = pack_cache::load_or_pack_i8(dir, pack_cache::PackKind::Int8Transposed, b, pack);
+                disk_hit.set(hit);
+                (buf, scale)
+            }
+            _ => pack(),
+        };
+        let col_sums = column_sums_i8(buf.as_slice(), k);
+        PackedBtI8 { buf, scale, col_sums }
+    };
+    let (packed, hit) = caches.int8.get_or_pack(b, (), pack);
+    (packed, hit || disk_hit.get())
+}
+
+#[cfg(feature = "fp16")]
+#[inline(always)]
+fn dot_f32(a: *const f32, b: *const f32, len: usize) -> f32 {
+    unsafe { (simd::paths().dot_f32)(a, b, len) }
+}
+
+/// Four multiply-add lanes, reduced pairwise, then the tail in order
+#[cfg(all(feature = "fp16", target_arch = "aarch64"))]
+unsafe fn dot_f32_neon(a: *const f32, b: *const f32, len: usize) -> f32 {
+    let mut acc = vdupq_n_f32(0.0);
+    let mut p = 0usize;
+    while p + 4 <= len {
+        let av = vld1q_f32(a.add(p));
+        let bv = vld1q_f32(b.add(p));
+        acc = vmlaq_f32(acc, av, bv);
+        p += 4;
     }
+    let acc_low = vget_low_f32(acc);
+    let acc_high = vget_high_f32(acc);
+    let sum2 = vadd_f32(acc_low, acc_high);
+    let sum1 = vpadd_f32(sum2, sum2);
+    let mut total = vget_lane_f32(sum1, 0);
+    while p < len {
+        total += *a.add(p) * *b.add(p);
+        p += 1;
+    }
+    total
 }
 
-// Internal representation: flat Vec<f32> with dimensions
-// Serializes/deserializes as Vec<Vec<f32>> for JSON compatibility
-#[derive(Debug, Clone)]
+#[cfg(feature = "fp16")]
+unsafe fn dot_f32_scalar(a: *const f32, b: *const f32, len: usize) -> f32 {
+    let mut total = 0.0f32;
+    let mut p = 0usize;
+    while p < len {
+        total += *a.add(p) * *b.add(p);
+        p += 1;
+    }
+    total
+}
+
+#[inline(always)]
+fn dot_i8(a: *const i8, b: *const i8, len: usize) -> i32 {
+    unsafe { (simd::paths().dot_i8)(a, b, len) }
+}
+
+/// 16 bytes per step, multiplied widening into i16 and pairwise added into i32 lanes
+#[cfg(target_arch = "aarch64")]
+unsafe fn dot_i8_neon(a: *const i8, b: *const i8, len: usize) -> i32 {
+    let mut acc = vdupq_n_s32(0);
+    let mut p = 0usize;
+    while p + 16 <= len {
+        let av = vld1q_s8(a.add(p));
+        let bv = vld1q_s8(b.add(p));
+        let prod_low = vmull_s8(vget_low_s8(av), vget_low_s8(bv));
+        let prod_high = vmull_s8(vget_high_s8(av), vget_high_s8(bv));
+        let sum_low = vpaddlq_s16(prod_low);
+        let sum_high = vpaddlq_s16(prod_high);
+        acc = vaddq_s32(acc, vaddq_s32(sum_low, sum_high));
+        p += 16;
+    }
+    let acc_low = vget_low_s32(acc);
+    let acc_high = vget_high_s32(acc);
+    let sum2 = vadd_s32(acc_low, acc_high);
+    let sum1 = vpadd_s32(sum2, sum2);
+    let mut total = vget_lane_s32(sum1, 0);
+    while p < len {
+        total += (*a.add(p) as i32) * (*b.add(p) as i32);
+        p += 1;
+    }
+    total
+}
+
+unsafe fn dot_i8_scalar(a: *const i8, b: *const i8, len: usize) -> i32 {
+    let mut total = 0i32;
+    let mut p = 0usize;
+    while p < len {
+        total += (*a.add(p) as i32) * (*b.add(p) as i32);
+        p += 1;
+    }
+    total
+}
+
+/// 16 bytes per step, widened to i16 and multiplied pairwise into i32 lanes (vpmaddwd), so
+/// every product and pair sum is exact and the result equals the scalar loop's
+#[cfg(target_arch = "x86_64")]
+#[target_feature(enable = "avx2")]
+unsafe fn dot_i8_avx2(a: *const i8, b: *const i8, len: usize) -> i32 {
+    let mut acc = _mm256_setzero_si256();
+    let mut p = 0usize;
+    while p + 16 <= len {
+        let av = _mm256_cvtepi8_epi16(_mm_loadu_si128(a.add(p) as *const __m128i));
+        let bv = _mm256_cvtepi8_epi16(_mm_loadu_si128(b.add(p) as *const __m128i));
+        acc = _mm256_add_epi32(acc, _mm256_madd_epi16(av, bv));
+        p += 16;
+    }
+    let mut total = hsum_epi32_avx2(acc);
+    while p < len {
+        total += (*a.add(p) as i32) * (*b.add(p) as i32);
+        p += 1;
+    }
+    total
+}
+
+/// VPDPBUSD multiplies unsigned by signed bytes, so A is offset to `a + 128` (flipping its
+/// sign bit) and the offset's share, `128 · Σb`, taken off again; Σb comes from a second
+/// VPDPBUSD against ones. The 64-byte body is exact, the rest runs on the AVX2 path.
+#[cfg(target_arch = "x86_64")]
+#[target_feature(enable = "avx512f,avx512vnni,avx2")]
+unsafe fn dot_i8_vnni(a: *const i8, b: *const i8, len: usize) -> i32 {
+    let (flip, ones) = (_mm512_set1_epi8(i8::MIN), _mm512_set1_epi8(1));
+    let (mut acc, mut b_sum) = (_mm512_setzero_si512(), _mm512_setzero_si512());
+    let mut p = 0usize;
+    while p + 64 <= len {
+        let av = _mm512_xor_si512(_mm512_loadu_si512(a.add(p) as *const __m512i), flip);
+        let bv = _mm512_loadu_si512(b.add(p) as *const __m512i);
+        acc = _mm512_dpbusd_epi32(acc, av, bv);
+        b_sum = _mm512_dpbusd_epi32(b_sum, ones, bv);
+        p += 64;
+    }
+    let body = _mm512_reduce_add_epi32(acc).wrapping_sub(_mm512_reduce_add_epi32(b_sum).wrapping_mul(128));
+    body.wrapping_add(dot_i8_avx2(a.add(p), b.add(p), len - p))
+}
+
+/// `dot_u8i8` on VPDPBUSD directly; the rest after the 64-byte body runs on the AVX2 path
+#[cfg(target_arch = "x86_64")]
+#[target_feature(enable = "avx512f,avx512vnni,avx2")]
+unsafe fn dot_u8i8_vnni(a: *const u8, b: *const i8, len: usize) -> i32 {
+    let mut acc = _mm512_setzero_si512();
+    let mut p = 0usize;
+    while p + 64 <= len {
+        let av = _mm512_loadu_si512(a.add(p) as *const __m512i);
+        acc = _mm512_dpbusd_epi32(acc, av, _mm512_loadu_si512(b.add(p) as *const __m512i));
+        p += 64;
+    }
+    _mm512_reduce_add_epi32(acc).wrapping_add(dot_u8i8_avx2(a.add(p), b.add(p), len - p))
+}
+
+/// `dot_i8` with the first operand unsigned (int8_asym's u8 A codes)
+#[inline(always)]
+fn dot_u8i8(a: *const u8, b: *const i8, len: usize) -> i32 {
+    unsafe { (simd::paths().dot_u8i8)(a, b, len) }
+}
+
+/// `dot_u8i8` of any length: slices of at most `U8I8_SAFE_K`, whose i32 sums cannot
+/// overflow, added up in i64
+#[inline(always)]
+fn dot_u8i8_wide(a: *const u8, b: *const i8, len: usize) -> i64 {
+    (0..len).step_by(U8I8_SAFE_K).map(|p| dot_u8i8(unsafe { a.add(p) }, unsafe { b.add(p) }, (len - p).min(U8I8_SAFE_K)) as i64).sum()
+}
+
+/// 16 bytes per step, both operands widened to i16 and multiply-accumulated into i32 lanes
+#[cfg(target_arch = "aarch64")]
+unsafe fn dot_u8i8_neon(a: *const u8, b: *const i8, len: usize) -> i32 {
+    let mut acc = vdupq_n_s32(0);
+    let mut p = 0usize;
+    while p + 16 <= len {
+        let av = vld1q_u8(a.add(p));
+        let bv = vld1q_s8(b.add(p));
+        let a_low = vreinterpretq_s16_u16(vmovl_u8(vget_low_u8(av)));
+        let a_high = vreinterpretq_s16_u16(vmovl_u8(vget_high_u8(av)));
+        let b_low = vmovl_s8(vget_low_s8(bv));
+        let b_high = vmovl_s8(vget_high_s8(bv));
+        acc = vmlal_s16(acc, vget_low_s16(a_low), vget_low_s16(b_low));
+        acc = vmlal_s16(acc, vget_high_s16(a_low), vget_high_s16(b_low));
+        acc = vmlal_s16(acc, vget_low_s16(a_high), vget_low_s16(b_high));
+        acc = vmlal_s16(acc, vget_high_s16(a_high), vget_high_s16(b_high));
+        p += 16;
+    }
+    let acc_low = vget_low_s32(acc);
+    let acc_high = vget_high_s32(acc);
+    let sum2 = vadd_s32(acc_low, acc_high);
+    let sum1 = vpadd_s32(sum2, sum2);
+    let mut total = vget_lane_s32(sum1, 0);
+    while p < len {
+        total += (*a.add(p) as i32) * (*b.add(p) as i32);
+        p += 1;
+    }
+    total
+}
+
+unsafe fn dot_u8i8_scalar(a: *const u8, b: *const i8, len: usize) -> i32 {
+    let mut total = 0i32;
+    let mut p = 0usize;
+    while p < len {
+        total += (*a.add(p) as i32) * (*b.add(p) as i32);
+        p += 1;
+    }
+    total
+}
+
+/// `dot_i8_avx2` with A's bytes zero-extended instead of sign-extended
+#[cfg(target_arch = "x86_64")]
+#[target_feature(enable = "avx2")]
+unsafe fn dot_u8i8_avx2(a: *const u8, b: *const i8, len: usize) -> i32 {
+    let mut acc = _mm256_setzero_si256();
+    let mut p = 0usize;
+    while p + 16 <= len {
+        let av = _mm256_cvtepu8_epi16(_mm_loadu_si128(a.add(p) as *const __m128i));
+        let bv = _mm256_cvtepi8_epi16(_mm_loadu_si128(b.add(p) as *const __m128i));
+        acc = _mm256_add_epi32(acc, _mm256_madd_epi16(av, bv));
+        p += 16;
+    }
+    let mut total = hsum_epi32_avx2(acc);
+    while p < len {
+        total += (*a.add(p) as i32) * (*b.add(p) as i32);
+        p += 1;
+    }
+    total
+}
+
+/// SDOT: each i32 lane of `acc` adds the four i8·i8 products of its bytes in `a` and `b`.
+/// This is `vdotq_s32`, which std only offers on nightly.
+#[cfg(target_arch = "aarch64")]
+#[target_feature(enable = "neon,dotprod")]
+#[inline]
+unsafe fn sdot(mut acc: int32x4_t, a: int8x16_t, b: int8x16_t) -> int32x4_t {
+    std::arch::asm!("sdot {0:v}.4s, {1:v}.16b, {2:v}.16b", inout(vreg) acc, in(vreg) a, in(vreg) b, options(pure, nomem, nostack));
+    acc
+}
+
+/// USMMLA: `acc`, a row-major 2×2 i32 block, adds the product of `a` (two rows of eight u8)
+/// and the transpose of `b` (two rows of eight i8, i.e. two columns of B). Like SDOT this
+/// is an intrinsic (`vusmmlaq_s32`) std only offers on nightly.
+#[cfg(target_arch = "aarch64")]
+#[target_feature(enable = "neon,i8mm")]
+#[inline]
+unsafe fn usmmla(mut acc: int32x4_t, a: uint8x16_t, b: int8x16_t) -> int32x4_t {
+    std::arch::asm!("usmmla {0:v}.4s, {1:v}.16b, {2:v}.16b", inout(vreg) acc, in(vreg) a, in(vreg) b, options(pure, nomem, nostack));
+    acc
+}
+
+/// 16 bytes per SDOT; four products of at most 128·128 sum exactly in a lane, so the result
+/// equals the scalar loop's
+#[cfg(target_arch = "aarch64")]
+#[target_feature(enable = "neon,dotprod")]
+unsafe fn dot_i8_dotprod(a: *const i8, b: *const i8, len: usize) -> i32 {
+    let mut acc = vdupq_n_s32(0);
+    let mut p = 0usize;
+    while p + 16 <= len {
+        acc = sdot(acc, vld1q_s8(a.add(p)), vld1q_s8(b.add(p)));
+        p += 16;
+    }
+    let mut total = vaddvq_s32(acc);
+    while p < len {
+        total += (*a.add(p) as i32) * (*b.add(p) as i32);
+        p += 1;
+    }
+    total
+}
+
+/// SDOT takes signed bytes only, so A is offset to `a - 128` (flipping its sign bit) and
+/// the offset's share, `128 · Σb`, added back; Σb comes from a second SDOT against ones.
+/// (USDOT would take A as is, but needs i8mm, which not every dotprod core has.)
+#[cfg(target_arch = "aarch64")]
+#[target_feature(enable = "neon,dotprod")]
+unsafe fn dot_u8i8_dotprod(a: *const u8, b: *const i8, len: usize) -> i32 {
+    let (flip, ones) = (vdupq_n_u8(0x80), vdupq_n_s8(1));
+    let (mut acc, mut b_sum) = (vdupq_n_s32(0), vdupq_n_s32(0));
+    let mut p = 0usize;
+    while p + 16 <= len {
+        let av = vreinterpretq_s8_u8(veorq_u8(vld1q_u8(a.add(p)), flip));
+        let bv = vld1q_s8(b.add(p));
+        acc = sdot(acc, av, bv);
+        b_sum = sdot(b_sum, ones, bv);
+        p += 16;
+    }
+    let mut total = vaddvq_s32(acc).wrapping_add(vaddvq_s32(b_sum).wrapping_mul(128));
+    while p < len {
+        total += (*a.add(p) as i32) * (*b.add(p) as i32);
+        p += 1;
+    }
+    total
+}
+
+/// Sum of the eight i32 lanes (wrapping, like the scalar loops' release-mode adds)
+#[cfg(target_arch = "x86_64")]
+#[target_feature(enable = "avx2")]
+unsafe fn hsum_epi32_avx2(v: __m256i) -> i32 {
+    let sum4 = _mm_add_epi32(_mm256_castsi256_si128(v), _mm256_extracti128_si256(v, 1));
+    let sum2 = _mm_add_epi32(sum4, _mm_unpackhi_epi64(sum4, sum4));
+    let sum1 = _mm_add_epi32(sum2, _mm_shuffle_epi32(sum2, 1));
+    _mm_cvtsi128_si32(sum1)
+}
+
+/// Which path the integer dot products and the u8i8 16x16 inner loop take in this process:
+/// "avx512vnni", "avx2", "i8mm", "dotprod", "neon" or "scalar" (recorded as
+/// `OutputMetadata::simd_variant`; see simd.rs)
+pub(crate) fn int_dot_variant() -> &'static str {
+    simd::active().as_str()
+}
+
+/// A row-major matrix whose `data` holds exactly `rows * cols` elements.
+///
+/// Build one with `new`, `from_nested`, `zeros` or `from_fn`, which keep that invariant.
+/// The fields stay public for existing callers, but a struct literal is discouraged: one
+/// whose `data` is the wrong length is refused by `compute_workload` and friends before
+/// any kernel reads it.
+#[derive(Debug, Clone, Default)]
 pub struct FlatMatrix {
     pub data: Vec<f32>,
     pub rows: usize,
     pub cols: usize,
 }
 
-// Custom deserializer: JSON Vec<Vec<f32>> → FlatMatrix (direct flattening, no intermediate Vec<Vec>)
+impl FlatMatrix {
+    /// `data` as a `rows`×`cols` matrix, if it holds exactly `rows * cols` elements
+    pub fn new(data: Vec<f32>, rows: usize, cols: usize) -> Result<FlatMatrix, MatrixError> {
+        let matrix = FlatMatrix { data, rows, cols };
+        matrix.check()?;
+        Ok(matrix)
+    }
+
+    /// One matrix from its rows, which must all be the same length (no rows is 0×0)
+    pub fn from_nested(rows: Vec<Vec<f32>>) -> Result<FlatMatrix, MatrixError> {
+        let cols = rows.first().map_or(0, Vec::len);
+        let mut data = Vec::with_capacity(rows.len().saturating_mul(cols));
+        for (row, values) in rows.iter().enumerate() {
+            if values.len() != cols {
+                return Err(MatrixError::RaggedRow { row, len: values.len(), cols });
+            }
+            data.extend_from_slice(values);
+        }
+        Ok(FlatMatrix { data, rows: rows.len(), cols })
+    }
+
+    /// A `rows`×`cols` matrix of 0.0; panics if `rows * cols` overflows, as `vec!` does
+    /// when the allocation is too large
+    pub fn zeros(rows: usize, cols: usize) -> FlatMatrix {
+        FlatMatrix { data: vec![0.0; Self::elements(rows, cols)], rows, cols }
+    }
+
+    /// A `rows`×`cols` matrix whose element (i, j) is `f(i, j)`, called in row-major order;
+    /// panics like `zeros` if `rows * cols` overflows
+    pub fn from_fn(rows: usize, cols: usize, mut f: impl FnMut(usize, usize) -> f32) -> FlatMatrix {
+        let mut data = Vec::with_capacity(Self::elements(rows, cols));
+        for i in 0..rows {
+            data.extend((0..cols).map(|j| f(i, j)));
+        }
+        FlatMatrix { data, rows, cols }
+    }
+
+    fn elements(rows: usize, cols: usize) -> usize {
+        rows.checked_mul(cols).unwrap_or_else(|| panic!("{}", MatrixError::TooLarge { rows, cols }))
+    }
+
+    /// Whether `data` holds exactly `rows * cols` elements, as every kernel assumes
+    pub fn check(&self) -> Result<(), MatrixError> {
+        let (rows, cols) = (self.rows, self.cols);
+        match rows.checked_mul(cols) {
+            None => Err(MatrixError::TooLarge { rows, cols }),
+            Some(elements) if elements != self.data.len() => Err(MatrixError::LengthMismatch { len: self.data.len(), rows, cols }),
+            Some(_) => Ok(()),
+        }
+    }
+}
+
+// Custom deserializer: JSON Vec<Vec<f32>> → FlatMatrix (rows are appended straight into the
+// flat buffer, no intermediate Vec<Vec>). Each element is read as its JSON token type so
+// lossy conversions can be reported (see numbers.rs); the f32 values are unchanged. Under
+// `numbers::with_row_format` a row may also be an object keyed by column index.
 impl<'de> Deserialize<'de> for FlatMatrix {
     fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
     where
         D: Deserializer<'de>,
     {
-        let nested: Vec<Vec<f32>> = Vec::deserialize(deserializer)?;
-        let rows = nested.len();
-        if rows == 0 {
-            return Ok(FlatMatrix {
-                data: Vec::new(),
-                rows: 0,
-                cols: 0,
-            });
-        }
-        let cols = nested[0].len();
+        struct MatrixVisitor;
         
-        // Directly flatten during deserialization - single allocation!
-        let mut data = Vec::with_capacity(rows * cols);
-        for row in nested {
-            if row.len() != cols {
-                return Err(serde::de::Error::custom("Inconsistent row lengths"));
+        impl<'de> serde::de::Visitor<'de> for MatrixVisitor {
+            type Value = FlatMatrix;
+            
+            fn expecting(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+                f.write_str("an array of equal-length numeric rows")
+            }
+            
+            fn visit_seq<A: serde::de::SeqAccess<'de>>(self, mut seq: A) -> Result<FlatMatrix, A::Error> {
+                let limits = numbers::active_limits();
+                let format = numbers::active_row_format();
+                let mut data = Vec::new();
+                let mut rows = 0;
+                let mut cols = None;
+                loop {
+                    let seed = RowSeed { data: &mut data, row: rows, cols, limits, format };
+                    let Some(len) = seq.next_element_seed(seed)? else { break };
+                    match cols {
+                        None => cols = Some(len),
+                        Some(c) if c != len => return Err(serde::de::Error::custom("Inconsistent row lengths")),
+                        _ => {}
+                    }
+                    rows += 1;
+                }
+                Ok(FlatMatrix { data, rows, cols: cols.unwrap_or(0) })
             }
-            data.extend_from_slice(&row);
         }
         
-        Ok(FlatMatrix { data, rows, cols })
+        deserializer.deserialize_seq(MatrixVisitor)
+    }
+}
+
+/// Appends one JSON row to the flat buffer, returning its length. Refuses the row, or its
+/// next element, once `limits` would be exceeded. `cols` is the width set by the rows
+/// before it, which a map row is padded to.
+struct RowSeed<'a> {
+    data: &'a mut Vec<f32>,
+    row: usize,
+    cols: Option<usize>,
+    limits: numbers::MatrixLimits,
+    format: numbers::InputRowFormat,
+}
+
+impl RowSeed<'_> {
+    fn check_room<E: serde::de::Error>(&self, col: usize) -> Result<(), E> {
+        if col >= self.limits.max_cols {
+            return Err(numbers::limit_exceeded(numbers::MatrixLimit::MaxCols, self.limits.max_cols, self.row));
+        }
+        if self.data.len() >= self.limits.max_elements {
+            return Err(numbers::limit_exceeded(numbers::MatrixLimit::MaxElements, self.limits.max_elements, self.row));
+        }
+        Ok(())
+    }
+}
+
+impl<'de> serde::de::DeserializeSeed<'de> for RowSeed<'_> {
+    type Value = usize;
+    
+    fn deserialize<D: Deserializer<'de>>(self, deserializer: D) -> Result<usize, D::Error> {
+        if self.row >= self.limits.max_rows {
+            return Err(numbers::limit_exceeded(numbers::MatrixLimit::MaxRows, self.limits.max_rows, self.row));
+        }
+        match self.format {
+            numbers::InputRowFormat::Array => deserializer.deserialize_seq(self),
+            numbers::InputRowFormat::Map | numbers::InputRowFormat::MapStrict => deserializer.deserialize_any(self),
+        }
+    }
+}
+
+impl<'de> serde::de::Visitor<'de> for RowSeed<'_> {
+    type Value = usize;
+    
+    fn expecting(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self.format {
+            numbers::InputRowFormat::Array => f.write_str("an array of numbers"),
+            _ => f.write_str("an array of numbers or an object keyed by column index"),
+        }
+    }
+    
+    fn visit_seq<A: serde::de::SeqAccess<'de>>(self, mut seq: A) -> Result<usize, A::Error> {
+        let mut col = 0;
+        while let Some(number) = seq.next_element::<numbers::JsonNumber>()? {
+            self.check_room(col)?;
+            self.data.push(number.to_f32(self.row, col));
+            col += 1;
+        }
+        Ok(col)
+    }
+
+    // Only reached under a map row format: keys must be "0", "1", ... in order, and the
+    // columns after the last one are filled with 0.0 (or refused by MapStrict)
+    fn visit_map<A: serde::de::MapAccess<'de>>(self, mut map: A) -> Result<usize, A::Error> {
+        use serde::de::Error;
+        let row = self.row;
+        let mut col = 0;
+        while let Some(key) = map.next_key::<String>()? {
+            let index = key
+                .parse::<usize>()
+                .ok()
+                .filter(|i| i.to_string() == key)
+                .ok_or_else(|| A::Error::custom(format!("Row {}: key \"{}\" is not a column index", row, key)))?;
+            if let Some(cols) = self.cols.filter(|&c| index >= c) {
+                return Err(A::Error::custom(format!("Row {}: column {} is out of range for {} columns", row, index, cols)));
+            }
+            if index != col {
+                return Err(A::Error::custom(format!(
+                    "Row {}: column {} where column {} was expected (keys must run 0, 1, 2, ... in order)",
+                    row, index, col
+                )));
+            }
+            let number = map.next_value::<numbers::JsonNumber>()?;
+            self.check_room(col)?;
+            self.data.push(number.to_f32(row, col));
+            col += 1;
+        }
+        let width = self.cols.unwrap_or(col);
+        if col < width && self.format == numbers::InputRowFormat::MapStrict {
+            return Err(A::Error::custom(format!("Row {}: missing column {} of {}", row, col, width)));
+        }
+        for col in col..width {
+            self.check_room(col)?;
+            self.data.push(0.0);
+        }
+        Ok(width)
     }
 }
 
-// Custom serializer: FlatMatrix → JSON Vec<Vec<f32>> (only for output serialization)
+// Custom serializer: FlatMatrix → JSON Vec<Vec<f32>>, written straight from the flat buffer
+// a row at a time. While `fused::write_json` is writing an Output, each row of its result is
+// also fed to the result hasher just before it is serialized.
 impl Serialize for FlatMatrix {
     fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
     where
         S: Serializer,
     {
-        // Convert to Vec<Vec<f32>> only for serialization (not during computation)
-        let mut nested = Vec::with_capacity(self.rows);
+        use serde::ser::SerializeSeq;
+        let mut seq = serializer.serialize_seq(Some(self.rows))?;
         for i in 0..self.rows {
-            let start = i * self.cols;
-            let end = start + self.cols;
-            nested.push(self.data[start..end].to_vec());
+            let row = &self.data[i * self.cols..(i + 1) * self.cols];
+            fused::feed_row(self, row);
+            seq.serialize_element(row)?;
         }
-        nested.serialize(serializer)
+        seq.end()
     }
 }
 
@@ -352,62 +1000,256 @@ impl Serialize for FlatMatrix {
 /// Returns: (matrix_a as FlatMatrix, matrix_b as FlatMatrix)
 /// 
 /// For seed dimensions: matrix_a is 16×50240 (u8 bytes), matrix_b is 50240×16 (i8 bytes)
+/// 
+/// This is `seed_layout` applied to `generate_seed_bytes`; see `SeedLayout` for the byte map
+#[cfg(any(feature = "seed-gen", test))]
 pub fn generate_matrices_from_seed(seed: &[u8], rows_a: usize, cols_a: usize, rows_b: usize, cols_b: usize) -> (FlatMatrix, FlatMatrix) {
-    // Calculate total bytes needed
-    let matrix_a_bytes = rows_a * cols_a;
-    let matrix_b_bytes = rows_b * cols_b;
-    let total_bytes = matrix_a_bytes + matrix_b_bytes;
-    
-    // Use Blake3 XOF to generate deterministic random bytes
-    let mut hasher = blake3::Hasher::new();
-    hasher.update(seed);
-    let mut output_reader = hasher.finalize_xof();
-    
-    // Read all bytes needed for both matrices
-    let mut all_bytes = vec![0u8; total_bytes];
-    output_reader.fill(&mut all_bytes);
-    
-    // Split bytes: first part for matrix_a (u8), second part for matrix_b (i8)
-    let (a_bytes, b_bytes) = all_bytes.split_at(matrix_a_bytes);
-    
-    // Convert matrix_a bytes to f32 (u8: 0-255)
-    // For u8i8, we'll interpret these as u8 directly in the matmul function
-    let matrix_a_data: Vec<f32> = a_bytes.iter().map(|&b| b as f32).collect();
-    
-    // Convert matrix_b bytes to f32 (i8: -128 to 127)
-    // Raw bytes are 0-255, but we interpret as i8 by subtracting 128
-    let matrix_b_data: Vec<f32> = b_bytes.iter().map(|&b| (b.wrapping_sub(128)) as i8 as f32).collect();
-    
-    (
-        FlatMatrix { data: matrix_a_data, rows: rows_a, cols: cols_a },
-        FlatMatrix { data: matrix_b_data, rows: rows_b, cols: cols_b },
-    )
+    let layout = seed_layout((rows_a, cols_a), (rows_b, cols_b));
+    layout.decode(&generate_seed_bytes(seed, layout.total_len()))
 }
 
-/// Generate matrices from seed hex string (convenience function)
-pub fn generate_matrices_from_seed_hex(seed_hex: &str, rows_a: usize, cols_a: usize, rows_b: usize, cols_b: usize) -> Result<(FlatMatrix, FlatMatrix), String> {
-    let seed_bytes = hex::decode(seed_hex)
-        .map_err(|e| format!("Invalid hex seed: {}", e))?;
-    Ok(generate_matrices_from_seed(&seed_bytes, rows_a, cols_a, rows_b, cols_b))
+/// How a seed byte becomes a matrix element
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ByteInterpretation {
+    /// The byte as an unsigned value, 0..=255
+    U8,
+    /// `byte.wrapping_sub(128) as i8`, i.e. -128..=127 with 0x80 → 0
+    OffsetI8,
 }
 
-pub mod types {
-    pub use super::FlatMatrix;
-    pub use serde::{Deserialize, Serialize};
-    
-    #[derive(Debug, Clone, Deserialize)]
-    pub struct Input {
-        // MatMul fields - stored as FlatMatrix internally
+impl ByteInterpretation {
+    #[inline]
+    pub fn to_f32(self, byte: u8) -> f32 {
+        match self {
+            ByteInterpretation::U8 => byte as f32,
+            ByteInterpretation::OffsetI8 => byte.wrapping_sub(128) as i8 as f32,
+        }
+    }
+
+    #[inline]
+    pub fn to_i32(self, byte: u8) -> i32 {
+        match self {
+            ByteInterpretation::U8 => byte as i32,
+            ByteInterpretation::OffsetI8 => byte.wrapping_sub(128) as i8 as i32,
+        }
+    }
+}
+
+/// Where each matrix lives in the seed's XOF stream. Both matrices are row-major and
+/// one byte per element; A comes first, B immediately after it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SeedLayout {
+    pub a_shape: (usize, usize),
+    pub b_shape: (usize, usize),
+    pub a_offset: usize,
+    pub a_len: usize,
+    pub a_interpretation: ByteInterpretation,
+    pub b_offset: usize,
+    pub b_len: usize,
+    pub b_interpretation: ByteInterpretation,
+}
+
+impl SeedLayout {
+    /// XOF bytes consumed by both matrices
+    pub fn total_len(&self) -> usize {
+        self.b_offset + self.b_len
+    }
+
+    /// Build the matrices from XOF bytes (at least `total_len` of them)
+    pub fn decode(&self, bytes: &[u8]) -> (FlatMatrix, FlatMatrix) {
+        let (mut a, mut b) = (FlatMatrix::default(), FlatMatrix::default());
+        self.decode_into(bytes, &mut a, &mut b);
+        (a, b)
+    }
+
+    /// Same as `decode`, but reuses the allocations of `matrix_a` and `matrix_b`
+    pub fn decode_into(&self, bytes: &[u8], matrix_a: &mut FlatMatrix, matrix_b: &mut FlatMatrix) {
+        let fill = |m: &mut FlatMatrix, offset: usize, len: usize, shape: (usize, usize), interpretation: ByteInterpretation| {
+            m.data.clear();
+            m.data.extend(bytes[offset..offset + len].iter().map(|&b| interpretation.to_f32(b)));
+            (m.rows, m.cols) = shape;
+        };
+        fill(matrix_a, self.a_offset, self.a_len, self.a_shape, self.a_interpretation);
+        fill(matrix_b, self.b_offset, self.b_len, self.b_shape, self.b_interpretation);
+    }
+}
+
+/// Dimensions of seed-generated matrices: A is m×k, B is k×n
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct SeedDims {
+    pub m: usize,
+    pub k: usize,
+    pub n: usize,
+}
+
+impl Default for SeedDims {
+    /// The PoW seed shape, 16×50240 × 50240×16
+    fn default() -> Self {
+        Self { m: 16, k: 50240, n: 16 }
+    }
+}
+
+/// Layout of the seed stream for A (`a_shape`) and B (`b_shape`)
+pub fn seed_layout(a_shape: (usize, usize), b_shape: (usize, usize)) -> SeedLayout {
+    let a_len = a_shape.0 * a_shape.1;
+    SeedLayout {
+        a_shape,
+        b_shape,
+        a_offset: 0,
+        a_len,
+        a_interpretation: ByteInterpretation::U8,
+        b_offset: a_len,
+        b_len: b_shape.0 * b_shape.1,
+        b_interpretation: ByteInterpretation::OffsetI8,
+    }
+}
+
+#[cfg(any(feature = "seed-gen", test))]
+fn seed_xof(seed: &[u8]) -> blake3::OutputReader {
+    let mut hasher = blake3::Hasher::new();
+    hasher.update(seed);
+    hasher.finalize_xof()
+}
+
+/// The first `total_len` bytes of the seed's Blake3 XOF stream
+#[cfg(any(feature = "seed-gen", test))]
+pub fn generate_seed_bytes(seed: &[u8], total_len: usize) -> Vec<u8> {
+    let mut bytes = vec![0u8; total_len];
+    seed_xof(seed).fill(&mut bytes);
+    bytes
+}
+
+/// Same as `generate_matrices_from_seed`, but fills existing matrices so repeated
+/// generation (benchmarks, profiling) reuses their allocations
+#[cfg(any(feature = "seed-gen", test))]
+pub fn generate_matrices_from_seed_into(
+    seed: &[u8],
+    rows_a: usize,
+    cols_a: usize,
+    rows_b: usize,
+    cols_b: usize,
+    matrix_a: &mut FlatMatrix,
+    matrix_b: &mut FlatMatrix,
+) {
+    let layout = seed_layout((rows_a, cols_a), (rows_b, cols_b));
+    let mut output_reader = seed_xof(seed);
+    
+    // Stream the XOF in fixed chunks instead of materializing generate_seed_bytes;
+    // A and B are contiguous, so reading them in order follows the layout
+    let mut chunk = [0u8; 4096];
+    let mut fill = |dst: &mut Vec<f32>, len: usize, interpretation: ByteInterpretation| {
+        dst.clear();
+        dst.reserve(len);
+        let mut remaining = len;
+        while remaining > 0 {
+            let take = remaining.min(chunk.len());
+            output_reader.fill(&mut chunk[..take]);
+            dst.extend(chunk[..take].iter().map(|&b| interpretation.to_f32(b)));
+            remaining -= take;
+        }
+    };
+    
+    fill(&mut matrix_a.data, layout.a_len, layout.a_interpretation);
+    fill(&mut matrix_b.data, layout.b_len, layout.b_interpretation);
+    
+    matrix_a.rows = rows_a;
+    matrix_a.cols = cols_a;
+    matrix_b.rows = rows_b;
+    matrix_b.cols = cols_b;
+}
+
+/// Seed derivation for nonce searches, where every candidate seed is `prefix || nonce`.
+///
+/// The Blake3 state after absorbing the prefix is kept and cloned for each nonce instead of
+/// re-hashing the prefix, and the XOF buffer is reused between calls, as are the caller's
+/// matrices. `derive_into` produces exactly the matrices of
+/// `generate_matrices_from_seed(&[prefix, nonce].concat(), ..)`.
+#[cfg(any(feature = "seed-gen", test))]
+#[derive(Debug, Clone)]
+pub struct SeedPrefix {
+    hasher: blake3::Hasher,
+    layout: SeedLayout,
+    bytes: Vec<u8>,
+}
+
+#[cfg(any(feature = "seed-gen", test))]
+impl SeedPrefix {
+    pub fn new(prefix: &[u8], a_shape: (usize, usize), b_shape: (usize, usize)) -> Self {
+        let mut hasher = blake3::Hasher::new();
+        hasher.update(prefix);
+        let layout = seed_layout(a_shape, b_shape);
+        Self { hasher, layout, bytes: vec![0u8; layout.total_len()] }
+    }
+
+    pub fn layout(&self) -> &SeedLayout {
+        &self.layout
+    }
+
+    /// Fill `matrix_a` and `matrix_b` from the seed `prefix || nonce`
+    pub fn derive_into(&mut self, nonce: &[u8], matrix_a: &mut FlatMatrix, matrix_b: &mut FlatMatrix) {
+        let mut hasher = self.hasher.clone();
+        hasher.update(nonce);
+        hasher.finalize_xof().fill(&mut self.bytes);
+        self.layout.decode_into(&self.bytes, matrix_a, matrix_b);
+    }
+}
+
+/// Generate matrices from seed hex string (convenience function)
+#[cfg(any(feature = "seed-gen", test))]
+pub fn generate_matrices_from_seed_hex(seed_hex: &str, rows_a: usize, cols_a: usize, rows_b: usize, cols_b: usize) -> Result<(FlatMatrix, FlatMatrix), String> {
+    let seed_bytes = hex::decode(seed_hex)
+        .map_err(|e| format!("Invalid hex seed: {}", e))?;
+    Ok(generate_matrices_from_seed(&seed_bytes, rows_a, cols_a, rows_b, cols_b))
+}
+
+pub mod types {
+    pub use super::FlatMatrix;
+    pub use serde::{Deserialize, Serialize};
+    
+    /// One workload. `Default` leaves every optional field unset (and `precision` empty), so
+    /// a literal names only the fields it sets: `Input { matrix_a, matrix_b, precision,
+    /// ..Default::default() }`.
+    #[derive(Debug, Clone, Default, Serialize, Deserialize)]
+    pub struct Input {
+        // MatMul fields - stored as FlatMatrix internally
+        #[serde(deserialize_with = "crate::numbers::matrix_a")]
         pub matrix_a: FlatMatrix,
+        /// Not read by `workload_type: "quantize"`, which may omit it
+        #[serde(default, deserialize_with = "crate::numbers::matrix_b")]
         pub matrix_b: FlatMatrix,
+        /// B in CSR form, instead of matrix_b (fp32 matmul only, see sparse.rs)
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        pub matrix_b_sparse: Option<super::sparse::SparseMatrix>,
         
         // Optional workload type for future workloads
-        #[serde(default)]
+        #[serde(default, skip_serializing_if = "Option::is_none")]
         pub workload_type: Option<String>, // "matmul", "convolution", "attention", "inference"
         
-        pub precision: String, // "fp32", "fp16", "int8", "u8i8"
-        #[serde(default)]
+        pub precision: String, // "fp32", "fp32_strict", "fp64", "fp16", "bf16", "int8", "int8_rowwise", "int8_asym", "int4", "u8i8"
+        #[serde(default, skip_serializing_if = "Option::is_none")]
         pub metadata: Option<InputMetadata>,
+        /// Hash preimage: "f32le" (default) or "i32le" (u8i8 only, the exact i32 accumulators)
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        pub hash_input: Option<String>,
+        /// How matrix_a is stored: "row_major" (default) or "transposed" (Aᵀ, k×m)
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        pub a_layout: Option<String>,
+        /// How matrix_b is stored: "row_major" (default) or "transposed" (Bᵀ, n×k)
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        pub b_layout: Option<String>,
+        /// GEMM-style fp32 result `alpha * A·B + beta * matrix_c`; alpha defaults to 1 and
+        /// beta to 0, and matrix_c (a.rows × b.cols) is required when beta is nonzero
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        pub alpha: Option<f32>,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        pub beta: Option<f32>,
+        #[serde(default, deserialize_with = "crate::numbers::optional_matrix_c", skip_serializing_if = "Option::is_none")]
+        pub matrix_c: Option<FlatMatrix>,
+        /// `workload_type: "batched_matmul"`: how many m×k items matrix_a stacks row-wise,
+        /// each multiplied by the same matrix_b
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        pub batch: Option<usize>,
         
         // Future workload-specific fields will be added here when schemas are provided
         // For example:
@@ -415,22 +1257,83 @@ pub mod types {
         // pub attention_params: Option<AttentionParams>,
     }
     
+    impl Input {
+        /// Test fixture: a plain matmul of `matrix_a` and `matrix_b` at `precision`
+        #[cfg(test)]
+        pub(crate) fn matmul(matrix_a: FlatMatrix, matrix_b: FlatMatrix, precision: &str) -> Input {
+            Input { matrix_a, matrix_b, precision: precision.to_string(), ..Default::default() }
+        }
+
+        /// Put `precision` and `workload_type` in `normalize_name` form
+        pub fn normalize_names(&mut self) {
+            self.precision = super::normalize_name(&self.precision);
+            if let Some(workload_type) = &mut self.workload_type {
+                *workload_type = super::normalize_name(workload_type);
+            }
+        }
+
+        /// matrix_b, or matrix_b_sparse densified when B was supplied sparse
+        pub fn dense_b(&self) -> Result<std::borrow::Cow<'_, FlatMatrix>, super::SolverError> {
+            match &self.matrix_b_sparse {
+                Some(sparse) => {
+                    sparse.check()?;
+                    Ok(std::borrow::Cow::Owned(sparse.to_dense()))
+                }
+                None => Ok(std::borrow::Cow::Borrowed(&self.matrix_b)),
+            }
+        }
+    }
+    
     #[derive(Debug, Clone, Serialize, Deserialize)]
     pub struct InputMetadata {
         pub compiler_flags: Option<String>,
         pub libraries: Option<Vec<String>>,
+        /// false packs B for this request alone (`ComputeOptions::bypass_b_cache`)
         pub cache_enabled: Option<bool>,
     }
     
-    #[derive(Debug, Serialize)]
+    #[derive(Debug, Clone, Serialize, Deserialize)]
     pub struct Output {
-        pub result_matrix: FlatMatrix,
+        /// Shared rather than owned: clones of an Output (the job store, the response)
+        /// point at one buffer. Serializes as the plain matrix.
+        pub result_matrix: std::sync::Arc<FlatMatrix>,
+        /// Row-major f64 result of an fp64 run, which `result_hash` covers when the result
+        /// dtype is "f64"; `result_matrix` holds the same values rounded to f32
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        pub result_f64: Option<std::sync::Arc<Vec<f64>>>,
+        /// Empty when computed with `ComputeOptions::defer_result_hash`, until
+        /// `fused::write_json` writes it
+        #[serde(serialize_with = "super::fused::serialize_result_hash")]
         pub result_hash: String,
+        /// Per-tile digests, present when tracing was requested
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        pub tile_trace: Option<Vec<super::TileDigest>>,
+        /// blake3 over the concatenated tile digests
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        pub trace_root: Option<String>,
+        /// Set once the written output file was re-read and reproduced result_hash
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        pub audit_passed: Option<bool>,
+        /// Whether recomputing from the same matrices reproduced result_hash, when the
+        /// request asked for it (API `verify: true`)
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        pub verified: Option<bool>,
+        /// Things the user should know about that did not fail the run
+        #[serde(default, skip_serializing_if = "Vec::is_empty")]
+        pub warnings: Vec<super::warnings::SolverWarning>,
+        /// `result_hash` of each item of a batched matmul, in order; item i's result is rows
+        /// i·m..(i+1)·m of `result_matrix`, and `result_hash` covers all of them
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        pub batch_hashes: Option<Vec<String>>,
+        /// Further full computations of the workload, such as the fp32 reference of a
+        /// reduced-precision run (`ComputeOptions::error_metrics`)
+        #[serde(default, skip_serializing_if = "Vec::is_empty")]
+        pub auxiliary_results: Vec<super::error_metrics::AuxResult>,
         pub metrics: Metrics,
         pub metadata: OutputMetadata,
     }
     
-    #[derive(Debug, Serialize, Deserialize)]
+    #[derive(Debug, Clone, Serialize, Deserialize)]
     pub struct Metrics {
         pub latency_ms: f64,
         pub throughput_ops_per_sec: f64,
@@ -442,9 +1345,56 @@ pub mod types {
         pub kernel_time_ms: Option<f64>,
         #[serde(skip_serializing_if = "Option::is_none")]
         pub serialize_time_ms: Option<f64>,
+        /// Whether the 16x16 kernel reused an already-packed B (memory or disk cache), or
+        /// fp32_blocked B's cached column panels
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        pub pack_cache_hit: Option<bool>,
+        /// Kernel time of each item of a batched matmul; `kernel_time_ms` is their sum
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        pub batch_kernel_time_ms: Option<Vec<f64>>,
+        /// With `redundancy: "dual"`, how often both runs were repeated after disagreeing;
+        /// latency and kernel time then cover every run
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        pub redundancy_retries: Option<u32>,
+        /// Whether seed generation overlapped the u8i8 kernel (`ComputeOptions::seed_pipeline`);
+        /// latency and kernel time then include generation
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        pub seed_pipelined: Option<bool>,
+        /// Peak MB the API allocated for this request on every thread that worked on it,
+        /// parsing through the result (`counting-alloc` builds only)
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        pub peak_request_memory_mb: Option<f64>,
+        /// Time spent verifying the result, when `verified` is set
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        pub verify_time_ms: Option<f64>,
+        /// Threads the kernel ran on (`OutputMetadata::threads`), also when a requested
+        /// count (`ComputeOptions::threads`) ran serially
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        pub threads_used: Option<usize>,
+        /// Counters of the in-process packed-B cache the kernel packed B through, when it did
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        pub cache_stats: Option<CacheStats>,
+    }
+
+    /// One in-process packed-B cache's counters since the process started (see b_cache.rs)
+    #[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+    pub struct CacheStats {
+        /// "fp16", "bf16", "int8" or "fp32"
+        pub cache: String,
+        pub hits: u64,
+        pub misses: u64,
+        pub evictions: u64,
+        /// Packed Bs held now, at most `capacity`
+        pub entries: usize,
+        pub capacity: usize,
+        /// Bytes those packed Bs hold, at most `max_bytes`
+        #[serde(default)]
+        pub bytes: usize,
+        #[serde(default)]
+        pub max_bytes: usize,
     }
     
-    #[derive(Debug, Serialize, Deserialize)]
+    #[derive(Debug, Clone, Serialize, Deserialize)]
     pub struct OutputMetadata {
         pub precision: String,
         pub matrix_a_shape: (usize, usize),
@@ -452,47 +1402,130 @@ pub mod types {
         pub result_shape: (usize, usize),
         pub compiler_flags: Option<String>,
         pub libraries: Option<Vec<String>>,
+        /// Element type of the hashed result ("f32", "i32")
+        #[serde(default = "default_result_dtype")]
+        pub result_dtype: String,
+        /// Exact layout of the bytes fed to SHA-256, e.g. "f32le row-major"
+        #[serde(default = "default_hash_input_description")]
+        pub hash_input_description: String,
+        /// "exact" or "rounded"; see `hash_rounding` for the parameters
+        #[serde(default = "default_hash_mode")]
+        pub hash_mode: String,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        pub hash_rounding: Option<super::HashRounding>,
+        /// Name of the registered kernel that produced the result
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        pub kernel: Option<String>,
+        /// The dispatch decision that picked `kernel`, as made by `kernels::select_kernel`
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        pub kernel_choice: Option<super::kernels::KernelChoice>,
+        /// Accumulation order ("strict"/"fast") when the fp32_16x16 kernel ran
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        pub fp32_accumulation: Option<String>,
+        /// Summation over k ("kahan"/"pairwise") when not naive
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        pub fp32_summation: Option<String>,
+        /// Path of the integer dot products ("avx512vnni", "avx2", "i8mm", "dotprod", "neon" or "scalar") when
+        /// the kernel runs on them; informational, every path gives the same result
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        pub simd_variant: Option<String>,
+        /// Threads the kernel split the product across, when more than one (feature `parallel`)
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        pub threads: Option<usize>,
+        /// Non-default int8/u8i8 accumulator ("i64", "i16_saturating" or "i32_saturating"), when one was used
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        pub accumulator: Option<String>,
+        /// Set on fp64 outputs: the result was accumulated in f64 and `result_matrix` rounds it to f32
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        pub f64_accumulation: Option<bool>,
+        /// How quantized operands were scaled: "per_tensor" (int8, int4), "per_row" (int8_rowwise)
+        /// or "asymmetric" (int8_asym)
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        pub quantization_scheme: Option<String>,
+        /// How operands were converted to binary16, on every fp16 output
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        pub fp16_rounding: Option<super::Fp16Rounding>,
+        /// "transposed" when matrix_a was supplied as Aᵀ; `matrix_a_shape` is still m×k
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        pub a_layout: Option<String>,
+        /// "transposed" when matrix_b was supplied as Bᵀ; `matrix_b_shape` is still k×n
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        pub b_layout: Option<String>,
+        /// "dual" when the result was computed twice and the hashes agreed
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        pub redundancy: Option<String>,
+        /// Kernel of the second run under `redundancy: "dual"` (`kernel` ran the first)
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        pub cross_check_kernel: Option<String>,
+        /// Input numbers that did not convert to f32 faithfully (overflow, integers
+        /// beyond 2^24); set by the JSON-parsing caller
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        pub lossy_parse_count: Option<usize>,
+        /// Set when only a subset of output columns was computed; result_matrix
+        /// and result_hash then cover exactly these columns in this order
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        pub result_columns: Option<Vec<usize>>,
+        /// Scales applied by `workload_type: "quantize"` (one per tensor or per column)
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        pub quantization_scales: Option<Vec<f32>>,
+        /// The input's GEMM alpha and beta, when either was not the default (1 and 0)
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        pub alpha: Option<f32>,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        pub beta: Option<f32>,
+        /// Items of a batched matmul; `matrix_a_shape` and `result_shape` cover the whole stack
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        pub batch: Option<usize>,
+        /// Whether B was packed through an in-process packed-B cache, on runs of a kernel
+        /// that has one; false when the request disabled it
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        pub b_cache_used: Option<bool>,
+    }
+
+    fn default_result_dtype() -> String {
+        super::ResultDtype::F32.as_str().to_string()
+    }
+
+    fn default_hash_mode() -> String {
+        "exact".to_string()
+    }
+
+    fn default_hash_input_description() -> String {
+        super::ResultDtype::F32.hash_input_description().to_string()
     }
 }
 
-/// Optimized fp32 matrix multiplication with cache blocking (tiling) and flat memory layout
-/// Uses optimized loop order (i -> p -> j) with cache-friendly tiling
-/// Default tile sizes: BM=16, BN=64, BK=64 (tunable for different cache sizes)
-/// Works directly with FlatMatrix - no conversion overhead!
-/// 
+/// Output tile height of the blocked fp32 kernel; also the row size of trace tiles
+pub const TILE_ROWS: usize = 16;
+/// Output tile width of the blocked fp32 kernel; also the column size of trace tiles
+pub const TILE_COLS: usize = 64;
+
 /// Returns (result, kernel_time) where kernel_time is the duration of the computation loop only
+#[deprecated(since = "0.2.0", note = "internal kernel; use compute_workload_with_options with ComputeOptions::kernel = Some(\"fp32_blocked\")")]
 pub fn matmul_fp32_optimized(a: &FlatMatrix, b: &FlatMatrix) -> (FlatMatrix, std::time::Duration) {
-    const BM: usize = 16;  // Block size for rows of C
-    const BN: usize = 64;  // Block size for cols of C
-    const BK: usize = 64;  // Block size for reduction dimension
-    
-    let m = a.rows;        // rows of A and C
-    let k = a.cols;        // cols of A, rows of B
-    let n = b.cols;        // cols of B and C
-    
-    // Already flat! No conversion needed
-    let a_flat = &a.data;
-    let b_flat = &b.data;
-    
-    // Result in flat layout: C[i * n + j] = C[i][j]
-    let mut result_flat = vec![0.0f32; m * n];
+    matmul_fp32_blocked(a, b, &SystemClock)
+}
+
+/// fp64 matmul for high-accuracy reference runs: both operands are widened to f64 and
+/// multiplied with the same tiling as `matmul_fp32_blocked`. Returns the row-major f64
+/// result; the timing includes the widening.
+pub(crate) fn matmul_fp64(a: &FlatMatrix, b: &FlatMatrix, clock: &dyn Clock) -> (Vec<f64>, std::time::Duration) {
+    const BM: usize = TILE_ROWS;
+    const BN: usize = TILE_COLS;
+    const BK: usize = 64;
     
-    // Kernel-only timing: measure only the computation loop
-    let start = std::time::Instant::now();
+    let (m, k, n) = (a.rows, a.cols, b.cols);
+    let start = clock.now();
+    let a_flat: Vec<f64> = a.data.iter().map(|&x| x as f64).collect();
+    let b_flat: Vec<f64> = b.data.iter().map(|&x| x as f64).collect();
+    let mut result = vec![0.0f64; m * n];
     
-    // Cache blocking: block over i (BM), j (BN), and p (BK)
     for ii in (0..m).step_by(BM) {
         let i_end = (ii + BM).min(m);
         for jj in (0..n).step_by(BN) {
             let j_end = (jj + BN).min(n);
             for pp in (0..k).step_by(BK) {
                 let p_end = (pp + BK).min(k);
-                
-                // Microkernel on tile: C[ii:i_end, jj:j_end] += A[ii:i_end, pp:p_end] × B[pp:p_end, jj:j_end]
-                // Optimized loop order: i -> p -> j
-                // Flat indexing: A[i * k + p], B[p * n + j], C[i * n + j]
-                // This streams across B[p, :] (contiguous) and C[i, :] (contiguous)
-                // Hoisting a_ip out of inner loop for better register reuse
                 for i in ii..i_end {
                     let c_base = i * n;
                     let a_base = i * k;
@@ -500,7 +1533,7 @@ pub fn matmul_fp32_optimized(a: &FlatMatrix, b: &FlatMatrix) -> (FlatMatrix, std
                         let a_ip = a_flat[a_base + p];
                         let b_base = p * n;
                         for j in jj..j_end {
-                            result_flat[c_base + j] += a_ip * b_flat[b_base + j];
+                            result[c_base + j] += a_ip * b_flat[b_base + j];
                         }
                     }
                 }
@@ -508,214 +1541,829 @@ pub fn matmul_fp32_optimized(a: &FlatMatrix, b: &FlatMatrix) -> (FlatMatrix, std
         }
     }
     
+    (result, clock.elapsed_since(start))
+}
+
+/// Optimized fp32 matrix multiplication with cache blocking (tiling) and flat memory layout
+/// Uses optimized loop order (i -> p -> j) with cache-friendly tiling
+/// Default tile sizes: BM=16, BN=64, BK=64 (tunable for different cache sizes)
+/// Works directly with FlatMatrix - no conversion overhead!
+fn matmul_fp32_blocked(a: &FlatMatrix, b: &FlatMatrix, clock: &dyn Clock) -> (FlatMatrix, std::time::Duration) {
+    let (result, kernel_time, _) = matmul_fp32_blocked_threads(a, b, None, clock);
+    (result, kernel_time)
+}
+
+/// `matmul_fp32_blocked` on `threads` (see `ComputeOptions::threads`), also returning how
+/// many threads worked on it. The timing is the wall time of the whole (parallel) loop.
+fn matmul_fp32_blocked_threads(a: &FlatMatrix, b: &FlatMatrix, threads: Option<usize>, clock: &dyn Clock) -> (FlatMatrix, std::time::Duration, usize) {
+    // Result in flat layout: C[i * n + j] = C[i][j]
+    let mut result_flat = vec![0.0f32; a.rows * b.cols];
+    
+    // Kernel-only timing: measure only the computation loop
+    let start = clock.now();
+    let used = fp32_blocked_accumulate(a, Fp32B::RowMajor(b), 1.0, &mut result_flat, threads);
     // Kernel timing ends here
-    let kernel_time = start.elapsed();
+    let kernel_time = clock.elapsed_since(start);
     
     // Return as FlatMatrix - no conversion needed!
-    (FlatMatrix { data: result_flat, rows: m, cols: n }, kernel_time)
+    (FlatMatrix { data: result_flat, rows: a.rows, cols: b.cols }, kernel_time, used)
 }
 
-#[inline(always)]
-fn matmul_fp32_16x16(a: &FlatMatrix, b: &FlatMatrix) -> (FlatMatrix, std::time::Duration) {
-    let m = a.rows;
-    let k = a.cols;
-    let n = b.cols;
-
-    let mut result_flat = vec![0.0f32; m * n];
-    let start = Instant::now();
+/// Whether the blocked kernel reads a k×n B from cached column panels: B spans more than
+/// one TILE_COLS panel (row-major B is already laid out as one), and B is keyed by blake3.
+/// Without `blake3-hash` the SHA-256 key of a large B costs more than packing it, so B is
+/// read as is.
+fn fp32_packs_panels(k: usize, n: usize) -> bool {
+    cfg!(feature = "blake3-hash") && k > 0 && n > TILE_COLS
+}
 
-    let a_ptr = a.data.as_ptr();
-    let c_ptr = result_flat.as_mut_ptr();
+/// B (k×n) as TILE_COLS-wide column panels one after another: panel q holds
+/// B[:, q·TILE_COLS..] row by row (the last may be narrower), so the blocked kernel reads
+/// each k-block of a tile from one contiguous run instead of rows n apart
+fn pack_b_fp32_panels(b: &FlatMatrix) -> AlignedBufferF32 {
+    let (k, n) = (b.rows, b.cols);
+    let mut buf = AlignedBufferF32::new(k * n, 64);
+    let mut offset = 0;
+    for jj in (0..n).step_by(TILE_COLS) {
+        let width = (n - jj).min(TILE_COLS);
+        for p in 0..k {
+            unsafe { std::ptr::copy_nonoverlapping(b.data[p * n + jj..].as_ptr(), buf.as_mut_ptr().add(offset), width) };
+            offset += width;
+        }
+    }
+    buf
+}
 
-    unsafe {
-        let b_ptr = b.data.as_ptr();
-        let mut p = 0usize;
-        while p + 3 < k {
-            for i in 0..16 {
-                let a_base = i * k;
-                let a0 = *a_ptr.add(a_base + p);
-                let a1 = *a_ptr.add(a_base + p + 1);
-                let a2 = *a_ptr.add(a_base + p + 2);
-                let a3 = *a_ptr.add(a_base + p + 3);
+/// B as `fp32_blocked_rows` reads it
+#[derive(Clone, Copy)]
+enum Fp32B<'a> {
+    RowMajor(&'a FlatMatrix),
+    /// `pack_b_fp32_panels` of a k×n B: (panels, k, n)
+    Panels(&'a [f32], usize, usize),
+}
 
-                let c_base = i * 16;
-                let b_base = p * 16;
-                for j in 0..16 {
-                    let b0 = *b_ptr.add(b_base + j);
-                    let b1 = *b_ptr.add(b_base + 16 + j);
-                    let b2 = *b_ptr.add(b_base + 32 + j);
-                    let b3 = *b_ptr.add(b_base + 48 + j);
-                    let c = c_ptr.add(c_base + j);
-                    *c += a0 * b0 + a1 * b1 + a2 * b2 + a3 * b3;
-                }
-            }
-            p += 4;
+impl<'a> Fp32B<'a> {
+    fn cols(&self) -> usize {
+        match *self {
+            Fp32B::RowMajor(b) => b.cols,
+            Fp32B::Panels(_, _, n) => n,
         }
+    }
 
-        while p < k {
-            for i in 0..16 {
-                let a_ip = *a_ptr.add(i * k + p);
-                let c_base = i * 16;
-                let b_base = p * 16;
-                for j in 0..16 {
-                    let b_pj = *b_ptr.add(b_base + j);
-                    let c = c_ptr.add(c_base + j);
-                    *c += a_ip * b_pj;
-                }
+    /// B[p, jj..j_end] of a tile starting at column jj, a multiple of TILE_COLS
+    #[inline(always)]
+    fn row(&self, p: usize, jj: usize, j_end: usize) -> &'a [f32] {
+        match *self {
+            Fp32B::RowMajor(b) => &b.data[p * b.cols + jj..p * b.cols + j_end],
+            Fp32B::Panels(panels, k, _) => {
+                // Every panel before this one is TILE_COLS wide
+                let base = jj * k + p * (j_end - jj);
+                &panels[base..base + (j_end - jj)]
             }
-            p += 1;
         }
     }
-
-    let kernel_time = start.elapsed();
-    (FlatMatrix { data: result_flat, rows: 16, cols: 16 }, kernel_time)
 }
 
-#[cfg(feature = "openblas")]
-fn matmul_fp32_openblas(a: &FlatMatrix, b: &FlatMatrix) -> (FlatMatrix, std::time::Duration) {
-    let m = a.rows;
-    let k = a.cols;
-    let n = b.cols;
-
-    let a_flat = &a.data;
-    let b_flat = &b.data;
-    let mut result_flat = vec![0.0f32; m * n];
-
-    let start = Instant::now();
-    unsafe {
-        cblas_sgemm(
-            CBLAS_ORDER::CblasRowMajor,
-            CBLAS_TRANSPOSE::CblasNoTrans,
-            CBLAS_TRANSPOSE::CblasNoTrans,
-            m as i32,
-            n as i32,
-            k as i32,
-            1.0,
-            a_flat.as_ptr(),
-            k as i32,
-            b_flat.as_ptr(),
-            n as i32,
-            0.0,
-            result_flat.as_mut_ptr(),
-            n as i32,
-        );
+/// `matmul_fp32_blocked_threads` on `options`' threads, reading a B wider than one tile from
+/// the panels kept by the fp32 cache in `options` (`pack_b_fp32_panels`, run inside the
+/// timing on a miss, so a B seen before is faster; B's key is digested before the timing
+/// starts). Also returns the threads used and
+/// whether the panels were cached: None when B was read as is, which the summation order
+/// makes no different.
+fn matmul_fp32_blocked_cached(a: &FlatMatrix, b: &FlatMatrix, options: &ComputeOptions) -> (FlatMatrix, std::time::Duration, usize, Option<bool>) {
+    let clock = options.clock();
+    if !fp32_packs_panels(b.rows, b.cols) || options.bypass_b_cache {
+        let (result, kernel_time, used) = matmul_fp32_blocked_threads(a, b, options.threads, clock);
+        return (result, kernel_time, used, None);
     }
-    let kernel_time = start.elapsed();
-
-    (FlatMatrix { data: result_flat, rows: m, cols: n }, kernel_time)
+    let mut result_flat = vec![0.0f32; a.rows * b.cols];
+    let key = b_cache::CacheKey::of(b);
+    let start = clock.now();
+    let (panels, hit) = options.caches().fp32.get_or_pack_keyed(key, (), || pack_b_fp32_panels(b));
+    let used = fp32_blocked_accumulate(a, Fp32B::Panels(panels.as_slice(), b.rows, b.cols), 1.0, &mut result_flat, options.threads);
+    let kernel_time = clock.elapsed_since(start);
+    (FlatMatrix { data: result_flat, rows: a.rows, cols: b.cols }, kernel_time, used, Some(hit))
 }
 
-#[cfg(feature = "openblas")]
-fn matmul_fp32(a: &FlatMatrix, b: &FlatMatrix) -> (FlatMatrix, std::time::Duration) {
-    if a.rows == 16 && b.cols == 16 {
-        return matmul_fp32_16x16(a, b);
-    }
-    matmul_fp32_openblas(a, b)
-}
+/// Fewest multiply-adds for which `fp32_blocked_accumulate` splits its row blocks across
+/// threads; below it handing out the blocks costs more than it saves
+#[cfg(feature = "parallel")]
+const PARALLEL_MIN_MACS: usize = 1 << 22;
 
-#[cfg(not(feature = "openblas"))]
-fn matmul_fp32(a: &FlatMatrix, b: &FlatMatrix) -> (FlatMatrix, std::time::Duration) {
-    if a.rows == 16 && b.cols == 16 {
-        return matmul_fp32_16x16(a, b);
+/// The blocked kernel's tiles: add `alpha * A·B` into the row-major a.rows × b.cols `c`.
+/// alpha scales each A element once per k-step, so alpha = 1 adds exactly A·B.
+///
+/// With the `parallel` feature, large products hand each thread whole BM-row blocks of C
+/// (`threads`: see `ComputeOptions::threads`). The blocks are disjoint and every element is
+/// summed in the same order as serially, so the result is bit-identical. Returns how many
+/// threads worked on it.
+fn fp32_blocked_accumulate(a: &FlatMatrix, b: Fp32B, alpha: f32, result_flat: &mut [f32], threads: Option<usize>) -> usize {
+    let (m, n) = (a.rows, b.cols());
+    assert_eq!(result_flat.len(), m * n);
+    #[cfg(feature = "parallel")]
+    if threads != Some(1) && m > TILE_ROWS && m * a.cols * n >= PARALLEL_MIN_MACS {
+        return parallel::for_each_chunk(threads, result_flat, TILE_ROWS * n, |block, c_rows| {
+            fp32_blocked_rows(a, b, alpha, block * TILE_ROWS, c_rows)
+        });
     }
-    matmul_fp32_optimized(a, b)
+    let _ = threads;
+    fp32_blocked_rows(a, b, alpha, 0, result_flat);
+    1
 }
 
-fn matmul_fp16(a: &FlatMatrix, b: &FlatMatrix) -> FlatMatrix {
-    use half::f16;
-    
-    let m = a.rows;
-    let k = a.cols;
-    let n = b.cols;
+/// `fp32_blocked_accumulate` for the rows of C from `first_row` that `c_rows` holds
+fn fp32_blocked_rows(a: &FlatMatrix, b: Fp32B, alpha: f32, first_row: usize, c_rows: &mut [f32]) {
+    const BM: usize = TILE_ROWS;  // Block size for rows of C
+    const BN: usize = TILE_COLS;  // Block size for cols of C
+    const BK: usize = 64;  // Block size for reduction dimension
     
-    // Convert to fp16 (flat layout)
-    let a_fp16: Vec<f16> = a.data.iter().map(|&x| f16::from_f32(x)).collect();
-    let b_fp16: Vec<f16> = b.data.iter().map(|&x| f16::from_f32(x)).collect();
+    let k = a.cols;        // cols of A, rows of B
+    let n = b.cols();      // cols of B and C
+    let m = first_row + c_rows.len() / n.max(1);  // last row of C, exclusive
     
-    let mut result_fp16 = vec![f16::from_f32(0.0); m * n];
+    // Already flat! No conversion needed
+    let a_flat = &a.data;
     
-    // Optimized loop order: i -> p -> j
-    // This streams across B[p, :] (contiguous) and C[i, :] (contiguous)
-    // Hoisting a_ip out of inner loop for better register reuse
+    // Cache blocking: block over i (BM), j (BN), and p (BK)
+    for ii in (first_row..m).step_by(BM) {
+        let i_end = (ii + BM).min(m);
+        for jj in (0..n).step_by(BN) {
+            let j_end = (jj + BN).min(n);
+            for pp in (0..k).step_by(BK) {
+                let p_end = (pp + BK).min(k);
+                
+                // Microkernel on tile: C[ii:i_end, jj:j_end] += A[ii:i_end, pp:p_end] × B[pp:p_end, jj:j_end]
+                // Optimized loop order: i -> p -> j
+                // Flat indexing: A[i * k + p], C[i * n + j]; B[p, jj..j_end] from `b.row`
+                // This streams across B[p, :] (contiguous) and C[i, :] (contiguous)
+                // Hoisting a_ip out of inner loop for better register reuse
+                for i in ii..i_end {
+                    let c_base = (i - first_row) * n;
+                    let a_base = i * k;
+                    for p in pp..p_end {
+                        let a_ip = alpha * a_flat[a_base + p];
+                        let c_tile = &mut c_rows[c_base + jj..c_base + j_end];
+                        for (c, &b_pj) in c_tile.iter_mut().zip(b.row(p, jj, j_end)) {
+                            *c += a_ip * b_pj;
+                        }
+                    }
+                }
+            }
+        }
+    }
+}
+
+/// fp32 with one documented summation order on every build and target
+/// (`precision: "fp32_strict"`), so its hash is the same on x86 and aarch64, with or
+/// without openblas. Each C[i][j] = (…((0 + A[i][0]·B[0][j]) + A[i][1]·B[1][j]) + …) +
+/// A[i][k-1]·B[k-1][j]: every product is rounded to f32 and then added, in p order from 0.
+/// The loops run i → p → j with no tiles, lanes or BLAS, and Rust never contracts `a * b + c`
+/// into a fused multiply-add unless asked, so nothing reassociates or skips a rounding.
+pub(crate) fn matmul_fp32_strict(a: &FlatMatrix, b: &FlatMatrix, clock: &dyn Clock) -> (FlatMatrix, std::time::Duration) {
+    let (m, k, n) = (a.rows, a.cols, b.cols);
+    let start = clock.now();
+    let mut data = vec![0.0f32; m * n];
     for i in 0..m {
-        let c_base = i * n;
-        let a_base = i * k;
+        let c_row = &mut data[i * n..(i + 1) * n];
         for p in 0..k {
-            let a_ip = a_fp16[a_base + p];
-            let b_base = p * n;
-            for j in 0..n {
-                result_fp16[c_base + j] += a_ip * b_fp16[b_base + j];
+            let a_ip = a.data[i * k + p];
+            for (c, &b_pj) in c_row.iter_mut().zip(&b.data[p * n..(p + 1) * n]) {
+                let product = a_ip * b_pj;
+                *c += product;
             }
         }
     }
-    
-    // Convert back to fp32 (flat layout)
-    let result_flat: Vec<f32> = result_fp16.iter().map(|&x| x.to_f32()).collect();
-    
-    FlatMatrix { data: result_flat, rows: m, cols: n }
+    (FlatMatrix { data, rows: m, cols: n }, clock.elapsed_since(start))
 }
 
-#[inline(always)]
-fn matmul_fp16_16x16(a: &FlatMatrix, b: &FlatMatrix) -> (FlatMatrix, std::time::Duration) {
-    use half::f16;
-
-    let k = a.cols;
-
-    let mut result_flat = vec![0.0f32; 16 * 16];
-    let a_ptr = a.data.as_ptr();
-    let c_ptr = result_flat.as_mut_ptr();
-
-    let kernel_time = unsafe {
-        let mut a_q = AlignedBufferF32::new(16 * k, 64);
-        let a_q_ptr = a_q.as_mut_ptr();
-        for i in 0..16 {
-            let a_base = i * k;
-            for p in 0..k {
-                let val = *a_ptr.add(a_base + p);
-                *a_q_ptr.add(a_base + p) = f16::from_f32(val).to_f32();
+/// fp32 matrix-vector product, for A 1×k or B k×1. The blocked kernel's tiles buy nothing
+/// when one side is a vector, so this sums each output directly, in k order from 0 as the
+/// tiles do, and the result is bit-identical to `matmul_fp32_blocked`. With B k×1 each
+/// output is a contiguous row of A dotted with B; with A 1×k the rows of B are added into
+/// the result scaled by A's elements. (`dot_f32` sums in four lanes on aarch64, which
+/// would change the last bits.)
+fn matmul_fp32_gemv(a: &FlatMatrix, b: &FlatMatrix, clock: &dyn Clock) -> (FlatMatrix, std::time::Duration) {
+    let (m, k, n) = (a.rows, a.cols, b.cols);
+    let start = clock.now();
+    let data = if k == 0 {
+        vec![0.0f32; m * n]
+    } else if n == 1 {
+        a.data.chunks_exact(k).map(|row| row.iter().zip(&b.data).fold(0.0f32, |sum, (&x, &y)| sum + x * y)).collect()
+    } else {
+        let mut result = vec![0.0f32; n];
+        for (a_p, b_row) in a.data.iter().zip(b.data.chunks_exact(n)) {
+            for (c, &b_pj) in result.iter_mut().zip(b_row) {
+                *c += a_p * b_pj;
             }
         }
+        result
+    };
+    let kernel_time = clock.elapsed_since(start);
+    (FlatMatrix { data, rows: m, cols: n }, kernel_time)
+}
 
-        let a_q_ptr = a_q.as_ptr();
-        let (b_t_ptr, _) = get_bt_fp16_cache(b);
+/// `C = alpha·A·B + beta·C` on the blocked fp32 kernel, accumulating into `c` in place so
+/// chained or block-streamed products reuse one result buffer. `c` must be a.rows × b.cols.
+/// As in BLAS, beta = 0 overwrites C without reading it, so NaNs already in C are dropped.
+/// The products are added into the scaled C, so the rounding can differ in the last bits
+/// from computing `alpha·(A·B)` and `beta·C` separately.
+///
+/// C cannot share storage with A or B; the borrow checker rejects it:
+///
+/// ```compile_fail
+/// use matmul_solver::{matmul_fp32_into, FlatMatrix};
+/// let mut m = FlatMatrix { data: vec![1.0; 4], rows: 2, cols: 2 };
+/// matmul_fp32_into(&m, &m, &mut m, 1.0, 0.0).unwrap();
+/// ```
+pub fn matmul_fp32_into(a: &FlatMatrix, b: &FlatMatrix, c: &mut FlatMatrix, alpha: f32, beta: f32) -> Result<(), SolverError> {
+    if a.cols != b.rows {
+        return Err(SolverError::InvalidInput(format!("Matrix dimensions incompatible: A is {}x{}, B is {}x{}",
+            a.rows, a.cols, b.rows, b.cols)));
+    }
+    if (c.rows, c.cols) != (a.rows, b.cols) || c.data.len() != c.rows * c.cols {
+        return Err(SolverError::InvalidInput(format!(
+            "C is {}x{} but A·B is {}x{}",
+            c.rows, c.cols, a.rows, b.cols
+        )));
+    }
+    if beta == 0.0 {
+        c.data.fill(0.0);
+    } else if beta != 1.0 {
+        c.data.iter_mut().for_each(|x| *x *= beta);
+    }
+    fp32_blocked_accumulate(a, Fp32B::RowMajor(b), alpha, &mut c.data, None);
+    Ok(())
+}
 
-        let kernel_start = Instant::now();
-        for i in 0..16 {
-            let a_row = a_q_ptr.add(i * k);
-            let c_base = i * 16;
-            for j in 0..16 {
-                let b_row = b_t_ptr.add(j * k);
-                let acc = dot_f32(a_row, b_row, k);
-                *c_ptr.add(c_base + j) = acc;
+/// Accumulation order of the 16x16 fp32 kernel.
+///
+/// `Strict` is the canonical form and the default: each group of four k-steps is
+/// summed as `((a0*b0 + a1*b1) + a2*b2) + a3*b3` with every product and sum rounded to
+/// f32 (no fused multiply-add), then added to the accumulator; leftover k-steps are
+/// added one product at a time. Rust never contracts mul+add into FMA on its own, so
+/// this order fixes the result bit-for-bit on every target.
+///
+/// `Fast` uses `f32::mul_add` when the target has hardware FMA (`target-feature=+fma`
+/// or equivalent) and the strict form otherwise, so its hashes depend on build flags.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Fp32Accumulation {
+    #[default]
+    Strict,
+    Fast,
+}
+
+impl Fp32Accumulation {
+    pub fn parse(s: &str) -> Result<Self, SolverError> {
+        match s {
+            "strict" => Ok(Fp32Accumulation::Strict),
+            "fast" => Ok(Fp32Accumulation::Fast),
+            other => Err(SolverError::InvalidInput(format!("Unknown fp32 accumulation: {} (expected strict or fast)", other))),
+        }
+    }
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Fp32Accumulation::Strict => "strict",
+            Fp32Accumulation::Fast => "fast",
+        }
+    }
+
+    #[inline(always)]
+    fn dot4(self, a: [f32; 4], b: [f32; 4]) -> f32 {
+        if self == Fp32Accumulation::Fast && cfg!(target_feature = "fma") {
+            a[3].mul_add(b[3], a[2].mul_add(b[2], a[1].mul_add(b[1], a[0] * b[0])))
+        } else {
+            ((a[0] * b[0] + a[1] * b[1]) + a[2] * b[2]) + a[3] * b[3]
+        }
+    }
+
+    #[inline(always)]
+    fn fmadd(self, acc: f32, a: f32, b: f32) -> f32 {
+        if self == Fp32Accumulation::Fast && cfg!(target_feature = "fma") {
+            a.mul_add(b, acc)
+        } else {
+            acc + a * b
+        }
+    }
+}
+
+/// How fp32 matmuls sum each C[i][j] over k (`ComputeOptions::fp32_summation`).
+///
+/// `Naive` adds the products into the running sum in each kernel's own order, and is the
+/// default because every existing fp32 hash was computed that way. Over a long reduction
+/// (k = 50240) its rounding error grows with k, and a sum that is large before small
+/// products arrive drops them entirely. `Kahan` keeps a compensation term per output that
+/// carries the low-order bits each add loses, for an error independent of k; `Pairwise`
+/// halves the k range until at most `PAIRWISE_BLOCK` steps remain, sums those in order and
+/// adds the halves, for an error growing with log k at nearly the naive cost, which makes
+/// it the one to pick when accuracy matters.
+///
+/// Both are implemented once (`matmul_fp32_summed`) and run by every kernel that supports
+/// them (`MatmulKernel::supports_fp32_summation`: fp32_16x16, fp32_gemv and fp32_blocked),
+/// so their results do not depend on the kernel. They apply to fp32 only, with strict
+/// accumulation, and `metadata.fp32_summation` records them for verification.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Fp32Summation {
+    #[default]
+    Naive,
+    Kahan,
+    Pairwise,
+}
+
+impl Fp32Summation {
+    pub fn parse(s: &str) -> Result<Self, SolverError> {
+        match s {
+            "naive" => Ok(Fp32Summation::Naive),
+            "kahan" => Ok(Fp32Summation::Kahan),
+            "pairwise" => Ok(Fp32Summation::Pairwise),
+            other => Err(SolverError::InvalidInput(format!("Unknown fp32 summation: {} (expected naive, kahan or pairwise)", other))),
+        }
+    }
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Fp32Summation::Naive => "naive",
+            Fp32Summation::Kahan => "kahan",
+            Fp32Summation::Pairwise => "pairwise",
+        }
+    }
+}
+
+/// Longest run of k-steps `Fp32Summation::Pairwise` sums in order
+pub const PAIRWISE_BLOCK: usize = 32;
+
+/// Integer accumulator of the int8 and u8i8 kernels (`ComputeOptions::accumulator`).
+///
+/// `I32` widens every product into an i32 sum; a u8i8 sum that does not fit (possible once
+/// k exceeds `U8I8_SAFE_K`) is an error naming the output cell, never a wrapped value. `I64`
+/// (u8i8 only) keeps exact i64 sums for any k and rounds them once to f32, so it has no i32
+/// accumulators to hash. The saturating modes emulate fixed-width
+/// hardware: products are added in k order and the sum is clamped to the width's range
+/// after every add, so a sum that hits a bound stays there until products of the other
+/// sign pull it back. Every int8/u8i8 kernel gives the same saturated result.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Accumulator {
+    #[default]
+    I32,
+    I16Saturating,
+    I32Saturating,
+    I64,
+}
+
+impl Accumulator {
+    pub fn parse(s: &str) -> Result<Self, SolverError> {
+        match s {
+            "i32" => Ok(Accumulator::I32),
+            "i16_saturating" => Ok(Accumulator::I16Saturating),
+            "i32_saturating" => Ok(Accumulator::I32Saturating),
+            "i64" => Ok(Accumulator::I64),
+            other => Err(SolverError::InvalidInput(format!(
+                "Unknown accumulator: {} (expected i32, i64, i16_saturating or i32_saturating)",
+                other
+            ))),
+        }
+    }
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Accumulator::I32 => "i32",
+            Accumulator::I16Saturating => "i16_saturating",
+            Accumulator::I32Saturating => "i32_saturating",
+            Accumulator::I64 => "i64",
+        }
+    }
+
+    pub fn is_saturating(&self) -> bool {
+        matches!(self, Accumulator::I16Saturating | Accumulator::I32Saturating)
+    }
+
+    /// Range a saturating sum is clamped to
+    fn bounds(&self) -> (i64, i64) {
+        match self {
+            Accumulator::I16Saturating => (i16::MIN as i64, i16::MAX as i64),
+            Accumulator::I32 | Accumulator::I32Saturating => (i32::MIN as i64, i32::MAX as i64),
+            Accumulator::I64 => (i64::MIN, i64::MAX),
+        }
+    }
+}
+
+/// How fp16 paths convert f32 operands to binary16 (`ComputeOptions::fp16_rounding`).
+///
+/// `NearestEven` is IEEE round-to-nearest-even, the default and what `half::f16::from_f32`
+/// does. `TowardZero` truncates the dropped mantissa bits, as some hardware converters do,
+/// and saturates at ±65504 instead of overflowing to infinity. `Stochastic` rounds away
+/// from zero with probability equal to the dropped fraction of an ulp; the random bits come
+/// from `seed` and the element's operand and index, so a result is reproducible from the
+/// seed whichever kernel ran. The conversion is done on the bits here rather than in `half`,
+/// so every mode is pinned by this crate. The fp16 kernels sum their products in f32, so the
+/// operand conversion is the only rounding to f16.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(tag = "mode", rename_all = "snake_case")]
+pub enum Fp16Rounding {
+    #[default]
+    NearestEven,
+    TowardZero,
+    Stochastic { seed: u64 },
+}
+
+/// Which operand an element belongs to, for `Fp16Rounding::Stochastic`'s random stream
+#[cfg(feature = "fp16")]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Operand {
+    A,
+    B,
+}
+
+impl Fp16Rounding {
+    /// Build from the request-level fields: `mode` is "nearest_even", "toward_zero" or
+    /// "stochastic", and a seed is required by (and only accepted with) "stochastic"
+    pub fn from_mode(mode: &str, seed: Option<u64>) -> Result<Self, SolverError> {
+        match (mode, seed) {
+            ("nearest_even", None) => Ok(Fp16Rounding::NearestEven),
+            ("toward_zero", None) => Ok(Fp16Rounding::TowardZero),
+            ("stochastic", Some(seed)) => Ok(Fp16Rounding::Stochastic { seed }),
+            ("stochastic", None) => Err(SolverError::InvalidInput("stochastic fp16 rounding needs a seed".to_string())),
+            ("nearest_even" | "toward_zero", Some(_)) => Err(SolverError::InvalidInput(format!(
+                "an fp16 rounding seed applies to stochastic rounding, not {}",
+                mode
+            ))),
+            (other, _) => Err(SolverError::InvalidInput(format!(
+                "Unknown fp16 rounding: {} (expected nearest_even, toward_zero or stochastic)",
+                other
+            ))),
+        }
+    }
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Fp16Rounding::NearestEven => "nearest_even",
+            Fp16Rounding::TowardZero => "toward_zero",
+            Fp16Rounding::Stochastic { .. } => "stochastic",
+        }
+    }
+
+    /// binary16 bits of `val`, for element `index` (row-major) of `operand`
+    #[cfg(feature = "fp16")]
+    fn to_f16_bits(self, val: f32, operand: Operand, index: usize) -> u16 {
+        let bits = val.to_bits();
+        let sign = ((bits >> 16) & 0x8000) as u16;
+        let abs = bits & 0x7FFF_FFFF;
+        if abs > 0x7F80_0000 {
+            // NaN: quiet, keeping the top of the payload
+            return sign | 0x7E00 | ((abs >> 13) & 0x3FF) as u16;
+        }
+        if abs >= 0x4780_0000 {
+            // 65536 or more (infinity included): past the largest finite f16
+            return match (self, abs == 0x7F80_0000) {
+                (Fp16Rounding::TowardZero, false) => sign | 0x7BFF,
+                _ => sign | 0x7C00,
+            };
+        }
+        let exponent = (abs >> 23) as i32 - 127;
+        // `base` is the magnitude truncated to f16; the `shift` dropped bits are `rest`.
+        // base + 1 is the next f16 up, carrying into the exponent (and to infinity) as needed.
+        let (base, rest, shift) = if exponent >= -14 {
+            let base = (((exponent + 15) as u32) << 10) | ((abs & 0x7F_FFFF) >> 13);
+            (base, (abs & 0x1FFF) as u64, 13)
+        } else {
+            // f16 subnormal (or zero): units of 2^-24
+            let significand = if abs >= 0x0080_0000 { (abs & 0x7F_FFFF) | 0x80_0000 } else { abs };
+            let shift = (-1 - exponent.max(-126)) as u32;
+            if shift >= 64 {
+                return sign;
+            }
+            let significand = significand as u64;
+            ((significand >> shift) as u32, significand & ((1u64 << shift) - 1), shift)
+        };
+        let half = 1u64 << (shift - 1);
+        let round_up = match self {
+            Fp16Rounding::NearestEven => rest > half || (rest == half && base & 1 == 1),
+            Fp16Rounding::TowardZero => false,
+            // P(round up) = rest / 2^shift, compared on 32 random bits
+            Fp16Rounding::Stochastic { seed } => (stochastic_bits(seed, operand, index) as u64) < (rest << 32) >> shift,
+        };
+        sign | (base + round_up as u32) as u16
+    }
+
+    /// `val` rounded through binary16 under this mode, as an f32
+    #[cfg(feature = "fp16")]
+    #[inline(always)]
+    fn round(self, val: f32, operand: Operand, index: usize) -> f32 {
+        f16_bits_to_f32(self.to_f16_bits(val, operand, index))
+    }
+}
+
+/// 32 random bits for one element under `Fp16Rounding::Stochastic` (a SplitMix64 step)
+#[cfg(feature = "fp16")]
+#[inline(always)]
+fn stochastic_bits(seed: u64, operand: Operand, index: usize) -> u32 {
+    let stream = ((index as u64) << 1) | (operand == Operand::B) as u64;
+    let mut z = seed ^ stream.wrapping_add(1).wrapping_mul(0x9E37_79B9_7F4A_7C15);
+    z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+    z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+    ((z ^ (z >> 31)) >> 32) as u32
+}
+
+/// Exact f32 value of binary16 `bits`
+#[cfg(feature = "fp16")]
+fn f16_bits_to_f32(bits: u16) -> f32 {
+    let sign = ((bits & 0x8000) as u32) << 16;
+    let exponent = ((bits >> 10) & 0x1F) as u32;
+    let mantissa = (bits & 0x3FF) as u32;
+    match exponent {
+        0 => f32::from_bits((mantissa as f32 * 2f32.powi(-24)).to_bits() | sign),
+        0x1F => f32::from_bits(sign | 0x7F80_0000 | (mantissa << 13)),
+        _ => f32::from_bits(sign | ((exponent + 112) << 23) | (mantissa << 13)),
+    }
+}
+
+/// How many times a matmul is computed before its result is trusted (`redundancy::compute_dual`)
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Redundancy {
+    #[default]
+    Single,
+    /// Compute twice and fail with `InconsistentComputation` unless the hashes agree
+    Dual,
+}
+
+impl Redundancy {
+    pub fn parse(s: &str) -> Result<Self, SolverError> {
+        match s {
+            "single" => Ok(Redundancy::Single),
+            "dual" => Ok(Redundancy::Dual),
+            other => Err(SolverError::InvalidInput(format!("Unknown redundancy: {} (expected single or dual)", other))),
+        }
+    }
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Redundancy::Single => "single",
+            Redundancy::Dual => "dual",
+        }
+    }
+}
+
+/// Storage order of `Input::matrix_a`
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum ALayout {
+    /// A itself, m×k row-major
+    #[default]
+    RowMajor,
+    /// Aᵀ, k×m row-major (i.e. A column-major)
+    Transposed,
+}
+
+impl ALayout {
+    /// Parse `Input::a_layout`; None is row-major
+    pub fn parse(s: Option<&str>) -> Result<Self, SolverError> {
+        match s {
+            None | Some("row_major") => Ok(ALayout::RowMajor),
+            Some("transposed") => Ok(ALayout::Transposed),
+            Some(other) => Err(SolverError::InvalidInput(format!(
+                "Unknown a_layout: {} (expected row_major or transposed)",
+                other
+            ))),
+        }
+    }
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ALayout::RowMajor => "row_major",
+            ALayout::Transposed => "transposed",
+        }
+    }
+
+    /// A in the row-major layout every kernel reads
+    fn to_row_major(self, a: &FlatMatrix) -> std::borrow::Cow<'_, FlatMatrix> {
+        match self {
+            ALayout::RowMajor => std::borrow::Cow::Borrowed(a),
+            ALayout::Transposed => std::borrow::Cow::Owned(transpose(a)),
+        }
+    }
+}
+
+/// Storage order of `Input::matrix_b`
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum BLayout {
+    /// B itself, k×n row-major
+    #[default]
+    RowMajor,
+    /// Bᵀ, n×k row-major (i.e. B column-major)
+    Transposed,
+}
+
+impl BLayout {
+    /// Parse `Input::b_layout`; None is row-major
+    pub fn parse(s: Option<&str>) -> Result<Self, SolverError> {
+        match s {
+            None | Some("row_major") => Ok(BLayout::RowMajor),
+            Some("transposed") => Ok(BLayout::Transposed),
+            Some(other) => Err(SolverError::InvalidInput(format!(
+                "Unknown b_layout: {} (expected row_major or transposed)",
+                other
+            ))),
+        }
+    }
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            BLayout::RowMajor => "row_major",
+            BLayout::Transposed => "transposed",
+        }
+    }
+}
+
+/// Transpose of `m`, copied in cache-sized tiles. The 16-column case (Aᵀ of the 16×k
+/// seed-shaped A, as the 16x16 kernels want it) has its own routine.
+pub fn transpose(m: &FlatMatrix) -> FlatMatrix {
+    if m.cols == 16 {
+        return transpose_16_cols(m);
+    }
+    const TILE: usize = 32;
+    let (rows, cols) = (m.rows, m.cols);
+    let mut data = vec![0.0f32; rows * cols];
+    for r0 in (0..rows).step_by(TILE) {
+        for c0 in (0..cols).step_by(TILE) {
+            for r in r0..(r0 + TILE).min(rows) {
+                for c in c0..(c0 + TILE).min(cols) {
+                    data[c * rows + r] = m.data[r * cols + c];
+                }
             }
         }
-        kernel_start.elapsed()
-    };
+    }
+    FlatMatrix { data, rows: cols, cols: rows }
+}
+
+/// k×16 → 16×k: each 64-byte input row scatters to one column of the 16 output rows.
+/// Blocks of k keep the 16 output row segments being written in cache.
+fn transpose_16_cols(m: &FlatMatrix) -> FlatMatrix {
+    const BLOCK: usize = 256;
+    let k = m.rows;
+    let mut data = vec![0.0f32; 16 * k];
+    for p0 in (0..k).step_by(BLOCK) {
+        let p1 = (p0 + BLOCK).min(k);
+        for (i, out_row) in data.chunks_exact_mut(k).enumerate() {
+            for (p, out) in (p0..p1).zip(&mut out_row[p0..p1]) {
+                *out = m.data[p * 16 + i];
+            }
+        }
+    }
+    FlatMatrix { data, rows: 16, cols: k }
+}
 
+#[inline(always)]
+fn matmul_fp32_16x16(
+    a: &FlatMatrix,
+    b: &FlatMatrix,
+    accumulation: Fp32Accumulation,
+    clock: &dyn Clock,
+) -> (FlatMatrix, std::time::Duration) {
+    let mut result_flat = vec![0.0f32; 16 * 16];
+    let start = clock.now();
+    fp32_16x16_accumulate(a, b, accumulation, 0..a.cols, &mut result_flat);
+    let kernel_time = clock.elapsed_since(start);
     (FlatMatrix { data: result_flat, rows: 16, cols: 16 }, kernel_time)
 }
 
-#[cfg(feature = "openblas")]
-fn matmul_fp16_openblas(a: &FlatMatrix, b: &FlatMatrix) -> FlatMatrix {
-    use half::f16;
+/// Add A[:, range] · B[range, :] into the 16x16 accumulator `c`.
+///
+/// Products are grouped four at a time from `range.start`, so splitting k at multiples of
+/// 4 reproduces the single-pass result bit for bit.
+#[inline(always)]
+fn fp32_16x16_accumulate(
+    a: &FlatMatrix,
+    b: &FlatMatrix,
+    accumulation: Fp32Accumulation,
+    range: std::ops::Range<usize>,
+    c: &mut [f32],
+) {
+    let k = a.cols;
+    assert!(c.len() >= 16 * 16 && range.end <= k && b.rows == k && b.cols == 16);
+    let a_ptr = a.data.as_ptr();
+    let c_ptr = c.as_mut_ptr();
+
+    unsafe {
+        let b_ptr = b.data.as_ptr();
+        let mut p = range.start;
+        while p + 3 < range.end {
+            for i in 0..16 {
+                let a_base = i * k;
+                let a0 = *a_ptr.add(a_base + p);
+                let a1 = *a_ptr.add(a_base + p + 1);
+                let a2 = *a_ptr.add(a_base + p + 2);
+                let a3 = *a_ptr.add(a_base + p + 3);
+
+                let c_base = i * 16;
+                let b_base = p * 16;
+                for j in 0..16 {
+                    let b0 = *b_ptr.add(b_base + j);
+                    let b1 = *b_ptr.add(b_base + 16 + j);
+                    let b2 = *b_ptr.add(b_base + 32 + j);
+                    let b3 = *b_ptr.add(b_base + 48 + j);
+                    let c = c_ptr.add(c_base + j);
+                    *c += accumulation.dot4([a0, a1, a2, a3], [b0, b1, b2, b3]);
+                }
+            }
+            p += 4;
+        }
+
+        while p < range.end {
+            for i in 0..16 {
+                let a_ip = *a_ptr.add(i * k + p);
+                let c_base = i * 16;
+                let b_base = p * 16;
+                for j in 0..16 {
+                    let b_pj = *b_ptr.add(b_base + j);
+                    let c = c_ptr.add(c_base + j);
+                    *c = accumulation.fmadd(*c, a_ip, b_pj);
+                }
+            }
+            p += 1;
+        }
+    }
+}
+
+/// fp32 matmul summing each C[i][j] over k with `summation`, Kahan or pairwise (see
+/// `Fp32Summation`). Both walk k in order for a whole row of C at once, so B is read row by
+/// row as in the blocked kernel.
+pub(crate) fn matmul_fp32_summed(a: &FlatMatrix, b: &FlatMatrix, summation: Fp32Summation, clock: &dyn Clock) -> (FlatMatrix, std::time::Duration) {
+    let (m, k, n) = (a.rows, a.cols, b.cols);
+    let mut result = vec![0.0f32; m * n];
+    let start = clock.now();
+    if n > 0 {
+        for (a_row, c_row) in a.data.chunks_exact(k.max(1)).zip(result.chunks_exact_mut(n)) {
+            match summation {
+                Fp32Summation::Naive => {
+                    for (&a_ip, b_row) in a_row.iter().zip(b.data.chunks_exact(n)) {
+                        for (c, &b_pj) in c_row.iter_mut().zip(b_row) {
+                            *c += a_ip * b_pj;
+                        }
+                    }
+                }
+                Fp32Summation::Kahan => {
+                    let mut compensation = vec![0.0f32; n];
+                    for (&a_ip, b_row) in a_row.iter().zip(b.data.chunks_exact(n)) {
+                        for ((c, lost), &b_pj) in c_row.iter_mut().zip(&mut compensation).zip(b_row) {
+                            let y = a_ip * b_pj - *lost;
+                            let t = *c + y;
+                            *lost = (t - *c) - y;
+                            *c = t;
+                        }
+                    }
+                }
+                Fp32Summation::Pairwise => {
+                    // One buffer per level of halving below the top
+                    let (mut levels, mut len) = (0, k);
+                    while len > PAIRWISE_BLOCK {
+                        (levels, len) = (levels + 1, len - len / 2);
+                    }
+                    let mut scratch = vec![0.0f32; n * levels];
+                    pairwise_row(&a_row[..k], b, 0..k, c_row, &mut scratch);
+                }
+            }
+        }
+    }
+    let kernel_time = clock.elapsed_since(start);
+    (FlatMatrix { data: result, rows: m, cols: n }, kernel_time)
+}
+
+/// Add A[i, range] · B[range, :] into the zeroed row `c`: ranges of at most `PAIRWISE_BLOCK`
+/// in k order, longer ones as the sum of their halves (the first half the shorter)
+fn pairwise_row(a_row: &[f32], b: &FlatMatrix, range: std::ops::Range<usize>, c: &mut [f32], scratch: &mut [f32]) {
+    let n = c.len();
+    if range.len() <= PAIRWISE_BLOCK {
+        for p in range {
+            for (c, &b_pj) in c.iter_mut().zip(&b.data[p * n..(p + 1) * n]) {
+                *c += a_row[p] * b_pj;
+            }
+        }
+        return;
+    }
+    let mid = range.start + range.len() / 2;
+    pairwise_row(a_row, b, range.start..mid, c, scratch);
+    let (right, rest) = scratch.split_at_mut(n);
+    right.fill(0.0);
+    pairwise_row(a_row, b, mid..range.end, right, rest);
+    for (c, &r) in c.iter_mut().zip(right.iter()) {
+        *c += r;
+    }
+}
 
+#[cfg(feature = "openblas")]
+fn matmul_fp32_openblas(a: &FlatMatrix, b: &FlatMatrix, clock: &dyn Clock) -> (FlatMatrix, std::time::Duration) {
     let m = a.rows;
     let k = a.cols;
     let n = b.cols;
 
-    // Quantize inputs to fp16 then back to fp32 so BLAS computes on fp16-like values.
-    let a_fp32: Vec<f32> = a
-        .data
-        .iter()
-        .map(|&x| f16::from_f32(x).to_f32())
-        .collect();
-    let b_fp32: Vec<f32> = b
-        .data
-        .iter()
-        .map(|&x| f16::from_f32(x).to_f32())
-        .collect();
-
+    let a_flat = &a.data;
+    let b_flat = &b.data;
     let mut result_flat = vec![0.0f32; m * n];
+
+    let start = clock.now();
     unsafe {
         cblas_sgemm(
             CBLAS_ORDER::CblasRowMajor,
@@ -725,235 +2373,209 @@ fn matmul_fp16_openblas(a: &FlatMatrix, b: &FlatMatrix) -> FlatMatrix {
             n as i32,
             k as i32,
             1.0,
-            a_fp32.as_ptr(),
+            a_flat.as_ptr(),
             k as i32,
-            b_fp32.as_ptr(),
+            b_flat.as_ptr(),
             n as i32,
             0.0,
             result_flat.as_mut_ptr(),
             n as i32,
         );
     }
+    let kernel_time = clock.elapsed_since(start);
 
-    FlatMatrix { data: result_flat, rows: m, cols: n }
+    (FlatMatrix { data: result_flat, rows: m, cols: n }, kernel_time)
 }
 
-fn matmul_int8(a: &FlatMatrix, b: &FlatMatrix) -> FlatMatrix {
-    let m = a.rows;
-    let k = a.cols;
-    let n = b.cols;
-    
-    // Convert to int8 (flat layout)
-    let scale_a = 127.0 / a.data.iter().map(|&x| x.abs()).fold(0.0f32, f32::max);
-    let scale_b = 127.0 / b.data.iter().map(|&x| x.abs()).fold(0.0f32, f32::max);
-    
-    let a_int8: Vec<i8> = a.data.iter()
-        .map(|&x| (x * scale_a).clamp(-128.0, 127.0) as i8)
-        .collect();
-    
-    let b_int8: Vec<i8> = b.data.iter()
-        .map(|&x| (x * scale_b).clamp(-128.0, 127.0) as i8)
-        .collect();
-    
-    let mut result_int32 = vec![0i32; m * n];
-    
-    // Optimized loop order: i -> p -> j
-    // This streams across B[p, :] (contiguous) and C[i, :] (contiguous)
-    // Hoisting a_ip out of inner loop for better register reuse
-    for i in 0..m {
-        let c_base = i * n;
-        let a_base = i * k;
-        for p in 0..k {
-            let a_ip = a_int8[a_base + p] as i32;
-            let b_base = p * n;
-            for j in 0..n {
-                result_int32[c_base + j] += a_ip * b_int8[b_base + j] as i32;
-            }
-        }
-    }
-    
-    // Convert back to fp32 with proper scaling (flat layout)
-    let scale_result = 1.0 / (scale_a * scale_b);
-    let result_flat: Vec<f32> = result_int32.iter()
-        .map(|&x| x as f32 * scale_result)
-        .collect();
-    
-    FlatMatrix { data: result_flat, rows: m, cols: n }
+/// fp32 product with the automatically selected kernel
+#[cfg(test)]
+fn matmul_fp32(a: &FlatMatrix, b: &FlatMatrix) -> (FlatMatrix, std::time::Duration) {
+    let options = ComputeOptions::default();
+    let (_, kernel) = kernels::resolve_choice("fp32", (a.rows, a.cols), (b.rows, b.cols), &options).unwrap();
+    let out = kernel.run(a, b, &options).unwrap();
+    (out.result, out.kernel_time)
 }
 
-/// u8*i8 matrix multiplication (unsigned 8-bit × signed 8-bit)
-/// matrix_a is interpreted as u8 (0-255), matrix_b as i8 (-128 to 127)
-/// This matches the seed workload specification where matrices come from raw binary
-pub fn matmul_u8i8(a: &FlatMatrix, b: &FlatMatrix) -> FlatMatrix {
+/// fp16 product of any shape: operands rounded to f16 (per `rounding`), products summed in
+/// f32 in k order, as `matmul_fp16_16x16` does. Summing in f16 would overflow at ±65504 and
+/// lose most of the precision over a long reduction.
+#[cfg(feature = "fp16")]
+fn matmul_fp16(a: &FlatMatrix, b: &FlatMatrix, rounding: Fp16Rounding) -> FlatMatrix {
     let m = a.rows;
     let k = a.cols;
     let n = b.cols;
     
-    // For u8i8, assume matrix_a values are 0..255 and matrix_b values are -128..127.
-    // This matches the seed pipeline where bytes are already interpreted as u8/i8.
-    let a_u8: Vec<u8> = a.data.iter().map(|&x| x as u8).collect();
-    let b_i8: Vec<i8> = b.data.iter().map(|&x| x as i8).collect();
+    // f16 values held in f32 (flat layout)
+    let a_q: Vec<f32> = a.data.iter().enumerate().map(|(i, &x)| rounding.round(x, Operand::A, i)).collect();
+    let b_q: Vec<f32> = b.data.iter().enumerate().map(|(i, &x)| rounding.round(x, Operand::B, i)).collect();
     
-    let mut result_int32 = vec![0i32; m * n];
+    let mut result_flat = vec![0.0f32; m * n];
     
     // Optimized loop order: i -> p -> j
-    // u8 * i8 multiplication: u8 is promoted to i32, i8 is promoted to i32
+    // This streams across B[p, :] (contiguous) and C[i, :] (contiguous)
+    // Hoisting a_ip out of inner loop for better register reuse
     for i in 0..m {
         let c_base = i * n;
         let a_base = i * k;
         for p in 0..k {
-            let a_ip = a_u8[a_base + p] as i32;  // u8 -> i32
+            let a_ip = a_q[a_base + p];
             let b_base = p * n;
             for j in 0..n {
-                result_int32[c_base + j] += a_ip * b_i8[b_base + j] as i32;  // i8 -> i32
+                result_flat[c_base + j] += a_ip * b_q[b_base + j];
             }
         }
     }
     
-    // Convert result back to f32 (no scaling needed for u8*i8, result is already correct)
-    let result_flat: Vec<f32> = result_int32.iter()
-        .map(|&x| x as f32)
-        .collect();
-    
     FlatMatrix { data: result_flat, rows: m, cols: n }
 }
 
-/// Optimized u8*i8 for 16x16 result (seed dimensions: 16×50240 × 50240×16 = 16×16)
+#[cfg(feature = "fp16")]
 #[inline(always)]
-pub fn matmul_u8i8_16x16(a: &FlatMatrix, b: &FlatMatrix) -> (FlatMatrix, std::time::Duration) {
-    let k = a.cols;  // Should be 50240 for seed dimensions
+fn matmul_fp16_16x16(
+    a: &FlatMatrix,
+    b: BOperand,
+    columns: Option<&[usize]>,
+    rounding: Fp16Rounding,
+    threads: Option<usize>,
+    clock: &dyn Clock,
+) -> (FlatMatrix, std::time::Duration, usize) {
+    let k = a.cols;
+    // Dot-product formulation: a column subset just restricts j
+    let columns: Vec<usize> = columns.map_or_else(|| (0..16).collect(), |c| c.to_vec());
+    let n_out = columns.len();
 
-    let mut result_i32 = vec![0i32; 16 * 16];
-    let c_ptr = result_i32.as_mut_ptr();
+    let mut result_flat = vec![0.0f32; 16 * n_out];
+    let a_ptr = a.data.as_ptr();
 
-    let kernel_time = unsafe {
-        let mut a_u8 = AlignedBufferU8::new(16 * k, 64);
-        let a_u8_ptr = a_u8.as_mut_ptr();
-        let a_ptr = a.data.as_ptr();
+    let (kernel_time, used) = unsafe {
+        let mut a_q = AlignedBufferF32::new(16 * k, 64);
+        let a_q_ptr = a_q.as_mut_ptr();
         for i in 0..16 {
             let a_base = i * k;
             for p in 0..k {
-                *a_u8_ptr.add(a_base + p) = *a_ptr.add(a_base + p) as u8;
+                let val = *a_ptr.add(a_base + p);
+                *a_q_ptr.add(a_base + p) = rounding.round(val, Operand::A, a_base + p);
             }
         }
 
-        let mut b_i8 = AlignedBufferI8::new(k * 16, 64);
-        let b_i8_ptr = b_i8.as_mut_ptr();
-        let b_ptr = b.data.as_ptr();
-        for p in 0..k {
-            let b_base = p * 16;
-            for j in 0..16 {
-                *b_i8_ptr.add(b_base + j) = *b_ptr.add(b_base + j) as i8;
+        let a_q_ptr = a_q.as_ptr();
+        let packed = match b {
+            BOperand::RowMajor(b, caches) => get_bt_fp16_cache(b, caches, rounding),
+            BOperand::Transposed(b_t) => Arc::new(pack_bt_fp16(b_t, rounding)),
+            BOperand::Uncached(b) => Arc::new(pack_b_fp16(b, rounding)),
+        };
+        let b_t_ptr = packed.as_ptr();
+
+        let (a_q, b_t) = (std::slice::from_raw_parts(a_q_ptr, 16 * k), std::slice::from_raw_parts(b_t_ptr, 16 * k));
+        let kernel_start = clock.now();
+        let used = seed_row_groups(&mut result_flat, n_out, 1, threads, |first_row, c_rows| {
+            for (r, c_row) in c_rows.chunks_exact_mut(n_out).enumerate() {
+                let a_row = a_q[(first_row + r) * k..].as_ptr();
+                for (c, &j) in c_row.iter_mut().zip(&columns) {
+                    *c = dot_f32(a_row, b_t[j * k..].as_ptr(), k);
+                }
             }
-        }
+        });
+        (clock.elapsed_since(kernel_start), used)
+    };
 
-        let a_u8_ptr = a_u8.as_ptr();
-        let b_i8_ptr = b_i8.as_ptr();
+    (FlatMatrix { data: result_flat, rows: 16, cols: n_out }, kernel_time, used)
+}
 
-        let kernel_start = Instant::now();
-        for i in 0..16 {
-            let a_row = a_u8_ptr.add(i * k);
-            let c_base = i * 16;
-            #[cfg(target_arch = "aarch64")]
-            {
-                let mut c0 = vdupq_n_s32(0);
-                let mut c1 = vdupq_n_s32(0);
-                let mut c2 = vdupq_n_s32(0);
-                let mut c3 = vdupq_n_s32(0);
-                for p in 0..k {
-                    let a_ip = *a_row.add(p) as i16;
-                    let b_vec = vld1q_s8(b_i8_ptr.add(p * 16));
-                    let b_low = vmovl_s8(vget_low_s8(b_vec));
-                    let b_high = vmovl_s8(vget_high_s8(b_vec));
-                    c0 = vmlal_n_s16(c0, vget_low_s16(b_low), a_ip);
-                    c1 = vmlal_n_s16(c1, vget_high_s16(b_low), a_ip);
-                    c2 = vmlal_n_s16(c2, vget_low_s16(b_high), a_ip);
-                    c3 = vmlal_n_s16(c3, vget_high_s16(b_high), a_ip);
-                }
-                vst1q_s32(c_ptr.add(c_base), c0);
-                vst1q_s32(c_ptr.add(c_base + 4), c1);
-                vst1q_s32(c_ptr.add(c_base + 8), c2);
-                vst1q_s32(c_ptr.add(c_base + 12), c3);
-            }
-            #[cfg(not(target_arch = "aarch64"))]
-            {
-                for p in 0..k {
-                    let a_ip = *a_row.add(p) as i32;
-                    let b_base = p * 16;
-                    for j in 0..16 {
-                        let b_pj = *b_i8_ptr.add(b_base + j) as i32;
-                        *c_ptr.add(c_base + j) += a_ip * b_pj;
-                    }
-                }
+/// bf16 product of any shape: operands truncated to bf16, products summed in f32 in k order
+fn matmul_bf16(a: &FlatMatrix, b: &FlatMatrix) -> FlatMatrix {
+    let m = a.rows;
+    let k = a.cols;
+    let n = b.cols;
+
+    let a_q: Vec<f32> = a.data.iter().map(|&x| round_bf16(x)).collect();
+    let b_q: Vec<f32> = b.data.iter().map(|&x| round_bf16(x)).collect();
+
+    let mut result_flat = vec![0.0f32; m * n];
+    for i in 0..m {
+        let c_row = &mut result_flat[i * n..(i + 1) * n];
+        for p in 0..k {
+            let a_ip = a_q[i * k + p];
+            for (c, &b_pj) in c_row.iter_mut().zip(&b_q[p * n..(p + 1) * n]) {
+                *c += a_ip * b_pj;
             }
         }
-        kernel_start.elapsed()
-    };
+    }
 
-    let result_f32: Vec<f32> = result_i32.iter().map(|&x| x as f32).collect();
-    (FlatMatrix { data: result_f32, rows: 16, cols: 16 }, kernel_time)
+    FlatMatrix { data: result_flat, rows: m, cols: n }
+}
+
+/// Add `a_row[q] * b_t[j * stride + q]` for q in `0..len`, in q order, to `acc[jo]` for each
+/// column `j = columns[jo]`. `matmul_bf16` adds the same products in the same order, so the
+/// bf16 16x16 kernels (chunked or not) match it bit for bit.
+#[inline(always)]
+unsafe fn bf16_accumulate_row(a_row: *const f32, b_t: *const f32, stride: usize, len: usize, columns: &[usize], acc: &mut [f32]) {
+    for q in 0..len {
+        let a_iq = *a_row.add(q);
+        for (jo, &j) in columns.iter().enumerate() {
+            acc[jo] += a_iq * *b_t.add(j * stride + q);
+        }
+    }
 }
 
 #[inline(always)]
-fn matmul_int8_16x16(a: &FlatMatrix, b: &FlatMatrix) -> (FlatMatrix, std::time::Duration) {
+fn matmul_bf16_16x16(
+    a: &FlatMatrix,
+    b: BOperand,
+    columns: Option<&[usize]>,
+    clock: &dyn Clock,
+) -> (FlatMatrix, std::time::Duration) {
     let k = a.cols;
-    let max_a = a.data.iter().map(|&x| x.abs()).fold(0.0f32, f32::max);
-    let scale_a = if max_a == 0.0 { 1.0 } else { 127.0 / max_a };
-    let (b_t_ptr, scale_b, _) = get_bt_i8_cache(b);
-    let scale_result = 1.0 / (scale_a * scale_b);
+    let columns: Vec<usize> = columns.map_or_else(|| (0..16).collect(), |c| c.to_vec());
+    let n_out = columns.len();
 
-    let mut result_flat = vec![0.0f32; 16 * 16];
+    let mut result_flat = vec![0.0f32; 16 * n_out];
     let a_ptr = a.data.as_ptr();
-    let c_ptr = result_flat.as_mut_ptr();
 
     let kernel_time = unsafe {
-        let mut a_q = AlignedBufferI8::new(16 * k, 64);
+        let mut a_q = AlignedBufferF32::new(16 * k, 64);
         let a_q_ptr = a_q.as_mut_ptr();
-        for i in 0..16 {
-            let a_base = i * k;
-            for p in 0..k {
-                let val = *a_ptr.add(a_base + p);
-                *a_q_ptr.add(a_base + p) = (val * scale_a).clamp(-128.0, 127.0) as i8;
-            }
+        for p in 0..16 * k {
+            *a_q_ptr.add(p) = round_bf16(*a_ptr.add(p));
         }
 
         let a_q_ptr = a_q.as_ptr();
+        let packed = match b {
+            BOperand::RowMajor(b, caches) => get_bt_bf16_cache(b, caches),
+            BOperand::Transposed(b_t) => Arc::new(pack_bt_bf16(b_t)),
+            BOperand::Uncached(b) => Arc::new(pack_b_bf16(b)),
+        };
+        let b_t_ptr = packed.as_ptr();
 
-        let kernel_start = Instant::now();
+        let kernel_start = clock.now();
         for i in 0..16 {
-            let a_row = a_q_ptr.add(i * k);
-            let c_base = i * 16;
-            for j in 0..16 {
-                let b_row = b_t_ptr.add(j * k);
-                let acc = dot_i8(a_row, b_row, k);
-                *c_ptr.add(c_base + j) = acc as f32 * scale_result;
-            }
+            let acc = &mut result_flat[i * n_out..(i + 1) * n_out];
+            bf16_accumulate_row(a_q_ptr.add(i * k), b_t_ptr, k, k, &columns, acc);
         }
-        kernel_start.elapsed()
+        clock.elapsed_since(kernel_start)
     };
 
-    (FlatMatrix { data: result_flat, rows: 16, cols: 16 }, kernel_time)
+    (FlatMatrix { data: result_flat, rows: 16, cols: n_out }, kernel_time)
 }
 
-#[cfg(feature = "openblas")]
-fn matmul_int8_openblas(a: &FlatMatrix, b: &FlatMatrix) -> FlatMatrix {
+#[cfg(all(feature = "openblas", feature = "fp16"))]
+fn matmul_fp16_openblas(a: &FlatMatrix, b: &FlatMatrix, rounding: Fp16Rounding) -> FlatMatrix {
     let m = a.rows;
     let k = a.cols;
     let n = b.cols;
 
-    let scale_a = 127.0 / a.data.iter().map(|&x| x.abs()).fold(0.0f32, f32::max);
-    let scale_b = 127.0 / b.data.iter().map(|&x| x.abs()).fold(0.0f32, f32::max);
-
-    // Quantize to int8, then convert to f32 for BLAS.
-    let a_q: Vec<f32> = a
+    // Quantize inputs to fp16 then back to fp32 so BLAS computes on fp16-like values.
+    let a_fp32: Vec<f32> = a
         .data
         .iter()
-        .map(|&x| (x * scale_a).clamp(-128.0, 127.0) as i8 as f32)
+        .enumerate()
+        .map(|(i, &x)| rounding.round(x, Operand::A, i))
         .collect();
-    let b_q: Vec<f32> = b
+    let b_fp32: Vec<f32> = b
         .data
         .iter()
-        .map(|&x| (x * scale_b).clamp(-128.0, 127.0) as i8 as f32)
+        .enumerate()
+        .map(|(i, &x)| rounding.round(x, Operand::B, i))
         .collect();
 
     let mut result_flat = vec![0.0f32; m * n];
@@ -966,9 +2588,9 @@ fn matmul_int8_openblas(a: &FlatMatrix, b: &FlatMatrix) -> FlatMatrix {
             n as i32,
             k as i32,
             1.0,
-            a_q.as_ptr(),
+            a_fp32.as_ptr(),
             k as i32,
-            b_q.as_ptr(),
+            b_fp32.as_ptr(),
             n as i32,
             0.0,
             result_flat.as_mut_ptr(),
@@ -976,203 +2598,2490 @@ fn matmul_int8_openblas(a: &FlatMatrix, b: &FlatMatrix) -> FlatMatrix {
         );
     }
 
-    // Scale back to match int8 quantization semantics.
-    let scale_result = 1.0 / (scale_a * scale_b);
-    for val in &mut result_flat {
-        *val *= scale_result;
-    }
-
     FlatMatrix { data: result_flat, rows: m, cols: n }
 }
 
-fn compute_hash(matrix: &FlatMatrix) -> String {
-    let mut hasher = Sha256::new();
+fn matmul_int8(a: &FlatMatrix, b: &FlatMatrix, scaling: Int8Scaling) -> FlatMatrix {
+    let m = a.rows;
+    let k = a.cols;
+    let n = b.cols;
     
-    // Hash flat data directly - same order as Vec<Vec<f32>> (row-major)
-    for &val in &matrix.data {
-        let bytes = val.to_le_bytes();
-        hasher.update(&bytes);
+    // Convert to int8 (flat layout)
+    let scales_a = scaling.a_scales(a);
+    let scale_b = int8_scale(b.data.iter().copied());
+    
+    let a_int8: Vec<i8> = a.data.iter()
+        .enumerate()
+        .map(|(idx, &x)| quantize_i8(x, scales_a[idx / k]))
+        .collect();
+    
+    let b_int8: Vec<i8> = b.data.iter()
+        .map(|&x| quantize_i8(x, scale_b))
+        .collect();
+    
+    let mut result_int32 = vec![0i32; m * n];
+    
+    // Optimized loop order: i -> p -> j
+    // This streams across B[p, :] (contiguous) and C[i, :] (contiguous)
+    // Hoisting a_ip out of inner loop for better register reuse
+    for i in 0..m {
+        let c_base = i * n;
+        let a_base = i * k;
+        for p in 0..k {
+            let a_ip = a_int8[a_base + p] as i32;
+            let b_base = p * n;
+            for j in 0..n {
+                result_int32[c_base + j] += a_ip * b_int8[b_base + j] as i32;
+            }
+        }
     }
     
-    hex::encode(hasher.finalize())
+    // Convert back to fp32 with each row's scale (flat layout)
+    let scale_results: Vec<f32> = scales_a.iter().map(|&scale_a| 1.0 / (scale_a * scale_b)).collect();
+    let result_flat: Vec<f32> = result_int32.iter()
+        .enumerate()
+        .map(|(idx, &x)| x as f32 * scale_results[idx / n])
+        .collect();
+    
+    FlatMatrix { data: result_flat, rows: m, cols: n }
 }
 
-fn estimate_memory_usage(rows_a: usize, cols_a: usize, rows_b: usize, cols_b: usize) -> f64 {
-    // Rough estimate: input matrices + output matrix (all as f32)
-    let input_size = (rows_a * cols_a + rows_b * cols_b) * 4; // 4 bytes per f32
-    let output_size = rows_a * cols_b * 4;
-    (input_size + output_size) as f64 / (1024.0 * 1024.0) // Convert to MB
+/// int8 matrix-vector product, for A 1×k or B k×1: quantized and scaled back exactly as
+/// `matmul_int8`, so the results are identical, but each output is one `dot_i8` over
+/// contiguous rows: a row of A against B quantized transposed (B k×1 already is one row,
+/// and A 1×k meets each of B's n columns as a row of Bᵀ)
+fn matmul_int8_gemv(a: &FlatMatrix, b: &FlatMatrix, scaling: Int8Scaling) -> FlatMatrix {
+    let (m, k, n) = (a.rows, a.cols, b.cols);
+    let scales_a = scaling.a_scales(a);
+    let scale_b = int8_scale(b.data.iter().copied());
+    let a_int8: Vec<i8> = a.data.iter().enumerate().map(|(idx, &x)| quantize_i8(x, scales_a[idx / k])).collect();
+    let mut b_t = vec![0i8; n * k];
+    for (idx, &x) in b.data.iter().enumerate() {
+        b_t[(idx % n) * k + idx / n] = quantize_i8(x, scale_b);
+    }
+
+    let mut result_flat = Vec::with_capacity(m * n);
+    for (i, &scale_a) in scales_a.iter().enumerate() {
+        let a_row = a_int8[i * k..].as_ptr();
+        let scale_result = 1.0 / (scale_a * scale_b);
+        for j in 0..n {
+            result_flat.push(dot_i8(a_row, b_t[j * k..].as_ptr(), k) as f32 * scale_result);
+        }
+    }
+    FlatMatrix { data: result_flat, rows: m, cols: n }
 }
 
-// Shared computation function that can be used by both CLI and API
-pub fn compute_workload(input: types::Input) -> Result<types::Output, String> {
-    let workload_type = input.workload_type.as_deref().unwrap_or("matmul");
-    
-    match workload_type {
-        "matmul" => {
-            compute_matmul_internal(input.matrix_a, input.matrix_b, &input.precision, &input.metadata)
+/// int4 product of any shape: A and B quantized to 4 bits with one scale each and packed
+/// two per byte, multiplied exactly in i32 and scaled back as in `matmul_int8`
+fn matmul_int4(a: &FlatMatrix, b: &FlatMatrix) -> FlatMatrix {
+    let m = a.rows;
+    let k = a.cols;
+    let n = b.cols;
+
+    let (a_packed, scale_a) = pack_int4(a.data.iter().copied());
+    let (b_packed, scale_b) = pack_int4(b.data.iter().copied());
+    let (a_q, b_q) = (a_packed.unpack(), b_packed.unpack());
+    let (a_q, b_q) = (a_q.as_slice(), b_q.as_slice());
+
+    let mut result_int32 = vec![0i32; m * n];
+    for i in 0..m {
+        let c_row = &mut result_int32[i * n..(i + 1) * n];
+        for p in 0..k {
+            let a_ip = a_q[i * k + p] as i32;
+            for (c, &b_pj) in c_row.iter_mut().zip(&b_q[p * n..(p + 1) * n]) {
+                *c += a_ip * b_pj as i32;
+            }
         }
-        // Future workloads will be handled here when schemas are provided:
-        // "convolution" => { compute_convolution(...) }
-        // "attention" => { compute_attention(...) }
-        // "inference" => { compute_inference(...) }
-        _ => Err(format!("Unsupported workload type: {}. Currently only 'matmul' is supported.", workload_type)),
     }
+
+    let scale_result = 1.0 / (scale_a * scale_b);
+    let result_flat: Vec<f32> = result_int32.iter().map(|&x| x as f32 * scale_result).collect();
+    FlatMatrix { data: result_flat, rows: m, cols: n }
 }
 
-fn compute_matmul_internal(
-    matrix_a: FlatMatrix,
-    matrix_b: FlatMatrix,
-    precision: &str,
-    metadata: &Option<types::InputMetadata>,
-) -> Result<types::Output, String> {
-    let rows_a = matrix_a.rows;
-    let cols_a = matrix_a.cols;
-    let rows_b = matrix_b.rows;
-    let cols_b = matrix_b.cols;
-    
-    if cols_a != rows_b {
-        return Err(format!("Matrix dimensions incompatible: A is {}x{}, B is {}x{}", 
-            rows_a, cols_a, rows_b, cols_b));
+/// int8_asym product of any shape: A quantized to u8 codes with a zero point, B to
+/// symmetric i8, multiplied exactly in i64 (u8·i8 sums overflow i32 past `U8I8_SAFE_K`)
+/// and corrected by `zero_point * column_sum(B)`
+fn matmul_int8_asym(a: &FlatMatrix, b: &FlatMatrix) -> FlatMatrix {
+    let m = a.rows;
+    let k = a.cols;
+    let n = b.cols;
+
+    let quant_a = AsymmetricU8::of(a.data.iter().copied());
+    let scale_b = int8_scale(b.data.iter().copied());
+    let a_u8: Vec<u8> = a.data.iter().map(|&x| quant_a.quantize(x)).collect();
+    let b_int8: Vec<i8> = b.data.iter().map(|&x| quantize_i8(x, scale_b)).collect();
+
+    let mut result_i64 = vec![0i64; m * n];
+    for i in 0..m {
+        let c_row = &mut result_i64[i * n..(i + 1) * n];
+        for p in 0..k {
+            let a_ip = a_u8[i * k + p] as i32;
+            for (c, &b_pj) in c_row.iter_mut().zip(&b_int8[p * n..(p + 1) * n]) {
+                *c += (a_ip * b_pj as i32) as i64;
+            }
+        }
     }
+    let mut col_sums = vec![0i32; n];
+    for row in b_int8.chunks_exact(n.max(1)) {
+        for (sum, &x) in col_sums.iter_mut().zip(row) {
+            *sum += x as i32;
+        }
+    }
+
+    let scale_result = 1.0 / (quant_a.scale * scale_b);
+    let result_flat: Vec<f32> = result_i64
+        .iter()
+        .enumerate()
+        .map(|(idx, &acc)| quant_a.dequantize(acc, col_sums[idx % n], scale_result))
+        .collect();
+    FlatMatrix { data: result_flat, rows: m, cols: n }
+}
+
+#[deprecated(since = "0.2.0", note = "internal kernel; use compute_workload_with_options with ComputeOptions::kernel = Some(\"u8i8_generic\")")]
+pub fn matmul_u8i8(a: &FlatMatrix, b: &FlatMatrix) -> FlatMatrix {
+    matmul_u8i8_generic(a, b)
+}
+
+/// u8*i8 matrix multiplication (unsigned 8-bit × signed 8-bit)
+/// matrix_a is interpreted as u8 (0-255), matrix_b as i8 (-128 to 127)
+/// This matches the seed workload specification where matrices come from raw binary
+pub(crate) fn matmul_u8i8_generic(a: &FlatMatrix, b: &FlatMatrix) -> FlatMatrix {
+    i32_to_f32_matrix(&matmul_u8i8_generic_i32(a, b), a.rows, b.cols)
+}
+
+/// `matmul_u8i8_generic` keeping the exact i32 accumulators (k must not exceed `U8I8_SAFE_K`)
+pub(crate) fn matmul_u8i8_generic_i32(a: &FlatMatrix, b: &FlatMatrix) -> Vec<i32> {
+    u8i8_generic_sums(a, b)
+}
+
+/// `matmul_u8i8_generic` summing in i64, exact for any k
+pub(crate) fn matmul_u8i8_generic_i64(a: &FlatMatrix, b: &FlatMatrix) -> Vec<i64> {
+    u8i8_generic_sums(a, b)
+}
+
+fn u8i8_generic_sums<T: Copy + Default + From<i32> + std::ops::AddAssign>(a: &FlatMatrix, b: &FlatMatrix) -> Vec<T> {
+    let m = a.rows;
+    let k = a.cols;
+    let n = b.cols;
     
-    // Perform matrix multiplication with timing
-    // Fast 16x16 kernels use kernel-only timing; fallback paths include conversion overhead.
-    let (result, elapsed) = match precision {
-        "fp32" => {
-            let (res, kernel_time) = matmul_fp32(&matrix_a, &matrix_b);
-            (res, kernel_time)
-        },
-        "fp16" => {
-            let (res, elapsed) = if matrix_a.rows == 16 && matrix_b.cols == 16 {
-                matmul_fp16_16x16(&matrix_a, &matrix_b)
-            } else {
-                let start = Instant::now();
-                #[cfg(feature = "openblas")]
-                let res = matmul_fp16_openblas(&matrix_a, &matrix_b);
-                #[cfg(not(feature = "openblas"))]
-                let res = matmul_fp16(&matrix_a, &matrix_b);
-                (res, start.elapsed())
-            };
-            (res, elapsed)
-        },
-        "int8" => {
-            let (res, elapsed) = if matrix_a.rows == 16 && matrix_b.cols == 16 {
-                matmul_int8_16x16(&matrix_a, &matrix_b)
-            } else {
-                let start = Instant::now();
-                #[cfg(feature = "openblas")]
-                let res = matmul_int8_openblas(&matrix_a, &matrix_b);
-                #[cfg(not(feature = "openblas"))]
-                let res = matmul_int8(&matrix_a, &matrix_b);
-                (res, start.elapsed())
-            };
-            (res, elapsed)
-        },
-        "u8i8" => {
-            // u8*i8: matrix_a as u8 (unsigned), matrix_b as i8 (signed)
-            // Optimized path for seed dimensions (16×50240 × 50240×16 = 16×16)
-            let (res, elapsed) = if matrix_a.rows == 16 && matrix_b.cols == 16 {
-                matmul_u8i8_16x16(&matrix_a, &matrix_b)
-            } else {
-                let start = Instant::now();
-                let res = matmul_u8i8(&matrix_a, &matrix_b);
-                (res, start.elapsed())
-            };
-            (res, elapsed)
-        },
-        _ => return Err(format!("Unsupported precision: {}", precision)),
-    };
-    
-    // Compute metrics
-    let latency_ms = elapsed.as_secs_f64() * 1000.0;
-    let total_ops = (rows_a * cols_a * cols_b) as f64; // Multiply-add operations
-    let ops_per_second = total_ops / elapsed.as_secs_f64();
-    let throughput_ops_per_sec = ops_per_second;
+    // For u8i8, assume matrix_a values are 0..255 and matrix_b values are -128..127.
+    // This matches the seed pipeline where bytes are already interpreted as u8/i8.
+    let a_u8: Vec<u8> = a.data.iter().map(|&x| to_u8(x)).collect();
+    let b_i8: Vec<i8> = b.data.iter().map(|&x| x as i8).collect();
     
-    // Compute result hash
-    let result_hash = compute_hash(&result);
+    let mut result_int32 = vec![T::default(); m * n];
     
-    // Estimate memory usage
-    let memory_usage_mb = Some(estimate_memory_usage(rows_a, cols_a, rows_b, cols_b));
+    // Optimized loop order: i -> p -> j
+    // u8 * i8 multiplication: u8 is promoted to i32, i8 is promoted to i32
+    for i in 0..m {
+        let c_base = i * n;
+        let a_base = i * k;
+        for p in 0..k {
+            let a_ip = a_u8[a_base + p] as i32;  // u8 -> i32
+            let b_base = p * n;
+            for j in 0..n {
+                result_int32[c_base + j] += T::from(a_ip * b_i8[b_base + j] as i32);  // i8 -> i32
+            }
+        }
+    }
     
-    // Build output
-    Ok(types::Output {
-        result_matrix: result,
-        result_hash,
-        metrics: types::Metrics {
-            latency_ms,
-            throughput_ops_per_sec,
-            ops_per_second,
-            memory_usage_mb,
-            parse_time_ms: None,  // Set by caller (main.rs)
-            kernel_time_ms: Some(elapsed.as_secs_f64() * 1000.0),
-            serialize_time_ms: None,  // Set by caller (main.rs)
-        },
-        metadata: types::OutputMetadata {
-            precision: precision.to_string(),
-            matrix_a_shape: (rows_a, cols_a),
-            matrix_b_shape: (rows_b, cols_b),
-            result_shape: (rows_a, cols_b),
-            compiler_flags: metadata.as_ref().and_then(|m| m.compiler_flags.clone()),
-            libraries: metadata.as_ref().and_then(|m| m.libraries.clone()),
-        },
-    })
+    result_int32
 }
 
-/// Helper function to add timing breakdown to metrics
-pub fn add_timing_breakdown(
-    mut output: types::Output,
-    parse_time_ms: Option<f64>,
-    serialize_time_ms: Option<f64>,
-) -> types::Output {
-    output.metrics.parse_time_ms = parse_time_ms;
-    output.metrics.serialize_time_ms = serialize_time_ms;
-    output
+/// Optimized u8*i8 for 16x16 result (seed dimensions: 16×50240 × 50240×16 = 16×16).
+/// Panics on any other shape, or when a matrix's `data` is not `rows * cols` long.
+#[deprecated(since = "0.2.0", note = "internal kernel; use compute_workload_with_options with ComputeOptions::kernel = Some(\"u8i8_16x16\")")]
+pub fn matmul_u8i8_16x16(a: &FlatMatrix, b: &FlatMatrix) -> (FlatMatrix, std::time::Duration) {
+    check_16x16_operands(a, b).unwrap_or_else(|err| panic!("{}", err));
+    let b_i8 = pack_b_u8i8(b);
+    matmul_u8i8_16x16_packed(a, &b_i8, &SystemClock)
 }
 
-// Keep old function name for backward compatibility
-pub fn compute_matmul(input: types::Input) -> Result<types::Output, String> {
-    compute_workload(input)
+/// Pack B (k×16) into the aligned row-major i8 layout the u8i8 16x16 kernel reads
+fn pack_b_u8i8(b: &FlatMatrix) -> AlignedBufferI8 {
+    let mut b_i8 = AlignedBufferI8::new(b.rows * 16, 64);
+    pack_b_u8i8_rows(b, 0..b.rows, &mut b_i8);
+    b_i8
 }
 
-/// Verify correctness of a result by recomputing and comparing hashes
-pub fn verify_correctness(
-    matrix_a: &FlatMatrix,
-    matrix_b: &FlatMatrix,
-    precision: &str,
-    expected_hash: &str,
-) -> Result<bool, String> {
-    let result = match precision {
-        "fp32" => {
-            let (res, _) = matmul_fp32(matrix_a, matrix_b);
-            res
-        },
-        "fp16" => matmul_fp16(matrix_a, matrix_b),
-        "int8" => matmul_int8(matrix_a, matrix_b),
-        _ => return Err(format!("Unsupported precision: {}", precision)),
-    };
-    
-    let computed_hash = compute_hash(&result);
-    Ok(computed_hash == expected_hash)
+/// Pack rows `range` of B into the front of `b_i8` (the slice's rows start at 0)
+fn pack_b_u8i8_rows(b: &FlatMatrix, range: std::ops::Range<usize>, b_i8: &mut AlignedBufferI8) {
+    assert!(b.check().is_ok() && range.end <= b.rows && b.cols == 16 && range.len() * 16 <= b_i8.len);
+    let b_i8_ptr = b_i8.as_mut_ptr();
+    let b_ptr = b.data.as_ptr();
+    unsafe {
+        for (q, p) in range.enumerate() {
+            for j in 0..16 {
+                *b_i8_ptr.add(q * 16 + j) = *b_ptr.add(p * 16 + j) as i8;
+            }
+        }
+    }
 }
 
-#[cfg(test)]
-mod tests {
-    use super::*;
-    
-    // Helper function to create FlatMatrix from Vec<Vec<f32>> for tests
-    fn to_flat_matrix(nested: Vec<Vec<f32>>) -> FlatMatrix {
-        let rows = nested.len();
-        if rows == 0 {
-            return FlatMatrix { data: Vec::new(), rows: 0, cols: 0 };
-        }
-        let cols = nested[0].len();
-        let mut data = Vec::with_capacity(rows * cols);
-        for row in nested {
-            data.extend_from_slice(&row);
-        }
-        FlatMatrix { data, rows, cols }
+#[inline(always)]
+fn matmul_u8i8_16x16_packed(a: &FlatMatrix, b_i8: &AlignedBufferI8, clock: &dyn Clock) -> (FlatMatrix, std::time::Duration) {
+    let (result_i32, kernel_time, _) = matmul_u8i8_16x16_packed_i32(a, b_i8, None, clock);
+    (i32_to_f32_matrix(&result_i32, 16, 16), kernel_time)
+}
+
+/// `matmul_u8i8_16x16_packed` keeping the exact i32 accumulators, on up to `threads` threads
+/// (`seed_row_groups`); also returns how many worked on it
+fn matmul_u8i8_16x16_packed_i32(a: &FlatMatrix, b_i8: &AlignedBufferI8, threads: Option<usize>, clock: &dyn Clock) -> (Vec<i32>, std::time::Duration, usize) {
+    let a_u8 = pack_a_u8(a);
+    let ((result_i32, used), kernel_time) = clock::time(clock, || u8i8_16x16_kernel(&a_u8, b_i8, a.cols, threads));
+    (result_i32, kernel_time, used)
+}
+
+/// The u8i8 16x16 sums with B supplied as Bᵀ (16×k), one dot product per output; the
+/// i32 sums are exact, so they equal `matmul_u8i8_16x16_packed_i32`'s
+fn matmul_u8i8_16x16_bt_i32(a: &FlatMatrix, b_t: &FlatMatrix, clock: &dyn Clock) -> (Vec<i32>, std::time::Duration) {
+    let k = a.cols;
+    let a_u8 = pack_a_u8(a);
+    let mut b_t_i8 = AlignedBufferI8::new(16 * k, 64);
+    for (q, &val) in b_t_i8.as_mut_slice().iter_mut().zip(&b_t.data) {
+        *q = val as i8;
+    }
+    clock::time(clock, || {
+        let (a_ptr, b_t_ptr) = (a_u8.as_ptr(), b_t_i8.as_ptr());
+        (0..256).map(|idx| dot_u8i8(unsafe { a_ptr.add(idx / 16 * k) }, unsafe { b_t_ptr.add(idx % 16 * k) }, k)).collect()
+    })
+}
+
+/// Integer accumulators as the f32 result matrix (exact up to 2^24)
+fn i32_to_f32_matrix(values: &[i32], rows: usize, cols: usize) -> FlatMatrix {
+    FlatMatrix { data: values.iter().map(|&x| x as f32).collect(), rows, cols }
+}
+
+/// Longest reduction whose u8i8 sums always fit in i32: every u8·i8 product lies within
+/// ±255·128, so k of them stay in range while k·32640 ≤ i32::MAX (k = 65793). The seed shape
+/// (k = 50240) is inside it; the u8i8 kernels sum longer reductions in i64.
+pub const U8I8_SAFE_K: usize = i32::MAX as usize / (255 * 128);
+
+/// Exact u8i8 sums (`cols` per row, over a reduction of length `k`) as i32 accumulators,
+/// or an error naming the first output cell whose sum does not fit
+fn u8i8_accumulators_i32(sums: &[i64], cols: usize, k: usize) -> Result<Vec<i32>, SolverError> {
+    sums.iter()
+        .enumerate()
+        .map(|(idx, &sum)| {
+            i32::try_from(sum).map_err(|_| {
+                SolverError::InvalidInput(format!(
+                    "u8i8 accumulator overflow at output ({}, {}): the sum {} does not fit in i32 (k = {} exceeds the safe bound {}); use accumulator i64",
+                    idx / cols,
+                    idx % cols,
+                    sum,
+                    k,
+                    U8I8_SAFE_K
+                ))
+            })
+        })
+        .collect()
+}
+
+/// Kernel result for exact u8i8 sums: rounded once to f32 under `Accumulator::I64`,
+/// otherwise the i32 accumulators (an error when one does not fit)
+fn u8i8_wide_result(sums: Vec<i64>, cols: usize, k: usize, accumulator: Accumulator, kernel_time: std::time::Duration) -> Result<KernelResult, SolverError> {
+    let rows = sums.len().checked_div(cols).unwrap_or(0);
+    if accumulator == Accumulator::I64 {
+        let data = sums.iter().map(|&x| x as f32).collect();
+        return Ok(KernelResult::new(FlatMatrix { data, rows, cols }, kernel_time));
+    }
+    let acc = u8i8_accumulators_i32(&sums, cols, k)?;
+    let mut result = KernelResult::new(i32_to_f32_matrix(&acc, rows, cols), kernel_time);
+    result.accumulators = Some(acc);
+    Ok(result)
+}
+
+/// Convert A (16×k) into the aligned u8 layout the u8i8 16x16 kernel reads
+fn pack_a_u8(a: &FlatMatrix) -> AlignedBufferU8 {
+    let k = a.cols;  // Should be 50240 for seed dimensions
+    let mut a_u8 = AlignedBufferU8::new(16 * k, 64);
+    pack_a_u8_cols(a, 0..k, &mut a_u8);
+    a_u8
+}
+
+/// Pack columns `range` of A into `a_u8` as 16 rows of `range.len()` bytes
+fn pack_a_u8_cols(a: &FlatMatrix, range: std::ops::Range<usize>, a_u8: &mut AlignedBufferU8) {
+    let (k, len) = (a.cols, range.len());
+    assert!(a.check().is_ok() && range.end <= k && a.rows == 16 && 16 * len <= a_u8.len);
+    let a_u8_ptr = a_u8.as_mut_ptr();
+    let a_ptr = a.data.as_ptr();
+    unsafe {
+        for i in 0..16 {
+            for (q, p) in range.clone().enumerate() {
+                *a_u8_ptr.add(i * len + q) = to_u8(*a_ptr.add(i * k + p));
+            }
+        }
+    }
+}
+
+/// Run `row_group(first_row, c_rows)` over the rows of a 16-row seed result, `c_rows` being
+/// rows `first_row..` of the row-major `result` (`n_out` columns). With the `parallel`
+/// feature and `threads` above 1 the rows are cut into one contiguous group per thread, each
+/// a multiple of `unit` rows; otherwise one call covers all 16. Every row is still worked
+/// out by itself in its serial order, so the result does not depend on the split. Returns
+/// how many threads worked on it.
+///
+/// Unlike `fp32_blocked_accumulate`, the seed kernels only split when a thread count is
+/// given: their serial timing is the benchmark figure.
+fn seed_row_groups<T: Send>(result: &mut [T], n_out: usize, unit: usize, threads: Option<usize>, row_group: impl Fn(usize, &mut [T]) + Sync) -> usize {
+    #[cfg(feature = "parallel")]
+    if let Some(threads) = threads.map(|t| t.min(parallel::max_threads())).filter(|&t| t > 1 && n_out > 0) {
+        let rows = 16usize.div_ceil(threads).next_multiple_of(unit);
+        return parallel::for_each_chunk(Some(threads), result, rows * n_out, |group, c_rows| row_group(group * rows, c_rows));
+    }
+    let _ = (n_out, unit, threads);
+    row_group(0, result);
+    1
+}
+
+/// Rows of C the u8i8 16x16 paths work on together (the SDOT path's block of four); their
+/// row groups are multiples of it
+const U8I8_ROW_BLOCK: usize = 4;
+
+/// u8i8 16x16 inner kernel over pre-packed operands (no conversion, no allocation besides C),
+/// split by rows as `seed_row_groups` describes. Returns the accumulators and the threads used.
+#[inline(always)]
+fn u8i8_16x16_kernel(a_u8: &AlignedBufferU8, b_i8: &AlignedBufferI8, k: usize, threads: Option<usize>) -> (Vec<i32>, usize) {
+    let mut result_i32 = vec![0i32; 16 * 16];
+    let paths = simd::paths();
+    let used = seed_row_groups(&mut result_i32, 16, U8I8_ROW_BLOCK, threads, |first_row, c_rows| unsafe {
+        (paths.u8i8_16x16)(a_u8.as_ptr().add(first_row * k), b_i8.as_ptr(), k, c_rows.len() / 16, c_rows.as_mut_ptr())
+    });
+    (result_i32, used)
+}
+
+/// Add the product of packed 16×k A and k×16 B into the 16x16 accumulator `acc`.
+/// The i32 lanes wrap on overflow, so the total k added into one accumulator must stay
+/// within `U8I8_SAFE_K`: the single-pass and seed paths only run below it and the chunked
+/// path caps its slices there.
+#[inline(always)]
+fn u8i8_16x16_accumulate(a_u8: &AlignedBufferU8, b_i8: &AlignedBufferI8, k: usize, acc: &mut [i32]) {
+    assert!(acc.len() >= 16 * 16);
+    unsafe { (simd::paths().u8i8_16x16)(a_u8.as_ptr(), b_i8.as_ptr(), k, 16, acc.as_mut_ptr()) }
+}
+
+unsafe fn u8i8_16x16_accumulate_scalar(a_u8: *const u8, b_i8: *const i8, k: usize, rows: usize, c: *mut i32) {
+    for i in 0..rows {
+        u8i8_16x16_row_scalar(a_u8.add(i * k), b_i8, k, c.add(i * 16));
+    }
+}
+
+#[cfg(target_arch = "x86_64")]
+#[target_feature(enable = "avx2")]
+unsafe fn u8i8_16x16_accumulate_avx2(a_u8: *const u8, b_i8: *const i8, k: usize, rows: usize, c: *mut i32) {
+    for i in 0..rows {
+        u8i8_16x16_row_avx2(a_u8.add(i * k), b_i8, k, c.add(i * 16));
+    }
+}
+
+#[cfg(target_arch = "aarch64")]
+unsafe fn u8i8_16x16_accumulate_neon(a_u8: *const u8, b_i8: *const i8, k: usize, rows: usize, c: *mut i32) {
+    for i in 0..rows {
+        u8i8_16x16_row_neon(a_u8.add(i * k), b_i8, k, c.add(i * 16));
+    }
+}
+
+/// `u8i8_16x16_accumulate` on VPDPBUSD, which adds four adjacent u8·i8 products into each
+/// i32 lane: B's rows are taken four at a time and interleaved so lane j holds column j's
+/// four bytes, then each row of C (one register) adds A's four bytes broadcast against them.
+/// Four products of at most 255·128 sum exactly in an i32, so only the accumulation can
+/// wrap, as in the other paths. A k that is not a multiple of four ends on the scalar loop.
+#[cfg(target_arch = "x86_64")]
+#[target_feature(enable = "avx512f,avx512vnni")]
+unsafe fn u8i8_16x16_accumulate_vnni(a_u8: *const u8, b_i8: *const i8, k: usize, rows: usize, c: *mut i32) {
+    // The row count is a constant in each body so the accumulators stay in registers
+    match rows {
+        16 => u8i8_rows_vnni::<16>(a_u8, b_i8, k, c),
+        8 => u8i8_rows_vnni::<8>(a_u8, b_i8, k, c),
+        _ => {
+            for block in 0..rows / 4 {
+                u8i8_rows_vnni::<4>(a_u8.add(4 * block * k), b_i8, k, c.add(4 * block * 16));
+            }
+        }
+    }
+}
+
+/// `u8i8_16x16_accumulate_vnni` for `ROWS` rows
+#[cfg(target_arch = "x86_64")]
+#[target_feature(enable = "avx512f,avx512vnni")]
+unsafe fn u8i8_rows_vnni<const ROWS: usize>(a_u8: *const u8, b_i8: *const i8, k: usize, c: *mut i32) {
+    let mut acc = [_mm512_setzero_si512(); ROWS];
+    for (i, acc) in acc.iter_mut().enumerate() {
+        *acc = _mm512_loadu_si512(c.add(i * 16) as *const __m512i);
+    }
+    let groups = k / 4;
+    for g in 0..groups {
+        let row = |r: usize| _mm_loadu_si128(b_i8.add((4 * g + r) * 16) as *const __m128i);
+        let (lo01, hi01) = (_mm_unpacklo_epi8(row(0), row(1)), _mm_unpackhi_epi8(row(0), row(1)));
+        let (lo23, hi23) = (_mm_unpacklo_epi8(row(2), row(3)), _mm_unpackhi_epi8(row(2), row(3)));
+        let mut b = _mm512_castsi128_si512(_mm_unpacklo_epi16(lo01, lo23));
+        b = _mm512_inserti32x4(b, _mm_unpackhi_epi16(lo01, lo23), 1);
+        b = _mm512_inserti32x4(b, _mm_unpacklo_epi16(hi01, hi23), 2);
+        b = _mm512_inserti32x4(b, _mm_unpackhi_epi16(hi01, hi23), 3);
+        for (i, acc) in acc.iter_mut().enumerate() {
+            let a4 = (a_u8.add(i * k + 4 * g) as *const i32).read_unaligned();
+            *acc = _mm512_dpbusd_epi32(*acc, _mm512_set1_epi32(a4), b);
+        }
+    }
+    for (i, acc) in acc.iter().enumerate() {
+        _mm512_storeu_si512(c.add(i * 16) as *mut __m512i, *acc);
+        let tail = a_u8.add(i * k + 4 * groups);
+        u8i8_16x16_row_scalar(tail, b_i8.add(4 * groups * 16), k - 4 * groups, c.add(i * 16));
+    }
+}
+
+/// `u8i8_16x16_accumulate` on SDOT, the aarch64 counterpart of the VNNI path: B's rows are
+/// taken four at a time and zipped so lane j of register q holds column 4q + j's four bytes,
+/// and each row of C (four registers) adds A's four bytes, offset to signed as in
+/// `dot_u8i8_dotprod`, broadcast against them; `128 · Σb` per column is added back at the
+/// end. C is worked four rows at a time so its accumulators and B's registers all stay in
+/// registers. A k that is not a multiple of four ends on the widening NEON loop.
+#[cfg(target_arch = "aarch64")]
+#[target_feature(enable = "neon,dotprod")]
+unsafe fn u8i8_16x16_accumulate_dotprod(a_u8: *const u8, b_i8: *const i8, k: usize, rows: usize, c: *mut i32) {
+    let groups = k / 4;
+    let ones = vdupq_n_s8(1);
+    let mut b_sum = [vdupq_n_s32(0); 4];
+    for block in 0..rows / 4 {
+        let mut acc = [[vdupq_n_s32(0); 4]; 4];
+        for (r, acc) in acc.iter_mut().enumerate() {
+            for (q, acc) in acc.iter_mut().enumerate() {
+                *acc = vld1q_s32(c.add((4 * block + r) * 16 + 4 * q));
+            }
+        }
+        for g in 0..groups {
+            let b = zip_u8i8_b_rows(b_i8.add(4 * g * 16));
+            if block == 0 {
+                for (b_sum, b) in b_sum.iter_mut().zip(b) {
+                    *b_sum = sdot(*b_sum, ones, b);
+                }
+            }
+            for (r, acc) in acc.iter_mut().enumerate() {
+                let a4 = (a_u8.add((4 * block + r) * k + 4 * g) as *const u32).read_unaligned() ^ 0x8080_8080;
+                let a4 = vreinterpretq_s8_u32(vdupq_n_u32(a4));
+                for (acc, b) in acc.iter_mut().zip(b) {
+                    *acc = sdot(*acc, a4, b);
+                }
+            }
+        }
+        for (r, acc) in acc.iter().enumerate() {
+            let i = 4 * block + r;
+            for (q, (acc, b_sum)) in acc.iter().zip(&b_sum).enumerate() {
+                vst1q_s32(c.add(i * 16 + 4 * q), vaddq_s32(*acc, vmulq_n_s32(*b_sum, 128)));
+            }
+            let tail = a_u8.add(i * k + 4 * groups);
+            u8i8_16x16_row_neon(tail, b_i8.add(4 * groups * 16), k - 4 * groups, c.add(i * 16));
+        }
+    }
+}
+
+/// Four consecutive 16-byte rows of packed B, zipped into four registers of four columns,
+/// each column's four bytes adjacent (the operand layout SDOT reads)
+#[cfg(target_arch = "aarch64")]
+#[inline(always)]
+unsafe fn zip_u8i8_b_rows(b_i8: *const i8) -> [int8x16_t; 4] {
+    let row = |r: usize| vld1q_s8(b_i8.add(r * 16));
+    let (lo01, hi01) = (vreinterpretq_s16_s8(vzip1q_s8(row(0), row(1))), vreinterpretq_s16_s8(vzip2q_s8(row(0), row(1))));
+    let (lo23, hi23) = (vreinterpretq_s16_s8(vzip1q_s8(row(2), row(3))), vreinterpretq_s16_s8(vzip2q_s8(row(2), row(3))));
+    [
+        vreinterpretq_s8_s16(vzip1q_s16(lo01, lo23)),
+        vreinterpretq_s8_s16(vzip2q_s16(lo01, lo23)),
+        vreinterpretq_s8_s16(vzip1q_s16(hi01, hi23)),
+        vreinterpretq_s8_s16(vzip2q_s16(hi01, hi23)),
+    ]
+}
+
+/// Groups of eight k that `u8i8_16x16_accumulate_i8mm` repacks at a time (8 KiB of panels)
+#[cfg(target_arch = "aarch64")]
+const I8MM_PANEL_GROUPS: usize = 64;
+
+/// `u8i8_16x16_accumulate` on USMMLA, which multiplies a 2×8 u8 tile of A by an 8×2 i8 tile
+/// of B into a 2×2 block of C. B is repacked on the stack, 64 groups of eight k at a time,
+/// into 8-deep panels (`pack_u8i8_b_panels`); A's 2-row tiles are two rows' eight bytes
+/// combined in one register. Each row pair of C keeps its eight blocks in registers across
+/// the groups. Eight products of at most 255·128 sum exactly in an i32, so as in the other
+/// paths only the accumulation can wrap. A k that is not a multiple of eight ends on the
+/// widening NEON loop.
+#[cfg(target_arch = "aarch64")]
+#[target_feature(enable = "neon,i8mm")]
+unsafe fn u8i8_16x16_accumulate_i8mm(a_u8: *const u8, b_i8: *const i8, k: usize, rows: usize, c: *mut i32) {
+    let groups = k / 8;
+    let mut panels = [0i8; I8MM_PANEL_GROUPS * 128];
+    let mut g0 = 0;
+    while g0 < groups {
+        let n = (groups - g0).min(I8MM_PANEL_GROUPS);
+        pack_u8i8_b_panels(b_i8.add(8 * g0 * 16), n, &mut panels);
+        for pair in 0..rows / 2 {
+            let (a0, a1) = (a_u8.add(2 * pair * k + 8 * g0), a_u8.add((2 * pair + 1) * k + 8 * g0));
+            let mut acc = [vdupq_n_s32(0); 8];
+            for g in 0..n {
+                let a = vcombine_u8(vld1_u8(a0.add(8 * g)), vld1_u8(a1.add(8 * g)));
+                for (cols, acc) in acc.iter_mut().enumerate() {
+                    *acc = usmmla(*acc, a, vld1q_s8(panels.as_ptr().add((8 * g + cols) * 16)));
+                }
+            }
+            // Lanes 0-1 of block q hold row 2·pair's columns 2q and 2q + 1, lanes 2-3 the next row's
+            let (c0, c1) = (c.add(2 * pair * 16), c.add((2 * pair + 1) * 16));
+            for q in 0..4 {
+                let (left, right) = (acc[2 * q], acc[2 * q + 1]);
+                vst1q_s32(c0.add(4 * q), vaddq_s32(vld1q_s32(c0.add(4 * q)), vcombine_s32(vget_low_s32(left), vget_low_s32(right))));
+                vst1q_s32(c1.add(4 * q), vaddq_s32(vld1q_s32(c1.add(4 * q)), vcombine_s32(vget_high_s32(left), vget_high_s32(right))));
+            }
+        }
+        g0 += n;
+    }
+    for i in 0..rows {
+        u8i8_16x16_row_neon(a_u8.add(i * k + 8 * groups), b_i8.add(8 * groups * 16), k - 8 * groups, c.add(i * 16));
+    }
+}
+
+/// `groups` groups of eight rows of packed B (k×16) from `b_i8` as USMMLA operands: panel
+/// `8g + p` of `panels` holds column 2p's eight bytes of group g, then column 2p + 1's
+#[cfg(any(target_arch = "aarch64", test))]
+#[inline(always)]
+unsafe fn pack_u8i8_b_panels(b_i8: *const i8, groups: usize, panels: &mut [i8]) {
+    assert!(groups * 128 <= panels.len());
+    for g in 0..groups {
+        for (p, panel) in panels[g * 128..(g + 1) * 128].chunks_exact_mut(16).enumerate() {
+            for (q, byte) in panel.iter_mut().enumerate() {
+                *byte = *b_i8.add((8 * g + q % 8) * 16 + 2 * p + q / 8);
+            }
+        }
+    }
+}
+
+/// `u8i8_16x16_row_scalar` widened to i16 and multiply-accumulated into four i32 registers
+#[cfg(target_arch = "aarch64")]
+#[inline(always)]
+unsafe fn u8i8_16x16_row_neon(a_row: *const u8, b_i8: *const i8, k: usize, c: *mut i32) {
+    let mut c0 = vld1q_s32(c);
+    let mut c1 = vld1q_s32(c.add(4));
+    let mut c2 = vld1q_s32(c.add(8));
+    let mut c3 = vld1q_s32(c.add(12));
+    for p in 0..k {
+        let a_ip = *a_row.add(p) as i16;
+        let b_vec = vld1q_s8(b_i8.add(p * 16));
+        let b_low = vmovl_s8(vget_low_s8(b_vec));
+        let b_high = vmovl_s8(vget_high_s8(b_vec));
+        c0 = vmlal_n_s16(c0, vget_low_s16(b_low), a_ip);
+        c1 = vmlal_n_s16(c1, vget_high_s16(b_low), a_ip);
+        c2 = vmlal_n_s16(c2, vget_low_s16(b_high), a_ip);
+        c3 = vmlal_n_s16(c3, vget_high_s16(b_high), a_ip);
+    }
+    vst1q_s32(c, c0);
+    vst1q_s32(c.add(4), c1);
+    vst1q_s32(c.add(8), c2);
+    vst1q_s32(c.add(12), c3);
+}
+
+/// Add row `a_row` (k bytes) of A times packed B (k×16) into the 16 accumulators at `c`
+#[inline(always)]
+unsafe fn u8i8_16x16_row_scalar(a_row: *const u8, b_i8: *const i8, k: usize, c: *mut i32) {
+    for p in 0..k {
+        let a_ip = *a_row.add(p) as i32;
+        let b_base = p * 16;
+        for j in 0..16 {
+            let b_pj = *b_i8.add(b_base + j) as i32;
+            *c.add(j) += a_ip * b_pj;
+        }
+    }
+}
+
+/// `u8i8_16x16_row_scalar` with A's byte broadcast against B's 16-byte row: 255 · ±128
+/// fits an i16, so the 16-bit products are exact before they are widened into the two
+/// eight-lane i32 accumulators
+#[cfg(target_arch = "x86_64")]
+#[target_feature(enable = "avx2")]
+unsafe fn u8i8_16x16_row_avx2(a_row: *const u8, b_i8: *const i8, k: usize, c: *mut i32) {
+    let mut c0 = _mm256_loadu_si256(c as *const __m256i);
+    let mut c1 = _mm256_loadu_si256(c.add(8) as *const __m256i);
+    for p in 0..k {
+        let a_ip = _mm256_set1_epi16(*a_row.add(p) as i16);
+        let b_row = _mm256_cvtepi8_epi16(_mm_loadu_si128(b_i8.add(p * 16) as *const __m128i));
+        let prod = _mm256_mullo_epi16(b_row, a_ip);
+        c0 = _mm256_add_epi32(c0, _mm256_cvtepi16_epi32(_mm256_castsi256_si128(prod)));
+        c1 = _mm256_add_epi32(c1, _mm256_cvtepi16_epi32(_mm256_extracti128_si256(prod, 1)));
+    }
+    _mm256_storeu_si256(c as *mut __m256i, c0);
+    _mm256_storeu_si256(c.add(8) as *mut __m256i, c1);
+}
+
+/// int8 16x16 kernel; B's packing (and its cache) is the same under either `Int8Scaling`
+#[inline(always)]
+fn matmul_int8_16x16(
+    a: &FlatMatrix,
+    b: BOperand,
+    scaling: Int8Scaling,
+    pack_cache_dir: Option<&std::path::Path>,
+    columns: Option<&[usize]>,
+    threads: Option<usize>,
+    clock: &dyn Clock,
+) -> (FlatMatrix, std::time::Duration, Option<bool>, usize) {
+    let k = a.cols;
+    let columns: Vec<usize> = columns.map_or_else(|| (0..16).collect(), |c| c.to_vec());
+    let (packed, cache_hit) = int8_bt(b, pack_cache_dir);
+    let scale_b = packed.scale;
+    let scales_a = scaling.a_scales(a);
+    let mut a_q = AlignedBufferI8::new(16 * k, 64);
+    quantize_a_i8_cols(a, 0..k, &scales_a, &mut a_q);
+    let scale_results: Vec<f32> = scales_a.iter().map(|&scale_a| 1.0 / (scale_a * scale_b)).collect();
+
+    let b_t = packed.buf.as_slice();
+    let ((result_flat, used), kernel_time) =
+        clock::time(clock, || int8_16x16_kernel(a_q.as_slice(), b_t, k, &scale_results, &columns, threads));
+
+    (FlatMatrix { data: result_flat, rows: 16, cols: columns.len() }, kernel_time, cache_hit, used)
+}
+
+/// int4 16x16 kernel: both operands quantized and packed to int4, B transposed to 16×k,
+/// then unpacked for the int8 dot product; matches `matmul_int4` bit for bit
+#[inline(always)]
+fn matmul_int4_16x16(a: &FlatMatrix, b: &FlatMatrix, columns: Option<&[usize]>, clock: &dyn Clock) -> (FlatMatrix, std::time::Duration) {
+    let k = a.cols;
+    let columns: Vec<usize> = columns.map_or_else(|| (0..16).collect(), |c| c.to_vec());
+    let (a_packed, scale_a) = pack_int4(a.data.iter().copied());
+    let b_transposed = (0..16).flat_map(|j| (0..k).map(move |p| b.data[p * 16 + j]));
+    let (b_packed, scale_b) = pack_int4(b_transposed);
+    let scale_result = 1.0 / (scale_a * scale_b);
+
+    let (a_q, b_t) = (a_packed.unpack(), b_packed.unpack());
+    let (result_flat, kernel_time) =
+        clock::time(clock, || int8_16x16_kernel(a_q.as_slice(), b_t.as_slice(), k, &[scale_result; 16], &columns, None).0);
+
+    (FlatMatrix { data: result_flat, rows: 16, cols: columns.len() }, kernel_time)
+}
+
+/// int8_asym 16x16 kernel: B shares int8's packed, cached transpose (whose entry also holds
+/// its column sums) and A is quantized to u8 rows; matches `matmul_int8_asym` bit for bit
+#[inline(always)]
+fn matmul_int8_asym_16x16(
+    a: &FlatMatrix,
+    b: BOperand,
+    pack_cache_dir: Option<&std::path::Path>,
+    columns: Option<&[usize]>,
+    threads: Option<usize>,
+    clock: &dyn Clock,
+) -> (FlatMatrix, std::time::Duration, Option<bool>, usize) {
+    let k = a.cols;
+    let columns: Vec<usize> = columns.map_or_else(|| (0..16).collect(), |c| c.to_vec());
+    let (packed, cache_hit) = int8_bt(b, pack_cache_dir);
+    let (scale_b, col_sums) = (packed.scale, packed.col_sums);
+    let quant_a = AsymmetricU8::of(a.data.iter().copied());
+    let mut a_u8 = AlignedBufferU8::new(16 * k, 64);
+    let a_u8_ptr = a_u8.as_mut_ptr();
+    for (idx, &x) in a.data.iter().enumerate() {
+        unsafe { *a_u8_ptr.add(idx) = quant_a.quantize(x) };
+    }
+    let scale_result = 1.0 / (quant_a.scale * scale_b);
+
+    let b_t = packed.buf.as_slice();
+    let ((result_flat, used), kernel_time) = clock::time(clock, || {
+        let n_out = columns.len();
+        let mut result_flat = vec![0.0f32; 16 * n_out];
+        let used = seed_row_groups(&mut result_flat, n_out, 1, threads, |first_row, c_rows| {
+            for (r, c_row) in c_rows.chunks_exact_mut(n_out).enumerate() {
+                let a_row = unsafe { a_u8.as_ptr().add((first_row + r) * k) };
+                for (c, &j) in c_row.iter_mut().zip(&columns) {
+                    let acc = dot_u8i8_wide(a_row, b_t[j * k..(j + 1) * k].as_ptr(), k);
+                    *c = quant_a.dequantize(acc, col_sums[j], scale_result);
+                }
+            }
+        });
+        (result_flat, used)
+    });
+
+    (FlatMatrix { data: result_flat, rows: 16, cols: columns.len() }, kernel_time, cache_hit, used)
+}
+
+/// Quantize A (16×k) to i8 with a per-matrix scale
+fn quantize_a_i8(a: &FlatMatrix) -> (AlignedBufferI8, f32) {
+    let k = a.cols;
+    let scale_a = int8_scale(a.data.iter().copied());
+    let mut a_q = AlignedBufferI8::new(16 * k, 64);
+    quantize_a_i8_cols(a, 0..k, &[scale_a; 16], &mut a_q);
+    (a_q, scale_a)
+}
+
+/// How the int8 kernels scale A. `PerTensor` ("int8") maps A's largest magnitude to 127;
+/// `PerRow` ("int8_rowwise") maps each row's, so one outlier row no longer flattens the
+/// others to a couple of codes. B always has one scale, and output (i, j) is dequantized
+/// by `1 / (scale_a[i] * scale_b)`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum Int8Scaling {
+    PerTensor,
+    PerRow,
+}
+
+impl Int8Scaling {
+    /// Recorded as `metadata.quantization_scheme`
+    pub(crate) fn as_str(&self) -> &'static str {
+        match self {
+            Int8Scaling::PerTensor => "per_tensor",
+            Int8Scaling::PerRow => "per_row",
+        }
+    }
+
+    /// The scale of each row of `a`
+    pub(crate) fn a_scales(&self, a: &FlatMatrix) -> Vec<f32> {
+        match self {
+            Int8Scaling::PerTensor => vec![int8_scale(a.data.iter().copied()); a.rows],
+            Int8Scaling::PerRow => {
+                (0..a.rows).map(|i| int8_scale(a.data[i * a.cols..(i + 1) * a.cols].iter().copied())).collect()
+            }
+        }
+    }
+}
+
+/// Asymmetric quantization of int8_asym's A: `scale` maps A's range, widened to include 0,
+/// onto 0..=255 and `zero_point` is the code of 0.0, so a non-negative A uses all 256
+/// codes instead of symmetric int8's 128. Values round to the nearest code.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub(crate) struct AsymmetricU8 {
+    pub(crate) scale: f32,
+    pub(crate) zero_point: i32,
+}
+
+impl AsymmetricU8 {
+    pub(crate) fn of(data: impl IntoIterator<Item = f32>) -> Self {
+        let (min, max) = data.into_iter().fold((0.0f32, 0.0f32), |(lo, hi), x| (lo.min(x), hi.max(x)));
+        if max == min {
+            return Self { scale: 1.0, zero_point: 0 };
+        }
+        let scale = 255.0 / (max - min);
+        Self { scale, zero_point: ((-min * scale).round() as i32).clamp(0, 255) }
+    }
+
+    #[inline(always)]
+    pub(crate) fn quantize(&self, val: f32) -> u8 {
+        ((val * self.scale).round() as i32).saturating_add(self.zero_point).clamp(0, 255) as u8
+    }
+
+    /// Output element from the u8·i8 accumulator `acc` of its row and B column, that
+    /// column's quantized sum and `1 / (scale * scale_b)`
+    #[inline(always)]
+    fn dequantize(&self, acc: i64, col_sum: i32, scale_result: f32) -> f32 {
+        (acc - self.zero_point as i64 * col_sum as i64) as f32 * scale_result
+    }
+}
+
+/// Sums of the 16 rows of a transposed 16×k i8 B, i.e. of B's quantized columns
+fn column_sums_i8(b_t: &[i8], k: usize) -> [i32; 16] {
+    let mut sums = [0i32; 16];
+    for (j, sum) in sums.iter_mut().enumerate() {
+        *sum = b_t[j * k..(j + 1) * k].iter().map(|&x| x as i32).sum();
+    }
+    sums
+}
+
+/// Quantization scheme of a precision's operands, for `metadata.quantization_scheme`
+fn quantization_scheme(precision: &str) -> Option<&'static str> {
+    match precision {
+        "int8" | "int4" => Some(Int8Scaling::PerTensor.as_str()),
+        "int8_rowwise" => Some(Int8Scaling::PerRow.as_str()),
+        "int8_asym" => Some("asymmetric"),
+        _ => None,
+    }
+}
+
+/// Symmetric per-matrix quantization scale: the largest magnitude maps to 127. Below about
+/// 3.7e-37, 127 / max overflows to infinity, so the scale is capped at `f32::MAX` and the
+/// largest magnitude maps to less than 127 instead of every value clamping.
+fn int8_scale(data: impl IntoIterator<Item = f32>) -> f32 {
+    let max = data.into_iter().map(f32::abs).fold(0.0f32, f32::max);
+    if max == 0.0 { 1.0 } else { (127.0 / max).min(f32::MAX) }
+}
+
+/// int8 value of `val` under `scale`: scaled, clamped, truncated toward zero.
+///
+/// Every int8 path takes its scales from `int8_scale`, which maps the largest magnitude to
+/// at most 127, so nothing is clipped: the clamp only guards float rounding at the boundary.
+#[inline(always)]
+fn quantize_i8(val: f32, scale: f32) -> i8 {
+    (val * scale).clamp(-128.0, 127.0) as i8
+}
+
+/// Symmetric per-matrix int4 scale: the largest magnitude maps to 7
+fn int4_scale(data: impl IntoIterator<Item = f32>) -> f32 {
+    let max = data.into_iter().map(f32::abs).fold(0.0f32, f32::max);
+    if max == 0.0 { 1.0 } else { 7.0 / max }
+}
+
+/// int4 value of `val` under `scale`: scaled, clamped to -8..=7, truncated toward zero
+#[inline(always)]
+fn quantize_i4(val: f32, scale: f32) -> i8 {
+    (val * scale).clamp(-8.0, 7.0) as i8
+}
+
+/// Quantize `values` to int4 with one scale for all of them and pack them
+fn pack_int4(values: impl Iterator<Item = f32> + Clone) -> (PackedI4, f32) {
+    let scale = int4_scale(values.clone());
+    let q: Vec<i8> = values.map(|val| quantize_i4(val, scale)).collect();
+    (PackedI4::pack(&q), scale)
+}
+
+/// `val` truncated to bfloat16 (the top 16 bits of its f32 encoding), as the bf16 kernels
+/// read their operands; NaN stays NaN instead of truncating to infinity
+#[inline(always)]
+fn round_bf16(val: f32) -> f32 {
+    if val.is_nan() {
+        return f32::NAN;
+    }
+    f32::from_bits(val.to_bits() & 0xFFFF_0000)
+}
+
+/// u8 operand value (saturating, NaN → 0), as the u8i8 kernels read A
+#[inline(always)]
+fn to_u8(val: f32) -> u8 {
+    val as u8
+}
+
+/// Quantize columns `range` of A into `a_q` as 16 rows of `range.len()`, row i under `scales_a[i]`
+fn quantize_a_i8_cols(a: &FlatMatrix, range: std::ops::Range<usize>, scales_a: &[f32], a_q: &mut AlignedBufferI8) {
+    let (k, len) = (a.cols, range.len());
+    assert!(range.end <= k && a.rows == 16 && scales_a.len() == 16 && 16 * len <= a_q.len);
+    let a_q_ptr = a_q.as_mut_ptr();
+    let a_ptr = a.data.as_ptr();
+    unsafe {
+        for (i, &scale_a) in scales_a.iter().enumerate() {
+            for (q, p) in range.clone().enumerate() {
+                let val = *a_ptr.add(i * k + p);
+                *a_q_ptr.add(i * len + q) = quantize_i8(val, scale_a);
+            }
+        }
+    }
+}
+
+/// Quantize B (k×16) to i8 and transpose it to 16×k so each output column is a contiguous dot
+fn pack_b_int8_transposed(b: &FlatMatrix) -> (AlignedBufferI8, f32) {
+    let k = b.rows;
+    let scale_b = int8_scale(b.data.iter().copied());
+    let mut buf = AlignedBufferI8::new(16 * k, 64);
+    pack_b_int8_transposed_rows(b, 0..k, scale_b, &mut buf);
+    (buf, scale_b)
+}
+
+/// Quantize rows `range` of B into `buf` transposed to 16 rows of `range.len()`
+fn pack_b_int8_transposed_rows(b: &FlatMatrix, range: std::ops::Range<usize>, scale_b: f32, buf: &mut AlignedBufferI8) {
+    let len = range.len();
+    assert!(range.end <= b.rows && b.cols == 16 && 16 * len <= buf.len);
+    let buf_ptr = buf.as_mut_ptr();
+    let b_ptr = b.data.as_ptr();
+    unsafe {
+        for (q, p) in range.enumerate() {
+            for j in 0..16 {
+                let val = *b_ptr.add(p * 16 + j);
+                *buf_ptr.add(j * len + q) = quantize_i8(val, scale_b);
+            }
+        }
+    }
+}
+
+/// int8 16x16 inner kernel: quantized A rows dotted with transposed B rows for `columns`,
+/// row i dequantized by `scale_results[i]`
+#[inline(always)]
+fn int8_16x16_kernel(a_q: &[i8], b_t: &[i8], k: usize, scale_results: &[f32], columns: &[usize], threads: Option<usize>) -> (Vec<f32>, usize) {
+    let n_out = columns.len();
+    let mut result_flat = vec![0.0f32; 16 * n_out];
+    let used = seed_row_groups(&mut result_flat, n_out, 1, threads, |first_row, c_rows| {
+        for (r, c_row) in c_rows.chunks_exact_mut(n_out).enumerate() {
+            let i = first_row + r;
+            let a_row = a_q[i * k..(i + 1) * k].as_ptr();
+            for (c, &j) in c_row.iter_mut().zip(columns) {
+                let b_row = b_t[j * k..(j + 1) * k].as_ptr();
+                *c = dot_i8(a_row, b_row, k) as f32 * scale_results[i];
+            }
+        }
+    });
+    (result_flat, used)
+}
+
+enum PreparedKind {
+    U8I8 { a_u8: AlignedBufferU8, b_i8: AlignedBufferI8 },
+    Int8 { a_q: AlignedBufferI8, b_t: AlignedBufferI8, scale_result: f32 },
+}
+
+/// Operands converted and packed once for the 16x16 seed kernels.
+///
+/// Splitting preparation from the kernel lets callers time or repeat the phases
+/// independently: `prepare_operands` covers conversion/packing, `run` covers only the
+/// inner loop.
+pub struct PreparedOperands {
+    kind: PreparedKind,
+    k: usize,
+}
+
+impl PreparedOperands {
+    /// Run the kernel against the packed buffers
+    pub fn run(&self) -> FlatMatrix {
+        let data = match &self.kind {
+            PreparedKind::U8I8 { a_u8, b_i8 } => {
+                u8i8_16x16_kernel(a_u8, b_i8, self.k, None).0.iter().map(|&x| x as f32).collect()
+            }
+            PreparedKind::Int8 { a_q, b_t, scale_result } => {
+                let columns: Vec<usize> = (0..16).collect();
+                int8_16x16_kernel(a_q.as_slice(), b_t.as_slice(), self.k, &[*scale_result; 16], &columns, None).0
+            }
+        };
+        FlatMatrix { data, rows: 16, cols: 16 }
+    }
+
+    /// What the u8i8_16x16 kernel returns for these operands on up to `threads` threads,
+    /// timing only the inner loop
+    #[cfg(any(feature = "seed-gen", test))]
+    fn u8i8_kernel_result(&self, threads: Option<usize>, clock: &dyn Clock) -> Result<KernelResult, SolverError> {
+        let PreparedKind::U8I8 { a_u8, b_i8 } = &self.kind else {
+            return Err(SolverError::Internal("int8 operands given to the u8i8 kernel".to_string()));
+        };
+        let ((acc, used), t) = clock::time(clock, || u8i8_16x16_kernel(a_u8, b_i8, self.k, threads));
+        Ok(KernelResult {
+            result: i32_to_f32_matrix(&acc, 16, 16),
+            kernel_time: t,
+            pack_cache_hit: Some(false),
+            accumulators: Some(acc),
+            result_f64: None,
+            simd_variant: Some(int_dot_variant()),
+            threads: (used > 1).then_some(used),
+        })
+    }
+
+    /// Dtype the result must be hashed as
+    pub fn result_dtype(&self) -> ResultDtype {
+        match self.kind {
+            PreparedKind::U8I8 { .. } => ResultDtype::for_precision("u8i8"),
+            PreparedKind::Int8 { .. } => ResultDtype::for_precision("int8"),
+        }
+        .expect("packed precisions have a result dtype")
+    }
+}
+
+#[deprecated(since = "0.2.0", note = "unstable internal; enable the unstable-internals feature and use internals::prepare_operands")]
+pub fn prepare_operands(a: &FlatMatrix, b: &FlatMatrix, precision: &str) -> Result<PreparedOperands, SolverError> {
+    pack_operands(a, b, precision)
+}
+
+/// Whether A and B are well-formed 16×k and k×16 matrices, as the packed 16x16 kernels
+/// read them through raw pointers
+fn check_16x16_operands(a: &FlatMatrix, b: &FlatMatrix) -> Result<(), SolverError> {
+    if a.rows != 16 || b.cols != 16 || a.cols != b.rows {
+        return Err(SolverError::InvalidInput(format!(
+            "Prepared operands require 16×k × k×16 matrices, got {}x{} × {}x{}",
+            a.rows, a.cols, b.rows, b.cols
+        )));
+    }
+    MatmulOperands::Matrices(a, b).check_lengths()
+}
+
+/// Convert and pack A (16×k) and B (k×16) for `precision` ("u8i8" or "int8")
+pub(crate) fn pack_operands(a: &FlatMatrix, b: &FlatMatrix, precision: &str) -> Result<PreparedOperands, SolverError> {
+    check_16x16_operands(a, b)?;
+    let kind = match precision {
+        "u8i8" => PreparedKind::U8I8 { a_u8: pack_a_u8(a), b_i8: pack_b_u8i8(b) },
+        "int8" => {
+            let (a_q, scale_a) = quantize_a_i8(a);
+            let (b_t, scale_b) = pack_b_int8_transposed(b);
+            PreparedKind::Int8 { a_q, b_t, scale_result: 1.0 / (scale_a * scale_b) }
+        }
+        other => return Err(SolverError::UnsupportedPrecision(other.to_string())),
+    };
+    Ok(PreparedOperands { kind, k: a.cols })
+}
+
+/// `pack_operands` for the u8i8 16×k × k×16 matrices of `seed`, filled straight from its
+/// XOF stream. A's bytes are already its u8 values in the kernel's 16 rows of k, and B's
+/// row-major bytes only need the `OffsetI8` shift to be the packed k×16 i8 layout, so
+/// neither matrix is ever materialized as f32.
+#[cfg(any(feature = "seed-gen", test))]
+pub(crate) fn pack_seed_u8i8(seed: &[u8], k: usize) -> PreparedOperands {
+    let layout = seed_layout((16, k), (k, 16));
+    debug_assert_eq!(
+        (layout.a_interpretation, layout.b_interpretation, layout.b_offset),
+        (ByteInterpretation::U8, ByteInterpretation::OffsetI8, layout.a_len)
+    );
+    let mut reader = seed_xof(seed);
+    let mut a_u8 = AlignedBufferU8::new(layout.a_len, 64);
+    reader.fill(a_u8.as_mut_slice());
+    let mut b_i8 = AlignedBufferI8::new(layout.b_len, 64);
+    let bytes = b_i8.as_mut_bytes();
+    reader.fill(bytes);
+    for byte in bytes {
+        // Same bits as `byte.wrapping_sub(128) as i8`
+        *byte = byte.wrapping_sub(128);
+    }
+    PreparedOperands { kind: PreparedKind::U8I8 { a_u8, b_i8 }, k }
+}
+
+#[cfg(feature = "openblas")]
+fn matmul_int8_openblas(a: &FlatMatrix, b: &FlatMatrix) -> FlatMatrix {
+    let m = a.rows;
+    let k = a.cols;
+    let n = b.cols;
+
+    let scale_a = int8_scale(a.data.iter().copied());
+    let scale_b = int8_scale(b.data.iter().copied());
+
+    // Quantize to int8, then convert to f32 for BLAS.
+    let a_q: Vec<f32> = a.data.iter().map(|&x| quantize_i8(x, scale_a) as f32).collect();
+    let b_q: Vec<f32> = b.data.iter().map(|&x| quantize_i8(x, scale_b) as f32).collect();
+
+    let mut result_flat = vec![0.0f32; m * n];
+    unsafe {
+        cblas_sgemm(
+            CBLAS_ORDER::CblasRowMajor,
+            CBLAS_TRANSPOSE::CblasNoTrans,
+            CBLAS_TRANSPOSE::CblasNoTrans,
+            m as i32,
+            n as i32,
+            k as i32,
+            1.0,
+            a_q.as_ptr(),
+            k as i32,
+            b_q.as_ptr(),
+            n as i32,
+            0.0,
+            result_flat.as_mut_ptr(),
+            n as i32,
+        );
+    }
+
+    // Scale back to match int8 quantization semantics.
+    let scale_result = 1.0 / (scale_a * scale_b);
+    for val in &mut result_flat {
+        *val *= scale_result;
+    }
+
+    FlatMatrix { data: result_flat, rows: m, cols: n }
+}
+
+/// Canonical form of a user-supplied name (precision, workload_type, kernel): surrounding
+/// whitespace trimmed and ASCII-lowercased, so "FP32", " fp16" and "Int8\n" all match.
+/// Names are compared in this form unless `ComputeOptions::exact_names` is set.
+pub fn normalize_name(name: &str) -> String {
+    name.trim().to_ascii_lowercase()
+}
+
+/// Supported precision paths, the typed form of `Input::precision`.
+///
+/// Deserializes from any spelling `normalize_name` maps to a precision; `parse` is exact.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Precision {
+    Fp32,
+    #[serde(rename = "fp32_strict")]
+    Fp32Strict,
+    Fp64,
+    Fp16,
+    Bf16,
+    Int8,
+    #[serde(rename = "int8_rowwise")]
+    Int8Rowwise,
+    #[serde(rename = "int8_asym")]
+    Int8Asym,
+    Int4,
+    U8i8,
+}
+
+impl Precision {
+    pub const ALL: [Precision; 10] = [
+        Precision::Fp32,
+        Precision::Fp32Strict,
+        Precision::Fp64,
+        Precision::Fp16,
+        Precision::Bf16,
+        Precision::Int8,
+        Precision::Int8Rowwise,
+        Precision::Int8Asym,
+        Precision::Int4,
+        Precision::U8i8,
+    ];
+
+    pub fn parse(s: &str) -> Result<Self, SolverError> {
+        Precision::ALL
+            .into_iter()
+            .find(|p| p.as_str() == s)
+            .ok_or_else(|| Precision::unsupported(s))
+    }
+
+    /// `UnsupportedPrecision` for `s`, listing the precisions this build accepts
+    pub fn unsupported(s: &str) -> SolverError {
+        let accepted: Vec<&str> = Precision::ALL.iter().filter(|p| p.is_available()).map(Precision::as_str).collect();
+        SolverError::UnsupportedPrecision(format!("{:?} (accepted: {})", s, accepted.join(", ")))
+    }
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Precision::Fp32 => "fp32",
+            Precision::Fp32Strict => "fp32_strict",
+            Precision::Fp64 => "fp64",
+            Precision::Fp16 => "fp16",
+            Precision::Bf16 => "bf16",
+            Precision::Int8 => "int8",
+            Precision::Int8Rowwise => "int8_rowwise",
+            Precision::Int8Asym => "int8_asym",
+            Precision::Int4 => "int4",
+            Precision::U8i8 => "u8i8",
+        }
+    }
+
+    /// Whether this build computes the precision: fp16 needs the `fp16` feature
+    pub fn is_available(&self) -> bool {
+        *self != Precision::Fp16 || cfg!(feature = "fp16")
+    }
+}
+
+impl std::fmt::Display for Precision {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+impl<'de> Deserialize<'de> for Precision {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let name = String::deserialize(deserializer)?;
+        Precision::parse(&normalize_name(&name)).map_err(serde::de::Error::custom)
+    }
+}
+
+impl std::str::FromStr for Precision {
+    type Err = SolverError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Precision::parse(s)
+    }
+}
+
+/// Element encoding fed to the result hash.
+///
+/// The hash is always SHA-256 over the result elements in row-major order; the dtype
+/// fixes how each element is turned into bytes so verifiers in other languages can
+/// rebuild the exact preimage from `OutputMetadata` alone.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ResultDtype {
+    /// IEEE-754 binary32, little-endian (every precision but fp64)
+    F32,
+    /// IEEE-754 binary64, little-endian (fp64)
+    F64,
+    /// Two's-complement 32-bit integer, little-endian (integer-exact output)
+    I32,
+    /// One two's-complement byte per element (quantized int8 values)
+    I8,
+    /// One unsigned byte per element (quantized u8 values)
+    U8,
+    /// IEEE-754 binary16, little-endian (fp16-rounded values)
+    #[cfg(feature = "fp16")]
+    F16,
+}
+
+impl ResultDtype {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ResultDtype::F32 => "f32",
+            ResultDtype::F64 => "f64",
+            ResultDtype::I32 => "i32",
+            ResultDtype::I8 => "i8",
+            ResultDtype::U8 => "u8",
+            #[cfg(feature = "fp16")]
+            ResultDtype::F16 => "f16",
+        }
+    }
+
+    /// Human/machine readable description of the hash preimage layout
+    pub fn hash_input_description(&self) -> &'static str {
+        match self {
+            ResultDtype::F32 => "f32le row-major",
+            ResultDtype::F64 => "f64le row-major",
+            ResultDtype::I32 => "i32le row-major",
+            ResultDtype::I8 => "i8 row-major",
+            ResultDtype::U8 => "u8 row-major",
+            #[cfg(feature = "fp16")]
+            ResultDtype::F16 => "f16le row-major",
+        }
+    }
+
+    pub fn parse(s: &str) -> Option<Self> {
+        match s {
+            "f32" => Some(ResultDtype::F32),
+            "f64" => Some(ResultDtype::F64),
+            "i32" => Some(ResultDtype::I32),
+            "i8" => Some(ResultDtype::I8),
+            "u8" => Some(ResultDtype::U8),
+            #[cfg(feature = "fp16")]
+            "f16" => Some(ResultDtype::F16),
+            _ => None,
+        }
+    }
+
+    /// Preimage bytes of one element: the first `len` bytes of the returned array.
+    /// F64 widens the f32 value; fp64 results are hashed from their f64 values instead
+    /// (`compute_hash_f64`).
+    fn encode(self, val: f32) -> ([u8; 8], usize) {
+        let mut bytes = [0u8; 8];
+        let len = match self {
+            ResultDtype::F32 => {
+                bytes[..4].copy_from_slice(&val.to_le_bytes());
+                4
+            }
+            ResultDtype::F64 => {
+                bytes = (val as f64).to_le_bytes();
+                8
+            }
+            ResultDtype::I32 => {
+                bytes[..4].copy_from_slice(&(val as i32).to_le_bytes());
+                4
+            }
+            ResultDtype::I8 => {
+                bytes[0] = val as i8 as u8;
+                1
+            }
+            ResultDtype::U8 => {
+                bytes[0] = val as u8;
+                1
+            }
+            #[cfg(feature = "fp16")]
+            ResultDtype::F16 => {
+                bytes[..2].copy_from_slice(&half::f16::from_f32(val).to_le_bytes());
+                2
+            }
+        };
+        (bytes, len)
+    }
+
+    /// Result dtype declared by each precision path
+    pub fn for_precision(precision: &str) -> Option<Self> {
+        match precision {
+            "fp32" | "fp32_strict" | "bf16" | "int8" | "int8_rowwise" | "int8_asym" | "int4" | "u8i8" => Some(ResultDtype::F32),
+            "fp64" => Some(ResultDtype::F64),
+            "fp16" if cfg!(feature = "fp16") => Some(ResultDtype::F32),
+            _ => None,
+        }
+    }
+
+    /// Dtype for `Input::hash_input`: "f32le" (the default), "i32le" (u8i8 only) or
+    /// "f64le" (fp64 only, and its default)
+    pub fn for_hash_input(precision: &str, hash_input: Option<&str>) -> Result<Self, SolverError> {
+        let default = ResultDtype::for_precision(precision)
+            .ok_or_else(|| Precision::unsupported(precision))?;
+        match hash_input {
+            None => Ok(default),
+            Some("f32le") => Ok(ResultDtype::F32),
+            Some("i32le") if precision == "u8i8" => Ok(ResultDtype::I32),
+            Some("f64le") if precision == "fp64" => Ok(ResultDtype::F64),
+            Some(name @ ("i32le" | "f64le")) => Err(SolverError::InvalidInput(format!(
+                "hash_input {} applies to {}, not {}",
+                name,
+                if name == "i32le" { "u8i8" } else { "fp64" },
+                precision
+            ))),
+            Some(other) => Err(SolverError::InvalidInput(format!(
+                "Unknown hash_input: {} (expected f32le, i32le or f64le)",
+                other
+            ))),
+        }
+    }
+}
+
+/// Rounding applied to f32 results before hashing (`hash_mode: "rounded"`).
+///
+/// Exact fp32 hashes differ between machines whose kernels accumulate in a different
+/// order (FMA vs mul+add, different blocking). Rounding each element first makes the
+/// hash robust to ULP-level divergence while still catching real errors. Values that
+/// straddle a rounding boundary can still disagree, so keep the rounding well above
+/// the expected error.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum HashRounding {
+    /// Keep this many explicit mantissa bits (0..=23), round-to-nearest-even
+    MantissaBits { bits: u32 },
+    /// Round to the nearest multiple of `quantum` (ties away from zero)
+    Quantum { quantum: f64 },
+}
+
+impl HashRounding {
+    /// Mantissa bits kept when `hash_mode` is "rounded" and nothing else is given
+    pub const DEFAULT_MANTISSA_BITS: u32 = 16;
+
+    /// Build from the request-level fields: `mode` is "exact" or "rounded"
+    pub fn from_mode(mode: &str, mantissa_bits: Option<u32>, quantum: Option<f64>) -> Result<Option<Self>, SolverError> {
+        let rounding = match (mode, mantissa_bits, quantum) {
+            ("exact", None, None) => return Ok(None),
+            ("exact", _, _) => {
+                return Err(SolverError::InvalidInput(
+                    "hash rounding parameters require hash_mode \"rounded\"".to_string(),
+                ))
+            }
+            ("rounded", Some(_), Some(_)) => {
+                return Err(SolverError::InvalidInput(
+                    "specify either mantissa bits or a quantum for rounded hashing, not both".to_string(),
+                ))
+            }
+            ("rounded", None, Some(quantum)) => HashRounding::Quantum { quantum },
+            ("rounded", bits, None) => HashRounding::MantissaBits { bits: bits.unwrap_or(Self::DEFAULT_MANTISSA_BITS) },
+            (other, _, _) => return Err(SolverError::InvalidInput(format!("Unknown hash mode: {}", other))),
+        };
+        rounding.validate()?;
+        Ok(Some(rounding))
+    }
+
+    pub fn validate(&self) -> Result<(), SolverError> {
+        match *self {
+            HashRounding::MantissaBits { bits } if bits > 23 => Err(SolverError::InvalidInput(format!(
+                "mantissa bits must be in 0..=23, got {}",
+                bits
+            ))),
+            HashRounding::Quantum { quantum } if !(quantum.is_finite() && quantum > 0.0) => Err(
+                SolverError::InvalidInput(format!("hash quantum must be positive and finite, got {}", quantum)),
+            ),
+            _ => Ok(()),
+        }
+    }
+
+    pub fn apply(&self, val: f32) -> f32 {
+        if !val.is_finite() {
+            return val;
+        }
+        let rounded = match *self {
+            HashRounding::MantissaBits { bits } => {
+                let drop = 23 - bits.min(23);
+                if drop == 0 {
+                    return val;
+                }
+                let x = val.to_bits();
+                let lsb = (x >> drop) & 1;
+                let bias = (1u32 << (drop - 1)) - 1 + lsb;
+                f32::from_bits(x.wrapping_add(bias) & !((1u32 << drop) - 1))
+            }
+            HashRounding::Quantum { quantum } => ((val as f64 / quantum).round() * quantum) as f32,
+        };
+        // -0.0 and 0.0 must hash the same
+        if rounded == 0.0 { 0.0 } else { rounded }
+    }
+
+    /// Appended to `hash_input_description`
+    pub fn describe(&self) -> String {
+        match self {
+            HashRounding::MantissaBits { bits } => format!("each element rounded to {} mantissa bits (nearest-even)", bits),
+            HashRounding::Quantum { quantum } => format!("each element rounded to a multiple of {}", quantum),
+        }
+    }
+}
+
+/// SHA-256 behind the result hashes; a `faults::FaultPlan` can corrupt what it is fed
+#[cfg(any(feature = "fault-injection", test))]
+type ResultHasher = faults::Hasher;
+#[cfg(not(any(feature = "fault-injection", test)))]
+type ResultHasher = Sha256;
+
+/// SHA-256 of the result as f32 little-endian row-major bytes (the default contract)
+pub fn compute_hash(matrix: &FlatMatrix) -> String {
+    compute_hash_as(matrix, ResultDtype::F32)
+}
+
+/// Hash a result matrix using the byte encoding of the declared dtype
+pub fn compute_hash_as(matrix: &FlatMatrix, dtype: ResultDtype) -> String {
+    let mut hasher = ResultHasher::new();
+    
+    // Hash flat data directly - same order as Vec<Vec<f32>> (row-major)
+    hash_elements(&mut hasher, &matrix.data, dtype, None);
+    
+    hex::encode(hasher.finalize())
+}
+
+/// Feed `values` to a result hasher as `compute_hash_rounded` encodes them, so callers that
+/// see the result a row at a time (`fused`) hash the same bytes
+fn hash_elements(hasher: &mut ResultHasher, values: &[f32], dtype: ResultDtype, rounding: Option<HashRounding>) {
+    for &val in values {
+        let val = match rounding {
+            Some(rounding) => rounding.apply(val),
+            None => val,
+        };
+        let (bytes, len) = dtype.encode(val);
+        hasher.update(&bytes[..len]);
+    }
+}
+
+/// SHA-256 over i32 accumulators as little-endian bytes, row-major (`hash_input: "i32le"`).
+///
+/// Needs no floating point, so integer-only verifiers can reproduce it; unlike
+/// `compute_hash_as(.., ResultDtype::I32)` nothing passes through f32 on the way.
+pub fn compute_hash_i32(values: &[i32]) -> String {
+    let mut hasher = ResultHasher::new();
+    for &val in values {
+        hasher.update(val.to_le_bytes());
+    }
+    hex::encode(hasher.finalize())
+}
+
+/// The kernel's integer accumulators, or why i32le hashing cannot use this kernel
+fn exact_accumulators(kernel: &str, accumulators: Option<Vec<i32>>) -> Result<Vec<i32>, SolverError> {
+    accumulators.ok_or_else(|| {
+        SolverError::KernelUnavailable(format!("{} does not produce integer accumulators for i32le hashing", kernel))
+    })
+}
+
+/// SHA-256 over f64 results as little-endian bytes, row-major (`hash_input: "f64le"`, the
+/// fp64 default). Unlike `compute_hash_as(.., ResultDtype::F64)` the values are not
+/// rounded through f32 first.
+pub fn compute_hash_f64(values: &[f64]) -> String {
+    let mut hasher = ResultHasher::new();
+    for &val in values {
+        hasher.update(val.to_le_bytes());
+    }
+    hex::encode(hasher.finalize())
+}
+
+/// The kernel's f64 results, or why f64le hashing cannot use this kernel
+fn exact_f64(kernel: &str, values: Option<Vec<f64>>) -> Result<Vec<f64>, SolverError> {
+    values.ok_or_else(|| {
+        SolverError::KernelUnavailable(format!("{} does not produce f64 results for f64le hashing", kernel))
+    })
+}
+
+/// Recompute a u8i8 seed workload and check its `hash_input: "i32le"` hash using
+/// integer arithmetic only: seed bytes are read through `seed_layout` as u8 (A) and
+/// offset i8 (B) and summed exactly; as in the u8i8 kernels, a sum that does not fit in
+/// i32 is an error naming its output cell.
+#[cfg(any(feature = "seed-gen", test))]
+pub fn verify_u8i8_integer(seed: &[u8], dims: (usize, usize, usize), expected_hash: &str) -> Result<bool, SolverError> {
+    let (m, k, n) = dims;
+    if m == 0 || k == 0 || n == 0 || m.checked_mul(k).is_none() || k.checked_mul(n).is_none() {
+        return Err(SolverError::InvalidInput(format!("Invalid dims {:?}", dims)));
+    }
+    let layout = seed_layout((m, k), (k, n));
+    let bytes = generate_seed_bytes(seed, layout.total_len());
+    let a = &bytes[layout.a_offset..layout.a_offset + layout.a_len];
+    let b = &bytes[layout.b_offset..layout.b_offset + layout.b_len];
+    let mut sums = vec![0i64; m * n];
+    for i in 0..m {
+        let row = &mut sums[i * n..(i + 1) * n];
+        for p in 0..k {
+            let a_ip = layout.a_interpretation.to_i32(a[i * k + p]);
+            for (j, c) in row.iter_mut().enumerate() {
+                *c += (a_ip * layout.b_interpretation.to_i32(b[p * n + j])) as i64;
+            }
+        }
+    }
+    Ok(compute_hash_i32(&u8i8_accumulators_i32(&sums, n, k)?) == expected_hash)
+}
+
+/// Hash with optional rounding; `None` is identical to `compute_hash_as`
+pub fn compute_hash_rounded(matrix: &FlatMatrix, dtype: ResultDtype, rounding: Option<HashRounding>) -> String {
+    let mut hasher = ResultHasher::new();
+    hash_elements(&mut hasher, &matrix.data, dtype, rounding);
+    hex::encode(hasher.finalize())
+}
+
+/// Digest of one output tile, as recorded in `Output::tile_trace`
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct TileDigest {
+    /// First result row of the tile (multiple of TILE_ROWS)
+    pub row: usize,
+    /// First result column of the tile (multiple of TILE_COLS)
+    pub col: usize,
+    pub rows: usize,
+    pub cols: usize,
+    /// blake3 (hex) of the tile's elements, row-major, encoded per result dtype
+    pub digest: String,
+}
+
+#[cfg(feature = "blake3-hash")]
+fn tile_digest(result: &FlatMatrix, row: usize, col: usize, rows: usize, cols: usize, dtype: ResultDtype) -> String {
+    let mut hasher = blake3::Hasher::new();
+    for i in row..row + rows {
+        for &val in &result.data[i * result.cols + col..i * result.cols + col + cols] {
+            let (bytes, len) = dtype.encode(val);
+            hasher.update(&bytes[..len]);
+        }
+    }
+    hasher.finalize().to_hex().to_string()
+}
+
+/// Per-tile commitments over a result plus their root.
+///
+/// Tiles are the blocked fp32 kernel's TILE_ROWS×TILE_COLS output blocks in row-major
+/// tile order (edge tiles are smaller; a 16x16 result is a single tile). The root is
+/// blake3 over the concatenated raw 32-byte tile digests in that order.
+#[cfg(feature = "blake3-hash")]
+pub fn tile_trace(result: &FlatMatrix, dtype: ResultDtype) -> (Vec<TileDigest>, String) {
+    let mut trace = Vec::new();
+    let mut root = blake3::Hasher::new();
+    for row in (0..result.rows).step_by(TILE_ROWS) {
+        let rows = TILE_ROWS.min(result.rows - row);
+        for col in (0..result.cols).step_by(TILE_COLS) {
+            let cols = TILE_COLS.min(result.cols - col);
+            let digest = tile_digest(result, row, col, rows, cols, dtype);
+            root.update(&hex::decode(&digest).expect("blake3 hex"));
+            trace.push(TileDigest { row, col, rows, cols, digest });
+        }
+    }
+    (trace, root.finalize().to_hex().to_string())
+}
+
+/// Copy the given columns of `matrix` (in the given order) into a new rows×len matrix
+pub fn select_columns(matrix: &FlatMatrix, columns: &[usize]) -> FlatMatrix {
+    let mut data = Vec::with_capacity(matrix.rows * columns.len());
+    for i in 0..matrix.rows {
+        let row = &matrix.data[i * matrix.cols..(i + 1) * matrix.cols];
+        data.extend(columns.iter().map(|&j| row[j]));
+    }
+    FlatMatrix { data, rows: matrix.rows, cols: columns.len() }
+}
+
+/// Hash of a column subset of a full result.
+///
+/// Layout: the rows×len(columns) sub-matrix with columns in the requested order,
+/// row-major, each element encoded per `dtype`. This is exactly the `result_hash`
+/// a partial computation with `result_columns = columns` reports.
+pub fn partial_hash(full_result: &FlatMatrix, columns: &[usize], dtype: ResultDtype) -> String {
+    compute_hash_as(&select_columns(full_result, columns), dtype)
+}
+
+/// Compile-time configuration captured by build.rs (opt-level, target-cpu, target features)
+pub fn build_compiler_flags() -> &'static str {
+    env!("MATMUL_BUILD_COMPILER_FLAGS")
+}
+
+/// Enabled cargo features and the BLAS backend compiled in
+pub fn build_libraries() -> Vec<String> {
+    let mut libraries = Vec::new();
+    if cfg!(feature = "api") {
+        libraries.push("feature:api".to_string());
+    }
+    if cfg!(feature = "openblas") {
+        libraries.push("feature:openblas".to_string());
+    }
+    let blas = if cfg!(feature = "openblas") { "openblas" } else { "none" };
+    libraries.push(format!("blas:{}", blas));
+    libraries
+}
+
+fn estimate_memory_usage(rows_a: usize, cols_a: usize, rows_b: usize, cols_b: usize) -> f64 {
+    // Rough estimate: input matrices + output matrix (all as f32)
+    let input_size = (rows_a * cols_a + rows_b * cols_b) * 4; // 4 bytes per f32
+    let output_size = rows_a * cols_b * 4;
+    (input_size + output_size) as f64 / (1024.0 * 1024.0) // Convert to MB
+}
+
+/// Caller-supplied knobs that are not part of the workload itself
+#[derive(Debug, Clone, Default)]
+pub struct ComputeOptions {
+    /// Persist packed B buffers here so later processes can skip packing
+    pub pack_cache_dir: Option<std::path::PathBuf>,
+    /// Force a registered kernel by name instead of automatic selection
+    pub kernel: Option<String>,
+    /// Compute only these output columns (in this order); see `partial_hash`
+    pub result_columns: Option<Vec<usize>>,
+    /// Time source for every measurement; `SystemClock` when unset
+    pub clock: Option<std::sync::Arc<dyn Clock>>,
+    /// Fail with `DeadlineExceeded` when computing takes longer than this
+    pub deadline: Option<std::time::Duration>,
+    /// Round elements before hashing (`hash_mode: "rounded"`); exact hash when unset
+    pub hash_rounding: Option<HashRounding>,
+    /// Record per-tile digests (`Output::tile_trace`) for fraud proofs
+    pub tile_trace: bool,
+    /// Accumulation order of the 16x16 fp32 kernel; strict unless asked otherwise
+    pub fp32_accumulation: Fp32Accumulation,
+    /// How fp32 kernels sum over k; naive unless asked otherwise
+    pub fp32_summation: Fp32Summation,
+    /// Reject (analytically) or abort (with `counting-alloc`) workloads needing more memory
+    pub max_memory_bytes: Option<u64>,
+    /// Split the 16x16 kernels' reduction into slices of this length (rounded down to a
+    /// multiple of 4); `chunked::DEFAULT_K_CHUNK` when unset
+    pub k_chunk: Option<usize>,
+    /// Set to stop a chunked kernel at the next slice boundary with `Cancelled`
+    pub cancel: Option<std::sync::Arc<std::sync::atomic::AtomicBool>>,
+    /// int8/u8i8 accumulator; the saturating modes run in one pass (no k chunking)
+    pub accumulator: Accumulator,
+    /// How fp16 paths convert operands to binary16; nearest-even unless asked otherwise
+    pub fp16_rounding: Fp16Rounding,
+    /// Compute matmuls twice and cross-check the hashes
+    pub redundancy: Redundancy,
+    /// Smallest k for which automatic selection takes a kernel that packs its operands
+    /// (fp32, int8 and u8i8 16x16); `kernels::DEFAULT_PACKED_MIN_K` when unset
+    pub packed_min_k: Option<usize>,
+    /// In `compute_seed_workload`, generate the seed on a second thread while the u8i8
+    /// kernel consumes it, where the seed packs directly (see seed_pipeline.rs)
+    pub seed_pipeline: bool,
+    /// Match precision, workload_type and kernel names exactly, instead of in
+    /// `normalize_name` form
+    pub exact_names: bool,
+    /// Leave `result_hash` empty when it covers `result_matrix` as encoded (not i32le or
+    /// f64le), for `fused::write_json` to compute while it serializes the result. Dual
+    /// redundancy and batched items compare hashes, so they still hash as they go.
+    pub defer_result_hash: bool,
+    /// Also compute reduced-precision matmuls at fp32 and record that reference, with the
+    /// largest difference from the result, in `Output::auxiliary_results`
+    pub error_metrics: bool,
+    /// Include the reference matrices in `auxiliary_results`, not only their hashes
+    pub aux_result_matrices: bool,
+    /// Tolerance profile and overrides every comparison of the run uses (see tolerance.rs)
+    pub tolerance: tolerance::TolerancePolicy,
+    /// Threads the fp32 blocked kernel splits large products across (feature `parallel`):
+    /// rayon's global pool when unset (every core, or `RAYON_NUM_THREADS`), serial at 1.
+    /// The 16x16 seed kernels split their rows only when a count above 1 is given. A count
+    /// above the machine's available parallelism is capped at it. Results are identical
+    /// for any count; without the feature the kernels are serial.
+    pub threads: Option<usize>,
+    /// Pack B into a buffer of this run's own instead of through the in-process packed-B
+    /// caches (and `pack_cache_dir`), leaving them untouched; set for a run whose
+    /// `InputMetadata::cache_enabled` is false. Results are the same either way.
+    pub bypass_b_cache: bool,
+    /// Packed-B caches the 16x16 kernels use; `CacheContext::global()` when unset
+    pub caches: Option<std::sync::Arc<CacheContext>>,
+}
+
+impl ComputeOptions {
+    pub fn clock(&self) -> &dyn Clock {
+        match &self.clock {
+            Some(clock) => clock.as_ref(),
+            None => &SystemClock,
+        }
+    }
+
+    pub fn caches(&self) -> &CacheContext {
+        match &self.caches {
+            Some(caches) => caches.as_ref(),
+            None => CacheContext::global(),
+        }
+    }
+
+    /// `name` as these options match it
+    pub fn match_name<'a>(&self, name: &'a str) -> std::borrow::Cow<'a, str> {
+        if self.exact_names {
+            std::borrow::Cow::Borrowed(name)
+        } else {
+            std::borrow::Cow::Owned(normalize_name(name))
+        }
+    }
+}
+
+// Shared computation function that can be used by both CLI and API
+pub fn compute_workload(input: types::Input) -> Result<types::Output, SolverError> {
+    compute_workload_with_options(input, &ComputeOptions::default())
+}
+
+/// `workload_type` values `compute_workload_with_options` dispatches ("matmul" when unset)
+pub const WORKLOAD_TYPES: [&str; 3] = ["matmul", "quantize", "batched_matmul"];
+
+/// Same as `compute_workload`, with explicit options
+pub fn compute_workload_with_options(mut input: types::Input, options: &ComputeOptions) -> Result<types::Output, SolverError> {
+    if !options.exact_names {
+        input.normalize_names();
+    }
+    compute_workload_ref(&input, options)
+}
+
+/// `compute_workload_with_options` on a borrowed input, so the matrices stay with the
+/// caller (to verify the result against them, say) without a copy. It cannot rewrite the
+/// input, so names are matched as given: call `Input::normalize_names` first unless
+/// `options.exact_names`.
+pub fn compute_workload_ref(input: &types::Input, options: &ComputeOptions) -> Result<types::Output, SolverError> {
+    let workload_type = input.workload_type.as_deref().unwrap_or("matmul");
+    validation::validate(workload_type, input)?;
+    
+    b_cache::with_request_b(&input.matrix_b, || match workload_type {
+        "matmul" => {
+            let mut output = match options.redundancy {
+                Redundancy::Single => compute_matmul_once(input, options),
+                Redundancy::Dual => redundancy::compute_dual(input, options),
+            }?;
+            if options.error_metrics {
+                error_metrics::attach_reference(input, &mut output, options)?;
+            }
+            Ok(output)
+        }
+        "quantize" => quantize::compute_quantize(input, options),
+        "batched_matmul" => batched::compute_batched(input, options),
+        // Future workloads will be handled here when schemas are provided:
+        // "convolution" => { compute_convolution(...) }
+        // "attention" => { compute_attention(...) }
+        // "inference" => { compute_inference(...) }
+        _ => Err(unsupported_workload(workload_type)),
+    })
+}
+
+fn unsupported_workload(workload_type: &str) -> SolverError {
+    SolverError::UnsupportedWorkload(format!("{:?} (accepted: {})", workload_type, WORKLOAD_TYPES.join(", ")))
+}
+
+/// Matmul of the matrices `generate_matrices_from_seed(seed, m, k, k, n)` would produce,
+/// with the same Output as running those through `compute_workload_with_options`.
+///
+/// Plain u8i8 16×k×16 runs on the `u8i8_16x16` kernel skip the f32 matrices entirely and
+/// pack the XOF stream directly (`pack_seed_u8i8`), which cuts the peak memory of a
+/// 16×50240 seed from about 8 MB to 1.6 MB. Anything else (other precisions or shapes,
+/// chunked or saturating runs, the pack cache, dual redundancy) generates the matrices.
+/// With `options.seed_pipeline`, direct runs longer than one slice also overlap generation
+/// with the kernel (`metrics.seed_pipelined`).
+#[cfg(any(feature = "seed-gen", test))]
+pub fn compute_seed_workload(
+    seed: &[u8],
+    (m, k, n): (usize, usize, usize),
+    precision: &str,
+    metadata: Option<types::InputMetadata>,
+    hash_input: Option<String>,
+    options: &ComputeOptions,
+) -> Result<types::Output, SolverError> {
+    let precision = &*options.match_name(precision);
+    if seed_packs_directly(precision, (m, k, n), options) {
+        let result_dtype = ResultDtype::for_hash_input(precision, hash_input.as_deref())?;
+        let mut warnings = warnings::WarningCollector::default();
+        let pipelined = seed_pipeline::applies(k, options);
+        let operands = if pipelined {
+            MatmulOperands::SeedPipelined { seed: seed.to_vec(), k }
+        } else {
+            MatmulOperands::SeedU8I8(pack_seed_u8i8(seed, k))
+        };
+        let mut output = compute_matmul_internal(operands, precision, result_dtype, &metadata, None, options, &mut warnings)?;
+        output.warnings = warnings.into_vec();
+        output.metrics.seed_pipelined = pipelined.then_some(true);
+        return Ok(output);
+    }
+    let (matrix_a, matrix_b) = generate_matrices_from_seed(seed, m, k, k, n);
+    let input = types::Input {
+        matrix_a,
+        matrix_b,
+        precision: precision.to_string(),
+        metadata,
+        hash_input,
+        ..Default::default()
+    };
+    compute_workload_with_options(input, options)
+}
+
+/// Whether `compute_seed_workload` can run on `pack_seed_u8i8` operands
+#[cfg(any(feature = "seed-gen", test))]
+fn seed_packs_directly(precision: &str, (m, k, n): (usize, usize, usize), options: &ComputeOptions) -> bool {
+    precision == "u8i8"
+        && (m, n) == (16, 16)
+        && (1..=U8I8_SAFE_K).contains(&k)
+        && options.accumulator == Accumulator::I32
+        && options.redundancy == Redundancy::Single
+        && options.pack_cache_dir.is_none()
+        && !options.error_metrics
+        && chunked::chunk_len(k, options).is_none()
+        && kernels::resolve_choice(precision, (m, k), (k, n), options).is_ok_and(|(_, kernel)| kernel.name() == "u8i8_16x16")
+}
+
+/// One run of the matmul workload on a validated Input, planned by the same
+/// `preflight::plan_matmul` that /validate answers from
+fn compute_matmul_once(input: &types::Input, options: &ComputeOptions) -> Result<types::Output, SolverError> {
+    let preflight::MatmulPlan { result_dtype, a_layout, b_layout, gemm, .. } =
+        preflight::plan_matmul(&preflight::WorkloadShape::of(input), options)?;
+    let sparse_b = sparse::operand(input)?;
+    let mut warnings = warnings::WarningCollector::default();
+    let matrix_a = a_layout.to_row_major(&input.matrix_a);
+    let operands = match (sparse_b, b_layout) {
+        (Some(b), _) => MatmulOperands::SparseB(&matrix_a, b),
+        (None, BLayout::RowMajor) => MatmulOperands::Matrices(&matrix_a, &input.matrix_b),
+        (None, BLayout::Transposed) => MatmulOperands::TransposedB(&matrix_a, &input.matrix_b),
+    };
+    let gemm = gemm.then(|| Gemm::of(input));
+    let mut output = compute_matmul_internal(
+        operands,
+        &input.precision,
+        result_dtype,
+        &input.metadata,
+        gemm,
+        options,
+        &mut warnings,
+    )?;
+    output.warnings = warnings.into_vec();
+    if let Some(gemm) = gemm {
+        output.metadata.alpha = (gemm.alpha != 1.0).then_some(gemm.alpha);
+        output.metadata.beta = (gemm.beta != 0.0).then_some(gemm.beta);
+    }
+    output.metadata.a_layout = (a_layout == ALayout::Transposed).then(|| a_layout.as_str().to_string());
+    output.metadata.b_layout = (b_layout == BLayout::Transposed).then(|| b_layout.as_str().to_string());
+    Ok(output)
+}
+
+/// The result dtype and layouts of a matmul, which a sparse B must leave row-major
+fn matmul_layouts(
+    precision: &str,
+    hash_input: Option<&str>,
+    a_layout: Option<&str>,
+    b_layout: Option<&str>,
+    sparse_b: bool,
+) -> Result<(ResultDtype, ALayout, BLayout), SolverError> {
+    let result_dtype = ResultDtype::for_hash_input(precision, hash_input)?;
+    let a_layout = ALayout::parse(a_layout)?;
+    let b_layout = BLayout::parse(b_layout)?;
+    if sparse_b && b_layout == BLayout::Transposed {
+        return Err(SolverError::InvalidInput("b_layout applies to matrix_b, not matrix_b_sparse".to_string()));
+    }
+    Ok((result_dtype, a_layout, b_layout))
+}
+
+/// GEMM-style scaling of an fp32 Input's result: `alpha·(A·B) + beta·C`
+#[derive(Debug, Clone, Copy)]
+struct Gemm<'a> {
+    alpha: f32,
+    beta: f32,
+    c: Option<&'a FlatMatrix>,
+}
+
+impl<'a> Gemm<'a> {
+    /// The input's alpha, beta and matrix_c, once `check` has passed them
+    fn of(input: &'a types::Input) -> Self {
+        Gemm { alpha: input.alpha.unwrap_or(1.0), beta: input.beta.unwrap_or(0.0), c: input.matrix_c.as_ref() }
+    }
+
+    /// Whether alpha, beta and a C of `c_extents` (rows, cols, elements) change an A·B of
+    /// `shape`, or why they cannot
+    fn check(
+        precision: &str,
+        alpha: f32,
+        beta: f32,
+        c_extents: Option<(usize, usize, usize)>,
+        shape: (usize, usize),
+        options: &ComputeOptions,
+    ) -> Result<bool, SolverError> {
+        if alpha == 1.0 && beta == 0.0 && c_extents.is_none() {
+            return Ok(false);
+        }
+        if precision != "fp32" {
+            return Err(SolverError::InvalidInput(format!("alpha, beta and matrix_c apply to fp32, not {}", precision)));
+        }
+        if options.result_columns.is_some() {
+            return Err(SolverError::InvalidInput(
+                "alpha, beta and matrix_c apply to the full result and cannot be combined with result_columns".to_string(),
+            ));
+        }
+        match c_extents {
+            None if beta != 0.0 => Err(SolverError::InvalidInput(format!("beta {} scales matrix_c, which is missing", beta))),
+            Some((rows, cols, len)) if (rows, cols) != shape || len != rows * cols => Err(SolverError::InvalidInput(format!(
+                "matrix_c is {}x{} but A·B is {}x{}",
+                rows, cols, shape.0, shape.1
+            ))),
+            _ => Ok(true),
+        }
+    }
+
+    /// Scale the kernel's A·B in place; beta = 0 never reads C, as in BLAS
+    fn apply(&self, result: &mut FlatMatrix) {
+        match self.c {
+            Some(c) if self.beta != 0.0 => {
+                for (x, &c) in result.data.iter_mut().zip(&c.data) {
+                    *x = self.alpha * *x + self.beta * c;
+                }
+            }
+            _ => result.data.iter_mut().for_each(|x| *x *= self.alpha),
+        }
+    }
+}
+
+/// What `compute_matmul_internal` multiplies
+enum MatmulOperands<'a> {
+    /// Row-major A and B
+    Matrices(&'a FlatMatrix, &'a FlatMatrix),
+    /// Row-major A and Bᵀ (n×k), for `MatmulKernel::run_b_transposed`
+    TransposedB(&'a FlatMatrix, &'a FlatMatrix),
+    /// Row-major A and a CSR B, for `sparse::matmul_fp32_sparse`
+    SparseB(&'a FlatMatrix, &'a sparse::SparseMatrix),
+    /// u8i8 16×k × k×16 operands packed straight from a seed (`pack_seed_u8i8`)
+    #[cfg(any(feature = "seed-gen", test))]
+    SeedU8I8(PreparedOperands),
+    /// The same operands, generated slice by slice alongside the kernel (`seed_pipeline`)
+    #[cfg(any(feature = "seed-gen", test))]
+    SeedPipelined { seed: Vec<u8>, k: usize },
+}
+
+impl MatmulOperands<'_> {
+    fn shapes(&self) -> ((usize, usize), (usize, usize)) {
+        match self {
+            MatmulOperands::Matrices(a, b) => ((a.rows, a.cols), (b.rows, b.cols)),
+            MatmulOperands::TransposedB(a, b_t) => ((a.rows, a.cols), (b_t.cols, b_t.rows)),
+            MatmulOperands::SparseB(a, b) => ((a.rows, a.cols), (b.rows, b.cols)),
+            #[cfg(any(feature = "seed-gen", test))]
+            MatmulOperands::SeedU8I8(prepared) => ((16, prepared.k), (prepared.k, 16)),
+            #[cfg(any(feature = "seed-gen", test))]
+            MatmulOperands::SeedPipelined { k, .. } => ((16, *k), (*k, 16)),
+        }
+    }
+
+    /// `FlatMatrix::check` on each dense operand (seed-packed ones are sized by construction)
+    fn check_lengths(&self) -> Result<(), SolverError> {
+        let (a, b) = match self {
+            MatmulOperands::Matrices(a, b) | MatmulOperands::TransposedB(a, b) => (*a, Some(*b)),
+            MatmulOperands::SparseB(a, _) => (*a, None),
+            #[cfg(any(feature = "seed-gen", test))]
+            MatmulOperands::SeedU8I8(_) | MatmulOperands::SeedPipelined { .. } => return Ok(()),
+        };
+        let named = |name: &str, err: MatrixError| SolverError::InvalidInput(format!("{}: {}", name, err));
+        a.check().map_err(|err| named("matrix_a", err))?;
+        b.map_or(Ok(()), FlatMatrix::check).map_err(|err| named("matrix_b", err))
+    }
+}
+
+/// The checks `compute_matmul_internal` makes before running anything: the options against
+/// the precision, result dtype and shapes, and the memory budget
+fn check_matmul_options(
+    precision: &str,
+    result_dtype: ResultDtype,
+    a_shape: (usize, usize),
+    b_shape: (usize, usize),
+    options: &ComputeOptions,
+) -> Result<(), SolverError> {
+    if let Some(rounding) = &options.hash_rounding {
+        rounding.validate()?;
+        if result_dtype != ResultDtype::F32 {
+            return Err(SolverError::InvalidInput(format!(
+                "rounded hashing applies to f32 results, {} declares {}",
+                precision,
+                result_dtype.as_str()
+            )));
+        }
+    }
+    
+    if options.tile_trace && options.result_columns.is_some() {
+        return Err(SolverError::InvalidInput(
+            "tile_trace covers the full result and cannot be combined with result_columns".to_string(),
+        ));
+    }
+
+    if !cfg!(feature = "blake3-hash") && (options.tile_trace || options.pack_cache_dir.is_some()) {
+        return Err(SolverError::InvalidInput(
+            "tile_trace and pack_cache_dir need a build with the blake3-hash feature".to_string(),
+        ));
+    }
+    
+    if options.accumulator.is_saturating() && !matches!(precision, "int8" | "u8i8") {
+        return Err(SolverError::InvalidInput(format!(
+            "accumulator {} applies to int8 and u8i8, not {}",
+            options.accumulator.as_str(),
+            precision
+        )));
+    }
+    
+    if options.accumulator == Accumulator::I64 && precision != "u8i8" {
+        return Err(SolverError::InvalidInput(format!("accumulator i64 applies to u8i8, not {}", precision)));
+    }
+    
+    if options.fp32_summation != Fp32Summation::Naive {
+        if precision != "fp32" {
+            return Err(SolverError::InvalidInput(format!(
+                "fp32 summation {} applies to fp32, not {}",
+                options.fp32_summation.as_str(),
+                precision
+            )));
+        }
+        if options.fp32_accumulation == Fp32Accumulation::Fast {
+            return Err(SolverError::InvalidInput(format!(
+                "fp32 summation {} cannot be combined with fast fp32 accumulation",
+                options.fp32_summation.as_str()
+            )));
+        }
+    }
+    
+    if options.fp16_rounding != Fp16Rounding::default() && precision != "fp16" {
+        return Err(SolverError::InvalidInput(format!(
+            "fp16 rounding {} applies to fp16, not {}",
+            options.fp16_rounding.as_str(),
+            precision
+        )));
+    }
+    
+    if result_dtype == ResultDtype::I32 && (options.tile_trace || options.result_columns.is_some()) {
+        return Err(SolverError::InvalidInput(
+            "i32le hashing covers the full accumulator and cannot be combined with tile_trace or result_columns".to_string(),
+        ));
+    }
+    
+    if result_dtype == ResultDtype::I32 && options.accumulator == Accumulator::I64 {
+        return Err(SolverError::InvalidInput(
+            "i32le hashing needs the i32 accumulators and cannot be combined with accumulator i64".to_string(),
+        ));
+    }
+    
+    if result_dtype == ResultDtype::F64 && (options.tile_trace || options.result_columns.is_some()) {
+        return Err(SolverError::InvalidInput(
+            "f64le hashing covers the full f64 result and cannot be combined with tile_trace or result_columns".to_string(),
+        ));
+    }
+    
+    if let Some(columns) = &options.result_columns {
+        if let Some(&bad) = columns.iter().find(|&&j| j >= b_shape.1) {
+            return Err(SolverError::InvalidInput(format!(
+                "result_columns index {} is out of range for a result with {} columns",
+                bad, b_shape.1
+            )));
+        }
+    }
+    
+    if let Some(limit) = options.max_memory_bytes {
+        memory::check_budget(precision, a_shape, b_shape, limit)?;
+    }
+    Ok(())
+}
+
+fn compute_matmul_internal(
+    operands: MatmulOperands,
+    precision: &str,
+    result_dtype: ResultDtype,
+    metadata: &Option<types::InputMetadata>,
+    gemm: Option<Gemm>,
+    options: &ComputeOptions,
+    warnings: &mut warnings::WarningCollector,
+) -> Result<types::Output, SolverError> {
+    let bypassing;
+    let options = match metadata {
+        Some(types::InputMetadata { cache_enabled: Some(false), .. }) if !options.bypass_b_cache => {
+            bypassing = ComputeOptions { bypass_b_cache: true, ..options.clone() };
+            &bypassing
+        }
+        _ => options,
+    };
+    let clock = options.clock();
+    let compute_start = clock.now();
+    let ((rows_a, cols_a), (rows_b, cols_b)) = operands.shapes();
+    // The 16x16 kernels index the buffers unchecked, so a mis-sized one must stop here
+    operands.check_lengths()?;
+    
+    check_matmul_options(precision, result_dtype, (rows_a, cols_a), (rows_b, cols_b), options)?;
+    
+    let memory_guard = match options.max_memory_bytes {
+        Some(limit) => {
+            let inputs = memory::estimate_workload_bytes(precision, (rows_a, cols_a), (rows_b, cols_b)).inputs;
+            Some(memory::MemoryGuard::start(limit, inputs))
+        }
+        None => None,
+    };
+    
+    // Perform matrix multiplication with timing
+    // Fast 16x16 kernels use kernel-only timing; fallback paths include conversion overhead.
+    let (kernel_choice, kernel) = match &operands {
+        MatmulOperands::SparseB(..) => sparse::resolve_choice(precision, (rows_a, cols_a), (rows_b, cols_b), options)?,
+        _ => kernels::resolve_choice(precision, (rows_a, cols_a), (rows_b, cols_b), options)?,
+    };
+    shapes::record_dispatch(precision, kernel.name());
+    #[allow(unused_mut)]
+    let mut run = match &operands {
+        MatmulOperands::Matrices(matrix_a, matrix_b) => kernel.run(matrix_a, matrix_b, options)?,
+        MatmulOperands::TransposedB(matrix_a, b_t) => kernel.run_b_transposed(matrix_a, b_t, options)?,
+        MatmulOperands::SparseB(matrix_a, matrix_b) => {
+            let (result, kernel_time) = sparse::matmul_fp32_sparse(matrix_a, matrix_b, options.clock());
+            KernelResult::new(result, kernel_time)
+        }
+        #[cfg(any(feature = "seed-gen", test))]
+        MatmulOperands::SeedU8I8(prepared) if kernel.name() == "u8i8_16x16" => {
+            prepared.u8i8_kernel_result(options.threads, options.clock())?
+        }
+        #[cfg(any(feature = "seed-gen", test))]
+        MatmulOperands::SeedPipelined { seed, k } if kernel.name() == "u8i8_16x16" => seed_pipeline::run(seed, *k, options)?,
+        #[cfg(any(feature = "seed-gen", test))]
+        MatmulOperands::SeedU8I8(_) | MatmulOperands::SeedPipelined { .. } => {
+            return Err(SolverError::Internal(format!("seed-packed operands cannot run on {}", kernel.name())));
+        }
+    };
+    #[cfg(any(feature = "fault-injection", test))]
+    faults::after_kernel(&mut run)?;
+    let KernelResult { mut result, kernel_time: elapsed, pack_cache_hit, accumulators, result_f64, simd_variant, threads } = run;
+    // Transposed and seed-packed B bypass the caches
+    let b_cache_used = (matches!(operands, MatmulOperands::Matrices(..)) && packs_b_through_cache(kernel.name(), (rows_b, cols_b), options))
+        .then_some(!options.bypass_b_cache);
+    let cache_stats = match b_cache_used {
+        Some(true) => options.caches().stats_for(kernel.name()),
+        _ => None,
+    };
+    if let Some(gemm) = &gemm {
+        gemm.apply(&mut result);
+    }
+    if let Some(guard) = &memory_guard {
+        guard.check("kernel")?;
+    }
+    
+    // Single-pass kernels cannot be interrupted, so the deadline is enforced once they return
+    // (chunked kernels also check it between k slices, see chunked.rs)
+    if let Some(deadline) = options.deadline {
+        let spent = clock.elapsed_since(compute_start);
+        if spent > deadline {
+            return Err(SolverError::DeadlineExceeded(format!(
+                "compute took {:.3} ms, deadline was {:.3} ms",
+                spent.as_secs_f64() * 1000.0,
+                deadline.as_secs_f64() * 1000.0
+            )));
+        }
+    }
+    
+    // Kernels that cannot restrict j computed everything; slice afterwards
+    let result = match &options.result_columns {
+        Some(columns) if !kernel.supports_column_subset() => select_columns(&result, columns),
+        _ => result,
+    };
+    let result_cols = result.cols;
+    
+    // Compute metrics
+    let latency_ms = elapsed.as_secs_f64() * 1000.0;
+    let total_ops = (rows_a * cols_a * cols_b) as f64; // Multiply-add operations
+    let ops_per_second = total_ops / elapsed.as_secs_f64();
+    let throughput_ops_per_sec = ops_per_second;
+    
+    // Compute result hash over the dtype this precision path declares
+    let (result_hash, result_f64) = match result_dtype {
+        ResultDtype::I32 => (compute_hash_i32(&exact_accumulators(kernel.name(), accumulators)?), None),
+        ResultDtype::F64 => {
+            let values = exact_f64(kernel.name(), result_f64)?;
+            (compute_hash_f64(&values), Some(std::sync::Arc::new(values)))
+        }
+        _ if options.defer_result_hash => (String::new(), None),
+        _ => (compute_hash_rounded(&result, result_dtype, options.hash_rounding), None),
+    };
+    #[cfg(feature = "blake3-hash")]
+    let (tile_trace, trace_root) = if options.tile_trace {
+        let (trace, root) = tile_trace(&result, result_dtype);
+        (Some(trace), Some(root))
+    } else {
+        (None, None)
+    };
+    #[cfg(not(feature = "blake3-hash"))]
+    let (tile_trace, trace_root) = (None, None);
+    if let Some(guard) = &memory_guard {
+        guard.check("hashing")?;
+    }
+    
+    if let Some(input_metadata) = metadata {
+        let filled: Vec<&str> = [
+            ("compiler_flags", input_metadata.compiler_flags.is_none()),
+            ("libraries", input_metadata.libraries.is_none()),
+        ]
+        .into_iter()
+        .filter_map(|(field, missing)| missing.then_some(field))
+        .collect();
+        if !filled.is_empty() {
+            warnings.push(
+                warnings::SolverWarning::new(
+                    warnings::METADATA_AUTO_POPULATED,
+                    format!("input metadata had no {}; filled in from this build", filled.join(" or ")),
+                )
+                .with_context(serde_json::json!({ "fields": filled })),
+            );
+        }
+    }
+    if precision == "fp16" {
+        warnings.push(
+            warnings::SolverWarning::new(
+                warnings::FP16_NOT_BIT_STABLE,
+                "fp16 results are not bit-stable across kernels; verify with the same kernel",
+            )
+            .with_context(serde_json::json!({ "kernel": kernel.name() })),
+        );
+    }
+    if kernel.name() == "fp32_16x16" && options.fp32_accumulation == Fp32Accumulation::Fast {
+        warnings.push(warnings::SolverWarning::new(
+            warnings::FAST_ACCUMULATION,
+            "fast fp32 accumulation was used; the hash depends on build flags and will not verify with strict",
+        ));
+    }
+    
+    // Estimate memory usage
+    let memory_usage_mb = Some(estimate_memory_usage(rows_a, cols_a, rows_b, cols_b));
+    
+    // Build output
+    Ok(types::Output {
+        result_matrix: std::sync::Arc::new(result),
+        result_f64,
+        result_hash,
+        tile_trace,
+        trace_root,
+        audit_passed: None,  // Set by caller (audit::write_output_audited)
+        verified: None,  // Set by the API handlers
+        warnings: Vec::new(),  // Filled from the collector by compute_workload_with_options
+        batch_hashes: None,  // Set by caller (batched::compute_batched)
+        auxiliary_results: Vec::new(),  // Set by caller (error_metrics::attach_reference)
+        metrics: types::Metrics {
+            latency_ms,
+            throughput_ops_per_sec,
+            ops_per_second,
+            memory_usage_mb,
+            parse_time_ms: None,  // Set by caller (main.rs)
+            kernel_time_ms: Some(elapsed.as_secs_f64() * 1000.0),
+            serialize_time_ms: None,  // Set by caller (main.rs)
+            pack_cache_hit,
+            batch_kernel_time_ms: None,  // Set by caller (batched::compute_batched)
+            redundancy_retries: None,  // Set by caller (redundancy::compute_dual)
+            seed_pipelined: None,  // Set by caller (compute_seed_workload)
+            peak_request_memory_mb: None,  // Set by the API handlers
+            verify_time_ms: None,  // Set by the API handlers
+            threads_used: threads.or(options.threads.map(|_| 1)),
+            cache_stats,
+        },
+        metadata: types::OutputMetadata {
+            precision: precision.to_string(),
+            matrix_a_shape: (rows_a, cols_a),
+            matrix_b_shape: (rows_b, cols_b),
+            result_shape: (rows_a, result_cols),
+            // Explicit values win; otherwise describe this build
+            compiler_flags: metadata
+                .as_ref()
+                .and_then(|m| m.compiler_flags.clone())
+                .or_else(|| Some(build_compiler_flags().to_string())),
+            libraries: metadata
+                .as_ref()
+                .and_then(|m| m.libraries.clone())
+                .or_else(|| Some(build_libraries())),
+            result_dtype: result_dtype.as_str().to_string(),
+            hash_input_description: match &options.hash_rounding {
+                Some(rounding) => format!("{}, {}", result_dtype.hash_input_description(), rounding.describe()),
+                None => result_dtype.hash_input_description().to_string(),
+            },
+            hash_mode: if options.hash_rounding.is_some() { "rounded" } else { "exact" }.to_string(),
+            hash_rounding: options.hash_rounding,
+            kernel: Some(kernel.name().to_string()),
+            kernel_choice: Some(kernel_choice),
+            fp32_accumulation: (kernel.name() == "fp32_16x16").then(|| options.fp32_accumulation.as_str().to_string()),
+            fp32_summation: (options.fp32_summation != Fp32Summation::Naive).then(|| options.fp32_summation.as_str().to_string()),
+            simd_variant: simd_variant.map(str::to_string),
+            threads,
+            accumulator: (options.accumulator != Accumulator::I32).then(|| options.accumulator.as_str().to_string()),
+            f64_accumulation: (precision == "fp64").then_some(true),
+            quantization_scheme: quantization_scheme(precision).map(str::to_string),
+            fp16_rounding: (precision == "fp16").then_some(options.fp16_rounding),
+            a_layout: None,  // Set by caller (compute_matmul_once)
+            b_layout: None,  // Set by caller (compute_matmul_once)
+            redundancy: None,  // Set by caller (redundancy::compute_dual)
+            cross_check_kernel: None,
+            lossy_parse_count: None,  // Set by caller (main.rs / API)
+            result_columns: options.result_columns.clone(),
+            quantization_scales: None,
+            alpha: None,  // Set by caller (compute_matmul_once)
+            beta: None,
+            batch: None,  // Set by caller (batched::compute_batched)
+            b_cache_used,
+        },
+    })
+}
+
+/// Helper function to add timing breakdown to metrics
+pub fn add_timing_breakdown(
+    mut output: types::Output,
+    parse_time_ms: Option<f64>,
+    serialize_time_ms: Option<f64>,
+) -> types::Output {
+    output.metrics.parse_time_ms = parse_time_ms;
+    output.metrics.serialize_time_ms = serialize_time_ms;
+    output
+}
+
+// Keep old function name for backward compatibility
+pub fn compute_matmul(input: types::Input) -> Result<types::Output, SolverError> {
+    compute_workload(input)
+}
+
+/// Verify correctness of a result by recomputing and comparing hashes
+pub fn verify_correctness(
+    matrix_a: &FlatMatrix,
+    matrix_b: &FlatMatrix,
+    precision: &str,
+    expected_hash: &str,
+) -> Result<bool, SolverError> {
+    verify_correctness_with_rounding(matrix_a, matrix_b, precision, None, expected_hash)
+}
+
+/// `verify_correctness` for hashes produced with `hash_mode: "rounded"`
+pub fn verify_correctness_with_rounding(
+    matrix_a: &FlatMatrix,
+    matrix_b: &FlatMatrix,
+    precision: &str,
+    rounding: Option<HashRounding>,
+    expected_hash: &str,
+) -> Result<bool, SolverError> {
+    let precision = &normalize_name(precision);
+    let dtype = ResultDtype::for_precision(precision)
+        .ok_or_else(|| Precision::unsupported(precision))?;
+    verify_correctness_as(matrix_a, matrix_b, precision, dtype, rounding, &ComputeOptions::default(), 1, expected_hash)
+}
+
+/// Re-verify a stored output using only its metadata plus the original inputs.
+/// The hash preimage is rebuilt from the declared `result_dtype`.
+pub fn verify_with_metadata(
+    matrix_a: &FlatMatrix,
+    matrix_b: &FlatMatrix,
+    metadata: &types::OutputMetadata,
+    expected_hash: &str,
+) -> Result<bool, SolverError> {
+    let dtype = ResultDtype::parse(&metadata.result_dtype)
+        .ok_or_else(|| SolverError::InvalidInput(format!("Unsupported result dtype: {}", metadata.result_dtype)))?;
+    if metadata.alpha.is_some() || metadata.beta.is_some() {
+        return Err(SolverError::InvalidInput(
+            "outputs scaled by alpha or beta cannot be verified from matrix_a and matrix_b alone".to_string(),
+        ));
+    }
+    let row_major;
+    let matrix_a = match ALayout::parse(metadata.a_layout.as_deref())? {
+        ALayout::RowMajor => matrix_a,
+        ALayout::Transposed => {
+            row_major = transpose(matrix_a);
+            &row_major
+        }
+    };
+    // The transposed-B kernels give bit-identical results, so B is checked row-major
+    let row_major_b;
+    let matrix_b = match BLayout::parse(metadata.b_layout.as_deref())? {
+        BLayout::RowMajor => matrix_b,
+        BLayout::Transposed => {
+            row_major_b = transpose(matrix_b);
+            &row_major_b
+        }
+    };
+    // A batched output is recomputed item by item
+    let batch = match metadata.batch {
+        Some(_) => batched::items(metadata.batch, matrix_a.rows)?,
+        None => 1,
+    };
+    // Re-run the kernel the output was produced with, when this machine has it
+    let recorded = metadata.kernel_choice.as_ref().map(|c| c.kernel.clone());
+    let forced = recorded.filter(|name| {
+        let options = ComputeOptions { kernel: Some(name.clone()), ..Default::default() };
+        kernels::select_kernel(
+            (matrix_a.rows / batch, matrix_a.cols),
+            (matrix_b.rows, matrix_b.cols),
+            &metadata.precision,
+            &options,
+            kernels::DetectedFeatures::current(),
+        )
+        .is_ok()
+    });
+    let options = ComputeOptions {
+        kernel: forced,
+        fp32_accumulation: match metadata.fp32_accumulation.as_deref() {
+            Some(name) => Fp32Accumulation::parse(name)?,
+            None => Fp32Accumulation::default(),
+        },
+        fp32_summation: match metadata.fp32_summation.as_deref() {
+            Some(name) => Fp32Summation::parse(name)?,
+            None => Fp32Summation::default(),
+        },
+        accumulator: match metadata.accumulator.as_deref() {
+            Some(name) => Accumulator::parse(name)?,
+            None => Accumulator::default(),
+        },
+        fp16_rounding: metadata.fp16_rounding.unwrap_or_default(),
+        ..Default::default()
+    };
+    verify_correctness_as(matrix_a, matrix_b, &metadata.precision, dtype, metadata.hash_rounding, &options, batch, expected_hash)
+}
+
+/// Recompute the hash of A·B, where A stacks `batch` items that each multiply B (1 for a
+/// plain matmul), and compare it with `expected_hash`
+#[allow(clippy::too_many_arguments)]
+fn verify_correctness_as(
+    matrix_a: &FlatMatrix,
+    matrix_b: &FlatMatrix,
+    precision: &str,
+    dtype: ResultDtype,
+    rounding: Option<HashRounding>,
+    options: &ComputeOptions,
+    batch: usize,
+    expected_hash: &str,
+) -> Result<bool, SolverError> {
+    MatmulOperands::Matrices(matrix_a, matrix_b).check_lengths()?;
+    if matrix_a.cols != matrix_b.rows {
+        return Err(SolverError::InvalidInput(format!("Matrix dimensions incompatible: A is {}x{}, B is {}x{}",
+            matrix_a.rows, matrix_a.cols, matrix_b.rows, matrix_b.cols)));
+    }
+    // Same selector as compute_matmul_internal, so both always run the same kernel
+    let item_shape = (matrix_a.rows / batch, matrix_a.cols);
+    let (_, kernel) = kernels::resolve_choice(precision, item_shape, (matrix_b.rows, matrix_b.cols), options)?;
+    let runs = b_cache::with_request_b(matrix_b, || (0..batch)
+        .map(|i| {
+            let item;
+            let item_a = if batch == 1 {
+                matrix_a
+            } else {
+                item = batched::item(matrix_a, i, batch);
+                &item
+            };
+            #[allow(unused_mut)]
+            let mut run = kernel.run(item_a, matrix_b, options)?;
+            #[cfg(any(feature = "fault-injection", test))]
+            faults::after_kernel(&mut run)?;
+            Ok(run)
+        })
+        .collect::<Result<Vec<_>, SolverError>>())?;
+    let run = batched::stack(runs);
+    
+    let computed_hash = match dtype {
+        ResultDtype::I32 => compute_hash_i32(&exact_accumulators(kernel.name(), run.accumulators)?),
+        ResultDtype::F64 => compute_hash_f64(&exact_f64(kernel.name(), run.result_f64)?),
+        _ => compute_hash_rounded(&run.result, dtype, rounding),
+    };
+    Ok(computed_hash == expected_hash)
+}
+
+/// Check one disputed tile of a traced result against its claimed digest.
+///
+/// `tile` is the (row, col) origin from the trace. The tile is recomputed with the
+/// kernel automatic selection picks for the full problem; when that kernel can run the
+/// tile as a sub-problem (fp32, fp16, u8i8 - int8 quantizes with whole-matrix scales),
+/// only the tile's rows of A and columns of B are multiplied.
+#[cfg(feature = "blake3-hash")]
+pub fn verify_tile(
+    matrix_a: &FlatMatrix,
+    matrix_b: &FlatMatrix,
+    precision: &str,
+    tile: (usize, usize),
+    claimed_digest: &str,
+) -> Result<bool, SolverError> {
+    let precision = &normalize_name(precision);
+    let (m, n) = (matrix_a.rows, matrix_b.cols);
+    let (row, col) = tile;
+    if matrix_a.cols != matrix_b.rows {
+        return Err(SolverError::InvalidInput(format!("Matrix dimensions incompatible: A is {}x{}, B is {}x{}",
+            matrix_a.rows, matrix_a.cols, matrix_b.rows, matrix_b.cols)));
+    }
+    if row % TILE_ROWS != 0 || col % TILE_COLS != 0 || row >= m || col >= n {
+        return Err(SolverError::InvalidInput(format!("({}, {}) is not a tile origin of a {}x{} result", row, col, m, n)));
+    }
+    let dtype = ResultDtype::for_precision(precision)
+        .ok_or_else(|| Precision::unsupported(precision))?;
+    let rows = TILE_ROWS.min(m - row);
+    let cols = TILE_COLS.min(n - col);
+    
+    let (_, kernel) = kernels::resolve_choice(precision, (m, matrix_a.cols), (matrix_b.rows, n), &ComputeOptions::default())?;
+    let k = matrix_a.cols;
+    let sub_shapes = ((rows, k), (k, cols));
+    let options = ComputeOptions::default();
+    // Quantization scales span whole operands, so a sub-matrix would be quantized differently
+    let quantized = quantization_scheme(precision).is_some();
+    let digest = if (rows, cols) != (m, n) && !quantized && kernel.supports(precision, sub_shapes.0, sub_shapes.1) {
+        let sub_a = FlatMatrix { data: matrix_a.data[row * k..(row + rows) * k].to_vec(), rows, cols: k };
+        let sub_b = select_columns(matrix_b, &(col..col + cols).collect::<Vec<_>>());
+        let sub = kernel.run(&sub_a, &sub_b, &options)?.result;
+        tile_digest(&sub, 0, 0, rows, cols, dtype)
+    } else {
+        let full = kernel.run(matrix_a, matrix_b, &options)?.result;
+        tile_digest(&full, row, col, rows, cols, dtype)
+    };
+    Ok(digest == claimed_digest)
+}
+
+#[cfg(test)]
+#[allow(clippy::identity_op, clippy::erasing_op)]
+mod tests {
+    use super::*;
+
+    // The unit tests count allocations as the crate's binaries do
+    #[cfg(feature = "counting-alloc")]
+    #[global_allocator]
+    static GLOBAL: memory::CountingAllocator = memory::CountingAllocator;
+
+    /// Precision names this build computes, for tests that loop over all of them
+    pub(crate) fn precisions() -> Vec<&'static str> {
+        Precision::ALL.into_iter().filter(Precision::is_available).map(|p| p.as_str()).collect()
+    }
+    
+    // Helper function to create FlatMatrix from Vec<Vec<f32>> for tests
+    fn to_flat_matrix(nested: Vec<Vec<f32>>) -> FlatMatrix {
+        let rows = nested.len();
+        if rows == 0 {
+            return FlatMatrix { data: Vec::new(), rows: 0, cols: 0 };
+        }
+        let cols = nested[0].len();
+        let mut data = Vec::with_capacity(rows * cols);
+        for row in nested {
+            data.extend_from_slice(&row);
+        }
+        FlatMatrix { data, rows, cols }
     }
     
     #[test]
@@ -1220,9 +5129,212 @@ mod tests {
         assert_eq!(hash1, hash2);
         assert_eq!(hash2, hash3);
     }
-    
+    
+    #[test]
+    fn test_verify_correctness() {
+        let a = to_flat_matrix(vec![
+            vec![1.0, 2.0],
+            vec![3.0, 4.0],
+        ]);
+        let b = to_flat_matrix(vec![
+            vec![5.0, 6.0],
+            vec![7.0, 8.0],
+        ]);
+        
+        // Compute and get hash
+        let (result, _) = matmul_fp32(&a, &b);
+        let correct_hash = compute_hash(&result);
+        
+        // Verify it matches
+        assert!(verify_correctness(&a, &b, "fp32", &correct_hash).unwrap());
+        
+        // Wrong hash should fail
+        assert!(!verify_correctness(&a, &b, "fp32", "wrong_hash").unwrap());
+    }
+    
+    #[cfg(feature = "fp16")]
+    #[test]
+    fn test_fp16_correctness() {
+        let a = to_flat_matrix(vec![
+            vec![1.0, 2.0],
+            vec![3.0, 4.0],
+        ]);
+        let b = to_flat_matrix(vec![
+            vec![5.0, 6.0],
+            vec![7.0, 8.0],
+        ]);
+        
+        let result = matmul_fp16(&a, &b, Fp16Rounding::NearestEven);
+        
+        // FP16 should give approximately correct results (may have small precision differences)
+        assert!((result.data[0 * result.cols + 0] - 19.0).abs() < 0.1);
+        assert!((result.data[0 * result.cols + 1] - 22.0).abs() < 0.1);
+        assert!((result.data[1 * result.cols + 0] - 43.0).abs() < 0.1);
+        assert!((result.data[1 * result.cols + 1] - 50.0).abs() < 0.1);
+    }
+
+    #[cfg(feature = "fp16")]
+    #[test]
+    fn test_fp16_long_reduction_stays_near_fp32() {
+        // 16x4096x20 runs on fp16_generic; its sums go far past f16's ±65504
+        let (mut a, mut b) = generate_matrices_from_seed(b"fp16-long-k", 16, 4096, 4096, 20);
+        a.data.iter_mut().chain(b.data.iter_mut()).for_each(|x| *x /= 3.0);
+        let input = types::Input::matmul(a.clone(), b.clone(), "fp16");
+        let output = compute_workload(input).unwrap();
+        assert_eq!(output.metadata.kernel.as_deref(), Some("fp16_generic"));
+        let fp32 = matmul_fp32(&a, &b).0;
+        let scale = fp32.data.iter().fold(0f32, |max, x| max.max(x.abs()));
+        assert!(scale > 65504.0, "{}", scale);
+        for (i, (&x, &y)) in output.result_matrix.data.iter().zip(&fp32.data).enumerate() {
+            assert!(x.is_finite() && (x - y).abs() <= 0.02 * scale, "element {}: fp16 {} vs fp32 {}", i, x, y);
+        }
+        assert!(verify_correctness(&a, &b, "fp16", &output.result_hash).unwrap());
+
+        // Both kernels sum in f32 in k order (outside the NEON dot product)
+        if simd::paths().f32_in_order {
+            let b16 = select_columns(&b, &(0..16).collect::<Vec<_>>());
+            let packed = matmul_fp16_16x16(&a, BOperand::RowMajor(&b16, CacheContext::global()), None, Fp16Rounding::NearestEven, None, &SystemClock).0;
+            assert_eq!(matmul_fp16(&a, &b16, Fp16Rounding::NearestEven).data, packed.data);
+        }
+    }
+
+    #[cfg(feature = "fp16")]
+    #[test]
+    fn test_fp16_rounding_boundaries() {
+        let (ne, tz) = (Fp16Rounding::NearestEven, Fp16Rounding::TowardZero);
+        let ulp_1 = 2f32.powi(-10);
+        let max = 65504.0;
+        // (input, nearest-even, toward-zero)
+        let cases = [
+            (1.0 + ulp_1 / 2.0, 1.0, 1.0),                                 // tie, even below
+            (1.0 + 1.5 * ulp_1, 1.0 + 2.0 * ulp_1, 1.0 + ulp_1),           // tie, odd below
+            (1.0 + 0.75 * ulp_1, 1.0 + ulp_1, 1.0),
+            (-(1.0 + 0.75 * ulp_1), -(1.0 + ulp_1), -1.0),
+            (2f32.powi(-24), 2f32.powi(-24), 2f32.powi(-24)),               // smallest subnormal
+            (2f32.powi(-25), 0.0, 0.0),                                     // tie with zero
+            (3.0 * 2f32.powi(-26), 2f32.powi(-24), 0.0),
+            (2.5 * 2f32.powi(-24), 2.0 * 2f32.powi(-24), 2.0 * 2f32.powi(-24)), // subnormal tie
+            (2f32.powi(-14) - 2f32.powi(-26), 2f32.powi(-14), 2f32.powi(-14) - 2f32.powi(-24)), // into the normals
+            (f32::from_bits(1), 0.0, 0.0),                                  // f32 subnormal
+            (65519.0, max, max),
+            (65520.0, f32::INFINITY, max),                                  // overflow on a tie
+            (-1e6, f32::NEG_INFINITY, -max),
+            (f32::INFINITY, f32::INFINITY, f32::INFINITY),
+            (-0.0, -0.0, -0.0),
+        ];
+        for (x, expected_ne, expected_tz) in cases {
+            assert_eq!(ne.round(x, Operand::A, 0).to_bits(), expected_ne.to_bits(), "nearest_even {:e}", x);
+            assert_eq!(tz.round(x, Operand::A, 0).to_bits(), expected_tz.to_bits(), "toward_zero {:e}", x);
+            // Stochastic rounding lands on the truncated value or the next one out (past 65504
+            // that is infinity)
+            let stochastic = Fp16Rounding::Stochastic { seed: 7 }.round(x, Operand::B, 3);
+            let up = f16_bits_to_f32(tz.to_f16_bits(x, Operand::A, 0) + 1);
+            assert!(stochastic == expected_tz || stochastic == up, "stochastic {:e} gave {:e}", x, stochastic);
+        }
+        for mode in [ne, tz, Fp16Rounding::Stochastic { seed: 7 }] {
+            assert!(mode.round(f32::NAN, Operand::A, 0).is_nan());
+            assert_eq!(mode.round(1.0 + ulp_1, Operand::A, 0), 1.0 + ulp_1);
+        }
+
+        // Nearest-even is half's conversion, bit for bit
+        for bits in (0..=u32::MAX).step_by(65_521).chain([0x3880_0000, 0x3300_0000, 0x477F_F000, 0x477F_EFFF]) {
+            let x = f32::from_bits(bits);
+            let expected = half::f16::from_f32(x);
+            assert_eq!(ne.to_f16_bits(x, Operand::A, 0), expected.to_bits(), "{:#010x}", bits);
+            assert_eq!(f16_bits_to_f32(expected.to_bits()).to_bits(), expected.to_f32().to_bits(), "{:#010x}", bits);
+        }
+
+        // A value a quarter ulp above 1 rounds up about a quarter of the time, the same way
+        // for the same seed and element
+        let quarter = 1.0 + ulp_1 / 4.0;
+        let ups = |seed| (0..4096).filter(|&i| Fp16Rounding::Stochastic { seed }.round(quarter, Operand::A, i) > 1.0).count();
+        assert!((900..1150).contains(&ups(1)), "{}", ups(1));
+        assert_eq!(ups(1), ups(1));
+        assert_ne!(ups(1), ups(2));
+
+        assert_eq!(Fp16Rounding::from_mode("stochastic", Some(9)).unwrap(), Fp16Rounding::Stochastic { seed: 9 });
+        assert!(Fp16Rounding::from_mode("stochastic", None).is_err());
+        assert!(Fp16Rounding::from_mode("toward_zero", Some(9)).is_err());
+        assert!(Fp16Rounding::from_mode("up", None).is_err());
+        let json = serde_json::to_string(&Fp16Rounding::Stochastic { seed: 9 }).unwrap();
+        assert_eq!(json, r#"{"mode":"stochastic","seed":9}"#);
+    }
+
+    #[cfg(feature = "fp16")]
+    #[test]
+    fn test_fp16_rounding_changes_the_hash() {
+        // Seed bytes are exact in fp16; thirds are not
+        let (mut a, mut b) = generate_matrices_from_seed(b"fp16-rounding", 16, 64, 64, 16);
+        a.data.iter_mut().chain(&mut b.data).for_each(|x| *x /= 3.0);
+        let modes = [
+            Fp16Rounding::NearestEven,
+            Fp16Rounding::TowardZero,
+            Fp16Rounding::Stochastic { seed: 1 },
+            Fp16Rounding::Stochastic { seed: 2 },
+        ];
+        let mut hashes = Vec::new();
+        for fp16_rounding in modes {
+            let input = types::Input::matmul(a.clone(), b.clone(), "fp16");
+            let options = ComputeOptions { fp16_rounding, ..Default::default() };
+            let output = compute_workload_with_options(input.clone(), &options).unwrap();
+            assert_eq!(output.metadata.fp16_rounding, Some(fp16_rounding));
+            assert_eq!(compute_workload_with_options(input, &options).unwrap().result_hash, output.result_hash);
+            assert!(verify_with_metadata(&a, &b, &output.metadata, &output.result_hash).unwrap(), "{:?}", fp16_rounding);
+            hashes.push(output.result_hash);
+        }
+        assert!(hashes.iter().enumerate().all(|(i, h)| !hashes[..i].contains(h)), "{:?}", hashes);
+
+        // The cached Bᵀ is keyed by mode: the same B under another mode is converted afresh
+        let run = |mode| matmul_fp16_16x16(&a, BOperand::RowMajor(&b, CacheContext::global()), None, mode, None, &SystemClock).0.data;
+        let nearest = run(Fp16Rounding::NearestEven);
+        let toward_zero = run(Fp16Rounding::TowardZero);
+        assert_ne!(nearest, toward_zero);
+        assert_eq!(run(Fp16Rounding::NearestEven), nearest);
+
+        let options = ComputeOptions { fp16_rounding: Fp16Rounding::TowardZero, ..Default::default() };
+        let input = types::Input::matmul(a, b, "fp32");
+        let err = compute_workload_with_options(input, &options).unwrap_err();
+        assert!(err.to_string().contains("fp16 rounding toward_zero applies to fp16, not fp32"), "{}", err);
+    }
+
+    #[test]
+    fn test_bf16_kernels_agree() {
+        assert_eq!(round_bf16(1.0 + 2f32.powi(-8)), 1.0);
+        assert_eq!(round_bf16(-3.0078125), -3.0);
+        assert!(round_bf16(f32::from_bits(0x7F80_0001)).is_nan());
+
+        // Seed bytes are exact in bf16; thirds are not
+        let (mut a, mut b) = generate_matrices_from_seed(b"bf16", 16, 300, 300, 16);
+        a.data.iter_mut().chain(&mut b.data).for_each(|x| *x /= 3.0);
+        let input = types::Input::matmul(a.clone(), b.clone(), "bf16");
+        let output = compute_workload_with_options(input.clone(), &ComputeOptions::default()).unwrap();
+        assert_eq!(output.metadata.precision, "bf16");
+        assert_eq!(output.metadata.kernel.as_deref(), Some("bf16_16x16"));
+        assert_eq!(output.metadata.result_dtype, "f32");
+        assert!(verify_correctness(&a, &b, "bf16", &output.result_hash).unwrap());
+        assert!(!verify_correctness(&a, &b, "fp32", &output.result_hash).unwrap());
+
+        // Both kernels add the same f32 products in k order, so they agree bit for bit
+        let generic = ComputeOptions { kernel: Some("bf16_generic".to_string()), ..Default::default() };
+        assert_eq!(compute_workload_with_options(input.clone(), &generic).unwrap().result_hash, output.result_hash);
+        let columns = ComputeOptions { result_columns: Some(vec![9, 2]), ..Default::default() };
+        let subset = compute_workload_with_options(input, &columns).unwrap();
+        for i in 0..16 {
+            assert_eq!(subset.result_matrix.data[i * 2], output.result_matrix.data[i * 16 + 9]);
+            assert_eq!(subset.result_matrix.data[i * 2 + 1], output.result_matrix.data[i * 16 + 2]);
+        }
+
+        // Truncation moves each operand by less than 2^-7 relative, so a product by less than 2^-6
+        let (reference, _) = matmul_fp32(&a, &b);
+        for p in 0..16 * 16 {
+            let (i, j) = (p / 16, p % 16);
+            let bound: f32 = (0..300).map(|q| (a.data[i * 300 + q] * b.data[q * 16 + j]).abs()).sum::<f32>() * 2f32.powi(-5);
+            assert!((output.result_matrix.data[p] - reference.data[p]).abs() <= bound, "element {}", p);
+        }
+    }
+
     #[test]
-    fn test_verify_correctness() {
+    fn test_int8_correctness() {
         let a = to_flat_matrix(vec![
             vec![1.0, 2.0],
             vec![3.0, 4.0],
@@ -1232,112 +5344,1861 @@ mod tests {
             vec![7.0, 8.0],
         ]);
         
-        // Compute and get hash
-        let (result, _) = matmul_fp32(&a, &b);
-        let correct_hash = compute_hash(&result);
-        
-        // Verify it matches
-        assert!(verify_correctness(&a, &b, "fp32", &correct_hash).unwrap());
+        let result = matmul_int8(&a, &b, Int8Scaling::PerTensor);
         
-        // Wrong hash should fail
-        assert!(!verify_correctness(&a, &b, "fp32", "wrong_hash").unwrap());
+        // INT8 should give approximately correct results (quantization may cause differences)
+        assert!((result.data[0 * result.cols + 0] - 19.0).abs() < 1.0);
+        assert!((result.data[0 * result.cols + 1] - 22.0).abs() < 1.0);
+        assert!((result.data[1 * result.cols + 0] - 43.0).abs() < 1.0);
+        assert!((result.data[1 * result.cols + 1] - 50.0).abs() < 1.0);
     }
     
     #[test]
-    fn test_fp16_correctness() {
-        let a = to_flat_matrix(vec![
-            vec![1.0, 2.0],
-            vec![3.0, 4.0],
-        ]);
-        let b = to_flat_matrix(vec![
-            vec![5.0, 6.0],
-            vec![7.0, 8.0],
-        ]);
+    fn test_int4_matches_fp32_within_quantization_error() {
+        let values = [-8i8, 7, 0, -1, 3, -5, 1];
+        let packed = PackedI4::pack(&values);
+        assert_eq!(packed.buf.len, 4);
+        assert_eq!(packed.unpack().as_slice(), &values);
+        assert_eq!((quantize_i4(1.0, 7.0), quantize_i4(-0.99, 7.0), quantize_i4(2.0, 7.0)), (7, -6, 7));
+
+        let a = to_flat_matrix(vec![vec![1.0, -2.5, 3.0], vec![0.5, 4.0, -1.0]]);
+        let b = to_flat_matrix(vec![vec![2.0, -1.0], vec![0.25, 3.0], vec![-4.0, 1.5]]);
+        let (reference, _) = matmul_fp32(&a, &b);
+        let result = matmul_int4(&a, &b);
+        // Truncation moves each operand by less than one step (max / 7)
+        let (max_a, max_b) = (4.0f32, 4.0f32);
+        for p in 0..result.data.len() {
+            let (i, j) = (p / 2, p % 2);
+            let bound: f32 = (0..3)
+                .map(|q| a.data[i * 3 + q].abs() * max_b / 7.0 + b.data[q * 2 + j].abs() * max_a / 7.0 + max_a * max_b / 49.0)
+                .sum();
+            assert!((result.data[p] - reference.data[p]).abs() <= bound, "element {}: {} vs {}", p, result.data[p], reference.data[p]);
+        }
+
+        // The 16x16 kernel unpacks the same values, so both kernels agree bit for bit
+        let (mut a, mut b) = generate_matrices_from_seed(b"int4", 16, 200, 200, 16);
+        a.data.iter_mut().chain(&mut b.data).for_each(|x| *x /= 3.0);
+        let input = types::Input::matmul(a.clone(), b.clone(), "int4");
+        let output = compute_workload_with_options(input.clone(), &ComputeOptions::default()).unwrap();
+        assert_eq!(output.metadata.kernel.as_deref(), Some("int4_16x16"));
+        assert_eq!(output.result_matrix.data, matmul_int4(&a, &b).data);
+        let generic = ComputeOptions { kernel: Some("int4_generic".to_string()), ..Default::default() };
+        assert_eq!(compute_workload_with_options(input.clone(), &generic).unwrap().result_hash, output.result_hash);
+        assert!(verify_correctness(&a, &b, "int4", &output.result_hash).unwrap());
+        assert!(!verify_correctness(&a, &b, "int8", &output.result_hash).unwrap());
+        let columns = ComputeOptions { result_columns: Some(vec![5]), ..Default::default() };
+        let subset = compute_workload_with_options(input, &columns).unwrap();
+        assert!((0..16).all(|i| subset.result_matrix.data[i] == output.result_matrix.data[i * 16 + 5]));
+    }
+
+    #[test]
+    fn test_int4_zero_inputs_have_finite_scales() {
+        assert_eq!(int4_scale([0.0f32; 4]), 1.0);
+        let zeros = FlatMatrix { data: vec![0.0; 16 * 32], rows: 16, cols: 32 };
+        let b = FlatMatrix { data: vec![0.0; 32 * 16], rows: 32, cols: 16 };
+        assert!(matmul_int4(&zeros, &b).data.iter().all(|&x| x == 0.0));
+        let (result, _) = matmul_int4_16x16(&zeros, &b, None, &SystemClock);
+        assert!(result.data.iter().all(|&x| x == 0.0));
+
+        // One zero operand still gives zeros, not NaN
+        let ones = FlatMatrix { data: vec![1.0; 32 * 16], rows: 32, cols: 16 };
+        assert!(matmul_int4(&zeros, &ones).data.iter().all(|&x| x == 0.0));
+    }
+
+    #[test]
+    fn test_int8_zero_inputs_have_finite_scales() {
+        assert_eq!(int8_scale([0.0f32; 4]), 1.0);
+        let zeros_a = FlatMatrix { data: vec![0.0; 16 * 32], rows: 16, cols: 32 };
+        let zeros_b = FlatMatrix { data: vec![0.0; 32 * 16], rows: 32, cols: 16 };
+        let (a, b) = generate_matrices_from_seed(b"int8-zeros", 16, 32, 32, 16);
+        for (a, b) in [(&zeros_a, &b), (&a, &zeros_b), (&zeros_a, &zeros_b)] {
+            for scaling in [Int8Scaling::PerTensor, Int8Scaling::PerRow] {
+                assert!(matmul_int8(a, b, scaling).data.iter().all(|&x| x == 0.0));
+                let (result, _, _, _) = matmul_int8_16x16(a, BOperand::RowMajor(b, CacheContext::global()), scaling, None, None, None, &SystemClock);
+                assert!(result.data.iter().all(|&x| x == 0.0));
+            }
+            #[cfg(feature = "openblas")]
+            assert!(matmul_int8_openblas(a, b).data.iter().all(|&x| x == 0.0));
+
+            // The hash is of zeros, so it verifies
+            let input = types::Input::matmul(a.clone(), b.clone(), "int8");
+            let output = compute_workload(input).unwrap();
+            assert!(output.result_matrix.data.iter().all(|&x| x == 0.0));
+            assert!(verify_correctness(a, b, "int8", &output.result_hash).unwrap());
+        }
+    }
+
+    #[test]
+    fn test_u8i8_sums_beyond_i32_are_exact_or_rejected() {
+        // Row 2 of A is all 255 and column 5 of B all -128, so only output (2, 5) is
+        // nonzero: 255 * -128 * k, just below i32::MIN at this k
+        let k = U8I8_SAFE_K + 7;
+        let exact = -32640 * k as i64;
+        assert!(exact < i32::MIN as i64);
+        let mut a = FlatMatrix { data: vec![0.0; 16 * k], rows: 16, cols: k };
+        a.data[2 * k..3 * k].fill(255.0);
+        let mut b = FlatMatrix { data: vec![0.0; k * 16], rows: k, cols: 16 };
+        for p in 0..k {
+            b.data[p * 16 + 5] = -128.0;
+        }
+        let input = |b: &FlatMatrix| types::Input::matmul(a.clone(), b.clone(), "u8i8");
+        let options = |kernel: &str, accumulator| ComputeOptions { kernel: Some(kernel.to_string()), accumulator, ..Default::default() };
+
+        for kernel in ["u8i8_16x16", "u8i8_generic"] {
+            let err = compute_workload_with_options(input(&b), &options(kernel, Accumulator::I32)).unwrap_err();
+            assert!(err.to_string().contains("overflow at output (2, 5): the sum -2147712000"), "{}: {}", kernel, err);
+
+            let output = compute_workload_with_options(input(&b), &options(kernel, Accumulator::I64)).unwrap();
+            for (idx, &x) in output.result_matrix.data.iter().enumerate() {
+                assert_eq!(x, if idx == 2 * 16 + 5 { exact as f32 } else { 0.0 }, "{} element {}", kernel, idx);
+            }
+            assert_eq!(output.metadata.accumulator.as_deref(), Some("i64"));
+            assert!(verify_with_metadata(&a, &b, &output.metadata, &output.result_hash).unwrap());
+        }
+
+        // Past the bound but with sums that fit, i32 runs still succeed with exact accumulators
+        let mut fits = b.clone();
+        for p in (0..k).step_by(2) {
+            fits.data[p * 16 + 5] = 127.0;
+        }
+        let single = compute_workload_with_options(input(&fits), &options("u8i8_16x16", Accumulator::I32)).unwrap();
+        let generic = compute_workload_with_options(input(&fits), &options("u8i8_generic", Accumulator::I32)).unwrap();
+        assert_eq!(single.result_hash, generic.result_hash);
+        // k is even, so each 127 is paired with a -128
+        assert_eq!(single.result_matrix.data[2 * 16 + 5], (-255 * (k as i64 / 2)) as f32);
+
+        // i64 sums have no i32 accumulators to hash and only apply to u8i8
+        let mut integer = input(&fits);
+        integer.hash_input = Some("i32le".to_string());
+        assert!(compute_workload_with_options(integer, &options("u8i8_16x16", Accumulator::I64)).is_err());
+        let int8 = types::Input { precision: "int8".to_string(), ..input(&fits) };
+        assert!(compute_workload_with_options(int8, &ComputeOptions { accumulator: Accumulator::I64, ..Default::default() }).is_err());
+    }
+
+    #[test]
+    fn test_fault_plans_reach_verification_and_caches() {
+        use faults::{with_plan, BitFlip, FaultPlan};
+        let (a, b) = generate_matrices_from_seed(b"faults", 16, 333, 333, 16);
+        let (_, other_b) = generate_matrices_from_seed(b"faults-b", 16, 333, 333, 16);
+        let input = |b: &FlatMatrix| types::Input::matmul(a.clone(), b.clone(), "int8");
+        let clean = compute_workload(input(&b)).unwrap();
+
+        // Verification recomputes through the same kernel hook, so a fault there rejects a true hash
+        let flip = FaultPlan { flip_result_bit: Some(BitFlip { element: 0, bit: 30 }), ..Default::default() };
+        assert!(!with_plan(flip, || verify_correctness(&a, &b, "int8", &clean.result_hash)).unwrap());
+        let fail = FaultPlan { kernel_error: true, ..Default::default() };
+        assert!(matches!(with_plan(fail, || verify_correctness(&a, &b, "int8", &clean.result_hash)), Err(SolverError::Internal(_))));
+        assert!(verify_correctness(&a, &b, "int8", &clean.result_hash).unwrap());
+
+        // A stale hit multiplies by the B packed before; other tests share the cache, so retry
+        // until nothing else packs between the two runs
+        let stale = FaultPlan { stale_cache_hit: true, ..Default::default() };
+        let output = (0..20)
+            .map(|_| {
+                compute_workload(input(&other_b)).unwrap();
+                with_plan(stale.clone(), || compute_workload(input(&b))).unwrap()
+            })
+            .find(|output| output.metrics.pack_cache_hit == Some(true))
+            .unwrap();
+        assert_ne!(output.result_hash, clean.result_hash);
+        assert_eq!(output.result_hash, compute_workload(input(&other_b)).unwrap().result_hash);
+    }
+
+    #[test]
+    fn test_fp32_strict_golden_hash() {
+        // Pinned: any build, target or BLAS must reproduce it
+        const GOLDEN: &str = "0d4d0cf9ff3e2fc06b95f705ca7a414b8cf31aa572c58a088f010c0eb5b29f0c";
+        let (mut a, mut b) = generate_matrices_from_seed(b"fp32_strict", 16, 300, 300, 16);
+        // Fractional operands, so the summation order shows in the last bits
+        a.data.iter_mut().chain(b.data.iter_mut()).for_each(|x| *x /= 7.0);
+        let input = types::Input::matmul(a.clone(), b.clone(), "fp32_strict");
+        let output = compute_workload(input.clone()).unwrap();
+        assert_eq!((output.metadata.kernel.as_deref(), output.metadata.result_dtype.as_str()), (Some("fp32_strict"), "f32"));
+        assert_eq!(output.result_hash, GOLDEN);
+
+        // The documented order: products rounded, then added in p order from 0
+        let mut reference = vec![0.0f32; 16 * 16];
+        for i in 0..16 {
+            for p in 0..300 {
+                for j in 0..16 {
+                    reference[i * 16 + j] += a.data[i * 300 + p] * b.data[p * 16 + j];
+                }
+            }
+        }
+        assert_eq!(output.result_matrix.data, reference);
+
+        // Options that pick other fp32 orders do not apply; no other kernel can be forced
+        let fast = ComputeOptions { fp32_accumulation: Fp32Accumulation::Fast, k_chunk: Some(64), packed_min_k: Some(0), ..Default::default() };
+        assert_eq!(compute_workload_with_options(input.clone(), &fast).unwrap().result_hash, GOLDEN);
+        let forced = ComputeOptions { kernel: Some("fp32_16x16".to_string()), ..Default::default() };
+        assert!(compute_workload_with_options(input, &forced).is_err());
+
+        // Verification dispatches to the same kernel
+        assert!(verify_correctness(&a, &b, "fp32_strict", GOLDEN).unwrap());
+        assert!(verify_with_metadata(&a, &b, &output.metadata, GOLDEN).unwrap());
+        assert!(!verify_correctness(&a, &b, "fp32_strict", &compute_hash(&matmul_fp32_16x16(&a, &b, Fp32Accumulation::Fast, &SystemClock).0)).unwrap());
+    }
+
+    #[test]
+    fn test_fp64_hashes_the_f64_result() {
+        let (mut a, mut b) = generate_matrices_from_seed(b"fp64", 16, 300, 300, 20);
+        a.data.iter_mut().chain(b.data.iter_mut()).for_each(|x| *x /= 3.0);
+        let input = |hash_input: Option<&str>| types::Input { matrix_a: a.clone(), matrix_b: b.clone(), precision: "fp64".to_string(), hash_input: hash_input.map(str::to_string), ..Default::default() };
+        let output = compute_workload(input(None)).unwrap();
+        assert_eq!(output.metadata.kernel.as_deref(), Some("fp64_blocked"));
+        assert_eq!((output.metadata.result_dtype.as_str(), output.metadata.f64_accumulation), ("f64", Some(true)));
+
+        // Same summation order as a plain f64 loop, rounded to f32 once at the end
+        let mut reference = vec![0.0f64; 16 * 20];
+        for i in 0..16 {
+            for p in 0..300 {
+                for j in 0..20 {
+                    reference[i * 20 + j] += a.data[i * 300 + p] as f64 * b.data[p * 20 + j] as f64;
+                }
+            }
+        }
+        let values = output.result_f64.as_deref().unwrap();
+        assert_eq!(values, &reference[..]);
+        assert_eq!(output.result_hash, compute_hash_f64(&reference));
+        assert!(output.result_matrix.data.iter().zip(&reference).all(|(&x, &r)| x == r as f32));
+        let fp32 = matmul_fp32(&a, &b).0;
+        assert!(fp32.data.iter().zip(&reference).any(|(&x, &r)| x != r as f32));
+        assert!(verify_correctness(&a, &b, "fp64", &output.result_hash).unwrap());
+        assert!(verify_with_metadata(&a, &b, &output.metadata, &output.result_hash).unwrap());
+        audit::audit_output_json(&serde_json::to_string(&output).unwrap()).unwrap();
+
+        // f32le hashes the rounded result instead
+        let rounded = compute_workload(input(Some("f32le"))).unwrap();
+        assert_eq!(rounded.result_hash, compute_hash(&rounded.result_matrix));
+        assert_eq!((rounded.metadata.result_dtype.as_str(), rounded.result_f64.is_none()), ("f32", true));
+
+        let columns = ComputeOptions { result_columns: Some(vec![1]), ..Default::default() };
+        assert!(compute_workload_with_options(input(None), &columns).is_err());
+        assert!(ResultDtype::for_hash_input("fp32", Some("f64le")).is_err());
+        assert!(delta::diff_outputs(&output, &output).is_err());
+    }
+
+    #[test]
+    fn test_int8_rowwise_survives_an_outlier_row() {
+        let (mut a, mut b) = generate_matrices_from_seed(b"rowwise", 16, 256, 256, 16);
+        a.data.iter_mut().chain(&mut b.data).for_each(|x| *x /= 3.0);
+        a.data[3] = 1.0e5;
+        let input = |precision: &str| types::Input::matmul(a.clone(), b.clone(), precision);
+        let (reference, _) = matmul_fp32(&a, &b);
+        let per_tensor = compute_workload(input("int8")).unwrap();
+        let per_row = compute_workload(input("int8_rowwise")).unwrap();
+        assert_eq!(per_tensor.metadata.quantization_scheme.as_deref(), Some("per_tensor"));
+        assert_eq!(per_row.metadata.quantization_scheme.as_deref(), Some("per_row"));
+        assert_eq!(per_row.metadata.kernel.as_deref(), Some("int8_rowwise_16x16"));
+
+        // Truncation moves each operand by less than one step of its own row's (or B's) scale
+        let max_b = b.data.iter().fold(0.0f32, |m, x| m.max(x.abs()));
+        let error = |output: &types::Output, i: usize| -> f32 {
+            (0..16).map(|j| (output.result_matrix.data[i * 16 + j] - reference.data[i * 16 + j]).abs()).sum()
+        };
+        for i in 1..16 {
+            let row = &a.data[i * 256..(i + 1) * 256];
+            let max_a = row.iter().fold(0.0f32, |m, x| m.max(x.abs()));
+            for j in 0..16 {
+                let bound: f32 = (0..256)
+                    .map(|q| row[q].abs() * max_b / 127.0 + b.data[q * 16 + j].abs() * max_a / 127.0 + max_a * max_b / 127.0 / 127.0)
+                    .sum();
+                let p = i * 16 + j;
+                assert!((per_row.result_matrix.data[p] - reference.data[p]).abs() <= bound, "element {}", p);
+            }
+            // One scale for all of A leaves the ordinary rows a code or two per element
+            assert!(error(&per_tensor, i) > 10.0 * error(&per_row, i), "row {}", i);
+        }
+
+        // Every int8_rowwise path quantizes the same way
+        let run = |options: ComputeOptions| compute_workload_with_options(input("int8_rowwise"), &options).unwrap();
+        let generic = run(ComputeOptions { kernel: Some("int8_rowwise_generic".to_string()), ..Default::default() });
+        assert_eq!(generic.result_matrix.data, per_row.result_matrix.data);
+        assert_eq!(run(ComputeOptions { k_chunk: Some(64), ..Default::default() }).result_hash, per_row.result_hash);
+        let subset = run(ComputeOptions { result_columns: Some(vec![9]), ..Default::default() });
+        assert!((0..16).all(|i| subset.result_matrix.data[i] == per_row.result_matrix.data[i * 16 + 9]));
+        assert!(verify_correctness(&a, &b, "int8_rowwise", &per_row.result_hash).unwrap());
+        assert!(!verify_correctness(&a, &b, "int8", &per_row.result_hash).unwrap());
+
+        // Without an outlier the scales differ only slightly, and zero rows stay zero
+        let mut zero_rows = a.clone();
+        zero_rows.data[..256].fill(0.0);
+        assert!(matmul_int8(&zero_rows, &b, Int8Scaling::PerRow).data[..16].iter().all(|&x| x == 0.0));
+        let err = compute_workload_with_options(input("int8_rowwise"), &ComputeOptions { accumulator: Accumulator::I16Saturating, ..Default::default() }).unwrap_err();
+        assert!(err.to_string().contains("int8 and u8i8"), "{}", err);
+    }
+
+    #[test]
+    fn test_int8_asym_beats_symmetric_on_positive_a() {
+        // B's i8 values with 127 as the largest magnitude quantize exactly, leaving only A's error
+        let (mut a, mut b) = generate_matrices_from_seed(b"asym", 16, 300, 300, 16);
+        a.data.iter_mut().for_each(|x| *x = x.abs().sqrt());
+        b.data.iter_mut().for_each(|x| *x = x.clamp(-127.0, 127.0));
+        b.data[0] = 127.0;
+        let input = |a: &FlatMatrix, precision: &str| types::Input::matmul(a.clone(), b.clone(), precision);
+        let error = |a: &FlatMatrix, output: &types::Output| -> f32 {
+            let (reference, _) = matmul_fp32(a, &b);
+            output.result_matrix.data.iter().zip(&reference.data).map(|(x, r)| (x - r).abs()).sum()
+        };
+
+        let symmetric = compute_workload(input(&a, "int8")).unwrap();
+        let asym = compute_workload(input(&a, "int8_asym")).unwrap();
+        assert_eq!(asym.metadata.kernel.as_deref(), Some("int8_asym_16x16"));
+        assert_eq!(asym.metadata.quantization_scheme.as_deref(), Some("asymmetric"));
+        assert_eq!(AsymmetricU8::of(a.data.iter().copied()).zero_point, 0);
+        // Twice the codes, and rounding instead of truncation
+        assert!(3.0 * error(&a, &asym) < error(&a, &symmetric), "{} vs {}", error(&a, &asym), error(&a, &symmetric));
+
+        // Mostly positive: the zero point is inside the range and the column-sum correction applies
+        let shifted = FlatMatrix { data: a.data.iter().map(|x| x - 4.0).collect(), ..a.clone() };
+        let quant = AsymmetricU8::of(shifted.data.iter().copied());
+        assert!(quant.zero_point > 0 && quant.zero_point < 255 && quant.quantize(0.0) as i32 == quant.zero_point);
+        let asym = compute_workload(input(&shifted, "int8_asym")).unwrap();
+        let symmetric = compute_workload(input(&shifted, "int8")).unwrap();
+        assert!(error(&shifted, &asym) < error(&shifted, &symmetric));
+
+        let run = |options: ComputeOptions| compute_workload_with_options(input(&shifted, "int8_asym"), &options).unwrap();
+        let generic = run(ComputeOptions { kernel: Some("int8_asym_generic".to_string()), ..Default::default() });
+        assert_eq!(generic.result_hash, asym.result_hash);
+        let subset = run(ComputeOptions { result_columns: Some(vec![2, 13]), ..Default::default() });
+        assert!((0..16).all(|i| subset.result_matrix.data[i * 2 + 1] == asym.result_matrix.data[i * 16 + 13]));
+        assert!(verify_correctness(&shifted, &b, "int8_asym", &asym.result_hash).unwrap());
+
+        // The cached column sums belong to the packed B they were taken from
+        let (b_t, _) = pack_b_int8_transposed(&b);
+        let (packed, _) = get_bt_i8_cache(&b, CacheContext::global(), None);
+        assert_eq!(packed.col_sums, column_sums_i8(b_t.as_slice(), 300));
+        let zeros = FlatMatrix { data: vec![0.0; 16 * 300], rows: 16, cols: 300 };
+        assert!(matmul_int8_asym(&zeros, &b).data.iter().all(|&x| x == 0.0));
+    }
+
+    #[test]
+    fn test_int8_asym_sums_past_the_i32_bound() {
+        // Codes 255 × 127 in every step: k·32385 passes i32::MAX from k = 66312
+        let k = U8I8_SAFE_K + 1000;
+        let a = FlatMatrix::from_fn(16, k, |_, p| if p == 0 { 0.0 } else { 1.0 });
+        let b = FlatMatrix::from_fn(k, 16, |_, _| 1.0);
+        assert!(255 * 127 * (k - 1) > i32::MAX as usize);
+        let generic = matmul_int8_asym(&a, &b);
+        let (packed, _, _, _) = matmul_int8_asym_16x16(&a, BOperand::RowMajor(&b, CacheContext::global()), None, None, None, &SystemClock);
+        assert_eq!(generic.data, packed.data);
+        for &x in &generic.data {
+            assert!((x - (k - 1) as f32).abs() <= 1e-3 * k as f32, "{} vs {}", x, k - 1);
+        }
+    }
+
+    #[test]
+    fn test_int8_scales_never_clip() {
+        let (mut a, _) = generate_matrices_from_seed(b"clip", 16, 64, 64, 16);
+        a.data[5] = -3.0e6;
+        a.data[70] = 1.0e-30;
+        for matrix in [a.clone(), FlatMatrix { data: a.data.iter().map(|x| x * 1.0e-30).collect(), ..a.clone() }] {
+            for scaling in [Int8Scaling::PerTensor, Int8Scaling::PerRow] {
+                let scales = scaling.a_scales(&matrix);
+                for (idx, &x) in matrix.data.iter().enumerate() {
+                    let scaled = x * scales[idx / matrix.cols];
+                    assert!(scaled.abs() < 128.0 && (scaled as i8 as f32 - scaled).abs() < 1.0, "{} at {}", x, idx);
+                }
+            }
+        }
+
+        // Below ~3.7e-37, 127 / max is infinite; the capped scale keeps the values
+        let (a, b) = generate_matrices_from_seed(b"clip-tiny", 16, 64, 64, 16);
+        let max_a = a.data.iter().fold(0f32, |max, x| max.max(x.abs()));
+        let tiny = FlatMatrix { data: a.data.iter().map(|x| x / max_a * 1.0e-37).collect(), ..a.clone() };
+        let huge = FlatMatrix { data: b.data.iter().map(|x| x * 1.0e30).collect(), ..b.clone() };
+        assert_eq!(int8_scale(tiny.data.iter().copied()), f32::MAX);
+        for scaling in [Int8Scaling::PerTensor, Int8Scaling::PerRow] {
+            let scales = scaling.a_scales(&tiny);
+            assert!(tiny.data.iter().enumerate().all(|(idx, &x)| (x * scales[idx / tiny.cols]).abs() < 128.0));
+            let expected = matmul_fp32(&tiny, &huge).0;
+            let scale = expected.data.iter().fold(0f32, |max, x| max.max(x.abs()));
+            let result = matmul_int8(&tiny, &huge, scaling);
+            assert!(scale > 0.0 && result.data.iter().any(|&x| x != 0.0), "{:?}", scaling);
+            for (x, y) in result.data.iter().zip(&expected.data) {
+                assert!((x - y).abs() <= 0.1 * scale, "{:?}: {} vs {}", scaling, x, y);
+            }
+        }
+    }
+
+    #[test]
+    fn test_compute_workload_integration() {
+        // Create input JSON and deserialize to test the full flow
+        let input_json = r#"{
+            "matrix_a": [[1.0, 2.0], [3.0, 4.0]],
+            "matrix_b": [[5.0, 6.0], [7.0, 8.0]],
+            "precision": "fp32",
+            "workload_type": "matmul"
+        }"#;
         
-        let result = matmul_fp16(&a, &b);
+        let input: types::Input = serde_json::from_str(input_json).unwrap();
+        let output = compute_workload(input).unwrap();
         
-        // FP16 should give approximately correct results (may have small precision differences)
-        assert!((result.data[0 * result.cols + 0] - 19.0).abs() < 0.1);
-        assert!((result.data[0 * result.cols + 1] - 22.0).abs() < 0.1);
-        assert!((result.data[1 * result.cols + 0] - 43.0).abs() < 0.1);
-        assert!((result.data[1 * result.cols + 1] - 50.0).abs() < 0.1);
+        // Check result correctness (using flat indexing)
+        assert_eq!(output.result_matrix.data[0 * output.result_matrix.cols + 0], 19.0);
+        assert_eq!(output.result_matrix.data[0 * output.result_matrix.cols + 1], 22.0);
+        assert_eq!(output.result_matrix.data[1 * output.result_matrix.cols + 0], 43.0);
+        assert_eq!(output.result_matrix.data[1 * output.result_matrix.cols + 1], 50.0);
+        
+        // Check hash is present
+        assert!(!output.result_hash.is_empty());
+        
+        // Check metrics are reasonable
+        assert!(output.metrics.latency_ms >= 0.0);
+        assert!(output.metrics.ops_per_second > 0.0);
+        
+        // Verify hash matches recomputed hash
+        let input2_json = r#"{
+            "matrix_a": [[1.0, 2.0], [3.0, 4.0]],
+            "matrix_b": [[5.0, 6.0], [7.0, 8.0]],
+            "precision": "fp32",
+            "workload_type": "matmul"
+        }"#;
+        let input2: types::Input = serde_json::from_str(input2_json).unwrap();
+        
+        assert!(verify_correctness(
+            &input2.matrix_a,
+            &input2.matrix_b,
+            "fp32",
+            &output.result_hash
+        ).unwrap());
     }
     
     #[test]
-    fn test_int8_correctness() {
+    fn test_matrix_dimension_validation() {
+        let input_json = r#"{
+            "matrix_a": [[1.0, 2.0], [3.0, 4.0]],
+            "matrix_b": [[5.0, 6.0]],
+            "precision": "fp32",
+            "workload_type": "matmul"
+        }"#;
+        
+        let input: types::Input = serde_json::from_str(input_json).unwrap();
+        let result = compute_workload(input);
+        assert!(result.is_err());
+        assert!(result.unwrap_err().to_string().contains("incompatible"));
+    }
+    
+    #[test]
+    fn test_result_dtype_descriptions() {
+        for precision in precisions().into_iter().filter(|&p| p != "fp64") {
+            let dtype = ResultDtype::for_precision(precision).unwrap();
+            assert_eq!(dtype.as_str(), "f32");
+            assert_eq!(dtype.hash_input_description(), "f32le row-major");
+        }
+        assert_eq!(ResultDtype::for_precision("fp64"), Some(ResultDtype::F64));
+        assert_eq!(ResultDtype::F64.hash_input_description(), "f64le row-major");
+        assert_eq!(ResultDtype::I32.hash_input_description(), "i32le row-major");
+        assert!(ResultDtype::for_precision("fp8").is_none());
+    }
+    
+    #[test]
+    fn test_reverify_from_stored_metadata() {
         let a = to_flat_matrix(vec![
-            vec![1.0, 2.0],
-            vec![3.0, 4.0],
+            vec![1.0, 2.0, 3.0],
+            vec![4.0, 5.0, 6.0],
         ]);
         let b = to_flat_matrix(vec![
-            vec![5.0, 6.0],
             vec![7.0, 8.0],
+            vec![9.0, 10.0],
+            vec![11.0, 12.0],
         ]);
         
-        let result = matmul_int8(&a, &b);
+        for precision in precisions() {
+            let input = types::Input::matmul(a.clone(), b.clone(), precision);
+            let output = compute_workload(input).unwrap();
+            
+            // Round-trip the metadata through JSON as a stored output would
+            let stored = serde_json::to_string(&output.metadata).unwrap();
+            let metadata: types::OutputMetadata = serde_json::from_str(&stored).unwrap();
+            let dtype = ResultDtype::for_precision(precision).unwrap();
+            assert_eq!(metadata.hash_input_description, dtype.hash_input_description());
+            assert!(verify_with_metadata(&a, &b, &metadata, &output.result_hash).unwrap());
+        }
+    }
+    
+    #[test]
+    fn test_result_columns_match_full_computation() {
+        let columns = vec![3, 7, 12];
+        // Seed shape exercises the 16x16 kernels; 16x40x20 exercises the generic ones
+        for (rows_a, k, cols_b) in [(16, 96, 16), (16, 40, 20)] {
+            let (a, b) = generate_matrices_from_seed(b"columns", rows_a, k, k, cols_b);
+            // fp64 rejects result_columns, see test_fp64_hashes_the_f64_result
+            for precision in precisions().into_iter().filter(|&p| p != "fp64") {
+                let input = |a: &FlatMatrix, b: &FlatMatrix| types::Input::matmul(a.clone(), b.clone(), precision);
+                let full = compute_workload(input(&a, &b)).unwrap();
+                let options = ComputeOptions {
+                    result_columns: Some(columns.clone()),
+                    ..Default::default()
+                };
+                let partial = compute_workload_with_options(input(&a, &b), &options).unwrap();
+                
+                let expected = select_columns(&full.result_matrix, &columns);
+                assert_eq!(partial.result_matrix.data, expected.data, "{} {}x{}", precision, rows_a, cols_b);
+                assert_eq!(partial.metadata.result_shape, (rows_a, 3));
+                assert_eq!(partial.metadata.result_columns.as_deref(), Some(&columns[..]));
+                assert_eq!(
+                    partial.result_hash,
+                    partial_hash(&full.result_matrix, &columns, ResultDtype::F32)
+                );
+            }
+        }
+    }
+    
+    #[test]
+    fn test_result_columns_out_of_range() {
+        let (a, b) = generate_matrices_from_seed(b"columns", 16, 32, 32, 16);
+        let input = types::Input::matmul(a, b, "u8i8");
+        let options = ComputeOptions {
+            result_columns: Some(vec![2, 16]),
+            ..Default::default()
+        };
+        let err = compute_workload_with_options(input, &options).unwrap_err();
+        assert!(err.to_string().contains("out of range"));
+    }
+    
+    #[test]
+    fn test_seed_generation_matches_single_xof_read() {
+        let (a, b) = generate_matrices_from_seed(b"chunked", 16, 700, 700, 16);
+        let mut bytes = vec![0u8; 2 * 16 * 700];
+        let mut hasher = blake3::Hasher::new();
+        hasher.update(b"chunked");
+        hasher.finalize_xof().fill(&mut bytes);
+        assert_eq!(a.data[..], bytes[..11200].iter().map(|&x| x as f32).collect::<Vec<_>>()[..]);
+        assert_eq!(b.data[11199], (bytes[22399].wrapping_sub(128)) as i8 as f32);
+
+        // Reusing buffers with a smaller shape must not leave stale data behind
+        let (mut a2, mut b2) = (a.clone(), b.clone());
+        generate_matrices_from_seed_into(b"other", 16, 8, 8, 16, &mut a2, &mut b2);
+        assert_eq!(a2.data.len(), 128);
+        assert_eq!(generate_matrices_from_seed(b"other", 16, 8, 8, 16).1.data, b2.data);
+    }
+    
+    #[test]
+    fn test_transposed_a_matches_explicit_transpose() {
+        for (m, k, n) in [(16, 50240, 16), (16, 37, 16), (5, 300, 7), (33, 64, 16)] {
+            let (a, b) = generate_matrices_from_seed(format!("a_layout {}", k).as_bytes(), m, k, k, n);
+            let a_t = FlatMatrix {
+                data: (0..k * m).map(|idx| a.data[(idx % m) * k + idx / m]).collect(),
+                rows: k,
+                cols: m,
+            };
+            assert_eq!(transpose(&a_t).data, a.data);
+            assert_eq!(transpose(&a).data, a_t.data);
+            for precision in ["fp32", "int8", "u8i8"] {
+                let input = |matrix_a: &FlatMatrix, a_layout: Option<&str>| types::Input {
+                    matrix_a: matrix_a.clone(),
+                    matrix_b: b.clone(),
+                    precision: precision.to_string(),
+                    a_layout: a_layout.map(str::to_string),
+                    ..Default::default()
+                };
+                let explicit = compute_workload(input(&a, None)).unwrap();
+                let fused = compute_workload(input(&a_t, Some("transposed"))).unwrap();
+                assert_eq!(fused.result_hash, explicit.result_hash, "{} {}x{}x{}", precision, m, k, n);
+                assert_eq!(fused.metadata.matrix_a_shape, (m, k));
+                assert_eq!(fused.metadata.a_layout.as_deref(), Some("transposed"));
+                assert_eq!(explicit.metadata.a_layout, None);
+                assert!(verify_with_metadata(&a_t, &b, &fused.metadata, &fused.result_hash).unwrap());
+            }
+        }
+
+        // Aᵀ's rows, not its columns, must match B's rows
+        let (a, b) = generate_matrices_from_seed(b"a_layout", 4, 6, 6, 3);
+        let input = types::Input {
+            matrix_a: a,
+            matrix_b: b,
+            precision: "fp32".to_string(),
+            a_layout: Some("transposed".to_string()),
+            ..Default::default()
+        };
+        let err = compute_workload(input.clone()).unwrap_err();
+        assert_eq!(err.to_string(), "Matrix dimensions incompatible: matrix_a.rows (=4) must equal matrix_b.rows (=6)");
+        let err = compute_workload(types::Input { a_layout: Some("column".to_string()), ..input }).unwrap_err();
+        assert!(err.to_string().contains("Unknown a_layout"), "{}", err);
+    }
+
+    #[test]
+    fn test_transposed_b_matches_row_major() {
+        let mut precisions = vec!["fp32", "fp64", "bf16", "int8", "int8_rowwise", "int8_asym", "int4", "u8i8"];
+        if cfg!(feature = "fp16") {
+            precisions.push("fp16");
+        }
+        // 16x16 runs the kernels that read Bᵀ directly, 32x16 a shape specialization
+        for (m, k, n) in [(16, 300, 16), (16, 37, 16), (5, 300, 7), (32, 64, 16)] {
+            let (a, b) = generate_matrices_from_seed(format!("b_layout {}", k).as_bytes(), m, k, k, n);
+            let b_t = transpose(&b);
+            for &precision in &precisions {
+                let input = |matrix_b: &FlatMatrix, b_layout: Option<&str>| types::Input {
+                    matrix_a: a.clone(),
+                    matrix_b: matrix_b.clone(),
+                    precision: precision.to_string(),
+                    b_layout: b_layout.map(str::to_string),
+                    ..Default::default()
+                };
+                let explicit = compute_workload(input(&b, None)).unwrap();
+                let fused = compute_workload(input(&b_t, Some("transposed"))).unwrap();
+                let label = format!("{} {}x{}x{}", precision, m, k, n);
+                assert_eq!(fused.result_hash, explicit.result_hash, "{}", label);
+                assert_eq!(fused.metadata.kernel, explicit.metadata.kernel, "{}", label);
+                assert_eq!((fused.metadata.matrix_b_shape, fused.metadata.b_layout.as_deref()), ((k, n), Some("transposed")));
+                assert!(verify_with_metadata(&a, &b_t, &fused.metadata, &fused.result_hash).unwrap(), "{}", label);
+                // Bᵀ is already the layout the packed-B caches hold, so they are not consulted
+                if explicit.metrics.pack_cache_hit.is_some() {
+                    assert_eq!(fused.metrics.pack_cache_hit, None, "{}", label);
+                }
+            }
+        }
+
+        // Stochastic fp16 rounding draws the same value for each element of B either way
+        #[cfg(feature = "fp16")]
+        {
+            let (a, b) = generate_matrices_from_seed(b"b_layout stochastic", 16, 40, 40, 16);
+            let options = ComputeOptions { fp16_rounding: Fp16Rounding::Stochastic { seed: 7 }, ..Default::default() };
+            let run = |matrix_b: FlatMatrix, b_layout: Option<&str>| {
+                let input = types::Input { matrix_a: a.clone(), matrix_b, precision: "fp16".to_string(), b_layout: b_layout.map(str::to_string), ..Default::default() };
+                compute_workload_with_options(input, &options).unwrap().result_hash
+            };
+            assert_eq!(run(transpose(&b), Some("transposed")), run(b, None));
+        }
+
+        // With both transposed, Aᵀ (k×m) and Bᵀ (n×k) give AB
+        let (a, b) = generate_matrices_from_seed(b"b_layout both", 6, 9, 9, 4);
+        let both = types::Input {
+            matrix_a: transpose(&a),
+            matrix_b: transpose(&b),
+            precision: "fp32".to_string(),
+            a_layout: Some("transposed".to_string()),
+            b_layout: Some("transposed".to_string()),
+            ..Default::default()
+        };
+        let explicit = types::Input { matrix_a: a, matrix_b: b, a_layout: None, b_layout: None, ..both.clone() };
+        assert_eq!(compute_workload(both).unwrap().result_hash, compute_workload(explicit.clone()).unwrap().result_hash);
+        let err = compute_workload(types::Input { b_layout: Some("column".to_string()), ..explicit }).unwrap_err();
+        assert!(err.to_string().contains("Unknown b_layout"), "{}", err);
+    }
+
+    #[test]
+    fn test_matmul_fp32_into_scales_and_accumulates() {
+        let (a, b) = generate_matrices_from_seed(b"gemm", 5, 70, 70, 3);
+        let (c0, _) = generate_matrices_from_seed(b"gemm c", 5, 3, 3, 1);
+        let ab = matmul_fp32_blocked(&a, &b, &SystemClock).0;
+
+        let mut c = c0.clone();
+        matmul_fp32_into(&a, &b, &mut c, -0.5, 2.0).unwrap();
+        for ((x, p), c) in c.data.iter().zip(&ab.data).zip(&c0.data) {
+            assert!((x - (-0.5 * p + 2.0 * c)).abs() <= 1e-4 * (1.0 + p.abs()), "{} vs {}", x, -0.5 * p + 2.0 * c);
+        }
+
+        // alpha 1, beta 0 is the blocked kernel bit for bit, whatever C held
+        let mut c = FlatMatrix { data: vec![f32::NAN; 15], rows: 5, cols: 3 };
+        matmul_fp32_into(&a, &b, &mut c, 1.0, 0.0).unwrap();
+        assert_eq!(c.data, ab.data);
+
+        let mut wrong = FlatMatrix { data: vec![0.0; 15], rows: 3, cols: 5 };
+        let err = matmul_fp32_into(&a, &b, &mut wrong, 1.0, 1.0).unwrap_err();
+        assert_eq!(err.to_string(), "C is 3x5 but A·B is 5x3");
+    }
+
+    #[test]
+    fn test_compute_workload_gemm_inputs() {
+        let (a, b) = generate_matrices_from_seed(b"gemm workload", 16, 40, 40, 16);
+        let (c, _) = generate_matrices_from_seed(b"gemm workload c", 16, 16, 16, 1);
+        let input = |precision: &str, alpha, beta, matrix_c: Option<FlatMatrix>| types::Input {
+            matrix_a: a.clone(),
+            matrix_b: b.clone(),
+            precision: precision.to_string(),
+            alpha,
+            beta,
+            matrix_c,
+            ..Default::default()
+        };
+        let plain = compute_workload(input("fp32", None, None, None)).unwrap();
+        let gemm = compute_workload(input("fp32", Some(3.0), Some(-1.0), Some(c.clone()))).unwrap();
+        assert_eq!((gemm.metadata.alpha, gemm.metadata.beta), (Some(3.0), Some(-1.0)));
+        assert_eq!(gemm.metadata.kernel, plain.metadata.kernel);
+        let expected: Vec<f32> = plain.result_matrix.data.iter().zip(&c.data).map(|(p, c)| 3.0 * p - c).collect();
+        assert_eq!(gemm.result_matrix.data, expected);
+        assert_eq!(gemm.result_hash, compute_hash(&FlatMatrix { data: expected, rows: 16, cols: 16 }));
+        let err = verify_with_metadata(&a, &b, &gemm.metadata, &gemm.result_hash).unwrap_err();
+        assert!(err.to_string().contains("cannot be verified"), "{}", err);
+
+        // The defaults, spelled out, are the plain product
+        let identity = compute_workload(input("fp32", Some(1.0), Some(0.0), Some(c.clone()))).unwrap();
+        assert_eq!((identity.result_hash, identity.metadata.alpha, identity.metadata.beta), (plain.result_hash, None, None));
+
+        let err = |input| compute_workload(input).unwrap_err().to_string();
+        assert_eq!(err(input("int8", Some(2.0), None, None)), "alpha, beta and matrix_c apply to fp32, not int8");
+        assert_eq!(err(input("fp32", None, Some(1.0), None)), "beta 1 scales matrix_c, which is missing");
+        let short = FlatMatrix { data: vec![0.0; 16 * 15], rows: 16, cols: 15 };
+        assert_eq!(err(input("fp32", None, Some(1.0), Some(short))), "matrix_c is 16x15 but A·B is 16x16");
+        let columns = ComputeOptions { result_columns: Some(vec![0]), ..Default::default() };
+        assert!(compute_workload_with_options(input("fp32", Some(2.0), None, None), &columns).is_err());
+    }
+
+    #[test]
+    fn test_seed_prefix_matches_naive_derivation() {
+        let prefix = vec![0x5au8; 1500]; // longer than one Blake3 chunk
+        let mut search = SeedPrefix::new(&prefix, (16, 300), (300, 16));
+        let (mut a, mut b) = (FlatMatrix::default(), FlatMatrix::default());
+        for nonce in [0u64, 1, 2, 0xdead_beef, u64::MAX] {
+            let nonce = nonce.to_le_bytes();
+            search.derive_into(&nonce, &mut a, &mut b);
+            let (a_ref, b_ref) = generate_matrices_from_seed(&[&prefix[..], &nonce].concat(), 16, 300, 300, 16);
+            assert_eq!(a.data, a_ref.data);
+            assert_eq!(b.data, b_ref.data);
+            assert_eq!((a.rows, a.cols, b.rows, b.cols), (16, 300, 300, 16));
+        }
+        // An empty nonce is the prefix on its own
+        search.derive_into(&[], &mut a, &mut b);
+        assert_eq!(a.data, generate_matrices_from_seed(&prefix, 16, 300, 300, 16).0.data);
+
+        // Per-nonce cost against the naive construction; informational only
+        let (nonces, clock) = (200u64, SystemClock);
+        let start = clock.now();
+        for nonce in 0..nonces {
+            search.derive_into(&nonce.to_le_bytes(), &mut a, &mut b);
+        }
+        let reused = clock.elapsed_since(start);
+        let start = clock.now();
+        for nonce in 0..nonces {
+            let seed = [&prefix[..], &nonce.to_le_bytes()].concat();
+            std::hint::black_box(generate_matrices_from_seed(&seed, 16, 300, 300, 16));
+        }
+        let naive = clock.elapsed_since(start);
+        eprintln!("seed derivation per nonce: {:?} reused, {:?} naive", reused / nonces as u32, naive / nonces as u32);
+    }
+    
+    #[test]
+    fn test_seed_layout_recomposes_generation() {
+        for (seed, a_shape, b_shape) in [
+            (&b"layout"[..], (16, 50), (50, 16)),
+            (&b""[..], (3, 5000), (5000, 2)),
+            (&b"\x00\xff"[..], (1, 1), (7, 9)),
+        ] {
+            let layout = seed_layout(a_shape, b_shape);
+            assert_eq!((layout.a_offset, layout.b_offset), (0, a_shape.0 * a_shape.1));
+            let bytes = generate_seed_bytes(seed, layout.total_len());
+            let (a, b) = generate_matrices_from_seed(seed, a_shape.0, a_shape.1, b_shape.0, b_shape.1);
+            let (mut a2, mut b2) = (FlatMatrix { data: vec![1.0; 3], rows: 0, cols: 0 }, FlatMatrix { data: Vec::new(), rows: 0, cols: 0 });
+            generate_matrices_from_seed_into(seed, a_shape.0, a_shape.1, b_shape.0, b_shape.1, &mut a2, &mut b2);
+            for (m, m2, offset, len, interpretation) in [
+                (&a, &a2, layout.a_offset, layout.a_len, layout.a_interpretation),
+                (&b, &b2, layout.b_offset, layout.b_len, layout.b_interpretation),
+            ] {
+                let expected: Vec<f32> = bytes[offset..offset + len].iter().map(|&x| interpretation.to_f32(x)).collect();
+                let to_bits = |d: &[f32]| d.iter().map(|v| v.to_bits()).collect::<Vec<_>>();
+                assert_eq!(to_bits(&m.data), to_bits(&expected));
+                assert_eq!(to_bits(&m2.data), to_bits(&expected));
+                assert_eq!((m.rows, m.cols), (m2.rows, m2.cols));
+            }
+        }
+        assert_eq!(ByteInterpretation::OffsetI8.to_f32(0x80), 0.0);
+        assert_eq!(ByteInterpretation::OffsetI8.to_f32(0x00), -128.0);
+        assert_eq!(ByteInterpretation::OffsetI8.to_f32(0x7f), -1.0);
+    }
+
+    #[test]
+    fn test_prepared_operands_match_compute() {
+        let (a, b) = generate_matrices_from_seed(b"prepared", 16, 80, 80, 16);
+        for precision in ["u8i8", "int8"] {
+            let prepared = pack_operands(&a, &b, precision).unwrap();
+            let input = types::Input::matmul(a.clone(), b.clone(), precision);
+            let expected = compute_workload(input).unwrap();
+            assert_eq!(compute_hash_as(&prepared.run(), prepared.result_dtype()), expected.result_hash, "{}", precision);
+        }
+    }
+    
+    #[test]
+    fn test_build_metadata_auto_populated() {
+        let make_input = |metadata| types::Input {
+            matrix_a: FlatMatrix { data: vec![1.0, 2.0], rows: 1, cols: 2 },
+            matrix_b: FlatMatrix { data: vec![3.0, 4.0], rows: 2, cols: 1 },
+            precision: "fp32".to_string(),
+            metadata,
+            ..Default::default()
+        };
+
+        let auto = compute_workload(make_input(None)).unwrap().metadata;
+        let flags = auto.compiler_flags.unwrap();
+        assert!(flags.contains("opt-level=") && flags.contains("target-cpu="));
+        assert!(auto.libraries.unwrap().iter().any(|l| l.starts_with("blas:")));
+
+        let explicit = types::InputMetadata {
+            compiler_flags: Some("-C target-cpu=znver4".to_string()),
+            libraries: Some(vec!["mkl".to_string()]),
+            cache_enabled: None,
+        };
+        let out = compute_workload(make_input(Some(explicit))).unwrap().metadata;
+        assert_eq!(out.compiler_flags.as_deref(), Some("-C target-cpu=znver4"));
+        assert_eq!(out.libraries, Some(vec!["mkl".to_string()]));
+    }
+    
+    /// Kernel that "takes" 250ms of mock time
+    struct SlowMockKernel(MockClock);
+    
+    impl MatmulKernel for SlowMockKernel {
+        fn name(&self) -> &str {
+            "slow_mock"
+        }
+        fn precisions(&self) -> &[&str] {
+            &["fp32"]
+        }
+        fn supports_shape(&self, a_shape: (usize, usize), b_shape: (usize, usize)) -> bool {
+            a_shape == (5, 11) && b_shape == (11, 3)
+        }
+        fn run(&self, a: &FlatMatrix, b: &FlatMatrix, options: &ComputeOptions) -> Result<KernelResult, SolverError> {
+            let (res, elapsed) = clock::time(options.clock(), || {
+                self.0.advance(std::time::Duration::from_millis(250));
+                matmul_fp32_blocked(a, b, &SystemClock).0
+            });
+            Ok(KernelResult::new(res, elapsed))
+        }
+    }
+    
+    #[test]
+    fn test_mock_clock_deadline_and_exact_metrics() {
+        let mock = MockClock::new();
+        // Another test may have registered it already in this process
+        let _ = register_kernel(Box::new(SlowMockKernel(mock.clone())));
+        let (a, b) = generate_matrices_from_seed(b"clock", 5, 11, 11, 3);
+        let input = || types::Input::matmul(a.clone(), b.clone(), "fp32");
+        let options = |deadline_ms| ComputeOptions {
+            kernel: Some("slow_mock".to_string()),
+            clock: Some(std::sync::Arc::new(mock.clone())),
+            deadline: Some(std::time::Duration::from_millis(deadline_ms)),
+            ..Default::default()
+        };
+        
+        let err = compute_workload_with_options(input(), &options(100)).unwrap_err();
+        assert!(matches!(err, SolverError::DeadlineExceeded(_)), "{}", err);
         
-        // INT8 should give approximately correct results (quantization may cause differences)
-        assert!((result.data[0 * result.cols + 0] - 19.0).abs() < 1.0);
-        assert!((result.data[0 * result.cols + 1] - 22.0).abs() < 1.0);
-        assert!((result.data[1 * result.cols + 0] - 43.0).abs() < 1.0);
-        assert!((result.data[1 * result.cols + 1] - 50.0).abs() < 1.0);
+        let out = compute_workload_with_options(input(), &options(1000)).unwrap();
+        assert_eq!(out.metrics.kernel_time_ms, Some(250.0));
+        assert_eq!(out.metrics.latency_ms, 250.0);
+        assert_eq!(out.metrics.ops_per_second, (5 * 11 * 3) as f64 / 0.25);
+        
+        // Built-in kernels read the same clock: a frozen clock measures nothing
+        let frozen = ComputeOptions {
+            clock: Some(std::sync::Arc::new(MockClock::new())),
+            ..Default::default()
+        };
+        let (a16, b16) = generate_matrices_from_seed(b"clock", 16, 64, 64, 16);
+        let input16 = types::Input::matmul(a16, b16, "u8i8");
+        assert_eq!(compute_workload_with_options(input16, &frozen).unwrap().metrics.kernel_time_ms, Some(0.0));
     }
     
+    /// Golden hash of the strict fp32 16x16 accumulation order. If this fails, codegen
+    /// or the kernel changed the numerics of the canonical form; do not just update it.
     #[test]
-    fn test_compute_workload_integration() {
-        // Create input JSON and deserialize to test the full flow
-        let input_json = r#"{
-            "matrix_a": [[1.0, 2.0], [3.0, 4.0]],
-            "matrix_b": [[5.0, 6.0], [7.0, 8.0]],
-            "precision": "fp32",
-            "workload_type": "matmul"
-        }"#;
+    fn test_fp32_strict_accumulation_golden_hash() {
+        // k = 258 covers both the 4-step body and the single-step tail
+        let (matrix_a, matrix_b) = generate_matrices_from_seed(b"fp32-golden", 16, 258, 258, 16);
+        let input = types::Input::matmul(matrix_a, matrix_b, "fp32");
+        let strict = compute_workload(input.clone()).unwrap();
+        assert_eq!(strict.metadata.kernel.as_deref(), Some("fp32_16x16"));
+        assert_eq!(strict.metadata.fp32_accumulation.as_deref(), Some("strict"));
+        assert_eq!(strict.result_hash, "101cf642a6dd2554ba840bf767965e09a988f7c5ccb697454cb84fba3698bfae");
+
+        let options = ComputeOptions { fp32_accumulation: Fp32Accumulation::Fast, ..Default::default() };
+        let fast = compute_workload_with_options(input, &options).unwrap();
+        assert_eq!(fast.metadata.fp32_accumulation.as_deref(), Some("fast"));
+        if !cfg!(target_feature = "fma") {
+            assert_eq!(fast.result_hash, strict.result_hash);
+        }
+        assert!(Fp32Accumulation::parse("fused").is_err());
+    }
+
+    #[test]
+    fn test_fp32_summation_beats_naive_on_long_reductions() {
+        // Alternating huge and tiny products: naive sums drop the tiny ones and round the
+        // huge ones against an ever larger total
+        let k = 8192;
+        let (mut a, b) = generate_matrices_from_seed(b"fp32-summation", 16, k, k, 16);
+        for (p, x) in a.data.iter_mut().enumerate() {
+            *x = if p % 2 == 0 { 1.0e4 * (1.5 + *x) } else { 1.0e-3 * *x };
+        }
+        let error = |c: &FlatMatrix| {
+            (0..16 * 16)
+                .map(|ij| {
+                    let (i, j) = (ij / 16, ij % 16);
+                    let exact: f64 = (0..k).map(|p| a.data[i * k + p] as f64 * b.data[p * 16 + j] as f64).sum();
+                    (c.data[ij] as f64 - exact).abs()
+                })
+                .sum::<f64>()
+        };
+        let naive = error(&matmul_fp32_blocked(&a, &b, &SystemClock).0);
+        for summation in [Fp32Summation::Kahan, Fp32Summation::Pairwise] {
+            let summed = error(&matmul_fp32_summed(&a, &b, summation, &SystemClock).0);
+            assert!(summed * 4.0 < naive, "{}: {} vs naive {}", summation.as_str(), summed, naive);
+        }
+    }
+
+    #[test]
+    fn test_fp32_summation_is_kernel_independent() {
+        for ((m, k, n), kernels) in [((16, 300, 16), &["fp32_16x16", "fp32_blocked"][..]), ((1, 300, 24), &["fp32_gemv", "fp32_blocked"][..])] {
+            let (matrix_a, matrix_b) = generate_matrices_from_seed(b"summation-kernels", m, k, k, n);
+            let input = types::Input::matmul(matrix_a, matrix_b, "fp32");
+            for summation in [Fp32Summation::Kahan, Fp32Summation::Pairwise] {
+                let hashes: Vec<_> = kernels
+                    .iter()
+                    .map(|kernel| {
+                        let options = ComputeOptions { fp32_summation: summation, kernel: Some(kernel.to_string()), ..Default::default() };
+                        let output = compute_workload_with_options(input.clone(), &options).unwrap();
+                        assert_eq!(output.metadata.fp32_summation.as_deref(), Some(summation.as_str()));
+                        assert!(verify_with_metadata(&input.matrix_a, &input.matrix_b, &output.metadata, &output.result_hash).unwrap());
+                        output.result_hash
+                    })
+                    .collect();
+                assert!(hashes.windows(2).all(|w| w[0] == w[1]), "{} {:?}", summation.as_str(), kernels);
+                // Automatic selection lands on a kernel that implements it
+                let options = ComputeOptions { fp32_summation: summation, ..Default::default() };
+                assert_eq!(compute_workload_with_options(input.clone(), &options).unwrap().result_hash, hashes[0]);
+            }
+            assert_eq!(compute_workload(input.clone()).unwrap().metadata.fp32_summation, None);
+        }
+
+        let (matrix_a, matrix_b) = generate_matrices_from_seed(b"summation-kernels", 4, 8, 8, 4);
+        let input = types::Input::matmul(matrix_a, matrix_b, "bf16");
+        let options = ComputeOptions { fp32_summation: Fp32Summation::Kahan, ..Default::default() };
+        let err = compute_workload_with_options(input.clone(), &options).unwrap_err();
+        assert!(err.to_string().contains("fp32 summation kahan applies to fp32, not bf16"), "{}", err);
+        let options = ComputeOptions { fp32_summation: Fp32Summation::Pairwise, fp32_accumulation: Fp32Accumulation::Fast, ..Default::default() };
+        let err = compute_workload_with_options(types::Input { precision: "fp32".to_string(), ..input }, &options).unwrap_err();
+        assert!(err.to_string().contains("cannot be combined with fast fp32 accumulation"), "{}", err);
+        assert!(Fp32Summation::parse("compensated").is_err());
+        assert_eq!(Fp32Summation::parse("pairwise").unwrap(), Fp32Summation::Pairwise);
+    }
+
+    #[test]
+    fn test_rounded_hash_tolerates_accumulation_order() {
+        let (a, b) = generate_matrices_from_seed(b"rounded", 16, 64, 64, 16);
+        // Perturb per element so results are far from any integer grid and summation order matters
+        let perturb = |m: FlatMatrix| FlatMatrix {
+            data: m.data.iter().enumerate().map(|(i, x)| x * (1.0 + 0.1 * (i as f32).sin())).collect(),
+            ..m
+        };
+        let (a, b) = (perturb(a), perturb(b));
+        let run = |kernel: &str, rounding| {
+            let input = types::Input::matmul(a.clone(), b.clone(), "fp32");
+            let options = ComputeOptions {
+                kernel: Some(kernel.to_string()),
+                hash_rounding: rounding,
+                ..Default::default()
+            };
+            compute_workload_with_options(input, &options).unwrap()
+        };
         
-        let input: types::Input = serde_json::from_str(input_json).unwrap();
-        let output = compute_workload(input).unwrap();
+        // fp32_16x16 sums four products at a time, fp32_blocked one at a time
+        let exact_16 = run("fp32_16x16", None);
+        let exact_blocked = run("fp32_blocked", None);
+        assert_ne!(exact_16.result_hash, exact_blocked.result_hash);
         
-        // Check result correctness (using flat indexing)
-        assert_eq!(output.result_matrix.data[0 * output.result_matrix.cols + 0], 19.0);
-        assert_eq!(output.result_matrix.data[0 * output.result_matrix.cols + 1], 22.0);
-        assert_eq!(output.result_matrix.data[1 * output.result_matrix.cols + 0], 43.0);
-        assert_eq!(output.result_matrix.data[1 * output.result_matrix.cols + 1], 50.0);
+        let rounding = HashRounding::from_mode("rounded", Some(10), None).unwrap();
+        let rounded_16 = run("fp32_16x16", rounding);
+        let rounded_blocked = run("fp32_blocked", rounding);
+        assert_eq!(rounded_16.result_hash, rounded_blocked.result_hash);
+        assert_eq!(rounded_16.metadata.hash_mode, "rounded");
+        assert_eq!(rounded_16.metadata.hash_rounding, rounding);
         
-        // Check hash is present
-        assert!(!output.result_hash.is_empty());
+        // Verification replays the rounding from metadata
+        assert!(verify_with_metadata(&a, &b, &rounded_16.metadata, &rounded_16.result_hash).unwrap());
+        assert!(verify_correctness_with_rounding(&a, &b, "fp32", rounding, &rounded_blocked.result_hash).unwrap());
         
-        // Check metrics are reasonable
-        assert!(output.metrics.latency_ms >= 0.0);
-        assert!(output.metrics.ops_per_second > 0.0);
+        // A real error is still detected
+        let mut corrupted = FlatMatrix::clone(&rounded_16.result_matrix);
+        corrupted.data[37] *= 1.01;
+        assert_ne!(compute_hash_rounded(&corrupted, ResultDtype::F32, rounding), rounded_16.result_hash);
+    }
+    
+    #[test]
+    fn test_hash_rounding_modes() {
+        let bits = HashRounding::MantissaBits { bits: 2 };
+        // 1.0 + 2^-3 is exactly halfway between 1.0 and 1.25: ties to even
+        assert_eq!(bits.apply(1.125), 1.0);
+        assert_eq!(bits.apply(1.2), 1.25);
+        assert_eq!(bits.apply(-0.0), 0.0);
+        assert_eq!(bits.apply(f32::INFINITY), f32::INFINITY);
         
-        // Verify hash matches recomputed hash
-        let input2_json = r#"{
-            "matrix_a": [[1.0, 2.0], [3.0, 4.0]],
-            "matrix_b": [[5.0, 6.0], [7.0, 8.0]],
-            "precision": "fp32",
-            "workload_type": "matmul"
-        }"#;
-        let input2: types::Input = serde_json::from_str(input2_json).unwrap();
+        let quantum = HashRounding::Quantum { quantum: 0.5 };
+        assert_eq!(quantum.apply(2.26), 2.5);
+        assert_eq!(quantum.apply(-0.2).to_bits(), 0.0f32.to_bits());
         
-        assert!(verify_correctness(
-            &input2.matrix_a,
-            &input2.matrix_b,
-            "fp32",
-            &output.result_hash
-        ).unwrap());
+        assert!(HashRounding::from_mode("exact", None, None).unwrap().is_none());
+        assert!(HashRounding::from_mode("rounded", Some(24), None).is_err());
+        assert!(HashRounding::from_mode("rounded", Some(8), Some(0.1)).is_err());
+        assert!(HashRounding::from_mode("exact", Some(8), None).is_err());
     }
     
+    #[cfg(feature = "blake3-hash")]
     #[test]
-    fn test_matrix_dimension_validation() {
-        let input_json = r#"{
-            "matrix_a": [[1.0, 2.0], [3.0, 4.0]],
-            "matrix_b": [[5.0, 6.0]],
-            "precision": "fp32",
-            "workload_type": "matmul"
-        }"#;
+    fn test_tile_trace_and_verify_tile() {
+        // 40x80 result: 3x2 tiles, with short edge tiles in both directions
+        let (a, b) = generate_matrices_from_seed(b"tiles", 40, 33, 33, 80);
+        for precision in ["fp32", "u8i8", "int8"] {
+            let input = types::Input::matmul(a.clone(), b.clone(), precision);
+            let options = ComputeOptions { tile_trace: true, ..Default::default() };
+            let out = compute_workload_with_options(input, &options).unwrap();
+            let trace = out.tile_trace.unwrap();
+            assert_eq!(trace.len(), 6);
+            assert_eq!((trace[5].row, trace[5].col, trace[5].rows, trace[5].cols), (32, 64, 8, 16));
+            assert_eq!(out.trace_root, Some(tile_trace(&out.result_matrix, ResultDtype::F32).1));
+            
+            for tile in &trace {
+                assert!(verify_tile(&a, &b, precision, (tile.row, tile.col), &tile.digest).unwrap(), "{} {:?}", precision, tile);
+            }
+            
+            // A forged claim for one tile is caught without touching the others
+            let mut forged = FlatMatrix::clone(&out.result_matrix);
+            forged.data[17 * 80 + 70] += 1.0;
+            let (forged_trace, forged_root) = tile_trace(&forged, ResultDtype::F32);
+            assert_ne!(Some(forged_root), out.trace_root);
+            let bad = &forged_trace[3];
+            assert_eq!((bad.row, bad.col), (16, 64));
+            assert!(!verify_tile(&a, &b, precision, (bad.row, bad.col), &bad.digest).unwrap());
+            assert_eq!(forged_trace[2], trace[2]);
+        }
         
-        let input: types::Input = serde_json::from_str(input_json).unwrap();
-        let result = compute_workload(input);
-        assert!(result.is_err());
-        assert!(result.unwrap_err().contains("incompatible"));
+        // Seed-shaped results are a single tile
+        let (a16, b16) = generate_matrices_from_seed(b"tiles", 16, 64, 64, 16);
+        let (trace, _) = tile_trace(&matmul_fp32(&a16, &b16).0, ResultDtype::F32);
+        assert_eq!(trace.len(), 1);
+        assert!(verify_tile(&a16, &b16, "fp32", (0, 16), &trace[0].digest).is_err());
+    }
+
+    /// Top-level public items of lib.rs and of every `pub mod` it declares, whatever this
+    /// build's features, including inline `pub mod` blocks (`types`, `api::api`): one
+    /// signature per line
+    fn public_api_lines() -> Vec<String> {
+        let src_dir = std::path::Path::new(env!("CARGO_MANIFEST_DIR")).join("src");
+        let lib = include_str!("lib.rs");
+        let mut sources = vec![(String::new(), lib.to_string())];
+        for module in lib.lines().filter_map(|line| line.strip_prefix("pub mod ")?.strip_suffix(';')) {
+            let src = std::fs::read_to_string(src_dir.join(format!("{}.rs", module))).unwrap();
+            sources.push((format!("{}: ", module), src));
+        }
+        let mut api = Vec::new();
+        for (prefix, src) in &sources {
+            let mut lines = src.lines();
+            // The scope of the inline `pub mod` being read, whose lines are indented once
+            let mut inline: Option<String> = None;
+            let mut deprecated = false;
+            while let Some(line) = lines.next() {
+                let in_types = inline.is_some();
+                let item = if in_types { line.strip_prefix("    ") } else { Some(line) };
+                if item == Some("mod tests {") {
+                    break;
+                }
+                if let Some(name) = line.strip_prefix("pub mod ").and_then(|rest| rest.strip_suffix(" {")) {
+                    api.push(format!("{}pub mod {}", prefix, name));
+                    let outer = prefix.trim_end_matches(": ");
+                    inline = Some(if outer.is_empty() { format!("{}: ", name) } else { format!("{}::{}: ", outer, name) });
+                    continue;
+                }
+                if in_types && line == "}" {
+                    inline = None;
+                    continue;
+                }
+                let Some(item) = item.filter(|l| l.starts_with("pub ") || l.starts_with("#[deprecated")) else {
+                    continue;
+                };
+                if item.starts_with("#[deprecated") {
+                    deprecated = true;
+                    continue;
+                }
+                let mut signature = item.to_string();
+                let is_use = item.starts_with("pub use ");
+                while (is_use || !signature.contains('{')) && !signature.trim_end().ends_with(';') {
+                    let Some(next) = lines.next() else { break };
+                    signature.push(' ');
+                    signature.push_str(next.trim());
+                }
+                let signature = if is_use { signature.as_str() } else { signature.split(" {").next().unwrap() };
+                let signature = signature.trim_end_matches(';');
+                let signature = signature.split_whitespace().collect::<Vec<_>>().join(" ").replace("( ", "(").replace("{ ", "{").replace(", )", ")").replace(", }", "}");
+                let marker = if std::mem::take(&mut deprecated) { "#[deprecated] " } else { "" };
+                let scope = inline.as_deref().unwrap_or(prefix);
+                api.push(format!("{}{}{}", scope, marker, signature));
+                // A struct's pub fields and an enum's variants are API too: one line each
+                let kind = ["pub struct ", "pub enum "].into_iter().find(|kind| item.starts_with(kind));
+                if let Some(kind) = kind.filter(|_| item.trim_end().ends_with('{')) {
+                    let name = item[kind.len()..].split(|c: char| !c.is_alphanumeric() && c != '_').next().unwrap();
+                    let mut variant = String::new();
+                    for line in lines.by_ref() {
+                        let line = if in_types { line.strip_prefix("    ").unwrap_or(line) } else { line };
+                        if line == "}" {
+                            break;
+                        }
+                        let member = line.split(" //").next().unwrap().trim();
+                        if member.is_empty() || member.starts_with("//") || member.starts_with("#[") {
+                            continue;
+                        }
+                        if kind == "pub struct " {
+                            // Fields are one to a line; deeper lines would be inside a field's type
+                            if line.starts_with("    pub ") && !line.starts_with("     ") {
+                                api.push(format!("{}{}.{}", scope, name, member.trim_start_matches("pub ").trim_end_matches(',')));
+                            }
+                            continue;
+                        }
+                        // Variants may span lines (`Name {`, one field a line, `},`)
+                        if !variant.is_empty() {
+                            variant.push(' ');
+                        }
+                        variant.push_str(member);
+                        if !variant.ends_with('{') && !variant.ends_with(',') {
+                            continue;
+                        }
+                        if variant.ends_with('{') || (variant.contains('{') && !variant.contains('}')) {
+                            continue;
+                        }
+                        let variant = std::mem::take(&mut variant);
+                        let variant = variant.trim_end_matches(',').replace("{ ", "{").replace(", }", "}").replace("{", "{ ").replace("}", " }");
+                        api.push(format!("{}{}::{}", scope, name, variant.split_whitespace().collect::<Vec<_>>().join(" ")));
+                    }
+                }
+            }
+        }
+        api
+    }
+
+    #[test]
+    fn test_public_api_snapshot() {
+        let current = public_api_lines().join("\n") + "\n";
+        // Fields and variants are in the snapshot, so adding one to the prelude's types fails it
+        assert!(current.contains("\ntypes: Input.precision: String\n"), "{}", current);
+        assert!(current.contains("\nerror: SolverError::Internal(String)\n"), "{}", current);
+        // As are the other public modules, feature-gated or not
+        assert!(current.contains("\nmemory: pub fn parse_size(s: &str) -> Result<u64, SolverError>\n"), "{}", current);
+        assert!(current.contains("\napi::api: pub struct ApiConfig\n"), "{}", current);
+        let path = std::path::Path::new(env!("CARGO_MANIFEST_DIR")).join("public-api.txt");
+        if std::env::var_os("UPDATE_PUBLIC_API").is_some() {
+            std::fs::write(&path, &current).unwrap();
+        }
+        let expected = std::fs::read_to_string(&path).unwrap_or_default();
+        assert!(
+            current == expected,
+            "public API changed; review the diff and rerun with UPDATE_PUBLIC_API=1 to accept it:\n{}",
+            current.lines().filter(|l| !expected.lines().any(|e| e == *l)).map(|l| format!("+ {}\n", l)).chain(
+                expected.lines().filter(|e| !current.lines().any(|l| l == *e)).map(|e| format!("- {}\n", e))
+            ).collect::<String>()
+        );
+    }
+
+    #[test]
+    #[allow(deprecated)]
+    fn test_deprecated_shims_match_kernels() {
+        let (a, b) = generate_matrices_from_seed(b"shims", 16, 32, 32, 16);
+        let via = |kernel: &str, precision: &str| {
+            let options = ComputeOptions { kernel: Some(kernel.to_string()), ..Default::default() };
+            let input = types::Input::matmul(a.clone(), b.clone(), precision);
+            compute_workload_with_options(input, &options).unwrap().result_matrix.data.clone()
+        };
+        assert_eq!(matmul_fp32_optimized(&a, &b).0.data, via("fp32_blocked", "fp32"));
+        assert_eq!(matmul_u8i8(&a, &b).data, via("u8i8_generic", "u8i8"));
+        assert_eq!(matmul_u8i8_16x16(&a, &b).0.data, via("u8i8_16x16", "u8i8"));
+        assert_eq!(prepare_operands(&a, &b, "u8i8").unwrap().run().data, via("u8i8_16x16", "u8i8"));
+    }
+
+    #[test]
+    #[allow(deprecated)]
+    fn test_gemv_matches_general_kernels() {
+        let bits = |m: &FlatMatrix| m.data.iter().map(|x| x.to_bits()).collect::<Vec<_>>();
+        // k crosses the blocked kernel's 64-long k blocks; 1×k × k×1 is both kinds of vector
+        for (m, k, n) in [(1, 300, 24), (24, 300, 1), (1, 77, 1), (1, 5, 130)] {
+            let (a, b) = generate_matrices_from_seed(b"gemv", m, k, k, n);
+            let (gemv, _) = matmul_fp32_gemv(&a, &b, &SystemClock);
+            assert_eq!(bits(&gemv), bits(&matmul_fp32_optimized(&a, &b).0), "{}x{}x{}", m, k, n);
+            for scaling in [Int8Scaling::PerTensor, Int8Scaling::PerRow] {
+                assert_eq!(bits(&matmul_int8_gemv(&a, &b, scaling)), bits(&matmul_int8(&a, &b, scaling)), "{}x{}x{}", m, k, n);
+            }
+
+            // Selected automatically, with the general kernel's hash and shape
+            for (precision, general) in [("fp32", "fp32_blocked"), ("int8", "int8_generic"), ("int8_rowwise", "int8_rowwise_generic")] {
+                let input = types::Input::matmul(a.clone(), b.clone(), precision);
+                let fast = compute_workload(input.clone()).unwrap();
+                let options = ComputeOptions { kernel: Some(general.to_string()), ..Default::default() };
+                let general = compute_workload_with_options(input, &options).unwrap();
+                assert_eq!(fast.metadata.kernel, Some(format!("{}_gemv", precision)));
+                assert_eq!((&fast.result_hash, fast.metadata.result_shape), (&general.result_hash, (m, n)), "{} {}x{}x{}", precision, m, k, n);
+                assert!(verify_correctness(&a, &b, precision, &fast.result_hash).unwrap());
+            }
+        }
+    }
+
+    /// u8 bytes, i8 codes and the bytes as i8, with both extremes of each up front
+    #[cfg(any(target_arch = "x86_64", target_arch = "aarch64"))]
+    fn simd_test_bytes() -> (Vec<u8>, Vec<i8>, Vec<i8>) {
+        let mut seed = 0x9e3779b9u32;
+        let mut next = || {
+            seed ^= seed << 13;
+            seed ^= seed >> 17;
+            seed ^= seed << 5;
+            seed
+        };
+        let bytes: Vec<u8> = (0..1000).map(|i| if i < 4 { [0, 255, 128, 127][i] } else { next() as u8 }).collect();
+        let codes: Vec<i8> = (0..1000).map(|i| if i < 4 { [-128, -128, 127, 0][i] } else { next() as i8 }).collect();
+        let signed = bytes.iter().map(|&x| x as i8).collect();
+        (bytes, codes, signed)
+    }
+
+    /// Packed 16×k A and k×16 B for the u8i8 16x16 inner loops
+    #[cfg(any(target_arch = "x86_64", target_arch = "aarch64"))]
+    fn simd_test_operands(k: usize) -> (AlignedBufferU8, AlignedBufferI8) {
+        let (bytes, codes, _) = simd_test_bytes();
+        let (mut a_u8, mut b_i8) = (AlignedBufferU8::new(16 * k, 64), AlignedBufferI8::new(k * 16, 64));
+        unsafe {
+            for i in 0..16 * k {
+                *a_u8.as_mut_ptr().add(i) = bytes[i % 1000];
+                *b_i8.as_mut_ptr().add(i) = codes[(i * 7) % 1000];
+            }
+        }
+        (a_u8, b_i8)
+    }
+
+    #[cfg(target_arch = "x86_64")]
+    #[test]
+    fn test_avx512_vnni_matches_scalar() {
+        if !simd::SimdVariant::Avx512Vnni.is_supported() {
+            eprintln!("skipping: no AVX-512 VNNI on this CPU");
+            return;
+        }
+        // Lengths cover the 64-byte body, the AVX2 and scalar tails, and neither
+        let (bytes, codes, signed) = simd_test_bytes();
+        for len in [0, 1, 17, 63, 64, 65, 80, 128, 999, 1000] {
+            unsafe {
+                assert_eq!(dot_i8_vnni(signed.as_ptr(), codes.as_ptr(), len), dot_i8_scalar(signed.as_ptr(), codes.as_ptr(), len), "{}", len);
+                assert_eq!(dot_u8i8_vnni(bytes.as_ptr(), codes.as_ptr(), len), dot_u8i8_scalar(bytes.as_ptr(), codes.as_ptr(), len), "{}", len);
+            }
+        }
+        // -128 · -128 everywhere: the largest products, and the offset trick's worst case
+        let lows = vec![i8::MIN; 256];
+        assert_eq!(unsafe { dot_i8_vnni(lows.as_ptr(), lows.as_ptr(), 256) }, 256 * 128 * 128);
+
+        for k in [1, 3, 4, 7, 62, 64] {
+            let (a_u8, b_i8) = simd_test_operands(k);
+            let mut simd: Vec<i32> = (0..256).collect();
+            let mut scalar = simd.clone();
+            unsafe {
+                u8i8_16x16_accumulate_vnni(a_u8.as_ptr(), b_i8.as_ptr(), k, 16, simd.as_mut_ptr());
+                for i in 0..16 {
+                    u8i8_16x16_row_scalar(a_u8.as_ptr().add(i * k), b_i8.as_ptr(), k, scalar.as_mut_ptr().add(i * 16));
+                }
+            }
+            assert_eq!(simd, scalar, "k = {}", k);
+        }
+    }
+
+    #[cfg(target_arch = "aarch64")]
+    #[test]
+    fn test_dotprod_matches_scalar() {
+        if !simd::SimdVariant::Dotprod.is_supported() {
+            eprintln!("skipping: no dotprod on this CPU");
+            return;
+        }
+        // SDOT groups the products four to a lane, but integer sums do not depend on the
+        // grouping; lengths cover the 16-byte body, the tail and neither
+        let (bytes, codes, signed) = simd_test_bytes();
+        for len in [0, 1, 15, 16, 17, 33, 63, 999, 1000] {
+            unsafe {
+                assert_eq!(dot_i8_dotprod(signed.as_ptr(), codes.as_ptr(), len), dot_i8_scalar(signed.as_ptr(), codes.as_ptr(), len), "{}", len);
+                assert_eq!(dot_u8i8_dotprod(bytes.as_ptr(), codes.as_ptr(), len), dot_u8i8_scalar(bytes.as_ptr(), codes.as_ptr(), len), "{}", len);
+            }
+        }
+        let lows = vec![i8::MIN; 256];
+        assert_eq!(unsafe { dot_i8_dotprod(lows.as_ptr(), lows.as_ptr(), 256) }, 256 * 128 * 128);
+
+        for k in [1, 3, 4, 7, 62, 65] {
+            let (a_u8, b_i8) = simd_test_operands(k);
+            let mut simd: Vec<i32> = (0..256).collect();
+            let mut scalar = simd.clone();
+            unsafe {
+                u8i8_16x16_accumulate_dotprod(a_u8.as_ptr(), b_i8.as_ptr(), k, 16, simd.as_mut_ptr());
+                for i in 0..16 {
+                    u8i8_16x16_row_scalar(a_u8.as_ptr().add(i * k), b_i8.as_ptr(), k, scalar.as_mut_ptr().add(i * 16));
+                }
+            }
+            assert_eq!(simd, scalar, "k = {}", k);
+        }
+        let (a, b) = generate_matrices_from_seed(b"dotprod", 16, 300, 300, 16);
+        assert_eq!(matmul_u8i8_16x16_packed_i32(&a, &pack_b_u8i8(&b), None, &SystemClock).0, matmul_u8i8_generic_i32(&a, &b));
+    }
+
+    #[cfg(target_arch = "aarch64")]
+    #[test]
+    fn test_i8mm_matches_scalar() {
+        if !simd::SimdVariant::I8mm.is_supported() {
+            eprintln!("skipping: no i8mm on this CPU");
+            return;
+        }
+        // k covers the eight-deep groups, the NEON tail, and more than one stack of panels
+        for k in [1, 7, 8, 9, 62, 512, 520, 1031] {
+            let (a_u8, b_i8) = simd_test_operands(k);
+            let mut simd: Vec<i32> = (0..256).collect();
+            let mut scalar = simd.clone();
+            unsafe {
+                u8i8_16x16_accumulate_i8mm(a_u8.as_ptr(), b_i8.as_ptr(), k, 16, simd.as_mut_ptr());
+                u8i8_16x16_accumulate_scalar(a_u8.as_ptr(), b_i8.as_ptr(), k, 16, scalar.as_mut_ptr());
+            }
+            assert_eq!(simd, scalar, "k = {}", k);
+        }
+        let (a, b) = generate_matrices_from_seed(b"i8mm", 16, 50240, 50240, 16);
+        assert_eq!(matmul_u8i8_16x16_packed_i32(&a, &pack_b_u8i8(&b), None, &SystemClock).0, matmul_u8i8_generic_i32(&a, &b));
+    }
+
+    #[cfg(any(target_arch = "x86_64", target_arch = "aarch64"))]
+    #[test]
+    fn test_i8mm_panel_layout() {
+        // USMMLA's definition applied to the panels in scalar code, so the tiling the i8mm
+        // kernel relies on is checked on every target
+        let k = 24;
+        let (a_u8, b_i8) = simd_test_operands(k);
+        let mut panels = vec![0i8; k / 8 * 128];
+        unsafe { pack_u8i8_b_panels(b_i8.as_ptr(), k / 8, &mut panels) };
+        let a = unsafe { std::slice::from_raw_parts(a_u8.as_ptr(), 16 * k) };
+        let mut tiled = vec![0i32; 256];
+        for pair in 0..8 {
+            for g in 0..k / 8 {
+                for cols in 0..8 {
+                    let panel = &panels[(8 * g + cols) * 16..][..16];
+                    for (r, c) in [(0, 0), (0, 1), (1, 0), (1, 1)] {
+                        let sum: i32 = (0..8).map(|q| a[(2 * pair + r) * k + 8 * g + q] as i32 * panel[8 * c + q] as i32).sum();
+                        tiled[(2 * pair + r) * 16 + 2 * cols + c] += sum;
+                    }
+                }
+            }
+        }
+        let mut scalar = vec![0i32; 256];
+        unsafe { u8i8_16x16_accumulate_scalar(a_u8.as_ptr(), b_i8.as_ptr(), k, 16, scalar.as_mut_ptr()) };
+        assert_eq!(tiled, scalar);
+    }
+
+    #[cfg(feature = "parallel")]
+    #[test]
+    fn test_parallel_fp32_blocked_is_bit_identical() {
+        // 203 rows: twelve full row blocks and a partial one; 203·300·150 multiply-adds is
+        // above PARALLEL_MIN_MACS
+        let (matrix_a, matrix_b) = generate_matrices_from_seed(b"parallel", 203, 300, 300, 150);
+        let input = types::Input::matmul(matrix_a, matrix_b, "fp32");
+        let run = |threads| {
+            let options = ComputeOptions { kernel: Some("fp32_blocked".to_string()), threads, ..Default::default() };
+            compute_workload_with_options(input.clone(), &options).unwrap()
+        };
+        let serial = run(Some(1));
+        assert_eq!(serial.metadata.threads, None);
+        for threads in [Some(2), Some(5), None] {
+            let output = run(threads);
+            assert_eq!(output.result_matrix.data, serial.result_matrix.data, "{:?}", threads);
+            assert_eq!(output.result_hash, serial.result_hash);
+            // Counts above the machine's parallelism are capped at it
+            if let Some(n) = threads.map(|n| n.min(parallel::max_threads())) {
+                assert_eq!(output.metadata.threads, (n > 1).then_some(n));
+            }
+        }
+        // Small products stay serial
+        let (a, b) = generate_matrices_from_seed(b"parallel", 32, 64, 64, 64);
+        assert_eq!(matmul_fp32_blocked_threads(&a, &b, Some(4), &SystemClock).2, 1);
+    }
+
+    #[test]
+    fn test_packed_b_cache_serves_interleaved_bs() {
+        let (a, b1) = generate_matrices_from_seed(b"lru one", 16, 300, 300, 16);
+        let (_, b2) = generate_matrices_from_seed(b"lru two", 16, 300, 300, 16);
+        let run = |b: &FlatMatrix| {
+            let input = types::Input::matmul(a.clone(), b.clone(), "int8");
+            compute_workload(input).unwrap()
+        };
+        // Alternating Bs both stay cached (each run's B is a new copy); other tests share
+        // the caches, so retry until nothing else evicts one between the runs
+        let outputs = (0..20)
+            .map(|_| [&b1, &b2, &b1, &b2].map(run))
+            .find(|outputs| outputs[2..].iter().all(|o| o.metrics.pack_cache_hit == Some(true)))
+            .unwrap();
+        assert_eq!(outputs[2].result_hash, outputs[0].result_hash);
+        assert_eq!(outputs[3].result_hash, outputs[1].result_hash);
+        assert_ne!(outputs[0].result_hash, outputs[1].result_hash);
+        let stats = outputs[3].metrics.cache_stats.clone().unwrap();
+        assert_eq!(stats.cache, "int8");
+        assert!(stats.hits >= 2 && stats.misses >= 2 && stats.entries <= stats.capacity, "{:?}", stats);
+    }
+
+    #[test]
+    fn test_cache_disabled_requests_match_cached_ones() {
+        let (a, b) = generate_matrices_from_seed(b"cache off", 16, 300, 300, 16);
+        let metadata = |cache_enabled| Some(types::InputMetadata { compiler_flags: None, libraries: None, cache_enabled });
+        for precision in ["bf16", "int8", "int8_rowwise", "int8_asym", #[cfg(feature = "fp16")] "fp16"] {
+            let run = |cache_enabled| {
+                let input = types::Input { matrix_a: a.clone(), matrix_b: b.clone(), precision: precision.to_string(), metadata: metadata(cache_enabled), ..Default::default() };
+                compute_workload(input).unwrap()
+            };
+            let (cached, uncached) = (run(Some(true)), run(Some(false)));
+            assert_eq!(uncached.result_hash, cached.result_hash, "{}", precision);
+            assert_eq!(uncached.result_matrix.data, cached.result_matrix.data, "{}", precision);
+            assert_eq!((cached.metadata.b_cache_used, uncached.metadata.b_cache_used), (Some(true), Some(false)), "{}", precision);
+            assert!(cached.metrics.cache_stats.is_some(), "{}", precision);
+            assert!(uncached.metrics.cache_stats.is_none() && uncached.metrics.pack_cache_hit.is_none(), "{}", precision);
+        }
+        // Kernels without a cache do not report one
+        let input = types::Input { matrix_a: a.clone(), matrix_b: b.clone(), precision: "fp32".to_string(), metadata: metadata(Some(false)), ..Default::default() };
+        assert_eq!(compute_workload(input).unwrap().metadata.b_cache_used, None);
+    }
+
+    #[test]
+    fn test_cache_contexts_are_isolated() {
+        let (a, b) = generate_matrices_from_seed(b"own caches", 16, 300, 300, 16);
+        let input = || types::Input::matmul(a.clone(), b.clone(), "int8");
+        let (first, second) = (Arc::new(CacheContext::with_capacity(2)), Arc::new(CacheContext::with_capacity(2)));
+        let run = |caches: &Arc<CacheContext>| {
+            let options = ComputeOptions { caches: Some(caches.clone()), ..Default::default() };
+            compute_workload_with_options(input(), &options).unwrap()
+        };
+        // No other test reaches these contexts, so their counters are exact
+        let outputs = [run(&first), run(&first), run(&second)];
+        assert_eq!(outputs.iter().map(|o| o.metrics.pack_cache_hit).collect::<Vec<_>>(), [Some(false), Some(true), Some(false)]);
+        let stats = |o: &types::Output| {
+            let stats = o.metrics.cache_stats.clone().unwrap();
+            (stats.hits, stats.misses, stats.entries, stats.capacity)
+        };
+        assert_eq!(outputs.each_ref().map(stats), [(0, 1, 1, 2), (1, 1, 1, 2), (0, 1, 1, 2)]);
+        assert!(outputs.iter().all(|o| o.result_hash == outputs[0].result_hash));
+        assert_eq!(first.clear(), 1);
+        assert_eq!(run(&first).metrics.pack_cache_hit, Some(false));
+        assert_eq!(run(&second).metrics.pack_cache_hit, Some(true));
+    }
+
+    #[test]
+    fn test_fp32_panel_cache_keeps_results() {
+        let caches = Arc::new(CacheContext::with_capacity(4));
+        // One panel, a partial last panel, several panels, and enough work to split rows
+        for (m, k, n) in [(5, 9, 64), (7, 33, 70), (3, 130, 200), (40, 300, 400)] {
+            let (a, b) = generate_matrices_from_seed(format!("panels {}", n).as_bytes(), m, k, k, n);
+            let direct = matmul_fp32_blocked(&a, &b, &SystemClock).0;
+            for threads in [None, Some(1), Some(3)] {
+                let options = ComputeOptions { kernel: Some("fp32_blocked".to_string()), caches: Some(caches.clone()), threads, ..Default::default() };
+                let input = types::Input::matmul(a.clone(), b.clone(), "fp32");
+                let output = compute_workload_with_options(input, &options).unwrap();
+                assert_eq!(output.result_matrix.data, direct.data, "{}x{}x{}", m, k, n);
+                assert_eq!(output.result_hash, compute_hash(&direct));
+                // Panels are packed on the first run and reused after; one panel is read as is,
+                // as is any B without blake3 keys
+                let cached = cfg!(feature = "blake3-hash") && n > TILE_COLS;
+                assert_eq!(output.metrics.pack_cache_hit, cached.then_some(threads.is_some()), "{}x{}x{} {:?}", m, k, n, threads);
+                assert_eq!(output.metadata.b_cache_used, cached.then_some(true));
+                assert_eq!(output.metrics.cache_stats.is_some(), cached);
+            }
+        }
+        let misses = if cfg!(feature = "blake3-hash") { 3 } else { 0 };
+        assert_eq!(caches.stats_for("fp32_blocked").unwrap().misses, misses);
+    }
+
+    #[cfg(feature = "blake3-hash")]
+    #[test]
+    fn test_fp32_cache_hit_is_faster_than_a_miss() {
+        let (a, b) = generate_matrices_from_seed(b"hit vs miss", 1, 1024, 1024, 1024);
+        let caches = Arc::new(CacheContext::with_capacity(1));
+        let kernel = Some("fp32_blocked".to_string());
+        let options = ComputeOptions { kernel, caches: Some(caches.clone()), threads: Some(1), ..Default::default() };
+        let run = |clear: bool| {
+            if clear {
+                caches.clear();
+            }
+            let output = compute_workload_with_options(types::Input::matmul(a.clone(), b.clone(), "fp32"), &options).unwrap();
+            assert_eq!(output.metrics.pack_cache_hit, Some(!clear));
+            output.metrics.kernel_time_ms.unwrap()
+        };
+        let digest = || {
+            let start = std::time::Instant::now();
+            std::hint::black_box(b_cache::CacheKey::digest(&b));
+            start.elapsed().as_secs_f64() * 1000.0
+        };
+        // A hit's kernel time leaves out the packing, and the digest it needs outside the
+        // timing costs less than that packing. Fastest of interleaved runs, a few attempts
+        // over, as other tests share the machine.
+        let mut timings = (0.0, 0.0, 0.0);
+        for _ in 0..3 {
+            let fastest = |(miss, hit, key): (f64, f64, f64), _| (miss.min(run(true)), hit.min(run(false)), key.min(digest()));
+            timings = (0..9).fold((f64::INFINITY, f64::INFINITY, f64::INFINITY), fastest);
+            let (miss, hit, key) = timings;
+            if hit + key < miss {
+                return;
+            }
+        }
+        panic!("miss {}ms, hit {}ms, digest {}ms", timings.0, timings.1, timings.2);
+    }
+
+    #[test]
+    fn test_flat_matrix_constructors_keep_rows_times_cols() {
+        let expected = vec![1.0, 2.0, 3.0, 4.0, 5.0, 6.0];
+        let matrix = FlatMatrix::new(expected.clone(), 2, 3).unwrap();
+        assert_eq!((matrix.rows, matrix.cols, &matrix.data), (2, 3, &expected));
+        let nested = FlatMatrix::from_nested(vec![vec![1.0, 2.0, 3.0], vec![4.0, 5.0, 6.0]]).unwrap();
+        assert_eq!((nested.rows, nested.cols, &nested.data), (2, 3, &expected));
+        let built = FlatMatrix::from_fn(2, 3, |i, j| (i * 3 + j + 1) as f32);
+        assert_eq!((built.rows, built.cols, &built.data), (2, 3, &expected));
+        let zeros = FlatMatrix::zeros(3, 2);
+        assert_eq!((zeros.rows, zeros.cols, zeros.data), (3, 2, vec![0.0; 6]));
+        let empty = FlatMatrix::from_nested(Vec::new()).unwrap();
+        assert_eq!((empty.rows, empty.cols, empty.data.len()), (0, 0, 0));
+
+        assert_eq!(FlatMatrix::new(vec![1.0; 5], 2, 3).unwrap_err(), MatrixError::LengthMismatch { len: 5, rows: 2, cols: 3 });
+        assert_eq!(FlatMatrix::new(Vec::new(), usize::MAX, 2).unwrap_err(), MatrixError::TooLarge { rows: usize::MAX, cols: 2 });
+        for build in [|| FlatMatrix::zeros(usize::MAX, 2), || FlatMatrix::from_fn(usize::MAX, 2, |_, _| 0.0)] {
+            let panic = std::panic::catch_unwind(build).unwrap_err();
+            let message = panic.downcast_ref::<String>().unwrap();
+            assert!(message.contains("more elements than fit in memory"), "{}", message);
+        }
+        let err = FlatMatrix::from_nested(vec![vec![1.0, 2.0], vec![3.0]]).unwrap_err();
+        assert_eq!(err, MatrixError::RaggedRow { row: 1, len: 1, cols: 2 });
+        assert_eq!(err.to_string(), "Inconsistent row lengths: row 1 has 1 elements, expected 2");
+    }
+
+    #[test]
+    fn test_malformed_flat_matrix_is_refused_before_any_kernel() {
+        // 16x16 operands go to the 16x16 kernels, which read A and B unchecked
+        let good = FlatMatrix::zeros(16, 16);
+        let short = FlatMatrix { data: vec![1.0; 255], rows: 16, cols: 16 };
+        let long = FlatMatrix { data: vec![1.0; 257], rows: 16, cols: 16 };
+        for precision in tests::precisions() {
+            let dtype = ResultDtype::for_precision(precision).unwrap();
+            let run = |operands| {
+                let mut warnings = warnings::WarningCollector::default();
+                compute_matmul_internal(operands, precision, dtype, &None, None, &ComputeOptions::default(), &mut warnings).unwrap_err()
+            };
+            let cases = [
+                (MatmulOperands::Matrices(&short, &good), "matrix_a: data has 255 elements but a 16x16 matrix needs 256"),
+                (MatmulOperands::Matrices(&good, &short), "matrix_b: data has 255 elements but a 16x16 matrix needs 256"),
+                (MatmulOperands::Matrices(&good, &long), "matrix_b: data has 257 elements but a 16x16 matrix needs 256"),
+                (MatmulOperands::TransposedB(&good, &short), "matrix_b: data has 255 elements but a 16x16 matrix needs 256"),
+            ];
+            for (operands, message) in cases {
+                let err = run(operands);
+                assert!(matches!(err, SolverError::InvalidInput(_)), "{}: {:?}", precision, err);
+                assert_eq!(err.to_string(), message, "{}", precision);
+            }
+            // Verification runs the kernels directly, so it checks the same invariant
+            let err = verify_correctness(&short, &good, precision, "00").unwrap_err();
+            assert_eq!(err.to_string(), "matrix_a: data has 255 elements but a 16x16 matrix needs 256");
+        }
+
+        // So do the public shims that pack straight into the 16x16 layouts
+        let empty = FlatMatrix { data: Vec::new(), rows: 16, cols: 64 };
+        let (a, b) = (FlatMatrix::zeros(16, 64), FlatMatrix::zeros(64, 16));
+        #[allow(deprecated)]
+        let shim = |a: &FlatMatrix, b: &FlatMatrix| {
+            let panic = std::panic::catch_unwind(|| matmul_u8i8_16x16(a, b)).unwrap_err();
+            panic.downcast_ref::<String>().unwrap().clone()
+        };
+        assert_eq!(shim(&empty, &b), "matrix_a: data has 0 elements but a 16x64 matrix needs 1024");
+        assert_eq!(shim(&a, &FlatMatrix { data: Vec::new(), ..b.clone() }), "matrix_b: data has 0 elements but a 64x16 matrix needs 1024");
+        // A reading past the end of B
+        assert!(shim(&a, &FlatMatrix::zeros(16, 16)).starts_with("Prepared operands require 16×k × k×16 matrices"));
+        for precision in ["u8i8", "int8"] {
+            let err = pack_operands(&empty, &b, precision).err().unwrap();
+            assert_eq!(err.to_string(), "matrix_a: data has 0 elements but a 16x64 matrix needs 1024");
+        }
+    }
+
+    #[test]
+    fn test_reused_b_allocation_is_not_served_stale() {
+        // Each kernel packs a row-major B through its cache; a transposed B skips it
+        type Kernel = fn(&FlatMatrix, BOperand) -> FlatMatrix;
+        let kernels: Vec<(&str, Kernel)> = vec![
+            ("bf16", |a, b| matmul_bf16_16x16(a, b, None, &SystemClock).0),
+            ("int8", |a, b| matmul_int8_16x16(a, b, Int8Scaling::PerTensor, None, None, None, &SystemClock).0),
+            #[cfg(feature = "fp16")]
+            ("fp16", |a, b| matmul_fp16_16x16(a, b, None, Fp16Rounding::NearestEven, None, &SystemClock).0),
+        ];
+        for (name, kernel) in kernels {
+            let check = |a: &FlatMatrix, b: &FlatMatrix| {
+                assert_eq!(kernel(a, BOperand::RowMajor(b, CacheContext::global())).data, kernel(a, BOperand::Transposed(&transpose(b))).data, "{}", name);
+            };
+            // Overwriting B in place keeps its address, shape and length
+            let (a, mut b) = generate_matrices_from_seed(b"stale one", 16, 300, 300, 16);
+            check(&a, &b);
+            let address = b.data.as_ptr();
+            b.data.copy_from_slice(&generate_matrices_from_seed(b"stale two", 16, 300, 300, 16).1.data);
+            assert_eq!(b.data.as_ptr(), address);
+            check(&a, &b);
+            // A B dropped and another allocated in its place, usually at the same address
+            drop(b);
+            for seed in [&b"stale three"[..], b"stale four"] {
+                let (_, b) = generate_matrices_from_seed(seed, 16, 300, 300, 16);
+                check(&a, &b);
+            }
+        }
+    }
+
+    #[cfg(feature = "parallel")]
+    #[test]
+    fn test_parallel_seed_kernels_hash_like_serial() {
+        let mut precisions = vec!["u8i8", "int8", "int8_asym"];
+        if cfg!(feature = "fp16") {
+            precisions.push("fp16");
+        }
+        for precision in precisions {
+            let run = |threads| {
+                let options = ComputeOptions { threads, ..Default::default() };
+                compute_seed_workload(b"row groups", (16, 3001, 16), precision, None, None, &options).unwrap()
+            };
+            let serial = run(Some(1));
+            assert!(serial.metadata.kernel.as_deref().unwrap().ends_with("_16x16"), "{}", precision);
+            assert_eq!((serial.metrics.threads_used, serial.metadata.threads), (Some(1), None));
+            let unset = run(None);
+            assert_eq!((unset.result_hash.as_str(), unset.metrics.threads_used), (serial.result_hash.as_str(), None));
+            // u8i8 row groups are whole four-row blocks
+            let expected: [usize; 3] = if precision == "u8i8" { [2, 2, 4] } else { [2, 3, 16] };
+            for (threads, expected) in [2, 3, 16].into_iter().zip(expected) {
+                let output = run(Some(threads));
+                assert_eq!(output.result_hash, serial.result_hash, "{} on {} threads", precision, threads);
+                let used = output.metrics.threads_used.unwrap();
+                // Counts above the machine's parallelism are capped at it
+                if threads <= parallel::max_threads() {
+                    assert_eq!(used, expected, "{} on {} threads", precision, threads);
+                } else {
+                    assert!(used <= parallel::max_threads(), "{} on {} threads", precision, threads);
+                }
+            }
+        }
+        // The u8i8 kernel over packed B (not straight from the seed) splits the same way
+        let (a, b) = generate_matrices_from_seed(b"row groups", 16, 999, 999, 16);
+        let serial = matmul_u8i8_16x16_packed_i32(&a, &pack_b_u8i8(&b), None, &SystemClock);
+        let split = matmul_u8i8_16x16_packed_i32(&a, &pack_b_u8i8(&b), Some(2), &SystemClock);
+        assert_eq!(serial.0, matmul_u8i8_generic_i32(&a, &b));
+        assert_eq!((split.0, split.2), (serial.0, 2.min(parallel::max_threads())));
+    }
+
+    #[test]
+    fn test_simd_variant_is_recorded() {
+        let (matrix_a, matrix_b) = generate_matrices_from_seed(b"simd-variant", 16, 300, 300, 16);
+        let input = types::Input::matmul(matrix_a, matrix_b, "u8i8");
+        let variant = int_dot_variant();
+        assert!(["avx512vnni", "avx2", "i8mm", "dotprod", "neon", "scalar"].contains(&variant));
+        for (precision, kernel, expected) in [
+            ("u8i8", "u8i8_16x16", Some(variant)),
+            ("u8i8", "u8i8_generic", None),
+            ("int8", "int8_16x16", Some(variant)),
+            ("int8_asym", "int8_asym_16x16", Some(variant)),
+            ("fp32", "fp32_16x16", None),
+        ] {
+            let options = ComputeOptions { kernel: Some(kernel.to_string()), ..Default::default() };
+            let output = compute_workload_with_options(types::Input { precision: precision.to_string(), ..input.clone() }, &options).unwrap();
+            assert_eq!(output.metadata.simd_variant.as_deref(), expected, "{}", kernel);
+        }
+        let seeded = compute_seed_workload(b"simd-variant", (16, 300, 16), "u8i8", None, None, &ComputeOptions::default()).unwrap();
+        assert_eq!(seeded.metadata.simd_variant.as_deref(), Some(variant));
+    }
+
+    #[cfg(target_arch = "x86_64")]
+    #[test]
+    fn test_avx2_matches_scalar() {
+        if !simd::SimdVariant::Avx2.is_supported() {
+            eprintln!("skipping: no AVX2 on this CPU");
+            return;
+        }
+        // Lengths cover the 16-byte body, the tail and neither
+        let (bytes, codes, signed) = simd_test_bytes();
+        for len in [0, 1, 15, 16, 17, 33, 64, 999, 1000] {
+            unsafe {
+                assert_eq!(dot_i8_avx2(signed.as_ptr(), codes.as_ptr(), len), dot_i8_scalar(signed.as_ptr(), codes.as_ptr(), len), "{}", len);
+                assert_eq!(dot_u8i8_avx2(bytes.as_ptr(), codes.as_ptr(), len), dot_u8i8_scalar(bytes.as_ptr(), codes.as_ptr(), len), "{}", len);
+            }
+        }
+
+        for k in [1, 7, 62] {
+            let (a_u8, b_i8) = simd_test_operands(k);
+            let mut simd: Vec<i32> = (0..256).collect();
+            let mut scalar = simd.clone();
+            unsafe {
+                for i in 0..16 {
+                    u8i8_16x16_row_avx2(a_u8.as_ptr().add(i * k), b_i8.as_ptr(), k, simd.as_mut_ptr().add(i * 16));
+                    u8i8_16x16_row_scalar(a_u8.as_ptr().add(i * k), b_i8.as_ptr(), k, scalar.as_mut_ptr().add(i * 16));
+                }
+            }
+            assert_eq!(simd, scalar, "k = {}", k);
+        }
+        // The dispatched kernel agrees with the generic one on the seed-shaped workload
+        let (a, b) = generate_matrices_from_seed(b"avx2", 16, 300, 300, 16);
+        assert_eq!(matmul_u8i8_16x16_packed_i32(&a, &pack_b_u8i8(&b), None, &SystemClock).0, matmul_u8i8_generic_i32(&a, &b));
+    }
+
+    #[test]
+    fn test_u8i8_i32le_hash() {
+        let dims = (16, 4096, 16);
+        let run = |hash_input: Option<&str>, kernel: Option<&str>| {
+            let (matrix_a, matrix_b) = generate_matrices_from_seed(b"i32le", dims.0, dims.1, dims.1, dims.2);
+            let input = types::Input {
+                matrix_a,
+                matrix_b,
+                precision: "u8i8".to_string(),
+                hash_input: hash_input.map(str::to_string),
+                ..Default::default()
+            };
+            let options = ComputeOptions { kernel: kernel.map(str::to_string), ..Default::default() };
+            compute_workload_with_options(input, &options).unwrap()
+        };
+        let f32le = run(None, None);
+        let i32le = run(Some("i32le"), None);
+        assert_eq!(f32le.result_hash, "16b0ecb70f7a1b245b362a7cd964d08ee056c9b1e6b15bcc125e98afebcae632");
+        assert_eq!(i32le.result_hash, "a7bb0b57437cedb3247bd010b061053e4cb42b678c5f6e284da53405540ad170");
+        assert_eq!(run(Some("f32le"), None).result_hash, f32le.result_hash);
+        assert_eq!((i32le.metadata.result_dtype.as_str(), i32le.metadata.hash_input_description.as_str()), ("i32", "i32le row-major"));
+        // Both u8i8 kernels keep the same accumulators
+        assert_eq!(run(Some("i32le"), Some("u8i8_generic")).result_hash, i32le.result_hash);
+
+        assert!(verify_u8i8_integer(b"i32le", dims, &i32le.result_hash).unwrap());
+        assert!(!verify_u8i8_integer(b"i32le", dims, &f32le.result_hash).unwrap());
+        let (a, b) = generate_matrices_from_seed(b"i32le", dims.0, dims.1, dims.1, dims.2);
+        assert!(verify_with_metadata(&a, &b, &i32le.metadata, &i32le.result_hash).unwrap());
+
+        assert!(ResultDtype::for_hash_input("fp32", Some("i32le")).is_err());
+        assert!(ResultDtype::for_hash_input("u8i8", Some("i64le")).is_err());
+    }
+
+    fn legacy_seed_output(seed: &[u8], dims: (usize, usize, usize), precision: &str, hash_input: Option<&str>) -> types::Output {
+        let (matrix_a, matrix_b) = generate_matrices_from_seed(seed, dims.0, dims.1, dims.1, dims.2);
+        let input = types::Input {
+            matrix_a,
+            matrix_b,
+            precision: precision.to_string(),
+            hash_input: hash_input.map(str::to_string),
+            ..Default::default()
+        };
+        compute_workload_with_options(input, &ComputeOptions::default()).unwrap()
+    }
+
+    #[test]
+    fn test_seed_packing_matches_generated_matrices() {
+        let options = ComputeOptions { packed_min_k: Some(0), ..Default::default() };
+        for (seed, k) in [(&b"seed-a"[..], 50240), (b"seed-b", 64), (b"", 1), (b"seed-c", 333)] {
+            assert!(seed_packs_directly("u8i8", (16, k, 16), &options));
+            for hash_input in [None, Some("i32le")] {
+                let legacy = legacy_seed_output(seed, (16, k, 16), "u8i8", hash_input);
+                let fused =
+                    compute_seed_workload(seed, (16, k, 16), "u8i8", None, hash_input.map(str::to_string), &options).unwrap();
+                assert_eq!(fused.result_hash, legacy.result_hash, "k={} {:?}", k, hash_input);
+                assert_eq!(fused.result_matrix.data, legacy.result_matrix.data);
+                assert_eq!(fused.metadata.kernel.as_deref(), Some("u8i8_16x16"));
+                assert_eq!(fused.metadata.matrix_b_shape, (k, 16));
+            }
+        }
+
+        // Runs the packed operands cannot serve still match through the generated matrices
+        let dual = ComputeOptions { redundancy: Redundancy::Dual, ..Default::default() };
+        let chunked = ComputeOptions { k_chunk: Some(64), ..Default::default() };
+        let default = ComputeOptions::default();
+        for (precision, dims, options) in [
+            ("u8i8", (16, 512, 16), &dual),
+            ("u8i8", (16, 512, 16), &chunked),
+            ("fp32", (16, 64, 16), &options),
+            ("u8i8", (8, 64, 16), &options),
+            // Below the packed kernels' threshold
+            ("u8i8", (16, 8, 16), &default),
+        ] {
+            assert!(!seed_packs_directly(precision, dims, options));
+            let output = compute_seed_workload(b"fallback", dims, precision, None, None, options).unwrap();
+            assert_eq!(output.result_hash, legacy_seed_output(b"fallback", dims, precision, None).result_hash);
+        }
+    }
+
+    #[cfg(feature = "counting-alloc")]
+    #[test]
+    fn test_seed_packing_skips_f32_matrices() {
+        let dims = (16, 50240, 16);
+        let peak = |f: &dyn Fn() -> types::Output| {
+            let guard = memory::MemoryGuard::start(u64::MAX, 0);
+            let output = std::hint::black_box(f());
+            (guard.peak_allocated().unwrap(), output.result_hash)
+        };
+        let (fused, fused_hash) =
+            peak(&|| compute_seed_workload(b"peak", dims, "u8i8", None, None, &ComputeOptions::default()).unwrap());
+        let (legacy, legacy_hash) = peak(&|| legacy_seed_output(b"peak", dims, "u8i8", None));
+        assert_eq!(fused_hash, legacy_hash);
+        // One f32 copy of A alone is 16·50240·4 bytes; the fused run holds only the u8/i8 buffers
+        let f32_matrix = (16 * 50240 * 4) as u64;
+        assert!(fused < f32_matrix, "fused peak {} bytes", fused);
+        assert!(legacy > 2 * f32_matrix, "legacy peak {} bytes", legacy);
+    }
+
+    #[cfg(feature = "counting-alloc")]
+    #[test]
+    fn test_max_memory_trips_at_runtime() {
+        let (a, b) = generate_matrices_from_seed(b"runtime trip", 16, 256, 256, 16);
+        // Far above the estimate, so only the allocations counted after the kernel can refuse it
+        let limit = 4 * memory::estimate_workload_bytes("fp32", (16, 256), (256, 16)).total();
+        let options = ComputeOptions { max_memory_bytes: Some(limit), ..Default::default() };
+        let compute = || compute_workload_with_options(types::Input::matmul(a.clone(), b.clone(), "fp32"), &options);
+        assert!(compute().is_ok());
+        let ballast = faults::FaultPlan { kernel_ballast_bytes: Some(limit as usize), ..Default::default() };
+        let err = faults::with_plan(ballast, compute).unwrap_err();
+        assert!(matches!(err, SolverError::MemoryLimitExceeded(_)), "{}", err);
+        assert!(err.to_string().contains("kernel used"), "{}", err);
+    }
+
+    #[test]
+    fn test_precision_round_trip() {
+        for precision in Precision::ALL {
+            assert_eq!(precision.as_str().parse::<Precision>().unwrap(), precision);
+            assert_eq!(serde_json::to_string(&precision).unwrap(), format!("\"{}\"", precision));
+            assert_eq!(ResultDtype::for_precision(precision.as_str()).is_some(), precision.is_available());
+        }
+        let accepted: Vec<&str> = Precision::ALL.iter().filter(|p| p.is_available()).map(Precision::as_str).collect();
+        assert_eq!(
+            Precision::parse("fp8"),
+            Err(SolverError::UnsupportedPrecision(format!("\"fp8\" (accepted: {})", accepted.join(", "))))
+        );
+        assert_eq!(serde_json::from_str::<Precision>("\" U8I8\\n\"").unwrap(), Precision::U8i8);
+        assert!(serde_json::from_str::<Precision>("\"fp8\"").is_err());
+
+        // Without the fp16 feature, fp16 parses but is refused like an unknown precision
+        if !Precision::Fp16.is_available() {
+            let a = to_flat_matrix(vec![vec![1.0, 2.0]]);
+            let b = to_flat_matrix(vec![vec![3.0], vec![4.0]]);
+            let input = types::Input::matmul(a, b, "fp16");
+            assert!(matches!(compute_workload(input), Err(SolverError::UnsupportedPrecision(_))));
+        }
+    }
+
+    #[test]
+    fn test_messy_names_are_normalized() {
+        let json = |precision: &str, workload_type: &str| {
+            serde_json::json!({
+                "matrix_a": [[1.0, 2.0], [3.0, 4.0]],
+                "matrix_b": [[5.0, 6.0], [7.0, 8.0]],
+                "precision": precision,
+                "workload_type": workload_type,
+            })
+            .to_string()
+        };
+        let run = |precision: &str, workload_type: &str, options: &ComputeOptions| {
+            let (input, _) = numbers::parse_input_json(&json(precision, workload_type), false).unwrap();
+            compute_workload_with_options(input, options)
+        };
+        for precision in tests::precisions() {
+            let clean = run(precision, "matmul", &ComputeOptions::default()).unwrap();
+            for messy in [precision.to_uppercase(), format!(" {}", precision), format!("{}\n", precision), format!("\t{}  ", precision.to_uppercase())] {
+                let output = run(&messy, " MatMul\n", &ComputeOptions::default()).unwrap();
+                assert_eq!(output.metadata.precision, precision);
+                assert_eq!(output.result_hash, clean.result_hash, "{:?}", messy);
+                assert!(verify_correctness(&to_flat_matrix(vec![vec![1.0, 2.0], vec![3.0, 4.0]]), &to_flat_matrix(vec![vec![5.0, 6.0], vec![7.0, 8.0]]), &messy, &output.result_hash).unwrap());
+            }
+        }
+        let forced = ComputeOptions { kernel: Some(" FP32_Blocked ".to_string()), ..Default::default() };
+        assert_eq!(run("FP32", "matmul", &forced).unwrap().metadata.kernel.as_deref(), Some("fp32_blocked"));
+
+        // exact_names restores exact matching
+        let exact = ComputeOptions { exact_names: true, ..Default::default() };
+        assert!(run("fp32", "matmul", &exact).is_ok());
+        let err = run("FP32", "matmul", &exact).unwrap_err();
+        assert!(matches!(err, SolverError::UnsupportedPrecision(_)), "{}", err);
+        assert!(err.to_string().contains("\"FP32\" (accepted: fp32"), "{}", err);
+        assert!(matches!(run("fp32", "Matmul", &exact), Err(SolverError::UnsupportedWorkload(_))));
+        let err = run("fp32", "matmul", &ComputeOptions { kernel: Some("FP32_blocked".to_string()), ..exact }).unwrap_err();
+        assert!(err.to_string().starts_with("Kernel unavailable: no kernel named \"FP32_blocked\" (registered: "), "{}", err);
+
+        // Unknown values still fail, naming what is accepted
+        let err = run(" fp8 ", "matmul", &ComputeOptions::default()).unwrap_err();
+        assert_eq!(err.code(), "unsupported_precision");
+        assert!(err.to_string().contains("\"fp8\" (accepted: fp32, "), "{}", err);
+        let err = run("fp32", "Convolution", &ComputeOptions::default()).unwrap_err();
+        assert_eq!(err.to_string(), "Unsupported workload type: \"convolution\" (accepted: matmul, quantize, batched_matmul)");
     }
 }
@@ -0,0 +1,555 @@
+//! Memory ceiling for a single computation (`ComputeOptions::max_memory_bytes`).
+//!
+//! Two layers: `check_budget` rejects a workload up front when the analytic estimate
+//! (inputs + packed operands + result + serialized output) exceeds the ceiling, and
+//! `MemoryGuard` compares actual allocations against it while computing. The runtime
+//! layer needs the `counting-alloc` feature and a binary that installs
+//! `CountingAllocator` as its global allocator (the crate's binaries do); a library never
+//! picks the allocator for its users. Without both `MemoryGuard::check` always passes.
+//!
+//! The guard counts the calling thread only: what `parallel`'s worker threads allocate for
+//! the fp32 blocked kernel is not charged to it. Kernels cannot be interrupted, so like the
+//! deadline the guard is checked between stages, the first time once the kernel returns;
+//! a kernel that overshoots still runs to completion before the computation fails.
+//!
+//! `RequestMemory` attributes allocations to a request rather than a thread, for the API's
+//! per-request figures; it is fed by the same allocator.
+
+use crate::{histogram, SolverError};
+use serde::Serialize;
+use std::cell::Cell;
+use std::collections::VecDeque;
+use std::future::Future;
+use std::pin::Pin;
+use std::sync::atomic::{AtomicIsize, Ordering};
+use std::sync::{Arc, Mutex};
+use std::task::{Context, Poll};
+
+/// Conservative size of one pretty-printed JSON result element ("-1.2345678e-38,\n" + indent)
+const SERIALIZED_BYTES_PER_ELEMENT: u64 = 32;
+
+/// Where the bytes of `estimate_workload_bytes` go
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+pub struct MemoryEstimate {
+    pub inputs: u64,
+    pub packs: u64,
+    pub result: u64,
+    pub serialization: u64,
+}
+
+impl MemoryEstimate {
+    pub fn total(&self) -> u64 {
+        self.inputs + self.packs + self.result + self.serialization
+    }
+}
+
+/// Analytic peak memory of a matmul with these shapes and precision
+pub fn estimate_workload_bytes(precision: &str, a_shape: (usize, usize), b_shape: (usize, usize)) -> MemoryEstimate {
+    let a = (a_shape.0 as u64).saturating_mul(a_shape.1 as u64);
+    let b = (b_shape.0 as u64).saturating_mul(b_shape.1 as u64);
+    let c = (a_shape.0 as u64).saturating_mul(b_shape.1 as u64);
+    let packs = match precision {
+        // Packed B panels
+        "fp32" => b.saturating_mul(4),
+        // Both operands converted to f16
+        "fp16" => a.saturating_add(b).saturating_mul(2),
+        // Quantized/packed operands plus the i32 accumulator
+        "int8" | "int8_rowwise" | "int8_asym" | "u8i8" => a.saturating_add(b).saturating_add(c.saturating_mul(4)),
+        // Packed nibbles, the bytes they unpack to, and the i32 accumulator
+        "int4" => (a.saturating_add(b).saturating_mul(3) / 2).saturating_add(c.saturating_mul(4)),
+        // Nothing packed or converted
+        "fp32_strict" => 0,
+        // Both operands widened to f64, and the f64 result kept beside the f32 one
+        "fp64" => a.saturating_add(b).saturating_add(c).saturating_mul(8),
+        _ => a.saturating_add(b).saturating_mul(4),
+    };
+    MemoryEstimate {
+        inputs: a.saturating_add(b).saturating_mul(4),
+        packs,
+        result: c.saturating_mul(4),
+        serialization: c.saturating_mul(SERIALIZED_BYTES_PER_ELEMENT),
+    }
+}
+
+/// Analytic peak memory of quantizing an A of this shape: the input, its quantized copy and
+/// their serialization
+pub fn estimate_quantize_bytes(a_shape: (usize, usize)) -> MemoryEstimate {
+    let a = (a_shape.0 as u64).saturating_mul(a_shape.1 as u64);
+    MemoryEstimate {
+        inputs: a.saturating_mul(4),
+        packs: 0,
+        result: a.saturating_mul(4),
+        serialization: a.saturating_mul(SERIALIZED_BYTES_PER_ELEMENT),
+    }
+}
+
+/// Reject a workload whose estimated footprint exceeds `limit` bytes
+pub fn check_budget(precision: &str, a_shape: (usize, usize), b_shape: (usize, usize), limit: u64) -> Result<(), SolverError> {
+    let estimate = estimate_workload_bytes(precision, a_shape, b_shape);
+    if estimate.total() > limit {
+        return Err(SolverError::MemoryLimitExceeded(format!(
+            "{} matmul {}x{} × {}x{} needs an estimated {} bytes (inputs {}, packs {}, result {}, serialization {}), limit is {}",
+            precision,
+            a_shape.0,
+            a_shape.1,
+            b_shape.0,
+            b_shape.1,
+            estimate.total(),
+            estimate.inputs,
+            estimate.packs,
+            estimate.result,
+            estimate.serialization,
+            limit
+        )));
+    }
+    Ok(())
+}
+
+/// Parse a byte size: plain bytes or a K/M/G suffix (powers of 1024), e.g. "512M"
+pub fn parse_size(s: &str) -> Result<u64, SolverError> {
+    let s = s.trim();
+    let (digits, shift) = match s.char_indices().last() {
+        Some((i, c)) if c.eq_ignore_ascii_case(&'k') => (&s[..i], 10),
+        Some((i, c)) if c.eq_ignore_ascii_case(&'m') => (&s[..i], 20),
+        Some((i, c)) if c.eq_ignore_ascii_case(&'g') => (&s[..i], 30),
+        _ => (s, 0),
+    };
+    digits
+        .trim()
+        .parse::<u64>()
+        .ok()
+        .and_then(|n| n.checked_mul(1 << shift))
+        .ok_or_else(|| SolverError::InvalidInput(format!("Invalid memory size: {:?} (expected e.g. 1073741824, 512M, 2G)", s)))
+}
+
+#[cfg(feature = "counting-alloc")]
+mod counting {
+    use std::alloc::{GlobalAlloc, Layout, System};
+    use std::cell::Cell;
+    use std::sync::atomic::{AtomicBool, Ordering};
+
+    /// Set by the first allocation through `CountingAllocator`
+    static INSTALLED: AtomicBool = AtomicBool::new(false);
+
+    thread_local! {
+        static CURRENT: Cell<usize> = const { Cell::new(0) };
+        static PEAK: Cell<usize> = const { Cell::new(0) };
+    }
+
+    /// System allocator that tracks live and peak bytes per thread, for
+    /// `ComputeOptions::max_memory_bytes` and `RequestMemory`. A binary opts in with
+    /// `#[global_allocator] static GLOBAL: CountingAllocator = CountingAllocator;`.
+    pub struct CountingAllocator;
+
+    /// Whether `CountingAllocator` is the global allocator, so the counts mean something
+    pub fn installed() -> bool {
+        INSTALLED.load(Ordering::Relaxed)
+    }
+
+    fn add(size: usize) {
+        if !INSTALLED.load(Ordering::Relaxed) {
+            INSTALLED.store(true, Ordering::Relaxed);
+        }
+        let _ = CURRENT.try_with(|current| {
+            let now = current.get() + size;
+            current.set(now);
+            let _ = PEAK.try_with(|peak| peak.set(peak.get().max(now)));
+        });
+        super::charge_request(size as isize);
+    }
+
+    fn sub(size: usize) {
+        // Memory freed on another thread than it was allocated on must not underflow
+        let _ = CURRENT.try_with(|current| current.set(current.get().saturating_sub(size)));
+        super::charge_request(-(size as isize));
+    }
+
+    unsafe impl GlobalAlloc for CountingAllocator {
+        unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+            let ptr = System.alloc(layout);
+            if !ptr.is_null() {
+                add(layout.size());
+            }
+            ptr
+        }
+
+        unsafe fn alloc_zeroed(&self, layout: Layout) -> *mut u8 {
+            let ptr = System.alloc_zeroed(layout);
+            if !ptr.is_null() {
+                add(layout.size());
+            }
+            ptr
+        }
+
+        unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+            System.dealloc(ptr, layout);
+            sub(layout.size());
+        }
+
+        unsafe fn realloc(&self, ptr: *mut u8, layout: Layout, new_size: usize) -> *mut u8 {
+            let new = System.realloc(ptr, layout, new_size);
+            if !new.is_null() {
+                sub(layout.size());
+                add(new_size);
+            }
+            new
+        }
+    }
+
+    /// Live bytes allocated by this thread; resets its peak to the current value
+    pub fn reset_thread_peak() -> usize {
+        let current = CURRENT.with(Cell::get);
+        PEAK.with(|peak| peak.set(current));
+        current
+    }
+
+    pub fn thread_peak() -> usize {
+        PEAK.with(Cell::get)
+    }
+
+    pub fn thread_live() -> usize {
+        CURRENT.with(Cell::get)
+    }
+}
+
+#[cfg(feature = "counting-alloc")]
+pub use counting::CountingAllocator;
+
+/// Live bytes allocated by the calling thread, when allocations are counted
+pub fn thread_allocated_bytes() -> Option<u64> {
+    #[cfg(feature = "counting-alloc")]
+    return counting::installed().then(|| counting::thread_live() as u64);
+    #[cfg(not(feature = "counting-alloc"))]
+    None
+}
+
+/// Runtime ceiling on the memory one computation allocates on the calling thread
+#[derive(Debug)]
+pub struct MemoryGuard {
+    limit: u64,
+    /// Bytes charged before the guard started (the inputs)
+    preexisting: u64,
+    #[cfg_attr(not(feature = "counting-alloc"), allow(dead_code))]
+    start: usize,
+}
+
+impl MemoryGuard {
+    /// Start measuring; `preexisting` is charged against the limit up front
+    pub fn start(limit: u64, preexisting: u64) -> Self {
+        #[cfg(feature = "counting-alloc")]
+        let start = counting::reset_thread_peak();
+        #[cfg(not(feature = "counting-alloc"))]
+        let start = 0;
+        Self { limit, preexisting, start }
+    }
+
+    /// Peak bytes allocated on the calling thread since `start`, when allocations are counted
+    pub fn peak_allocated(&self) -> Option<u64> {
+        #[cfg(feature = "counting-alloc")]
+        return counting::installed().then(|| counting::thread_peak().saturating_sub(self.start) as u64);
+        #[cfg(not(feature = "counting-alloc"))]
+        None
+    }
+
+    /// Fail with `MemoryLimitExceeded` if usage crossed the ceiling before the end of `stage`
+    pub fn check(&self, stage: &str) -> Result<(), SolverError> {
+        let Some(peak) = self.peak_allocated() else {
+            return Ok(());
+        };
+        let used = self.preexisting.saturating_add(peak);
+        if used > self.limit {
+            return Err(SolverError::MemoryLimitExceeded(format!(
+                "{} used {} bytes ({} input + {} allocated), limit is {}",
+                stage, used, self.preexisting, peak, self.limit
+            )));
+        }
+        Ok(())
+    }
+}
+
+thread_local! {
+    /// Request charged for this thread's allocations, set while a `RequestScope` is alive
+    static ACTIVE_REQUEST: Cell<*const RequestMemory> = const { Cell::new(std::ptr::null()) };
+}
+
+#[cfg_attr(not(feature = "counting-alloc"), allow(dead_code))]
+fn charge_request(bytes: isize) {
+    let _ = ACTIVE_REQUEST.try_with(|active| {
+        let request = active.get();
+        if !request.is_null() {
+            // SAFETY: the RequestScope that set the pointer holds an Arc to the request
+            unsafe { &*request }.charge(bytes);
+        }
+    });
+}
+
+/// Bytes allocated on behalf of one request, on whichever threads work on it
+///
+/// `enter` charges the calling thread's allocations and frees to the request until the
+/// scope drops, and `track` enters around every poll of a future, so an async handler is
+/// charged wherever the runtime polls it. Work handed to another thread (the blocking pool,
+/// scoped workers) takes a clone along and enters it there. Frees of memory allocated before
+/// the request count too, so the live total can go negative; the peak does not.
+#[derive(Debug, Default)]
+pub struct RequestMemory {
+    current: AtomicIsize,
+    peak: AtomicIsize,
+}
+
+impl RequestMemory {
+    pub fn new() -> Arc<Self> {
+        Arc::new(Self::default())
+    }
+
+    #[cfg_attr(not(feature = "counting-alloc"), allow(dead_code))]
+    fn charge(&self, bytes: isize) {
+        let now = self.current.fetch_add(bytes, Ordering::Relaxed) + bytes;
+        if bytes > 0 {
+            self.peak.fetch_max(now, Ordering::Relaxed);
+        }
+    }
+
+    /// Charge this thread to the request until the scope drops
+    pub fn enter(self: &Arc<Self>) -> RequestScope {
+        let previous = ACTIVE_REQUEST.with(|active| active.replace(Arc::as_ptr(self)));
+        RequestScope { _request: self.clone(), previous }
+    }
+
+    /// The request this thread is charged to
+    pub fn current() -> Option<Arc<Self>> {
+        let request = ACTIVE_REQUEST.with(Cell::get);
+        (!request.is_null()).then(|| {
+            // SAFETY: the pointer came from Arc::as_ptr and the active scope keeps that Arc alive
+            unsafe {
+                Arc::increment_strong_count(request);
+                Arc::from_raw(request)
+            }
+        })
+    }
+
+    /// Peak bytes charged so far, when allocations are counted
+    pub fn peak_bytes(&self) -> Option<u64> {
+        #[cfg(feature = "counting-alloc")]
+        return counting::installed().then(|| self.peak.load(Ordering::Relaxed).max(0) as u64);
+        #[cfg(not(feature = "counting-alloc"))]
+        None
+    }
+}
+
+/// Charges the thread that created it to a request; restores the previous one on drop
+#[derive(Debug)]
+pub struct RequestScope {
+    _request: Arc<RequestMemory>,
+    previous: *const RequestMemory,
+}
+
+impl Drop for RequestScope {
+    fn drop(&mut self) {
+        ACTIVE_REQUEST.with(|active| active.set(self.previous));
+    }
+}
+
+/// Run `future` charged to `request`
+pub fn track<F: Future>(request: Arc<RequestMemory>, future: F) -> Tracked<F> {
+    Tracked { request, future: Box::pin(future) }
+}
+
+/// Future returned by `track`
+pub struct Tracked<F> {
+    request: Arc<RequestMemory>,
+    future: Pin<Box<F>>,
+}
+
+impl<F: Future> Future for Tracked<F> {
+    type Output = F::Output;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<F::Output> {
+        let this = self.get_mut();
+        let _scope = this.request.enter();
+        this.future.as_mut().poll(cx)
+    }
+}
+
+/// Bytes to MB, as `Metrics` reports memory
+pub fn bytes_to_mb(bytes: u64) -> f64 {
+    bytes as f64 / (1024.0 * 1024.0)
+}
+
+/// Requests whose peaks `RequestMemoryStats` keeps for percentiles
+pub const REQUEST_MEMORY_WINDOW: usize = 1024;
+
+/// Distribution of per-request peaks: the most recent `REQUEST_MEMORY_WINDOW` for percentiles,
+/// counts and the maximum over all requests
+#[derive(Debug, Default)]
+pub struct RequestMemoryStats {
+    inner: Mutex<RequestMemoryInner>,
+}
+
+#[derive(Debug, Default)]
+struct RequestMemoryInner {
+    recent: VecDeque<u64>,
+    requests: u64,
+    over_soft_limit: u64,
+    max: u64,
+}
+
+/// `RequestMemoryStats` as /stats reports it; MB figures are absent before the first request
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct RequestMemorySummary {
+    pub requests: u64,
+    /// Requests that peaked above the soft threshold
+    pub over_soft_limit: u64,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub p50_mb: Option<f64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub p90_mb: Option<f64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub p99_mb: Option<f64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub max_mb: Option<f64>,
+}
+
+impl RequestMemoryStats {
+    pub fn record(&self, peak_bytes: u64, over_soft_limit: bool) {
+        let mut inner = self.inner.lock().unwrap();
+        if inner.recent.len() == REQUEST_MEMORY_WINDOW {
+            inner.recent.pop_front();
+        }
+        inner.recent.push_back(peak_bytes);
+        inner.requests += 1;
+        inner.over_soft_limit += over_soft_limit as u64;
+        inner.max = inner.max.max(peak_bytes);
+    }
+
+    pub fn summary(&self) -> RequestMemorySummary {
+        let inner = self.inner.lock().unwrap();
+        let mut sorted: Vec<u64> = inner.recent.iter().copied().collect();
+        sorted.sort_unstable();
+        let sorted_mb: Vec<f64> = sorted.into_iter().map(bytes_to_mb).collect();
+        let percentile = |p: f64| histogram::percentile(&sorted_mb, p);
+        RequestMemorySummary {
+            requests: inner.requests,
+            over_soft_limit: inner.over_soft_limit,
+            p50_mb: percentile(0.5),
+            p90_mb: percentile(0.9),
+            p99_mb: percentile(0.99),
+            max_mb: (inner.requests > 0).then(|| bytes_to_mb(inner.max)),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{compute_workload_with_options, generate_matrices_from_seed, types, ComputeOptions};
+
+    fn options(max_memory_bytes: u64) -> ComputeOptions {
+        ComputeOptions { max_memory_bytes: Some(max_memory_bytes), ..Default::default() }
+    }
+
+    fn input(precision: &str) -> types::Input {
+        let (matrix_a, matrix_b) = generate_matrices_from_seed(b"memory", 16, 64, 64, 16);
+        types::Input::matmul(matrix_a, matrix_b, precision)
+    }
+
+    #[test]
+    fn test_analytic_rejection() {
+        for precision in crate::tests::precisions() {
+            let estimate = estimate_workload_bytes(precision, (16, 64), (64, 16));
+            assert_eq!(estimate.inputs, (16 * 64 + 64 * 16) * 4);
+            assert_eq!(estimate.serialization, 16 * 16 * SERIALIZED_BYTES_PER_ELEMENT);
+
+            let err = compute_workload_with_options(input(precision), &options(estimate.total() - 1)).unwrap_err();
+            assert!(matches!(err, SolverError::MemoryLimitExceeded(_)), "{}", err);
+            assert!(err.to_string().contains(&format!("needs an estimated {} bytes", estimate.total())), "{}", err);
+
+            // Generous ceilings leave the result untouched
+            let limited = compute_workload_with_options(input(precision), &options(1 << 30)).unwrap();
+            let free = compute_workload_with_options(input(precision), &ComputeOptions::default()).unwrap();
+            assert_eq!(limited.result_hash, free.result_hash);
+        }
+    }
+
+    #[test]
+    fn test_parse_size() {
+        assert_eq!(parse_size("4096").unwrap(), 4096);
+        assert_eq!(parse_size("512M").unwrap(), 512 << 20);
+        assert_eq!(parse_size("2g").unwrap(), 2 << 30);
+        assert!(parse_size("lots").is_err());
+        assert!(parse_size("99999999999G").is_err());
+    }
+
+    #[cfg(feature = "counting-alloc")]
+    #[test]
+    fn test_runtime_trip() {
+        let guard = MemoryGuard::start(1 << 20, 512 << 10);
+        let small = std::hint::black_box(vec![0u8; 256 << 10]);
+        guard.check("small").unwrap();
+        drop(small);
+
+        // The peak counts even after the buffer is gone
+        let big = std::hint::black_box(vec![1u8; 600 << 10]);
+        drop(big);
+        let err = guard.check("big").unwrap_err();
+        assert!(matches!(err, SolverError::MemoryLimitExceeded(_)), "{}", err);
+        assert!(err.to_string().contains("big used"), "{}", err);
+    }
+
+    #[cfg(feature = "counting-alloc")]
+    #[test]
+    fn test_request_memory_follows_handoffs() {
+        let request = RequestMemory::new();
+        let outside = RequestMemory::new();
+        {
+            let _scope = request.enter();
+            let parsed = std::hint::black_box(vec![0u8; 1 << 20]);
+            assert!(Arc::ptr_eq(&RequestMemory::current().unwrap(), &request));
+            // Nested scopes charge the inner request and restore the outer one
+            {
+                let _inner = outside.enter();
+                std::hint::black_box(vec![0u8; 4 << 20]);
+            }
+            assert!(Arc::ptr_eq(&RequestMemory::current().unwrap(), &request));
+            // The same request entered on another thread, as the blocking pool does
+            let handed = RequestMemory::current().unwrap();
+            std::thread::spawn(move || {
+                let _scope = handed.enter();
+                std::hint::black_box(vec![0u8; 2 << 20]);
+            })
+            .join()
+            .unwrap();
+            drop(parsed);
+        }
+        assert!(RequestMemory::current().is_none());
+        std::hint::black_box(vec![0u8; 8 << 20]);
+
+        let peak = request.peak_bytes().unwrap();
+        // The 1 MiB parse was still held when the other thread allocated 2 MiB; the nested
+        // and unscoped buffers are not the request's
+        assert!((3 << 20..4 << 20).contains(&peak), "request peak {} bytes", peak);
+        let outside_peak = outside.peak_bytes().unwrap();
+        assert!((4 << 20..5 << 20).contains(&outside_peak), "nested peak {} bytes", outside_peak);
+    }
+
+    #[test]
+    fn test_request_memory_summary() {
+        let stats = RequestMemoryStats::default();
+        let empty = stats.summary();
+        assert_eq!((empty.requests, empty.p50_mb, empty.max_mb), (0, None, None));
+
+        for mb in 1..=100u64 {
+            stats.record(mb << 20, mb > 95);
+        }
+        let summary = stats.summary();
+        assert_eq!((summary.requests, summary.over_soft_limit), (100, 5));
+        assert_eq!((summary.p50_mb, summary.p90_mb, summary.p99_mb), (Some(50.0), Some(90.0), Some(99.0)));
+        assert_eq!(summary.max_mb, Some(100.0));
+
+        // Percentiles cover the recent window; the maximum and counts cover everything
+        for _ in 0..REQUEST_MEMORY_WINDOW {
+            stats.record(1 << 20, false);
+        }
+        let summary = stats.summary();
+        assert_eq!((summary.requests, summary.p99_mb, summary.max_mb), (100 + REQUEST_MEMORY_WINDOW as u64, Some(1.0), Some(100.0)));
+    }
+}
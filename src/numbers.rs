@@ -0,0 +1,491 @@
+//! Detection of JSON numbers that do not survive conversion to f32.
+//!
+//! The FlatMatrix deserializer reads every element as the JSON token's own type
+//! (u64, i64 or f64) and classifies the conversion. Values that overflow to infinity or
+//! integers beyond 2^24 that f32 cannot represent exactly are reported to the active
+//! collector (if any); parsing itself never changes behavior. Callers then either count
+//! the losses (`lossy_parse_count`) or reject them in strict mode via `check_strict`.
+//!
+//! The same deserializer enforces `MatrixLimits` while rows arrive, when a caller runs the
+//! parse under `with_limits` (the API does). A million one-element rows or one row of a
+//! hundred million elements is refused at the first row or element past the cap, before
+//! it is allocated, and the `LimitViolation` says which matrix and cap it was.
+//!
+//! Under `with_row_format` it also accepts rows written as objects keyed by column index
+//! (`{"0": 1.5, "1": 2.0}`), filling the columns a row leaves off with 0.0 or refusing
+//! them (`InputRowFormat`). Outside it only arrays are rows.
+
+use crate::{FlatMatrix, SolverError};
+use serde::de::{self, Deserialize, Deserializer, Visitor};
+use std::cell::{Cell, RefCell};
+use std::fmt;
+
+/// Largest magnitude below which every integer is exactly representable in f32
+const F32_EXACT_INT_LIMIT: f64 = 16_777_216.0; // 2^24
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LossKind {
+    /// Finite JSON value that becomes ±inf as f32
+    Overflow,
+    /// Integer-valued token above 2^24 that rounds to a different f32
+    IntegerPrecision,
+}
+
+/// One element whose f32 value differs from what the producer wrote
+#[derive(Debug, Clone, PartialEq)]
+pub struct LossyValue {
+    /// Field the matrix came from ("matrix_a", "matrix_b") or "matrix" if unknown
+    pub matrix: &'static str,
+    pub row: usize,
+    pub col: usize,
+    /// The parsed JSON token, printed back
+    pub token: String,
+    pub kind: LossKind,
+}
+
+impl fmt::Display for LossyValue {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let what = match self.kind {
+            LossKind::Overflow => "is outside the finite f32 range",
+            LossKind::IntegerPrecision => "is an integer beyond 2^24 that f32 cannot represent exactly",
+        };
+        write!(f, "{}[{}][{}] = {} {}", self.matrix, self.row, self.col, self.token, what)
+    }
+}
+
+#[derive(Default)]
+struct Collector {
+    label: Option<&'static str>,
+    issues: Vec<LossyValue>,
+}
+
+thread_local! {
+    static COLLECTOR: RefCell<Option<Collector>> = const { RefCell::new(None) };
+}
+
+/// Run `f` (typically a serde_json parse) and return every lossy element it produced
+pub fn collect_lossy<T>(f: impl FnOnce() -> T) -> (T, Vec<LossyValue>) {
+    let previous = COLLECTOR.with(|c| c.borrow_mut().replace(Collector::default()));
+    let out = f();
+    let collected = COLLECTOR.with(|c| std::mem::replace(&mut *c.borrow_mut(), previous));
+    (out, collected.map(|c| c.issues).unwrap_or_default())
+}
+
+fn with_label<T>(label: &'static str, f: impl FnOnce() -> T) -> T {
+    let previous = COLLECTOR.with(|c| c.borrow_mut().as_mut().and_then(|c| c.label.replace(label)));
+    let out = f();
+    COLLECTOR.with(|c| {
+        if let Some(c) = c.borrow_mut().as_mut() {
+            c.label = previous;
+        }
+    });
+    out
+}
+
+fn record(row: usize, col: usize, token: String, kind: LossKind) {
+    COLLECTOR.with(|c| {
+        if let Some(c) = c.borrow_mut().as_mut() {
+            let matrix = c.label.unwrap_or("matrix");
+            c.issues.push(LossyValue { matrix, row, col, token, kind });
+        }
+    });
+}
+
+/// Caps on a matrix as it is parsed, checked row by row and element by element
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct MatrixLimits {
+    pub max_rows: usize,
+    pub max_cols: usize,
+    pub max_elements: usize,
+}
+
+impl MatrixLimits {
+    /// No caps; what parses outside `with_limits` get
+    pub const UNLIMITED: MatrixLimits = MatrixLimits { max_rows: usize::MAX, max_cols: usize::MAX, max_elements: usize::MAX };
+
+    /// The violation that would stop parsing a `rows`×`cols` matrix, for requests that give
+    /// only its shape
+    pub fn check_shape(&self, matrix: &'static str, (rows, cols): (usize, usize)) -> Result<(), LimitViolation> {
+        // Where the parse stops, as (row, elements read in it): a refused row before its
+        // first element, a refused element after those before it. At the same element the
+        // column cap is checked first.
+        let mut stops = Vec::new();
+        if rows > self.max_rows {
+            stops.push(((self.max_rows, 0), MatrixLimit::MaxRows, self.max_rows));
+        }
+        if rows > 0 && cols > self.max_cols {
+            stops.push(((0, self.max_cols + 1), MatrixLimit::MaxCols, self.max_cols));
+        }
+        if rows.saturating_mul(cols) > self.max_elements {
+            let e = self.max_elements;
+            stops.push(((e / cols, e % cols + 1), MatrixLimit::MaxElements, self.max_elements));
+        }
+        match stops.into_iter().min_by_key(|&(at, _, _)| at) {
+            Some(((row, _), limit, max)) => Err(LimitViolation { matrix, limit, max, row }),
+            None => Ok(()),
+        }
+    }
+}
+
+impl Default for MatrixLimits {
+    /// The API's caps: 2^18 rows or columns, 2^24 elements per matrix
+    fn default() -> Self {
+        Self { max_rows: 1 << 18, max_cols: 1 << 18, max_elements: 1 << 24 }
+    }
+}
+
+/// Which of the `MatrixLimits` a matrix exceeded
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, serde::Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum MatrixLimit {
+    MaxRows,
+    MaxCols,
+    MaxElements,
+}
+
+impl MatrixLimit {
+    pub const ALL: [MatrixLimit; 3] = [MatrixLimit::MaxRows, MatrixLimit::MaxCols, MatrixLimit::MaxElements];
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            MatrixLimit::MaxRows => "max_rows",
+            MatrixLimit::MaxCols => "max_cols",
+            MatrixLimit::MaxElements => "max_elements",
+        }
+    }
+}
+
+/// Where parsing stopped: the first row (or element of `row`) past a cap
+#[derive(Debug, Clone, PartialEq, Eq, serde::Serialize)]
+pub struct LimitViolation {
+    /// Field the matrix came from ("matrix_a", "matrix_b") or "matrix" if unknown
+    pub matrix: &'static str,
+    pub limit: MatrixLimit,
+    pub max: usize,
+    /// Row being read when the cap was hit
+    pub row: usize,
+}
+
+impl fmt::Display for LimitViolation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} exceeds {} = {} at row {}", self.matrix, self.limit.as_str(), self.max, self.row)
+    }
+}
+
+/// How a matrix row may be written in JSON
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum InputRowFormat {
+    /// `[1.5, 2.0]` only
+    #[default]
+    Array,
+    /// Arrays, or objects keyed by column index: keys must run 0, 1, 2, ... in order and the
+    /// columns after the last key are 0.0. The first row sets the width.
+    Map,
+    /// As `Map`, but every row must give every column
+    MapStrict,
+}
+
+impl InputRowFormat {
+    pub fn parse(s: &str) -> Result<Self, SolverError> {
+        match s {
+            "array" => Ok(InputRowFormat::Array),
+            "map" => Ok(InputRowFormat::Map),
+            "map_strict" => Ok(InputRowFormat::MapStrict),
+            _ => Err(SolverError::InvalidInput(format!("Unknown input row format: {} (expected array, map or map_strict)", s))),
+        }
+    }
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            InputRowFormat::Array => "array",
+            InputRowFormat::Map => "map",
+            InputRowFormat::MapStrict => "map_strict",
+        }
+    }
+}
+
+thread_local! {
+    static LIMITS: Cell<MatrixLimits> = const { Cell::new(MatrixLimits::UNLIMITED) };
+    static VIOLATION: RefCell<Option<LimitViolation>> = const { RefCell::new(None) };
+    static ROW_FORMAT: Cell<InputRowFormat> = const { Cell::new(InputRowFormat::Array) };
+}
+
+/// Run `f` (typically a serde_json parse) with `limits` on every FlatMatrix it reads,
+/// returning the violation that aborted it, if any
+pub fn with_limits<T>(limits: MatrixLimits, f: impl FnOnce() -> T) -> (T, Option<LimitViolation>) {
+    let previous = LIMITS.with(|l| l.replace(limits));
+    let previous_violation = VIOLATION.with(|v| v.borrow_mut().take());
+    let out = f();
+    LIMITS.with(|l| l.set(previous));
+    let violation = VIOLATION.with(|v| std::mem::replace(&mut *v.borrow_mut(), previous_violation));
+    (out, violation)
+}
+
+/// Limits in force for the matrix about to be read
+pub(crate) fn active_limits() -> MatrixLimits {
+    LIMITS.with(Cell::get)
+}
+
+/// Run `f` (typically a serde_json parse) reading every FlatMatrix row as `format` allows
+pub fn with_row_format<T>(format: InputRowFormat, f: impl FnOnce() -> T) -> T {
+    let previous = ROW_FORMAT.with(|r| r.replace(format));
+    let out = f();
+    ROW_FORMAT.with(|r| r.set(previous));
+    out
+}
+
+/// Row format in force for the matrix about to be read
+pub(crate) fn active_row_format() -> InputRowFormat {
+    ROW_FORMAT.with(Cell::get)
+}
+
+/// Record the violation and turn it into the error that aborts the parse
+pub(crate) fn limit_exceeded<E: de::Error>(limit: MatrixLimit, max: usize, row: usize) -> E {
+    let matrix = COLLECTOR.with(|c| c.borrow().as_ref().and_then(|c| c.label)).unwrap_or("matrix");
+    let violation = LimitViolation { matrix, limit, max, row };
+    let error = E::custom(&violation);
+    VIOLATION.with(|v| *v.borrow_mut() = Some(violation));
+    error
+}
+
+/// A JSON number as written, before conversion to f32
+#[derive(Debug, Clone, Copy)]
+pub(crate) enum JsonNumber {
+    U64(u64),
+    I64(i64),
+    F64(f64),
+}
+
+impl JsonNumber {
+    /// Convert to f32 exactly like a plain `f32` deserialize would, reporting losses
+    pub(crate) fn to_f32(self, row: usize, col: usize) -> f32 {
+        let (value, integral) = match self {
+            JsonNumber::U64(n) => (n as f64, true),
+            JsonNumber::I64(n) => (n as f64, true),
+            JsonNumber::F64(v) => (v, v.fract() == 0.0),
+        };
+        let converted = match self {
+            JsonNumber::U64(n) => n as f32,
+            JsonNumber::I64(n) => n as f32,
+            JsonNumber::F64(v) => v as f32,
+        };
+        // The token is only formatted for a loss, not for every element
+        if value.is_finite() && !converted.is_finite() {
+            record(row, col, self.token(), LossKind::Overflow);
+        } else if integral && value.abs() > F32_EXACT_INT_LIMIT && !exact(self, converted) {
+            record(row, col, self.token(), LossKind::IntegerPrecision);
+        }
+        converted
+    }
+
+    /// The number as reported in a loss
+    fn token(self) -> String {
+        match self {
+            JsonNumber::U64(n) => n.to_string(),
+            JsonNumber::I64(n) => n.to_string(),
+            JsonNumber::F64(v) => format!("{:?}", v),
+        }
+    }
+}
+
+fn exact(number: JsonNumber, converted: f32) -> bool {
+    match number {
+        JsonNumber::U64(n) => converted as f64 == n as f64 && converted as u64 == n,
+        JsonNumber::I64(n) => converted as f64 == n as f64 && converted as i64 == n,
+        JsonNumber::F64(v) => converted as f64 == v,
+    }
+}
+
+impl<'de> Deserialize<'de> for JsonNumber {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        struct NumberVisitor;
+
+        impl Visitor<'_> for NumberVisitor {
+            type Value = JsonNumber;
+
+            fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+                f.write_str("a number")
+            }
+
+            fn visit_u64<E: de::Error>(self, v: u64) -> Result<JsonNumber, E> {
+                Ok(JsonNumber::U64(v))
+            }
+
+            fn visit_i64<E: de::Error>(self, v: i64) -> Result<JsonNumber, E> {
+                Ok(JsonNumber::I64(v))
+            }
+
+            fn visit_f64<E: de::Error>(self, v: f64) -> Result<JsonNumber, E> {
+                Ok(JsonNumber::F64(v))
+            }
+        }
+
+        deserializer.deserialize_any(NumberVisitor)
+    }
+}
+
+/// Precisions whose inputs are integers, where a silently rounded integer changes the
+/// result rather than just perturbing it
+pub fn is_integer_precision(precision: &str) -> bool {
+    matches!(crate::normalize_name(precision).as_str(), "int8" | "int8_rowwise" | "int8_asym" | "int4" | "u8i8")
+}
+
+/// Strict mode: reject overflowing values always, and integer precision loss when the
+/// precision is integer-oriented. Reports the first offending element.
+pub fn check_strict(issues: &[LossyValue], precision: &str) -> Result<(), SolverError> {
+    let fatal = issues.iter().find(|issue| match issue.kind {
+        LossKind::Overflow => true,
+        LossKind::IntegerPrecision => is_integer_precision(precision),
+    });
+    match fatal {
+        Some(issue) => Err(SolverError::InvalidInput(format!("Strict number parsing: {}", issue))),
+        None => Ok(()),
+    }
+}
+
+pub(crate) fn matrix_a<'de, D: Deserializer<'de>>(d: D) -> Result<FlatMatrix, D::Error> {
+    with_label("matrix_a", || FlatMatrix::deserialize(d))
+}
+
+pub(crate) fn matrix_b<'de, D: Deserializer<'de>>(d: D) -> Result<FlatMatrix, D::Error> {
+    with_label("matrix_b", || FlatMatrix::deserialize(d))
+}
+
+#[cfg(feature = "api")]
+pub(crate) fn optional_matrix_a<'de, D: Deserializer<'de>>(d: D) -> Result<Option<FlatMatrix>, D::Error> {
+    with_label("matrix_a", || Option::<FlatMatrix>::deserialize(d))
+}
+
+#[cfg(feature = "api")]
+pub(crate) fn optional_matrix_b<'de, D: Deserializer<'de>>(d: D) -> Result<Option<FlatMatrix>, D::Error> {
+    with_label("matrix_b", || Option::<FlatMatrix>::deserialize(d))
+}
+
+pub(crate) fn optional_matrix_c<'de, D: Deserializer<'de>>(d: D) -> Result<Option<FlatMatrix>, D::Error> {
+    with_label("matrix_c", || Option::<FlatMatrix>::deserialize(d))
+}
+
+/// Parse an Input document, optionally in strict mode.
+/// Returns the input and the number of lossy elements that were accepted.
+pub fn parse_input_json(json: &str, strict: bool) -> Result<(crate::types::Input, usize), SolverError> {
+    let (parsed, issues) = collect_lossy(|| serde_json::from_str::<crate::types::Input>(json));
+    let input = parsed.map_err(|e| SolverError::InvalidInput(format!("Invalid input JSON: {}", e)))?;
+    if strict {
+        check_strict(&issues, &input.precision)?;
+    }
+    Ok((input, issues.len()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn doc(a: &str, precision: &str) -> String {
+        format!(r#"{{"matrix_a": {}, "matrix_b": [[1.0], [2.0]], "precision": "{}"}}"#, a, precision)
+    }
+
+    #[test]
+    fn test_overflow_to_inf() {
+        let (input, lossy) = parse_input_json(&doc("[[1e40, 2.0]]", "fp32"), false).unwrap();
+        assert_eq!(input.matrix_a.data[0], f32::INFINITY);
+        assert_eq!(lossy, 1);
+
+        let err = parse_input_json(&doc("[[3.0, -1e40]]", "fp32"), true).unwrap_err();
+        let msg = err.to_string();
+        assert!(msg.contains("matrix_a[0][1]") && msg.contains("-1e40"), "{}", msg);
+    }
+
+    #[test]
+    fn test_big_integer_precision_loss() {
+        let json = doc("[[12345678901234, 16777216]]", "u8i8");
+        let (input, lossy) = parse_input_json(&json, false).unwrap();
+        assert_eq!(input.matrix_a.data[0], 12345678901234u64 as f32);
+        // 2^24 itself is exact and not counted
+        assert_eq!(lossy, 1);
+
+        let err = parse_input_json(&json, true).unwrap_err().to_string();
+        assert!(err.contains("matrix_a[0][0] = 12345678901234"), "{}", err);
+
+        // Float precisions only count integer loss, even in strict mode
+        let (_, lossy) = parse_input_json(&doc("[[12345678901234, 1.5]]", "fp32"), true).unwrap();
+        assert_eq!(lossy, 1);
+    }
+
+    #[test]
+    fn test_limits_stop_the_parse() {
+        let limits = MatrixLimits { max_rows: 2, max_cols: 3, max_elements: 4 };
+        let parse = |a: &str| with_limits(limits, || collect_lossy(|| serde_json::from_str::<crate::types::Input>(&doc(a, "fp32"))));
+
+        let ((parsed, _), violation) = parse("[[1, 2], [3, 4]]");
+        assert_eq!(parsed.unwrap().matrix_a.data, [1.0, 2.0, 3.0, 4.0]);
+        assert_eq!(violation, None);
+        for (a, limit, row) in [
+            ("[[1], [2], [3]]", MatrixLimit::MaxRows, 2),
+            ("[[1, 2, 3, 4]]", MatrixLimit::MaxCols, 0),
+            ("[[1, 2, 3], [4, 5, 6]]", MatrixLimit::MaxElements, 1),
+        ] {
+            let ((parsed, _), violation) = parse(a);
+            let violation = violation.unwrap();
+            assert_eq!((violation.matrix, violation.limit, violation.row), ("matrix_a", limit, row), "{}", a);
+            assert!(parsed.unwrap_err().to_string().contains(&violation.to_string()));
+        }
+
+        // Outside with_limits nothing is capped
+        assert!(parse_input_json(&doc("[[1], [2], [3]]", "fp32"), false).is_ok());
+    }
+
+    #[test]
+    fn test_check_shape_matches_the_parse() {
+        for limits in [MatrixLimits { max_rows: 2, max_cols: 3, max_elements: 4 }, MatrixLimits { max_rows: 5, max_cols: 2, max_elements: 7 }] {
+            for (rows, cols) in (0..7).flat_map(|rows| (0..6).map(move |cols| (rows, cols))) {
+                let a = format!("[{}]", vec![format!("[{}]", vec!["1"; cols].join(", ")); rows].join(", "));
+                let (_, parsed) = with_limits(limits, || collect_lossy(|| serde_json::from_str::<crate::types::Input>(&doc(&a, "fp32"))));
+                assert_eq!(limits.check_shape("matrix_a", (rows, cols)).err(), parsed, "{:?} {}x{}", limits, rows, cols);
+            }
+        }
+    }
+
+    #[test]
+    fn test_map_rows_match_array_rows() {
+        let json = |a: &str, b: &str| format!(r#"{{"matrix_a": {}, "matrix_b": {}, "precision": "fp32"}}"#, a, b);
+        let parse = |format, doc: &str| with_row_format(format, || parse_input_json(doc, false));
+        let hash = |doc: &str, format| crate::compute_workload(parse(format, doc).unwrap().0).unwrap().result_hash;
+        let arrays = hash(&json("[[1.5, 2.0, 0.0], [0.0, -3.0, 4.25]]", "[[1, 2], [3, 4], [5, 6]]"), InputRowFormat::Array);
+
+        // Objects, or a mix of objects and arrays
+        let maps = json(r#"[{"0": 1.5, "1": 2.0, "2": 0.0}, {"0": 0, "1": -3.0, "2": 4.25}]"#, r#"[{"0": 1, "1": 2}, [3, 4], {"0": 5, "1": 6}]"#);
+        for format in [InputRowFormat::Map, InputRowFormat::MapStrict] {
+            assert_eq!(hash(&maps, format), arrays);
+        }
+        // Arrays are the only rows by default
+        assert!(parse(InputRowFormat::Array, &maps).unwrap_err().to_string().contains("expected an array of numbers"));
+
+        // A row leaving off its last column: 0.0 when filling, refused under map_strict
+        let missing = json(r#"[{"0": 1.5, "1": 2.0, "2": 0.0}, {"0": 0, "1": -3.0}]"#, "[[1, 2], [3, 4], [5, 6]]");
+        let filled = json("[[1.5, 2.0, 0.0], [0.0, -3.0, 0.0]]", "[[1, 2], [3, 4], [5, 6]]");
+        assert_eq!(hash(&missing, InputRowFormat::Map), hash(&filled, InputRowFormat::Array));
+        let err = parse(InputRowFormat::MapStrict, &missing).unwrap_err().to_string();
+        assert!(err.contains("Row 1: missing column 2 of 3"), "{}", err);
+
+        // Keys that are not indices, skip a column, or run past the width, named by row
+        for (a, expected) in [
+            (r#"[{"0": 1, "x": 2}]"#, r#"Row 0: key "x" is not a column index"#),
+            (r#"[[1, 2], {"0": 1, "01": 2}]"#, r#"Row 1: key "01" is not a column index"#),
+            (r#"[[1, 2], {"1": 2, "0": 1}]"#, "Row 1: column 1 where column 0 was expected"),
+            (r#"[{"0": 1, "2": 2}]"#, "Row 0: column 2 where column 1 was expected"),
+            (r#"[[1, 2], {"0": 1, "1": 2, "2": 3}]"#, "Row 1: column 2 is out of range for 2 columns"),
+        ] {
+            let err = parse(InputRowFormat::Map, &json(a, "[[1], [2]]")).unwrap_err().to_string();
+            assert!(err.contains(expected), "{}: {}", a, err);
+        }
+    }
+
+    #[test]
+    fn test_lenient_parse_matches_plain_f32() {
+        let json = doc("[[0.1, -7, 1e-50, 16777217]]", "fp32");
+        let (input, _) = parse_input_json(&json, false).unwrap();
+        let plain: Vec<Vec<f32>> = serde_json::from_str("[[0.1, -7, 1e-50, 16777217]]").unwrap();
+        assert_eq!(input.matrix_a.data, plain[0]);
+        assert!(parse_input_json(&doc("[[1.0], [2.0, 3.0]]", "fp32"), false).is_err());
+    }
+}
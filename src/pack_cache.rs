@@ -0,0 +1,296 @@
+//! On-disk persistence for packed B buffers.
+//!
+//! The CLI is typically invoked once per nonce, so the in-memory packed-B caches never
+//! survive between runs. When a cache directory is configured, packed i8 buffers are
+//! written next to a small header and re-read on the next invocation with the same B.
+//!
+//! Files are wrapped in the solver's artifact envelope (`envelope::Format::PackedB`);
+//! the payload is laid out as (all integers little-endian):
+//!
+//! ```text
+//! kind      u8       PackKind tag
+//! k         u64      reduction length (rows of B)
+//! scale     f32      quantization scale (1.0 for u8i8)
+//! digest    32 bytes blake3 of B (shape + f32le data)
+//! len       u64      packed data length in bytes
+//! data      len bytes packed i8 data
+//! checksum  32 bytes blake3 of data
+//! ```
+//!
+//! A version bump, digest mismatch, or checksum failure is treated as a miss and the
+//! entry is regenerated.
+
+use crate::envelope::{self, Format};
+use crate::{AlignedBufferI8, FlatMatrix, SolverError};
+use crate::host;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+
+/// Bump whenever the packed layout or header changes
+pub const PACK_FORMAT_VERSION: u16 = 2;
+const PAYLOAD_HEADER_LEN: usize = 1 + 8 + 4 + 32 + 8;
+
+/// Which packing routine produced a buffer
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PackKind {
+    /// u8i8 16x16 kernel: B cast to i8, row-major k×16
+    U8I8RowMajor,
+    /// int8 16x16 kernel: B quantized to i8 and transposed to 16×k
+    Int8Transposed,
+}
+
+impl PackKind {
+    fn tag(&self) -> u8 {
+        match self {
+            PackKind::U8I8RowMajor => 1,
+            PackKind::Int8Transposed => 2,
+        }
+    }
+
+    fn from_tag(tag: u8) -> Option<PackKind> {
+        match tag {
+            1 => Some(PackKind::U8I8RowMajor),
+            2 => Some(PackKind::Int8Transposed),
+            _ => None,
+        }
+    }
+
+    fn file_prefix(&self) -> &'static str {
+        match self {
+            PackKind::U8I8RowMajor => "u8i8",
+            PackKind::Int8Transposed => "int8t",
+        }
+    }
+}
+
+/// Content digest of B used to key cache files
+pub fn matrix_digest(b: &FlatMatrix) -> [u8; 32] {
+    let mut hasher = blake3::Hasher::new();
+    hasher.update(&(b.rows as u64).to_le_bytes());
+    hasher.update(&(b.cols as u64).to_le_bytes());
+    for &val in &b.data {
+        hasher.update(&val.to_le_bytes());
+    }
+    *hasher.finalize().as_bytes()
+}
+
+fn entry_path(dir: &Path, kind: PackKind, digest: &[u8; 32]) -> PathBuf {
+    dir.join(format!("{}-{}.pack", kind.file_prefix(), hex::encode(digest)))
+}
+
+/// The fixed-size fields at the start of a payload
+struct PayloadHeader {
+    kind_tag: u8,
+    k: usize,
+    scale: f32,
+    digest: [u8; 32],
+    len: usize,
+}
+
+fn parse_payload_header(payload: &[u8]) -> Option<PayloadHeader> {
+    if payload.len() < PAYLOAD_HEADER_LEN {
+        return None;
+    }
+    Some(PayloadHeader {
+        kind_tag: payload[0],
+        k: u64::from_le_bytes(payload[1..9].try_into().ok()?) as usize,
+        scale: f32::from_le_bytes(payload[9..13].try_into().ok()?),
+        digest: payload[13..45].try_into().ok()?,
+        len: u64::from_le_bytes(payload[45..53].try_into().ok()?) as usize,
+    })
+}
+
+/// Header fields of a packed-B payload, for `envelope::inspect`
+pub(crate) fn describe(payload: &[u8], version: u16) -> Vec<(&'static str, String)> {
+    if version != PACK_FORMAT_VERSION {
+        return vec![("note", format!("this solver reads v{}; payload not described", PACK_FORMAT_VERSION))];
+    }
+    let Some(header) = parse_payload_header(payload) else {
+        return vec![("note", format!("payload shorter than its {}-byte header", PAYLOAD_HEADER_LEN))];
+    };
+    let kind = match PackKind::from_tag(header.kind_tag) {
+        Some(kind) => kind.file_prefix().to_string(),
+        None => format!("unknown ({})", header.kind_tag),
+    };
+    let data_end = PAYLOAD_HEADER_LEN + header.len;
+    let checksum = match payload.get(PAYLOAD_HEADER_LEN..data_end).zip(payload.get(data_end..)) {
+        Some((data, stored)) if blake3::hash(data).as_bytes() == stored => "ok".to_string(),
+        Some(_) => "MISMATCH".to_string(),
+        None => "missing (truncated payload)".to_string(),
+    };
+    vec![
+        ("kind", kind),
+        ("k", header.k.to_string()),
+        ("scale", header.scale.to_string()),
+        ("digest", hex::encode(header.digest)),
+        ("data", format!("{} bytes", header.len)),
+        ("checksum", checksum),
+    ]
+}
+
+/// Decode a cache file, failing with the reason it cannot serve `kind` for this B
+fn decode_entry(bytes: &[u8], kind: PackKind, digest: &[u8; 32], k: usize) -> Result<(AlignedBufferI8, f32), SolverError> {
+    let invalid = |msg: String| SolverError::InvalidInput(msg);
+    let payload = envelope::open(bytes, Format::PackedB, PACK_FORMAT_VERSION)?;
+    let header = parse_payload_header(payload).ok_or_else(|| invalid("packed-B cache payload is truncated".to_string()))?;
+    if header.kind_tag != kind.tag() {
+        let found = PackKind::from_tag(header.kind_tag).map_or("an unknown kind", |found| found.file_prefix());
+        return Err(invalid(format!("packed-B cache holds {} data, expected {}", found, kind.file_prefix())));
+    }
+    if header.k != k || &header.digest != digest {
+        return Err(invalid("packed-B cache entry was packed from a different B".to_string()));
+    }
+    if header.len != 16 * k || payload.len() != PAYLOAD_HEADER_LEN + header.len + 32 {
+        return Err(invalid(format!("packed-B cache entry has {} data bytes, expected {}", header.len, 16 * k)));
+    }
+    let data = &payload[PAYLOAD_HEADER_LEN..PAYLOAD_HEADER_LEN + header.len];
+    if blake3::hash(data).as_bytes() != &payload[PAYLOAD_HEADER_LEN + header.len..] {
+        return Err(invalid("packed-B cache checksum mismatch".to_string()));
+    }
+
+    let mut buf = AlignedBufferI8::new(header.len, 64);
+    for (dst, &src) in buf.as_mut_slice().iter_mut().zip(data) {
+        *dst = src as i8;
+    }
+    Ok((buf, header.scale))
+}
+
+/// Read a cache entry, returning None on any mismatch or corruption
+fn read_entry(path: &Path, kind: PackKind, digest: &[u8; 32], k: usize) -> Option<(AlignedBufferI8, f32)> {
+    let bytes = host::read(path).ok()?;
+    decode_entry(&bytes, kind, digest, k).ok()
+}
+
+/// Write a cache entry via a temp file + rename so readers never see a partial file
+fn write_entry(path: &Path, kind: PackKind, digest: &[u8; 32], k: usize, buf: &AlignedBufferI8, scale: f32) -> std::io::Result<()> {
+    let data: Vec<u8> = buf.as_slice().iter().map(|&x| x as u8).collect();
+    let mut payload = Vec::with_capacity(PAYLOAD_HEADER_LEN + data.len() + 32);
+    payload.push(kind.tag());
+    payload.extend_from_slice(&(k as u64).to_le_bytes());
+    payload.extend_from_slice(&scale.to_le_bytes());
+    payload.extend_from_slice(digest);
+    payload.extend_from_slice(&(data.len() as u64).to_le_bytes());
+    payload.extend_from_slice(&data);
+    payload.extend_from_slice(blake3::hash(&data).as_bytes());
+    let out = envelope::encode(Format::PackedB, PACK_FORMAT_VERSION, &payload);
+
+    let tmp = path.with_extension("pack.tmp");
+    let mut file = host::create(&tmp)?;
+    file.write_all(&out)?;
+    file.sync_all()?;
+    host::rename(&tmp, path)
+}
+
+/// Load a packed B from `dir`, or run `pack` and persist its output.
+///
+/// Returns the buffer, its scale, and whether packing was skipped. IO errors while
+/// writing are ignored: the cache is an optimization, never a correctness dependency.
+pub(crate) fn load_or_pack_i8<F>(dir: &Path, kind: PackKind, b: &FlatMatrix, pack: F) -> (AlignedBufferI8, f32, bool)
+where
+    F: FnOnce() -> (AlignedBufferI8, f32),
+{
+    let k = b.rows;
+    let digest = matrix_digest(b);
+    let path = entry_path(dir, kind, &digest);
+    if let Some((buf, scale)) = read_entry(&path, kind, &digest, k) {
+        return (buf, scale, true);
+    }
+
+    let (buf, scale) = pack();
+    if host::create_dir_all(dir).is_ok() {
+        let _ = write_entry(&path, kind, &digest, k, &buf, scale);
+    }
+    (buf, scale, false)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{compute_workload_with_options, generate_matrices_from_seed, types, ComputeOptions};
+    use std::fs;
+
+    const HEADER_LEN: usize = envelope::HEADER_LEN + PAYLOAD_HEADER_LEN;
+
+    fn seed_input(precision: &str) -> types::Input {
+        let (matrix_a, matrix_b) = generate_matrices_from_seed(b"pack-cache", 16, 512, 512, 16);
+        types::Input::matmul(matrix_a, matrix_b, precision)
+    }
+
+    #[test]
+    fn test_second_run_skips_packing() {
+        let dir = tempfile::tempdir().unwrap();
+        let options = ComputeOptions {
+            pack_cache_dir: Some(dir.path().to_path_buf()),
+            ..Default::default()
+        };
+
+        let first = compute_workload_with_options(seed_input("u8i8"), &options).unwrap();
+        let second = compute_workload_with_options(seed_input("u8i8"), &options).unwrap();
+
+        assert_eq!(first.metrics.pack_cache_hit, Some(false));
+        assert_eq!(second.metrics.pack_cache_hit, Some(true));
+        assert_eq!(first.result_hash, second.result_hash);
+    }
+
+    #[test]
+    fn test_corrupted_entry_is_regenerated() {
+        let dir = tempfile::tempdir().unwrap();
+        let options = ComputeOptions {
+            pack_cache_dir: Some(dir.path().to_path_buf()),
+            ..Default::default()
+        };
+        let expected = compute_workload_with_options(seed_input("u8i8"), &options).unwrap();
+
+        // Flip one payload byte; the checksum must reject the entry
+        let entry = fs::read_dir(dir.path()).unwrap().next().unwrap().unwrap().path();
+        let mut bytes = fs::read(&entry).unwrap();
+        bytes[HEADER_LEN + 3] ^= 0x5a;
+        fs::write(&entry, bytes).unwrap();
+
+        let rerun = compute_workload_with_options(seed_input("u8i8"), &options).unwrap();
+        assert_eq!(rerun.metrics.pack_cache_hit, Some(false));
+        assert_eq!(rerun.result_hash, expected.result_hash);
+
+        let healed = compute_workload_with_options(seed_input("u8i8"), &options).unwrap();
+        assert_eq!(healed.metrics.pack_cache_hit, Some(true));
+    }
+
+    #[test]
+    fn test_entry_is_inspectable_and_rejects_other_files() {
+        let dir = tempfile::tempdir().unwrap();
+        let options = ComputeOptions {
+            pack_cache_dir: Some(dir.path().to_path_buf()),
+            ..Default::default()
+        };
+        let output = compute_workload_with_options(seed_input("u8i8"), &options).unwrap();
+        let entry = fs::read_dir(dir.path()).unwrap().next().unwrap().unwrap().path();
+        let bytes = fs::read(&entry).unwrap();
+
+        let inspection = envelope::inspect(&bytes).unwrap();
+        assert_eq!(inspection.envelope.to_string(), "packed-B cache v2");
+        assert_eq!(inspection.file_len, bytes.len());
+        let field = |name: &str| inspection.fields.iter().find(|(n, _)| *n == name).unwrap().1.clone();
+        assert_eq!((field("kind"), field("k"), field("checksum")), ("u8i8".to_string(), "512".to_string(), "ok".to_string()));
+
+        let b = seed_input("u8i8").matrix_b;
+        let digest = matrix_digest(&b);
+        let error = |bytes: &[u8], kind| decode_entry(bytes, kind, &digest, 512).err().map(|e| e.to_string());
+        assert_eq!(error(&bytes, PackKind::U8I8RowMajor), None);
+        assert_eq!(error(&bytes, PackKind::Int8Transposed).unwrap(), "packed-B cache holds u8i8 data, expected int8t");
+
+        // An Output handed to the cache reader is named, not decoded
+        let json = serde_json::to_vec(&output).unwrap();
+        assert_eq!(error(&json, PackKind::U8I8RowMajor).unwrap(), "this file is a JSON document, expected a packed-B cache v2");
+        assert!(envelope::inspect(&json).is_err());
+
+        // Entries from before the envelope are misses, regenerated in the new format
+        let mut legacy = b"MMPACKB\0".to_vec();
+        legacy.extend_from_slice(&1u32.to_le_bytes());
+        legacy.extend_from_slice(&bytes[envelope::HEADER_LEN..]);
+        assert_eq!(error(&legacy, PackKind::U8I8RowMajor).unwrap(), "this file is a packed-B cache v1, expected a packed-B cache v2");
+        fs::write(&entry, legacy).unwrap();
+        let rerun = compute_workload_with_options(seed_input("u8i8"), &options).unwrap();
+        assert_eq!(rerun.metrics.pack_cache_hit, Some(false));
+        assert_eq!(fs::read(&entry).unwrap(), bytes);
+    }
+}
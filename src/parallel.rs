@@ -0,0 +1,106 @@
+//! Thread pools for kernels that split their output across threads (feature `parallel`).
+//!
+//! A kernel hands `for_each_chunk` its result buffer cut into disjoint chunks (whole row
+//! blocks), so threads never share an output element and no locking is needed; each
+//! element still accumulates in the serial order, so results are bit-identical.
+//! `ComputeOptions::threads` picks the pool: rayon's global one when unset (every core, or
+//! `RAYON_NUM_THREADS`), else one pool of `max_threads` threads, built on first use and
+//! shared by every count. A count is capped at `max_threads` and runs as that many groups
+//! of chunks, so at most that many of the pool's threads work on it.
+
+use rayon::prelude::*;
+use std::sync::OnceLock;
+
+/// Most threads a requested count gets: the machine's available parallelism
+pub(crate) fn max_threads() -> usize {
+    std::thread::available_parallelism().map_or(1, |n| n.get())
+}
+
+fn pool() -> Option<&'static rayon::ThreadPool> {
+    static POOL: OnceLock<Option<rayon::ThreadPool>> = OnceLock::new();
+    POOL.get_or_init(|| rayon::ThreadPoolBuilder::new().num_threads(max_threads()).thread_name(|i| format!("matmul-{}", i)).build().ok())
+        .as_ref()
+}
+
+/// Run `f(index, chunk)` over `data` in chunks of `chunk_len` on the pool `threads` selects.
+/// Returns how many threads could work at once (at most one per chunk, and at most
+/// `max_threads`); 1 when the run was serial because a pool could not be built.
+pub(crate) fn for_each_chunk<T, F>(threads: Option<usize>, data: &mut [T], chunk_len: usize, f: F) -> usize
+where
+    T: Send,
+    F: Fn(usize, &mut [T]) + Sync,
+{
+    let chunk_len = chunk_len.max(1);
+    let chunks = data.len().div_ceil(chunk_len);
+    match threads {
+        None => {
+            data.par_chunks_mut(chunk_len).enumerate().for_each(|(i, chunk)| f(i, chunk));
+            rayon::current_num_threads().min(chunks)
+        }
+        Some(threads) => {
+            let threads = threads.min(max_threads());
+            match (threads > 1).then(pool).flatten() {
+                Some(pool) => in_groups(pool, threads, data, chunk_len, &f),
+                None => {
+                    data.chunks_mut(chunk_len).enumerate().for_each(|(i, chunk)| f(i, chunk));
+                    1
+                }
+            }
+        }
+    }
+}
+
+/// `for_each_chunk` on `pool` with the chunks cut into `threads` contiguous groups of
+/// near-equal size (fewer when there are fewer chunks), one task each. Returns the number
+/// of groups.
+fn in_groups<T, F>(pool: &rayon::ThreadPool, threads: usize, data: &mut [T], chunk_len: usize, f: &F) -> usize
+where
+    T: Send,
+    F: Fn(usize, &mut [T]) + Sync,
+{
+    let chunks = data.len().div_ceil(chunk_len);
+    let groups = threads.min(chunks).max(1);
+    pool.scope(|scope| {
+        let (mut rest, mut first) = (data, 0);
+        for group in 0..groups {
+            let group_chunks = chunks / groups + usize::from(group < chunks % groups);
+            let len = (group_chunks * chunk_len).min(rest.len());
+            let (group_data, tail) = std::mem::take(&mut rest).split_at_mut(len);
+            rest = tail;
+            scope.spawn(move |_| group_data.chunks_mut(chunk_len).enumerate().for_each(|(i, chunk)| f(first + i, chunk)));
+            first += group_chunks;
+        }
+    });
+    groups
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_chunks_are_disjoint_and_complete() {
+        for threads in [None, Some(1), Some(3)] {
+            let mut data = vec![0usize; 103];
+            let used = for_each_chunk(threads, &mut data, 10, |i, chunk| chunk.iter_mut().for_each(|x| *x += i + 1));
+            let expected: Vec<usize> = (0..103).map(|j| j / 10 + 1).collect();
+            assert_eq!(data, expected, "{:?}", threads);
+            assert!((1..=11).contains(&used), "{:?}: {}", threads, used);
+        }
+        assert_eq!(for_each_chunk(Some(3), &mut [0u8; 4], 2, |_, _| ()), 2.min(max_threads()));
+        assert!(for_each_chunk(Some(100_000), &mut [0u8; 4096], 1, |_, _| ()) <= max_threads());
+    }
+
+    #[test]
+    fn test_groups_cover_every_chunk_once() {
+        // A pool of its own, so the split is exercised whatever the host's parallelism
+        let pool = rayon::ThreadPoolBuilder::new().num_threads(3).build().unwrap();
+        for (threads, len, expected_groups) in [(3, 103, 3), (3, 4, 1), (2, 40, 2), (16, 103, 11)] {
+            let mut data = vec![0usize; len];
+            let groups = in_groups(&pool, threads, &mut data, 10, &|i, chunk: &mut [usize]| chunk.iter_mut().for_each(|x| *x += i + 1));
+            let expected: Vec<usize> = (0..len).map(|j| j / 10 + 1).collect();
+            assert_eq!(data, expected, "{} threads over {}", threads, len);
+            assert_eq!(groups, expected_groups, "{} threads over {}", threads, len);
+        }
+    }
+}
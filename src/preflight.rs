@@ -0,0 +1,241 @@
+//! Checking a workload without computing it (`POST /validate`).
+//!
+//! Everything that rejects a workload before a kernel runs reads shapes and options, never
+//! matrix values: the shape constraints (validation.rs), the workload type, the hash dtype
+//! and layouts, alpha/beta/matrix_c, the option combinations, the memory budget and kernel
+//! selection. `check` runs those same functions, in the order computing does, on a
+//! `WorkloadShape` (an Input without its data), and predicts what computing would produce:
+//! the result shape and dtype, the operation count, the memory estimate and the kernel.
+//! Matmuls go further: `plan_matmul` is the one planning step both `check` and
+//! `compute_matmul_once` take, so /validate and /compute cannot drift apart.
+//!
+//! Only what needs the values is left to compute time: a CSR B's index arrays, strict
+//! number parsing, and what the run itself can exceed (deadline, runtime memory guard).
+
+use crate::kernels::{self, MatmulKernel};
+use crate::memory::{self, MemoryEstimate};
+use crate::validation::{self, Shapes};
+use crate::{batched, quantize, sparse, types, ALayout, BLayout, ComputeOptions, Gemm, ResultDtype, SolverError};
+use serde::Serialize;
+use std::sync::Arc;
+
+/// An Input's workload type, precision, options and shapes, without its matrices
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct WorkloadShape {
+    pub workload_type: Option<String>,
+    pub precision: String,
+    pub hash_input: Option<String>,
+    pub shapes: Shapes,
+    pub alpha: Option<f32>,
+    pub beta: Option<f32>,
+    /// matrix_c's rows, cols and elements
+    pub matrix_c: Option<(usize, usize, usize)>,
+    pub batch: Option<usize>,
+}
+
+impl WorkloadShape {
+    pub fn of(input: &types::Input) -> Self {
+        WorkloadShape {
+            workload_type: input.workload_type.clone(),
+            precision: input.precision.clone(),
+            hash_input: input.hash_input.clone(),
+            shapes: Shapes::of(input),
+            alpha: input.alpha,
+            beta: input.beta,
+            matrix_c: input.matrix_c.as_ref().map(|c| (c.rows, c.cols, c.data.len())),
+            batch: input.batch,
+        }
+    }
+}
+
+/// What computing a workload that passed `check` would produce
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct Preflight {
+    pub workload_type: String,
+    pub precision: String,
+    pub result_shape: (usize, usize),
+    pub result_dtype: String,
+    /// Multiply-adds (m·k·n) for matmuls, elements for quantize: what `metrics.ops_per_second`
+    /// counts
+    pub ops: u64,
+    /// The estimate `max_memory_bytes` is checked against
+    pub memory: MemoryEstimate,
+    pub memory_bytes: u64,
+    /// The kernel a matmul would run on (each item's, for batched_matmul)
+    pub kernel: Option<String>,
+}
+
+/// Run the checks `compute_workload_with_options` makes before computing `workload`
+pub fn check(workload: &WorkloadShape, options: &ComputeOptions) -> Result<Preflight, SolverError> {
+    let workload_type = workload.workload_type.as_deref().unwrap_or("matmul");
+    validation::validate_shapes(workload_type, &workload.shapes)?;
+    match workload_type {
+        "matmul" => matmul(workload, options),
+        "quantize" => {
+            let target = quantize::check(workload, options)?;
+            let (rows, cols, _) = workload.shapes.matrix_a;
+            let estimate = memory::estimate_quantize_bytes((rows, cols));
+            Ok(preflight(workload, (rows, cols), target.dtype().as_str(), ops(&[rows, cols]), estimate, None))
+        }
+        "batched_matmul" => {
+            let (batch, result_dtype, b_layout) = batched::check(workload, options)?;
+            let (rows, k, _) = workload.shapes.matrix_a;
+            let item = (rows / batch, k);
+            let b_shape = dense_shape(workload.shapes.matrix_b, b_layout == BLayout::Transposed);
+            crate::check_matmul_options(&workload.precision, result_dtype, item, b_shape, options)?;
+            let (_, kernel) = kernels::resolve_choice(&workload.precision, item, b_shape, options)?;
+            let estimate = memory::estimate_workload_bytes(&workload.precision, (rows, k), b_shape);
+            let ops = ops(&[rows, k, b_shape.1]);
+            Ok(preflight(workload, (rows, b_shape.1), result_dtype.as_str(), ops, estimate, Some(kernel.name())))
+        }
+        _ => Err(crate::unsupported_workload(workload_type)),
+    }
+}
+
+fn matmul(workload: &WorkloadShape, options: &ComputeOptions) -> Result<Preflight, SolverError> {
+    let plan = plan_matmul(workload, options)?;
+    let (a_shape, b_shape) = (plan.a_shape, plan.b_shape);
+    let estimate = memory::estimate_workload_bytes(&workload.precision, a_shape, b_shape);
+    let ops = ops(&[a_shape.0, a_shape.1, b_shape.1]);
+    Ok(preflight(workload, (a_shape.0, b_shape.1), plan.result_dtype.as_str(), ops, estimate, Some(plan.kernel.name())))
+}
+
+/// A matmul as it stands once every check before the kernel has passed
+pub(crate) struct MatmulPlan {
+    pub result_dtype: ResultDtype,
+    pub a_layout: ALayout,
+    pub b_layout: BLayout,
+    /// A and B as the kernel multiplies them, row-major
+    pub a_shape: (usize, usize),
+    pub b_shape: (usize, usize),
+    /// Whether alpha, beta and matrix_c change A·B
+    pub gemm: bool,
+    pub kernel: Arc<dyn MatmulKernel>,
+}
+
+/// The layout, GEMM, option, memory budget and kernel checks of a matmul, in the order
+/// computing makes them. `compute_matmul_once` plans its Input with this before computing,
+/// so /validate and /compute share one path.
+pub(crate) fn plan_matmul(workload: &WorkloadShape, options: &ComputeOptions) -> Result<MatmulPlan, SolverError> {
+    let shapes = &workload.shapes;
+    if shapes.matrix_b_sparse.is_some() {
+        sparse::check_alternative(shapes.matrix_b)?;
+    }
+    let (result_dtype, a_layout, b_layout) = crate::matmul_layouts(
+        &workload.precision,
+        workload.hash_input.as_deref(),
+        shapes.a_layout.as_deref(),
+        shapes.b_layout.as_deref(),
+        shapes.matrix_b_sparse.is_some(),
+    )?;
+    let a_shape = dense_shape(shapes.matrix_a, a_layout == ALayout::Transposed);
+    let b_shape = match shapes.matrix_b_sparse {
+        Some((rows, cols, _)) => (rows, cols),
+        None => dense_shape(shapes.matrix_b, b_layout == BLayout::Transposed),
+    };
+    let (alpha, beta) = (workload.alpha.unwrap_or(1.0), workload.beta.unwrap_or(0.0));
+    let gemm = Gemm::check(&workload.precision, alpha, beta, workload.matrix_c, (a_shape.0, b_shape.1), options)?;
+    crate::check_matmul_options(&workload.precision, result_dtype, a_shape, b_shape, options)?;
+    let (_, kernel) = match shapes.matrix_b_sparse {
+        Some(_) => sparse::resolve_choice(&workload.precision, a_shape, b_shape, options)?,
+        None => kernels::resolve_choice(&workload.precision, a_shape, b_shape, options)?,
+    };
+    Ok(MatmulPlan { result_dtype, a_layout, b_layout, a_shape, b_shape, gemm, kernel })
+}
+
+/// The row-major shape of an operand given as (rows, cols, elements), transposed or not
+fn dense_shape((rows, cols, _): (usize, usize, usize), transposed: bool) -> (usize, usize) {
+    if transposed {
+        (cols, rows)
+    } else {
+        (rows, cols)
+    }
+}
+
+fn ops(extents: &[usize]) -> u64 {
+    extents.iter().fold(1u64, |n, &x| n.saturating_mul(x as u64))
+}
+
+fn preflight(
+    workload: &WorkloadShape,
+    result_shape: (usize, usize),
+    result_dtype: &str,
+    ops: u64,
+    memory: MemoryEstimate,
+    kernel: Option<&str>,
+) -> Preflight {
+    Preflight {
+        workload_type: workload.workload_type.clone().unwrap_or_else(|| "matmul".to_string()),
+        precision: workload.precision.clone(),
+        result_shape,
+        result_dtype: result_dtype.to_string(),
+        ops,
+        memory,
+        memory_bytes: memory.total(),
+        kernel: kernel.map(str::to_string),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{compute_workload_with_options, generate_matrices_from_seed, FlatMatrix};
+
+    fn input(precision: &str, (m, k, n): (usize, usize, usize)) -> types::Input {
+        let (matrix_a, matrix_b) = generate_matrices_from_seed(b"preflight", m, k, k, n);
+        types::Input::matmul(matrix_a, matrix_b, precision)
+    }
+
+    #[test]
+    fn test_check_agrees_with_compute() {
+        let plain = input("fp32", (16, 40, 16));
+        let transposed = |layout: &str| types::Input { matrix_a: crate::transpose(&plain.matrix_a), a_layout: Some(layout.to_string()), ..plain.clone() };
+        let memory_limit = ComputeOptions { max_memory_bytes: Some(1024), ..Default::default() };
+        let cases = vec![
+            (plain.clone(), ComputeOptions::default()),
+            (input("int8", (5, 33, 7)), ComputeOptions::default()),
+            (input("u8i8", (16, 64, 16)), ComputeOptions { accumulator: crate::Accumulator::I64, ..Default::default() }),
+            (transposed("transposed"), ComputeOptions::default()),
+            (transposed("diagonal"), ComputeOptions::default()),
+            (types::Input { alpha: Some(2.0), beta: Some(0.5), matrix_c: Some(FlatMatrix { data: vec![1.0; 256], rows: 16, cols: 16 }), ..plain.clone() }, ComputeOptions::default()),
+            (types::Input { beta: Some(0.5), ..plain.clone() }, ComputeOptions::default()),
+            (types::Input { workload_type: Some("batched_matmul".to_string()), batch: Some(2), ..input("bf16", (8, 12, 5)) }, ComputeOptions::default()),
+            (types::Input { workload_type: Some("batched_matmul".to_string()), batch: Some(3), ..plain.clone() }, ComputeOptions::default()),
+            (types::Input { workload_type: Some("quantize".to_string()), precision: "int8".to_string(), ..plain.clone() }, ComputeOptions::default()),
+            (types::Input { workload_type: Some("quantize".to_string()), precision: "int8".to_string(), ..plain.clone() }, ComputeOptions { tile_trace: true, ..Default::default() }),
+            (types::Input { workload_type: Some("attention".to_string()), ..plain.clone() }, ComputeOptions::default()),
+            (input("fp32", (4, 6, 5)), ComputeOptions { result_columns: Some(vec![1, 5]), ..Default::default() }),
+            (input("fp32", (4, 6, 5)), ComputeOptions { kernel: Some("int8_16x16".to_string()), ..Default::default() }),
+            (input("fp64", (4, 6, 5)), ComputeOptions { hash_rounding: Some(crate::HashRounding::MantissaBits { bits: 10 }), ..Default::default() }),
+            (plain.clone(), memory_limit),
+            (types::Input { matrix_b: input("fp32", (16, 39, 16)).matrix_b, ..plain.clone() }, ComputeOptions::default()),
+        ];
+        for (input, options) in cases {
+            let predicted = check(&WorkloadShape::of(&input), &options);
+            match (compute_workload_with_options(input.clone(), &options), predicted) {
+                (Ok(output), Ok(preflight)) => {
+                    assert_eq!(preflight.result_shape, output.metadata.result_shape, "{:?}", preflight);
+                    assert_eq!(preflight.result_dtype, output.metadata.result_dtype, "{:?}", preflight);
+                    if preflight.workload_type != "quantize" {
+                        assert_eq!(preflight.kernel, output.metadata.kernel, "{:?}", preflight);
+                    }
+                }
+                (Err(err), Err(predicted)) => assert_eq!(predicted.to_string(), err.to_string()),
+                (output, predicted) => panic!("compute gave {:?}, check {:?}", output.map(|o| o.result_hash), predicted),
+            }
+        }
+    }
+
+    #[test]
+    fn test_prediction() {
+        let preflight = check(&WorkloadShape::of(&input("fp32", (16, 40, 24))), &ComputeOptions::default()).unwrap();
+        assert_eq!((preflight.result_shape, preflight.ops), ((16, 24), 16 * 40 * 24));
+        let estimate = memory::estimate_workload_bytes("fp32", (16, 40), (40, 24));
+        assert_eq!((preflight.memory, preflight.memory_bytes), (estimate, estimate.total()));
+
+        // Shapes alone are enough: no matrix is read
+        let workload = WorkloadShape { precision: "int8".to_string(), shapes: Shapes::dense((300, 1000), (1000, 200)), ..Default::default() };
+        let preflight = check(&workload, &ComputeOptions::default()).unwrap();
+        assert_eq!((preflight.result_shape, preflight.result_dtype.as_str(), preflight.ops), ((300, 200), "f32", 300 * 1000 * 200));
+    }
+}
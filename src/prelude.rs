@@ -0,0 +1,20 @@
+//! Supported public surface of the crate.
+//!
+//! `use matmul_solver::prelude::*;` brings in everything a downstream user needs to build
+//! workloads, compute and verify them. Items listed here follow semver: signatures only
+//! change in a breaking release, and removals get a release of `#[deprecated]` first.
+//! Anything else at the crate root is either a building block of these (kernels registry,
+//! hashing details) or slated for removal; internals without any guarantee live in
+//! `internals` behind the `unstable-internals` feature. `public-api.txt` snapshots the
+//! surface so accidental changes fail the test suite.
+
+pub use crate::error::SolverError;
+pub use crate::warnings::SolverWarning;
+pub use crate::types::{Input, InputMetadata, Metrics, Output, OutputMetadata};
+pub use crate::{
+    compute_hash, compute_hash_rounded, compute_workload, compute_workload_with_options, verify_correctness,
+    verify_correctness_with_rounding, verify_with_metadata, ALayout, Accumulator, BLayout, ComputeOptions, FlatMatrix, Fp16Rounding, Fp32Accumulation, Fp32Summation,
+    HashRounding, Precision, Redundancy, ResultDtype,
+};
+#[cfg(any(feature = "seed-gen", test))]
+pub use crate::{generate_matrices_from_seed, generate_matrices_from_seed_hex, verify_u8i8_integer};
@@ -0,0 +1,188 @@
+//! Phase-by-phase profiling of the seed workload.
+//!
+//! The seed path is generation → packing → kernel → hash. Where the time goes varies a
+//! lot between machines, so `profile_seed` times each phase in isolation (reusing
+//! buffers between iterations) and compares the per-phase medians against an
+//! end-to-end run of the same phases.
+
+use crate::clock::{self, Clock};
+use crate::{compute_hash_as, generate_matrices_from_seed_into, pack_operands, FlatMatrix, SolverError};
+use serde::Serialize;
+
+/// Median time of one phase and its share of the end-to-end median
+#[derive(Debug, Clone, Serialize)]
+pub struct PhaseTiming {
+    pub name: String,
+    pub median_ms: f64,
+    pub fraction_of_end_to_end: f64,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct ProfileReport {
+    pub seed: String,
+    pub precision: String,
+    pub matrix_a_shape: (usize, usize),
+    pub matrix_b_shape: (usize, usize),
+    pub iterations: usize,
+    /// generation, packing, kernel, hash (in pipeline order)
+    pub phases: Vec<PhaseTiming>,
+    pub end_to_end_median_ms: f64,
+    /// Sum of the phase medians; close to `end_to_end_median_ms` when phases are independent
+    pub phase_sum_ms: f64,
+    pub result_hash: String,
+}
+
+impl ProfileReport {
+    /// Human-readable table for the console
+    pub fn to_table(&self) -> String {
+        let mut out = format!(
+            "Seed profile: {} {}x{} × {}x{}, {} iterations\n",
+            self.precision,
+            self.matrix_a_shape.0,
+            self.matrix_a_shape.1,
+            self.matrix_b_shape.0,
+            self.matrix_b_shape.1,
+            self.iterations
+        );
+        out.push_str(&format!("{:<12} {:>12} {:>10}\n", "phase", "median ms", "share"));
+        for phase in &self.phases {
+            out.push_str(&format!(
+                "{:<12} {:>12.4} {:>9.1}%\n",
+                phase.name,
+                phase.median_ms,
+                phase.fraction_of_end_to_end * 100.0
+            ));
+        }
+        out.push_str(&format!("{:<12} {:>12.4}\n", "phase sum", self.phase_sum_ms));
+        out.push_str(&format!("{:<12} {:>12.4}\n", "end-to-end", self.end_to_end_median_ms));
+        out
+    }
+}
+
+/// Median of `samples` (sorted in place); mean of the middle two for even lengths
+pub(crate) fn median(samples: &mut [f64]) -> f64 {
+    samples.sort_by(|a, b| a.total_cmp(b));
+    let mid = samples.len() / 2;
+    if samples.len().is_multiple_of(2) {
+        (samples[mid - 1] + samples[mid]) / 2.0
+    } else {
+        samples[mid]
+    }
+}
+
+fn time_ms<T>(clock: &dyn Clock, f: impl FnOnce() -> T) -> (T, f64) {
+    let (out, elapsed) = clock::time(clock, f);
+    (out, elapsed.as_secs_f64() * 1000.0)
+}
+
+/// Profile the seed workload for `16×k × k×16` matrices.
+///
+/// Only precisions with a packing phase (`u8i8`, `int8`) are supported.
+pub fn profile_seed(
+    seed: &[u8],
+    precision: &str,
+    k: usize,
+    iterations: usize,
+    clock: &dyn Clock,
+) -> Result<ProfileReport, SolverError> {
+    if iterations == 0 {
+        return Err(SolverError::InvalidInput("iterations must be at least 1".to_string()));
+    }
+    let (m, n) = (16, 16);
+    let mut a = FlatMatrix { data: Vec::new(), rows: 0, cols: 0 };
+    let mut b = FlatMatrix { data: Vec::new(), rows: 0, cols: 0 };
+
+    // Warm-up: fills the reused buffers and validates the precision before timing
+    generate_matrices_from_seed_into(seed, m, k, k, n, &mut a, &mut b);
+    let prepared = pack_operands(&a, &b, precision)?;
+    let dtype = prepared.result_dtype();
+    let result = prepared.run();
+    let result_hash = compute_hash_as(&result, dtype);
+
+    let mut generation = Vec::with_capacity(iterations);
+    let mut packing = Vec::with_capacity(iterations);
+    let mut kernel = Vec::with_capacity(iterations);
+    let mut hashing = Vec::with_capacity(iterations);
+    let mut end_to_end = Vec::with_capacity(iterations);
+
+    for _ in 0..iterations {
+        generation.push(time_ms(clock, || generate_matrices_from_seed_into(seed, m, k, k, n, &mut a, &mut b)).1);
+        packing.push(time_ms(clock, || pack_operands(&a, &b, precision)).1);
+        kernel.push(time_ms(clock, || prepared.run()).1);
+        hashing.push(time_ms(clock, || compute_hash_as(&result, dtype)).1);
+
+        let (hash, ms) = time_ms(clock, || {
+            generate_matrices_from_seed_into(seed, m, k, k, n, &mut a, &mut b);
+            let ops = pack_operands(&a, &b, precision)?;
+            Ok::<_, SolverError>(compute_hash_as(&ops.run(), dtype))
+        });
+        if hash? != result_hash {
+            return Err(SolverError::Internal("seed pipeline is not deterministic".to_string()));
+        }
+        end_to_end.push(ms);
+    }
+
+    let end_to_end_median_ms = median(&mut end_to_end);
+    let phases: Vec<PhaseTiming> = [
+        ("generation", generation),
+        ("packing", packing),
+        ("kernel", kernel),
+        ("hash", hashing),
+    ]
+    .into_iter()
+    .map(|(name, mut samples)| {
+        let median_ms = median(&mut samples);
+        PhaseTiming {
+            name: name.to_string(),
+            median_ms,
+            fraction_of_end_to_end: if end_to_end_median_ms > 0.0 { median_ms / end_to_end_median_ms } else { 0.0 },
+        }
+    })
+    .collect();
+    let phase_sum_ms = phases.iter().map(|p| p.median_ms).sum();
+
+    Ok(ProfileReport {
+        seed: hex::encode(seed),
+        precision: precision.to_string(),
+        matrix_a_shape: (m, k),
+        matrix_b_shape: (k, n),
+        iterations,
+        phases,
+        end_to_end_median_ms,
+        phase_sum_ms,
+        result_hash,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{compute_workload, generate_matrices_from_seed, types};
+
+    #[test]
+    fn test_profile_report_structure() {
+        let report = profile_seed(b"profile", "u8i8", 4096, 3, &crate::SystemClock).unwrap();
+        let names: Vec<&str> = report.phases.iter().map(|p| p.name.as_str()).collect();
+        assert_eq!(names, ["generation", "packing", "kernel", "hash"]);
+        assert_eq!(report.matrix_a_shape, (16, 4096));
+        assert_eq!(report.iterations, 3);
+
+        // Phases run back to back make up the end-to-end run, modulo timer noise
+        let ratio = report.phase_sum_ms / report.end_to_end_median_ms;
+        assert!((0.5..2.0).contains(&ratio), "phase sum {} vs end-to-end {}", report.phase_sum_ms, report.end_to_end_median_ms);
+
+        let json = serde_json::to_value(&report).unwrap();
+        assert!(json["phases"][2]["fraction_of_end_to_end"].is_number());
+        assert!(report.to_table().contains("end-to-end"));
+
+        // Same hash as the regular pipeline
+        let (matrix_a, matrix_b) = generate_matrices_from_seed(b"profile", 16, 4096, 4096, 16);
+        let input = types::Input::matmul(matrix_a, matrix_b, "u8i8");
+        assert_eq!(report.result_hash, compute_workload(input).unwrap().result_hash);
+    }
+
+    #[test]
+    fn test_profile_rejects_unpacked_precision() {
+        assert!(matches!(profile_seed(b"profile", "fp32", 64, 1, &crate::SystemClock), Err(SolverError::UnsupportedPrecision(_))));
+    }
+}
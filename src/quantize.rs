@@ -0,0 +1,342 @@
+//! Standalone quantization (`workload_type: "quantize"`).
+//!
+//! Applies a precision's element conversion to matrix_a without multiplying, so clients can
+//! precompute an operand's quantized form and its digest exactly as the solver sees it.
+//! Every conversion here is the one the kernels call (`int8_scale`/`quantize_i8`,
+//! `Fp16Rounding`, `to_u8`), so the two cannot drift apart. fp16 follows
+//! `ComputeOptions::fp16_rounding`, converting each element as the kernels convert A.
+//!
+//! `precision` names the target: "int8" (one scale for the tensor), "int8_per_channel"
+//! (one scale per column, i.e. per output channel of a B operand), "int8_rowwise" (one
+//! scale per row, as the int8_rowwise kernels quantize A), "fp16" or "u8". The
+//! output's `result_matrix` holds the quantized values (integers, or fp16-rounded floats),
+//! `metadata.quantization_scales` the int8 scales (q = clamp(trunc(x * scale)), so
+//! x ≈ q / scale), and `result_hash` is SHA-256 over the values in their own width
+//! (`result_dtype` "i8", "u8" or "f16").
+
+use crate::preflight::WorkloadShape;
+use crate::{types, ComputeOptions, FlatMatrix, Fp16Rounding, ResultDtype, SolverError};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum QuantizeTarget {
+    /// Symmetric int8 with one scale for the whole matrix (the int8 kernels' scheme)
+    Int8,
+    /// Symmetric int8 with one scale per column
+    Int8PerChannel,
+    /// Symmetric int8 with one scale per row (the int8_rowwise kernels' A operand)
+    Int8Rowwise,
+    /// Rounded through binary16 (the fp16 16x16 kernels' operands)
+    #[cfg(feature = "fp16")]
+    Fp16,
+    /// Saturating cast to u8 (the u8i8 kernels' A operand)
+    U8,
+}
+
+impl QuantizeTarget {
+    #[cfg(feature = "fp16")]
+    pub const ALL: [QuantizeTarget; 5] = [
+        QuantizeTarget::Int8,
+        QuantizeTarget::Int8PerChannel,
+        QuantizeTarget::Int8Rowwise,
+        QuantizeTarget::Fp16,
+        QuantizeTarget::U8,
+    ];
+    #[cfg(not(feature = "fp16"))]
+    pub const ALL: [QuantizeTarget; 4] =
+        [QuantizeTarget::Int8, QuantizeTarget::Int8PerChannel, QuantizeTarget::Int8Rowwise, QuantizeTarget::U8];
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            QuantizeTarget::Int8 => "int8",
+            QuantizeTarget::Int8PerChannel => "int8_per_channel",
+            QuantizeTarget::Int8Rowwise => "int8_rowwise",
+            #[cfg(feature = "fp16")]
+            QuantizeTarget::Fp16 => "fp16",
+            QuantizeTarget::U8 => "u8",
+        }
+    }
+
+    pub fn parse(s: &str) -> Result<Self, SolverError> {
+        Self::ALL.into_iter().find(|t| t.as_str() == s).ok_or_else(|| {
+            let targets: Vec<&str> = Self::ALL.iter().map(QuantizeTarget::as_str).collect();
+            SolverError::UnsupportedPrecision(format!("{} (quantize targets: {})", s, targets.join(", ")))
+        })
+    }
+
+    /// Element type of the quantized values, which is also the hash preimage encoding
+    pub fn dtype(&self) -> ResultDtype {
+        match self {
+            QuantizeTarget::Int8 | QuantizeTarget::Int8PerChannel | QuantizeTarget::Int8Rowwise => ResultDtype::I8,
+            #[cfg(feature = "fp16")]
+            QuantizeTarget::Fp16 => ResultDtype::F16,
+            QuantizeTarget::U8 => ResultDtype::U8,
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct Quantized {
+    /// Quantized values, same shape as the input
+    pub values: FlatMatrix,
+    /// int8 scales: one for `Int8`, one per column for `Int8PerChannel`, one per row for
+    /// `Int8Rowwise`, none otherwise
+    pub scales: Vec<f32>,
+}
+
+/// Quantize `matrix` the way the kernels quantize their operands
+pub fn quantize_matrix(matrix: &FlatMatrix, target: QuantizeTarget) -> Quantized {
+    quantize_matrix_with_rounding(matrix, target, Fp16Rounding::default())
+}
+
+/// `quantize_matrix` with fp16 conversion under `fp16_rounding` (other targets ignore it)
+#[cfg_attr(not(feature = "fp16"), allow(unused_variables))]
+pub fn quantize_matrix_with_rounding(matrix: &FlatMatrix, target: QuantizeTarget, fp16_rounding: Fp16Rounding) -> Quantized {
+    let (rows, cols) = (matrix.rows, matrix.cols);
+    let (data, scales) = match target {
+        QuantizeTarget::Int8 => {
+            let scale = crate::int8_scale(matrix.data.iter().copied());
+            (matrix.data.iter().map(|&x| crate::quantize_i8(x, scale) as f32).collect(), vec![scale])
+        }
+        QuantizeTarget::Int8PerChannel => {
+            let scales: Vec<f32> =
+                (0..cols).map(|j| crate::int8_scale((0..rows).map(|i| matrix.data[i * cols + j]))).collect();
+            let data = matrix
+                .data
+                .iter()
+                .enumerate()
+                .map(|(idx, &x)| crate::quantize_i8(x, scales[idx % cols]) as f32)
+                .collect();
+            (data, scales)
+        }
+        QuantizeTarget::Int8Rowwise => {
+            let scales = crate::Int8Scaling::PerRow.a_scales(matrix);
+            let data = matrix
+                .data
+                .iter()
+                .enumerate()
+                .map(|(idx, &x)| crate::quantize_i8(x, scales[idx / cols]) as f32)
+                .collect();
+            (data, scales)
+        }
+        #[cfg(feature = "fp16")]
+        QuantizeTarget::Fp16 => {
+            let data = matrix.data.iter().enumerate().map(|(i, &x)| fp16_rounding.round(x, crate::Operand::A, i)).collect();
+            (data, Vec::new())
+        }
+        QuantizeTarget::U8 => (matrix.data.iter().map(|&x| crate::to_u8(x) as f32).collect(), Vec::new()),
+    };
+    Quantized { values: FlatMatrix { data, rows, cols }, scales }
+}
+
+/// The target of a quantize workload, or why it cannot run
+pub(crate) fn check(workload: &WorkloadShape, options: &ComputeOptions) -> Result<QuantizeTarget, SolverError> {
+    let target = QuantizeTarget::parse(&workload.precision)?;
+    let shapes = &workload.shapes;
+    let unsupported = [
+        ("hash_input", workload.hash_input.is_some()),
+        ("a_layout", shapes.a_layout.as_deref().is_some_and(|l| l != "row_major")),
+        ("b_layout", shapes.b_layout.as_deref().is_some_and(|l| l != "row_major")),
+        ("redundancy", options.redundancy != crate::Redundancy::Single),
+        ("hash_rounding", options.hash_rounding.is_some()),
+        ("result_columns", options.result_columns.is_some()),
+        ("tile_trace", options.tile_trace),
+        ("error_metrics", options.error_metrics),
+        ("matrix_b_sparse", shapes.matrix_b_sparse.is_some()),
+        ("fp16_rounding", options.fp16_rounding != Fp16Rounding::default() && target.as_str() != "fp16"),
+        ("alpha, beta and matrix_c", workload.alpha.is_some() || workload.beta.is_some() || workload.matrix_c.is_some()),
+    ];
+    if let Some((name, _)) = unsupported.iter().find(|(_, set)| *set) {
+        return Err(SolverError::InvalidInput(format!("{} is not supported for the quantize workload", name)));
+    }
+    Ok(target)
+}
+
+/// `compute_workload` for `workload_type: "quantize"`
+pub(crate) fn compute_quantize(input: &types::Input, options: &ComputeOptions) -> Result<types::Output, SolverError> {
+    let target = check(&WorkloadShape::of(input), options)?;
+    let a = &input.matrix_a;
+
+    let clock = options.clock();
+    let (quantized, elapsed) = crate::clock::time(clock, || quantize_matrix_with_rounding(a, target, options.fp16_rounding));
+    let dtype = target.dtype();
+    let result_hash = crate::compute_hash_as(&quantized.values, dtype);
+    let ops_per_second = a.data.len() as f64 / elapsed.as_secs_f64();
+
+    Ok(types::Output {
+        result_matrix: std::sync::Arc::new(quantized.values),
+        result_f64: None,
+        result_hash,
+        tile_trace: None,
+        trace_root: None,
+        audit_passed: None,  // Set by caller (audit::write_output_audited)
+        verified: None,
+        warnings: Vec::new(),
+        batch_hashes: None,
+        auxiliary_results: Vec::new(),
+        metrics: types::Metrics {
+            latency_ms: elapsed.as_secs_f64() * 1000.0,
+            throughput_ops_per_sec: ops_per_second,
+            ops_per_second,
+            memory_usage_mb: None,
+            parse_time_ms: None,  // Set by caller (main.rs)
+            kernel_time_ms: Some(elapsed.as_secs_f64() * 1000.0),
+            serialize_time_ms: None,  // Set by caller (main.rs)
+            pack_cache_hit: None,
+            batch_kernel_time_ms: None,
+            redundancy_retries: None,
+            seed_pipelined: None,
+            peak_request_memory_mb: None,
+            verify_time_ms: None,
+            threads_used: None,
+            cache_stats: None,
+        },
+        metadata: types::OutputMetadata {
+            precision: target.as_str().to_string(),
+            matrix_a_shape: (a.rows, a.cols),
+            matrix_b_shape: (input.matrix_b.rows, input.matrix_b.cols),
+            result_shape: (a.rows, a.cols),
+            compiler_flags: input
+                .metadata
+                .as_ref()
+                .and_then(|m| m.compiler_flags.clone())
+                .or_else(|| Some(crate::build_compiler_flags().to_string())),
+            libraries: input.metadata.as_ref().and_then(|m| m.libraries.clone()).or_else(|| Some(crate::build_libraries())),
+            result_dtype: dtype.as_str().to_string(),
+            hash_input_description: dtype.hash_input_description().to_string(),
+            hash_mode: "exact".to_string(),
+            hash_rounding: None,
+            kernel: None,
+            kernel_choice: None,
+            fp32_accumulation: None,
+            fp32_summation: None,
+            simd_variant: None,
+            threads: None,
+            accumulator: None,
+            f64_accumulation: None,
+            quantization_scheme: None,
+            fp16_rounding: (target.as_str() == "fp16").then_some(options.fp16_rounding),
+            a_layout: None,
+            b_layout: None,
+            redundancy: None,
+            cross_check_kernel: None,
+            lossy_parse_count: None,  // Set by caller (main.rs / API)
+            result_columns: None,
+            quantization_scales: (!quantized.scales.is_empty()).then_some(quantized.scales),
+            alpha: None,
+            beta: None,
+            batch: None,
+            b_cache_used: None,
+        },
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{compute_workload, generate_matrices_from_seed};
+
+    fn seed_operands() -> (FlatMatrix, FlatMatrix) {
+        let (mut a, mut b) = generate_matrices_from_seed(b"quantize", 16, 96, 96, 16);
+        // Spread values out so scales and rounding actually matter
+        for (i, x) in a.data.iter_mut().chain(b.data.iter_mut()).enumerate() {
+            *x = *x * 0.37 + (i % 7) as f32 * 0.013;
+        }
+        (a, b)
+    }
+
+    #[test]
+    fn test_int8_matches_kernel_packing() {
+        let (a, b) = seed_operands();
+
+        let (a_q, scale_a) = crate::quantize_a_i8(&a);
+        let q = quantize_matrix(&a, QuantizeTarget::Int8);
+        assert_eq!(q.scales, vec![scale_a]);
+        let kernel_a: Vec<f32> = a_q.as_slice().iter().map(|&v| v as f32).collect();
+        assert_eq!(q.values.data, kernel_a);
+
+        // The int8 kernels keep B transposed (16×k); undo that before comparing
+        let (b_t, scale_b) = crate::pack_b_int8_transposed(&b);
+        let q = quantize_matrix(&b, QuantizeTarget::Int8);
+        assert_eq!(q.scales, vec![scale_b]);
+        for p in 0..b.rows {
+            for j in 0..16 {
+                assert_eq!(q.values.data[p * 16 + j], b_t.as_slice()[j * b.rows + p] as f32);
+            }
+        }
+    }
+
+    #[test]
+    fn test_u8_fp16_per_channel_and_rowwise() {
+        let (a, b) = seed_operands();
+
+        let a_u8 = crate::pack_a_u8(&a);
+        let kernel_a: Vec<f32> =
+            unsafe { std::slice::from_raw_parts(a_u8.as_ptr(), a.data.len()) }.iter().map(|&v| v as f32).collect();
+        assert_eq!(quantize_matrix(&a, QuantizeTarget::U8).values.data, kernel_a);
+
+        #[cfg(feature = "fp16")]
+        {
+            let f16 = quantize_matrix(&a, QuantizeTarget::Fp16);
+            assert!(f16.scales.is_empty());
+            assert!(f16.values.data.iter().zip(&a.data).all(|(&r, &x)| r == half::f16::from_f32(x).to_f32()));
+            // Toward zero never rounds up in magnitude, and differs somewhere on these values
+            let truncated = quantize_matrix_with_rounding(&a, QuantizeTarget::Fp16, Fp16Rounding::TowardZero);
+            assert!(truncated.values.data.iter().zip(&a.data).all(|(&r, &x)| r.abs() <= x.abs()));
+            assert_ne!(truncated.values.data, f16.values.data);
+        }
+
+        // Each row quantizes like a one-row tensor, and the kernels see the same values
+        let rowwise = quantize_matrix(&a, QuantizeTarget::Int8Rowwise);
+        assert_eq!(rowwise.scales.len(), 16);
+        for i in [0, 7, 15] {
+            let row = FlatMatrix { data: a.data[i * a.cols..(i + 1) * a.cols].to_vec(), rows: 1, cols: a.cols };
+            let alone = quantize_matrix(&row, QuantizeTarget::Int8);
+            assert_eq!(alone.scales[0], rowwise.scales[i]);
+            assert_eq!(alone.values.data, rowwise.values.data[i * a.cols..(i + 1) * a.cols]);
+        }
+        let mut a_q = crate::AlignedBufferI8::new(a.data.len(), 64);
+        crate::quantize_a_i8_cols(&a, 0..a.cols, &rowwise.scales, &mut a_q);
+        assert!(a_q.as_slice().iter().zip(&rowwise.values.data).all(|(&k, &q)| k as f32 == q));
+
+        // Each column quantizes like a one-column tensor
+        let per_channel = quantize_matrix(&b, QuantizeTarget::Int8PerChannel);
+        assert_eq!(per_channel.scales.len(), 16);
+        for j in [0, 9, 15] {
+            let column = FlatMatrix { data: (0..b.rows).map(|p| b.data[p * 16 + j]).collect(), rows: b.rows, cols: 1 };
+            let alone = quantize_matrix(&column, QuantizeTarget::Int8);
+            assert_eq!(alone.scales[0], per_channel.scales[j]);
+            assert!((0..b.rows).all(|p| alone.values.data[p] == per_channel.values.data[p * 16 + j]));
+        }
+    }
+
+    #[test]
+    fn test_quantize_workload_json() {
+        let (a, _) = seed_operands();
+        let rows: Vec<Vec<f32>> = a.data.chunks(a.cols).map(<[f32]>::to_vec).collect();
+        // matrix_b may be omitted
+        let json = serde_json::json!({ "workload_type": "quantize", "precision": "int8", "matrix_a": rows });
+        let input: types::Input = serde_json::from_value(json).unwrap();
+        let output = compute_workload(input).unwrap();
+
+        let q = quantize_matrix(&a, QuantizeTarget::Int8);
+        assert_eq!(output.metadata.result_dtype, "i8");
+        assert_eq!(output.metadata.quantization_scales, Some(q.scales.clone()));
+        assert_eq!(output.result_hash, crate::compute_hash_as(&q.values, ResultDtype::I8));
+        // One byte per element in the preimage
+        let bytes: Vec<u8> = q.values.data.iter().map(|&v| v as i8 as u8).collect();
+        assert_eq!(output.result_hash, hex::encode(<sha2::Sha256 as sha2::Digest>::digest(&bytes)));
+
+        let err = compute_workload(types::Input { precision: "int4".to_string(), ..input_for(&a) }).unwrap_err();
+        assert!(matches!(err, SolverError::UnsupportedPrecision(_)), "{}", err);
+    }
+
+    fn input_for(a: &FlatMatrix) -> types::Input {
+        types::Input {
+            matrix_a: a.clone(),
+            matrix_b: FlatMatrix::default(),
+            workload_type: Some("quantize".to_string()),
+            precision: "int8".to_string(),
+            ..Default::default()
+        }
+    }
+}
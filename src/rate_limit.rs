@@ -0,0 +1,219 @@
+//! Token-bucket rate limiting for the API.
+//!
+//! Each client (bearer token, or IP address) owns two buckets that refill continuously
+//! over the configured window: one counting requests and one counting compute seconds.
+//! A request is admitted when the request bucket holds at least one token and the
+//! compute bucket is not in debt; the measured compute time is charged afterwards, so
+//! a single expensive request can push a client into debt and delay the next one.
+//! Both the refills and that compute time are measured on the limiter's `Clock`.
+
+use crate::clock::{Clock, SystemClock, Timestamp};
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+#[derive(Debug, Clone)]
+pub struct RateLimitConfig {
+    /// Requests allowed per window (bucket capacity)
+    pub requests_per_window: Option<u32>,
+    /// Compute seconds allowed per window
+    pub compute_seconds_per_window: Option<f64>,
+    /// Refill window; "per minute" limits use 60s
+    pub window: Duration,
+    /// Take the client IP from X-Forwarded-For (only behind a trusted proxy)
+    pub trust_forwarded_for: bool,
+    /// Buckets untouched for this long are dropped by `cleanup`
+    pub idle_ttl: Duration,
+}
+
+impl Default for RateLimitConfig {
+    fn default() -> Self {
+        Self {
+            requests_per_window: Some(60),
+            compute_seconds_per_window: None,
+            window: Duration::from_secs(60),
+            trust_forwarded_for: false,
+            idle_ttl: Duration::from_secs(600),
+        }
+    }
+}
+
+/// Outcome of an admission check, used to fill the rate-limit headers
+#[derive(Debug, Clone, PartialEq)]
+pub struct RateLimitDecision {
+    pub allowed: bool,
+    /// Request-bucket capacity (0 when only compute time is limited)
+    pub limit: u32,
+    /// Whole requests left in the bucket after this one
+    pub remaining: u32,
+    /// Seconds until the client may retry (0 when allowed)
+    pub retry_after_secs: u64,
+}
+
+#[derive(Debug)]
+struct Bucket {
+    requests: f64,
+    compute_seconds: f64,
+    last_refill: Timestamp,
+}
+
+pub struct RateLimiter {
+    config: RateLimitConfig,
+    clock: Arc<dyn Clock>,
+    buckets: Mutex<HashMap<String, Bucket>>,
+}
+
+impl RateLimiter {
+    pub fn new(config: RateLimitConfig) -> Self {
+        Self::with_clock(config, Arc::new(SystemClock))
+    }
+
+    /// A limiter whose callers read the time from `clock` (see `clock`)
+    pub fn with_clock(config: RateLimitConfig, clock: Arc<dyn Clock>) -> Self {
+        Self { config, clock, buckets: Mutex::new(HashMap::new()) }
+    }
+
+    pub fn config(&self) -> &RateLimitConfig {
+        &self.config
+    }
+
+    /// The time source the `now` passed to this limiter, and compute time charged to it,
+    /// come from
+    pub fn clock(&self) -> &dyn Clock {
+        &*self.clock
+    }
+
+    fn refill(&self, bucket: &mut Bucket, now: Timestamp) {
+        let elapsed = now.duration_since(bucket.last_refill).as_secs_f64();
+        let window = self.config.window.as_secs_f64();
+        if let Some(rpw) = self.config.requests_per_window {
+            let cap = rpw as f64;
+            bucket.requests = (bucket.requests + elapsed * cap / window).min(cap);
+        }
+        if let Some(cspw) = self.config.compute_seconds_per_window {
+            bucket.compute_seconds = (bucket.compute_seconds + elapsed * cspw / window).min(cspw);
+        }
+        bucket.last_refill = now;
+    }
+
+    fn new_bucket(&self, now: Timestamp) -> Bucket {
+        Bucket {
+            requests: self.config.requests_per_window.unwrap_or(0) as f64,
+            compute_seconds: self.config.compute_seconds_per_window.unwrap_or(0.0),
+            last_refill: now,
+        }
+    }
+
+    /// Try to admit one request for `key`, consuming a request token on success
+    pub fn check(&self, key: &str, now: Timestamp) -> RateLimitDecision {
+        let mut buckets = self.buckets.lock().unwrap();
+        let bucket = buckets.entry(key.to_string()).or_insert_with(|| self.new_bucket(now));
+        self.refill(bucket, now);
+
+        let window = self.config.window.as_secs_f64();
+        let mut wait = 0.0f64;
+        if let Some(rpw) = self.config.requests_per_window {
+            if bucket.requests < 1.0 {
+                wait = wait.max((1.0 - bucket.requests) * window / rpw as f64);
+            }
+        }
+        if let Some(cspw) = self.config.compute_seconds_per_window {
+            if bucket.compute_seconds <= 0.0 {
+                // Wait until the debt is repaid and a sliver of budget is available
+                wait = wait.max(-bucket.compute_seconds * window / cspw + 1e-3);
+            }
+        }
+
+        let allowed = wait == 0.0;
+        if allowed && self.config.requests_per_window.is_some() {
+            bucket.requests -= 1.0;
+        }
+        RateLimitDecision {
+            allowed,
+            limit: self.config.requests_per_window.unwrap_or(0),
+            remaining: bucket.requests.max(0.0).floor() as u32,
+            retry_after_secs: if allowed { 0 } else { wait.ceil().max(1.0) as u64 },
+        }
+    }
+
+    /// Charge measured compute time to `key`
+    pub fn record_compute(&self, key: &str, seconds: f64, now: Timestamp) {
+        if self.config.compute_seconds_per_window.is_none() {
+            return;
+        }
+        let mut buckets = self.buckets.lock().unwrap();
+        let bucket = buckets.entry(key.to_string()).or_insert_with(|| self.new_bucket(now));
+        self.refill(bucket, now);
+        bucket.compute_seconds -= seconds;
+    }
+
+    /// Drop buckets idle for longer than `idle_ttl`; returns how many were removed
+    pub fn cleanup(&self, now: Timestamp) -> usize {
+        let mut buckets = self.buckets.lock().unwrap();
+        let before = buckets.len();
+        let ttl = self.config.idle_ttl;
+        buckets.retain(|_, b| now.duration_since(b.last_refill) < ttl);
+        before - buckets.len()
+    }
+
+    pub fn tracked_clients(&self) -> usize {
+        self.buckets.lock().unwrap().len()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// `secs` into the limiter's clock
+    fn at(secs: u64) -> Timestamp {
+        Timestamp::from_offset(Duration::from_secs(secs))
+    }
+
+    fn limiter(rpw: Option<u32>, cspw: Option<f64>) -> RateLimiter {
+        RateLimiter::new(RateLimitConfig {
+            requests_per_window: rpw,
+            compute_seconds_per_window: cspw,
+            window: Duration::from_secs(60),
+            trust_forwarded_for: false,
+            idle_ttl: Duration::from_secs(120),
+        })
+    }
+
+    #[test]
+    fn test_request_bucket_refills_over_window() {
+        let limiter = limiter(Some(2), None);
+        assert_eq!(limiter.check("a", at(0)).remaining, 1);
+        assert_eq!(limiter.check("a", at(0)).remaining, 0);
+        let denied = limiter.check("a", at(0));
+        assert!(!denied.allowed);
+        assert_eq!(denied.retry_after_secs, 30);
+
+        // Other clients are unaffected
+        assert!(limiter.check("b", at(0)).allowed);
+
+        // Half a window later one token is back
+        assert!(limiter.check("a", at(30)).allowed);
+    }
+
+    #[test]
+    fn test_compute_seconds_debt() {
+        let limiter = limiter(None, Some(6.0));
+        assert!(limiter.check("a", at(0)).allowed);
+        limiter.record_compute("a", 9.0, at(0));
+        let denied = limiter.check("a", at(0));
+        assert!(!denied.allowed);
+        // 3s of debt at 0.1 budget-seconds per second
+        assert_eq!(denied.retry_after_secs, 31);
+        assert!(limiter.check("a", at(31)).allowed);
+    }
+
+    #[test]
+    fn test_cleanup_drops_idle_buckets() {
+        let limiter = limiter(Some(5), None);
+        limiter.check("old", at(0));
+        limiter.check("new", at(100));
+        assert_eq!(limiter.cleanup(at(150)), 1);
+        assert_eq!(limiter.tracked_clients(), 1);
+    }
+}
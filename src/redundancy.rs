@@ -0,0 +1,146 @@
+//! Dual computation with cross-checked hashes (`ComputeOptions::redundancy`).
+//!
+//! Overclocked or overheating machines occasionally flip a bit mid-kernel, and a single
+//! wrong hash costs a rejected block. `compute_dual` runs the matmul twice and returns
+//! only when both runs hash the same. For strict fp32 the second run uses the other of
+//! `fp32_16x16` and `fp32_blocked` when it applies: both add products in k order with
+//! separate rounding, so they agree bit for bit and a fault in one code path cannot
+//! repeat in the other. Every other precision (fp16 is not bit-stable across kernels,
+//! integer kernels are cross-checked as-is) runs the same kernel twice. On a mismatch both
+//! runs are repeated once; a second mismatch is `SolverError::InconsistentComputation`.
+
+use crate::{kernels, types, ComputeOptions, Fp32Accumulation, SolverError};
+
+/// The two fp32 kernels whose strict results are bit-identical
+const FP32_PAIR: [&str; 2] = ["fp32_16x16", "fp32_blocked"];
+
+pub(crate) fn compute_dual(input: &types::Input, options: &ComputeOptions) -> Result<types::Output, SolverError> {
+    // The runs are compared by hash, so neither can defer it
+    let options = &ComputeOptions { defer_result_hash: false, ..options.clone() };
+    let run = |kernel: Option<&str>| {
+        let options = match kernel {
+            Some(name) => ComputeOptions { kernel: Some(name.to_string()), ..options.clone() },
+            None => options.clone(),
+        };
+        crate::compute_matmul_once(input, &options)
+    };
+
+    let mut first = run(None)?;
+    let cross_check = cross_check_kernel(&first, options);
+    let mut second = run(Some(&cross_check))?;
+    let mut total_ms = [&first, &second].map(kernel_ms).iter().sum::<f64>();
+    let mut retries = 0;
+    while first.result_hash != second.result_hash {
+        if retries == 1 {
+            return Err(SolverError::InconsistentComputation {
+                first_hash: first.result_hash,
+                second_hash: second.result_hash,
+            });
+        }
+        retries += 1;
+        first = run(first.metadata.kernel.as_deref())?;
+        second = run(Some(&cross_check))?;
+        total_ms += kernel_ms(&first) + kernel_ms(&second);
+    }
+
+    let single_ms = kernel_ms(&first);
+    let metrics = &mut first.metrics;
+    if total_ms > 0.0 {
+        metrics.ops_per_second *= single_ms / total_ms;
+        metrics.throughput_ops_per_sec = metrics.ops_per_second;
+    }
+    metrics.latency_ms = total_ms;
+    metrics.kernel_time_ms = Some(total_ms);
+    metrics.redundancy_retries = Some(retries);
+    first.metadata.redundancy = Some(crate::Redundancy::Dual.as_str().to_string());
+    first.metadata.cross_check_kernel = Some(cross_check);
+    Ok(first)
+}
+
+fn kernel_ms(output: &types::Output) -> f64 {
+    output.metrics.kernel_time_ms.unwrap_or(output.metrics.latency_ms)
+}
+
+/// Kernel for the second run: the other half of `FP32_PAIR` where that is bit-identical
+/// and supports the shapes, the first run's kernel otherwise
+fn cross_check_kernel(first: &types::Output, options: &ComputeOptions) -> String {
+    let kernel = first.metadata.kernel.clone().unwrap_or_default();
+    let metadata = &first.metadata;
+    if metadata.precision != "fp32" || options.fp32_accumulation != Fp32Accumulation::Strict {
+        return kernel;
+    }
+    if !FP32_PAIR.contains(&kernel.as_str()) {
+        return kernel;
+    }
+    FP32_PAIR
+        .into_iter()
+        .filter(|&name| name != kernel)
+        .find(|&name| {
+            kernels::kernel_by_name(name)
+                .is_some_and(|k| k.supports(&metadata.precision, metadata.matrix_a_shape, metadata.matrix_b_shape))
+        })
+        .map(str::to_string)
+        .unwrap_or(kernel)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::faults::{with_plan, BitFlip, FaultPlan};
+    use crate::types::Input;
+    use crate::{compute_workload_with_options, generate_matrices_from_seed, Redundancy};
+
+    fn input(precision: &str, m: usize) -> Input {
+        let (matrix_a, matrix_b) = generate_matrices_from_seed(b"redundancy", m, 512, 512, 16);
+        Input::matmul(matrix_a, matrix_b, precision)
+    }
+
+    fn dual() -> ComputeOptions {
+        ComputeOptions { redundancy: Redundancy::Dual, ..Default::default() }
+    }
+
+    /// Flip a bit of the result of these kernel runs (0-based, in the order `compute_dual` makes them)
+    fn corrupt_runs(runs: &[usize]) -> FaultPlan {
+        FaultPlan { flip_result_bit: Some(BitFlip { element: 0, bit: 0 }), kernel_runs: Some(runs.to_vec()), ..Default::default() }
+    }
+
+    #[test]
+    fn test_runs_agree_across_kernels() {
+        for (precision, m, kernels) in [
+            ("fp32", 16, ("fp32_16x16", "fp32_blocked")),
+            ("fp32", 5, ("fp32_blocked", "fp32_blocked")),
+            ("u8i8", 16, ("u8i8_16x16", "u8i8_16x16")),
+            ("fp16", 16, ("fp16_16x16", "fp16_16x16")),
+        ]
+        .into_iter()
+        .filter(|row| crate::tests::precisions().contains(&row.0))
+        {
+            let single = compute_workload_with_options(input(precision, m), &ComputeOptions::default()).unwrap();
+            let output = compute_workload_with_options(input(precision, m), &dual()).unwrap();
+            assert_eq!(output.result_hash, single.result_hash, "{}", precision);
+            let metadata = &output.metadata;
+            assert_eq!((metadata.kernel.as_deref().unwrap(), metadata.cross_check_kernel.as_deref().unwrap()), kernels);
+            assert_eq!(metadata.redundancy.as_deref(), Some("dual"));
+            assert_eq!(output.metrics.redundancy_retries, Some(0));
+            assert_eq!(single.metrics.redundancy_retries, None);
+        }
+    }
+
+    #[test]
+    fn test_single_fault_is_retried() {
+        let clean = compute_workload_with_options(input("fp32", 16), &dual()).unwrap();
+        let output = with_plan(corrupt_runs(&[1]), || compute_workload_with_options(input("fp32", 16), &dual())).unwrap();
+        assert_eq!(output.result_hash, clean.result_hash);
+        assert_eq!(output.metrics.redundancy_retries, Some(1));
+    }
+
+    #[test]
+    fn test_repeated_fault_is_reported() {
+        let err = with_plan(corrupt_runs(&[0, 3]), || compute_workload_with_options(input("u8i8", 16), &dual()));
+        match err.unwrap_err() {
+            SolverError::InconsistentComputation { first_hash, second_hash } => assert_ne!(first_hash, second_hash),
+            other => panic!("expected an inconsistent computation, got {}", other),
+        }
+        assert!(Redundancy::parse("triple").is_err());
+    }
+}
@@ -0,0 +1,227 @@
+//! Bounded in-memory log of served compute requests.
+//!
+//! Each entry records what is needed to explain (and, for seed requests, re-run) a past
+//! response: the request configuration, digests of any supplied matrices, the result
+//! hash, the kernel and the timings. Matrices themselves are never retained, so only
+//! seed-based requests can be recomputed. Entries are evicted oldest-first once the log
+//! holds `capacity` entries, and dropped entirely once older than `max_age`.
+
+use serde::{Deserialize, Serialize};
+use std::collections::VecDeque;
+use std::sync::Mutex;
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+
+#[derive(Debug, Clone)]
+pub struct ReplayLogConfig {
+    /// Maximum number of entries kept
+    pub capacity: usize,
+    /// Entries older than this are dropped
+    pub max_age: Duration,
+}
+
+impl Default for ReplayLogConfig {
+    fn default() -> Self {
+        Self { capacity: 1000, max_age: Duration::from_secs(24 * 3600) }
+    }
+}
+
+/// Everything about a request except the matrices; the request digest is taken over this
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ReplayRequest {
+    pub seed: Option<String>,
+    pub seed_dims: Option<crate::api::api::SeedDims>,
+    /// blake3 of shape + f32le data (see `pack_cache::matrix_digest`), for matrix requests
+    pub matrix_a_digest: Option<String>,
+    pub matrix_b_digest: Option<String>,
+    pub precision: String,
+    pub workload_type: Option<String>,
+    pub kernel: Option<String>,
+    pub result_columns: Option<Vec<usize>>,
+    pub hash_mode: Option<String>,
+    pub hash_mantissa_bits: Option<u32>,
+    pub hash_quantum: Option<f64>,
+    pub tile_trace: Option<bool>,
+    pub fp32_accumulation: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub fp32_summation: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub hash_input: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub accumulator: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub fp16_rounding: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub fp16_rounding_seed: Option<u64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub a_layout: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub b_layout: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub batch: Option<usize>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub redundancy: Option<String>,
+}
+
+impl ReplayRequest {
+    pub fn digest(&self) -> String {
+        let bytes = serde_json::to_vec(self).expect("replay request serializes");
+        blake3::hash(&bytes).to_hex().to_string()
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct ReplayEntry {
+    pub recorded_at_unix_ms: u64,
+    pub request_digest: String,
+    pub request: ReplayRequest,
+    /// Tenant that made the request, when tenancy is enabled
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub tenant: Option<String>,
+    pub result_hash: String,
+    pub kernel: Option<String>,
+    pub latency_ms: f64,
+    pub kernel_time_ms: Option<f64>,
+    pub parse_time_ms: Option<f64>,
+    pub serialize_time_ms: Option<f64>,
+}
+
+impl ReplayEntry {
+    pub fn new(request: ReplayRequest, tenant: Option<String>, output: &crate::types::Output) -> Self {
+        let recorded_at_unix_ms = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|d| d.as_millis() as u64)
+            .unwrap_or(0);
+        Self {
+            recorded_at_unix_ms,
+            request_digest: request.digest(),
+            request,
+            tenant,
+            result_hash: output.result_hash.clone(),
+            kernel: output.metadata.kernel.clone(),
+            latency_ms: output.metrics.latency_ms,
+            kernel_time_ms: output.metrics.kernel_time_ms,
+            parse_time_ms: output.metrics.parse_time_ms,
+            serialize_time_ms: output.metrics.serialize_time_ms,
+        }
+    }
+}
+
+pub struct ReplayLog {
+    config: ReplayLogConfig,
+    entries: Mutex<VecDeque<(Instant, ReplayEntry)>>,
+}
+
+impl ReplayLog {
+    pub fn new(config: ReplayLogConfig) -> Self {
+        Self { config, entries: Mutex::new(VecDeque::new()) }
+    }
+
+    fn prune(&self, entries: &mut VecDeque<(Instant, ReplayEntry)>, now: Instant) {
+        while entries
+            .front()
+            .is_some_and(|(at, _)| now.saturating_duration_since(*at) > self.config.max_age)
+        {
+            entries.pop_front();
+        }
+        while entries.len() > self.config.capacity {
+            entries.pop_front();
+        }
+    }
+
+    pub fn record(&self, entry: ReplayEntry, now: Instant) {
+        let mut entries = self.entries.lock().unwrap();
+        entries.push_back((now, entry));
+        self.prune(&mut entries, now);
+    }
+
+    /// Newest first, at most `limit` entries, optionally only those of one tenant
+    pub fn recent(&self, limit: usize, tenant: Option<&str>, now: Instant) -> Vec<ReplayEntry> {
+        let mut entries = self.entries.lock().unwrap();
+        self.prune(&mut entries, now);
+        entries
+            .iter()
+            .rev()
+            .filter(|(_, e)| tenant.is_none() || e.tenant.as_deref() == tenant)
+            .take(limit)
+            .map(|(_, e)| e.clone())
+            .collect()
+    }
+
+    /// Most recent entry with this request digest
+    pub fn find(&self, digest: &str, now: Instant) -> Option<ReplayEntry> {
+        let mut entries = self.entries.lock().unwrap();
+        self.prune(&mut entries, now);
+        entries.iter().rev().find(|(_, e)| e.request_digest == digest).map(|(_, e)| e.clone())
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries.lock().unwrap().len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn entry(seed: &str) -> ReplayEntry {
+        let request = ReplayRequest {
+            seed: Some(seed.to_string()),
+            seed_dims: None,
+            matrix_a_digest: None,
+            matrix_b_digest: None,
+            precision: "u8i8".to_string(),
+            workload_type: None,
+            kernel: None,
+            result_columns: None,
+            hash_mode: None,
+            hash_mantissa_bits: None,
+            hash_quantum: None,
+            tile_trace: None,
+            fp32_accumulation: None,
+            fp32_summation: None,
+            hash_input: None,
+            accumulator: None,
+            fp16_rounding: None,
+            fp16_rounding_seed: None,
+            a_layout: None,
+            b_layout: None,
+            batch: None,
+            redundancy: None,
+        };
+        ReplayEntry {
+            recorded_at_unix_ms: 0,
+            request_digest: request.digest(),
+            request,
+            tenant: None,
+            result_hash: String::new(),
+            kernel: None,
+            latency_ms: 0.0,
+            kernel_time_ms: None,
+            parse_time_ms: None,
+            serialize_time_ms: None,
+        }
+    }
+
+    #[test]
+    fn test_count_and_age_bounds() {
+        let log = ReplayLog::new(ReplayLogConfig { capacity: 2, max_age: Duration::from_secs(60) });
+        let t0 = Instant::now();
+        log.record(entry("01"), t0);
+        log.record(entry("02"), t0 + Duration::from_secs(10));
+        log.record(entry("03"), t0 + Duration::from_secs(20));
+
+        // Count bound evicts the oldest
+        let seeds: Vec<_> = log.recent(10, None, t0 + Duration::from_secs(20)).into_iter().map(|e| e.request.seed.unwrap()).collect();
+        assert_eq!(seeds, ["03", "02"]);
+        assert!(log.find(&entry("01").request_digest, t0 + Duration::from_secs(20)).is_none());
+
+        // Age bound drops "02" (recorded at +10s) after +70s
+        assert_eq!(log.recent(10, None, t0 + Duration::from_secs(75)).len(), 1);
+        assert_eq!(log.recent(10, None, t0 + Duration::from_secs(200)).len(), 0);
+        assert!(log.is_empty());
+    }
+}
@@ -0,0 +1,217 @@
+//! Saturating int8/u8i8 accumulation (`ComputeOptions::accumulator`).
+//!
+//! Fixed-width integer hardware (DSPs, NPUs with i16 accumulators) clamps a running sum
+//! instead of widening it, so once a dot product overflows its result depends on the order
+//! of the adds. To reproduce such a device bit for bit, every path here adds the products
+//! of one output element strictly in k order and clamps after each add. Operands are the
+//! kernels' own conversions (`to_u8`, `quantize_i8` under `int8_scale`), and every
+//! u8×i8 or i8×i8 product fits in an i16, so only the sum can saturate.
+//!
+//! The 16x16 path vectorizes across the 16 output columns (NEON `sqadd` on aarch64,
+//! scalar elsewhere); the generic path is the scalar reference. Both give identical
+//! accumulators, and neither chunks k or touches the pack caches.
+
+use crate::{Accumulator, ComputeOptions, FlatMatrix, KernelResult, SolverError};
+
+/// Saturating kernel run for `precision` ("int8" or "u8i8"); `sixteen` selects the 16x16 path
+pub(crate) fn kernel_result(
+    a: &FlatMatrix,
+    b: &FlatMatrix,
+    precision: &str,
+    options: &ComputeOptions,
+    sixteen: bool,
+) -> Result<KernelResult, SolverError> {
+    let (m, k, n) = (a.rows, a.cols, b.cols);
+    let ((acc, dequant), elapsed) = crate::clock::time(options.clock(), || {
+        let (a_q, b_q, dequant) = operands(a, b, precision);
+        let acc = if sixteen {
+            accumulate_16x16(&a_q, &b_q, k, options.accumulator)
+        } else {
+            accumulate(&a_q, &b_q, (m, k, n), options.accumulator)
+        };
+        (acc, dequant)
+    });
+    Ok(match dequant {
+        Some(scale) => {
+            let result = FlatMatrix { data: acc.iter().map(|&x| x as f32 * scale).collect(), rows: m, cols: n };
+            let result = match options.result_columns.as_deref() {
+                Some(columns) => crate::select_columns(&result, columns),
+                None => result,
+            };
+            KernelResult::new(result, elapsed)
+        }
+        None => {
+            let mut result = KernelResult::new(crate::i32_to_f32_matrix(&acc, m, n), elapsed);
+            result.accumulators = Some(acc);
+            result
+        }
+    })
+}
+
+/// Integer operands widened to i16, plus the int8 dequantization factor (None for u8i8)
+fn operands(a: &FlatMatrix, b: &FlatMatrix, precision: &str) -> (Vec<i16>, Vec<i16>, Option<f32>) {
+    if precision == "int8" {
+        let scale_a = crate::int8_scale(a.data.iter().copied());
+        let scale_b = crate::int8_scale(b.data.iter().copied());
+        let a_q = a.data.iter().map(|&x| crate::quantize_i8(x, scale_a) as i16).collect();
+        let b_q = b.data.iter().map(|&x| crate::quantize_i8(x, scale_b) as i16).collect();
+        (a_q, b_q, Some(1.0 / (scale_a * scale_b)))
+    } else {
+        let a_q = a.data.iter().map(|&x| crate::to_u8(x) as i16).collect();
+        let b_q = b.data.iter().map(|&x| x as i8 as i16).collect();
+        (a_q, b_q, None)
+    }
+}
+
+/// Scalar reference: row-major m×k times k×n, clamped to the accumulator's range after every add
+fn accumulate(a: &[i16], b: &[i16], (m, k, n): (usize, usize, usize), accumulator: Accumulator) -> Vec<i32> {
+    let (lo, hi) = accumulator.bounds();
+    let mut acc = vec![0i64; m * n];
+    for i in 0..m {
+        let row = &mut acc[i * n..(i + 1) * n];
+        for p in 0..k {
+            let a_ip = a[i * k + p] as i64;
+            for (c, &b_pj) in row.iter_mut().zip(&b[p * n..(p + 1) * n]) {
+                *c = (*c + a_ip * b_pj as i64).clamp(lo, hi);
+            }
+        }
+    }
+    acc.into_iter().map(|x| x as i32).collect()
+}
+
+#[cfg(not(target_arch = "aarch64"))]
+fn accumulate_16x16(a: &[i16], b: &[i16], k: usize, accumulator: Accumulator) -> Vec<i32> {
+    accumulate(a, b, (16, k, 16), accumulator)
+}
+
+#[cfg(target_arch = "aarch64")]
+fn accumulate_16x16(a: &[i16], b: &[i16], k: usize, accumulator: Accumulator) -> Vec<i32> {
+    use std::arch::aarch64::*;
+
+    assert!(a.len() == 16 * k && b.len() == k * 16);
+    let mut out = vec![0i32; 256];
+    for i in 0..16 {
+        let a_row = &a[i * k..(i + 1) * k];
+        let c = &mut out[i * 16..(i + 1) * 16];
+        // SAFETY: every load reads 16 i16s of row p of b (in bounds by the assert above),
+        // every store writes 16 i32s of c
+        unsafe {
+            match accumulator {
+                Accumulator::I16Saturating => {
+                    let (mut lo, mut hi) = (vdupq_n_s16(0), vdupq_n_s16(0));
+                    for (p, &a_ip) in a_row.iter().enumerate() {
+                        let b_ptr = b.as_ptr().add(p * 16);
+                        lo = vqaddq_s16(lo, vmulq_n_s16(vld1q_s16(b_ptr), a_ip));
+                        hi = vqaddq_s16(hi, vmulq_n_s16(vld1q_s16(b_ptr.add(8)), a_ip));
+                    }
+                    for (q, half) in [lo, hi].into_iter().enumerate() {
+                        vst1q_s32(c.as_mut_ptr().add(q * 8), vmovl_s16(vget_low_s16(half)));
+                        vst1q_s32(c.as_mut_ptr().add(q * 8 + 4), vmovl_high_s16(half));
+                    }
+                }
+                Accumulator::I32 | Accumulator::I32Saturating | Accumulator::I64 => {
+                    let mut acc = [vdupq_n_s32(0); 4];
+                    for (p, &a_ip) in a_row.iter().enumerate() {
+                        let b_ptr = b.as_ptr().add(p * 16);
+                        for (q, half) in [vld1q_s16(b_ptr), vld1q_s16(b_ptr.add(8))].into_iter().enumerate() {
+                            acc[2 * q] = vqaddq_s32(acc[2 * q], vmull_n_s16(vget_low_s16(half), a_ip));
+                            acc[2 * q + 1] = vqaddq_s32(acc[2 * q + 1], vmull_high_n_s16(half, a_ip));
+                        }
+                    }
+                    for (q, v) in acc.into_iter().enumerate() {
+                        vst1q_s32(c.as_mut_ptr().add(q * 4), v);
+                    }
+                }
+            }
+        }
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::types::Input;
+    use crate::{compute_workload_with_options, generate_matrices_from_seed};
+
+    fn options(accumulator: Accumulator) -> ComputeOptions {
+        ComputeOptions { accumulator, ..Default::default() }
+    }
+
+    /// Seed operands whose first k/2 products are large and same-signed, so i16 sums saturate
+    fn saturating_operands(k: usize) -> (FlatMatrix, FlatMatrix) {
+        let (mut a, mut b) = generate_matrices_from_seed(b"saturate", 16, k, k, 16);
+        for i in 0..16 {
+            for p in 0..k / 2 {
+                a.data[i * k + p] = 255.0;
+            }
+        }
+        for p in 0..k / 2 {
+            for j in 0..16 {
+                b.data[p * 16 + j] = if j % 2 == 0 { 127.0 } else { -128.0 };
+            }
+        }
+        (a, b)
+    }
+
+    fn run(a: &FlatMatrix, b: &FlatMatrix, precision: &str, accumulator: Accumulator, kernel: &str) -> crate::types::Output {
+        let input = Input::matmul(a.clone(), b.clone(), precision);
+        let options = ComputeOptions { kernel: Some(kernel.to_string()), ..options(accumulator) };
+        compute_workload_with_options(input, &options).unwrap()
+    }
+
+    #[test]
+    fn test_16x16_matches_scalar_reference() {
+        let (a, b) = saturating_operands(300);
+        for precision in ["u8i8", "int8"] {
+            let (a_q, b_q, _) = operands(&a, &b, precision);
+            for accumulator in [Accumulator::I16Saturating, Accumulator::I32Saturating] {
+                let reference = accumulate(&a_q, &b_q, (16, 300, 16), accumulator);
+                assert_eq!(accumulate_16x16(&a_q, &b_q, 300, accumulator), reference);
+            }
+        }
+    }
+
+    #[test]
+    fn test_i16_saturates_and_kernels_agree() {
+        let (a, b) = saturating_operands(300);
+        let exact = run(&a, &b, "u8i8", Accumulator::I32, "u8i8_generic");
+        let sat_16 = run(&a, &b, "u8i8", Accumulator::I16Saturating, "u8i8_16x16");
+        let sat_generic = run(&a, &b, "u8i8", Accumulator::I16Saturating, "u8i8_generic");
+        assert_eq!(sat_16.result_hash, sat_generic.result_hash);
+        assert_eq!(sat_16.metadata.accumulator.as_deref(), Some("i16_saturating"));
+        assert_eq!(exact.metadata.accumulator, None);
+
+        // The first 150 products are all 32385 (or all -32640) per column, far past the i16
+        // range, so the saturated sums differ from the exact ones
+        assert_ne!(sat_16.result_hash, exact.result_hash);
+        assert!(sat_16.result_matrix.data.iter().all(|&x| (-32768.0..=32767.0).contains(&x)));
+        assert!(exact.result_matrix.data.iter().any(|&x| x > 32767.0));
+
+        // A single product always fits, so k = 1 is exact
+        let (a, b) = generate_matrices_from_seed(b"saturate", 16, 1, 1, 16);
+        let exact = run(&a, &b, "u8i8", Accumulator::I32, "u8i8_16x16");
+        assert_eq!(run(&a, &b, "u8i8", Accumulator::I16Saturating, "u8i8_16x16").result_hash, exact.result_hash);
+    }
+
+    #[test]
+    fn test_int8_kernels_agree_and_verify() {
+        let (a, b) = saturating_operands(1200);
+        let sat_16 = run(&a, &b, "int8", Accumulator::I16Saturating, "int8_16x16");
+        let sat_generic = run(&a, &b, "int8", Accumulator::I16Saturating, "int8_generic");
+        assert_eq!(sat_16.result_hash, sat_generic.result_hash);
+        assert_ne!(sat_16.result_hash, run(&a, &b, "int8", Accumulator::I32, "int8_16x16").result_hash);
+
+        // Verification picks the accumulator up from the metadata
+        assert!(crate::verify_with_metadata(&a, &b, &sat_16.metadata, &sat_16.result_hash).unwrap());
+    }
+
+    #[test]
+    fn test_rejected_for_float_precisions() {
+        let (a, b) = generate_matrices_from_seed(b"saturate", 16, 8, 8, 16);
+        let input = Input::matmul(a, b, "fp32");
+        let err = compute_workload_with_options(input, &options(Accumulator::I16Saturating)).unwrap_err();
+        assert!(err.to_string().contains("int8 and u8i8"), "{}", err);
+        assert!(Accumulator::parse("i8").is_err());
+    }
+}
@@ -0,0 +1,302 @@
+//! Priority admission for API computations.
+//!
+//! Every /compute, /jobs and replay recomputation takes a permit from one `Scheduler`
+//! before it runs, so at most `max_concurrent` computations are in flight. Requests are
+//! classified by their estimated FLOPs (2·m·k·n from the shapes) into three bands: `High`
+//! (seed-sized verifications), `Normal` and `Low` (huge custom workloads). A freed permit
+//! goes to the oldest waiter of the highest non-empty band, except that each lower band
+//! with waiters earns `low_share` of a turn every time it is passed over; once it holds a
+//! whole turn it is served next. With the default share of 0.1 a queued 8192³ request
+//! therefore still gets at least one permit in eleven, however many seeds keep arriving.
+
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, VecDeque};
+use std::sync::{Arc, Mutex};
+use tokio::sync::oneshot;
+
+/// Priority bands, highest first
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Band {
+    High,
+    Normal,
+    Low,
+}
+
+impl Band {
+    pub const ALL: [Band; 3] = [Band::High, Band::Normal, Band::Low];
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Band::High => "high",
+            Band::Normal => "normal",
+            Band::Low => "low",
+        }
+    }
+
+    fn index(self) -> usize {
+        self as usize
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct SchedulerConfig {
+    /// Computations allowed to run at once
+    pub max_concurrent: usize,
+    /// Requests up to this many FLOPs are `High` (the 16×50240×16 seed is 2.6e7)
+    pub high_max_flops: f64,
+    /// Requests up to this many FLOPs are `Normal`, larger ones `Low`
+    pub normal_max_flops: f64,
+    /// Share of a turn a waiting lower band earns each time a higher band is served
+    pub low_share: f64,
+}
+
+impl Default for SchedulerConfig {
+    fn default() -> Self {
+        Self {
+            max_concurrent: std::thread::available_parallelism().map(|n| n.get()).unwrap_or(4),
+            high_max_flops: 1e8,
+            normal_max_flops: 1e11,
+            low_share: 0.1,
+        }
+    }
+}
+
+impl SchedulerConfig {
+    pub fn classify(&self, flops: f64) -> Band {
+        if flops <= self.high_max_flops {
+            Band::High
+        } else if flops <= self.normal_max_flops {
+            Band::Normal
+        } else {
+            Band::Low
+        }
+    }
+}
+
+/// Estimated FLOPs of an m×k by k×n product
+pub fn matmul_flops(m: usize, k: usize, n: usize) -> f64 {
+    2.0 * m as f64 * k as f64 * n as f64
+}
+
+/// Queue depth and lifetime grants of one band, reported by /stats
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct BandStats {
+    /// Requests waiting for a permit
+    pub queued: usize,
+    /// Permits granted so far
+    pub served: u64,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct QueueStats {
+    pub max_concurrent: usize,
+    /// Permits currently held
+    pub running: usize,
+    pub bands: BTreeMap<String, BandStats>,
+}
+
+pub struct Scheduler {
+    config: SchedulerConfig,
+    shared: Arc<Mutex<State>>,
+}
+
+struct State {
+    available: usize,
+    queues: [VecDeque<oneshot::Sender<Permit>>; 3],
+    /// Turns earned by each band while passed over
+    credit: [f64; 3],
+    served: [u64; 3],
+    low_share: f64,
+}
+
+/// A running computation's slot; dropping it hands the slot to the next waiter
+pub struct Permit {
+    shared: Option<Arc<Mutex<State>>>,
+}
+
+impl Scheduler {
+    pub fn new(config: SchedulerConfig) -> Self {
+        let state = State {
+            available: config.max_concurrent.max(1),
+            queues: Default::default(),
+            credit: [0.0; 3],
+            served: [0; 3],
+            low_share: config.low_share,
+        };
+        Self { config, shared: Arc::new(Mutex::new(state)) }
+    }
+
+    pub fn config(&self) -> &SchedulerConfig {
+        &self.config
+    }
+
+    /// Wait for a permit in `band`
+    pub async fn acquire(&self, band: Band) -> Permit {
+        let receiver = {
+            let mut state = self.shared.lock().unwrap();
+            if state.available > 0 && state.queues.iter().all(VecDeque::is_empty) {
+                state.available -= 1;
+                state.grant(band);
+                return Permit { shared: Some(self.shared.clone()) };
+            }
+            let (sender, receiver) = oneshot::channel();
+            state.queues[band.index()].push_back(sender);
+            receiver
+        };
+        // The sender is only dropped after handing over a permit
+        receiver.await.expect("scheduler dropped a waiter")
+    }
+
+    pub fn stats(&self) -> QueueStats {
+        let state = self.shared.lock().unwrap();
+        let max_concurrent = self.config.max_concurrent.max(1);
+        let bands = Band::ALL
+            .iter()
+            .map(|band| {
+                let queue = &state.queues[band.index()];
+                let stats = BandStats {
+                    queued: queue.iter().filter(|sender| !sender.is_closed()).count(),
+                    served: state.served[band.index()],
+                };
+                (band.as_str().to_string(), stats)
+            })
+            .collect();
+        QueueStats { max_concurrent, running: max_concurrent - state.available, bands }
+    }
+}
+
+impl State {
+    fn grant(&mut self, band: Band) {
+        self.served[band.index()] += 1;
+        self.credit[band.index()] = 0.0;
+        for lower in band.index() + 1..3 {
+            if !self.queues[lower].is_empty() {
+                self.credit[lower] += self.low_share;
+            }
+        }
+    }
+
+    /// Band to serve next: a band owed a turn, else the highest with waiters
+    fn next_band(&self) -> Option<Band> {
+        let waiting = |band: &Band| !self.queues[band.index()].is_empty();
+        Band::ALL
+            .iter()
+            .copied()
+            .filter(waiting)
+            .find(|band| self.credit[band.index()] >= 1.0)
+            .or_else(|| Band::ALL.iter().copied().find(waiting))
+    }
+}
+
+impl Drop for Permit {
+    fn drop(&mut self) {
+        let Some(shared) = self.shared.take() else {
+            return;
+        };
+        let mut state = shared.lock().unwrap();
+        while let Some(band) = state.next_band() {
+            let sender = state.queues[band.index()].pop_front().expect("band has waiters");
+            if state.queues[band.index()].is_empty() {
+                state.credit[band.index()] = 0.0;
+            }
+            match sender.send(Permit { shared: Some(shared.clone()) }) {
+                Ok(()) => {
+                    state.grant(band);
+                    return;
+                }
+                // The waiter gave up; disarm the returned permit and try the next one
+                Err(mut permit) => permit.shared = None,
+            }
+        }
+        state.available += 1;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+
+    fn scheduler(max_concurrent: usize, low_share: f64) -> Arc<Scheduler> {
+        Arc::new(Scheduler::new(SchedulerConfig { max_concurrent, low_share, ..Default::default() }))
+    }
+
+    /// Queue `bands` in order behind a held permit, release it, and return the grant order
+    async fn grant_order(scheduler: Arc<Scheduler>, bands: &[(&'static str, Band)]) -> Vec<&'static str> {
+        let held = scheduler.acquire(Band::High).await;
+        let (tx, mut rx) = tokio::sync::mpsc::unbounded_channel();
+        for (queued, &(name, band)) in bands.iter().enumerate() {
+            let (waiter, tx) = (scheduler.clone(), tx.clone());
+            tokio::spawn(async move {
+                let _permit = waiter.acquire(band).await;
+                tx.send(name).unwrap();
+                tokio::time::sleep(Duration::from_millis(2)).await;
+            });
+            // Queue in submission order
+            while scheduler.stats().bands.values().map(|b| b.queued).sum::<usize>() <= queued {
+                tokio::task::yield_now().await;
+            }
+        }
+        drop(held);
+        let mut order = Vec::new();
+        for _ in bands {
+            order.push(rx.recv().await.unwrap());
+        }
+        while scheduler.stats().running > 0 {
+            tokio::time::sleep(Duration::from_millis(1)).await;
+        }
+        order
+    }
+
+    #[tokio::test]
+    async fn test_seed_jobs_overtake_large_ones() {
+        let scheduler = scheduler(1, 0.1);
+        let config = scheduler.config().clone();
+        let large = config.classify(matmul_flops(8192, 8192, 8192));
+        let seed = config.classify(matmul_flops(16, 50240, 16));
+        assert_eq!((seed, large), (Band::High, Band::Low));
+        assert_eq!(config.classify(matmul_flops(1024, 1024, 1024)), Band::Normal);
+
+        let order = grant_order(
+            scheduler.clone(),
+            &[("large-1", large), ("large-2", large), ("seed-1", seed), ("seed-2", seed), ("seed-3", seed)],
+        )
+        .await;
+        assert_eq!(order, ["seed-1", "seed-2", "seed-3", "large-1", "large-2"]);
+
+        let stats = scheduler.stats();
+        assert_eq!((stats.running, stats.max_concurrent), (0, 1));
+        assert_eq!(stats.bands["low"], BandStats { queued: 0, served: 2 });
+        assert_eq!(stats.bands["high"].served, 4);
+    }
+
+    #[tokio::test]
+    async fn test_low_band_share_prevents_starvation() {
+        // Half a turn per pass: the large job runs after two seeds despite more seeds waiting
+        let mut bands = vec![("large", Band::Low)];
+        bands.extend(["s1", "s2", "s3", "s4", "s5"].map(|name| (name, Band::High)));
+        let order = grant_order(scheduler(1, 0.5), &bands).await;
+        assert_eq!(order, ["s1", "s2", "large", "s3", "s4", "s5"]);
+    }
+
+    #[tokio::test]
+    async fn test_cancelled_waiters_are_skipped() {
+        let scheduler = scheduler(1, 0.1);
+        let held = scheduler.acquire(Band::High).await;
+        let waiting = {
+            let scheduler = scheduler.clone();
+            tokio::spawn(async move { scheduler.acquire(Band::Low).await })
+        };
+        while scheduler.stats().bands["low"].queued == 0 {
+            tokio::task::yield_now().await;
+        }
+        waiting.abort();
+        let _ = waiting.await;
+        assert_eq!(scheduler.stats().bands["low"].queued, 0);
+        drop(held);
+        // The slot came back rather than going to the aborted waiter
+        assert_eq!(scheduler.stats().running, 0);
+        let _permit = tokio::time::timeout(Duration::from_secs(1), scheduler.acquire(Band::Normal)).await.unwrap();
+    }
+}
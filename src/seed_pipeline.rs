@@ -0,0 +1,165 @@
+//! Pipelined seed generation for the u8i8 seed workload (`ComputeOptions::seed_pipeline`).
+//!
+//! On fast cores, generating the 1.6 MB XOF stream of a 16×50240 seed costs about as much
+//! as the u8i8 kernel that consumes it, and `pack_seed_u8i8` runs the two strictly one
+//! after the other. The kernel reduces over k in order, and the XOF can be read from any
+//! position, so here a producer thread fills k slices of A (16 row segments) and B (a run
+//! of rows) into a small ring of buffers while the calling thread accumulates each
+//! completed slice. Slices add exact i32 partial sums, so the result is bit-identical to
+//! the sequential path. Between slices the consumer checks `ComputeOptions::cancel` and the
+//! deadline, as chunked kernels do; `kernel_time` covers the whole overlapped run.
+//!
+//! Only runs that `compute_seed_workload` would pack directly use the pipeline, and only
+//! when k spans more than one slice.
+
+use crate::{chunked, seed_layout, AlignedBufferI8, AlignedBufferU8, ComputeOptions, KernelResult, SolverError};
+use std::sync::mpsc::{sync_channel, Receiver, SyncSender};
+
+/// k steps per slice: 64 KiB of A and of B
+pub const SLICE_K: usize = 4096;
+/// Slices in flight between the producer and the kernel
+const RING: usize = 4;
+
+/// One k slice of packed operands: A as 16 rows of `len`, B as `len` rows of 16
+struct Slice {
+    a_u8: AlignedBufferU8,
+    b_i8: AlignedBufferI8,
+    len: usize,
+}
+
+/// Whether a seed run that packs directly should go through the pipeline
+pub(crate) fn applies(k: usize, options: &ComputeOptions) -> bool {
+    options.seed_pipeline && k > SLICE_K
+}
+
+/// The u8i8_16x16 result for the 16×k × k×16 matrices of `seed`, generated and
+/// accumulated slice by slice
+pub(crate) fn run(seed: &[u8], k: usize, options: &ComputeOptions) -> Result<KernelResult, SolverError> {
+    let clock = options.clock();
+    let start = clock.now();
+    let (free_tx, free_rx) = sync_channel::<Slice>(RING);
+    let (full_tx, full_rx) = sync_channel::<Slice>(RING);
+    for _ in 0..RING {
+        let slice = Slice { a_u8: AlignedBufferU8::new(16 * SLICE_K, 64), b_i8: AlignedBufferI8::new(16 * SLICE_K, 64), len: 0 };
+        free_tx.send(slice).expect("the ring has room for every slice");
+    }
+
+    let mut acc = vec![0i32; 16 * 16];
+    std::thread::scope(|scope| {
+        scope.spawn(|| produce(seed, k, free_rx, full_tx));
+        let total = k.div_ceil(SLICE_K);
+        let consumed = (0..total).try_for_each(|index| {
+            if index > 0 {
+                chunked::check_interrupt(options, start, index, total)?;
+            }
+            let slice = full_rx
+                .recv()
+                .map_err(|_| SolverError::Internal("seed producer stopped early".to_string()))?;
+            crate::u8i8_16x16_accumulate(&slice.a_u8, &slice.b_i8, slice.len, &mut acc);
+            // The producer may already have finished and dropped its end
+            let _ = free_tx.send(slice);
+            Ok(())
+        });
+        // Hang up before the scope joins, so a producer waiting on either channel returns
+        drop(free_tx);
+        drop(full_rx);
+        consumed
+    })?;
+
+    let mut result = KernelResult::new(crate::i32_to_f32_matrix(&acc, 16, 16), clock.elapsed_since(start));
+    result.pack_cache_hit = Some(false);
+    result.accumulators = Some(acc);
+    Ok(result)
+}
+
+/// Fill free slices in k order until k is covered or the consumer hangs up
+fn produce(seed: &[u8], k: usize, free: Receiver<Slice>, full: SyncSender<Slice>) {
+    let layout = seed_layout((16, k), (k, 16));
+    let xof = crate::seed_xof(seed);
+    // One reader per A row and one for B, each left where the previous slice stopped
+    let mut a_rows: Vec<blake3::OutputReader> = (0..16)
+        .map(|i| {
+            let mut reader = xof.clone();
+            reader.set_position((layout.a_offset + i * k) as u64);
+            reader
+        })
+        .collect();
+    let mut b_rows = xof;
+    b_rows.set_position(layout.b_offset as u64);
+
+    for begin in (0..k).step_by(SLICE_K) {
+        let Ok(mut slice) = free.recv() else { return };
+        let len = SLICE_K.min(k - begin);
+        let a = slice.a_u8.as_mut_slice();
+        for (i, reader) in a_rows.iter_mut().enumerate() {
+            reader.fill(&mut a[i * len..(i + 1) * len]);
+        }
+        let b = &mut slice.b_i8.as_mut_bytes()[..16 * len];
+        b_rows.fill(b);
+        for byte in b {
+            // Same bits as `byte.wrapping_sub(128) as i8`, as in `pack_seed_u8i8`
+            *byte = byte.wrapping_sub(128);
+        }
+        slice.len = len;
+        if full.send(slice).is_err() {
+            return;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{compute_seed_workload, Accumulator, Redundancy};
+    use std::sync::atomic::AtomicBool;
+    use std::sync::Arc;
+
+    fn pipelined() -> ComputeOptions {
+        ComputeOptions { seed_pipeline: true, ..Default::default() }
+    }
+
+    #[test]
+    fn test_pipelined_hash_matches_sequential() {
+        // One slice plus one step, a few full slices with a short tail, the seed shape
+        for (seed, k) in [(&b"first"[..], SLICE_K + 1), (b"second", 3 * SLICE_K + 17), (b"third", 50240)] {
+            for hash_input in [None, Some("i32le")] {
+                let hash_input = hash_input.map(str::to_string);
+                let sequential = compute_seed_workload(seed, (16, k, 16), "u8i8", None, hash_input.clone(), &ComputeOptions::default()).unwrap();
+                let output = compute_seed_workload(seed, (16, k, 16), "u8i8", None, hash_input, &pipelined()).unwrap();
+                assert_eq!(output.result_hash, sequential.result_hash, "k={}", k);
+                assert_eq!(output.result_matrix.data, sequential.result_matrix.data);
+                assert_eq!(output.metrics.seed_pipelined, Some(true));
+                assert_eq!(sequential.metrics.seed_pipelined, None);
+                assert_eq!(output.metadata.kernel.as_deref(), Some("u8i8_16x16"));
+            }
+        }
+    }
+
+    #[test]
+    fn test_pipeline_only_where_supported() {
+        let cases = [
+            ("u8i8", (16, SLICE_K, 16), pipelined()),
+            ("u8i8", (16, 2 * SLICE_K, 24), pipelined()),
+            ("u8i8", (8, 2 * SLICE_K, 16), pipelined()),
+            ("int8", (16, 2 * SLICE_K, 16), pipelined()),
+            ("fp32", (16, 2 * SLICE_K, 16), pipelined()),
+            ("u8i8", (16, 2 * SLICE_K, 16), ComputeOptions { k_chunk: Some(SLICE_K), ..pipelined() }),
+            ("u8i8", (16, 2 * SLICE_K, 16), ComputeOptions { accumulator: Accumulator::I32Saturating, ..pipelined() }),
+            ("u8i8", (16, 2 * SLICE_K, 16), ComputeOptions { redundancy: Redundancy::Dual, ..pipelined() }),
+            ("u8i8", (16, 2 * SLICE_K, 16), ComputeOptions { kernel: Some("u8i8_generic".to_string()), ..pipelined() }),
+            ("u8i8", (16, 2 * SLICE_K, 16), ComputeOptions::default()),
+        ];
+        for (precision, dims, options) in cases {
+            let output = compute_seed_workload(b"unsupported", dims, precision, None, None, &options).unwrap();
+            assert_eq!(output.metrics.seed_pipelined, None, "{} {:?}", precision, dims);
+        }
+    }
+
+    #[test]
+    fn test_cancel_stops_the_producer() {
+        let cancel = Arc::new(AtomicBool::new(true));
+        let options = ComputeOptions { cancel: Some(cancel), ..pipelined() };
+        let err = compute_seed_workload(b"cancel", (16, 8 * SLICE_K, 16), "u8i8", None, None, &options).unwrap_err();
+        assert!(matches!(err, SolverError::Cancelled(_)), "{}", err);
+    }
+}
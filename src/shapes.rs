@@ -0,0 +1,264 @@
+//! Shape-specialized kernels and dispatch counters.
+//!
+//! Besides the seed's 16x16 output, workloads often produce a handful of other small fixed
+//! shapes (32x32, 64x16, ...). For each (M, N) listed in `shape_kernels!` this module
+//! registers a dot-product kernel per precision with M and N as const generics, so the row
+//! and column loops are fixed-trip and B is transposed once into N contiguous columns (a
+//! B supplied as Bᵀ already is, and is read without that copy).
+//! Adding a shape is one more line in the invocation at the bottom of this module. 16x16 is
+//! left to the hand-written 16x16 kernels, which outrank these.
+//!
+//! u8i8 specializations sum exactly like `u8i8_generic` and give bit-identical results
+//! (other accumulators and k beyond `U8I8_SAFE_K` run on `u8i8_generic` itself). fp32 ones sum
+//! each dot product in four lanes, so they agree with `fp32_blocked` to within rounding.
+//!
+//! `dispatch_stats` counts, per kernel, the workloads of a specialized precision computed by
+//! a shape specialization and those that fell back to another kernel (also in `/stats`).
+
+use crate::kernels::{KernelResult, MatmulKernel};
+use crate::{Accumulator, ComputeOptions, FlatMatrix, SolverError, U8I8_SAFE_K};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::sync::{Arc, Mutex};
+
+/// Priority of the specializations: above openblas (10), below the 16x16 kernels (20)
+const PRIORITY: i32 = 15;
+
+/// Precisions with shape specializations
+pub const SPECIALIZED_PRECISIONS: [&str; 2] = ["fp32", "u8i8"];
+
+#[derive(Debug, Clone, Copy)]
+enum Kind {
+    Fp32,
+    U8I8,
+}
+
+/// Dot-product kernel for an M×k × k×N workload
+struct ShapeKernel<const M: usize, const N: usize> {
+    name: &'static str,
+    kind: Kind,
+}
+
+impl<const M: usize, const N: usize> MatmulKernel for ShapeKernel<M, N> {
+    fn name(&self) -> &str {
+        self.name
+    }
+
+    fn precisions(&self) -> &[&str] {
+        match self.kind {
+            Kind::Fp32 => &["fp32"],
+            Kind::U8I8 => &["u8i8"],
+        }
+    }
+
+    fn supports_shape(&self, a_shape: (usize, usize), b_shape: (usize, usize)) -> bool {
+        a_shape.0 == M && b_shape.1 == N && a_shape.1 == b_shape.0
+    }
+
+    fn priority(&self) -> i32 {
+        PRIORITY
+    }
+
+    fn run(&self, a: &FlatMatrix, b: &FlatMatrix, options: &ComputeOptions) -> Result<KernelResult, SolverError> {
+        match self.kind {
+            Kind::Fp32 => {
+                let (data, t) = crate::clock::time(options.clock(), || fp32_dot::<M, N>(a, &columns(b, |x| x)));
+                Ok(KernelResult::new(FlatMatrix { data, rows: M, cols: N }, t))
+            }
+            Kind::U8I8 if options.accumulator != Accumulator::I32 || a.cols > U8I8_SAFE_K => {
+                crate::kernels::u8i8_generic(a, b, options)
+            }
+            Kind::U8I8 => u8i8_result::<M, N>(options, || u8i8_dot::<M, N>(a, &columns(b, |x| x as i8))),
+        }
+    }
+
+    /// Bᵀ (N×k) already holds the N contiguous columns, so it is read as is
+    fn run_b_transposed(&self, a: &FlatMatrix, b_t: &FlatMatrix, options: &ComputeOptions) -> Result<KernelResult, SolverError> {
+        match self.kind {
+            Kind::Fp32 => {
+                let (data, t) = crate::clock::time(options.clock(), || fp32_dot::<M, N>(a, &b_t.data));
+                Ok(KernelResult::new(FlatMatrix { data, rows: M, cols: N }, t))
+            }
+            Kind::U8I8 if options.accumulator != Accumulator::I32 || a.cols > U8I8_SAFE_K => {
+                crate::kernels::u8i8_generic(a, &crate::transpose(b_t), options)
+            }
+            Kind::U8I8 => u8i8_result::<M, N>(options, || {
+                u8i8_dot::<M, N>(a, &b_t.data.iter().map(|&x| x as i8).collect::<Vec<_>>())
+            }),
+        }
+    }
+}
+
+fn u8i8_result<const M: usize, const N: usize>(options: &ComputeOptions, dot: impl FnOnce() -> Vec<i32>) -> Result<KernelResult, SolverError> {
+    let (acc, t) = crate::clock::time(options.clock(), dot);
+    let mut result = KernelResult::new(crate::i32_to_f32_matrix(&acc, M, N), t);
+    result.accumulators = Some(acc);
+    result.simd_variant = Some(crate::int_dot_variant());
+    Ok(result)
+}
+
+/// B (k×N) as N contiguous columns of k
+fn columns<T>(b: &FlatMatrix, convert: impl Fn(f32) -> T) -> Vec<T> {
+    let (k, n) = (b.rows, b.cols);
+    (0..n).flat_map(|j| (0..k).map(move |p| (j, p))).map(|(j, p)| convert(b.data[p * n + j])).collect()
+}
+
+/// A (M×k) times the N columns of B in `b_t`
+fn fp32_dot<const M: usize, const N: usize>(a: &FlatMatrix, b_t: &[f32]) -> Vec<f32> {
+    let k = a.cols;
+    let mut out = vec![0.0f32; M * N];
+    for i in 0..M {
+        let a_row = &a.data[i * k..(i + 1) * k];
+        for j in 0..N {
+            let b_col = &b_t[j * k..(j + 1) * k];
+            let mut lanes = [0.0f32; 4];
+            let mut a_quads = a_row.chunks_exact(4);
+            for (x, y) in (&mut a_quads).zip(b_col.chunks_exact(4)) {
+                for l in 0..4 {
+                    lanes[l] += x[l] * y[l];
+                }
+            }
+            let mut sum = (lanes[0] + lanes[1]) + (lanes[2] + lanes[3]);
+            let tail = k - a_quads.remainder().len();
+            for p in tail..k {
+                sum += a_row[p] * b_col[p];
+            }
+            out[i * N + j] = sum;
+        }
+    }
+    out
+}
+
+/// Exact i32 sums of A (M×k) with the N columns of B in `b_t`; k must not exceed `U8I8_SAFE_K`
+fn u8i8_dot<const M: usize, const N: usize>(a: &FlatMatrix, b_t: &[i8]) -> Vec<i32> {
+    let k = a.cols;
+    let a_u8: Vec<u8> = a.data.iter().map(|&x| crate::to_u8(x)).collect();
+    let mut out = vec![0i32; M * N];
+    for i in 0..M {
+        for j in 0..N {
+            out[i * N + j] = crate::dot_u8i8(a_u8[i * k..].as_ptr(), b_t[j * k..].as_ptr(), k);
+        }
+    }
+    out
+}
+
+/// How often each kernel computed a workload of a specialized precision
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct DispatchStats {
+    /// Runs per shape specialization
+    pub specialized: BTreeMap<String, u64>,
+    /// Runs per other kernel, for shapes without a specialization
+    pub fallback: BTreeMap<String, u64>,
+}
+
+static STATS: Mutex<DispatchStats> = Mutex::new(DispatchStats { specialized: BTreeMap::new(), fallback: BTreeMap::new() });
+
+/// Count one computed workload of `precision` on `kernel`
+pub(crate) fn record_dispatch(precision: &str, kernel: &str) {
+    if !SPECIALIZED_PRECISIONS.contains(&precision) {
+        return;
+    }
+    let mut stats = STATS.lock().unwrap();
+    let counts = if is_specialization(kernel) { &mut stats.specialized } else { &mut stats.fallback };
+    *counts.entry(kernel.to_string()).or_default() += 1;
+}
+
+/// Dispatch counts since the process started
+pub fn dispatch_stats() -> DispatchStats {
+    STATS.lock().unwrap().clone()
+}
+
+/// Whether `kernel` is one of this module's specializations
+pub fn is_specialization(kernel: &str) -> bool {
+    let shape = SPECIALIZED_PRECISIONS.iter().find_map(|p| kernel.strip_prefix(p)?.strip_prefix("_dot_"));
+    shape.is_some_and(|shape| SHAPES.iter().any(|(m, n)| shape == format!("{}x{}", m, n)))
+}
+
+macro_rules! shape_kernels {
+    ($(($m:literal, $n:literal)),* $(,)?) => {
+        /// Output shapes (M, N) with specialized kernels
+        pub const SHAPES: &[(usize, usize)] = &[$(($m, $n)),*];
+
+        /// One kernel per specialized precision and shape
+        pub(crate) fn kernels() -> Vec<Arc<dyn MatmulKernel>> {
+            vec![$(
+                Arc::new(ShapeKernel::<$m, $n> { name: concat!("fp32_dot_", $m, "x", $n), kind: Kind::Fp32 }) as Arc<dyn MatmulKernel>,
+                Arc::new(ShapeKernel::<$m, $n> { name: concat!("u8i8_dot_", $m, "x", $n), kind: Kind::U8I8 }),
+            )*]
+        }
+    };
+}
+
+shape_kernels! {
+    (16, 32),
+    (32, 16),
+    (32, 32),
+    (64, 16),
+    (16, 64),
+    (64, 32),
+    (32, 64),
+    (64, 64),
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::kernels::kernel_by_name;
+    use crate::{compute_workload_with_options, generate_matrices_from_seed, types};
+
+    fn input(precision: &str, (m, k, n): (usize, usize, usize)) -> types::Input {
+        let (matrix_a, matrix_b) = generate_matrices_from_seed(b"shapes", m, k, k, n);
+        types::Input::matmul(matrix_a, matrix_b, precision)
+    }
+
+    #[test]
+    fn test_specializations_match_reference_kernels() {
+        for &(m, n) in SHAPES {
+            // k = 203 leaves a tail after the fp32 quads
+            let input = input("fp32", (m, 203, n));
+            let (a, b) = (&input.matrix_a, &input.matrix_b);
+            let options = ComputeOptions::default();
+
+            let name = format!("u8i8_dot_{}x{}", m, n);
+            let kernel = kernel_by_name(&name).unwrap();
+            assert!(kernel.supports("u8i8", (m, 203), (203, n)) && !kernel.supports("u8i8", (m, 203), (203, n + 1)));
+            let special = kernel.run(a, b, &options).unwrap();
+            let reference = crate::kernels::u8i8_generic(a, b, &options).unwrap();
+            assert_eq!(special.result.data, reference.result.data, "{}", name);
+            assert_eq!(special.accumulators, reference.accumulators, "{}", name);
+
+            let name = format!("fp32_dot_{}x{}", m, n);
+            let special = kernel_by_name(&name).unwrap().run(a, b, &options).unwrap().result;
+            let reference = kernel_by_name("fp32_blocked").unwrap().run(a, b, &options).unwrap().result;
+            let scale = reference.data.iter().fold(1.0f32, |s, x| s.max(x.abs()));
+            for (x, y) in special.data.iter().zip(&reference.data) {
+                assert!((x - y).abs() <= 1e-5 * scale, "{}: {} vs {}", name, x, y);
+            }
+        }
+    }
+
+    #[test]
+    fn test_dispatch_counters() {
+        // 64x32 is only computed through the dispatcher by this test
+        let before = dispatch_stats();
+        let count = |stats: &DispatchStats, name: &str| stats.specialized.get(name).copied().unwrap_or(0);
+        for precision in ["u8i8", "fp32", "u8i8"] {
+            let output = compute_workload_with_options(input(precision, (64, 40, 32)), &ComputeOptions::default()).unwrap();
+            assert_eq!(output.metadata.kernel, Some(format!("{}_dot_64x32", precision)));
+        }
+        // Other accumulators run the generic kernel but still dispatch to the specialization
+        let saturating = ComputeOptions { accumulator: Accumulator::I16Saturating, ..Default::default() };
+        compute_workload_with_options(input("u8i8", (64, 40, 32)), &saturating).unwrap();
+        let after = dispatch_stats();
+        assert_eq!(count(&after, "u8i8_dot_64x32") - count(&before, "u8i8_dot_64x32"), 3);
+        assert_eq!(count(&after, "fp32_dot_64x32") - count(&before, "fp32_dot_64x32"), 1);
+
+        // A shape without a specialization is a fallback; unspecialized precisions are not counted
+        compute_workload_with_options(input("u8i8", (63, 40, 32)), &ComputeOptions::default()).unwrap();
+        compute_workload_with_options(input("int8", (63, 40, 32)), &ComputeOptions::default()).unwrap();
+        let last = dispatch_stats();
+        assert!(last.fallback["u8i8_generic"] > before.fallback.get("u8i8_generic").copied().unwrap_or(0));
+        assert!(!last.fallback.contains_key("int8_generic") && !last.specialized.contains_key("int8_generic"));
+        assert!(is_specialization("fp32_dot_32x32") && !is_specialization("fp32_16x16"));
+    }
+}
@@ -0,0 +1,281 @@
+//! SIMD paths of the dot products and the u8i8 16x16 inner loop.
+//!
+//! The integer dot products (`dot_i8`, `dot_u8i8`), the fp16 kernels' `dot_f32` and the
+//! u8i8 16x16 accumulator each have one implementation per instruction set. Which one runs
+//! is decided once per process: the first call detects the CPU's features
+//! (`kernels::DetectedFeatures`) and stores the best variant's function pointers in a
+//! `OnceLock`, so a single binary built for the baseline target still takes AVX-512 VNNI,
+//! AVX2, USMMLA or SDOT where the CPU has them. Every kernel built on these paths records the
+//! variant as `OutputMetadata::simd_variant`.
+//!
+//! `force` picks a variant instead (the CLI's `--simd`, the API's `SIMD_VARIANT`), e.g. to
+//! A/B a path against another on the same machine. It must run before anything computes,
+//! since the choice is process-wide and cannot change afterwards. Integer results are
+//! exact on every path, so their hashes do not depend on the variant. `dot_f32` keeps the
+//! scalar loop's k order on x86-64, since fp16 hashes are verified on other machines and a
+//! vector reduction would tie them to the CPU; only NEON sums it in four lanes.
+
+use crate::kernels::DetectedFeatures;
+use crate::SolverError;
+use std::sync::OnceLock;
+
+/// One set of SIMD paths, in order of preference
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum SimdVariant {
+    /// AVX-512 VNNI (VPDPBUSD); the tails on AVX2
+    Avx512Vnni,
+    /// AVX2; `dot_f32` stays the in-order loop, so fp16 hashes match `Scalar`
+    Avx2,
+    /// Armv8.6 int8 matrix multiply (USMMLA) for the u8i8 16x16 inner loop; the dot
+    /// products as `Dotprod`
+    I8mm,
+    /// Armv8.2 dot product instructions (SDOT); `dot_f32` on NEON
+    Dotprod,
+    /// Widening NEON loops
+    Neon,
+    /// Plain loops, on every target
+    Scalar,
+}
+
+impl SimdVariant {
+    pub const ALL: [SimdVariant; 6] = [SimdVariant::Avx512Vnni, SimdVariant::Avx2, SimdVariant::I8mm, SimdVariant::Dotprod, SimdVariant::Neon, SimdVariant::Scalar];
+
+    pub fn parse(s: &str) -> Result<Self, SolverError> {
+        match s {
+            "avx512vnni" => Ok(SimdVariant::Avx512Vnni),
+            "avx2" => Ok(SimdVariant::Avx2),
+            "i8mm" => Ok(SimdVariant::I8mm),
+            "dotprod" => Ok(SimdVariant::Dotprod),
+            "neon" => Ok(SimdVariant::Neon),
+            "scalar" => Ok(SimdVariant::Scalar),
+            _ => Err(SolverError::InvalidInput(format!("Unknown SIMD variant: {} (expected avx512vnni, avx2, i8mm, dotprod, neon or scalar)", s))),
+        }
+    }
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            SimdVariant::Avx512Vnni => "avx512vnni",
+            SimdVariant::Avx2 => "avx2",
+            SimdVariant::I8mm => "i8mm",
+            SimdVariant::Dotprod => "dotprod",
+            SimdVariant::Neon => "neon",
+            SimdVariant::Scalar => "scalar",
+        }
+    }
+
+    /// CPU features the variant needs, as `DetectedFeatures` names them
+    pub fn required_features(&self) -> &'static [&'static str] {
+        match self {
+            SimdVariant::Avx512Vnni => &["avx2", "avx512f", "avx512vnni"],
+            SimdVariant::Avx2 => &["avx2"],
+            SimdVariant::I8mm => &["neon", "dotprod", "i8mm"],
+            SimdVariant::Dotprod => &["neon", "dotprod"],
+            SimdVariant::Neon => &["neon"],
+            SimdVariant::Scalar => &[],
+        }
+    }
+
+    /// Whether this build can run the variant on this CPU
+    pub fn is_supported(&self) -> bool {
+        let features = DetectedFeatures::current();
+        self.required_features().iter().all(|f| features.has(f))
+    }
+}
+
+/// The best supported variant on this CPU
+pub fn detect() -> SimdVariant {
+    SimdVariant::ALL.into_iter().find(SimdVariant::is_supported).unwrap_or(SimdVariant::Scalar)
+}
+
+/// Function pointers of one variant. Each requires the variant's CPU features, which
+/// `for_variant` callers check first.
+#[derive(Debug, Clone, Copy)]
+pub(crate) struct SimdPaths {
+    pub variant: SimdVariant,
+    pub dot_i8: unsafe fn(*const i8, *const i8, usize) -> i32,
+    pub dot_u8i8: unsafe fn(*const u8, *const i8, usize) -> i32,
+    /// Adds packed rows×k A times packed k×16 B into rows×16 accumulators; rows is a
+    /// multiple of four (16 for the whole seed product)
+    pub u8i8_16x16: unsafe fn(*const u8, *const i8, usize, usize, *mut i32),
+    #[cfg(feature = "fp16")]
+    pub dot_f32: unsafe fn(*const f32, *const f32, usize) -> f32,
+    /// Whether `dot_f32` adds its products one at a time in k order (the scalar loop)
+    #[cfg(all(feature = "fp16", test))]
+    pub f32_in_order: bool,
+}
+
+impl SimdPaths {
+    /// The paths of `variant`; variants of another architecture get the scalar ones
+    pub(crate) fn for_variant(variant: SimdVariant) -> Self {
+        match variant {
+            #[cfg(target_arch = "x86_64")]
+            SimdVariant::Avx512Vnni => {
+                let mut paths = Self::for_variant(SimdVariant::Avx2);
+                paths.variant = variant;
+                paths.dot_i8 = crate::dot_i8_vnni;
+                paths.dot_u8i8 = crate::dot_u8i8_vnni;
+                paths.u8i8_16x16 = crate::u8i8_16x16_accumulate_vnni;
+                paths
+            }
+            #[cfg(target_arch = "x86_64")]
+            SimdVariant::Avx2 => {
+                Self {
+                    variant,
+                    dot_i8: crate::dot_i8_avx2,
+                    dot_u8i8: crate::dot_u8i8_avx2,
+                    u8i8_16x16: crate::u8i8_16x16_accumulate_avx2,
+                    #[cfg(feature = "fp16")]
+                    dot_f32: crate::dot_f32_scalar,
+                    #[cfg(all(feature = "fp16", test))]
+                    f32_in_order: true,
+                }
+            }
+            #[cfg(target_arch = "aarch64")]
+            SimdVariant::I8mm => {
+                let mut paths = Self::for_variant(SimdVariant::Dotprod);
+                paths.variant = variant;
+                paths.u8i8_16x16 = crate::u8i8_16x16_accumulate_i8mm;
+                paths
+            }
+            #[cfg(target_arch = "aarch64")]
+            SimdVariant::Dotprod => {
+                let mut paths = Self::for_variant(SimdVariant::Neon);
+                paths.variant = variant;
+                paths.dot_i8 = crate::dot_i8_dotprod;
+                paths.dot_u8i8 = crate::dot_u8i8_dotprod;
+                paths.u8i8_16x16 = crate::u8i8_16x16_accumulate_dotprod;
+                paths
+            }
+            #[cfg(target_arch = "aarch64")]
+            SimdVariant::Neon => Self {
+                variant,
+                dot_i8: crate::dot_i8_neon,
+                dot_u8i8: crate::dot_u8i8_neon,
+                u8i8_16x16: crate::u8i8_16x16_accumulate_neon,
+                #[cfg(feature = "fp16")]
+                dot_f32: crate::dot_f32_neon,
+                #[cfg(all(feature = "fp16", test))]
+                f32_in_order: false,
+            },
+            _ => Self {
+                variant: SimdVariant::Scalar,
+                dot_i8: crate::dot_i8_scalar,
+                dot_u8i8: crate::dot_u8i8_scalar,
+                u8i8_16x16: crate::u8i8_16x16_accumulate_scalar,
+                #[cfg(feature = "fp16")]
+                dot_f32: crate::dot_f32_scalar,
+                #[cfg(all(feature = "fp16", test))]
+                f32_in_order: true,
+            },
+        }
+    }
+}
+
+static PATHS: OnceLock<SimdPaths> = OnceLock::new();
+
+/// The paths every dispatch goes through, detected on first use unless `force`d earlier
+#[inline(always)]
+pub(crate) fn paths() -> &'static SimdPaths {
+    PATHS.get_or_init(|| SimdPaths::for_variant(detect()))
+}
+
+/// The variant this process runs
+pub fn active() -> SimdVariant {
+    paths().variant
+}
+
+/// Run every later dispatch on `variant` instead of the detected one. Fails if the CPU
+/// lacks its features, or if another variant is already in use (something computed, or
+/// was forced, first); forcing the variant already in use is a no-op.
+pub fn force(variant: SimdVariant) -> Result<(), SolverError> {
+    if !variant.is_supported() {
+        let missing: Vec<&str> = variant.required_features().iter().copied().filter(|f| !DetectedFeatures::current().has(f)).collect();
+        return Err(SolverError::KernelUnavailable(format!("SIMD variant '{}' requires {}", variant.as_str(), missing.join(", "))));
+    }
+    let active = PATHS.get_or_init(|| SimdPaths::for_variant(variant)).variant;
+    if active != variant {
+        return Err(SolverError::InvalidInput(format!(
+            "cannot force SIMD variant '{}': '{}' is already in use",
+            variant.as_str(),
+            active.as_str()
+        )));
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_variant_names_round_trip() {
+        for variant in SimdVariant::ALL {
+            assert_eq!(SimdVariant::parse(variant.as_str()).unwrap(), variant);
+        }
+        let err = SimdVariant::parse("sse").unwrap_err().to_string();
+        assert!(err.contains("Unknown SIMD variant: sse"), "{}", err);
+        assert!(SimdVariant::Scalar.is_supported());
+        assert!(detect().is_supported());
+        assert_eq!(SimdVariant::Neon.is_supported(), cfg!(target_arch = "aarch64"));
+        assert!(!SimdVariant::Avx2.is_supported() || cfg!(target_arch = "x86_64"));
+    }
+
+    #[test]
+    fn test_every_supported_variant_matches_scalar() {
+        let scalar = SimdPaths::for_variant(SimdVariant::Scalar);
+        let bytes: Vec<u8> = (0..1000u32).map(|i| (i.wrapping_mul(2654435761) >> 13) as u8).collect();
+        let codes: Vec<i8> = bytes.iter().rev().map(|&b| b as i8).collect();
+        let signed: Vec<i8> = bytes.iter().map(|&b| b.wrapping_mul(7) as i8).collect();
+        for variant in SimdVariant::ALL.into_iter().filter(SimdVariant::is_supported) {
+            let paths = SimdPaths::for_variant(variant);
+            assert_eq!(paths.variant, variant);
+            for len in [0, 1, 15, 16, 17, 63, 64, 65, 999, 1000] {
+                unsafe {
+                    assert_eq!((paths.dot_i8)(signed.as_ptr(), codes.as_ptr(), len), (scalar.dot_i8)(signed.as_ptr(), codes.as_ptr(), len), "{:?} {}", variant, len);
+                    assert_eq!((paths.dot_u8i8)(bytes.as_ptr(), codes.as_ptr(), len), (scalar.dot_u8i8)(bytes.as_ptr(), codes.as_ptr(), len), "{:?} {}", variant, len);
+                }
+            }
+            for k in [1, 4, 7, 62] {
+                let (a, b) = (&bytes[..16 * k], &codes[..k * 16]);
+                let mut simd: Vec<i32> = (0..256).collect();
+                let mut plain = simd.clone();
+                unsafe {
+                    (paths.u8i8_16x16)(a.as_ptr(), b.as_ptr(), k, 16, simd.as_mut_ptr());
+                    (scalar.u8i8_16x16)(a.as_ptr(), b.as_ptr(), k, 16, plain.as_mut_ptr());
+                }
+                assert_eq!(simd, plain, "{:?} k = {}", variant, k);
+            }
+        }
+    }
+
+    #[cfg(feature = "fp16")]
+    #[test]
+    fn test_dot_f32_is_bit_identical_off_neon() {
+        // fp16 hashes are checked on other machines, so only NEON may reorder the f32 sums
+        let a: Vec<f32> = (0..100).map(|i| (i as f32 * 0.37).sin()).collect();
+        let b: Vec<f32> = (0..100).map(|i| (i as f32 * 0.11).cos()).collect();
+        let scalar = SimdPaths::for_variant(SimdVariant::Scalar);
+        for variant in SimdVariant::ALL.into_iter().filter(|v| v.is_supported() && !v.required_features().contains(&"neon")) {
+            let paths = SimdPaths::for_variant(variant);
+            assert!(paths.f32_in_order, "{:?}", variant);
+            for len in [0, 7, 8, 9, 100] {
+                let (simd, plain) = unsafe { ((paths.dot_f32)(a.as_ptr(), b.as_ptr(), len), (scalar.dot_f32)(a.as_ptr(), b.as_ptr(), len)) };
+                assert_eq!(simd.to_bits(), plain.to_bits(), "{:?} {}", variant, len);
+            }
+        }
+    }
+
+    #[test]
+    fn test_force_rejects_a_second_variant() {
+        let active = active();
+        force(active).unwrap();
+        let other = if active == SimdVariant::Scalar { detect() } else { SimdVariant::Scalar };
+        if other != active {
+            let err = force(other).unwrap_err().to_string();
+            assert!(err.contains("is already in use"), "{}", err);
+        }
+        if let Some(foreign) = SimdVariant::ALL.into_iter().find(|v| !v.is_supported()) {
+            assert!(matches!(force(foreign), Err(SolverError::KernelUnavailable(_))));
+        }
+    }
+}
@@ -0,0 +1,396 @@
+//! Long-running soak of a build before it goes to the fleet (`matmul-solver soak`).
+//!
+//! `run_soak` computes and verifies the seed workload for `prefix || nonce` with sequential
+//! nonces until the duration runs out. Every `sample_every` iterations it samples the
+//! process RSS and the live bytes of the counting allocator (with `counting-alloc`) together
+//! with the throughput since the previous sample; every `control_every` iterations it
+//! recomputes a fixed control seed and compares the hash with the one computed at the start.
+//! A least-squares memory trend above the threshold, a control hash that drifted, a failed
+//! verification or a compute error fail the run.
+
+use crate::clock::Clock;
+use crate::{
+    compute_workload_ref, generate_matrices_from_seed_into, host, memory, types, verify_with_metadata, ComputeOptions,
+    FlatMatrix, SeedPrefix, SolverError,
+};
+use serde::{Deserialize, Serialize};
+use std::time::Duration;
+
+/// Seed of the control workload re-run throughout the soak
+pub const CONTROL_SEED: &[u8] = b"matmul-solver soak control";
+
+#[derive(Debug, Clone)]
+pub struct SoakConfig {
+    pub seed_prefix: Vec<u8>,
+    pub precision: String,
+    /// (m, k, n)
+    pub dims: (usize, usize, usize),
+    pub duration: Duration,
+    /// Stop after this many iterations even if time is left
+    pub max_iterations: Option<u64>,
+    /// Iterations between memory and throughput samples
+    pub sample_every: u64,
+    /// Iterations between control re-runs
+    pub control_every: u64,
+    /// Fail when memory grows faster than this many bytes per iteration
+    pub max_trend_bytes_per_iter: f64,
+}
+
+impl Default for SoakConfig {
+    fn default() -> Self {
+        Self {
+            seed_prefix: Vec::new(),
+            precision: "u8i8".to_string(),
+            dims: (16, 50240, 16),
+            duration: Duration::from_secs(3600),
+            max_iterations: None,
+            sample_every: 1000,
+            control_every: 10000,
+            max_trend_bytes_per_iter: 64.0,
+        }
+    }
+}
+
+/// Memory and throughput after `iteration` iterations
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SoakSample {
+    pub iteration: u64,
+    pub elapsed_secs: f64,
+    /// Since the previous sample
+    pub iterations_per_sec: f64,
+    pub rss_bytes: Option<u64>,
+    /// Live bytes of the soak thread, less the report's samples (`counting-alloc` builds)
+    pub allocated_bytes: Option<u64>,
+}
+
+/// Something that fails the soak; `iteration` is the number of iterations completed before it
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum Anomaly {
+    ControlDrift { iteration: u64, expected: String, actual: String },
+    VerifyFailed { iteration: u64, nonce: u64, result_hash: String },
+    Error { iteration: u64, nonce: Option<u64>, message: String },
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SoakReport {
+    pub seed_prefix: String,
+    pub precision: String,
+    pub dims: (usize, usize, usize),
+    pub iterations: u64,
+    pub elapsed_secs: f64,
+    pub iterations_per_sec: f64,
+    pub control_hash: String,
+    pub control_checks: u64,
+    pub samples: Vec<SoakSample>,
+    /// Least-squares slope of the RSS samples over iterations
+    pub rss_trend_bytes_per_iter: Option<f64>,
+    /// Least-squares slope of the allocator samples over iterations
+    pub allocated_trend_bytes_per_iter: Option<f64>,
+    pub max_trend_bytes_per_iter: f64,
+    pub anomalies: Vec<Anomaly>,
+}
+
+impl SoakReport {
+    /// Why the soak failed; empty when it passed
+    pub fn failures(&self) -> Vec<String> {
+        let mut failures: Vec<String> = self
+            .anomalies
+            .iter()
+            .map(|anomaly| match anomaly {
+                Anomaly::ControlDrift { iteration, expected, actual } => {
+                    format!("control hash drifted after {} iterations: {} != {}", iteration, actual, expected)
+                }
+                Anomaly::VerifyFailed { iteration, nonce, .. } => {
+                    format!("nonce {} failed verification after {} iterations", nonce, iteration)
+                }
+                Anomaly::Error { iteration, message, .. } => format!("error after {} iterations: {}", iteration, message),
+            })
+            .collect();
+        for (name, trend) in [("RSS", self.rss_trend_bytes_per_iter), ("allocated memory", self.allocated_trend_bytes_per_iter)] {
+            if let Some(trend) = trend.filter(|&t| t > self.max_trend_bytes_per_iter) {
+                failures.push(format!(
+                    "{} grows {:.1} bytes per iteration (limit {})",
+                    name, trend, self.max_trend_bytes_per_iter
+                ));
+            }
+        }
+        failures
+    }
+
+    pub fn passed(&self) -> bool {
+        self.failures().is_empty()
+    }
+
+    /// Human-readable summary for the console
+    pub fn to_table(&self) -> String {
+        let trend = |t: Option<f64>| t.map_or("n/a".to_string(), |t| format!("{:.1} B/iter", t));
+        let mut out = format!(
+            "Soak: {} {}x{} × {}x{}, {} iterations in {:.1}s ({:.1}/s), {} control checks\n",
+            self.precision,
+            self.dims.0,
+            self.dims.1,
+            self.dims.1,
+            self.dims.2,
+            self.iterations,
+            self.elapsed_secs,
+            self.iterations_per_sec,
+            self.control_checks
+        );
+        out.push_str(&format!(
+            "memory trend: RSS {}, allocated {} (limit {} B/iter)\n",
+            trend(self.rss_trend_bytes_per_iter),
+            trend(self.allocated_trend_bytes_per_iter),
+            self.max_trend_bytes_per_iter
+        ));
+        for failure in self.failures() {
+            out.push_str(&format!("❌ {}\n", failure));
+        }
+        out
+    }
+}
+
+/// Least-squares slope of `y` over `x`; None for fewer than two distinct x
+pub fn memory_trend(points: &[(f64, f64)]) -> Option<f64> {
+    let n = points.len() as f64;
+    let mean_x = points.iter().map(|p| p.0).sum::<f64>() / n;
+    let mean_y = points.iter().map(|p| p.1).sum::<f64>() / n;
+    let var_x: f64 = points.iter().map(|p| (p.0 - mean_x).powi(2)).sum();
+    let cov: f64 = points.iter().map(|p| (p.0 - mean_x) * (p.1 - mean_y)).sum();
+    (var_x > 0.0).then(|| cov / var_x)
+}
+
+fn trend_of(samples: &[SoakSample], bytes: impl Fn(&SoakSample) -> Option<u64>) -> Option<f64> {
+    let points: Vec<(f64, f64)> = samples.iter().filter_map(|s| Some((s.iteration as f64, bytes(s)? as f64))).collect();
+    memory_trend(&points)
+}
+
+fn seed_input(precision: &str, (m, k, n): (usize, usize, usize)) -> types::Input {
+    types::Input {
+        matrix_a: FlatMatrix { data: Vec::new(), rows: m, cols: k },
+        matrix_b: FlatMatrix { data: Vec::new(), rows: k, cols: n },
+        precision: precision.to_string(),
+        ..Default::default()
+    }
+}
+
+/// Soak this build. `on_sample` gets the report so far after every sample, to keep a
+/// rolling report on disk. Fails only when the control workload cannot be computed at all;
+/// everything later is recorded in the report.
+pub fn run_soak(
+    config: &SoakConfig,
+    options: &ComputeOptions,
+    clock: &dyn Clock,
+    mut on_sample: impl FnMut(&SoakReport),
+) -> Result<SoakReport, SolverError> {
+    if config.sample_every == 0 || config.control_every == 0 {
+        return Err(SolverError::InvalidInput("sample_every and control_every must be at least 1".to_string()));
+    }
+    let (m, k, n) = config.dims;
+    let mut control = seed_input(&config.precision, config.dims);
+    if !options.exact_names {
+        control.normalize_names();
+    }
+    let run_control = |control: &mut types::Input| {
+        generate_matrices_from_seed_into(CONTROL_SEED, m, k, k, n, &mut control.matrix_a, &mut control.matrix_b);
+        compute_workload_ref(control, options).map(|output| output.result_hash)
+    };
+    let control_hash = run_control(&mut control)?;
+
+    let mut input = types::Input { precision: control.precision.clone(), ..seed_input(&config.precision, config.dims) };
+    let mut prefix = SeedPrefix::new(&config.seed_prefix, (m, k), (k, n));
+    let mut report = SoakReport {
+        seed_prefix: hex::encode(&config.seed_prefix),
+        precision: control.precision.clone(),
+        dims: config.dims,
+        iterations: 0,
+        elapsed_secs: 0.0,
+        iterations_per_sec: 0.0,
+        control_hash,
+        control_checks: 0,
+        samples: Vec::new(),
+        rss_trend_bytes_per_iter: None,
+        allocated_trend_bytes_per_iter: None,
+        max_trend_bytes_per_iter: config.max_trend_bytes_per_iter,
+        anomalies: Vec::new(),
+    };
+
+    let start = clock.now();
+    let mut last_sample = (0u64, 0.0f64);
+    while clock.elapsed_since(start) < config.duration && config.max_iterations.is_none_or(|max| report.iterations < max) {
+        let nonce = report.iterations;
+        prefix.derive_into(&nonce.to_le_bytes(), &mut input.matrix_a, &mut input.matrix_b);
+        let checked = compute_workload_ref(&input, options).and_then(|output| {
+            let verified = verify_with_metadata(&input.matrix_a, &input.matrix_b, &output.metadata, &output.result_hash)?;
+            Ok((verified, output.result_hash))
+        });
+        let iteration = report.iterations;
+        match checked {
+            Ok((true, _)) => {}
+            Ok((false, result_hash)) => report.anomalies.push(Anomaly::VerifyFailed { iteration, nonce, result_hash }),
+            Err(e) => report.anomalies.push(Anomaly::Error { iteration, nonce: Some(nonce), message: e.to_string() }),
+        }
+        report.iterations += 1;
+        let iteration = report.iterations;
+
+        if iteration.is_multiple_of(config.control_every) {
+            report.control_checks += 1;
+            match run_control(&mut control) {
+                Ok(hash) if hash == report.control_hash => {}
+                Ok(actual) => {
+                    let expected = report.control_hash.clone();
+                    report.anomalies.push(Anomaly::ControlDrift { iteration, expected, actual });
+                }
+                Err(e) => report.anomalies.push(Anomaly::Error { iteration, nonce: None, message: e.to_string() }),
+            }
+        }
+        if iteration.is_multiple_of(config.sample_every) {
+            let elapsed_secs = clock.elapsed_since(start).as_secs_f64();
+            let window = elapsed_secs - last_sample.1;
+            // The samples themselves are the one thing a soak is meant to accumulate
+            let samples_bytes = (report.samples.capacity() * std::mem::size_of::<SoakSample>()) as u64;
+            report.samples.push(SoakSample {
+                iteration,
+                elapsed_secs,
+                iterations_per_sec: if window > 0.0 { (iteration - last_sample.0) as f64 / window } else { 0.0 },
+                rss_bytes: host::resident_set_bytes(),
+                allocated_bytes: memory::thread_allocated_bytes().map(|b| b.saturating_sub(samples_bytes)),
+            });
+            last_sample = (iteration, elapsed_secs);
+            update_totals(&mut report, elapsed_secs);
+            on_sample(&report);
+        }
+    }
+    update_totals(&mut report, clock.elapsed_since(start).as_secs_f64());
+    Ok(report)
+}
+
+fn update_totals(report: &mut SoakReport, elapsed_secs: f64) {
+    report.elapsed_secs = elapsed_secs;
+    report.iterations_per_sec = if elapsed_secs > 0.0 { report.iterations as f64 / elapsed_secs } else { 0.0 };
+    report.rss_trend_bytes_per_iter = trend_of(&report.samples, |s| s.rss_bytes);
+    report.allocated_trend_bytes_per_iter = trend_of(&report.samples, |s| s.allocated_bytes);
+}
+
+/// Parse a duration: plain seconds or an s/m/h suffix, e.g. "90", "30s", "10m", "1h"
+pub fn parse_duration(s: &str) -> Result<Duration, SolverError> {
+    let s = s.trim();
+    let (digits, unit) = match s.char_indices().last() {
+        Some((i, 's')) => (&s[..i], 1),
+        Some((i, 'm')) => (&s[..i], 60),
+        Some((i, 'h')) => (&s[..i], 3600),
+        _ => (s, 1),
+    };
+    digits
+        .trim()
+        .parse::<u64>()
+        .ok()
+        .and_then(|n| n.checked_mul(unit))
+        .map(Duration::from_secs)
+        .ok_or_else(|| SolverError::InvalidInput(format!("Invalid duration: {:?} (expected e.g. 90, 30s, 10m, 1h)", s)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::faults::{self, BitFlip, FaultPlan};
+    use crate::{MockClock, SystemClock};
+
+    fn config(max_iterations: Option<u64>, duration: Duration) -> SoakConfig {
+        SoakConfig {
+            seed_prefix: b"soak".to_vec(),
+            dims: (16, 64, 16),
+            duration,
+            max_iterations,
+            sample_every: 2,
+            control_every: 2,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_short_soak_report() {
+        let config = SoakConfig { sample_every: 16, control_every: 8, ..config(None, Duration::from_secs(1)) };
+        let mut rolling = Vec::new();
+        let report = run_soak(&config, &ComputeOptions::default(), &SystemClock, |r| rolling.push(r.iterations)).unwrap();
+        assert!(report.iterations >= 16 && report.elapsed_secs >= 1.0, "{}", report.to_table());
+        assert!(report.anomalies.is_empty(), "{}", report.to_table());
+        assert_eq!(report.control_checks, report.iterations / 8);
+        assert_eq!(report.samples.len() as u64, report.iterations / 16);
+        assert_eq!(rolling, report.samples.iter().map(|s| s.iteration).collect::<Vec<_>>());
+        assert!(report.samples.iter().all(|s| s.iterations_per_sec > 0.0));
+        assert_eq!(report.samples[0].allocated_bytes.is_some(), cfg!(feature = "counting-alloc"));
+        assert_eq!(report.samples[0].rss_bytes.is_some(), cfg!(target_os = "linux"));
+
+        let json = serde_json::to_value(&report).unwrap();
+        for key in ["iterations", "iterations_per_sec", "control_hash", "samples", "rss_trend_bytes_per_iter", "anomalies"] {
+            assert!(json.get(key).is_some(), "{}", key);
+        }
+        let expected = crate::compute_seed_workload(CONTROL_SEED, (16, 64, 16), "u8i8", None, None, &ComputeOptions::default());
+        assert_eq!(report.control_hash, expected.unwrap().result_hash);
+    }
+
+    #[test]
+    fn test_control_drift_and_verify_failures_fail_the_soak() {
+        // Kernel runs: 0 is the initial control, each iteration computes and verifies (two
+        // runs) and every second one is followed by a control re-run, so run 5 is the first
+        // re-run and run 1 the compute of nonce 0
+        let flip = |runs| FaultPlan {
+            flip_result_bit: Some(BitFlip { element: 3, bit: 2 }),
+            kernel_runs: Some(runs),
+            ..Default::default()
+        };
+        let soak = || run_soak(&config(Some(4), Duration::from_secs(60)), &ComputeOptions::default(), &MockClock::new(), |_| {});
+
+        let report = faults::with_plan(flip(vec![5]), soak).unwrap();
+        assert_eq!((report.iterations, report.control_checks), (4, 2));
+        assert!(matches!(&report.anomalies[..], [Anomaly::ControlDrift { iteration: 2, expected, actual }] if *expected == report.control_hash && actual != expected));
+        assert!(report.failures()[0].starts_with("control hash drifted after 2 iterations"), "{:?}", report.failures());
+
+        let report = faults::with_plan(flip(vec![1]), soak).unwrap();
+        assert!(matches!(&report.anomalies[..], [Anomaly::VerifyFailed { iteration: 0, nonce: 0, .. }]), "{:?}", report.anomalies);
+        assert!(!report.passed());
+
+        let report = faults::with_plan(FaultPlan { kernel_error: true, kernel_runs: Some(vec![3]), ..Default::default() }, soak).unwrap();
+        assert!(matches!(&report.anomalies[..], [Anomaly::Error { iteration: 1, nonce: Some(1), .. }]), "{:?}", report.anomalies);
+
+        // A clean run with a clock that never advances has no throughput to report
+        let report = soak().unwrap();
+        assert!(report.passed() && report.samples.iter().all(|s| s.iterations_per_sec == 0.0), "{}", report.to_table());
+    }
+
+    #[test]
+    fn test_memory_trend() {
+        let leak: Vec<(f64, f64)> = (0..10).map(|i| (i as f64 * 100.0, 1e6 + 800.0 * i as f64)).collect();
+        assert_eq!(memory_trend(&leak), Some(8.0));
+        let flat: Vec<(f64, f64)> = (0..10).map(|i| (i as f64, 5e6)).collect();
+        assert_eq!(memory_trend(&flat), Some(0.0));
+        // Growth that is given back again is no trend
+        let bump = [(0.0, 100.0), (1.0, 140.0), (2.0, 140.0), (3.0, 100.0)];
+        assert_eq!(memory_trend(&bump), Some(0.0));
+        assert_eq!(memory_trend(&[(4.0, 1.0)]), None);
+        assert_eq!(memory_trend(&[(4.0, 1.0), (4.0, 9.0)]), None);
+
+        let sample = |iteration, bytes| SoakSample {
+            iteration,
+            elapsed_secs: 0.0,
+            iterations_per_sec: 0.0,
+            rss_bytes: Some(bytes),
+            allocated_bytes: None,
+        };
+        let mut report = run_soak(&config(Some(0), Duration::ZERO), &ComputeOptions::default(), &MockClock::new(), |_| {}).unwrap();
+        report.samples = vec![sample(100, 1 << 20), sample(200, (1 << 20) + 12800), sample(300, (1 << 20) + 25600)];
+        update_totals(&mut report, 1.0);
+        assert_eq!((report.rss_trend_bytes_per_iter, report.allocated_trend_bytes_per_iter), (Some(128.0), None));
+        assert_eq!(report.failures(), ["RSS grows 128.0 bytes per iteration (limit 64)"]);
+    }
+
+    #[test]
+    fn test_parse_duration() {
+        assert_eq!(parse_duration("1h").unwrap(), Duration::from_secs(3600));
+        assert_eq!(parse_duration("10m").unwrap(), Duration::from_secs(600));
+        assert_eq!(parse_duration(" 30s").unwrap(), Duration::from_secs(30));
+        assert_eq!(parse_duration("90").unwrap(), Duration::from_secs(90));
+        assert!(parse_duration("1d").is_err() && parse_duration("h").is_err());
+    }
+}
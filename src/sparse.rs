@@ -0,0 +1,261 @@
+//! Sparse B operands (`Input::matrix_b_sparse`).
+//!
+//! A B that is mostly zeros can be supplied in CSR form instead of as `matrix_b`: `values`
+//! holds the stored elements row by row, `col_indices` their columns, and
+//! `row_ptr[p]..row_ptr[p + 1]` is the range of row p in both. Sparse B is fp32 only and
+//! always runs on the `fp32_sparse` kernel, which walks the stored elements alone, so a
+//! multiply costs m·nnz rather than m·k·n.
+//!
+//! Each result element is still summed over p in order from 0, as `fp32_blocked` sums it.
+//! A skipped zero of B would only add ±0 to an accumulator that starts at +0 and so is
+//! never −0, which leaves it unchanged; for finite A the result, its hash and the Output
+//! are bit-identical to the same input sent with B dense and run on `fp32_blocked`. (An
+//! infinite or NaN element of A times a skipped zero would be NaN in the dense product.)
+//! The kernel is registered, so `verify_with_metadata` replays it from the densified B.
+
+use crate::kernels::{self, KernelChoice, MatmulKernel};
+use crate::{types, Clock, ComputeOptions, FlatMatrix, SolverError};
+use serde::{Deserialize, Serialize};
+use std::sync::Arc;
+
+/// The kernel sparse B runs on
+pub const KERNEL: &str = "fp32_sparse";
+
+/// A rows × cols matrix in compressed sparse row form
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SparseMatrix {
+    pub rows: usize,
+    pub cols: usize,
+    /// Stored elements, row by row
+    pub values: Vec<f32>,
+    /// Column of each stored element, strictly increasing within a row
+    pub col_indices: Vec<usize>,
+    /// rows + 1 offsets into values: row p is `row_ptr[p]..row_ptr[p + 1]`
+    pub row_ptr: Vec<usize>,
+}
+
+impl SparseMatrix {
+    /// The nonzero elements of `m`
+    pub fn from_dense(m: &FlatMatrix) -> Self {
+        let mut sparse = SparseMatrix { rows: m.rows, cols: m.cols, values: Vec::new(), col_indices: Vec::new(), row_ptr: vec![0] };
+        for i in 0..m.rows {
+            for (j, &x) in m.data[i * m.cols..(i + 1) * m.cols].iter().enumerate() {
+                if x != 0.0 {
+                    sparse.values.push(x);
+                    sparse.col_indices.push(j);
+                }
+            }
+            sparse.row_ptr.push(sparse.values.len());
+        }
+        sparse
+    }
+
+    /// The dense matrix; `self` must pass `check`
+    pub fn to_dense(&self) -> FlatMatrix {
+        let mut data = vec![0.0f32; self.rows * self.cols];
+        for (p, row) in self.row_ptr.windows(2).enumerate() {
+            for (&j, &x) in self.col_indices[row[0]..row[1]].iter().zip(&self.values[row[0]..row[1]]) {
+                data[p * self.cols + j] = x;
+            }
+        }
+        FlatMatrix { data, rows: self.rows, cols: self.cols }
+    }
+
+    /// Number of stored elements
+    pub fn nnz(&self) -> usize {
+        self.values.len()
+    }
+
+    /// Whether the CSR arrays describe a rows × cols matrix
+    pub fn check(&self) -> Result<(), SolverError> {
+        let invalid = |what: String| Err(SolverError::InvalidInput(format!("matrix_b_sparse {}", what)));
+        if self.row_ptr.len() != self.rows + 1 {
+            return invalid(format!("has {} row_ptr entries for {} rows, CSR needs rows + 1", self.row_ptr.len(), self.rows));
+        }
+        if self.col_indices.len() != self.values.len() {
+            return invalid(format!("has {} values but {} col_indices", self.values.len(), self.col_indices.len()));
+        }
+        if self.row_ptr[0] != 0 || self.row_ptr[self.rows] != self.values.len() {
+            return invalid(format!(
+                "row_ptr runs from {} to {}, it must run from 0 to the {} values",
+                self.row_ptr[0],
+                self.row_ptr[self.rows],
+                self.values.len()
+            ));
+        }
+        for (p, row) in self.row_ptr.windows(2).enumerate() {
+            if row[0] > row[1] || row[1] > self.values.len() {
+                return invalid(format!("row_ptr decreases at row {}", p));
+            }
+            let cols = &self.col_indices[row[0]..row[1]];
+            if let Some(&j) = cols.iter().find(|&&j| j >= self.cols) {
+                return invalid(format!("row {} has column {}, outside its {} columns", p, j, self.cols));
+            }
+            if cols.windows(2).any(|pair| pair[0] >= pair[1]) {
+                return invalid(format!("row {} has col_indices that are not strictly increasing", p));
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Dense A × sparse B in fp32, bit-identical to `matmul_fp32_blocked` on `b.to_dense()` for
+/// finite A (see the module docs). Row i of A scales and adds the stored elements of each
+/// row of B into row i of the result, in p order. `b` must pass `SparseMatrix::check`.
+pub fn matmul_fp32_sparse(a: &FlatMatrix, b: &SparseMatrix, clock: &dyn Clock) -> (FlatMatrix, std::time::Duration) {
+    let (m, k, n) = (a.rows, a.cols, b.cols);
+    let start = clock.now();
+    let mut data = vec![0.0f32; m * n];
+    for i in 0..m {
+        let result_row = &mut data[i * n..(i + 1) * n];
+        for (&a_ip, row) in a.data[i * k..(i + 1) * k].iter().zip(b.row_ptr.windows(2)) {
+            for (&j, &b_pj) in b.col_indices[row[0]..row[1]].iter().zip(&b.values[row[0]..row[1]]) {
+                result_row[j] += a_ip * b_pj;
+            }
+        }
+    }
+    let kernel_time = clock.elapsed_since(start);
+    (FlatMatrix { data, rows: m, cols: n }, kernel_time)
+}
+
+/// The sparse B of a matmul input, if it has one: set instead of matrix_b, not alongside
+/// it, and well-formed
+pub(crate) fn operand(input: &types::Input) -> Result<Option<&SparseMatrix>, SolverError> {
+    let Some(sparse) = &input.matrix_b_sparse else {
+        return Ok(None);
+    };
+    let b = &input.matrix_b;
+    check_alternative((b.rows, b.cols, b.data.len()))?;
+    sparse.check()?;
+    Ok(Some(sparse))
+}
+
+/// A sparse B leaves matrix_b, of these rows, cols and elements, empty
+pub(crate) fn check_alternative(matrix_b: (usize, usize, usize)) -> Result<(), SolverError> {
+    match matrix_b {
+        (0, 0, 0) => Ok(()),
+        _ => Err(SolverError::InvalidInput("matrix_b and matrix_b_sparse are alternatives; set only one".to_string())),
+    }
+}
+
+/// The kernel choice for a sparse B: always `fp32_sparse`, which must be the kernel named by
+/// `options.kernel` if any
+pub(crate) fn resolve_choice(
+    precision: &str,
+    a_shape: (usize, usize),
+    b_shape: (usize, usize),
+    options: &ComputeOptions,
+) -> Result<(KernelChoice, Arc<dyn MatmulKernel>), SolverError> {
+    if precision != "fp32" {
+        return Err(SolverError::InvalidInput(format!("matrix_b_sparse applies to fp32, not {}", precision)));
+    }
+    if let Some(name) = options.kernel.as_deref().filter(|&name| options.match_name(name) != KERNEL) {
+        return Err(SolverError::InvalidInput(format!("matrix_b_sparse runs on the {} kernel, not {}", KERNEL, name)));
+    }
+    let options = ComputeOptions { kernel: Some(KERNEL.to_string()), ..options.clone() };
+    kernels::resolve_choice(precision, a_shape, b_shape, &options)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{compute_workload, compute_workload_with_options, generate_matrices_from_seed, verify_with_metadata};
+
+    /// A k×n B with about `density` of its elements nonzero
+    fn sparse_b(k: usize, n: usize, density: f64) -> FlatMatrix {
+        let (_, mut b) = generate_matrices_from_seed(b"sparse", 0, k, k, n);
+        let (_, keep) = generate_matrices_from_seed(b"sparse-mask", 0, k, k, n);
+        // The mask bytes are uniform in -128..128
+        for (x, &u) in b.data.iter_mut().zip(&keep.data) {
+            if (u as f64 + 128.0) / 256.0 >= density {
+                *x = 0.0;
+            }
+        }
+        b
+    }
+
+    fn input(a: FlatMatrix, b: FlatMatrix, sparse: bool) -> types::Input {
+        let (matrix_b, matrix_b_sparse) = match sparse {
+            true => (FlatMatrix::default(), Some(SparseMatrix::from_dense(&b))),
+            false => (b, None),
+        };
+        types::Input { matrix_a: a, matrix_b, precision: "fp32".to_string(), matrix_b_sparse, ..Default::default() }
+    }
+
+    #[test]
+    fn test_sparse_matches_dense() {
+        let blocked = ComputeOptions { kernel: Some("fp32_blocked".to_string()), ..Default::default() };
+        for (m, k, n) in [(16, 200, 16), (7, 65, 33), (1, 130, 5), (40, 3, 1)] {
+            for density in [0.0, 0.02, 0.1, 0.5, 1.0] {
+                let (mut a, _) = generate_matrices_from_seed(b"sparse-a", m, k, 0, 0);
+                a.data.iter_mut().for_each(|x| *x /= 3.0);
+                let b = sparse_b(k, n, density);
+                let dense = compute_workload_with_options(input(a.clone(), b.clone(), false), &blocked).unwrap();
+                let sparse = compute_workload(input(a.clone(), b.clone(), true)).unwrap();
+                let case = format!("{}x{}x{} density {}", m, k, n, density);
+                assert_eq!(sparse.result_matrix.data, dense.result_matrix.data, "{}", case);
+                assert_eq!(sparse.result_hash, dense.result_hash, "{}", case);
+                assert_eq!(sparse.metadata.matrix_b_shape, (k, n), "{}", case);
+                assert_eq!(sparse.metadata.kernel.as_deref(), Some(KERNEL), "{}", case);
+                assert!(verify_with_metadata(&a, &b, &sparse.metadata, &sparse.result_hash).unwrap(), "{}", case);
+            }
+        }
+    }
+
+    #[test]
+    fn test_csr_round_trip_and_serde() {
+        let b = FlatMatrix { data: vec![0.0, 2.0, 0.0, -0.0, 0.0, 0.0, 5.0, 0.0, 7.5], rows: 3, cols: 3 };
+        let sparse = SparseMatrix::from_dense(&b);
+        assert_eq!((sparse.values.as_slice(), sparse.col_indices.as_slice(), sparse.row_ptr.as_slice()), ([2.0, 5.0, 7.5].as_slice(), [1, 0, 2].as_slice(), [0, 1, 1, 3].as_slice()));
+        assert_eq!(sparse.nnz(), 3);
+        assert_eq!(sparse.to_dense().data, [0.0, 2.0, 0.0, 0.0, 0.0, 0.0, 5.0, 0.0, 7.5]);
+        let json = r#"{"rows": 3, "cols": 3, "values": [2, 5, 7.5], "col_indices": [1, 0, 2], "row_ptr": [0, 1, 1, 3]}"#;
+        assert_eq!(serde_json::from_str::<SparseMatrix>(json).unwrap(), sparse);
+
+        // A parsed input with a sparse B
+        let json = format!(r#"{{"matrix_a": [[1, 2, 3]], "matrix_b_sparse": {}, "precision": "fp32"}}"#, json);
+        let (parsed, _) = crate::numbers::parse_input_json(&json, false).unwrap();
+        assert_eq!(compute_workload(parsed).unwrap().result_matrix.data, [15.0, 2.0, 22.5]);
+    }
+
+    #[test]
+    fn test_sparse_errors() {
+        let a = FlatMatrix { data: vec![1.0; 6], rows: 2, cols: 3 };
+        let b = FlatMatrix { data: vec![1.0, 0.0, 0.0, 2.0, 3.0, 0.0], rows: 3, cols: 2 };
+        let err = |input: types::Input| compute_workload(input).unwrap_err().to_string();
+
+        let both = types::Input { matrix_b: b.clone(), ..input(a.clone(), b.clone(), true) };
+        assert_eq!(err(both), "matrix_b and matrix_b_sparse are alternatives; set only one");
+        let int8 = types::Input { precision: "int8".to_string(), ..input(a.clone(), b.clone(), true) };
+        assert_eq!(err(int8), "matrix_b_sparse applies to fp32, not int8");
+        let transposed = types::Input { b_layout: Some("transposed".to_string()), ..input(a.clone(), b.clone(), true) };
+        assert_eq!(err(transposed), "b_layout applies to matrix_b, not matrix_b_sparse");
+        let forced = ComputeOptions { kernel: Some("fp32_blocked".to_string()), ..Default::default() };
+        let forced = compute_workload_with_options(input(a.clone(), b.clone(), true), &forced).unwrap_err().to_string();
+        assert_eq!(forced, "matrix_b_sparse runs on the fp32_sparse kernel, not fp32_blocked");
+        let batched = types::Input { workload_type: Some("batched_matmul".to_string()), batch: Some(1), ..input(a.clone(), b.clone(), true) };
+        assert_eq!(err(batched), "matrix_b_sparse is not supported for the batched_matmul workload");
+
+        // Malformed CSR
+        let malformed = |edit: fn(&mut SparseMatrix)| {
+            let mut input = input(a.clone(), b.clone(), true);
+            edit(input.matrix_b_sparse.as_mut().unwrap());
+            err(input)
+        };
+        assert!(malformed(|s| {
+            s.row_ptr.pop();
+        })
+        .contains("has 3 row_ptr entries for 3 rows"));
+        assert!(malformed(|s| s.col_indices[1] = 2).contains("row 1 has column 2, outside its 2 columns"));
+        assert!(malformed(|s| s.row_ptr[2] = 0).contains("row_ptr decreases at row 1"));
+        assert!(malformed(|s| s.col_indices.push(0)).contains("has 3 values but 4 col_indices"));
+        // Row 1 holding columns 1 and 0
+        assert!(malformed(|s| s.row_ptr[2] = 3).contains("row 1 has col_indices that are not strictly increasing"));
+
+        // The shape is checked like a dense B's
+        let mut short = input(a, b, true);
+        short.matrix_a.cols = 2;
+        short.matrix_a.data.truncate(4);
+        assert_eq!(err(short), "Matrix dimensions incompatible: matrix_a.cols (=2) must equal matrix_b_sparse.rows (=3)");
+    }
+}
@@ -0,0 +1,195 @@
+//! Tenant identification and per-tenant quotas for the API.
+//!
+//! A tenant is resolved per request: a bearer token listed in `TenantConfig::tokens`
+//! maps to its tenant, and any other request belongs to `DEFAULT_TENANT`. The `X-Tenant`
+//! header is only read with `trust_tenant_header`, for a proxy in front of the API that
+//! authenticates clients and sets it; otherwise a client could pick any tenant, reading
+//! its jobs and starting fresh quota windows by changing the name. The tenant namespaces
+//! rate-limit buckets and replay-log entries, and is charged against fixed-window quotas:
+//! requests over `max_requests` get 429, request bodies that would exceed `max_bytes` get
+//! 413.
+
+use serde::Serialize;
+use std::collections::{BTreeMap, HashMap};
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+/// Tenant of requests without a mapped token (or a trusted X-Tenant header)
+pub const DEFAULT_TENANT: &str = "default";
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct TenantQuota {
+    /// Requests admitted per window
+    pub max_requests: Option<u64>,
+    /// Request-body bytes admitted per window
+    pub max_bytes: Option<u64>,
+    pub window: Duration,
+}
+
+impl Default for TenantQuota {
+    fn default() -> Self {
+        Self { max_requests: None, max_bytes: None, window: Duration::from_secs(60) }
+    }
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct TenantConfig {
+    /// Bearer token → tenant name
+    pub tokens: HashMap<String, String>,
+    /// Per-tenant quotas; tenants without an entry use `default_quota`
+    pub quotas: HashMap<String, TenantQuota>,
+    pub default_quota: Option<TenantQuota>,
+    /// Name the tenant of requests without a mapped token from their X-Tenant header.
+    /// Only for a trusted proxy that sets (and strips client-sent) X-Tenant headers.
+    pub trust_tenant_header: bool,
+}
+
+impl TenantConfig {
+    /// Tenant for a request with this bearer token and X-Tenant header
+    pub fn resolve(&self, bearer: Option<&str>, header: Option<&str>) -> String {
+        if let Some(tenant) = bearer.and_then(|t| self.tokens.get(t)) {
+            return tenant.clone();
+        }
+        match header.map(str::trim).filter(|h| self.trust_tenant_header && !h.is_empty()) {
+            Some(tenant) => tenant.to_string(),
+            None => DEFAULT_TENANT.to_string(),
+        }
+    }
+
+    fn quota(&self, tenant: &str) -> Option<&TenantQuota> {
+        self.quotas.get(tenant).or(self.default_quota.as_ref())
+    }
+}
+
+/// Why a request was refused
+#[derive(Debug, Clone, PartialEq)]
+pub enum QuotaRejection {
+    /// Request quota used up; retry after this many seconds (429)
+    Requests { retry_after_secs: u64 },
+    /// The body does not fit in the byte quota (413)
+    Bytes { limit: u64, used: u64, requested: u64 },
+}
+
+/// Lifetime counters per tenant, reported by /stats
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+pub struct TenantUsage {
+    pub requests: u64,
+    pub bytes: u64,
+    pub rejected_requests: u64,
+    pub rejected_bytes: u64,
+}
+
+#[derive(Debug)]
+struct Window {
+    started: Instant,
+    requests: u64,
+    bytes: u64,
+}
+
+pub struct TenantTracker {
+    config: TenantConfig,
+    windows: Mutex<HashMap<String, Window>>,
+    usage: Mutex<HashMap<String, TenantUsage>>,
+}
+
+impl TenantTracker {
+    pub fn new(config: TenantConfig) -> Self {
+        Self { config, windows: Mutex::new(HashMap::new()), usage: Mutex::new(HashMap::new()) }
+    }
+
+    pub fn config(&self) -> &TenantConfig {
+        &self.config
+    }
+
+    /// Charge one request of `bytes` to `tenant`, or refuse it without charging
+    pub fn admit(&self, tenant: &str, bytes: u64, now: Instant) -> Result<(), QuotaRejection> {
+        let decision = match self.config.quota(tenant) {
+            None => Ok(()),
+            Some(quota) => {
+                let mut windows = self.windows.lock().unwrap();
+                let window = windows
+                    .entry(tenant.to_string())
+                    .or_insert_with(|| Window { started: now, requests: 0, bytes: 0 });
+                let elapsed = now.saturating_duration_since(window.started);
+                if elapsed >= quota.window {
+                    *window = Window { started: now, requests: 0, bytes: 0 };
+                }
+                if quota.max_requests.is_some_and(|max| window.requests >= max) {
+                    let left = quota.window.saturating_sub(now.saturating_duration_since(window.started));
+                    Err(QuotaRejection::Requests { retry_after_secs: left.as_secs_f64().ceil() as u64 })
+                } else if let Some(limit) = quota.max_bytes.filter(|&max| window.bytes + bytes > max) {
+                    Err(QuotaRejection::Bytes { limit, used: window.bytes, requested: bytes })
+                } else {
+                    window.requests += 1;
+                    window.bytes += bytes;
+                    Ok(())
+                }
+            }
+        };
+
+        let mut usage = self.usage.lock().unwrap();
+        let usage = usage.entry(tenant.to_string()).or_default();
+        match decision {
+            Ok(()) => {
+                usage.requests += 1;
+                usage.bytes += bytes;
+            }
+            Err(QuotaRejection::Requests { .. }) => usage.rejected_requests += 1,
+            Err(QuotaRejection::Bytes { .. }) => usage.rejected_bytes += 1,
+        }
+        decision
+    }
+
+    /// Usage per tenant, sorted by tenant name
+    pub fn stats(&self) -> BTreeMap<String, TenantUsage> {
+        self.usage.lock().unwrap().iter().map(|(k, v)| (k.clone(), v.clone())).collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_resolution_and_independent_windows() {
+        let config = TenantConfig {
+            tokens: HashMap::from([("tok-a".to_string(), "team-a".to_string())]),
+            quotas: HashMap::from([(
+                "team-a".to_string(),
+                TenantQuota { max_requests: Some(2), max_bytes: Some(100), window: Duration::from_secs(10) },
+            )]),
+            default_quota: None,
+            trust_tenant_header: false,
+        };
+        // A mapped token wins over a spoofed header, and without a trusted proxy the header
+        // never names a tenant
+        assert_eq!(config.resolve(Some("tok-a"), Some("team-b")), "team-a");
+        assert_eq!(config.resolve(Some("unknown"), Some("team-b")), DEFAULT_TENANT);
+        assert_eq!(config.resolve(None, Some("team-a")), DEFAULT_TENANT);
+        assert_eq!(config.resolve(None, None), DEFAULT_TENANT);
+        let proxied = TenantConfig { trust_tenant_header: true, ..config.clone() };
+        assert_eq!(proxied.resolve(Some("unknown"), Some("team-b")), "team-b");
+        assert_eq!(proxied.resolve(Some("tok-a"), Some("team-b")), "team-a");
+
+        let tracker = TenantTracker::new(config);
+        let t0 = Instant::now();
+        assert!(matches!(tracker.admit("team-a", 150, t0), Err(QuotaRejection::Bytes { limit: 100, .. })));
+        tracker.admit("team-a", 60, t0).unwrap();
+        assert!(matches!(tracker.admit("team-a", 60, t0), Err(QuotaRejection::Bytes { used: 60, .. })));
+        tracker.admit("team-a", 10, t0).unwrap();
+        assert_eq!(
+            tracker.admit("team-a", 1, t0 + Duration::from_millis(2500)),
+            Err(QuotaRejection::Requests { retry_after_secs: 8 })
+        );
+        // No quota for other tenants
+        for _ in 0..5 {
+            tracker.admit("team-b", 1000, t0).unwrap();
+        }
+        // A new window resets the counters
+        tracker.admit("team-a", 90, t0 + Duration::from_secs(10)).unwrap();
+
+        let stats = tracker.stats();
+        assert_eq!(stats["team-a"], TenantUsage { requests: 3, bytes: 160, rejected_requests: 1, rejected_bytes: 2 });
+        assert_eq!(stats["team-b"].requests, 5);
+    }
+}
@@ -0,0 +1,220 @@
+//! Numeric tolerances of the comparison features (`TolerancePolicy`).
+//!
+//! Error metrics, reference cross-checks and the acceptance suite all judge a result
+//! against a reference that need not match it bit for bit. Rather than each hardcoding an
+//! epsilon, they take their tolerance from one policy: a named profile ("strict",
+//! "default", "loose") giving a relative and an absolute tolerance per precision, plus
+//! per-precision overrides. So `--tolerance-profile strict` (API `"tolerance_profile"`,
+//! `ComputeOptions::tolerance`) governs every comparison of a run, and every report that
+//! judged something records the `EffectiveTolerance` it used.
+//!
+//! A result passes when every element is within `abs + rel · scale` of the reference,
+//! where scale is the largest |element| of the reference: rounding and quantization errors
+//! grow with the operands, not with each output, so an element near zero after
+//! cancellation is held to the matrix's scale rather than its own. Elements that are not
+//! finite must match (any NaN matches any NaN). Integer precisions are judged on their
+//! dequantized results, whose error the quantization step dominates.
+
+use crate::{FlatMatrix, SolverError};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+
+/// Named sets of per-precision tolerances
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ToleranceProfile {
+    Strict,
+    #[default]
+    Default,
+    Loose,
+}
+
+impl ToleranceProfile {
+    pub const ALL: [ToleranceProfile; 3] = [ToleranceProfile::Strict, ToleranceProfile::Default, ToleranceProfile::Loose];
+
+    pub fn parse(s: &str) -> Result<Self, SolverError> {
+        match s {
+            "strict" => Ok(ToleranceProfile::Strict),
+            "default" => Ok(ToleranceProfile::Default),
+            "loose" => Ok(ToleranceProfile::Loose),
+            other => Err(SolverError::InvalidInput(format!("Unknown tolerance profile: {} (expected strict, default or loose)", other))),
+        }
+    }
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ToleranceProfile::Strict => "strict",
+            ToleranceProfile::Default => "default",
+            ToleranceProfile::Loose => "loose",
+        }
+    }
+
+    /// This profile's tolerance for results computed at `precision`. Quantize targets and
+    /// other names without a row of their own get fp32's.
+    pub fn tolerance(&self, precision: &str) -> Tolerance {
+        let row = match precision {
+            "fp64" => [(1e-12, 0.0), (1e-10, 1e-12), (1e-8, 1e-10)],
+            "fp16" => [(2e-3, 0.0), (1e-2, 1e-4), (5e-2, 1e-3)],
+            "bf16" => [(1e-2, 0.0), (5e-2, 1e-3), (1e-1, 1e-2)],
+            "int8" | "int8_rowwise" | "int8_asym" | "u8i8" => [(2e-2, 0.0), (5e-2, 1e-3), (1e-1, 1e-2)],
+            "int4" => [(1e-1, 0.0), (2.5e-1, 1e-2), (5e-1, 5e-2)],
+            _ => [(1e-6, 0.0), (1e-5, 1e-6), (1e-3, 1e-5)],
+        };
+        let (rel, abs) = row[*self as usize];
+        Tolerance { rel, abs }
+    }
+}
+
+/// How far a result may be from its reference: `abs + rel · scale` per element
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct Tolerance {
+    pub rel: f64,
+    pub abs: f64,
+}
+
+impl Tolerance {
+    /// Whether an element `error` away from the reference is within tolerance, for a
+    /// reference whose largest |element| is `scale`
+    pub fn allows(&self, error: f64, scale: f64) -> bool {
+        error <= self.abs + self.rel * scale
+    }
+}
+
+/// The tolerance profile of a run, with per-precision overrides
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct TolerancePolicy {
+    pub profile: ToleranceProfile,
+    /// Replace the profile's tolerance for these precisions
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub overrides: BTreeMap<String, Tolerance>,
+}
+
+impl TolerancePolicy {
+    pub fn new(profile: ToleranceProfile) -> Self {
+        TolerancePolicy { profile, overrides: BTreeMap::new() }
+    }
+
+    /// A policy from a profile name and `PRECISION=REL,ABS` overrides (the CLI flags)
+    pub fn parse(profile: Option<&str>, overrides: &[String]) -> Result<Self, SolverError> {
+        let mut policy = TolerancePolicy::new(profile.map(ToleranceProfile::parse).transpose()?.unwrap_or_default());
+        for spec in overrides {
+            let invalid = || SolverError::InvalidInput(format!("Invalid tolerance override: {} (expected PRECISION=REL,ABS)", spec));
+            let (precision, values) = spec.split_once('=').ok_or_else(invalid)?;
+            let (rel, abs) = values.split_once(',').ok_or_else(invalid)?;
+            let tolerance = Tolerance { rel: rel.trim().parse().map_err(|_| invalid())?, abs: abs.trim().parse().map_err(|_| invalid())? };
+            policy.overrides.insert(precision.trim().to_string(), tolerance);
+        }
+        policy.validate()?;
+        Ok(policy)
+    }
+
+    /// Overrides must be finite and non-negative
+    pub fn validate(&self) -> Result<(), SolverError> {
+        match self.overrides.iter().find(|(_, t)| ![t.rel, t.abs].iter().all(|x| x.is_finite() && *x >= 0.0)) {
+            Some((precision, t)) => Err(SolverError::InvalidInput(format!(
+                "Invalid {} tolerance: rel {} and abs {} must be finite and non-negative",
+                precision, t.rel, t.abs
+            ))),
+            None => Ok(()),
+        }
+    }
+
+    /// The tolerance results at `precision` are held to
+    pub fn effective(&self, precision: &str) -> EffectiveTolerance {
+        let overridden = self.overrides.get(precision);
+        let Tolerance { rel, abs } = overridden.copied().unwrap_or_else(|| self.profile.tolerance(precision));
+        EffectiveTolerance { profile: self.profile, precision: precision.to_string(), rel, abs, overridden: overridden.is_some() }
+    }
+}
+
+/// The tolerance a comparison used, as recorded in its report
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct EffectiveTolerance {
+    pub profile: ToleranceProfile,
+    pub precision: String,
+    pub rel: f64,
+    pub abs: f64,
+    /// Set by an override rather than the profile
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub overridden: bool,
+}
+
+impl EffectiveTolerance {
+    pub fn tolerance(&self) -> Tolerance {
+        Tolerance { rel: self.rel, abs: self.abs }
+    }
+
+    /// "default tolerance (rel 1e-5, abs 1e-6)", for console reports
+    pub fn describe(&self) -> String {
+        let source = if self.overridden { "overridden" } else { self.profile.as_str() };
+        format!("{} tolerance (rel {:e}, abs {:e})", source, self.rel, self.abs)
+    }
+}
+
+/// Outcome of `compare`
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Comparison {
+    /// Largest |got − reference| over the finite elements
+    pub max_abs_error: f64,
+    pub within_tolerance: bool,
+}
+
+/// Compare `got` with `reference` (same shape) element by element under `tolerance`
+pub fn compare(got: &FlatMatrix, reference: &FlatMatrix, tolerance: Tolerance) -> Comparison {
+    let scale = reference.data.iter().filter(|x| x.is_finite()).fold(0f64, |m, &x| m.max(x.abs() as f64));
+    let mut comparison = Comparison { max_abs_error: 0.0, within_tolerance: got.data.len() == reference.data.len() };
+    for (&x, &y) in got.data.iter().zip(&reference.data) {
+        if !x.is_finite() || !y.is_finite() {
+            comparison.within_tolerance &= x.to_bits() == y.to_bits() || (x.is_nan() && y.is_nan());
+            continue;
+        }
+        let error = (x as f64 - y as f64).abs();
+        comparison.max_abs_error = comparison.max_abs_error.max(error);
+        comparison.within_tolerance &= tolerance.allows(error, scale);
+    }
+    comparison
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_profiles_and_overrides() {
+        // Each profile is looser than the one before, for every precision
+        for precision in ["fp64", "fp32", "fp16", "bf16", "int8", "int8_rowwise", "int8_asym", "int4", "u8i8"] {
+            let [strict, default, loose] = ToleranceProfile::ALL.map(|p| p.tolerance(precision));
+            assert!(strict.rel < default.rel && default.rel < loose.rel, "{}", precision);
+            assert!(strict.abs <= default.abs && default.abs < loose.abs, "{}", precision);
+        }
+        assert_eq!(ToleranceProfile::Default.tolerance("quantize-target"), ToleranceProfile::Default.tolerance("fp32"));
+
+        let policy = TolerancePolicy::parse(Some("strict"), &["fp16=0.5,0.25".to_string()]).unwrap();
+        let fp16 = policy.effective("fp16");
+        assert_eq!((fp16.profile, fp16.rel, fp16.abs, fp16.overridden), (ToleranceProfile::Strict, 0.5, 0.25, true));
+        assert_eq!(policy.effective("bf16").tolerance(), ToleranceProfile::Strict.tolerance("bf16"));
+        assert_eq!(fp16.describe(), "overridden tolerance (rel 5e-1, abs 2.5e-1)");
+        assert_eq!(policy.effective("fp32").describe(), "strict tolerance (rel 1e-6, abs 0e0)");
+
+        assert_eq!(TolerancePolicy::parse(None, &[]).unwrap(), TolerancePolicy::default());
+        assert!(TolerancePolicy::parse(Some("lenient"), &[]).unwrap_err().to_string().contains("expected strict, default or loose"));
+        for bad in ["fp16", "fp16=1e-3", "fp16=x,1", "fp16=-1,0", "fp16=inf,0"] {
+            assert!(TolerancePolicy::parse(None, &[bad.to_string()]).is_err(), "{}", bad);
+        }
+    }
+
+    #[test]
+    fn test_compare_scales_with_the_reference() {
+        let reference = FlatMatrix { data: vec![100.0, 0.0, -50.0, f32::NAN], rows: 2, cols: 2 };
+        let got = FlatMatrix { data: vec![100.5, 0.25, -50.0, f32::NAN], rows: 2, cols: 2 };
+        // The element near zero is judged against the scale of 100, not its own
+        let comparison = compare(&got, &reference, Tolerance { rel: 5e-3, abs: 0.0 });
+        assert_eq!(comparison, Comparison { max_abs_error: 0.5, within_tolerance: true });
+        assert!(!compare(&got, &reference, Tolerance { rel: 4e-3, abs: 0.0 }).within_tolerance);
+        assert!(compare(&got, &reference, Tolerance { rel: 0.0, abs: 0.5 }).within_tolerance);
+
+        // Non-finite elements must match
+        let infinite = FlatMatrix { data: vec![100.0, 0.0, -50.0, f32::INFINITY], rows: 2, cols: 2 };
+        assert!(!compare(&infinite, &reference, Tolerance { rel: 1.0, abs: 1.0 }).within_tolerance);
+    }
+}
@@ -0,0 +1,456 @@
+//! Captured API traffic and its replay, for load testing (`matmul-solver replay`).
+//!
+//! With `CAPTURE_FILE` set, the server appends one `CapturedRequest` per served /compute
+//! request to a JSONL file, stopping after `CaptureConfig::max_requests`. Inline matrices
+//! never reach the file: each request keeps their digests and shapes plus a seed from which
+//! the replay generates stand-ins of the same shapes (`SyntheticMatrices`), so bodies and
+//! kernels cost what they did. Seed requests are written as they came, with the result hash
+//! they got, which the replay checks; a synthetic request cannot reproduce its original
+//! result, so it carries no hash. Requests with a sparse B are not captured.
+//!
+//! `replay` re-sends a capture on its own schedule, the gaps between arrivals divided by a
+//! `ReplayRate`, and reports latency percentiles, errors and hash mismatches.
+
+use crate::api::api::ComputeRequest;
+use crate::histogram::Percentiles;
+use crate::host;
+use crate::SolverError;
+use serde::{Deserialize, Serialize};
+use std::fs::File;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+/// One served request as written to the capture
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CapturedRequest {
+    /// When the request arrived
+    pub arrived_unix_ms: u64,
+    /// The request as admitted, without matrix_a and matrix_b
+    pub request: ComputeRequest,
+    /// Stand-ins for the matrices the request carried
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub synthetic: Option<SyntheticMatrices>,
+    /// The response's result hash, for requests the replay sends unchanged
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub result_hash: Option<String>,
+}
+
+/// What a capture keeps of inline matrices, and how the replay regenerates them
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SyntheticMatrices {
+    /// blake3 of shape + f32le data (see `pack_cache::matrix_digest`)
+    pub matrix_a_digest: String,
+    pub matrix_b_digest: String,
+    pub matrix_a_shape: (usize, usize),
+    pub matrix_b_shape: (usize, usize),
+    /// Hex seed of `generate_matrices_from_seed_hex` at these shapes
+    pub seed: String,
+}
+
+impl SyntheticMatrices {
+    /// The request to replay: the captured one with generated matrices
+    pub fn regenerate(&self, request: &ComputeRequest) -> Result<ComputeRequest, SolverError> {
+        let ((a_rows, a_cols), (b_rows, b_cols)) = (self.matrix_a_shape, self.matrix_b_shape);
+        let (matrix_a, matrix_b) =
+            crate::generate_matrices_from_seed_hex(&self.seed, a_rows, a_cols, b_rows, b_cols).map_err(SolverError::InvalidInput)?;
+        Ok(ComputeRequest { matrix_a: Some(matrix_a), matrix_b: Some(matrix_b), ..request.clone() })
+    }
+}
+
+/// Capture record of `req`, which arrived at `arrived`: its matrices are taken out while the
+/// rest is copied, then put back. None for requests with a sparse B.
+pub fn capture_request(req: &mut ComputeRequest, arrived: SystemTime) -> Option<CapturedRequest> {
+    if req.matrix_b_sparse.is_some() {
+        return None;
+    }
+    let (matrix_a, matrix_b) = (req.matrix_a.take(), req.matrix_b.take());
+    let request = req.clone();
+    let synthetic = match (&matrix_a, &matrix_b) {
+        (Some(a), Some(b)) => {
+            let (a_digest, b_digest) = (crate::pack_cache::matrix_digest(a), crate::pack_cache::matrix_digest(b));
+            let seed = blake3::hash(&[a_digest, b_digest].concat()).to_hex()[..32].to_string();
+            Some(SyntheticMatrices {
+                matrix_a_digest: hex::encode(a_digest),
+                matrix_b_digest: hex::encode(b_digest),
+                matrix_a_shape: (a.rows, a.cols),
+                matrix_b_shape: (b.rows, b.cols),
+                seed,
+            })
+        }
+        _ => None,
+    };
+    (req.matrix_a, req.matrix_b) = (matrix_a, matrix_b);
+    let arrived_unix_ms = arrived.duration_since(UNIX_EPOCH).map(|d| d.as_millis() as u64).unwrap_or(0);
+    Some(CapturedRequest { arrived_unix_ms, request, synthetic, result_hash: None })
+}
+
+#[derive(Debug, Clone)]
+pub struct CaptureConfig {
+    /// JSONL file written from the start (truncated) while the server runs
+    pub path: PathBuf,
+    /// Requests written before capture stops
+    pub max_requests: usize,
+}
+
+impl CaptureConfig {
+    pub const DEFAULT_MAX_REQUESTS: usize = 10_000;
+}
+
+/// The server's open capture file
+pub struct TrafficCapture {
+    max_requests: usize,
+    file: Mutex<(File, usize)>,
+}
+
+impl TrafficCapture {
+    pub fn create(config: &CaptureConfig) -> std::io::Result<Self> {
+        Ok(Self { max_requests: config.max_requests, file: Mutex::new((host::create(&config.path)?, 0)) })
+    }
+
+    /// Append `captured` as one line; false once the capture is full
+    pub fn record(&self, captured: &CapturedRequest) -> std::io::Result<bool> {
+        let mut file = self.file.lock().unwrap();
+        if file.1 >= self.max_requests {
+            return Ok(false);
+        }
+        let mut line = serde_json::to_vec(captured)?;
+        line.push(b'\n');
+        file.0.write_all(&line)?;
+        file.1 += 1;
+        Ok(true)
+    }
+
+    /// Requests written so far
+    pub fn len(&self) -> usize {
+        self.file.lock().unwrap().1
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+}
+
+/// Parse a capture file's contents, one record per non-empty line
+pub fn parse_capture(jsonl: &str) -> Result<Vec<CapturedRequest>, SolverError> {
+    jsonl
+        .lines()
+        .enumerate()
+        .filter(|(_, line)| !line.trim().is_empty())
+        .map(|(i, line)| {
+            serde_json::from_str(line).map_err(|e| SolverError::InvalidInput(format!("Capture line {}: {}", i + 1, e)))
+        })
+        .collect()
+}
+
+pub fn read_capture(path: &Path) -> Result<Vec<CapturedRequest>, SolverError> {
+    let jsonl = host::read_to_string(path).map_err(|e| SolverError::InvalidInput(format!("{}: {}", path.display(), e)))?;
+    parse_capture(&jsonl)
+}
+
+/// How fast a capture is replayed relative to how it arrived
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ReplayRate {
+    /// The captured gaps divided by this factor: 2x sends twice as fast
+    Scaled(f64),
+    /// Everything at once
+    Max,
+}
+
+impl ReplayRate {
+    pub fn parse(s: &str) -> Result<Self, SolverError> {
+        let s = s.trim();
+        if s == "max" {
+            return Ok(ReplayRate::Max);
+        }
+        s.strip_suffix('x')
+            .unwrap_or(s)
+            .parse::<f64>()
+            .ok()
+            .filter(|f| f.is_finite() && *f > 0.0)
+            .map(ReplayRate::Scaled)
+            .ok_or_else(|| SolverError::InvalidInput(format!("Invalid replay rate: {:?} (expected e.g. 1x, 2x, 0.5x or max)", s)))
+    }
+
+    pub fn as_string(&self) -> String {
+        match self {
+            ReplayRate::Scaled(factor) => format!("{}x", factor),
+            ReplayRate::Max => "max".to_string(),
+        }
+    }
+}
+
+/// When each request is sent, relative to the start of the replay: its arrival after the
+/// capture's first arrival, divided by the rate. Indexed like `captured`, which is in
+/// completion order and so need not be sorted by arrival.
+pub fn schedule(captured: &[CapturedRequest], rate: ReplayRate) -> Vec<Duration> {
+    let first = captured.iter().map(|c| c.arrived_unix_ms).min().unwrap_or(0);
+    captured
+        .iter()
+        .map(|c| match rate {
+            ReplayRate::Scaled(factor) => Duration::from_secs_f64((c.arrived_unix_ms - first) as f64 / 1000.0 / factor),
+            ReplayRate::Max => Duration::ZERO,
+        })
+        .collect()
+}
+
+/// A replayed seed request whose result differs from the captured one
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct HashMismatch {
+    /// Line of the capture, from 0
+    pub index: usize,
+    pub expected: String,
+    pub actual: String,
+}
+
+/// A replayed request that got no result
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ReplayFailure {
+    pub index: usize,
+    pub message: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ReplayReport {
+    pub target: String,
+    pub rate: String,
+    pub requests: usize,
+    pub succeeded: usize,
+    /// Requests whose captured result hash was compared
+    pub hashes_checked: usize,
+    pub mismatches: Vec<HashMismatch>,
+    pub failures: Vec<ReplayFailure>,
+    /// End-to-end latency of the requests that succeeded
+    pub latency_ms: Option<Percentiles>,
+    /// Latest any request went out after its scheduled time
+    pub max_send_lag_ms: f64,
+    pub wall_secs: f64,
+}
+
+impl ReplayReport {
+    pub fn passed(&self) -> bool {
+        self.mismatches.is_empty() && self.failures.is_empty()
+    }
+
+    /// Human-readable summary for the console
+    pub fn to_table(&self) -> String {
+        let mut out = format!(
+            "Replay of {} requests at {} against {}: {} succeeded, {} hashes checked, {:.1}s (max send lag {:.1} ms)\n",
+            self.requests, self.rate, self.target, self.succeeded, self.hashes_checked, self.wall_secs, self.max_send_lag_ms
+        );
+        if let Some(p) = &self.latency_ms {
+            out.push_str(&format!(
+                "latency ms: min {:.1}  p50 {:.1}  p90 {:.1}  p99 {:.1}  max {:.1}\n",
+                p.min, p.p50, p.p90, p.p99, p.max
+            ));
+        }
+        for m in &self.mismatches {
+            out.push_str(&format!("❌ request {}: result hash {} != captured {}\n", m.index, m.actual, m.expected));
+        }
+        for f in &self.failures {
+            out.push_str(&format!("❌ request {}: {}\n", f.index, f.message));
+        }
+        out
+    }
+}
+
+/// Replay `captured` against `client` on `schedule(captured, rate)`. The requests, synthetic
+/// matrices included, are built before the clock starts so generating them does not delay
+/// sends; each is then sent from its own task at its time, without retries.
+#[cfg(feature = "client")]
+pub async fn replay(
+    captured: &[CapturedRequest],
+    client: std::sync::Arc<crate::client::SolverClient>,
+    target: &str,
+    rate: ReplayRate,
+) -> Result<ReplayReport, SolverError> {
+    let requests = captured
+        .iter()
+        .map(|c| match &c.synthetic {
+            Some(synthetic) => synthetic.regenerate(&c.request),
+            None => Ok(c.request.clone()),
+        })
+        .collect::<Result<Vec<_>, _>>()?;
+
+    let start = tokio::time::Instant::now();
+    let tasks: Vec<_> = requests
+        .into_iter()
+        .zip(schedule(captured, rate))
+        .map(|(request, at)| {
+            let client = client.clone();
+            tokio::spawn(async move {
+                tokio::time::sleep_until(start + at).await;
+                let lag = start.elapsed().saturating_sub(at);
+                let sent = tokio::time::Instant::now();
+                let result = client.compute(request).await;
+                (lag, sent.elapsed(), result)
+            })
+        })
+        .collect();
+
+    let mut report = ReplayReport {
+        target: target.to_string(),
+        rate: rate.as_string(),
+        requests: captured.len(),
+        succeeded: 0,
+        hashes_checked: 0,
+        mismatches: Vec::new(),
+        failures: Vec::new(),
+        latency_ms: None,
+        max_send_lag_ms: 0.0,
+        wall_secs: 0.0,
+    };
+    let mut latencies = Vec::with_capacity(captured.len());
+    for (index, (task, captured)) in tasks.into_iter().zip(captured).enumerate() {
+        let (lag, latency, result) = task.await.map_err(|e| SolverError::Internal(e.to_string()))?;
+        report.max_send_lag_ms = report.max_send_lag_ms.max(lag.as_secs_f64() * 1000.0);
+        match result {
+            Ok(output) => {
+                report.succeeded += 1;
+                latencies.push(latency.as_secs_f64() * 1000.0);
+                if let Some(expected) = &captured.result_hash {
+                    report.hashes_checked += 1;
+                    if output.result_hash != *expected {
+                        report.mismatches.push(HashMismatch { index, expected: expected.clone(), actual: output.result_hash });
+                    }
+                }
+            }
+            Err(e) => report.failures.push(ReplayFailure { index, message: e.to_string() }),
+        }
+    }
+    report.latency_ms = Percentiles::of(&latencies);
+    report.wall_secs = start.elapsed().as_secs_f64();
+    Ok(report)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::FlatMatrix;
+
+    fn captured(arrived_unix_ms: u64) -> CapturedRequest {
+        CapturedRequest { arrived_unix_ms, request: ComputeRequest::default(), synthetic: None, result_hash: None }
+    }
+
+    #[test]
+    fn test_capture_scrubs_matrices() {
+        let matrix = |rows, cols| FlatMatrix { data: (0..rows * cols).map(|i| i as f32).collect(), rows, cols };
+        let mut req = ComputeRequest {
+            matrix_a: Some(matrix(16, 8)),
+            matrix_b: Some(matrix(8, 4)),
+            precision: "fp32".to_string(),
+            kernel: Some("fp32_generic".to_string()),
+            ..Default::default()
+        };
+        let arrived = UNIX_EPOCH + Duration::from_millis(1234);
+        let capture = capture_request(&mut req, arrived).unwrap();
+        // The request keeps its matrices; the capture only their digests and shapes
+        assert_eq!((req.matrix_a.as_ref().unwrap().rows, req.matrix_b.as_ref().unwrap().cols), (16, 4));
+        assert!(capture.request.matrix_a.is_none() && capture.request.matrix_b.is_none());
+        assert_eq!((capture.arrived_unix_ms, capture.request.kernel.as_deref()), (1234, Some("fp32_generic")));
+        let synthetic = capture.synthetic.clone().unwrap();
+        assert_eq!(synthetic.matrix_a_digest, hex::encode(crate::pack_cache::matrix_digest(req.matrix_a.as_ref().unwrap())));
+        assert_eq!((synthetic.matrix_a_shape, synthetic.matrix_b_shape), ((16, 8), (8, 4)));
+
+        let line = serde_json::to_string(&capture).unwrap();
+        assert!(!line.contains("\"matrix_a\":["), "{}", line);
+        let parsed = parse_capture(&format!("{}\n\n{}\n", line, line)).unwrap();
+        assert_eq!(parsed.len(), 2);
+        assert_eq!(parsed[1].synthetic.as_ref(), Some(&synthetic));
+
+        // The stand-ins have the captured shapes, and are the same on every replay
+        let replayed = synthetic.regenerate(&parsed[0].request).unwrap();
+        let (a, b) = (replayed.matrix_a.unwrap(), replayed.matrix_b.unwrap());
+        assert_eq!(((a.rows, a.cols), (b.rows, b.cols)), ((16, 8), (8, 4)));
+        assert_eq!(synthetic.regenerate(&parsed[0].request).unwrap().matrix_a.unwrap().data, a.data);
+
+        let err = parse_capture(&format!("{}\nnot json\n", line)).unwrap_err().to_string();
+        assert!(err.contains("Capture line 2"), "{}", err);
+
+        let sparse_b = crate::sparse::SparseMatrix::from_dense(&matrix(8, 4));
+        let mut sparse = ComputeRequest { matrix_a: Some(matrix(16, 8)), matrix_b_sparse: Some(sparse_b), ..Default::default() };
+        assert!(capture_request(&mut sparse, arrived).is_none());
+    }
+
+    #[test]
+    fn test_capture_stops_at_max_requests() {
+        let dir = tempfile::tempdir().unwrap();
+        let config = CaptureConfig { path: dir.path().join("traffic.jsonl"), max_requests: 2 };
+        let capture = TrafficCapture::create(&config).unwrap();
+        let recorded: Vec<bool> = (0..3).map(|i| capture.record(&captured(i)).unwrap()).collect();
+        assert_eq!((recorded, capture.len()), (vec![true, true, false], 2));
+        let read = read_capture(&config.path).unwrap();
+        assert_eq!(read.iter().map(|c| c.arrived_unix_ms).collect::<Vec<_>>(), [0, 1]);
+
+        // Capture files are host IO like any other (see host.rs)
+        for touch in [|config: &CaptureConfig| drop(TrafficCapture::create(config)), |config: &CaptureConfig| drop(read_capture(&config.path))] {
+            let caught = std::panic::catch_unwind(|| crate::host::forbid_io(|| touch(&config)));
+            assert!(caught.is_err());
+        }
+    }
+
+    #[test]
+    fn test_rate_and_schedule() {
+        assert_eq!(ReplayRate::parse("2x").unwrap(), ReplayRate::Scaled(2.0));
+        assert_eq!(ReplayRate::parse(" 0.5x").unwrap(), ReplayRate::Scaled(0.5));
+        assert_eq!(ReplayRate::parse("3").unwrap(), ReplayRate::Scaled(3.0));
+        assert_eq!(ReplayRate::parse("max").unwrap(), ReplayRate::Max);
+        for bad in ["0x", "-1x", "fast", "x", "infx"] {
+            assert!(ReplayRate::parse(bad).is_err(), "{}", bad);
+        }
+        assert_eq!(ReplayRate::Scaled(2.0).as_string(), "2x");
+
+        // Completion order: the second record arrived first
+        let capture = [captured(10_500), captured(10_000), captured(12_000)];
+        let ms = |rate| schedule(&capture, rate).iter().map(|d| d.as_millis()).collect::<Vec<_>>();
+        assert_eq!(ms(ReplayRate::Scaled(1.0)), [500, 0, 2000]);
+        assert_eq!(ms(ReplayRate::Scaled(2.0)), [250, 0, 1000]);
+        assert_eq!(ms(ReplayRate::Scaled(0.5)), [1000, 0, 4000]);
+        assert_eq!(ms(ReplayRate::Max), [0, 0, 0]);
+        assert!(schedule(&[], ReplayRate::Max).is_empty());
+    }
+
+    #[cfg(feature = "client")]
+    #[tokio::test]
+    async fn test_capture_and_replay_against_the_server() {
+        use crate::api::api::{router, ApiConfig, AppState, SeedDims};
+        use crate::client::{ClientConfig, SolverClient};
+        use std::net::SocketAddr;
+        use std::sync::Arc;
+
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("traffic.jsonl");
+        let config = ApiConfig { capture: Some(CaptureConfig { path: path.clone(), max_requests: 10 }), ..Default::default() };
+        let app = router(Arc::new(AppState::new(config)));
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = format!("http://{}", listener.local_addr().unwrap());
+        tokio::spawn(async move { axum::serve(listener, app.into_make_service_with_connect_info::<SocketAddr>()).await.unwrap() });
+
+        let client = SolverClient::new(&url, None).with_config(ClientConfig { max_retries: 0, ..Default::default() });
+        let seeded = client.compute_seed("0a0b", "u8i8", SeedDims { m: 16, k: 64, n: 16 }).await.unwrap();
+        let (matrix_a, matrix_b) = crate::generate_matrices_from_seed_hex("0c0d", 16, 32, 32, 8).unwrap();
+        let request = ComputeRequest { matrix_a: Some(matrix_a), matrix_b: Some(matrix_b), precision: "fp32".to_string(), ..Default::default() };
+        client.compute(request).await.unwrap();
+
+        let captured = read_capture(&path).unwrap();
+        assert_eq!(captured.len(), 2);
+        assert_eq!(captured[0].result_hash.as_deref(), Some(seeded.result_hash.as_str()));
+        assert!(captured[1].result_hash.is_none());
+        assert_eq!(captured[1].synthetic.as_ref().map(|s| s.matrix_b_shape), Some((32, 8)));
+        assert!(!std::fs::read_to_string(&path).unwrap().contains("\"matrix_a\":["));
+
+        let report = replay(&captured, Arc::new(client), &url, ReplayRate::Scaled(2.0)).await.unwrap();
+        assert!(report.passed(), "{}", report.to_table());
+        assert_eq!((report.requests, report.succeeded, report.hashes_checked), (2, 2, 1));
+        assert_eq!(report.latency_ms.map(|p| p.count), Some(2));
+
+        // A capture whose hash the server does not reproduce is reported, not failed
+        let mut tampered = captured.clone();
+        tampered[0].result_hash = Some("00".to_string());
+        let client = Arc::new(SolverClient::new(&url, None));
+        let report = replay(&tampered, client, &url, ReplayRate::Max).await.unwrap();
+        assert_eq!(report.mismatches, [HashMismatch { index: 0, expected: "00".to_string(), actual: seeded.result_hash }]);
+        assert!(!report.passed());
+    }
+}
@@ -0,0 +1,343 @@
+//! Declarative shape constraints for workload inputs.
+//!
+//! Each workload lists the relations its fields must satisfy (`constraints`), and
+//! `validate` checks them against the parsed Input before any kernel runs. Every violated
+//! constraint is reported with the fields involved, their values and the expected
+//! relation, e.g. "matrix_a.cols (=2) must equal matrix_b.rows (=1)". The violations travel
+//! as data in `SolverError::ShapeMismatch`, so the API can return the field names in its
+//! error details. A new workload adds its fields to `Extent` and its list to `constraints`.
+//!
+//! The constraints read only `Shapes`, the extents and layouts of the operands, so a
+//! workload described by its shapes alone (`POST /validate`) is checked exactly as its
+//! Input would be.
+
+use crate::{types, FlatMatrix, SolverError};
+use serde::{Deserialize, Serialize};
+use std::fmt;
+
+/// An Input field holding a matrix
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Operand {
+    MatrixA,
+    MatrixB,
+    MatrixBSparse,
+}
+
+impl Operand {
+    pub fn name(&self) -> &'static str {
+        match self {
+            Operand::MatrixA => "matrix_a",
+            Operand::MatrixB => "matrix_b",
+            Operand::MatrixBSparse => "matrix_b_sparse",
+        }
+    }
+
+    fn extents(&self, shapes: &Shapes) -> (usize, usize, usize) {
+        match self {
+            Operand::MatrixA => shapes.matrix_a,
+            Operand::MatrixB => shapes.matrix_b,
+            Operand::MatrixBSparse => shapes.matrix_b_sparse.unwrap_or_default(),
+        }
+    }
+}
+
+/// What the constraints read from an Input: each operand's rows, cols and the number of
+/// elements present (stored, for matrix_b_sparse), and the layouts
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Shapes {
+    pub matrix_a: (usize, usize, usize),
+    pub matrix_b: (usize, usize, usize),
+    pub matrix_b_sparse: Option<(usize, usize, usize)>,
+    pub a_layout: Option<String>,
+    pub b_layout: Option<String>,
+}
+
+impl Shapes {
+    pub fn of(input: &types::Input) -> Self {
+        let dense = |m: &FlatMatrix| (m.rows, m.cols, m.data.len());
+        Shapes {
+            matrix_a: dense(&input.matrix_a),
+            matrix_b: dense(&input.matrix_b),
+            matrix_b_sparse: input.matrix_b_sparse.as_ref().map(|b| (b.rows, b.cols, b.nnz())),
+            a_layout: input.a_layout.clone(),
+            b_layout: input.b_layout.clone(),
+        }
+    }
+
+    /// Dense operands of these shapes, every element present
+    pub fn dense(a: (usize, usize), b: (usize, usize)) -> Self {
+        let full = |(rows, cols): (usize, usize)| (rows, cols, rows.saturating_mul(cols));
+        Shapes { matrix_a: full(a), matrix_b: full(b), ..Default::default() }
+    }
+}
+
+/// A size read from the Input
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Extent {
+    Rows(Operand),
+    Cols(Operand),
+    /// Number of elements present in `data`
+    Len(Operand),
+    /// `rows * cols`, the number of elements the shape declares
+    Elements(Operand),
+}
+
+impl Extent {
+    /// Field path used in error messages and API details
+    pub fn name(&self) -> String {
+        match self {
+            Extent::Rows(m) => format!("{}.rows", m.name()),
+            Extent::Cols(m) => format!("{}.cols", m.name()),
+            Extent::Len(m) => format!("{}.len", m.name()),
+            Extent::Elements(m) => format!("{0}.rows*{0}.cols", m.name()),
+        }
+    }
+
+    fn value(&self, shapes: &Shapes) -> usize {
+        let (rows, cols, len) = match self {
+            Extent::Rows(m) | Extent::Cols(m) | Extent::Len(m) | Extent::Elements(m) => m.extents(shapes),
+        };
+        match self {
+            Extent::Rows(_) => rows,
+            Extent::Cols(_) => cols,
+            Extent::Len(_) => len,
+            Extent::Elements(_) => rows.saturating_mul(cols),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Relation {
+    Equal,
+    AtMost,
+    AtLeast,
+}
+
+impl Relation {
+    fn holds(&self, left: usize, right: usize) -> bool {
+        match self {
+            Relation::Equal => left == right,
+            Relation::AtMost => left <= right,
+            Relation::AtLeast => left >= right,
+        }
+    }
+
+    fn phrase(&self) -> &'static str {
+        match self {
+            Relation::Equal => "must equal",
+            Relation::AtMost => "must be at most",
+            Relation::AtLeast => "must be at least",
+        }
+    }
+}
+
+/// `field` must stand in `relation` to `other`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Constraint {
+    pub field: Extent,
+    pub relation: Relation,
+    pub other: Extent,
+}
+
+const fn constraint(field: Extent, relation: Relation, other: Extent) -> Constraint {
+    Constraint { field, relation, other }
+}
+
+pub const MATMUL: &[Constraint] = &[
+    constraint(Extent::Len(Operand::MatrixA), Relation::Equal, Extent::Elements(Operand::MatrixA)),
+    constraint(Extent::Len(Operand::MatrixB), Relation::Equal, Extent::Elements(Operand::MatrixB)),
+    constraint(Extent::Cols(Operand::MatrixA), Relation::Equal, Extent::Rows(Operand::MatrixB)),
+];
+
+/// matmul with `a_layout: "transposed"`: matrix_a holds Aᵀ (k×m)
+pub const MATMUL_A_TRANSPOSED: &[Constraint] = &[
+    constraint(Extent::Len(Operand::MatrixA), Relation::Equal, Extent::Elements(Operand::MatrixA)),
+    constraint(Extent::Len(Operand::MatrixB), Relation::Equal, Extent::Elements(Operand::MatrixB)),
+    constraint(Extent::Rows(Operand::MatrixA), Relation::Equal, Extent::Rows(Operand::MatrixB)),
+];
+
+/// matmul with `b_layout: "transposed"`: matrix_b holds Bᵀ (n×k)
+pub const MATMUL_B_TRANSPOSED: &[Constraint] = &[
+    constraint(Extent::Len(Operand::MatrixA), Relation::Equal, Extent::Elements(Operand::MatrixA)),
+    constraint(Extent::Len(Operand::MatrixB), Relation::Equal, Extent::Elements(Operand::MatrixB)),
+    constraint(Extent::Cols(Operand::MatrixA), Relation::Equal, Extent::Cols(Operand::MatrixB)),
+];
+
+/// matmul with both operands transposed: Aᵀ (k×m) and Bᵀ (n×k)
+pub const MATMUL_BOTH_TRANSPOSED: &[Constraint] = &[
+    constraint(Extent::Len(Operand::MatrixA), Relation::Equal, Extent::Elements(Operand::MatrixA)),
+    constraint(Extent::Len(Operand::MatrixB), Relation::Equal, Extent::Elements(Operand::MatrixB)),
+    constraint(Extent::Rows(Operand::MatrixA), Relation::Equal, Extent::Cols(Operand::MatrixB)),
+];
+
+/// matmul with B in `matrix_b_sparse`, whose CSR arrays `SparseMatrix::check` covers
+pub const MATMUL_SPARSE_B: &[Constraint] = &[
+    constraint(Extent::Len(Operand::MatrixA), Relation::Equal, Extent::Elements(Operand::MatrixA)),
+    constraint(Extent::Cols(Operand::MatrixA), Relation::Equal, Extent::Rows(Operand::MatrixBSparse)),
+];
+
+/// matmul with Aᵀ (k×m) and B in `matrix_b_sparse`
+pub const MATMUL_SPARSE_B_A_TRANSPOSED: &[Constraint] = &[
+    constraint(Extent::Len(Operand::MatrixA), Relation::Equal, Extent::Elements(Operand::MatrixA)),
+    constraint(Extent::Rows(Operand::MatrixA), Relation::Equal, Extent::Rows(Operand::MatrixBSparse)),
+];
+
+/// Quantizing reads only matrix_a
+pub const QUANTIZE: &[Constraint] =
+    &[constraint(Extent::Len(Operand::MatrixA), Relation::Equal, Extent::Elements(Operand::MatrixA))];
+
+/// Constraints of `workload_type`; empty for workloads this build does not know
+pub fn constraints(workload_type: &str) -> &'static [Constraint] {
+    match workload_type {
+        // Every item of a batch is matrix_a.cols wide, as a plain A is
+        "matmul" | "batched_matmul" => MATMUL,
+        "quantize" => QUANTIZE,
+        _ => &[],
+    }
+}
+
+/// One failed constraint, with the values that were compared
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Violation {
+    pub field: String,
+    pub value: usize,
+    pub relation: Relation,
+    pub other: String,
+    pub other_value: usize,
+}
+
+impl fmt::Display for Violation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} (={}) {} {} (={})", self.field, self.value, self.relation.phrase(), self.other, self.other_value)
+    }
+}
+
+/// Every constraint in `constraints` that `input` violates, in declaration order
+pub fn check(input: &types::Input, constraints: &[Constraint]) -> Vec<Violation> {
+    check_shapes(&Shapes::of(input), constraints)
+}
+
+/// `check` on the shapes of an Input
+pub fn check_shapes(shapes: &Shapes, constraints: &[Constraint]) -> Vec<Violation> {
+    constraints
+        .iter()
+        .filter_map(|c| {
+            let (value, other_value) = (c.field.value(shapes), c.other.value(shapes));
+            (!c.relation.holds(value, other_value)).then(|| Violation {
+                field: c.field.name(),
+                value,
+                relation: c.relation,
+                other: c.other.name(),
+                other_value,
+            })
+        })
+        .collect()
+}
+
+/// Check `input` against the constraints of `workload_type` (and its `a_layout`, `b_layout`
+/// and sparse B)
+pub fn validate(workload_type: &str, input: &types::Input) -> Result<(), SolverError> {
+    validate_shapes(workload_type, &Shapes::of(input))
+}
+
+/// `validate` on the shapes of an Input
+pub fn validate_shapes(workload_type: &str, shapes: &Shapes) -> Result<(), SolverError> {
+    let sparse_b = shapes.matrix_b_sparse.is_some();
+    let constraints = match (workload_type, shapes.a_layout.as_deref(), shapes.b_layout.as_deref()) {
+        ("matmul", Some("transposed"), _) if sparse_b => MATMUL_SPARSE_B_A_TRANSPOSED,
+        ("matmul", ..) if sparse_b => MATMUL_SPARSE_B,
+        // batched_matmul rejects a sparse B itself; only matrix_a is checked here
+        ("batched_matmul", ..) if sparse_b => QUANTIZE,
+        ("matmul", Some("transposed"), Some("transposed")) => MATMUL_BOTH_TRANSPOSED,
+        ("matmul", Some("transposed"), _) => MATMUL_A_TRANSPOSED,
+        ("matmul" | "batched_matmul", _, Some("transposed")) => MATMUL_B_TRANSPOSED,
+        _ => constraints(workload_type),
+    };
+    let violations = check_shapes(shapes, constraints);
+    if violations.is_empty() {
+        Ok(())
+    } else {
+        Err(SolverError::ShapeMismatch(violations))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::compute_workload;
+
+    fn input(workload_type: &str, a: (usize, usize, usize), b: (usize, usize, usize)) -> types::Input {
+        let matrix = |(rows, cols, len): (usize, usize, usize)| FlatMatrix { data: vec![1.0; len], rows, cols };
+        types::Input {
+            matrix_a: matrix(a),
+            matrix_b: matrix(b),
+            workload_type: Some(workload_type.to_string()),
+            precision: if workload_type == "quantize" { "int8" } else { "fp32" }.to_string(),
+            ..Default::default()
+        }
+    }
+
+    fn violations(input: types::Input) -> Vec<Violation> {
+        match compute_workload(input).unwrap_err() {
+            SolverError::ShapeMismatch(violations) => violations,
+            other => panic!("expected a shape mismatch, got {}", other),
+        }
+    }
+
+    #[test]
+    fn test_matmul_constraints() {
+        let v = violations(input("matmul", (2, 2, 4), (1, 2, 2)));
+        assert_eq!(v.len(), 1);
+        assert_eq!(v[0].to_string(), "matrix_a.cols (=2) must equal matrix_b.rows (=1)");
+        assert_eq!((v[0].relation, v[0].value, v[0].other_value), (Relation::Equal, 2, 1));
+
+        let v = violations(input("matmul", (2, 3, 5), (3, 2, 6)));
+        assert_eq!(v.len(), 1);
+        assert_eq!(v[0].to_string(), "matrix_a.len (=5) must equal matrix_a.rows*matrix_a.cols (=6)");
+
+        let v = violations(input("matmul", (2, 3, 6), (3, 2, 7)));
+        assert_eq!(v[0].field, "matrix_b.len");
+        assert_eq!(v[0].other, "matrix_b.rows*matrix_b.cols");
+
+        // Every violated constraint is reported, in declaration order
+        let err = compute_workload(input("matmul", (2, 3, 5), (4, 2, 7))).unwrap_err();
+        let fields: Vec<String> = match &err {
+            SolverError::ShapeMismatch(v) => v.iter().map(|v| v.field.clone()).collect(),
+            other => panic!("{}", other),
+        };
+        assert_eq!(fields, ["matrix_a.len", "matrix_b.len", "matrix_a.cols"]);
+        assert!(err.to_string().starts_with("Matrix dimensions incompatible: matrix_a.len (=5)"), "{}", err);
+
+        // A transposed B (n×k) shares its columns with A's
+        let transposed_b = |a, b, a_layout: Option<&str>| types::Input {
+            a_layout: a_layout.map(str::to_string),
+            b_layout: Some("transposed".to_string()),
+            ..input("matmul", a, b)
+        };
+        let v = violations(transposed_b((2, 3, 6), (4, 2, 8), None));
+        assert_eq!(v[0].to_string(), "matrix_a.cols (=3) must equal matrix_b.cols (=2)");
+        let v = violations(transposed_b((3, 2, 6), (4, 2, 8), Some("transposed")));
+        assert_eq!(v[0].to_string(), "matrix_a.rows (=3) must equal matrix_b.cols (=2)");
+        assert!(check(&transposed_b((2, 3, 6), (4, 3, 12), None), MATMUL_B_TRANSPOSED).is_empty());
+    }
+
+    #[test]
+    fn test_quantize_constraints() {
+        let v = violations(input("quantize", (4, 4, 15), (0, 0, 0)));
+        assert_eq!(v.len(), 1);
+        assert_eq!(v[0].to_string(), "matrix_a.len (=15) must equal matrix_a.rows*matrix_a.cols (=16)");
+        // matrix_b is not read, so its shape is not checked
+        assert!(check(&input("quantize", (4, 4, 16), (3, 3, 1)), QUANTIZE).is_empty());
+    }
+
+    #[test]
+    fn test_relations() {
+        let a = input("matmul", (2, 3, 6), (3, 2, 6));
+        let at_most = [constraint(Extent::Rows(Operand::MatrixA), Relation::AtMost, Extent::Rows(Operand::MatrixB))];
+        assert!(check(&a, &at_most).is_empty());
+        let at_least = [constraint(Extent::Cols(Operand::MatrixB), Relation::AtLeast, Extent::Cols(Operand::MatrixA))];
+        assert_eq!(check(&a, &at_least)[0].to_string(), "matrix_b.cols (=2) must be at least matrix_a.cols (=3)");
+        assert!(constraints("convolution").is_empty());
+    }
+}
@@ -0,0 +1,293 @@
+//! How noisy this host's timings are (`matmul-solver variance`).
+//!
+//! `run_variance` computes the same seed workload `runs` times after one untimed warm-up
+//! and keeps each run's kernel time. `timing_stats` summarizes them: mean, median, sample
+//! standard deviation and coefficient of variation, the min/max spread, and the runs
+//! outside Tukey's fences (more than 1.5 interquartile ranges beyond the quartiles), which
+//! are one-off stalls such as a preemption rather than noise.
+//!
+//! The verdict's heuristic: the noise is the coefficient of variation of the runs inside
+//! the fences, and a change is worth comparing when it exceeds twice that, rounded up to a
+//! whole percent. A smaller change is within two standard deviations of a single run, so
+//! noise alone can produce it. With `isolate` the thread is pinned to the CPU it started
+//! on and the packed-B caches and last-level cache are flushed before every run, so each
+//! run does the same work from the same cold state.
+
+use crate::histogram::percentile;
+use crate::profile::median;
+use crate::{bench, compute_workload_ref, generate_matrices_from_seed, host, types, ComputeOptions, SolverError};
+use serde::{Deserialize, Serialize};
+
+/// Bytes overwritten before every isolated run to evict the last-level cache
+pub const EVICT_BYTES: usize = 64 << 20;
+
+#[derive(Debug, Clone)]
+pub struct VarianceConfig {
+    pub seed: Vec<u8>,
+    pub precision: String,
+    /// (m, k, n)
+    pub dims: (usize, usize, usize),
+    pub runs: usize,
+    /// Pin to one core and flush the caches before every run
+    pub isolate: bool,
+}
+
+/// Summary of a set of timings, in milliseconds
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct TimingStats {
+    pub count: usize,
+    pub mean_ms: f64,
+    pub median_ms: f64,
+    /// Sample standard deviation (n - 1)
+    pub stddev_ms: f64,
+    /// `stddev_ms / mean_ms`; 0 when the mean is 0
+    pub coefficient_of_variation: f64,
+    pub min_ms: f64,
+    pub max_ms: f64,
+    /// `(max_ms - min_ms) / median_ms`, in percent
+    pub spread_pct: f64,
+    /// Samples outside Tukey's fences
+    pub outliers: usize,
+    /// Coefficient of variation of the samples inside the fences, in percent
+    pub noise_pct: f64,
+}
+
+/// Mean and sample standard deviation; the deviation is 0 for fewer than two samples
+fn mean_stddev(samples: &[f64]) -> (f64, f64) {
+    let n = samples.len() as f64;
+    let mean = samples.iter().sum::<f64>() / n;
+    if samples.len() < 2 {
+        return (mean, 0.0);
+    }
+    let variance = samples.iter().map(|x| (x - mean).powi(2)).sum::<f64>() / (n - 1.0);
+    (mean, variance.sqrt())
+}
+
+fn ratio(x: f64, of: f64) -> f64 {
+    if of > 0.0 {
+        x / of
+    } else {
+        0.0
+    }
+}
+
+/// None when there are no samples; NaN samples are ignored
+pub fn timing_stats(samples: &[f64]) -> Option<TimingStats> {
+    let mut sorted: Vec<f64> = samples.iter().copied().filter(|s| !s.is_nan()).collect();
+    if sorted.is_empty() {
+        return None;
+    }
+    let (mean_ms, stddev_ms) = mean_stddev(&sorted);
+    let median_ms = median(&mut sorted);
+    let (q1, q3) = (percentile(&sorted, 0.25)?, percentile(&sorted, 0.75)?);
+    let fences = (q1 - 1.5 * (q3 - q1))..=(q3 + 1.5 * (q3 - q1));
+    let inliers: Vec<f64> = sorted.iter().copied().filter(|s| fences.contains(s)).collect();
+    let (inlier_mean, inlier_stddev) = mean_stddev(&inliers);
+    let (min_ms, max_ms) = (sorted[0], sorted[sorted.len() - 1]);
+    Some(TimingStats {
+        count: sorted.len(),
+        mean_ms,
+        median_ms,
+        stddev_ms,
+        coefficient_of_variation: ratio(stddev_ms, mean_ms),
+        min_ms,
+        max_ms,
+        spread_pct: ratio(max_ms - min_ms, median_ms) * 100.0,
+        outliers: sorted.len() - inliers.len(),
+        noise_pct: ratio(inlier_stddev, inlier_mean) * 100.0,
+    })
+}
+
+/// Smallest change in percent the noise leaves comparable: twice `noise_pct`, rounded up to
+/// a whole percent, and at least 1%
+pub fn comparable_change_pct(stats: &TimingStats) -> f64 {
+    (2.0 * stats.noise_pct).ceil().max(1.0)
+}
+
+/// One-line verdict, e.g. "this host's timing noise is ±3.2%, suitable for comparing
+/// changes larger than 7%"
+pub fn verdict(stats: &TimingStats) -> String {
+    let mut verdict = format!(
+        "this host's timing noise is ±{:.1}%, suitable for comparing changes larger than {}%",
+        stats.noise_pct,
+        comparable_change_pct(stats)
+    );
+    if stats.outliers > 0 {
+        verdict.push_str(&format!(" ({} of {} runs were outliers and are not counted)", stats.outliers, stats.count));
+    }
+    verdict
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct VarianceReport {
+    pub seed: String,
+    pub precision: String,
+    pub dims: (usize, usize, usize),
+    pub kernel: Option<String>,
+    pub runs: usize,
+    /// What `isolate` did: the pinned CPU and the caches flushed before every run; empty
+    /// without it
+    pub isolation: Vec<String>,
+    /// Kernel time of every run, in order
+    pub kernel_ms: Vec<f64>,
+    pub stats: TimingStats,
+    pub comparable_change_pct: f64,
+    pub verdict: String,
+}
+
+impl VarianceReport {
+    /// Human-readable summary for the console
+    pub fn to_table(&self) -> String {
+        let s = &self.stats;
+        let mut out = format!(
+            "Timing variance: {} {}x{} × {}x{} on {}, {} runs\n",
+            self.precision,
+            self.dims.0,
+            self.dims.1,
+            self.dims.1,
+            self.dims.2,
+            self.kernel.as_deref().unwrap_or("?"),
+            self.runs
+        );
+        for isolation in &self.isolation {
+            out.push_str(&format!("isolation: {}\n", isolation));
+        }
+        out.push_str(&format!(
+            "kernel ms: mean {:.4}, median {:.4}, stddev {:.4}, min {:.4}, max {:.4}\n",
+            s.mean_ms, s.median_ms, s.stddev_ms, s.min_ms, s.max_ms
+        ));
+        out.push_str(&format!(
+            "coefficient of variation {:.2}%, spread {:.1}%, {} outliers\n",
+            s.coefficient_of_variation * 100.0,
+            s.spread_pct,
+            s.outliers
+        ));
+        out.push_str(&format!("Verdict: {}\n", self.verdict));
+        out
+    }
+}
+
+/// Time the seed workload `config.runs` times; uses `options` for every run
+pub fn run_variance(config: &VarianceConfig, options: &ComputeOptions) -> Result<VarianceReport, SolverError> {
+    if config.runs < 2 {
+        return Err(SolverError::InvalidInput("variance needs at least 2 runs".to_string()));
+    }
+    if config.isolate && options.pack_cache_dir.is_some() {
+        return Err(SolverError::InvalidInput(
+            "isolated runs flush the in-process caches only; run them without pack_cache_dir".to_string(),
+        ));
+    }
+    let (m, k, n) = config.dims;
+    let (matrix_a, matrix_b) = generate_matrices_from_seed(&config.seed, m, k, k, n);
+    let mut input = types::Input {
+        matrix_a,
+        matrix_b,
+        precision: config.precision.clone(),
+        ..Default::default()
+    };
+    if !options.exact_names {
+        input.normalize_names();
+    }
+
+    let mut isolation = Vec::new();
+    let mut evict_buf = Vec::new();
+    let pinned = if config.isolate {
+        evict_buf = vec![0u8; EVICT_BYTES];
+        let pinned = host::pin_current_thread();
+        isolation.push(match &pinned {
+            Some(pinned) => format!("pinned to CPU {}", pinned.cpu),
+            None => "core pinning unavailable on this host".to_string(),
+        });
+        pinned
+    } else {
+        None
+    };
+    let kernel = compute_workload_ref(&input, options)?.metadata.kernel;
+    let mut kernel_ms = Vec::with_capacity(config.runs);
+    for _ in 0..config.runs {
+        if config.isolate {
+            let flushed = crate::flush_packed_b_caches();
+            bench::evict(&mut evict_buf);
+            if isolation.len() == 1 {
+                isolation.extend(flushed.into_iter().map(|cache| format!("{} flushed before every run", cache)));
+                isolation.push(format!("last-level cache evicted before every run ({} byte buffer overwritten)", EVICT_BYTES));
+            }
+        }
+        let metrics = compute_workload_ref(&input, options)?.metrics;
+        kernel_ms.push(metrics.kernel_time_ms.unwrap_or(metrics.latency_ms));
+    }
+    drop(pinned);
+
+    let stats = timing_stats(&kernel_ms).ok_or_else(|| SolverError::Internal("no kernel times recorded".to_string()))?;
+    Ok(VarianceReport {
+        seed: hex::encode(&config.seed),
+        precision: input.precision,
+        dims: config.dims,
+        kernel,
+        runs: config.runs,
+        isolation,
+        kernel_ms,
+        comparable_change_pct: comparable_change_pct(&stats),
+        verdict: verdict(&stats),
+        stats,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_timing_stats() {
+        let stats = timing_stats(&[10.0, 12.0, 11.0, 9.0, 8.0]).unwrap();
+        assert_eq!((stats.count, stats.mean_ms, stats.median_ms), (5, 10.0, 10.0));
+        assert!((stats.stddev_ms - 2.5f64.sqrt()).abs() < 1e-12);
+        assert!((stats.coefficient_of_variation - 2.5f64.sqrt() / 10.0).abs() < 1e-12);
+        assert_eq!((stats.min_ms, stats.max_ms, stats.spread_pct, stats.outliers), (8.0, 12.0, 40.0, 0));
+        assert!((stats.noise_pct - stats.coefficient_of_variation * 100.0).abs() < 1e-12);
+
+        // One stall is an outlier: it widens the spread but not the noise
+        let mut samples = vec![100.0, 101.0, 99.0, 100.0, 102.0, 98.0, 100.0, 101.0, 99.0, 100.0];
+        let quiet = timing_stats(&samples).unwrap();
+        samples.push(180.0);
+        let stalled = timing_stats(&samples).unwrap();
+        assert_eq!((quiet.outliers, stalled.outliers), (0, 1));
+        assert_eq!(stalled.noise_pct, quiet.noise_pct);
+        assert!(stalled.coefficient_of_variation > 10.0 * quiet.coefficient_of_variation);
+        assert_eq!(stalled.spread_pct, 82.0);
+
+        let constant = timing_stats(&[5.0; 4]).unwrap();
+        assert_eq!((constant.stddev_ms, constant.noise_pct, constant.outliers), (0.0, 0.0, 0));
+        let zero = timing_stats(&[0.0, 0.0]).unwrap();
+        assert_eq!((zero.coefficient_of_variation, zero.spread_pct), (0.0, 0.0));
+        assert!(timing_stats(&[]).is_none() && timing_stats(&[f64::NAN]).is_none());
+    }
+
+    #[test]
+    fn test_verdict() {
+        let stats = |noise_pct, outliers| TimingStats { noise_pct, outliers, count: 50, ..timing_stats(&[1.0]).unwrap() };
+        assert_eq!(comparable_change_pct(&stats(3.2, 0)), 7.0);
+        assert_eq!(comparable_change_pct(&stats(0.0, 0)), 1.0);
+        assert_eq!(verdict(&stats(3.2, 0)), "this host's timing noise is ±3.2%, suitable for comparing changes larger than 7%");
+        assert_eq!(
+            verdict(&stats(0.4, 2)),
+            "this host's timing noise is ±0.4%, suitable for comparing changes larger than 1% (2 of 50 runs were outliers and are not counted)"
+        );
+    }
+
+    #[test]
+    fn test_run_variance() {
+        let config = VarianceConfig { seed: b"variance".to_vec(), precision: "U8I8".to_string(), dims: (16, 64, 16), runs: 4, isolate: false };
+        let report = run_variance(&config, &ComputeOptions::default()).unwrap();
+        assert_eq!((report.precision.as_str(), report.kernel.as_deref()), ("u8i8", Some("u8i8_16x16")));
+        assert_eq!((report.kernel_ms.len(), report.stats.count), (4, 4));
+        assert!(report.isolation.is_empty() && report.verdict == verdict(&report.stats));
+
+        let isolated = run_variance(&VarianceConfig { isolate: true, ..config.clone() }, &ComputeOptions::default()).unwrap();
+        assert_eq!(isolated.isolation[0].starts_with("pinned to CPU"), cfg!(target_os = "linux"), "{:?}", isolated.isolation);
+        assert!(isolated.isolation.last().unwrap().starts_with("last-level cache evicted"));
+
+        let err = run_variance(&VarianceConfig { runs: 1, ..config }, &ComputeOptions::default()).unwrap_err();
+        assert!(err.to_string().contains("at least 2 runs"));
+    }
+}
@@ -0,0 +1,314 @@
+//! Batch verification of seed claims (`matmul-solver verify-batch`, `POST /verify/batch`).
+//!
+//! A validator receives many claims of the form "seed S, dims D, precision P hashes to H"
+//! and must recompute each. `verify_batch` runs a claims list across `jobs` worker threads
+//! and returns one `ClaimResult` per claim, in input order, plus a summary. Each claim is
+//! recomputed with `compute_seed_workload`, so u8i8 claims of the seed shape take the
+//! direct-pack path and never build f32 matrices. Claims that are identical apart from
+//! `expected_hash` share one computation; distinct seeds are distinct XOF streams, so
+//! nothing else can be shared between them.
+//!
+//! A claim that cannot be computed (bad hex, zero or oversized dims, unknown precision)
+//! fails with `error` set instead of aborting the batch. The verdict for every claim
+//! depends only on the claim, never on `jobs` or on scheduling.
+
+use crate::memory::RequestMemory;
+use crate::{compute_seed_workload, ComputeOptions, SeedDims};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::OnceLock;
+
+/// Largest matrix (in elements) a claim may ask for, the API's cap on seed requests
+pub const MAX_CLAIM_ELEMENTS: usize = 1 << 26;
+
+/// One claimed result to check
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Claim {
+    /// Seed the matrices are generated from (hex)
+    pub seed: String,
+    /// Defaults to the PoW shape, 16×50240 × 50240×16
+    #[serde(default)]
+    pub dims: Option<SeedDims>,
+    pub precision: String,
+    /// Hex SHA-256 the claimant reported; compared case-insensitively
+    pub expected_hash: String,
+    /// Result encoding the hash was taken over, as `Input::hash_input`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub hash_input: Option<String>,
+}
+
+impl Claim {
+    /// What the claim's hash is computed over; claims with equal keys share a computation
+    fn key(&self) -> (String, SeedDims, &str, Option<&str>) {
+        (self.seed.trim().to_ascii_lowercase(), self.dims.unwrap_or_default(), &self.precision, self.hash_input.as_deref())
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ClaimResult {
+    /// Position of the claim in the input
+    pub index: usize,
+    pub seed: String,
+    pub dims: SeedDims,
+    pub precision: String,
+    pub passed: bool,
+    pub expected_hash: String,
+    /// None when the claim could not be computed
+    pub computed_hash: Option<String>,
+    /// Why the claim could not be computed
+    pub error: Option<String>,
+    /// Time to compute the claim; a claim answered by an identical earlier one reports
+    /// that computation's time
+    pub elapsed_ms: f64,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct BatchSummary {
+    pub total: usize,
+    pub passed: usize,
+    /// Claims whose hash differs or that could not be computed
+    pub failed: usize,
+    /// Of `failed`, those that could not be computed
+    pub errors: usize,
+    /// Distinct computations the batch needed
+    pub computed: usize,
+    pub jobs: usize,
+    pub wall_ms: f64,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct BatchReport {
+    pub summary: BatchSummary,
+    pub results: Vec<ClaimResult>,
+}
+
+impl BatchReport {
+    pub fn all_passed(&self) -> bool {
+        self.summary.failed == 0
+    }
+
+    /// Human-readable summary for the console: failing claims, then the totals
+    pub fn to_table(&self) -> String {
+        let mut out = String::new();
+        for result in self.results.iter().filter(|r| !r.passed) {
+            let dims = result.dims;
+            let reason = match (&result.error, &result.computed_hash) {
+                (Some(error), _) => error.clone(),
+                (None, Some(hash)) => format!("computed {}, claimed {}", hash, result.expected_hash),
+                (None, None) => "no result".to_string(),
+            };
+            out.push_str(&format!(
+                "FAIL #{:<5} {} {}x{}x{} seed {}: {}\n",
+                result.index, result.precision, dims.m, dims.k, dims.n, result.seed, reason
+            ));
+        }
+        let s = &self.summary;
+        out.push_str(&format!(
+            "Verified {} claims: {} passed, {} failed ({} errors); {} computations on {} jobs in {:.1} ms\n",
+            s.total, s.passed, s.failed, s.errors, s.computed, s.jobs, s.wall_ms
+        ));
+        out
+    }
+}
+
+/// Outcome of one distinct computation: the hash or why there is none, and its time
+type Computed = (Result<String, String>, f64);
+
+/// Check every claim on `jobs` threads (at least one) with `options`
+pub fn verify_batch(claims: &[Claim], jobs: usize, options: &ComputeOptions) -> BatchReport {
+    let clock = options.clock();
+    let start = clock.now();
+    let jobs = jobs.max(1);
+    // Index of the first claim with each key; later duplicates read its cell
+    let mut first_of = HashMap::new();
+    let owners: Vec<usize> = claims.iter().enumerate().map(|(i, claim)| *first_of.entry(claim.key()).or_insert(i)).collect();
+    let cells: Vec<OnceLock<Computed>> = claims.iter().map(|_| OnceLock::new()).collect();
+    let distinct: Vec<usize> = (0..claims.len()).filter(|&i| owners[i] == i).collect();
+
+    let next = AtomicUsize::new(0);
+    // Workers allocate on behalf of the caller's request, if it is tracked
+    let memory = RequestMemory::current();
+    let worker = || {
+        let _scope = memory.as_ref().map(RequestMemory::enter);
+        while let Some(&i) = distinct.get(next.fetch_add(1, Ordering::Relaxed)) {
+            let (hash, elapsed) = crate::clock::time(clock, || compute_claim(&claims[i], options));
+            let _ = cells[i].set((hash, elapsed.as_secs_f64() * 1000.0));
+        }
+    };
+    if jobs == 1 {
+        worker();
+    } else {
+        std::thread::scope(|scope| {
+            for _ in 0..jobs.min(distinct.len()) {
+                scope.spawn(worker);
+            }
+        });
+    }
+
+    let results: Vec<ClaimResult> = claims
+        .iter()
+        .enumerate()
+        .map(|(index, claim)| {
+            let (hash, elapsed_ms) = cells[owners[index]].get().cloned().expect("every distinct claim was computed");
+            let (computed_hash, error) = match hash {
+                Ok(hash) => (Some(hash), None),
+                Err(error) => (None, Some(error)),
+            };
+            ClaimResult {
+                index,
+                seed: claim.seed.clone(),
+                dims: claim.dims.unwrap_or_default(),
+                precision: claim.precision.clone(),
+                passed: computed_hash.as_deref().is_some_and(|hash| hash.eq_ignore_ascii_case(claim.expected_hash.trim())),
+                expected_hash: claim.expected_hash.clone(),
+                computed_hash,
+                error,
+                elapsed_ms,
+            }
+        })
+        .collect();
+    let passed = results.iter().filter(|r| r.passed).count();
+    let summary = BatchSummary {
+        total: results.len(),
+        passed,
+        failed: results.len() - passed,
+        errors: results.iter().filter(|r| r.error.is_some()).count(),
+        computed: distinct.len(),
+        jobs,
+        wall_ms: clock.elapsed_since(start).as_secs_f64() * 1000.0,
+    };
+    BatchReport { summary, results }
+}
+
+/// The result hash of `claim`, or why it cannot be computed
+fn compute_claim(claim: &Claim, options: &ComputeOptions) -> Result<String, String> {
+    let seed = hex::decode(claim.seed.trim()).map_err(|e| format!("Invalid hex seed: {}", e))?;
+    let dims = claim.dims.unwrap_or_default();
+    let too_large = |rows: usize, cols: usize| rows.checked_mul(cols).is_none_or(|n| n > MAX_CLAIM_ELEMENTS);
+    if dims.m == 0 || dims.k == 0 || dims.n == 0 || too_large(dims.m, dims.k) || too_large(dims.k, dims.n) {
+        return Err(format!("Invalid dims {:?}", dims));
+    }
+    compute_seed_workload(&seed, (dims.m, dims.k, dims.n), &claim.precision, None, claim.hash_input.clone(), options)
+        .map(|output| output.result_hash)
+        .map_err(|e| e.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::faults::{with_plan, BitFlip, FaultPlan};
+
+    const DIMS: SeedDims = SeedDims { m: 16, k: 512, n: 16 };
+
+    fn claim(seed: &str, precision: &str, expected_hash: &str) -> Claim {
+        Claim { seed: seed.to_string(), dims: Some(DIMS), precision: precision.to_string(), expected_hash: expected_hash.to_string(), hash_input: None }
+    }
+
+    fn hash_of(seed: &str, precision: &str) -> String {
+        let seed = hex::decode(seed).unwrap();
+        compute_seed_workload(&seed, (DIMS.m, DIMS.k, DIMS.n), precision, None, None, &ComputeOptions::default()).unwrap().result_hash
+    }
+
+    /// Passing claims at even indices, then a wrong hash, bad hex, bad dims and a
+    /// duplicate of the first claim with its hash in upper case
+    fn mixed_claims() -> Vec<Claim> {
+        let mut claims = Vec::new();
+        for (i, precision) in ["u8i8", "fp32", "int8", "u8i8"].into_iter().enumerate() {
+            let seed = format!("{:02x}{:02x}", i, i * 7);
+            claims.push(claim(&seed, precision, &hash_of(&seed, precision)));
+            claims.push(claim(&seed, precision, &"0".repeat(64)));
+        }
+        claims.push(claim("not hex", "u8i8", &"0".repeat(64)));
+        claims.push(Claim { dims: Some(SeedDims { m: 0, k: 512, n: 16 }), ..claim("01", "u8i8", &"0".repeat(64)) });
+        let first = claims[0].clone();
+        claims.push(Claim { expected_hash: first.expected_hash.to_uppercase(), ..first });
+        claims
+    }
+
+    #[test]
+    fn test_mixed_batch() {
+        let claims = mixed_claims();
+        let report = verify_batch(&claims, 1, &ComputeOptions::default());
+        let verdicts: Vec<bool> = report.results.iter().map(|r| r.passed).collect();
+        let mut expected: Vec<bool> = (0..8).map(|i| i % 2 == 0).collect();
+        expected.extend([false, false, true]);
+        assert_eq!(verdicts, expected);
+        assert!(report.results.iter().enumerate().all(|(i, r)| r.index == i));
+
+        let s = &report.summary;
+        assert_eq!((s.total, s.passed, s.failed, s.errors), (11, 5, 6, 2));
+        // Each wrong-hash claim and the duplicate share the computation of a passing one
+        assert_eq!(s.computed, 6);
+        assert!(!report.all_passed());
+        assert!(report.results[8].error.as_deref().unwrap().contains("Invalid hex seed"));
+        assert!(report.results[9].error.as_deref().unwrap().contains("Invalid dims"));
+        assert_eq!(report.results[1].computed_hash, report.results[0].computed_hash);
+        let table = report.to_table();
+        assert!(table.contains("FAIL #1 "), "{}", table);
+        assert!(!table.contains("FAIL #0 "), "{}", table);
+        assert!(table.contains("Verified 11 claims: 5 passed, 6 failed (2 errors)"), "{}", table);
+
+        let passing: Vec<Claim> = claims.into_iter().step_by(2).take(4).collect();
+        assert!(verify_batch(&passing, 2, &ComputeOptions::default()).all_passed());
+        assert_eq!(verify_batch(&[], 4, &ComputeOptions::default()).summary.total, 0);
+    }
+
+    #[test]
+    fn test_parallel_verdicts_match_serial() {
+        let claims = mixed_claims();
+        let serial = verify_batch(&claims, 1, &ComputeOptions::default());
+        for jobs in [2, 4, 16] {
+            let parallel = verify_batch(&claims, jobs, &ComputeOptions::default());
+            assert_eq!(parallel.summary.jobs, jobs);
+            for (a, b) in serial.results.iter().zip(&parallel.results) {
+                assert_eq!((a.index, a.passed, &a.computed_hash, &a.error), (b.index, b.passed, &b.computed_hash, &b.error), "jobs={}", jobs);
+            }
+        }
+    }
+
+    #[test]
+    fn test_timings_follow_the_options_clock() {
+        // A clock nobody advances: every claim and the whole batch take exactly no time
+        let clock = crate::MockClock::new();
+        let options = ComputeOptions { clock: Some(std::sync::Arc::new(clock)), ..Default::default() };
+        let report = verify_batch(&mixed_claims(), 2, &options);
+        assert!(report.results.iter().all(|result| result.elapsed_ms == 0.0));
+        assert_eq!(report.summary.wall_ms, 0.0);
+    }
+
+    #[test]
+    fn test_faulty_solver_fails_claims() {
+        let claims: Vec<Claim> = mixed_claims().into_iter().step_by(2).take(4).collect();
+        assert!(verify_batch(&claims, 1, &ComputeOptions::default()).all_passed());
+
+        // A wrong result (here in every claim's last element, u8i8 included) fails each claim
+        let flip = FaultPlan { flip_result_bit: Some(BitFlip { element: 255, bit: 3 }), ..Default::default() };
+        let report = with_plan(flip, || verify_batch(&claims, 1, &ComputeOptions::default()));
+        assert_eq!((report.summary.failed, report.summary.errors), (4, 0));
+        assert!(report.results.iter().all(|r| r.computed_hash.as_ref() != Some(&r.expected_hash)));
+
+        // A kernel failing in the second computation is that claim's error alone
+        let fail = FaultPlan { kernel_error: true, kernel_runs: Some(vec![1]), ..Default::default() };
+        let report = with_plan(fail, || verify_batch(&claims, 1, &ComputeOptions::default()));
+        let errors: Vec<bool> = report.results.iter().map(|r| r.error.is_some()).collect();
+        assert_eq!(errors, [false, true, false, false]);
+        assert!(report.results[1].error.as_deref().unwrap().contains("injected kernel fault"));
+    }
+
+    #[test]
+    fn test_claims_file_format() {
+        let json = format!(
+            r#"[{{"seed": "0a0b", "precision": "u8i8", "expected_hash": "{}"}},
+                {{"seed": "0a0b", "dims": {{"m": 16, "k": 512, "n": 16}}, "precision": "u8i8", "expected_hash": "x", "hash_input": "i32le"}}]"#,
+            "0".repeat(64)
+        );
+        let claims: Vec<Claim> = serde_json::from_str(&json).unwrap();
+        assert_eq!(claims[0].dims, None);
+        assert_eq!(claims[1].dims, Some(DIMS));
+        assert_eq!(claims[1].hash_input.as_deref(), Some("i32le"));
+        let report = verify_batch(&claims[1..], 1, &ComputeOptions::default());
+        assert_eq!(report.results[0].dims, DIMS);
+        assert!(report.results[0].computed_hash.is_some());
+    }
+}
@@ -0,0 +1,99 @@
+//! Non-fatal notices attached to an Output.
+//!
+//! Some behavior deserves the user's attention without failing the run: numbers that
+//! did not parse faithfully, hashes that depend on build flags, metadata the solver had
+//! to fill in. The compute pipeline collects these in a `WarningCollector` and returns
+//! them as `Output::warnings`; the CLI prints them to stderr and the API returns them
+//! in the response body.
+
+use serde::{Deserialize, Serialize};
+
+/// Input numbers did not convert to f32 faithfully (see `lossy_parse_count`)
+pub const LOSSY_PARSE: &str = "lossy_parse";
+/// Input metadata was present but missing fields the solver filled in
+pub const METADATA_AUTO_POPULATED: &str = "metadata_auto_populated";
+/// fp16 results, and so their hashes, differ between kernels
+pub const FP16_NOT_BIT_STABLE: &str = "fp16_not_bit_stable";
+/// The fp32 16x16 kernel ran with fast accumulation; the hash depends on build flags
+pub const FAST_ACCUMULATION: &str = "fast_accumulation";
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SolverWarning {
+    /// Stable machine-readable identifier (one of the constants in this module)
+    pub code: String,
+    pub message: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub context: Option<serde_json::Value>,
+}
+
+impl SolverWarning {
+    pub fn new(code: &str, message: impl Into<String>) -> Self {
+        Self { code: code.to_string(), message: message.into(), context: None }
+    }
+
+    pub fn with_context(mut self, context: serde_json::Value) -> Self {
+        self.context = Some(context);
+        self
+    }
+
+    /// `count` input values were rounded, overflowed or lost integer precision
+    pub fn lossy_parse(count: usize) -> Self {
+        Self::new(LOSSY_PARSE, format!("{} input values did not convert to f32 exactly", count))
+            .with_context(serde_json::json!({ "count": count }))
+    }
+}
+
+/// Warnings gathered while computing one workload
+#[derive(Debug, Default)]
+pub(crate) struct WarningCollector(Vec<SolverWarning>);
+
+impl WarningCollector {
+    pub(crate) fn push(&mut self, warning: SolverWarning) {
+        self.0.push(warning);
+    }
+
+    pub(crate) fn into_vec(self) -> Vec<SolverWarning> {
+        self.0
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::types::{Input, InputMetadata};
+    use crate::{compute_workload, generate_matrices_from_seed};
+
+    fn output_json(precision: &str, metadata: Option<InputMetadata>) -> serde_json::Value {
+        let (matrix_a, matrix_b) = generate_matrices_from_seed(b"warnings", 16, 32, 32, 16);
+        let input = Input { matrix_a, matrix_b, precision: precision.to_string(), metadata, ..Default::default() };
+        serde_json::to_value(compute_workload(input).unwrap()).unwrap()
+    }
+
+    #[test]
+    fn test_warnings_in_json() {
+        // Nothing noteworthy: no warnings key at all
+        assert!(output_json("fp32", None).get("warnings").is_none());
+        let complete = InputMetadata {
+            compiler_flags: Some("-O3".to_string()),
+            libraries: Some(vec![]),
+            cache_enabled: None,
+        };
+        assert!(output_json("u8i8", Some(complete)).get("warnings").is_none());
+
+        let partial = InputMetadata { compiler_flags: Some("-O3".to_string()), libraries: None, cache_enabled: None };
+        let json = output_json("int8", Some(partial.clone()));
+        assert_eq!(json["warnings"][0]["code"], super::METADATA_AUTO_POPULATED);
+        assert_eq!(json["warnings"][0]["context"]["fields"], serde_json::json!(["libraries"]));
+        assert_eq!(json["metadata"]["libraries"], serde_json::json!(crate::build_libraries()));
+
+        #[cfg(feature = "fp16")]
+        {
+            let json = output_json("fp16", Some(partial));
+            let codes: Vec<&str> = json["warnings"].as_array().unwrap().iter().map(|w| w["code"].as_str().unwrap()).collect();
+            assert_eq!(codes, [super::METADATA_AUTO_POPULATED, super::FP16_NOT_BIT_STABLE]);
+
+            // Warnings survive a round trip through the serialized Output
+            let output: crate::types::Output = serde_json::from_value(json).unwrap();
+            assert_eq!(output.warnings.len(), 2);
+        }
+    }
+}
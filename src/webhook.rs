@@ -0,0 +1,285 @@
+//! Completion callbacks for background jobs (`callback_url` on `POST /jobs`).
+//!
+//! When a job with a callback finishes or fails, the server POSTs a `JobCallback` to the
+//! URL. Every delivery is signed with the shared `WebhookConfig::secret`:
+//!
+//! - `X-Solver-Timestamp: <unix seconds>`
+//! - `X-Solver-Signature: sha256=<hex HMAC-SHA256(secret, "<timestamp>.<body>")>`
+//!
+//! so a receiver can authenticate the body and refuse stale replays. Network errors, 5xx,
+//! 408 and 429 are retried with exponential backoff up to `max_attempts`; any other status
+//! ends the delivery. Its progress is kept on the job and shown by `GET /jobs/{id}`.
+//!
+//! A callback URL makes the server issue requests of the client's choosing, so URLs are
+//! refused unless their scheme and host are on the operator's allowlist, credentials in
+//! the URL are refused, and redirects are never followed. Without allowed hosts or a
+//! secret, callbacks are disabled.
+
+use crate::jobs::JobStatus;
+use crate::types::{Metrics, Output};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+pub const SIGNATURE_HEADER: &str = "x-solver-signature";
+pub const TIMESTAMP_HEADER: &str = "x-solver-timestamp";
+
+#[derive(Debug, Clone)]
+pub struct WebhookConfig {
+    /// Hosts callbacks may target: exact names or addresses, or `*.example.com` for any
+    /// subdomain. Empty disables callbacks.
+    pub allowed_hosts: Vec<String>,
+    /// URL schemes callbacks may use
+    pub allowed_schemes: Vec<String>,
+    /// HMAC key shared with the receivers; None disables callbacks
+    pub secret: Option<String>,
+    /// Attempts per delivery, including the first
+    pub max_attempts: u32,
+    /// Delay before the first retry; doubles per attempt
+    pub initial_backoff: Duration,
+    /// Timeout of each attempt
+    pub timeout: Duration,
+}
+
+impl Default for WebhookConfig {
+    fn default() -> Self {
+        Self {
+            allowed_hosts: Vec::new(),
+            allowed_schemes: vec!["https".to_string()],
+            secret: None,
+            max_attempts: 5,
+            initial_backoff: Duration::from_millis(500),
+            timeout: Duration::from_secs(10),
+        }
+    }
+}
+
+/// Body of a callback: the job's outcome without its result matrix
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct JobCallback {
+    pub id: String,
+    pub status: JobStatus,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub result_hash: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub metrics: Option<Metrics>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+impl JobCallback {
+    pub fn new(id: &str, outcome: &Result<Output, String>) -> Self {
+        match outcome {
+            Ok(output) => Self {
+                id: id.to_string(),
+                status: JobStatus::Ok,
+                result_hash: Some(output.result_hash.clone()),
+                metrics: Some(output.metrics.clone()),
+                error: None,
+            },
+            Err(message) => Self {
+                id: id.to_string(),
+                status: JobStatus::Error,
+                result_hash: None,
+                metrics: None,
+                error: Some(message.clone()),
+            },
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum DeliveryState {
+    /// The job is running or the delivery is still being attempted
+    Pending,
+    Delivered,
+    /// Attempts ran out or the receiver refused the callback
+    Failed,
+}
+
+/// Progress of a job's callback, reported by `GET /jobs/{id}`
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct CallbackDelivery {
+    pub url: String,
+    pub state: DeliveryState,
+    pub attempts: u32,
+    /// HTTP status of the last attempt
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub last_status: Option<u16>,
+    /// Network error of the last attempt
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub last_error: Option<String>,
+}
+
+impl CallbackDelivery {
+    pub fn pending(url: &reqwest::Url) -> Self {
+        Self { url: url.to_string(), state: DeliveryState::Pending, attempts: 0, last_status: None, last_error: None }
+    }
+}
+
+pub struct Webhooks {
+    config: WebhookConfig,
+    client: reqwest::Client,
+}
+
+impl Webhooks {
+    pub fn new(config: WebhookConfig) -> Self {
+        let client = reqwest::Client::builder()
+            .redirect(reqwest::redirect::Policy::none())
+            .timeout(config.timeout)
+            .build()
+            .expect("webhook HTTP client");
+        Self { config, client }
+    }
+
+    /// Parse `url` and check it against the allowlist
+    pub fn check_url(&self, url: &str) -> Result<reqwest::Url, String> {
+        if self.config.allowed_hosts.is_empty() || self.config.secret.is_none() {
+            return Err("callback_url is not enabled on this server".to_string());
+        }
+        let parsed = reqwest::Url::parse(url).map_err(|e| format!("Invalid callback_url: {}", e))?;
+        if !self.config.allowed_schemes.iter().any(|scheme| scheme.eq_ignore_ascii_case(parsed.scheme())) {
+            return Err(format!("callback_url scheme '{}' is not allowed", parsed.scheme()));
+        }
+        if !parsed.username().is_empty() || parsed.password().is_some() {
+            return Err("callback_url must not carry credentials".to_string());
+        }
+        let host = parsed.host_str().unwrap_or_default().to_ascii_lowercase();
+        if !self.config.allowed_hosts.iter().any(|allowed| host_matches(allowed, &host)) {
+            return Err(format!("callback_url host '{}' is not allowed", host));
+        }
+        Ok(parsed)
+    }
+
+    /// POST `payload` to `url` until it is accepted or attempts run out. `on_attempt` sees
+    /// the delivery after every attempt; the last one is returned.
+    pub async fn deliver(
+        &self,
+        url: &reqwest::Url,
+        payload: &JobCallback,
+        mut on_attempt: impl FnMut(&CallbackDelivery),
+    ) -> CallbackDelivery {
+        let mut delivery = CallbackDelivery::pending(url);
+        let body = serde_json::to_vec(payload).expect("callback payload serializes");
+        let secret = self.config.secret.as_deref().unwrap_or_default().as_bytes();
+        let mut delay = self.config.initial_backoff;
+        while delivery.attempts < self.config.max_attempts.max(1) {
+            if delivery.attempts > 0 {
+                tokio::time::sleep(delay).await;
+                delay *= 2;
+            }
+            let timestamp = SystemTime::now().duration_since(UNIX_EPOCH).map(|d| d.as_secs()).unwrap_or(0);
+            let sent = self
+                .client
+                .post(url.clone())
+                .header("content-type", "application/json")
+                .header(TIMESTAMP_HEADER, timestamp.to_string())
+                .header(SIGNATURE_HEADER, signature(secret, timestamp, &body))
+                .body(body.clone())
+                .send()
+                .await;
+            delivery.attempts += 1;
+            let retry = match sent {
+                Ok(response) => {
+                    let status = response.status();
+                    delivery.last_status = Some(status.as_u16());
+                    delivery.last_error = None;
+                    if status.is_success() {
+                        delivery.state = DeliveryState::Delivered;
+                    }
+                    status.is_server_error()
+                        || status == reqwest::StatusCode::REQUEST_TIMEOUT
+                        || status == reqwest::StatusCode::TOO_MANY_REQUESTS
+                }
+                Err(e) => {
+                    delivery.last_status = None;
+                    delivery.last_error = Some(e.to_string());
+                    true
+                }
+            };
+            if delivery.state == DeliveryState::Pending && (!retry || delivery.attempts >= self.config.max_attempts.max(1)) {
+                delivery.state = DeliveryState::Failed;
+            }
+            on_attempt(&delivery);
+            if delivery.state != DeliveryState::Pending {
+                break;
+            }
+        }
+        delivery
+    }
+}
+
+/// `X-Solver-Signature` of `body` sent at `timestamp`
+pub fn signature(secret: &[u8], timestamp: u64, body: &[u8]) -> String {
+    let mut message = format!("{}.", timestamp).into_bytes();
+    message.extend_from_slice(body);
+    format!("sha256={}", hex::encode(hmac_sha256(secret, &message)))
+}
+
+/// HMAC-SHA256 (RFC 2104)
+fn hmac_sha256(key: &[u8], message: &[u8]) -> [u8; 32] {
+    let mut block = [0u8; 64];
+    if key.len() > block.len() {
+        block[..32].copy_from_slice(&Sha256::digest(key));
+    } else {
+        block[..key.len()].copy_from_slice(key);
+    }
+    let pad = |byte: u8| block.map(|b| b ^ byte);
+    let inner = Sha256::new().chain_update(pad(0x36)).chain_update(message).finalize();
+    Sha256::new().chain_update(pad(0x5c)).chain_update(inner).finalize().into()
+}
+
+/// `allowed` is a host name or address, or `*.suffix` for any subdomain of suffix
+fn host_matches(allowed: &str, host: &str) -> bool {
+    let allowed = allowed.to_ascii_lowercase();
+    match allowed.strip_prefix("*.") {
+        Some(suffix) => host.strip_suffix(suffix).is_some_and(|rest| rest.len() > 1 && rest.ends_with('.')),
+        None => host == allowed,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_hmac_matches_rfc_4231() {
+        let mac = hmac_sha256(b"Jefe", b"what do ya want for nothing?");
+        assert_eq!(hex::encode(mac), "5bdcc146bf60754e6a042426089575c75a003f089d2739839dec58b964ec3843");
+        // Keys longer than a block are hashed first
+        let mac = hmac_sha256(&[0xaa; 131], b"Test Using Larger Than Block-Size Key - Hash Key First");
+        assert_eq!(hex::encode(mac), "60e431591ee0b67f0d8a26aacbf5b77f8e0bc6213728c5140546040f0ee37f54");
+        assert_eq!(signature(b"Jefe", 7, b"{}"), format!("sha256={}", hex::encode(hmac_sha256(b"Jefe", b"7.{}"))));
+    }
+
+    #[test]
+    fn test_callback_urls_are_allowlisted() {
+        let config = WebhookConfig {
+            allowed_hosts: vec!["hooks.example.com".to_string(), "*.internal.example".to_string()],
+            secret: Some("s3cret".to_string()),
+            ..Default::default()
+        };
+        let webhooks = Webhooks::new(config.clone());
+        assert!(webhooks.check_url("https://hooks.example.com/done?x=1").is_ok());
+        assert!(webhooks.check_url("https://HOOKS.example.com:8443/done").is_ok());
+        assert!(webhooks.check_url("https://a.internal.example/done").is_ok());
+        for (url, reason) in [
+            ("http://hooks.example.com/done", "scheme 'http'"),
+            ("file:///etc/passwd", "scheme 'file'"),
+            ("https://169.254.169.254/latest/meta-data", "host '169.254.169.254'"),
+            ("https://internal.example/done", "host 'internal.example'"),
+            ("https://evilinternal.example/done", "host 'evilinternal.example'"),
+            ("https://hooks.example.com.attacker.net/", "host 'hooks.example.com.attacker.net'"),
+            ("https://user:pw@hooks.example.com/", "credentials"),
+            ("not a url", "Invalid callback_url"),
+        ] {
+            let err = webhooks.check_url(url).unwrap_err();
+            assert!(err.contains(reason), "{}: {}", url, err);
+        }
+
+        let unsigned = Webhooks::new(WebhookConfig { secret: None, ..config });
+        assert!(unsigned.check_url("https://hooks.example.com/done").unwrap_err().contains("not enabled"));
+        assert!(Webhooks::new(WebhookConfig::default()).check_url("https://hooks.example.com/").is_err());
+    }
+}
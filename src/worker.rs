@@ -0,0 +1,285 @@
+//! Pull-based verification worker.
+//!
+//! Instead of receiving pushes, a worker polls a job server, executes each job with the
+//! same pipeline as `POST /compute`, and posts the result back. Job protocol:
+//!
+//! - `GET  {base}/jobs/next` → `200 {"id": "...", "request": ComputeRequest}`, or `204` when idle
+//! - `POST {base}/jobs/{id}/complete` ← `JobCompletion`
+//!
+//! Both requests carry `Authorization: Bearer <token>` when a token is configured.
+//! Transient failures (network errors, 5xx) are retried with exponential backoff.
+
+use crate::api::api::{process_request, ComputeRequest};
+use crate::types;
+use serde::{Deserialize, Serialize};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::sync::Semaphore;
+
+#[derive(Debug, Clone)]
+pub struct WorkerConfig {
+    /// Base URL of the job server
+    pub base_url: String,
+    /// Sleep between polls when the server has no work
+    pub poll_interval: Duration,
+    /// Jobs executed concurrently
+    pub concurrency: usize,
+    pub auth_token: Option<String>,
+    /// Attempts per HTTP call before giving up on it
+    pub max_retries: u32,
+    /// First backoff delay; doubles per attempt
+    pub initial_backoff: Duration,
+    /// Stop after this many jobs have been completed (None runs forever)
+    pub max_jobs: Option<usize>,
+}
+
+impl WorkerConfig {
+    pub fn new(base_url: impl Into<String>) -> Self {
+        Self {
+            base_url: base_url.into().trim_end_matches('/').to_string(),
+            poll_interval: Duration::from_millis(500),
+            concurrency: 1,
+            auth_token: None,
+            max_retries: 5,
+            initial_backoff: Duration::from_millis(100),
+            max_jobs: None,
+        }
+    }
+}
+
+/// A unit of work handed out by the job server
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Job {
+    pub id: String,
+    pub request: ComputeRequest,
+}
+
+/// Body posted to the completion endpoint
+#[derive(Debug, Serialize)]
+pub struct JobCompletion {
+    pub id: String,
+    /// "ok" or "error"
+    pub status: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub output: Option<types::Output>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+#[derive(Debug)]
+enum FetchError {
+    /// Worth retrying (network error, 5xx)
+    Transient(String),
+    Fatal(String),
+}
+
+struct Worker {
+    config: WorkerConfig,
+    client: reqwest::Client,
+}
+
+impl Worker {
+    fn authorize(&self, builder: reqwest::RequestBuilder) -> reqwest::RequestBuilder {
+        match &self.config.auth_token {
+            Some(token) => builder.bearer_auth(token),
+            None => builder,
+        }
+    }
+
+    async fn fetch_job(&self) -> Result<Option<Job>, FetchError> {
+        let url = format!("{}/jobs/next", self.config.base_url);
+        let response = self
+            .authorize(self.client.get(&url))
+            .send()
+            .await
+            .map_err(|e| FetchError::Transient(e.to_string()))?;
+        let status = response.status();
+        if status == reqwest::StatusCode::NO_CONTENT {
+            return Ok(None);
+        }
+        if status.is_server_error() {
+            return Err(FetchError::Transient(format!("job server returned {}", status)));
+        }
+        if !status.is_success() {
+            return Err(FetchError::Fatal(format!("job server returned {}", status)));
+        }
+        response
+            .json::<Job>()
+            .await
+            .map(Some)
+            .map_err(|e| FetchError::Fatal(format!("malformed job: {}", e)))
+    }
+
+    async fn post_completion(&self, completion: &JobCompletion) -> Result<(), String> {
+        let url = format!("{}/jobs/{}/complete", self.config.base_url, completion.id);
+        let mut delay = self.config.initial_backoff;
+        let mut last_error = String::new();
+        for attempt in 0..self.config.max_retries.max(1) {
+            if attempt > 0 {
+                tokio::time::sleep(delay).await;
+                delay *= 2;
+            }
+            match self.authorize(self.client.post(&url)).json(completion).send().await {
+                Ok(resp) if resp.status().is_success() => return Ok(()),
+                Ok(resp) if resp.status().is_server_error() => {
+                    last_error = format!("completion endpoint returned {}", resp.status());
+                }
+                Ok(resp) => return Err(format!("completion endpoint returned {}", resp.status())),
+                Err(e) => last_error = e.to_string(),
+            }
+        }
+        Err(last_error)
+    }
+}
+
+async fn execute(job: Job) -> JobCompletion {
+    let id = job.id.clone();
+    let outcome = tokio::task::spawn_blocking(move || process_request(job.request)).await;
+    match outcome {
+        Ok(Ok(output)) => JobCompletion { id, status: "ok".to_string(), output: Some(output), error: None },
+        Ok(Err(e)) => JobCompletion { id, status: "error".to_string(), output: None, error: Some(e.message) },
+        Err(e) => JobCompletion { id, status: "error".to_string(), output: None, error: Some(format!("job panicked: {}", e)) },
+    }
+}
+
+/// Poll for jobs until `max_jobs` have completed (or forever).
+/// Returns the number of completions successfully posted.
+pub async fn run_worker(config: WorkerConfig) -> Result<usize, String> {
+    let worker = Arc::new(Worker { client: reqwest::Client::new(), config });
+    let permits = Arc::new(Semaphore::new(worker.config.concurrency.max(1)));
+    let dispatched = Arc::new(AtomicUsize::new(0));
+    let posted = Arc::new(AtomicUsize::new(0));
+    let mut tasks = tokio::task::JoinSet::new();
+    let mut backoff = worker.config.initial_backoff;
+
+    loop {
+        if worker.config.max_jobs.is_some_and(|max| dispatched.load(Ordering::SeqCst) >= max) {
+            break;
+        }
+        let permit = permits.clone().acquire_owned().await.map_err(|e| e.to_string())?;
+        match worker.fetch_job().await {
+            Ok(Some(job)) => {
+                backoff = worker.config.initial_backoff;
+                dispatched.fetch_add(1, Ordering::SeqCst);
+                let worker = worker.clone();
+                let posted = posted.clone();
+                tasks.spawn(async move {
+                    let completion = execute(job).await;
+                    match worker.post_completion(&completion).await {
+                        Ok(()) => {
+                            posted.fetch_add(1, Ordering::SeqCst);
+                        }
+                        Err(e) => eprintln!("worker: failed to post result for job {}: {}", completion.id, e),
+                    }
+                    drop(permit);
+                });
+            }
+            Ok(None) => {
+                drop(permit);
+                tokio::time::sleep(worker.config.poll_interval).await;
+            }
+            Err(FetchError::Transient(e)) => {
+                drop(permit);
+                eprintln!("worker: poll failed ({}), retrying in {:?}", e, backoff);
+                tokio::time::sleep(backoff).await;
+                backoff = (backoff * 2).min(Duration::from_secs(30));
+            }
+            Err(FetchError::Fatal(e)) => return Err(e),
+        }
+        // Reap finished tasks so the set doesn't grow unbounded
+        while tasks.try_join_next().is_some() {}
+    }
+
+    while tasks.join_next().await.is_some() {}
+    Ok(posted.load(Ordering::SeqCst))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::extract::{Path, State};
+    use axum::http::{HeaderMap, StatusCode};
+    use axum::response::{IntoResponse, Response};
+    use axum::routing::{get, post};
+    use axum::{Json, Router};
+    use std::sync::Mutex;
+
+    #[derive(Default)]
+    struct MockServer {
+        queue: Mutex<Vec<Job>>,
+        completions: Mutex<Vec<serde_json::Value>>,
+        // Fail the first completion POST to exercise the retry path
+        fail_next_completion: Mutex<bool>,
+    }
+
+    async fn next_job(State(server): State<Arc<MockServer>>, headers: HeaderMap) -> Response {
+        if headers.get("authorization").and_then(|v| v.to_str().ok()) != Some("Bearer worker-token") {
+            return StatusCode::UNAUTHORIZED.into_response();
+        }
+        match server.queue.lock().unwrap().pop() {
+            Some(job) => Json(job).into_response(),
+            None => StatusCode::NO_CONTENT.into_response(),
+        }
+    }
+
+    async fn complete(
+        State(server): State<Arc<MockServer>>,
+        Path(id): Path<String>,
+        Json(body): Json<serde_json::Value>,
+    ) -> StatusCode {
+        let mut fail = server.fail_next_completion.lock().unwrap();
+        if *fail {
+            *fail = false;
+            return StatusCode::SERVICE_UNAVAILABLE;
+        }
+        assert_eq!(body["id"], id);
+        server.completions.lock().unwrap().push(body);
+        StatusCode::OK
+    }
+
+    fn seed_job(id: &str, seed: &str) -> Job {
+        Job {
+            id: id.to_string(),
+            request: ComputeRequest {
+                seed: Some(seed.to_string()),
+                precision: "u8i8".to_string(),
+                ..Default::default()
+            },
+        }
+    }
+
+    #[tokio::test]
+    async fn test_worker_completes_jobs_from_mock_server() {
+        let server = Arc::new(MockServer {
+            queue: Mutex::new(vec![seed_job("job-1", "00ff"), seed_job("job-2", "abcd")]),
+            fail_next_completion: Mutex::new(true),
+            ..Default::default()
+        });
+        let app = Router::new()
+            .route("/jobs/next", get(next_job))
+            .route("/jobs/:id/complete", post(complete))
+            .with_state(server.clone());
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        tokio::spawn(async move { axum::serve(listener, app).await.unwrap() });
+
+        let mut config = WorkerConfig::new(format!("http://{}", addr));
+        config.concurrency = 2;
+        config.auth_token = Some("worker-token".to_string());
+        config.poll_interval = Duration::from_millis(10);
+        config.initial_backoff = Duration::from_millis(10);
+        config.max_jobs = Some(2);
+
+        let posted = run_worker(config).await.unwrap();
+        assert_eq!(posted, 2);
+
+        let completions = server.completions.lock().unwrap();
+        for (id, seed) in [("job-1", "00ff"), ("job-2", "abcd")] {
+            let body = completions.iter().find(|c| c["id"] == id).expect("completion posted");
+            assert_eq!(body["status"], "ok");
+            let expected = process_request(seed_job(id, seed).request).unwrap().result_hash;
+            assert_eq!(body["output"]["result_hash"], expected.as_str());
+        }
+    }
+}
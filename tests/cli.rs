@@ -0,0 +1,178 @@
+//! The `matmul-solver` binary's observable behavior for the single-workload flags: files
+//! written, what it prints and its exit status.
+
+use matmul_solver::{compute_workload, numbers, types};
+use std::fs;
+use std::path::Path;
+use std::process::{Command, Output};
+
+const INPUT: &str = r#"{"matrix_a": [[1, 2, 3], [4, 5, 6]], "matrix_b": [[1, 2], [3, 4], [5, 7]], "precision": "u8i8"}"#;
+
+fn solver(dir: &Path, args: &[&str]) -> Output {
+    Command::new(env!("CARGO_BIN_EXE_matmul-solver")).current_dir(dir).args(args).output().unwrap()
+}
+
+fn stdout(output: &Output) -> String {
+    String::from_utf8(output.stdout.clone()).unwrap()
+}
+
+fn stderr(output: &Output) -> String {
+    String::from_utf8(output.stderr.clone()).unwrap()
+}
+
+fn read_output(path: &Path) -> types::Output {
+    serde_json::from_str(&fs::read_to_string(path).unwrap()).unwrap()
+}
+
+#[test]
+fn test_compute_and_verify() {
+    let dir = tempfile::tempdir().unwrap();
+    fs::write(dir.path().join("in.json"), INPUT).unwrap();
+    let expected = compute_workload(numbers::parse_input_json(INPUT, false).unwrap().0).unwrap();
+
+    let run = solver(dir.path(), &["--input", "in.json", "-o", "out.json", "--verify"]);
+    assert!(run.status.success(), "{}", stderr(&run));
+    let output = read_output(&dir.path().join("out.json"));
+    assert_eq!(output.result_hash, expected.result_hash);
+    assert_eq!(output.metadata.lossy_parse_count, Some(0));
+    assert!(output.metrics.parse_time_ms.is_some() && output.metrics.serialize_time_ms.is_some());
+
+    let printed = stdout(&run);
+    let lines: Vec<&str> = printed.lines().collect();
+    assert_eq!(lines[0], "Matrix multiplication completed successfully!");
+    assert!(lines[1].starts_with("Latency: ") && lines[1].ends_with(" ms"));
+    assert!(lines[2].starts_with("Throughput: ") && lines[2].ends_with(" ops/sec"));
+    assert_eq!(lines[3], format!("Result hash: {}", expected.result_hash));
+    assert_eq!(&lines[4..6], ["", "Timing Breakdown:"]);
+    assert!(lines[6].starts_with("  Parse time:     "));
+    assert!(lines[7].starts_with("  Kernel time:    ") && lines[7].ends_with(" ms (matmul computation)"));
+    assert!(lines[8].starts_with("  Serialize time: "));
+    assert_eq!(lines[9], "✅ Correctness verified: Hash matches recomputed result");
+    assert!(lines[11].starts_with("Note: Latency may vary"));
+    assert_eq!(lines.len(), 13);
+    assert_eq!(stderr(&run), "");
+
+    // i32 hashes verify from the metadata
+    let run = solver(dir.path(), &["--input", "in.json", "-o", "i32.json", "--verify", "--hash-input", "i32le"]);
+    assert!(run.status.success());
+    assert_eq!(read_output(&dir.path().join("i32.json")).metadata.result_dtype, "i32");
+    assert!(stdout(&run).contains("✅ Correctness verified"));
+}
+
+#[test]
+fn test_delta_audit_and_reconstruct() {
+    let dir = tempfile::tempdir().unwrap();
+    fs::write(dir.path().join("in.json"), INPUT).unwrap();
+    fs::write(dir.path().join("next.json"), INPUT.replace("[5, 7]", "[5, 8]")).unwrap();
+
+    let run = solver(dir.path(), &["--input", "in.json", "-o", "base.json", "--audit-output"]);
+    assert!(stdout(&run).starts_with("✅ Output audit passed: base.json reproduces result_hash\n"));
+    assert_eq!(read_output(&dir.path().join("base.json")).audit_passed, Some(true));
+
+    let run = solver(dir.path(), &["--input", "next.json", "-o", "delta.json", "--baseline", "base.json"]);
+    assert!(run.status.success());
+    assert!(stdout(&run).starts_with("Wrote delta against base.json: 2 of 4 elements changed\n"), "{}", stdout(&run));
+
+    let run = solver(dir.path(), &["reconstruct", "--baseline", "base.json", "--delta", "delta.json", "-o", "full.json"]);
+    assert!(run.status.success(), "{}", stderr(&run));
+    let expected = solver(dir.path(), &["--input", "next.json", "-o", "next_out.json"]);
+    assert!(expected.status.success());
+    assert_eq!(
+        read_output(&dir.path().join("full.json")).result_hash,
+        read_output(&dir.path().join("next_out.json")).result_hash
+    );
+}
+
+#[test]
+fn test_failures() {
+    let dir = tempfile::tempdir().unwrap();
+    fs::write(dir.path().join("in.json"), INPUT).unwrap();
+
+    let run = solver(dir.path(), &["--seed", "abcd"]);
+    assert_eq!(run.status.code(), Some(1));
+    assert!(stderr(&run).contains("--precision is required when using --seed"));
+
+    let run = solver(dir.path(), &["--input", "in.json", "-o", "out.json", "--audit-output", "--baseline", "in.json"]);
+    assert_eq!(run.status.code(), Some(1));
+    assert!(stderr(&run).contains("cannot be combined with --baseline"));
+
+    fs::write(dir.path().join("big.json"), INPUT.replace("[4, 5, 6]", "[4, 5, 1e40]")).unwrap();
+    let run = solver(dir.path(), &["--input", "big.json", "-o", "out.json", "--strict-numbers"]);
+    assert_eq!(run.status.code(), Some(1));
+    assert!(stderr(&run).contains("Strict number parsing: matrix_a[1][2] = 1e40"));
+    assert!(!dir.path().join("out.json").exists());
+
+    let run = solver(dir.path(), &["--input", "big.json", "-o", "out.json"]);
+    assert!(run.status.success());
+    assert!(stderr(&run).starts_with("⚠️  [lossy_parse] "), "{}", stderr(&run));
+}
+
+#[test]
+fn test_strict_and_lossy_numbers() {
+    let dir = tempfile::tempdir().unwrap();
+    // 16777217 and 33554433 are past 2^24 and round as f32s
+    let rows = ("[4, 5, 6]", "[4, 16777217, 33554433]");
+    fs::write(dir.path().join("u8i8.json"), INPUT.replace(rows.0, rows.1)).unwrap();
+    fs::write(dir.path().join("fp32.json"), INPUT.replace("\"u8i8\"", "\"fp32\"").replace(rows.0, rows.1)).unwrap();
+
+    // Lossy by default: counted and warned about
+    let run = solver(dir.path(), &["--input", "u8i8.json", "-o", "lossy.json"]);
+    assert!(run.status.success(), "{}", stderr(&run));
+    assert_eq!(read_output(&dir.path().join("lossy.json")).metadata.lossy_parse_count, Some(2));
+    assert!(stderr(&run).starts_with("⚠️  [lossy_parse] "), "{}", stderr(&run));
+
+    // Strict: integer precisions refuse the first rounded integer
+    let run = solver(dir.path(), &["--input", "u8i8.json", "-o", "strict.json", "--strict-numbers"]);
+    assert_eq!(run.status.code(), Some(1));
+    assert!(stderr(&run).contains("Strict number parsing: matrix_a[1][1] = 16777217"), "{}", stderr(&run));
+    assert!(!dir.path().join("strict.json").exists());
+
+    // Float precisions only count integer loss, even in strict mode
+    let run = solver(dir.path(), &["--input", "fp32.json", "-o", "fp32_out.json", "--strict-numbers"]);
+    assert!(run.status.success(), "{}", stderr(&run));
+    assert_eq!(read_output(&dir.path().join("fp32_out.json")).metadata.lossy_parse_count, Some(2));
+}
+
+#[test]
+fn test_variance_report() {
+    let dir = tempfile::tempdir().unwrap();
+    let run = solver(dir.path(), &["variance", "--seed", "abcd", "--runs", "5", "--k", "256", "--report", "variance.json"]);
+    assert!(run.status.success(), "{}", stderr(&run));
+    let printed = stdout(&run);
+    assert!(printed.starts_with("Timing variance: u8i8 16x256 × 256x16 on u8i8_16x16, 5 runs\n"), "{}", printed);
+    assert!(printed.contains("\nVerdict: this host's timing noise is ±"), "{}", printed);
+    assert!(printed.ends_with("Report written to variance.json\n"));
+
+    let report: serde_json::Value = serde_json::from_str(&fs::read_to_string(dir.path().join("variance.json")).unwrap()).unwrap();
+    assert_eq!((report["runs"].as_u64(), report["kernel_ms"].as_array().map(Vec::len)), (Some(5), Some(5)));
+    for key in ["mean_ms", "median_ms", "stddev_ms", "coefficient_of_variation", "min_ms", "max_ms", "spread_pct", "outliers", "noise_pct"] {
+        assert!(report["stats"].get(key).is_some(), "{}", key);
+    }
+    let stats = &report["stats"];
+    assert!(stats["min_ms"].as_f64() <= stats["median_ms"].as_f64() && stats["median_ms"].as_f64() <= stats["max_ms"].as_f64());
+    assert!(report["verdict"].as_str().unwrap().contains(&format!("larger than {}%", report["comparable_change_pct"].as_f64().unwrap())));
+    assert_eq!(report["isolation"], serde_json::json!([]));
+
+    let run = solver(dir.path(), &["variance", "--seed", "abcd", "--runs", "1"]);
+    assert_eq!(run.status.code(), Some(1));
+    assert!(stderr(&run).contains("variance needs at least 2 runs"));
+}
+
+#[test]
+fn test_forced_simd_variant() {
+    let dir = tempfile::tempdir().unwrap();
+    let run = solver(dir.path(), &["--seed", "abcd", "--precision", "u8i8", "-o", "detected.json"]);
+    assert!(run.status.success(), "{}", stderr(&run));
+    let run = solver(dir.path(), &["--seed", "abcd", "--precision", "u8i8", "-o", "scalar.json", "--simd", "scalar", "--verify"]);
+    assert!(run.status.success(), "{}", stderr(&run));
+    let (detected, scalar) = (read_output(&dir.path().join("detected.json")), read_output(&dir.path().join("scalar.json")));
+    assert_eq!(scalar.metadata.simd_variant.as_deref(), Some("scalar"));
+    assert_eq!(scalar.result_hash, detected.result_hash);
+
+    let foreign = if cfg!(target_arch = "aarch64") { "avx2" } else { "neon" };
+    let run = solver(dir.path(), &["--seed", "abcd", "--precision", "u8i8", "--simd", foreign]);
+    assert_eq!(run.status.code(), Some(1));
+    assert!(stderr(&run).contains(&format!("SIMD variant '{}' requires", foreign)), "{}", stderr(&run));
+    let run = solver(dir.path(), &["--seed", "abcd", "--precision", "u8i8", "--simd", "sse"]);
+    assert!(stderr(&run).contains("Unknown SIMD variant: sse"), "{}", stderr(&run));
+}